@@ -0,0 +1,316 @@
+// aether gen config：合并本地配置与服务器上注册的 service，生成 aether.config
+use aetherframework_kernel::proto::client_service_client::ClientServiceClient;
+use aetherframework_kernel::proto::{
+    GetServerInfoRequest, ListServicesRequest, RegisteredService, ResourceType, ServerInfo,
+};
+use serde_json::{json, Map, Value};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::doctor::grpc_endpoint;
+
+/// 配置来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigSource {
+    Local,
+    Remote,
+    Both,
+}
+
+impl ConfigSource {
+    pub fn includes_local(&self) -> bool {
+        matches!(self, ConfigSource::Local | ConfigSource::Both)
+    }
+
+    pub fn includes_remote(&self) -> bool {
+        matches!(self, ConfigSource::Remote | ConfigSource::Both)
+    }
+}
+
+impl FromStr for ConfigSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "local" => Ok(ConfigSource::Local),
+            "remote" => Ok(ConfigSource::Remote),
+            "both" => Ok(ConfigSource::Both),
+            _ => Err(format!(
+                "Invalid source '{}'. Must be: local, remote, or both",
+                s
+            )),
+        }
+    }
+}
+
+/// 输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Ts,
+    Json,
+}
+
+impl ConfigFormat {
+    /// 该格式的默认文件名
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            ConfigFormat::Ts => "aether.config.ts",
+            ConfigFormat::Json => "aether.config.json",
+        }
+    }
+}
+
+impl FromStr for ConfigFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ts" => Ok(ConfigFormat::Ts),
+            "json" => Ok(ConfigFormat::Json),
+            _ => Err(format!("Invalid format '{}'. Must be: ts or json", s)),
+        }
+    }
+}
+
+/// 从服务器查询到的信息
+#[derive(Debug, Clone)]
+pub struct RemoteConfig {
+    pub server: String,
+    pub info: ServerInfo,
+    pub services: Vec<RegisteredService>,
+}
+
+/// 查询服务器能力与已注册的 service
+pub async fn fetch_remote(server: &str) -> anyhow::Result<RemoteConfig> {
+    let mut client = ClientServiceClient::connect(grpc_endpoint(server))
+        .await
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "Could not connect to the Aether server at {}: {}\n\
+                 Start it with `aether serve`, pass --server <HOST:PORT>, \
+                 or use --config-source local",
+                server,
+                e
+            )
+        })?;
+    let info = client
+        .get_server_info(GetServerInfoRequest {})
+        .await
+        .map_err(|status| anyhow::anyhow!(status.message().to_string()))?
+        .into_inner();
+    let services = client
+        .list_services(ListServicesRequest {})
+        .await
+        .map_err(|status| anyhow::anyhow!(status.message().to_string()))?
+        .into_inner()
+        .services;
+    Ok(RemoteConfig {
+        server: server.to_string(),
+        info,
+        services,
+    })
+}
+
+/// 在目录中查找已有的配置文件，JSON 优先
+///
+/// TypeScript 配置只支持 `aether gen config` 生成的格式：`export default` 后是 JSON 对象。
+pub fn load_local_config(dir: &Path) -> anyhow::Result<Option<(PathBuf, Value)>> {
+    for format in [ConfigFormat::Json, ConfigFormat::Ts] {
+        let path = dir.join(format.file_name());
+        if !path.exists() {
+            continue;
+        }
+        let content = std::fs::read_to_string(&path)?;
+        let value = match format {
+            ConfigFormat::Json => serde_json::from_str(&content).ok(),
+            ConfigFormat::Ts => parse_ts_config(&content),
+        };
+        return match value {
+            Some(value @ Value::Object(_)) => Ok(Some((path, value))),
+            _ => Err(anyhow::anyhow!(
+                "Could not parse {}: only JSON configs and TypeScript configs generated by \
+                 `aether gen config` can be merged",
+                path.display()
+            )),
+        };
+    }
+    Ok(None)
+}
+
+/// 取出 `export default { ... } as const ...` 中的对象
+fn parse_ts_config(content: &str) -> Option<Value> {
+    let start = content.find("export default")? + "export default".len();
+    let body = &content[start..];
+    let end = body.rfind('}')? + 1;
+    serde_json::from_str(body[..end].trim()).ok()
+}
+
+fn default_config() -> Value {
+    json!({
+        "name": "my-workflow",
+        "services": {},
+        "scan": {
+            "workflows": "./src/workflows/**/*.{ts,js}",
+            "steps": "./src/steps/**/*.{ts,js}",
+            "activities": "./src/activities/**/*.{ts,js}"
+        }
+    })
+}
+
+fn resource_type_name(value: i32) -> &'static str {
+    match ResourceType::try_from(value) {
+        Ok(ResourceType::Activity) => "activity",
+        Ok(ResourceType::Workflow) => "workflow",
+        _ => "step",
+    }
+}
+
+/// 以本地配置为基础，用服务器上注册的 service 覆盖同名 service
+///
+/// 服务器未记录 endpoint 时保留本地配置中的 endpoint。
+pub fn build_config(local: Option<Value>, remote: Option<&RemoteConfig>) -> Value {
+    let mut config = local.unwrap_or_else(default_config);
+    let Some(remote) = remote else {
+        return config;
+    };
+
+    let limits = remote.info.limits.clone().unwrap_or_default();
+    config["server"] = json!({
+        "address": remote.server,
+        "version": remote.info.version,
+        "protocolVersion": remote.info.protocol_version,
+        "features": remote.info.features,
+        "limits": {
+            "maxPayloadBytes": limits.max_payload_bytes,
+            "maxBatchSize": limits.max_batch_size
+        }
+    });
+
+    if !config["services"].is_object() {
+        config["services"] = json!({});
+    }
+    for service in &remote.services {
+        let resources: Map<String, Value> = service
+            .provides
+            .iter()
+            .map(|r| {
+                (
+                    r.name.clone(),
+                    json!({ "type": resource_type_name(r.r#type) }),
+                )
+            })
+            .collect();
+        let endpoint = if service.endpoint.is_empty() {
+            config["services"][&service.service_name]["endpoint"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string()
+        } else {
+            service.endpoint.clone()
+        };
+        config["services"][&service.service_name] = json!({
+            "serviceName": service.service_name,
+            "group": service.group,
+            "endpoint": endpoint,
+            "resources": resources
+        });
+    }
+    config
+}
+
+/// 按格式输出配置文件内容
+pub fn render(config: &Value, format: ConfigFormat) -> anyhow::Result<String> {
+    let body = serde_json::to_string_pretty(config)?;
+    Ok(match format {
+        ConfigFormat::Json => format!("{}\n", body),
+        ConfigFormat::Ts => format!(
+            "// Auto-generated by Aether CLI\n\
+             // Run: aether gen config --config-source remote --server localhost:7234\n\n\
+             export default {} as const satisfies AetherConfig;\n",
+            body
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetherframework_kernel::proto::{ServerLimits, ServiceResource};
+
+    fn remote() -> RemoteConfig {
+        RemoteConfig {
+            server: "localhost:7234".to_string(),
+            info: ServerInfo {
+                version: "0.1.4".to_string(),
+                limits: Some(ServerLimits {
+                    max_payload_bytes: 1024,
+                    max_batch_size: 100,
+                }),
+                ..Default::default()
+            },
+            services: vec![RegisteredService {
+                service_name: "payments".to_string(),
+                group: "prod".to_string(),
+                provides: vec![
+                    ServiceResource {
+                        name: "charge".to_string(),
+                        r#type: ResourceType::Activity as i32,
+                        metadata: None,
+                    },
+                    ServiceResource {
+                        name: "checkout".to_string(),
+                        r#type: ResourceType::Workflow as i32,
+                        metadata: None,
+                    },
+                ],
+                ..Default::default()
+            }],
+        }
+    }
+
+    #[test]
+    fn test_remote_services_are_merged_into_local_config() {
+        let local = json!({
+            "name": "shop",
+            "services": {
+                "payments": { "serviceName": "payments", "group": "dev", "endpoint": "http://pay:3000" },
+                "email": { "serviceName": "email", "group": "dev", "endpoint": "" }
+            }
+        });
+
+        let config = build_config(Some(local), Some(&remote()));
+        assert_eq!(config["name"], "shop");
+        assert_eq!(config["server"]["limits"]["maxBatchSize"], 100);
+        let payments = &config["services"]["payments"];
+        assert_eq!(payments["group"], "prod");
+        assert_eq!(payments["endpoint"], "http://pay:3000");
+        assert_eq!(payments["resources"]["charge"]["type"], "activity");
+        assert_eq!(payments["resources"]["checkout"]["type"], "workflow");
+        assert_eq!(config["services"]["email"]["group"], "dev");
+    }
+
+    #[test]
+    fn test_generated_ts_config_can_be_loaded_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = build_config(None, Some(&remote()));
+        let content = render(&config, ConfigFormat::Ts).unwrap();
+        std::fs::write(dir.path().join("aether.config.ts"), content).unwrap();
+
+        let (path, loaded) = load_local_config(dir.path()).unwrap().unwrap();
+        assert!(path.ends_with("aether.config.ts"));
+        assert_eq!(loaded, config);
+    }
+
+    #[test]
+    fn test_unparseable_local_config_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(load_local_config(dir.path()).unwrap().is_none());
+
+        std::fs::write(
+            dir.path().join("aether.config.ts"),
+            "import { cfg } from './cfg';\nexport default cfg;\n",
+        )
+        .unwrap();
+        assert!(load_local_config(dir.path()).is_err());
+    }
+}
@@ -0,0 +1,471 @@
+//! aether workflow register / validate：读取并校验工作流定义文件
+//!
+//! 定义文件是 YAML 或 JSON 格式的 [`WorkflowDefinitionSpec`]，与 `PUT /workflow-definitions/{name}`
+//! 的请求体相同。校验在本地完成，每个错误带有所在的行号：
+//!
+//! ```text
+//! etl.yaml:9: steps[1].dependsOn[0]: Step 'load' depends on unknown step 'extrct'
+//! ```
+//!
+//! 文件由 serde_yaml / serde_json 解析，语法和类型错误的位置取自它们的错误。
+
+use aetherframework_kernel::definition::{
+    DefinitionError, DefinitionProblem, WorkflowDefinition, WorkflowDefinitionSpec,
+};
+use anyhow::Context;
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use std::fmt;
+use std::path::Path;
+
+/// 定义文件的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefinitionFormat {
+    Json,
+    Yaml,
+}
+
+impl DefinitionFormat {
+    /// 按扩展名判断格式；扩展名无法识别时以 `{` 开头的内容视为 JSON
+    pub fn detect(path: &Path, content: &str) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => DefinitionFormat::Json,
+            Some("yaml" | "yml") => DefinitionFormat::Yaml,
+            _ if content.trim_start().starts_with('{') => DefinitionFormat::Json,
+            _ => DefinitionFormat::Yaml,
+        }
+    }
+}
+
+/// 定义文件中的一个错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileError {
+    /// 从 1 开始的行号
+    pub line: usize,
+    /// 从 1 开始的列号，只有语法和类型错误带有
+    pub column: Option<usize>,
+    /// 出错的字段，如 `steps[1].dependsOn[0]`；语法和类型错误时为空
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.line)?;
+        if let Some(column) = self.column {
+            write!(f, ":{}", column)?;
+        }
+        if self.path.is_empty() {
+            write!(f, ": {}", self.message)
+        } else {
+            write!(f, ": {}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// 读取并校验定义文件，错误信息按 `文件:行: 字段: 错误` 列出所有错误，语法和类型错误还带有列号
+pub fn read_definition(path: &Path) -> anyhow::Result<WorkflowDefinition> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    parse_definition(&content, DefinitionFormat::detect(path, &content)).map_err(|errors| {
+        let lines: Vec<String> = errors
+            .iter()
+            .map(|error| format!("  {}:{}", path.display(), error))
+            .collect();
+        anyhow::anyhow!(
+            "{} is not a valid workflow definition:\n{}",
+            path.display(),
+            lines.join("\n")
+        )
+    })
+}
+
+/// 解析并校验定义文件的内容
+///
+/// 除了格式错误，还检查定义本身（依赖、重复的 step 等），但不检查服务注册表，
+/// 目标资源是否存在由服务器在注册时检查。
+pub fn parse_definition(
+    content: &str,
+    format: DefinitionFormat,
+) -> Result<WorkflowDefinition, Vec<FileError>> {
+    let spec: WorkflowDefinitionSpec = match format {
+        DefinitionFormat::Json => serde_json::from_str(content)
+            .map_err(|err| syntax_error(err.line(), err.column(), err.to_string())),
+        DefinitionFormat::Yaml => serde_yaml::from_str(content).map_err(|err| {
+            let (line, column) = err
+                .location()
+                .map_or((1, 0), |location| (location.line(), location.column()));
+            syntax_error(line, column, err.to_string())
+        }),
+    }
+    .map_err(|error| vec![error])?;
+
+    let (definition, conversion_problems) = spec.into_definition();
+    let mut problems = Vec::new();
+    if definition.name.is_empty() {
+        problems.push(DefinitionProblem::new("name", DefinitionError::MissingName));
+    }
+    problems.extend(conversion_problems);
+    problems.extend(definition.problems());
+    if problems.is_empty() {
+        return Ok(definition);
+    }
+    Err(problems
+        .into_iter()
+        .map(|problem| FileError {
+            line: line_of(content, format, &problem.path),
+            column: None,
+            message: problem.error.to_string(),
+            path: problem.path,
+        })
+        .collect())
+}
+
+/// 解析器的错误信息末尾带有 ` at line L column C`，位置单独报告，从信息中去掉
+fn syntax_error(line: usize, column: usize, message: String) -> FileError {
+    let message = match message.rsplit_once(" at line ") {
+        Some((message, location)) if location.contains(" column ") => message.to_string(),
+        _ => message,
+    };
+    FileError {
+        line: line.max(1),
+        column: (column > 0).then_some(column),
+        path: String::new(),
+        message,
+    }
+}
+
+/// `steps[1].dependsOn[0]` 这样的字段所在的行；字段不存在时返回最近的上级所在的行
+///
+/// 按路径走到字段时返回一个错误，解析器给错误附上当前位置，即字段所在的行。
+fn line_of(content: &str, format: DefinitionFormat, path: &str) -> usize {
+    let segments = path_segments(path);
+    (0..=segments.len())
+        .rev()
+        .find_map(|depth| {
+            let seed = Locate(&segments[..depth]);
+            match format {
+                DefinitionFormat::Json => seed
+                    .deserialize(&mut serde_json::Deserializer::from_str(content))
+                    .err()
+                    .map(|err| err.line()),
+                DefinitionFormat::Yaml => seed
+                    .deserialize(serde_yaml::Deserializer::from_str(content))
+                    .err()
+                    .and_then(|err| err.location())
+                    .map(|location| location.line()),
+            }
+        })
+        .unwrap_or(1)
+        .max(1)
+}
+
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+fn path_segments(path: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    for part in path.split('.').filter(|part| !part.is_empty()) {
+        let mut pieces = part.split('[');
+        if let Some(key) = pieces.next().filter(|key| !key.is_empty()) {
+            segments.push(Segment::Key(key.to_string()));
+        }
+        for index in pieces {
+            match index.trim_end_matches(']').parse() {
+                Ok(index) => segments.push(Segment::Index(index)),
+                Err(_) => break,
+            }
+        }
+    }
+    segments
+}
+
+/// 错误路径使用 camelCase 字段名，文件中可能使用 snake_case 或别名
+fn canonical_key(key: &str) -> String {
+    let key = key.replace('_', "").to_lowercase();
+    match key.as_str() {
+        "service" => "targetservice".to_string(),
+        "resource" => "targetresource".to_string(),
+        "type" => "resourcetype".to_string(),
+        _ => key,
+    }
+}
+
+/// 沿路径遍历文档，到达字段时返回 [`Locate::FOUND`] 错误，字段不存在时返回 `Ok`
+struct Locate<'a>(&'a [Segment]);
+
+impl Locate<'_> {
+    const FOUND: &'static str = "found";
+
+    fn found<E: de::Error>(&self) -> Result<(), E> {
+        match self.0 {
+            [] => Err(E::custom(Self::FOUND)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for Locate<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Locate<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "any value")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let key = match self.0 {
+            [] => return self.found(),
+            [Segment::Key(key), ..] => Some(canonical_key(key)),
+            _ => None,
+        };
+        while let Some(name) = map.next_key::<String>()? {
+            if key.as_deref() == Some(canonical_key(&name).as_str()) {
+                map.next_value_seed(Locate(&self.0[1..]))?;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let index = match self.0 {
+            [] => return self.found(),
+            [Segment::Index(index), ..] => Some(*index),
+            _ => None,
+        };
+        let mut position = 0;
+        while Some(position) != index {
+            if seq.next_element::<IgnoredAny>()?.is_none() {
+                return Ok(());
+            }
+            position += 1;
+        }
+        seq.next_element_seed(Locate(&self.0[1..]))?;
+        while seq.next_element::<IgnoredAny>()?.is_some() {}
+        Ok(())
+    }
+
+    fn visit_bool<E: de::Error>(self, _: bool) -> Result<(), E> {
+        self.found()
+    }
+
+    fn visit_i64<E: de::Error>(self, _: i64) -> Result<(), E> {
+        self.found()
+    }
+
+    fn visit_u64<E: de::Error>(self, _: u64) -> Result<(), E> {
+        self.found()
+    }
+
+    fn visit_f64<E: de::Error>(self, _: f64) -> Result<(), E> {
+        self.found()
+    }
+
+    fn visit_str<E: de::Error>(self, _: &str) -> Result<(), E> {
+        self.found()
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<(), E> {
+        self.found()
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<(), E> {
+        self.found()
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        self.deserialize(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetherframework_kernel::task::ResourceType;
+    use std::time::Duration;
+
+    const ETL_YAML: &str = "\
+# 每晚同步仓库数据
+name: etl
+version: 2
+steps:
+  - name: extract
+    target_service: warehouse
+    targetResource: extract
+    timeout: 30000
+  - name: load
+    dependsOn: [extract]
+    resourceType: activity
+    retry: { maxAttempts: 5, initialInterval: 500 }
+  - name: \"notify: done\"   # 引号中的冒号
+    depends_on:
+    - load
+    waitForSignal: 'ops''s approval'
+outputStep: load
+";
+
+    fn errors(content: &str, format: DefinitionFormat) -> Vec<(usize, String)> {
+        parse_definition(content, format)
+            .unwrap_err()
+            .into_iter()
+            .map(|error| (error.line, error.path))
+            .collect()
+    }
+
+    #[test]
+    fn test_yaml_and_json_parse_to_the_same_definition() {
+        let definition = parse_definition(ETL_YAML, DefinitionFormat::Yaml).unwrap();
+        assert_eq!(definition.name, "etl");
+        assert_eq!(definition.version, 2);
+        assert_eq!(definition.steps.len(), 3);
+        assert_eq!(
+            definition.steps[0].target_service.as_deref(),
+            Some("warehouse")
+        );
+        assert_eq!(definition.steps[0].timeout, Some(Duration::from_secs(30)));
+        assert_eq!(definition.steps[1].depends_on, vec!["extract"]);
+        assert_eq!(definition.steps[1].resource_type, ResourceType::Activity);
+        assert_eq!(
+            definition.steps[1].retry.as_ref().unwrap().initial_interval,
+            500
+        );
+        assert_eq!(definition.steps[2].name, "notify: done");
+        assert_eq!(definition.steps[2].depends_on, vec!["load"]);
+        assert_eq!(
+            definition.steps[2].wait_for_signal.as_deref(),
+            Some("ops's approval")
+        );
+        assert_eq!(definition.output_step.as_deref(), Some("load"));
+
+        let json = serde_json::to_string_pretty(&WorkflowDefinitionSpec::from(definition.clone()))
+            .unwrap();
+        assert_eq!(
+            parse_definition(&json, DefinitionFormat::Json).unwrap(),
+            definition
+        );
+    }
+
+    #[test]
+    fn test_full_yaml_syntax_is_accepted() {
+        let yaml = "\
+name: etl
+steps:
+  - &extract {name: extract, targetService: warehouse}
+  - name: load
+    dependsOn: [extract]
+    waitForSignal: >
+      ops
+      approval
+";
+        let definition = parse_definition(yaml, DefinitionFormat::Yaml).unwrap();
+        assert_eq!(definition.steps[0].name, "extract");
+        assert_eq!(
+            definition.steps[1].wait_for_signal.as_deref(),
+            Some("ops approval\n")
+        );
+    }
+
+    #[test]
+    fn test_problems_are_reported_with_lines() {
+        let yaml = "\
+steps:
+  - name: extract
+  - name: load
+    dependsOn:
+      - extract
+      - extrct
+    resourceType: job
+";
+        assert_eq!(
+            errors(yaml, DefinitionFormat::Yaml),
+            vec![
+                (1, "name".to_string()),
+                (7, "steps[1].resourceType".to_string()),
+                (6, "steps[1].dependsOn[1]".to_string())
+            ]
+        );
+
+        // 字段名使用 snake_case 时同样能找到所在的行
+        let yaml = "name: etl\nsteps:\n  - name: a\n  - name: b\n    depends_on: [a, c]\n";
+        assert_eq!(
+            errors(yaml, DefinitionFormat::Yaml),
+            vec![(5, "steps[1].dependsOn[1]".to_string())]
+        );
+
+        let json = "{\n  \"name\": \"etl\",\n  \"steps\": [\n    { \"name\": \"a\" },\n    { \"name\": \"a\" }\n  ]\n}";
+        assert_eq!(
+            errors(json, DefinitionFormat::Json),
+            vec![(5, "steps[1].name".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_syntax_errors_are_reported_with_lines() {
+        let cases = [
+            (
+                "name: etl\nsteps:\n\t- name: a\n",
+                3,
+                "cannot start any token",
+            ),
+            ("name: etl\n  version: 1\n", 2, "mapping values"),
+            // 重复的键在映射结束时才被发现，位置是映射开始的行
+            ("name: etl\nname: again\n", 1, "duplicate field `name`"),
+            (
+                "name: etl\nsteps:\n  - name: a\n    timeout: 30s\n",
+                4,
+                "steps[0].timeout: invalid type",
+            ),
+            (
+                "version: 1\nsteps:\n  - name: a\n    dependson: [b]\n",
+                4,
+                "unknown field `dependson`",
+            ),
+        ];
+        for (yaml, line, expected) in cases {
+            let errors = parse_definition(yaml, DefinitionFormat::Yaml).unwrap_err();
+            assert_eq!(errors.len(), 1, "{:?}", errors);
+            assert_eq!(errors[0].line, line, "{:?}", errors);
+            assert!(errors[0].column.is_some(), "{:?}", errors);
+            assert!(errors[0].message.contains(expected), "{:?}", errors);
+            assert!(!errors[0].message.contains("at line"), "{:?}", errors);
+        }
+
+        let errors = parse_definition(
+            "{\n  \"name\": \"etl\",\n  \"steps\": [}\n",
+            DefinitionFormat::Json,
+        )
+        .unwrap_err();
+        assert_eq!(errors[0].line, 3);
+        assert!(!errors[0].message.contains("at line"), "{:?}", errors);
+        assert_eq!(
+            errors[0].to_string(),
+            format!("3:{}: {}", errors[0].column.unwrap(), errors[0].message)
+        );
+    }
+
+    #[test]
+    fn test_format_detection() {
+        assert_eq!(
+            DefinitionFormat::detect(Path::new("etl.yml"), "{}"),
+            DefinitionFormat::Yaml
+        );
+        assert_eq!(
+            DefinitionFormat::detect(Path::new("etl.json"), "name: etl"),
+            DefinitionFormat::Json
+        );
+        assert_eq!(
+            DefinitionFormat::detect(Path::new("etl.def"), "  {\"name\": \"etl\"}"),
+            DefinitionFormat::Json
+        );
+    }
+}
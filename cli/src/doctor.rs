@@ -0,0 +1,431 @@
+// aether doctor：连接服务器并逐项检查部署状态
+use aetherframework_kernel::proto::admin_service_client::AdminServiceClient;
+use aetherframework_kernel::proto::{
+    Diagnostics, GetDiagnosticsRequest, GetServerInfoRequest, ServerInfo,
+};
+use aetherframework_kernel::server_info::PROTOCOL_VERSION;
+use serde::Serialize;
+use std::time::Duration;
+
+/// 存储探测延迟超过该值时给出警告
+const SLOW_PERSISTENCE_PROBE: Duration = Duration::from_millis(100);
+
+/// 单项检查结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl CheckStatus {
+    pub fn icon(&self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "✅",
+            CheckStatus::Warn => "⚠️ ",
+            CheckStatus::Fail => "❌",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<String>,
+}
+
+impl CheckResult {
+    fn new(name: &'static str, status: CheckStatus, message: impl Into<String>) -> Self {
+        CheckResult {
+            name,
+            status,
+            message: message.into(),
+            details: vec![],
+        }
+    }
+
+    fn with_details(mut self, details: Vec<String>) -> Self {
+        self.details = details;
+        self
+    }
+}
+
+/// 诊断报告
+#[derive(Debug, Clone, Serialize)]
+pub struct DoctorReport {
+    pub server: String,
+    pub checks: Vec<CheckResult>,
+}
+
+impl DoctorReport {
+    /// 是否存在失败的检查
+    pub fn has_failures(&self) -> bool {
+        self.checks.iter().any(|c| c.status == CheckStatus::Fail)
+    }
+
+    /// 人类可读的输出
+    pub fn render(&self) -> String {
+        let mut out = format!("Aether doctor: {}\n\n", self.server);
+        for check in &self.checks {
+            out.push_str(&format!(
+                "{} {}: {}\n",
+                check.status.icon(),
+                check.name,
+                check.message
+            ));
+            for detail in &check.details {
+                out.push_str(&format!("     - {}\n", detail));
+            }
+        }
+        out
+    }
+}
+
+/// 补全 gRPC 地址的协议前缀
+pub fn grpc_endpoint(server: &str) -> String {
+    if server.starts_with("http://") || server.starts_with("https://") {
+        server.to_string()
+    } else {
+        format!("http://{}", server)
+    }
+}
+
+/// 连接服务器并执行所有检查
+pub async fn run(server: &str, stuck_after: Duration) -> DoctorReport {
+    let endpoint = grpc_endpoint(server);
+
+    let mut checks = Vec::new();
+
+    let mut client = match AdminServiceClient::connect(endpoint.clone()).await {
+        Ok(client) => client,
+        Err(e) => {
+            checks.push(check_reachable(Err(e.to_string())));
+            return DoctorReport {
+                server: endpoint,
+                checks,
+            };
+        }
+    };
+
+    let info = match client.get_server_info(GetServerInfoRequest {}).await {
+        Ok(response) => response.into_inner(),
+        Err(e) => {
+            checks.push(check_reachable(Err(e.message().to_string())));
+            return DoctorReport {
+                server: endpoint,
+                checks,
+            };
+        }
+    };
+    checks.push(check_reachable(Ok(&info)));
+    checks.push(check_version(env!("CARGO_PKG_VERSION"), &info));
+    checks.push(check_protocol(PROTOCOL_VERSION, &info));
+
+    let request = GetDiagnosticsRequest {
+        stuck_after_seconds: stuck_after.as_secs() as i64,
+    };
+    match client.get_diagnostics(request).await {
+        Ok(response) => checks.extend(check_diagnostics(&response.into_inner())),
+        Err(e) => checks.push(CheckResult::new(
+            "diagnostics",
+            CheckStatus::Fail,
+            format!("Failed to fetch diagnostics: {}", e.message()),
+        )),
+    }
+
+    DoctorReport {
+        server: endpoint,
+        checks,
+    }
+}
+
+/// 基于诊断数据的检查
+pub fn check_diagnostics(diagnostics: &Diagnostics) -> Vec<CheckResult> {
+    vec![
+        check_persistence(diagnostics),
+        check_workers(diagnostics),
+        check_stuck_workflows(diagnostics),
+        check_subscribers(diagnostics),
+    ]
+}
+
+pub fn check_reachable(info: Result<&ServerInfo, String>) -> CheckResult {
+    match info {
+        Ok(info) => CheckResult::new(
+            "server",
+            CheckStatus::Pass,
+            format!(
+                "reachable (version {}, features: {})",
+                info.version,
+                info.features.join(", ")
+            ),
+        ),
+        Err(e) => CheckResult::new("server", CheckStatus::Fail, format!("unreachable: {}", e)),
+    }
+}
+
+/// CLI 与服务器版本兼容性：主版本不同为失败，次版本不同为警告
+pub fn check_version(cli_version: &str, info: &ServerInfo) -> CheckResult {
+    fn major_minor(version: &str) -> (&str, &str) {
+        let mut parts = version.split('.');
+        (parts.next().unwrap_or(""), parts.next().unwrap_or(""))
+    }
+
+    let (cli_major, cli_minor) = major_minor(cli_version);
+    let (server_major, server_minor) = major_minor(&info.version);
+    let message = format!("CLI {} / server {}", cli_version, info.version);
+
+    let status = if cli_major != server_major {
+        CheckStatus::Fail
+    } else if cli_minor != server_minor {
+        CheckStatus::Warn
+    } else {
+        CheckStatus::Pass
+    };
+
+    CheckResult::new("version", status, message)
+}
+
+/// 协议版本不一致时部分功能不可用，给出警告并列出服务器能力
+pub fn check_protocol(cli_protocol: u32, info: &ServerInfo) -> CheckResult {
+    let status = if info.protocol_version == cli_protocol {
+        CheckStatus::Pass
+    } else {
+        CheckStatus::Warn
+    };
+
+    let mut details = Vec::new();
+    if let Some(subsystems) = &info.subsystems {
+        details.push(format!(
+            "auth: {}, namespaces: {}",
+            on_off(subsystems.auth),
+            on_off(subsystems.namespaces)
+        ));
+    }
+    if let Some(limits) = &info.limits {
+        details.push(format!(
+            "max payload: {} bytes, max batch size: {}",
+            limits.max_payload_bytes, limits.max_batch_size
+        ));
+    }
+
+    CheckResult::new(
+        "protocol",
+        status,
+        format!(
+            "CLI protocol {} / server protocol {}",
+            cli_protocol, info.protocol_version
+        ),
+    )
+    .with_details(details)
+}
+
+fn on_off(enabled: bool) -> &'static str {
+    if enabled {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+pub fn check_persistence(diagnostics: &Diagnostics) -> CheckResult {
+    let probe = Duration::from_micros(diagnostics.persistence_probe_micros.max(0) as u64);
+    let status = if probe > SLOW_PERSISTENCE_PROBE {
+        CheckStatus::Warn
+    } else {
+        CheckStatus::Pass
+    };
+
+    CheckResult::new(
+        "persistence",
+        status,
+        format!(
+            "{} backend, probe latency {:?}",
+            diagnostics.persistence_backend, probe
+        ),
+    )
+}
+
+pub fn check_workers(diagnostics: &Diagnostics) -> CheckResult {
+    if diagnostics.workers.is_empty() {
+        return CheckResult::new(
+            "workers",
+            CheckStatus::Warn,
+            "no workers registered; tasks will not be dispatched",
+        );
+    }
+
+    let details = diagnostics
+        .workers
+        .iter()
+        .map(|w| {
+            let mut supports = w.workflow_types.clone();
+            supports.extend(w.resources.iter().cloned());
+            format!(
+                "{} ({}): {}",
+                w.service_name,
+                w.worker_id,
+                if supports.is_empty() {
+                    "no declared types".to_string()
+                } else {
+                    supports.join(", ")
+                }
+            )
+        })
+        .collect();
+
+    CheckResult::new(
+        "workers",
+        CheckStatus::Pass,
+        format!("{} registered", diagnostics.workers.len()),
+    )
+    .with_details(details)
+}
+
+pub fn check_stuck_workflows(diagnostics: &Diagnostics) -> CheckResult {
+    if diagnostics.stuck_workflows.is_empty() {
+        return CheckResult::new("workflows", CheckStatus::Pass, "no stuck workflows");
+    }
+
+    let details = diagnostics
+        .stuck_workflows
+        .iter()
+        .map(|w| format!("{} ({})", w.workflow_id, w.workflow_type))
+        .collect();
+
+    CheckResult::new(
+        "workflows",
+        CheckStatus::Warn,
+        format!(
+            "{} workflow(s) pending or running without a leased task",
+            diagnostics.stuck_workflows.len()
+        ),
+    )
+    .with_details(details)
+}
+
+pub fn check_subscribers(diagnostics: &Diagnostics) -> CheckResult {
+    CheckResult::new(
+        "events",
+        CheckStatus::Pass,
+        format!("{} event subscriber(s)", diagnostics.subscriber_count),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetherframework_kernel::grpc_server::AdminServiceImpl;
+    use aetherframework_kernel::persistence::l0_memory::L0MemoryStore;
+    use aetherframework_kernel::persistence::Persistence;
+    use aetherframework_kernel::proto::admin_service_server::AdminService;
+    use aetherframework_kernel::scheduler::Scheduler;
+    use aetherframework_kernel::state_machine::Workflow;
+    use std::sync::Arc;
+
+    async fn diagnostics_of(scheduler: Arc<Scheduler<L0MemoryStore>>) -> Diagnostics {
+        AdminServiceImpl::new(scheduler)
+            .get_diagnostics(tonic::Request::new(GetDiagnosticsRequest {
+                stuck_after_seconds: 0,
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+    }
+
+    #[tokio::test]
+    async fn test_empty_scheduler() {
+        let scheduler = Arc::new(Scheduler::new(L0MemoryStore::new()));
+        let diagnostics = diagnostics_of(scheduler).await;
+
+        assert_eq!(check_persistence(&diagnostics).status, CheckStatus::Pass);
+        assert_eq!(check_workers(&diagnostics).status, CheckStatus::Warn);
+        assert_eq!(
+            check_stuck_workflows(&diagnostics).status,
+            CheckStatus::Pass
+        );
+        assert_eq!(check_subscribers(&diagnostics).status, CheckStatus::Pass);
+    }
+
+    #[tokio::test]
+    async fn test_workers_and_stuck_workflows() {
+        let store = L0MemoryStore::new();
+        let workflow = Workflow::new("wf-1".to_string(), "order".to_string(), vec![]);
+        store.save_workflow(&workflow).await.unwrap();
+
+        let scheduler = Arc::new(Scheduler::new(store));
+        scheduler
+            .register_worker(
+                "worker-1".to_string(),
+                "orders".to_string(),
+                "default".to_string(),
+                vec!["order".to_string()],
+                vec![],
+            )
+            .await;
+
+        let diagnostics = diagnostics_of(scheduler).await;
+
+        let workers = check_workers(&diagnostics);
+        assert_eq!(workers.status, CheckStatus::Pass);
+        assert_eq!(workers.details, vec!["orders (worker-1): order"]);
+
+        let stuck = check_stuck_workflows(&diagnostics);
+        assert_eq!(stuck.status, CheckStatus::Warn);
+        assert_eq!(stuck.details, vec!["wf-1 (order)"]);
+    }
+
+    #[tokio::test]
+    async fn test_server_info_version() {
+        let scheduler = Arc::new(Scheduler::new(L0MemoryStore::new()));
+        let info = AdminServiceImpl::new(scheduler)
+            .get_server_info(tonic::Request::new(GetServerInfoRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(check_reachable(Ok(&info)).status, CheckStatus::Pass);
+        assert_eq!(
+            check_version(env!("CARGO_PKG_VERSION"), &info).status,
+            CheckStatus::Pass
+        );
+
+        let protocol = check_protocol(PROTOCOL_VERSION, &info);
+        assert_eq!(protocol.status, CheckStatus::Pass);
+        assert_eq!(protocol.details.len(), 2);
+    }
+
+    #[test]
+    fn test_protocol_mismatch() {
+        // 早于协议版本号的服务器返回 0
+        let info = ServerInfo {
+            version: "0.1.0".to_string(),
+            ..Default::default()
+        };
+        let protocol = check_protocol(PROTOCOL_VERSION, &info);
+        assert_eq!(protocol.status, CheckStatus::Warn);
+        assert!(protocol.details.is_empty());
+    }
+
+    #[test]
+    fn test_version_mismatch() {
+        let info = ServerInfo {
+            version: "1.2.3".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(check_version("1.2.9", &info).status, CheckStatus::Pass);
+        assert_eq!(check_version("1.3.0", &info).status, CheckStatus::Warn);
+        assert_eq!(check_version("2.0.0", &info).status, CheckStatus::Fail);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_server() {
+        let report = run("127.0.0.1:1", Duration::from_secs(60)).await;
+        assert_eq!(report.checks.len(), 1);
+        assert!(report.has_failures());
+    }
+}
@@ -0,0 +1,747 @@
+// aether init：确定项目名称、模板和模板选项，未通过参数指定的选项在终端中逐项询问；
+// 生成项目后按 `--install` / `--git` 安装依赖和初始化 git 仓库
+use anyhow::{Context, Result};
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::str::FromStr;
+use tokio::process::Command;
+
+use crate::templates::{to_snake_case, TemplateManifest, TemplateType, TemplateVariables};
+
+/// 未指定时使用的服务器地址
+pub const DEFAULT_SERVER: &str = "localhost:7233";
+
+/// 服务器默认的 gRPC 端口，与 `[server] grpc_port` 的默认值相同
+pub const DEFAULT_GRPC_PORT: u16 = 7234;
+
+/// Cargo 不接受的包名：Rust 关键字和标准库 crate
+const RESERVED_CRATE_NAMES: &[&str] = &[
+    "abstract",
+    "alloc",
+    "as",
+    "async",
+    "await",
+    "become",
+    "box",
+    "break",
+    "const",
+    "continue",
+    "core",
+    "crate",
+    "do",
+    "dyn",
+    "else",
+    "enum",
+    "extern",
+    "false",
+    "final",
+    "fn",
+    "for",
+    "if",
+    "impl",
+    "in",
+    "let",
+    "loop",
+    "macro",
+    "match",
+    "mod",
+    "move",
+    "mut",
+    "override",
+    "priv",
+    "proc_macro",
+    "pub",
+    "ref",
+    "return",
+    "self",
+    "static",
+    "std",
+    "struct",
+    "super",
+    "test",
+    "trait",
+    "true",
+    "try",
+    "type",
+    "typeof",
+    "unsafe",
+    "unsized",
+    "use",
+    "virtual",
+    "where",
+    "while",
+    "yield",
+];
+
+/// 包管理器
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageManager {
+    Npm,
+    Pnpm,
+    Yarn,
+    Pip,
+    Uv,
+    Cargo,
+}
+
+impl FromStr for PackageManager {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "npm" => Ok(PackageManager::Npm),
+            "pnpm" => Ok(PackageManager::Pnpm),
+            "yarn" => Ok(PackageManager::Yarn),
+            "pip" => Ok(PackageManager::Pip),
+            "uv" => Ok(PackageManager::Uv),
+            "cargo" => Ok(PackageManager::Cargo),
+            _ => Err(anyhow::anyhow!(
+                "Unknown package manager: {}. Supported: npm, pnpm, yarn, pip, uv, cargo",
+                s
+            )),
+        }
+    }
+}
+
+impl PackageManager {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PackageManager::Npm => "npm",
+            PackageManager::Pnpm => "pnpm",
+            PackageManager::Yarn => "yarn",
+            PackageManager::Pip => "pip",
+            PackageManager::Uv => "uv",
+            PackageManager::Cargo => "cargo",
+        }
+    }
+
+    /// 安装依赖的命令
+    pub fn install_command(&self) -> &'static str {
+        match self {
+            PackageManager::Npm => "npm install",
+            PackageManager::Pnpm => "pnpm install",
+            PackageManager::Yarn => "yarn install",
+            PackageManager::Pip => "pip install -e .",
+            PackageManager::Uv => "uv sync",
+            PackageManager::Cargo => "cargo build",
+        }
+    }
+
+    /// 启动项目的命令
+    pub fn run_command(&self, template_type: TemplateType) -> String {
+        let script = match template_type {
+            TemplateType::NestJS => "start:dev",
+            _ => "dev",
+        };
+        match self {
+            PackageManager::Npm => format!("npm run {}", script),
+            PackageManager::Pnpm => format!("pnpm {}", script),
+            PackageManager::Yarn => format!("yarn {}", script),
+            PackageManager::Pip => "python src/main.py".to_string(),
+            PackageManager::Uv => "uv run src/main.py".to_string(),
+            PackageManager::Cargo => "cargo run".to_string(),
+        }
+    }
+}
+
+impl TemplateType {
+    /// 该模板可用的包管理器，第一个为默认值
+    pub fn package_managers(&self) -> &'static [PackageManager] {
+        match self {
+            TemplateType::TypeScript | TemplateType::NestJS => &[
+                PackageManager::Npm,
+                PackageManager::Pnpm,
+                PackageManager::Yarn,
+            ],
+            TemplateType::Python => &[PackageManager::Pip, PackageManager::Uv],
+            TemplateType::Rust => &[PackageManager::Cargo],
+        }
+    }
+}
+
+/// 示例工作流的规模
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Example {
+    /// 只有一个 step
+    Minimal,
+    /// 多个 step 依次执行
+    Full,
+}
+
+impl FromStr for Example {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "minimal" => Ok(Example::Minimal),
+            "full" => Ok(Example::Full),
+            _ => Err(anyhow::anyhow!(
+                "Unknown example: {}. Supported: full, minimal",
+                s
+            )),
+        }
+    }
+}
+
+impl Example {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Example::Minimal => "minimal",
+            Example::Full => "full",
+        }
+    }
+}
+
+/// 命令行参数，`None` 表示未指定
+#[derive(Debug, Clone, Default)]
+pub struct InitArgs {
+    pub name: Option<String>,
+    pub template: Option<String>,
+    pub package_manager: Option<String>,
+    pub server: Option<String>,
+    pub example: Option<String>,
+}
+
+/// 确定后的项目选项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitOptions {
+    pub name: String,
+    pub template: TemplateType,
+    pub package_manager: PackageManager,
+    /// 服务器地址，如 `localhost:7233`
+    pub server: String,
+    pub example: Example,
+}
+
+impl InitOptions {
+    /// 服务器的 HTTP 地址，地址中没有 scheme 时补上 `http://`
+    pub fn server_url(&self) -> String {
+        if self.server.contains("://") {
+            self.server.clone()
+        } else {
+            format!("http://{}", self.server)
+        }
+    }
+
+    /// 服务器的 gRPC 地址：服务器地址的主机加上默认的 gRPC 端口
+    pub fn grpc_url(&self) -> String {
+        let url = self.server_url();
+        let (scheme, authority) = url.split_once("://").unwrap_or(("http", &url));
+        let host = match authority.rsplit_once(':') {
+            // IPv6 地址中的 `:` 不是端口分隔符
+            Some((host, port)) if !port.contains(']') => host,
+            _ => authority,
+        };
+        format!("{}://{}:{}", scheme, host, DEFAULT_GRPC_PORT)
+    }
+
+    /// 模板变量：[`TemplateVariables::new`] 的变量以及
+    ///
+    /// - `server_address` / `server_url`：服务器地址及其 HTTP 地址
+    /// - `grpc_url`：服务器的 gRPC 地址，见 [`InitOptions::grpc_url`]
+    /// - `package_manager`、`install_command`、`run_command`
+    /// - `example`（`full` | `minimal`）和 `example_full`
+    pub fn variables(&self) -> TemplateVariables {
+        let mut vars = TemplateVariables::new(&self.name);
+        vars.set("server_address", self.server.as_str())
+            .set("server_url", self.server_url())
+            .set("grpc_url", self.grpc_url())
+            .set("package_manager", self.package_manager.name())
+            .set("install_command", self.package_manager.install_command())
+            .set(
+                "run_command",
+                self.package_manager.run_command(self.template),
+            )
+            .set("example", self.example.name())
+            .set("example_full", self.example == Example::Full);
+        vars
+    }
+}
+
+/// 向用户提问
+pub trait Prompt {
+    /// 返回回答；`choices` 不为空时回答必须是其中之一，直接回车时使用 `default`
+    fn ask(&mut self, question: &str, choices: &[&str], default: &str) -> Result<String>;
+}
+
+/// 从输入流读取回答的 [`Prompt`]
+pub struct Prompter<R, W> {
+    input: R,
+    output: W,
+}
+
+impl<R: BufRead, W: Write> Prompter<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Self { input, output }
+    }
+}
+
+impl<R: BufRead, W: Write> Prompt for Prompter<R, W> {
+    fn ask(&mut self, question: &str, choices: &[&str], default: &str) -> Result<String> {
+        loop {
+            write!(self.output, "? {}", question)?;
+            if !choices.is_empty() {
+                write!(self.output, " ({})", choices.join("/"))?;
+            }
+            if !default.is_empty() {
+                write!(self.output, " [{}]", default)?;
+            }
+            write!(self.output, ": ")?;
+            self.output.flush()?;
+
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                // 输入已关闭，不再询问
+                if default.is_empty() {
+                    return Err(anyhow::anyhow!("No answer for: {}", question));
+                }
+                return Ok(default.to_string());
+            }
+            let answer = line.trim();
+            if answer.is_empty() {
+                if default.is_empty() {
+                    continue;
+                }
+                return Ok(default.to_string());
+            }
+            if choices.is_empty() {
+                return Ok(answer.to_string());
+            }
+            // 接受选项的唯一前缀，如 `y` 表示 `yes`
+            let answer = answer.to_lowercase();
+            let matches: Vec<&str> = choices
+                .iter()
+                .copied()
+                .filter(|choice| choice.starts_with(answer.as_str()))
+                .collect();
+            match matches[..] {
+                [choice] => return Ok(choice.to_string()),
+                _ if choices.contains(&answer.as_str()) => return Ok(answer),
+                _ => writeln!(
+                    self.output,
+                    "  Please answer one of: {}",
+                    choices.join(", ")
+                )?,
+            }
+        }
+    }
+}
+
+/// 确定项目选项：使用参数，未指定的选项在 `prompt` 存在时询问，否则使用默认值
+pub fn resolve_options(args: InitArgs, mut prompt: Option<&mut dyn Prompt>) -> Result<InitOptions> {
+    let name = match (args.name, prompt.as_deref_mut()) {
+        (Some(name), _) => name,
+        (None, Some(prompt)) => prompt.ask("Project name", &[], "")?,
+        (None, None) => return Err(anyhow::anyhow!("Missing project name")),
+    };
+
+    let template = match (args.template, prompt.as_deref_mut()) {
+        (Some(template), _) => template,
+        (None, Some(prompt)) => {
+            prompt.ask("Template", &["ts", "nestjs", "python", "rust"], "ts")?
+        }
+        (None, None) => "ts".to_string(),
+    };
+    let template = TemplateType::from_str(&template)?;
+    validate_project_name(&name, template)?;
+
+    let supported = template.package_managers();
+    let package_manager = match (args.package_manager, prompt.as_deref_mut()) {
+        (Some(package_manager), _) => PackageManager::from_str(&package_manager)?,
+        (None, Some(prompt)) => {
+            let choices: Vec<&str> = supported.iter().map(|pm| pm.name()).collect();
+            PackageManager::from_str(&prompt.ask("Package manager", &choices, choices[0])?)?
+        }
+        (None, None) => supported[0],
+    };
+    if !supported.contains(&package_manager) {
+        return Err(anyhow::anyhow!(
+            "Package manager {} cannot be used with the {} template. Supported: {}",
+            package_manager.name(),
+            template.dir_name(),
+            supported
+                .iter()
+                .map(|pm| pm.name())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    let server = match (args.server, prompt.as_deref_mut()) {
+        (Some(server), _) => server,
+        (None, Some(prompt)) => prompt.ask("Aether server address", &[], DEFAULT_SERVER)?,
+        (None, None) => DEFAULT_SERVER.to_string(),
+    };
+    let server = server.trim().trim_end_matches('/').to_string();
+    if server.is_empty() || server.contains(char::is_whitespace) {
+        return Err(anyhow::anyhow!("Invalid server address: {:?}", server));
+    }
+
+    let example = match (args.example, prompt) {
+        (Some(example), _) => Example::from_str(&example)?,
+        (None, Some(prompt)) => {
+            match prompt
+                .ask(
+                    "Include an example multi-step workflow?",
+                    &["yes", "no"],
+                    "no",
+                )?
+                .as_str()
+            {
+                "yes" => Example::Full,
+                _ => Example::Minimal,
+            }
+        }
+        (None, None) => Example::Minimal,
+    };
+
+    Ok(InitOptions {
+        name,
+        template,
+        package_manager,
+        server,
+        example,
+    })
+}
+
+/// 校验项目名称能作为 npm 包名、Python 包名或 Cargo 包名，且能生成合法的标识符
+pub fn validate_project_name(name: &str, template: TemplateType) -> Result<()> {
+    let invalid = |reason: &str| {
+        Err(anyhow::anyhow!(
+            "Invalid project name {:?}: {}",
+            name,
+            reason
+        ))
+    };
+
+    if name.is_empty() {
+        return invalid("the name is empty");
+    }
+    if name.len() > 214 {
+        return invalid("the name must be at most 214 characters");
+    }
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return invalid("the name must start with a letter");
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || *c == '_'))
+    {
+        return invalid(&format!(
+            "{:?} is not allowed; use letters, digits, '-' and '_'",
+            c
+        ));
+    }
+    match template {
+        TemplateType::TypeScript | TemplateType::NestJS => {
+            if name.chars().any(|c| c.is_ascii_uppercase()) {
+                return invalid("npm package names must be lowercase");
+            }
+        }
+        TemplateType::Python => {
+            if !name.ends_with(|c: char| c.is_ascii_alphanumeric()) {
+                return invalid("Python package names must end with a letter or digit");
+            }
+        }
+        TemplateType::Rust => {
+            // 包名按 snake_case 转换后作为 crate 名，不能与关键字或标准库 crate 重名
+            let crate_name = to_snake_case(name);
+            if RESERVED_CRATE_NAMES.contains(&crate_name.as_str()) {
+                return invalid(&format!("{:?} is a reserved Rust crate name", crate_name));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 生成项目之后执行的步骤，由 `--install` 和 `--git` 开启
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PostInitHooks {
+    /// 运行模板声明的安装命令
+    pub install: bool,
+    /// 初始化 git 仓库并提交生成的文件
+    pub git: bool,
+}
+
+/// 执行失败的步骤
+#[derive(Debug)]
+pub struct HookFailure {
+    /// 步骤名称，如 `install`
+    pub hook: &'static str,
+    pub error: anyhow::Error,
+    /// 在项目目录中手动重试的命令
+    pub retry: String,
+}
+
+/// git 仓库的第一个提交的说明
+pub const INITIAL_COMMIT_MESSAGE: &str = "Initial commit from aether init";
+
+/// 在项目目录中依次执行开启的步骤，命令的输出直接显示在终端中
+///
+/// 某个步骤失败时继续执行之后的步骤，返回所有失败的步骤；已生成的项目文件不会被删除。
+pub async fn run_hooks(
+    hooks: PostInitHooks,
+    project_dir: &Path,
+    manifest: &TemplateManifest,
+) -> Vec<HookFailure> {
+    let mut failures = Vec::new();
+
+    if hooks.install {
+        match &manifest.install {
+            Some(install) => {
+                println!("$ {}", install);
+                if let Err(error) = run_command(project_dir, install).await {
+                    failures.push(HookFailure {
+                        hook: "install",
+                        error,
+                        retry: install.clone(),
+                    });
+                }
+            }
+            None => println!("The template declares no install command, skipping --install"),
+        }
+    }
+
+    if hooks.git {
+        let commit = format!("git commit -m \"{}\"", INITIAL_COMMIT_MESSAGE);
+        let result = async {
+            run_command(project_dir, "git init --quiet").await?;
+            run_command(project_dir, "git add -A").await?;
+            let status = Command::new("git")
+                .args(["commit", "--quiet", "-m", INITIAL_COMMIT_MESSAGE])
+                .current_dir(project_dir)
+                .status()
+                .await
+                .context("Failed to run git")?;
+            if !status.success() {
+                return Err(anyhow::anyhow!(
+                    "`git commit` exited with {} (is git user.name / user.email configured?)",
+                    status
+                ));
+            }
+            Ok(())
+        }
+        .await;
+        if let Err(error) = result {
+            failures.push(HookFailure {
+                hook: "git",
+                error,
+                retry: format!("git init && git add -A && {}", commit),
+            });
+        }
+    }
+
+    failures
+}
+
+/// 在 `dir` 中运行按空白切分的命令，等待其结束
+async fn run_command(dir: &Path, command: &str) -> Result<()> {
+    let mut parts = command.split_whitespace();
+    let program = parts
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Empty command"))?;
+    let status = Command::new(program)
+        .args(parts)
+        .current_dir(dir)
+        .status()
+        .await
+        .with_context(|| format!("Failed to run `{}`", program))?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("`{}` exited with {}", command, status));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates::TemplateValue;
+    use std::io::Cursor;
+
+    fn prompter(answers: &str) -> Prompter<Cursor<Vec<u8>>, Vec<u8>> {
+        Prompter::new(Cursor::new(answers.as_bytes().to_vec()), Vec::new())
+    }
+
+    #[test]
+    fn test_defaults_without_prompt() {
+        let options = resolve_options(
+            InitArgs {
+                name: Some("shop".to_string()),
+                ..Default::default()
+            },
+            None,
+        )
+        .unwrap();
+        assert_eq!(options.template, TemplateType::TypeScript);
+        assert_eq!(options.package_manager, PackageManager::Npm);
+        assert_eq!(options.server, DEFAULT_SERVER);
+        assert_eq!(options.example, Example::Minimal);
+
+        assert!(resolve_options(InitArgs::default(), None).is_err());
+    }
+
+    #[test]
+    fn test_prompts_for_missing_options() {
+        // 名称留空时重新询问；`p` 是 `python` 的唯一前缀
+        let mut prompt = prompter("\nshop\np\nuv\nexample.com:7233\ny\n");
+        let options = resolve_options(InitArgs::default(), Some(&mut prompt)).unwrap();
+        assert_eq!(
+            options,
+            InitOptions {
+                name: "shop".to_string(),
+                template: TemplateType::Python,
+                package_manager: PackageManager::Uv,
+                server: "example.com:7233".to_string(),
+                example: Example::Full,
+            }
+        );
+        let output = String::from_utf8(prompt.output).unwrap();
+        assert!(output.contains("? Package manager (pip/uv) [pip]: "));
+    }
+
+    #[test]
+    fn test_flags_are_not_asked_again() {
+        // 只有示例没有通过参数指定；回车使用默认值
+        let mut prompt = prompter("maybe\n\n");
+        let options = resolve_options(
+            InitArgs {
+                name: Some("shop".to_string()),
+                template: Some("nestjs".to_string()),
+                package_manager: Some("pnpm".to_string()),
+                server: Some("https://aether.internal/".to_string()),
+                example: None,
+            },
+            Some(&mut prompt),
+        )
+        .unwrap();
+        assert_eq!(options.example, Example::Minimal);
+        assert_eq!(options.server_url(), "https://aether.internal");
+        let output = String::from_utf8(prompt.output).unwrap();
+        assert_eq!(output.matches("? Include an example").count(), 2);
+        assert!(!output.contains("? Project name"));
+        assert!(output.contains("Please answer one of: yes, no"));
+    }
+
+    #[test]
+    fn test_package_manager_must_match_template() {
+        let err = resolve_options(
+            InitArgs {
+                name: Some("shop".to_string()),
+                template: Some("python".to_string()),
+                package_manager: Some("npm".to_string()),
+                ..Default::default()
+            },
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Supported: pip, uv"), "{}", err);
+    }
+
+    #[test]
+    fn test_project_name_rules() {
+        let ts = TemplateType::TypeScript;
+        let py = TemplateType::Python;
+        assert!(validate_project_name("order-service", ts).is_ok());
+        assert!(validate_project_name("OrderService", py).is_ok());
+
+        let err = validate_project_name("OrderService", ts).unwrap_err();
+        assert!(err.to_string().contains("must be lowercase"), "{}", err);
+        let err = validate_project_name("my app", ts).unwrap_err();
+        assert!(err.to_string().contains("' ' is not allowed"), "{}", err);
+        assert!(validate_project_name("1st", ts).is_err());
+        assert!(validate_project_name("my.app", ts).is_err());
+        assert!(validate_project_name("shop-", py).is_err());
+        assert!(validate_project_name("", py).is_err());
+
+        let rs = TemplateType::Rust;
+        assert!(validate_project_name("Order-Service", rs).is_ok());
+        let err = validate_project_name("Self", rs).unwrap_err();
+        assert!(
+            err.to_string().contains("reserved Rust crate name"),
+            "{}",
+            err
+        );
+        assert!(validate_project_name("proc-macro", rs).is_err());
+    }
+
+    #[test]
+    fn test_rust_template_options() {
+        let options = resolve_options(
+            InitArgs {
+                name: Some("order-service".to_string()),
+                template: Some("rust".to_string()),
+                server: Some("https://aether.example.com:8080".to_string()),
+                ..Default::default()
+            },
+            None,
+        )
+        .unwrap();
+        assert_eq!(options.package_manager, PackageManager::Cargo);
+        assert_eq!(options.grpc_url(), "https://aether.example.com:7234");
+
+        let vars = options.variables();
+        let get = |name| vars.get(name).cloned().unwrap();
+        assert_eq!(get("crate_name"), "order_service".into());
+        assert_eq!(get("run_command"), "cargo run".into());
+    }
+
+    #[tokio::test]
+    async fn test_failed_hooks_are_reported_and_keep_the_project() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("README.md"), "# demo").unwrap();
+        let manifest = TemplateManifest {
+            install: Some("aether-missing-package-manager install".to_string()),
+            next_steps: vec![],
+        };
+
+        let failures = run_hooks(
+            PostInitHooks {
+                install: true,
+                git: false,
+            },
+            dir.path(),
+            &manifest,
+        )
+        .await;
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].hook, "install");
+        assert_eq!(failures[0].retry, "aether-missing-package-manager install");
+        assert!(failures[0]
+            .error
+            .to_string()
+            .contains("Failed to run `aether-missing-package-manager`"));
+        assert!(dir.path().join("README.md").is_file());
+
+        // 没有声明安装命令时跳过安装
+        let failures = run_hooks(
+            PostInitHooks {
+                install: true,
+                git: false,
+            },
+            dir.path(),
+            &TemplateManifest::default(),
+        )
+        .await;
+        assert!(failures.is_empty());
+    }
+
+    #[test]
+    fn test_variables() {
+        let options = InitOptions {
+            name: "shop".to_string(),
+            template: TemplateType::NestJS,
+            package_manager: PackageManager::Yarn,
+            server: "10.0.0.5:7233".to_string(),
+            example: Example::Full,
+        };
+        let vars = options.variables();
+        let get = |name| vars.get(name).cloned().unwrap();
+        assert_eq!(get("server_url"), "http://10.0.0.5:7233".into());
+        assert_eq!(get("server_address"), "10.0.0.5:7233".into());
+        assert_eq!(get("grpc_url"), "http://10.0.0.5:7234".into());
+        assert_eq!(get("install_command"), "yarn install".into());
+        assert_eq!(get("run_command"), "yarn start:dev".into());
+        assert_eq!(get("example_full"), TemplateValue::Bool(true));
+        assert_eq!(get("workflow_name"), "shop".into());
+    }
+}
@@ -1,2 +1,6 @@
 // CLI library module
+pub mod config_gen;
+pub mod definition_file;
+pub mod doctor;
+pub mod init;
 pub mod templates;
@@ -1,16 +1,39 @@
-use aetherframework_cli::templates::{render_template_dir, TemplateType, TemplateVariables};
+use aetherframework_cli::config_gen::{self, ConfigFormat, ConfigSource};
+use aetherframework_cli::definition_file;
+use aetherframework_cli::doctor;
+use aetherframework_cli::init::{resolve_options, run_hooks, InitArgs, PostInitHooks, Prompter};
+use aetherframework_cli::templates::render_template_dir;
+use aetherframework_kernel::api::auth::AuthConfig;
+use aetherframework_kernel::client::AetherClient;
+use aetherframework_kernel::config::{PersistenceSection, ServerConfig, ServerOverrides};
+use aetherframework_kernel::definition::WorkflowDefinition;
+use aetherframework_kernel::grpc_auth::GrpcAuthConfig;
+use aetherframework_kernel::persistence::blob::BlobStore;
 use aetherframework_kernel::persistence::l0_memory::L0MemoryStore;
 use aetherframework_kernel::persistence::l1_snapshot::L1SnapshotStore;
 use aetherframework_kernel::persistence::l2_state_action_log::L2StateActionStore;
-use aetherframework_kernel::persistence::{Persistence, PersistenceLevel};
-use aetherframework_kernel::scheduler::Scheduler;
-use aetherframework_kernel::server;
-use aetherframework_kernel::state_machine::{Workflow, WorkflowState};
+#[cfg(feature = "sqlite")]
+use aetherframework_kernel::persistence::sqlite::SqliteStore;
+use aetherframework_kernel::persistence::{
+    ListOptions, OrderBy, Persistence, PersistenceLevel, PurgeFilter, StateKind,
+};
+use aetherframework_kernel::proto;
+use aetherframework_kernel::proto::admin_service_client::AdminServiceClient;
+use aetherframework_kernel::proto::client_service_client::ClientServiceClient;
+use aetherframework_kernel::schedule::{OverlapPolicy, Schedule};
+use aetherframework_kernel::signal::Signal;
+use aetherframework_kernel::state_machine::{Priority, Workflow, WorkflowState};
+use aetherframework_kernel::step_cache::CachedResult;
+use aetherframework_kernel::step_timeout::StepDeadline;
+use aetherframework_kernel::timer::Timer;
+use aetherframework_kernel::tracker::{WorkflowExecution, WorkflowTracker};
+use aetherframework_kernel::AetherKernel;
 use anyhow::Context;
 use clap::{Parser, Subcommand};
-use std::path::PathBuf;
-use std::str::FromStr;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use tracing_subscriber::EnvFilter;
 
 /// Wrapper enum for persistence backends (uses Arc for shared state)
 #[derive(Clone)]
@@ -18,6 +41,10 @@ enum PersistenceBackend {
     L0Memory(Arc<L0MemoryStore>),
     L1Snapshot(Arc<L1SnapshotStore>),
     L2StateActionLog(Arc<L2StateActionStore>),
+    /// 大负载按引用保存到 blob 目录的文件存储
+    Blobs(Arc<BlobStore<PersistenceBackend>>),
+    #[cfg(feature = "sqlite")]
+    Sqlite(Arc<SqliteStore>),
 }
 
 #[async_trait::async_trait]
@@ -29,6 +56,54 @@ impl Persistence for PersistenceBackend {
             PersistenceBackend::L2StateActionLog(store) => {
                 store.as_ref().save_workflow(workflow).await
             }
+            PersistenceBackend::Blobs(store) => store.as_ref().save_workflow(workflow).await,
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => store.as_ref().save_workflow(workflow).await,
+        }
+    }
+
+    async fn save_workflow_if_absent(&self, workflow: &Workflow) -> anyhow::Result<bool> {
+        match self {
+            PersistenceBackend::L0Memory(store) => {
+                store.as_ref().save_workflow_if_absent(workflow).await
+            }
+            PersistenceBackend::L1Snapshot(store) => {
+                store.as_ref().save_workflow_if_absent(workflow).await
+            }
+            PersistenceBackend::L2StateActionLog(store) => {
+                store.as_ref().save_workflow_if_absent(workflow).await
+            }
+            PersistenceBackend::Blobs(store) => {
+                store.as_ref().save_workflow_if_absent(workflow).await
+            }
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => {
+                store.as_ref().save_workflow_if_absent(workflow).await
+            }
+        }
+    }
+
+    async fn save_workflows(&self, workflows: &[Workflow]) -> anyhow::Result<Vec<bool>> {
+        match self {
+            PersistenceBackend::L0Memory(store) => store.as_ref().save_workflows(workflows).await,
+            PersistenceBackend::L1Snapshot(store) => store.as_ref().save_workflows(workflows).await,
+            PersistenceBackend::L2StateActionLog(store) => {
+                store.as_ref().save_workflows(workflows).await
+            }
+            PersistenceBackend::Blobs(store) => store.as_ref().save_workflows(workflows).await,
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => store.as_ref().save_workflows(workflows).await,
+        }
+    }
+
+    fn atomic_batches(&self) -> bool {
+        match self {
+            PersistenceBackend::L0Memory(store) => store.atomic_batches(),
+            PersistenceBackend::L1Snapshot(store) => store.atomic_batches(),
+            PersistenceBackend::L2StateActionLog(store) => store.atomic_batches(),
+            PersistenceBackend::Blobs(store) => store.atomic_batches(),
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => store.atomic_batches(),
         }
     }
 
@@ -37,6 +112,9 @@ impl Persistence for PersistenceBackend {
             PersistenceBackend::L0Memory(store) => store.as_ref().get_workflow(id).await,
             PersistenceBackend::L1Snapshot(store) => store.as_ref().get_workflow(id).await,
             PersistenceBackend::L2StateActionLog(store) => store.as_ref().get_workflow(id).await,
+            PersistenceBackend::Blobs(store) => store.as_ref().get_workflow(id).await,
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => store.as_ref().get_workflow(id).await,
         }
     }
 
@@ -51,6 +129,39 @@ impl Persistence for PersistenceBackend {
             PersistenceBackend::L2StateActionLog(store) => {
                 store.as_ref().list_workflows(workflow_type).await
             }
+            PersistenceBackend::Blobs(store) => store.as_ref().list_workflows(workflow_type).await,
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => store.as_ref().list_workflows(workflow_type).await,
+        }
+    }
+
+    async fn list_workflows_paged(&self, options: &ListOptions) -> anyhow::Result<Vec<Workflow>> {
+        match self {
+            PersistenceBackend::L0Memory(store) => {
+                store.as_ref().list_workflows_paged(options).await
+            }
+            PersistenceBackend::L1Snapshot(store) => {
+                store.as_ref().list_workflows_paged(options).await
+            }
+            PersistenceBackend::L2StateActionLog(store) => {
+                store.as_ref().list_workflows_paged(options).await
+            }
+            PersistenceBackend::Blobs(store) => store.as_ref().list_workflows_paged(options).await,
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => store.as_ref().list_workflows_paged(options).await,
+        }
+    }
+
+    async fn count_workflows(&self, options: &ListOptions) -> anyhow::Result<usize> {
+        match self {
+            PersistenceBackend::L0Memory(store) => store.as_ref().count_workflows(options).await,
+            PersistenceBackend::L1Snapshot(store) => store.as_ref().count_workflows(options).await,
+            PersistenceBackend::L2StateActionLog(store) => {
+                store.as_ref().count_workflows(options).await
+            }
+            PersistenceBackend::Blobs(store) => store.as_ref().count_workflows(options).await,
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => store.as_ref().count_workflows(options).await,
         }
     }
 
@@ -65,6 +176,49 @@ impl Persistence for PersistenceBackend {
             PersistenceBackend::L2StateActionLog(store) => {
                 store.as_ref().update_workflow_state(id, state).await
             }
+            PersistenceBackend::Blobs(store) => {
+                store.as_ref().update_workflow_state(id, state).await
+            }
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => {
+                store.as_ref().update_workflow_state(id, state).await
+            }
+        }
+    }
+
+    async fn record_step_attempt(&self, workflow_id: &str, step_name: &str) -> anyhow::Result<u32> {
+        match self {
+            PersistenceBackend::L0Memory(store) => {
+                store
+                    .as_ref()
+                    .record_step_attempt(workflow_id, step_name)
+                    .await
+            }
+            PersistenceBackend::L1Snapshot(store) => {
+                store
+                    .as_ref()
+                    .record_step_attempt(workflow_id, step_name)
+                    .await
+            }
+            PersistenceBackend::L2StateActionLog(store) => {
+                store
+                    .as_ref()
+                    .record_step_attempt(workflow_id, step_name)
+                    .await
+            }
+            PersistenceBackend::Blobs(store) => {
+                store
+                    .as_ref()
+                    .record_step_attempt(workflow_id, step_name)
+                    .await
+            }
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => {
+                store
+                    .as_ref()
+                    .record_step_attempt(workflow_id, step_name)
+                    .await
+            }
         }
     }
 
@@ -93,336 +247,1979 @@ impl Persistence for PersistenceBackend {
                     .save_step_result(workflow_id, step_name, result)
                     .await
             }
+            PersistenceBackend::Blobs(store) => {
+                store
+                    .as_ref()
+                    .save_step_result(workflow_id, step_name, result)
+                    .await
+            }
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => {
+                store
+                    .as_ref()
+                    .save_step_result(workflow_id, step_name, result)
+                    .await
+            }
+        }
+    }
+
+    async fn get_step_result(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        match self {
+            PersistenceBackend::L0Memory(store) => {
+                store.as_ref().get_step_result(workflow_id, step_name).await
+            }
+            PersistenceBackend::L1Snapshot(store) => {
+                store.as_ref().get_step_result(workflow_id, step_name).await
+            }
+            PersistenceBackend::L2StateActionLog(store) => {
+                store.as_ref().get_step_result(workflow_id, step_name).await
+            }
+            PersistenceBackend::Blobs(store) => {
+                store.as_ref().get_step_result(workflow_id, step_name).await
+            }
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => {
+                store.as_ref().get_step_result(workflow_id, step_name).await
+            }
+        }
+    }
+
+    async fn append_signal(&self, signal: &Signal) -> anyhow::Result<()> {
+        match self {
+            PersistenceBackend::L0Memory(store) => store.as_ref().append_signal(signal).await,
+            PersistenceBackend::L1Snapshot(store) => store.as_ref().append_signal(signal).await,
+            PersistenceBackend::L2StateActionLog(store) => {
+                store.as_ref().append_signal(signal).await
+            }
+            PersistenceBackend::Blobs(store) => store.as_ref().append_signal(signal).await,
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => store.as_ref().append_signal(signal).await,
+        }
+    }
+
+    async fn get_signals(&self, workflow_id: &str) -> anyhow::Result<Vec<Signal>> {
+        match self {
+            PersistenceBackend::L0Memory(store) => store.as_ref().get_signals(workflow_id).await,
+            PersistenceBackend::L1Snapshot(store) => store.as_ref().get_signals(workflow_id).await,
+            PersistenceBackend::L2StateActionLog(store) => {
+                store.as_ref().get_signals(workflow_id).await
+            }
+            PersistenceBackend::Blobs(store) => store.as_ref().get_signals(workflow_id).await,
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => store.as_ref().get_signals(workflow_id).await,
+        }
+    }
+
+    async fn save_timer(&self, timer: &Timer) -> anyhow::Result<()> {
+        match self {
+            PersistenceBackend::L0Memory(store) => store.as_ref().save_timer(timer).await,
+            PersistenceBackend::L1Snapshot(store) => store.as_ref().save_timer(timer).await,
+            PersistenceBackend::L2StateActionLog(store) => store.as_ref().save_timer(timer).await,
+            PersistenceBackend::Blobs(store) => store.as_ref().save_timer(timer).await,
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => store.as_ref().save_timer(timer).await,
+        }
+    }
+
+    async fn list_timers(&self) -> anyhow::Result<Vec<Timer>> {
+        match self {
+            PersistenceBackend::L0Memory(store) => store.as_ref().list_timers().await,
+            PersistenceBackend::L1Snapshot(store) => store.as_ref().list_timers().await,
+            PersistenceBackend::L2StateActionLog(store) => store.as_ref().list_timers().await,
+            PersistenceBackend::Blobs(store) => store.as_ref().list_timers().await,
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => store.as_ref().list_timers().await,
+        }
+    }
+
+    async fn delete_timer(&self, workflow_id: &str, step_name: &str) -> anyhow::Result<bool> {
+        match self {
+            PersistenceBackend::L0Memory(store) => {
+                store.as_ref().delete_timer(workflow_id, step_name).await
+            }
+            PersistenceBackend::L1Snapshot(store) => {
+                store.as_ref().delete_timer(workflow_id, step_name).await
+            }
+            PersistenceBackend::L2StateActionLog(store) => {
+                store.as_ref().delete_timer(workflow_id, step_name).await
+            }
+            PersistenceBackend::Blobs(store) => {
+                store.as_ref().delete_timer(workflow_id, step_name).await
+            }
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => {
+                store.as_ref().delete_timer(workflow_id, step_name).await
+            }
+        }
+    }
+
+    async fn save_step_deadline(&self, deadline: &StepDeadline) -> anyhow::Result<()> {
+        match self {
+            PersistenceBackend::L0Memory(store) => {
+                store.as_ref().save_step_deadline(deadline).await
+            }
+            PersistenceBackend::L1Snapshot(store) => {
+                store.as_ref().save_step_deadline(deadline).await
+            }
+            PersistenceBackend::L2StateActionLog(store) => {
+                store.as_ref().save_step_deadline(deadline).await
+            }
+            PersistenceBackend::Blobs(store) => store.as_ref().save_step_deadline(deadline).await,
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => store.as_ref().save_step_deadline(deadline).await,
+        }
+    }
+
+    async fn list_step_deadlines(&self) -> anyhow::Result<Vec<StepDeadline>> {
+        match self {
+            PersistenceBackend::L0Memory(store) => store.as_ref().list_step_deadlines().await,
+            PersistenceBackend::L1Snapshot(store) => store.as_ref().list_step_deadlines().await,
+            PersistenceBackend::L2StateActionLog(store) => {
+                store.as_ref().list_step_deadlines().await
+            }
+            PersistenceBackend::Blobs(store) => store.as_ref().list_step_deadlines().await,
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => store.as_ref().list_step_deadlines().await,
+        }
+    }
+
+    async fn delete_step_deadline(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+    ) -> anyhow::Result<bool> {
+        match self {
+            PersistenceBackend::L0Memory(store) => {
+                store
+                    .as_ref()
+                    .delete_step_deadline(workflow_id, step_name)
+                    .await
+            }
+            PersistenceBackend::L1Snapshot(store) => {
+                store
+                    .as_ref()
+                    .delete_step_deadline(workflow_id, step_name)
+                    .await
+            }
+            PersistenceBackend::L2StateActionLog(store) => {
+                store
+                    .as_ref()
+                    .delete_step_deadline(workflow_id, step_name)
+                    .await
+            }
+            PersistenceBackend::Blobs(store) => {
+                store
+                    .as_ref()
+                    .delete_step_deadline(workflow_id, step_name)
+                    .await
+            }
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => {
+                store
+                    .as_ref()
+                    .delete_step_deadline(workflow_id, step_name)
+                    .await
+            }
+        }
+    }
+
+    async fn get_cached_result(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        match self {
+            PersistenceBackend::L0Memory(store) => store.as_ref().get_cached_result(key).await,
+            PersistenceBackend::L1Snapshot(store) => store.as_ref().get_cached_result(key).await,
+            PersistenceBackend::L2StateActionLog(store) => {
+                store.as_ref().get_cached_result(key).await
+            }
+            PersistenceBackend::Blobs(store) => store.as_ref().get_cached_result(key).await,
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => store.as_ref().get_cached_result(key).await,
+        }
+    }
+
+    async fn put_cached_result(
+        &self,
+        entry: &CachedResult,
+        max_entries: usize,
+    ) -> anyhow::Result<()> {
+        match self {
+            PersistenceBackend::L0Memory(store) => {
+                store.as_ref().put_cached_result(entry, max_entries).await
+            }
+            PersistenceBackend::L1Snapshot(store) => {
+                store.as_ref().put_cached_result(entry, max_entries).await
+            }
+            PersistenceBackend::L2StateActionLog(store) => {
+                store.as_ref().put_cached_result(entry, max_entries).await
+            }
+            PersistenceBackend::Blobs(store) => {
+                store.as_ref().put_cached_result(entry, max_entries).await
+            }
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => {
+                store.as_ref().put_cached_result(entry, max_entries).await
+            }
+        }
+    }
+
+    async fn save_schedule(&self, schedule: &Schedule) -> anyhow::Result<()> {
+        match self {
+            PersistenceBackend::L0Memory(store) => store.as_ref().save_schedule(schedule).await,
+            PersistenceBackend::L1Snapshot(store) => store.as_ref().save_schedule(schedule).await,
+            PersistenceBackend::L2StateActionLog(store) => {
+                store.as_ref().save_schedule(schedule).await
+            }
+            PersistenceBackend::Blobs(store) => store.as_ref().save_schedule(schedule).await,
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => store.as_ref().save_schedule(schedule).await,
+        }
+    }
+
+    async fn update_schedule(&self, schedule: &Schedule) -> anyhow::Result<bool> {
+        match self {
+            PersistenceBackend::L0Memory(store) => store.as_ref().update_schedule(schedule).await,
+            PersistenceBackend::L1Snapshot(store) => store.as_ref().update_schedule(schedule).await,
+            PersistenceBackend::L2StateActionLog(store) => {
+                store.as_ref().update_schedule(schedule).await
+            }
+            PersistenceBackend::Blobs(store) => store.as_ref().update_schedule(schedule).await,
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => store.as_ref().update_schedule(schedule).await,
+        }
+    }
+
+    async fn list_schedules(&self) -> anyhow::Result<Vec<Schedule>> {
+        match self {
+            PersistenceBackend::L0Memory(store) => store.as_ref().list_schedules().await,
+            PersistenceBackend::L1Snapshot(store) => store.as_ref().list_schedules().await,
+            PersistenceBackend::L2StateActionLog(store) => store.as_ref().list_schedules().await,
+            PersistenceBackend::Blobs(store) => store.as_ref().list_schedules().await,
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => store.as_ref().list_schedules().await,
+        }
+    }
+
+    async fn delete_schedule(&self, id: &str) -> anyhow::Result<bool> {
+        match self {
+            PersistenceBackend::L0Memory(store) => store.as_ref().delete_schedule(id).await,
+            PersistenceBackend::L1Snapshot(store) => store.as_ref().delete_schedule(id).await,
+            PersistenceBackend::L2StateActionLog(store) => store.as_ref().delete_schedule(id).await,
+            PersistenceBackend::Blobs(store) => store.as_ref().delete_schedule(id).await,
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => store.as_ref().delete_schedule(id).await,
+        }
+    }
+
+    async fn save_definition(&self, definition: &WorkflowDefinition) -> anyhow::Result<()> {
+        match self {
+            PersistenceBackend::L0Memory(store) => store.as_ref().save_definition(definition).await,
+            PersistenceBackend::L1Snapshot(store) => {
+                store.as_ref().save_definition(definition).await
+            }
+            PersistenceBackend::L2StateActionLog(store) => {
+                store.as_ref().save_definition(definition).await
+            }
+            PersistenceBackend::Blobs(store) => store.as_ref().save_definition(definition).await,
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => store.as_ref().save_definition(definition).await,
+        }
+    }
+
+    async fn list_definitions(&self) -> anyhow::Result<Vec<WorkflowDefinition>> {
+        match self {
+            PersistenceBackend::L0Memory(store) => store.as_ref().list_definitions().await,
+            PersistenceBackend::L1Snapshot(store) => store.as_ref().list_definitions().await,
+            PersistenceBackend::L2StateActionLog(store) => store.as_ref().list_definitions().await,
+            PersistenceBackend::Blobs(store) => store.as_ref().list_definitions().await,
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => store.as_ref().list_definitions().await,
+        }
+    }
+
+    async fn delete_workflow(&self, id: &str) -> anyhow::Result<bool> {
+        match self {
+            PersistenceBackend::L0Memory(store) => store.as_ref().delete_workflow(id).await,
+            PersistenceBackend::L1Snapshot(store) => store.as_ref().delete_workflow(id).await,
+            PersistenceBackend::L2StateActionLog(store) => store.as_ref().delete_workflow(id).await,
+            PersistenceBackend::Blobs(store) => store.as_ref().delete_workflow(id).await,
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => store.as_ref().delete_workflow(id).await,
+        }
+    }
+
+    async fn purge_workflows(&self, filter: &PurgeFilter) -> anyhow::Result<Vec<String>> {
+        match self {
+            PersistenceBackend::L0Memory(store) => store.as_ref().purge_workflows(filter).await,
+            PersistenceBackend::L1Snapshot(store) => store.as_ref().purge_workflows(filter).await,
+            PersistenceBackend::L2StateActionLog(store) => {
+                store.as_ref().purge_workflows(filter).await
+            }
+            PersistenceBackend::Blobs(store) => store.as_ref().purge_workflows(filter).await,
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => store.as_ref().purge_workflows(filter).await,
+        }
+    }
+
+    async fn save_execution(&self, execution: &WorkflowExecution) -> anyhow::Result<()> {
+        match self {
+            PersistenceBackend::L0Memory(store) => store.as_ref().save_execution(execution).await,
+            PersistenceBackend::L1Snapshot(store) => store.as_ref().save_execution(execution).await,
+            PersistenceBackend::L2StateActionLog(store) => {
+                store.as_ref().save_execution(execution).await
+            }
+            PersistenceBackend::Blobs(store) => store.as_ref().save_execution(execution).await,
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => store.as_ref().save_execution(execution).await,
+        }
+    }
+
+    async fn load_executions(&self) -> anyhow::Result<Vec<WorkflowExecution>> {
+        match self {
+            PersistenceBackend::L0Memory(store) => store.as_ref().load_executions().await,
+            PersistenceBackend::L1Snapshot(store) => store.as_ref().load_executions().await,
+            PersistenceBackend::L2StateActionLog(store) => store.as_ref().load_executions().await,
+            PersistenceBackend::Blobs(store) => store.as_ref().load_executions().await,
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => store.as_ref().load_executions().await,
+        }
+    }
+
+    async fn flush(&self) -> anyhow::Result<()> {
+        match self {
+            PersistenceBackend::L0Memory(store) => Persistence::flush(store.as_ref()).await,
+            PersistenceBackend::L1Snapshot(store) => Persistence::flush(store.as_ref()).await,
+            PersistenceBackend::L2StateActionLog(store) => Persistence::flush(store.as_ref()).await,
+            PersistenceBackend::Blobs(store) => Persistence::flush(store.as_ref()).await,
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => Persistence::flush(store.as_ref()).await,
         }
     }
 
-    async fn get_step_result(
-        &self,
-        workflow_id: &str,
-        step_name: &str,
-    ) -> anyhow::Result<Option<Vec<u8>>> {
-        match self {
-            PersistenceBackend::L0Memory(store) => {
-                store.as_ref().get_step_result(workflow_id, step_name).await
+    fn backend_name(&self) -> &'static str {
+        match self {
+            PersistenceBackend::L0Memory(store) => store.backend_name(),
+            PersistenceBackend::L1Snapshot(store) => store.backend_name(),
+            PersistenceBackend::L2StateActionLog(store) => store.backend_name(),
+            PersistenceBackend::Blobs(store) => store.backend_name(),
+            #[cfg(feature = "sqlite")]
+            PersistenceBackend::Sqlite(store) => store.backend_name(),
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+#[command(name = "aether")]
+#[command(about = "Aether workflow engine CLI")]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Start the Aether server
+    Serve {
+        /// Configuration file (TOML)
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+        /// Listen host (default: 0.0.0.0)
+        #[arg(long)]
+        host: Option<String>,
+        /// Data directory for snapshot / state-action-log / sqlite persistence (default: ./data/aether.db)
+        #[arg(long)]
+        db: Option<PathBuf>,
+        /// API port (default: 7233)
+        #[arg(long)]
+        port: Option<u16>,
+        /// gRPC port (default: 7234)
+        #[arg(long)]
+        grpc_port: Option<u16>,
+        /// Enable Dashboard (default: true)
+        #[arg(long)]
+        dashboard: Option<bool>,
+        /// Dashboard WebSocket port (default: 7235)
+        #[arg(long)]
+        dashboard_port: Option<u16>,
+        /// Persistence mode (memory|snapshot|state-action-log|sqlite)
+        #[arg(long)]
+        persistence: Option<String>,
+        /// Task lease timeout in seconds (default: 60)
+        #[arg(long)]
+        task_timeout: Option<u64>,
+        /// Evict workers that stop heartbeating for this many seconds (default: 90)
+        #[arg(long)]
+        worker_timeout: Option<u64>,
+        /// Seconds to wait for in-flight steps when shutting down (default: 30)
+        #[arg(long)]
+        shutdown_grace: Option<u64>,
+        /// Milliseconds an idle task stream waits before polling again (default: worker timeout / 3)
+        #[arg(long)]
+        poll_interval_ms: Option<u64>,
+        /// Events buffered per subscriber before slow subscribers miss events (default: 1000)
+        #[arg(long)]
+        broadcast_capacity: Option<usize>,
+        /// Purge finished workflows older than this many hours (enables retention)
+        #[arg(long)]
+        retention_hours: Option<u64>,
+        /// Require this key in the X-Api-Key header of REST requests (repeatable)
+        #[arg(long = "api-key")]
+        api_keys: Vec<String>,
+        /// Read accepted API keys from a file, one per line (`#` starts a comment)
+        #[arg(long)]
+        api_key_file: Option<PathBuf>,
+        /// Require this bearer token in the `authorization` metadata of gRPC calls (repeatable)
+        #[arg(long = "grpc-auth-token")]
+        grpc_auth_tokens: Vec<String>,
+        /// Accept this bearer token for gRPC worker registration only (repeatable)
+        #[arg(long = "grpc-bootstrap-token")]
+        grpc_bootstrap_tokens: Vec<String>,
+        /// Allow cross-origin REST requests from this origin, `*` for any (repeatable; default: any localhost port)
+        #[arg(long = "cors-origin")]
+        cors_origins: Vec<String>,
+        /// Log output format: pretty | json
+        #[arg(long, default_value = "pretty")]
+        log_format: String,
+        /// Log filter such as `debug` or `aetherframework_kernel=debug` (default: RUST_LOG, then info)
+        #[arg(long)]
+        log_level: Option<String>,
+    },
+    /// Inspect server configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Initialize a new Aether project (asks for omitted options when run in a terminal)
+    Init {
+        /// Project name
+        name: Option<String>,
+        /// Output directory
+        #[arg(short, long, default_value = ".")]
+        output: PathBuf,
+        /// Project template: ts | nestjs | python | rust (default: ts)
+        #[arg(short, long)]
+        template: Option<String>,
+        /// Package manager: npm | pnpm | yarn | pip | uv | cargo (default: npm, pip for python, cargo for rust)
+        #[arg(long)]
+        package_manager: Option<String>,
+        /// Aether server address the project connects to (default: localhost:7233)
+        #[arg(long)]
+        server: Option<String>,
+        /// Example workflow: full (multi-step) | minimal (default: minimal)
+        #[arg(long)]
+        example: Option<String>,
+        /// Install dependencies with the template's install command after generating the project
+        #[arg(long)]
+        install: bool,
+        /// Initialize a git repository with an initial commit after generating the project
+        #[arg(long)]
+        git: bool,
+    },
+    /// Generate configuration
+    Gen {
+        #[command(subcommand)]
+        action: GenAction,
+    },
+    /// List workflows
+    Workflow {
+        #[command(subcommand)]
+        action: WorkflowAction,
+    },
+    /// Show workflow status
+    Status {
+        workflow_id: String,
+        /// Aether gRPC server address (default: localhost:7234)
+        #[arg(short = 's', long, default_value = "localhost:7234")]
+        server: String,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+        /// Re-poll every second until the workflow finishes
+        #[arg(short, long)]
+        watch: bool,
+    },
+    /// Cancel a workflow (exits with code 2 if it has already finished)
+    Cancel {
+        workflow_id: String,
+        /// Aether gRPC server address (default: localhost:7234)
+        #[arg(short = 's', long, default_value = "localhost:7234")]
+        server: String,
+        /// Wait until the workflow is reported as cancelled
+        #[arg(short, long)]
+        wait: bool,
+        /// Seconds to wait with --wait before giving up
+        #[arg(long, default_value_t = 30)]
+        timeout: u64,
+    },
+    /// Force a workflow into the TERMINATED state, recording why and by whom
+    Terminate {
+        workflow_id: String,
+        /// Why the workflow is being terminated
+        #[arg(short, long)]
+        reason: String,
+        /// Operator requesting the termination
+        #[arg(long)]
+        requested_by: Option<String>,
+        /// Aether gRPC server address (default: localhost:7234)
+        #[arg(short = 's', long, default_value = "localhost:7234")]
+        server: String,
+    },
+    /// Delete a workflow together with its step results and history
+    Delete {
+        workflow_id: String,
+        /// Delete even if the workflow is still pending or running
+        #[arg(long)]
+        force: bool,
+        /// Aether gRPC server address (default: localhost:7234)
+        #[arg(short = 's', long, default_value = "localhost:7234")]
+        server: String,
+    },
+    /// Delete finished workflows in bulk
+    Purge {
+        /// Only purge workflows in this state: completed | failed | cancelled | terminated (repeatable)
+        #[arg(long)]
+        state: Vec<String>,
+        /// Only purge workflows that finished more than this many seconds ago
+        #[arg(long)]
+        older_than: Option<u64>,
+        /// Only purge workflows of this type
+        #[arg(short, long)]
+        r#type: Option<String>,
+        /// Aether gRPC server address (default: localhost:7234)
+        #[arg(short = 's', long, default_value = "localhost:7234")]
+        server: String,
+    },
+    /// Manage cron schedules that start workflows
+    Schedule {
+        #[command(subcommand)]
+        action: ScheduleAction,
+    },
+    /// Diagnose a running Aether server
+    Doctor {
+        /// Aether gRPC server address (default: localhost:7234)
+        #[arg(short = 's', long, default_value = "localhost:7234")]
+        server: String,
+        /// Seconds without progress before a workflow is reported as stuck
+        #[arg(long, default_value = "300")]
+        stuck_after: u64,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum GenAction {
+    /// Generate aether.config.ts from registered services
+    Config {
+        /// Configuration source: local | remote | both
+        #[arg(short = 'c', long, default_value = "both")]
+        config_source: String,
+        /// Aether gRPC server address (default: localhost:7234)
+        #[arg(short = 's', long, default_value = "localhost:7234")]
+        server: String,
+        /// Output file path (default: ./aether.config.ts or ./aether.config.json)
+        #[arg(short = 'o', long)]
+        output: Option<PathBuf>,
+        /// Output format: ts | json
+        #[arg(long, default_value = "ts")]
+        format: String,
+        /// Overwrite existing file
+        #[arg(long)]
+        overwrite: bool,
+        /// Preview without writing
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigAction {
+    /// Parse the configuration and print the effective merged result
+    Validate {
+        /// Configuration file (TOML)
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ScheduleAction {
+    /// List schedules with their next fire time
+    List {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+        /// Aether gRPC server address (default: localhost:7234)
+        #[arg(short = 's', long, default_value = "localhost:7234")]
+        server: String,
+    },
+    /// Start a workflow type on a cron schedule (UTC)
+    Create {
+        /// Cron expression: "min hour day month weekday", optionally with leading seconds
+        cron: String,
+        /// Workflow type
+        workflow_type: String,
+        /// JSON input passed to every started workflow
+        #[arg(short, long)]
+        input: Option<String>,
+        /// What to do when the previous run is still active (skip|allow|cancel_previous)
+        #[arg(long, default_value = "skip")]
+        overlap: OverlapPolicy,
+        /// Aether gRPC server address (default: localhost:7234)
+        #[arg(short = 's', long, default_value = "localhost:7234")]
+        server: String,
+    },
+    /// Delete a schedule; workflows it already started keep running
+    Delete {
+        schedule_id: String,
+        /// Aether gRPC server address (default: localhost:7234)
+        #[arg(short = 's', long, default_value = "localhost:7234")]
+        server: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum WorkflowAction {
+    /// Start a workflow and print its id
+    Start {
+        /// Workflow type
+        workflow_type: String,
+        /// JSON input
+        #[arg(short, long, conflicts_with_all = ["input_file", "input_raw"])]
+        input: Option<String>,
+        /// Read JSON input from a file
+        #[arg(long, conflicts_with = "input_raw")]
+        input_file: Option<PathBuf>,
+        /// Send the input bytes as-is without JSON validation
+        #[arg(long)]
+        input_raw: Option<String>,
+        /// Dispatch priority (low|normal|high)
+        #[arg(short, long, default_value = "normal")]
+        priority: Priority,
+        /// Skip validating step input and output against resource schemas (emergencies only)
+        #[arg(long)]
+        skip_schema_validation: bool,
+        /// Start a type with no registered workflow definition (run by an SDK worker)
+        #[arg(long)]
+        dynamic: bool,
+        /// Metadata kept with the workflow, as KEY=VALUE (repeatable)
+        #[arg(long, value_parser = parse_memo_entry)]
+        memo: Vec<(String, String)>,
+        /// Print step progress until the workflow finishes (exits non-zero unless it completes)
+        #[arg(short, long)]
+        follow: bool,
+        /// Aether gRPC server address (default: localhost:7234)
+        #[arg(short = 's', long, default_value = "localhost:7234")]
+        server: String,
+    },
+    List {
+        /// Workflow type filter
+        #[arg(short, long)]
+        r#type: Option<String>,
+        /// State filter (pending|running|completed|failed|cancelled|terminated)
+        #[arg(short, long)]
+        state: Option<String>,
+        /// Only workflows started at or after this time (RFC 3339)
+        #[arg(long)]
+        created_after: Option<String>,
+        /// Only workflows started before this time (RFC 3339)
+        #[arg(long)]
+        created_before: Option<String>,
+        /// Only workflows whose memo has KEY=VALUE (repeatable; all must match)
+        #[arg(long, value_parser = parse_memo_entry)]
+        memo: Vec<(String, String)>,
+        /// Maximum number of workflows per page (0 = server limit)
+        #[arg(short, long, default_value_t = 20)]
+        limit: u32,
+        /// Number of workflows to skip
+        #[arg(long, default_value_t = 0, conflicts_with = "page_token")]
+        offset: u32,
+        /// Page token printed by the previous page
+        #[arg(long)]
+        page_token: Option<String>,
+        /// Sort order by start time (started_at_asc|started_at_desc)
+        #[arg(long, default_value = "started_at_asc")]
+        order: String,
+        /// Output format: table | json
+        #[arg(short, long, default_value = "table")]
+        output: String,
+        /// Aether gRPC server address (default: localhost:7234)
+        #[arg(long, default_value = "localhost:7234")]
+        server: String,
+    },
+    /// Export a workflow's state transitions, steps and events
+    Export {
+        /// Workflow ID
+        workflow_id: String,
+        /// Write the export to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Export format: json | jsonl
+        #[arg(short, long, default_value = "json")]
+        format: String,
+        /// Truncate payloads larger than this many bytes (noted in the export)
+        #[arg(long)]
+        max_payload_bytes: Option<u64>,
+        /// Aether gRPC server address (default: localhost:7234)
+        #[arg(short = 's', long, default_value = "localhost:7234")]
+        server: String,
+    },
+    /// Register a workflow definition from a YAML or JSON file
+    Register {
+        /// Definition file (.yaml, .yml or .json)
+        file: PathBuf,
+        /// Aether gRPC server address (default: localhost:7234)
+        #[arg(short = 's', long, default_value = "localhost:7234")]
+        server: String,
+    },
+    /// Check a workflow definition file without registering it
+    Validate {
+        /// Definition file (.yaml, .yml or .json)
+        file: PathBuf,
+    },
+    /// Recreate an exported workflow on a (local) server for debugging
+    Replay {
+        /// File written by `aether workflow export` (JSON or JSONL)
+        file: PathBuf,
+        /// Keep the exported workflow id instead of generating a new one
+        #[arg(long)]
+        keep_id: bool,
+        /// Re-dispatch from the failed step instead of leaving the workflow paused
+        #[arg(long)]
+        resume: bool,
+        /// Aether gRPC server address (default: localhost:7234)
+        #[arg(short = 's', long, default_value = "localhost:7234")]
+        server: String,
+    },
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    match &cli.command {
+        Commands::Serve {
+            log_format,
+            log_level,
+            ..
+        } => init_logging(log_format, log_level.as_deref())?,
+        _ => init_logging("pretty", None)?,
+    }
+
+    match cli.command {
+        Commands::Serve {
+            config,
+            host,
+            db,
+            port,
+            grpc_port,
+            dashboard,
+            dashboard_port,
+            persistence,
+            task_timeout,
+            worker_timeout,
+            shutdown_grace,
+            poll_interval_ms,
+            broadcast_capacity,
+            retention_hours,
+            api_keys,
+            api_key_file,
+            grpc_auth_tokens,
+            grpc_bootstrap_tokens,
+            cors_origins,
+            ..
+        } => {
+            let auth = load_api_keys(api_keys, api_key_file.as_deref())?;
+            let grpc_auth =
+                GrpcAuthConfig::new(grpc_auth_tokens).with_bootstrap_tokens(grpc_bootstrap_tokens);
+            grpc_auth.validate()?;
+            let mut server_config = load_config(config.as_deref())?;
+            server_config.apply_overrides(ServerOverrides {
+                host,
+                port,
+                grpc_port,
+                db_path: db,
+                cors_origins: (!cors_origins.is_empty()).then_some(cors_origins),
+                persistence_mode: persistence,
+                dashboard_enabled: dashboard,
+                dashboard_port,
+                task_timeout_secs: task_timeout,
+                worker_timeout_secs: worker_timeout,
+                shutdown_grace_secs: shutdown_grace,
+                poll_interval_ms,
+                broadcast_capacity,
+                retention_max_age_secs: retention_hours.map(|hours| hours.saturating_mul(3600)),
+            });
+            serve_command(server_config, auth, grpc_auth).await
+        }
+        Commands::Config { action } => config_command(action),
+        Commands::Init {
+            name,
+            output,
+            template,
+            package_manager,
+            server,
+            example,
+            install,
+            git,
+        } => {
+            let args = InitArgs {
+                name,
+                template,
+                package_manager,
+                server,
+                example,
+            };
+            init_command(args, output, PostInitHooks { install, git }).await
+        }
+        Commands::Gen { action } => gen_command(action).await,
+        Commands::Workflow { action } => workflow_command(action).await,
+        Commands::Status {
+            workflow_id,
+            server,
+            json,
+            watch,
+        } => status_command(workflow_id, server, json, watch).await,
+        Commands::Cancel {
+            workflow_id,
+            server,
+            wait,
+            timeout,
+        } => cancel_command(workflow_id, server, wait, timeout).await,
+        Commands::Terminate {
+            workflow_id,
+            reason,
+            requested_by,
+            server,
+        } => terminate_command(workflow_id, reason, requested_by, server).await,
+        Commands::Delete {
+            workflow_id,
+            force,
+            server,
+        } => delete_command(workflow_id, force, server).await,
+        Commands::Purge {
+            state,
+            older_than,
+            r#type,
+            server,
+        } => purge_command(state, older_than, r#type, server).await,
+        Commands::Schedule { action } => schedule_command(action).await,
+        Commands::Doctor {
+            server,
+            stuck_after,
+            json,
+        } => doctor_command(server, stuck_after, json).await,
+    }
+}
+
+/// 初始化日志输出
+///
+/// `level` 使用 `RUST_LOG` 的语法，未指定时取 `RUST_LOG`，都没有时为 `info`。
+/// `json` 格式每行一个事件，所在 span 的字段（workflow_id、task_id、request_id 等）一并输出。
+fn init_logging(format: &str, level: Option<&str>) -> anyhow::Result<()> {
+    let filter = match level {
+        Some(level) => EnvFilter::try_new(level)
+            .map_err(|e| anyhow::anyhow!("Invalid log level '{}': {}", level, e))?,
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+    };
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+    match format {
+        "pretty" => subscriber.init(),
+        "json" => subscriber.json().flatten_event(true).init(),
+        _ => anyhow::bail!("Invalid log format '{}'. Must be: pretty or json", format),
+    }
+    Ok(())
+}
+
+/// 加载配置文件与环境变量，未识别的配置项给出警告
+fn load_config(path: Option<&std::path::Path>) -> anyhow::Result<ServerConfig> {
+    let loaded = ServerConfig::load(path)?;
+    for key in &loaded.unknown_keys {
+        eprintln!("⚠️  Unknown configuration key: {}", key);
+    }
+    Ok(loaded.config)
+}
+
+fn config_command(action: ConfigAction) -> anyhow::Result<()> {
+    match action {
+        ConfigAction::Validate { config } => {
+            let server_config = load_config(config.as_deref())?;
+            server_config
+                .persistence
+                .mode
+                .parse::<PersistenceLevel>()
+                .map_err(anyhow::Error::msg)?;
+            println!("{}", server_config.to_toml());
+        }
+    }
+    Ok(())
+}
+
+/// 打开持久化层，配置了 `blob_dir` 时文件存储的大负载按引用保存
+async fn open_persistence(
+    level: PersistenceLevel,
+    db: &Path,
+    section: &PersistenceSection,
+) -> anyhow::Result<PersistenceBackend> {
+    let store = open_store(level, db).await?;
+    match &section.blob_dir {
+        Some(dir) if level != PersistenceLevel::L0Memory => {
+            ensure_writable_dir(dir)?;
+            println!(
+                "📦 Payloads over {} bytes are stored in {}",
+                section.blob_threshold_bytes,
+                dir.display()
+            );
+            Ok(PersistenceBackend::Blobs(Arc::new(BlobStore::new(
+                store,
+                dir,
+                section.blob_threshold_bytes,
+            )?)))
+        }
+        _ => Ok(store),
+    }
+}
+
+/// 打开底层存储
+///
+/// snapshot、state-action-log 与 sqlite 模式把数据保存在 `db` 目录下，
+/// 目录不存在时创建，不可写时直接报错。
+async fn open_store(level: PersistenceLevel, db: &Path) -> anyhow::Result<PersistenceBackend> {
+    match level {
+        PersistenceLevel::L0Memory => {
+            println!("📦 Using L0 Memory persistence (no durability)");
+            Ok(PersistenceBackend::L0Memory(Arc::new(L0MemoryStore::new())))
+        }
+        PersistenceLevel::L1Snapshot => {
+            ensure_writable_dir(db)?;
+            let path = db.join("snapshot.json");
+            println!("📦 Using L1 Snapshot persistence ({})", path.display());
+            Ok(PersistenceBackend::L1Snapshot(Arc::new(
+                L1SnapshotStore::new(path, 100)?,
+            )))
+        }
+        PersistenceLevel::L2StateActionLog => {
+            ensure_writable_dir(db)?;
+            let path = db.join("actions.log");
+            println!(
+                "📦 Using L2 State-Action-Log persistence (full durability, {})",
+                path.display()
+            );
+            Ok(PersistenceBackend::L2StateActionLog(Arc::new(
+                L2StateActionStore::open(path)?,
+            )))
+        }
+        #[cfg(feature = "sqlite")]
+        PersistenceLevel::Sqlite => {
+            ensure_writable_dir(db)?;
+            let path = db.join("aether.sqlite");
+            println!("📦 Using SQLite persistence ({})", path.display());
+            Ok(PersistenceBackend::Sqlite(Arc::new(
+                SqliteStore::open(path).await?,
+            )))
+        }
+        #[cfg(not(feature = "sqlite"))]
+        PersistenceLevel::Sqlite => {
+            anyhow::bail!("Persistence mode 'sqlite' requires building with the `sqlite` feature")
+        }
+    }
+}
+
+/// 创建数据目录并确认可写
+fn ensure_writable_dir(dir: &Path) -> anyhow::Result<()> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create database directory {}", dir.display()))?;
+
+    let probe = dir.join(".write-test");
+    std::fs::write(&probe, b"")
+        .with_context(|| format!("Database directory {} is not writable", dir.display()))?;
+    std::fs::remove_file(&probe)?;
+    Ok(())
+}
+
+/// 合并命令行和文件中的 API key
+fn load_api_keys(mut keys: Vec<String>, file: Option<&Path>) -> anyhow::Result<AuthConfig> {
+    if let Some(file) = file {
+        let content = std::fs::read_to_string(file)
+            .with_context(|| format!("Failed to read API key file {}", file.display()))?;
+        let from_file: Vec<String> = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect();
+        if from_file.is_empty() {
+            anyhow::bail!("API key file {} contains no keys", file.display());
+        }
+        keys.extend(from_file);
+    }
+    Ok(AuthConfig::new(keys))
+}
+
+async fn serve_command(
+    config: ServerConfig,
+    auth: AuthConfig,
+    grpc_auth: GrpcAuthConfig,
+) -> anyhow::Result<()> {
+    let db = &config.server.db_path;
+    let dashboard = config.dashboard.enabled;
+    let persistence = &config.persistence.mode;
+
+    println!("Starting Aether server...");
+    println!("Database: {:?}", db);
+    println!("API Port: {}", config.server.port);
+    println!("gRPC Port: {}", config.server.grpc_port);
+    println!(
+        "Dashboard: {}",
+        if dashboard { "enabled" } else { "disabled" }
+    );
+    if dashboard {
+        println!("Dashboard WS Port: {}", config.dashboard.port);
+    }
+    println!("Persistence: {}", persistence);
+    println!(
+        "API key auth: {}",
+        if auth.is_enabled() {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    );
+    println!(
+        "gRPC auth: {}",
+        if grpc_auth.is_enabled() {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    );
+    println!();
+
+    // 解析持久化模式，未知模式直接报错而不是回退到内存
+    let persistence_level: PersistenceLevel = persistence.parse().map_err(anyhow::Error::msg)?;
+    let persistence = open_persistence(persistence_level, db, &config.persistence).await?;
+
+    // 持久化模式下执行追踪记录随 workflow 一起保存，重启后 Dashboard 仍能查看历史
+    let tracker = match persistence_level {
+        PersistenceLevel::L0Memory => WorkflowTracker::new(),
+        _ => WorkflowTracker::with_store(Arc::new(persistence.clone()))
+            .await
+            .context("Failed to restore workflow executions")?,
+    }
+    .with_max_retained_executions(config.dashboard.max_retained_executions)
+    .with_execution_ttl(config.execution_ttl());
+
+    // 调度器、REST、gRPC、Dashboard 与后台任务由内核统一启动（gRPC 与 REST 共享同一个调度器）
+    if dashboard && !cfg!(feature = "dashboard") {
+        println!("⚠️  Dashboard feature not enabled. Rebuild with --features dashboard");
+    }
+    let mut builder = AetherKernel::builder()
+        .persistence(persistence)
+        .config(config.scheduler_config())
+        .limits(config.limits())
+        .tracker(tracker)
+        .http_addr(config.rest_addr())
+        .grpc_addr(config.grpc_addr())
+        .dashboard(dashboard && cfg!(feature = "dashboard"))
+        .dashboard_addr(config.dashboard_addr())
+        .auth(auth)
+        .grpc_auth(grpc_auth);
+    for (workflow_type, max) in &config.scheduler.max_concurrent {
+        builder = builder.concurrency_limit(workflow_type.clone(), *max);
+    }
+    let kernel = builder.build();
+
+    if let Some(policy) = &kernel.scheduler().config().retention {
+        println!(
+            "Retention: every {}s (default max age: {})",
+            policy.interval.as_secs(),
+            policy
+                .max_age
+                .map(|age| format!("{}s", age.as_secs()))
+                .unwrap_or_else(|| "keep".to_string())
+        );
+    }
+
+    let addrs = kernel.start().await?;
+    println!();
+    println!("🚀 REST API listening on {}", addrs.http);
+    println!(
+        "📚 Swagger UI available at http://localhost:{}/swagger-ui",
+        addrs.http.port()
+    );
+    println!("🔌 gRPC server listening on {}", addrs.grpc);
+    println!(
+        "🔎 gRPC reflection and health checks enabled (grpcurl -plaintext {} list)",
+        addrs.grpc
+    );
+    if let Some(addr) = addrs.dashboard {
+        println!("🎨 Dashboard WebSocket server listening on {}", addr);
+    }
+    println!();
+    println!("Press Ctrl+C to stop the server");
+    println!();
+
+    // 收到 SIGINT/SIGTERM 后等待进行中的 step，再停止服务器并刷新持久化层
+    kernel.run_until(shutdown_signal()).await?;
+    println!("Aether server stopped");
+
+    Ok(())
+}
+
+/// 等待 Ctrl+C（SIGINT）或 SIGTERM
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Could not listen for SIGTERM: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+    println!();
+    println!("Shutting down, waiting for in-flight steps...");
+}
+
+async fn init_command(args: InitArgs, output: PathBuf, hooks: PostInitHooks) -> anyhow::Result<()> {
+    let options = if std::io::stdin().is_terminal() {
+        let stdin = std::io::stdin();
+        let mut prompter = Prompter::new(stdin.lock(), std::io::stdout());
+        resolve_options(args, Some(&mut prompter))?
+    } else {
+        resolve_options(args, None)?
+    };
+    let name = &options.name;
+
+    println!("Initializing Aether project: {}", name);
+    println!("Template: {}", options.template.dir_name());
+    println!();
+
+    let project_dir = output.join(name);
+
+    if project_dir.exists() {
+        return Err(anyhow::anyhow!(
+            "Project directory already exists: {:?}",
+            project_dir
+        ));
+    }
+
+    let vars = options.variables();
+
+    let manifest = render_template_dir(options.template, &project_dir, &vars)
+        .await
+        .with_context(|| format!("Failed to render template: {}", options.template.dir_name()))?;
+
+    println!("✅ Project created at: {:?}", project_dir);
+    println!();
+
+    let failures = run_hooks(hooks, &project_dir, &manifest).await;
+    if !failures.is_empty() {
+        println!();
+        for failure in &failures {
+            println!("⚠️  {} failed: {:#}", failure.hook, failure.error);
+        }
+        println!();
+        println!(
+            "The project was kept at {:?}. Fix the problem, then run in the project directory:",
+            project_dir
+        );
+        for failure in &failures {
+            println!("  {}", failure.retry);
+        }
+        return Err(anyhow::anyhow!(
+            "{} post-init step(s) failed",
+            failures.len()
+        ));
+    }
+
+    // 已经安装过依赖时不再提示安装命令
+    let installed = manifest.install.as_ref().filter(|_| hooks.install);
+    if hooks.install {
+        println!();
+    }
+    println!("Next steps:");
+    println!("  cd {}", name);
+    for step in &manifest.next_steps {
+        if Some(step) != installed {
+            println!("  {}", step);
+        }
+    }
+
+    Ok(())
+}
+
+async fn workflow_command(action: WorkflowAction) -> anyhow::Result<()> {
+    match action {
+        WorkflowAction::Start {
+            workflow_type,
+            input,
+            input_file,
+            input_raw,
+            priority,
+            skip_schema_validation,
+            dynamic,
+            memo,
+            follow,
+            server,
+        } => {
+            let input = match (input, input_file, input_raw) {
+                (Some(json), _, _) => parse_json_input("--input", &json)?,
+                (_, Some(path), _) => {
+                    let json = std::fs::read_to_string(&path)
+                        .with_context(|| format!("Failed to read {}", path.display()))?;
+                    parse_json_input(&path.display().to_string(), &json)?
+                }
+                (_, _, Some(raw)) => raw.into_bytes(),
+                (None, None, None) => Vec::new(),
+            };
+            let request = proto::StartWorkflowRequest {
+                workflow_type,
+                input,
+                priority: to_proto_priority(priority) as i32,
+                skip_schema_validation,
+                dynamic,
+                memo: memo.into_iter().collect(),
+                ..Default::default()
+            };
+            return start_workflow_command(request, follow, server).await;
+        }
+        WorkflowAction::List {
+            r#type,
+            state,
+            created_after,
+            created_before,
+            memo,
+            limit,
+            offset,
+            page_token,
+            order,
+            output,
+            server,
+        } => {
+            let state = state
+                .map(|s| {
+                    s.parse::<StateKind>()
+                        .map(|kind| to_proto_state(kind) as i32)
+                        .map_err(anyhow::Error::msg)
+                })
+                .transpose()?;
+            let order = match order.parse::<OrderBy>().map_err(anyhow::Error::msg)? {
+                OrderBy::StartedAtAsc => proto::ListOrder::StartedAtAsc,
+                OrderBy::StartedAtDesc => proto::ListOrder::StartedAtDesc,
+            };
+            let json = match output.as_str() {
+                "table" => false,
+                "json" => true,
+                other => anyhow::bail!("Invalid output '{}': expected table or json", other),
+            };
+
+            let mut client = ClientServiceClient::connect(doctor::grpc_endpoint(&server)).await?;
+            let page = client
+                .list_workflows(proto::ListWorkflowsRequest {
+                    workflow_type: r#type.unwrap_or_default(),
+                    state,
+                    created_after: parse_time_arg("--created-after", created_after)?,
+                    created_before: parse_time_arg("--created-before", created_before)?,
+                    page_size: limit,
+                    page_token: page_token.unwrap_or_default(),
+                    order: order as i32,
+                    offset,
+                    memo: memo.into_iter().collect(),
+                })
+                .await
+                .map_err(|status| anyhow::anyhow!(status.message().to_string()))?
+                .into_inner();
+
+            let now = chrono::Utc::now().timestamp();
+            if json {
+                let workflows: Vec<_> = page
+                    .workflows
+                    .iter()
+                    .map(|summary| {
+                        serde_json::json!({
+                            "workflowId": summary.workflow_id,
+                            "workflowType": summary.workflow_type,
+                            "state": state_name(summary.state),
+                            "currentStep": Some(&summary.current_step).filter(|s| !s.is_empty()),
+                            "startedAt": chrono::DateTime::from_timestamp(summary.started_at, 0)
+                                .map(|t| t.to_rfc3339()),
+                            "durationSecs": summary_duration(summary, now),
+                            "memo": summary.memo.iter().collect::<std::collections::BTreeMap<_, _>>(),
+                        })
+                    })
+                    .collect();
+                let value = serde_json::json!({
+                    "workflows": workflows,
+                    "nextPageToken": Some(&page.next_page_token).filter(|t| !t.is_empty()),
+                });
+                println!("{}", serde_json::to_string_pretty(&value)?);
+                return Ok(());
+            }
+
+            println!(
+                "{:<38} {:<20} {:<10} {:<20} {:<26} DURATION",
+                "ID", "TYPE", "STATE", "STEP", "STARTED"
+            );
+            for summary in &page.workflows {
+                let started = chrono::DateTime::from_timestamp(summary.started_at, 0)
+                    .map(|t| t.to_rfc3339())
+                    .unwrap_or_default();
+                println!(
+                    "{:<38} {:<20} {:<10} {:<20} {:<26} {}",
+                    summary.workflow_id,
+                    summary.workflow_type,
+                    state_name(summary.state),
+                    summary.current_step,
+                    started,
+                    format_duration(summary_duration(summary, now))
+                );
+            }
+            println!("\n{} workflow(s)", page.workflows.len());
+            if !page.next_page_token.is_empty() {
+                println!("Next page: --page-token {}", page.next_page_token);
+            }
+        }
+        WorkflowAction::Export {
+            workflow_id,
+            output,
+            format,
+            max_payload_bytes,
+            server,
+        } => {
+            let format = match format.as_str() {
+                "json" => proto::ExportFormat::ExportJson,
+                "jsonl" => proto::ExportFormat::ExportJsonl,
+                other => anyhow::bail!("Invalid format '{}': expected json or jsonl", other),
+            };
+            let mut client = ClientServiceClient::connect(doctor::grpc_endpoint(&server))
+                .await?
+                .max_decoding_message_size(usize::MAX);
+            let document = client
+                .export_workflow(proto::ExportWorkflowRequest {
+                    workflow_id: workflow_id.clone(),
+                    format: format as i32,
+                    max_payload_bytes: max_payload_bytes.unwrap_or_default(),
+                })
+                .await
+                .map_err(|status| anyhow::anyhow!(status.message().to_string()))?
+                .into_inner()
+                .document;
+
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, &document)
+                        .with_context(|| format!("Failed to write {}", path.display()))?;
+                    eprintln!(
+                        "📦 Exported workflow {} to {} ({} bytes)",
+                        workflow_id,
+                        path.display(),
+                        document.len()
+                    );
+                }
+                None => {
+                    use std::io::Write;
+                    let mut stdout = std::io::stdout().lock();
+                    stdout.write_all(&document)?;
+                    if !document.ends_with(b"\n") {
+                        stdout.write_all(b"\n")?;
+                    }
+                }
+            }
+        }
+        WorkflowAction::Register { file, server } => {
+            let definition = definition_file::read_definition(&file)?;
+            let client = AetherClient::connect(doctor::grpc_endpoint(&server)).await?;
+            client
+                .register_definition(&definition)
+                .await
+                .map_err(|err| anyhow::anyhow!(err.to_string()))?;
+            println!(
+                "📋 Registered workflow definition {} version {} ({} steps)",
+                definition.name,
+                definition.version,
+                definition.steps.len()
+            );
+        }
+        WorkflowAction::Validate { file } => {
+            let definition = definition_file::read_definition(&file)?;
+            println!(
+                "✅ {} is a valid definition of {} version {} ({} steps)",
+                file.display(),
+                definition.name,
+                definition.version,
+                definition.steps.len()
+            );
+        }
+        WorkflowAction::Replay {
+            file,
+            keep_id,
+            resume,
+            server,
+        } => {
+            let document = std::fs::read(&file)
+                .with_context(|| format!("Failed to read {}", file.display()))?;
+            let mut client = ClientServiceClient::connect(doctor::grpc_endpoint(&server))
+                .await?
+                .max_encoding_message_size(usize::MAX);
+            let imported = client
+                .import_workflow(proto::ImportWorkflowRequest {
+                    document,
+                    keep_id,
+                    resume,
+                })
+                .await
+                .map_err(|status| anyhow::anyhow!(status.message().to_string()))?
+                .into_inner();
+
+            println!(
+                "📥 Imported workflow {} ({})",
+                imported.workflow_id,
+                state_name(imported.state)
+            );
+            if resume {
+                println!("   Resumed steps: {}", imported.resumed_steps.join(", "));
+            } else if imported.state == proto::State::Failed as i32 {
+                println!(
+                    "   Paused at the failed step; resume with POST /workflows/{}/retry",
+                    imported.workflow_id
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 校验 JSON 输入，发送的是原始文本
+fn parse_json_input(source: &str, json: &str) -> anyhow::Result<Vec<u8>> {
+    serde_json::from_str::<serde_json::Value>(json)
+        .with_context(|| format!("{} is not valid JSON", source))?;
+    Ok(json.as_bytes().to_vec())
+}
+
+async fn start_workflow_command(
+    request: proto::StartWorkflowRequest,
+    follow: bool,
+    server: String,
+) -> anyhow::Result<()> {
+    let workflow_type = request.workflow_type.clone();
+    let mut client = ClientServiceClient::connect(doctor::grpc_endpoint(&server)).await?;
+    let workflow_id = client
+        .start_workflow(request)
+        .await
+        .map_err(|status| anyhow::anyhow!(status.message().to_string()))?
+        .into_inner()
+        .workflow_id;
+    println!("🚀 Started {} workflow {}", workflow_type, workflow_id);
+    if !follow {
+        return Ok(());
+    }
+
+    let mut current_step = String::new();
+    loop {
+        let status = fetch_status(&mut client, &workflow_id).await?;
+        if !status.current_step.is_empty() && status.current_step != current_step {
+            println!("  ▶ {}", status.current_step);
+            current_step = status.current_step.clone();
+        }
+        match proto::State::try_from(status.state) {
+            Ok(proto::State::Completed) => {
+                match std::str::from_utf8(&status.result) {
+                    Ok(result) if !result.is_empty() => println!("✅ Completed: {}", result),
+                    Ok(_) => println!("✅ Completed"),
+                    Err(_) => println!("✅ Completed ({} bytes)", status.result.len()),
+                }
+                return Ok(());
             }
-            PersistenceBackend::L1Snapshot(store) => {
-                store.as_ref().get_step_result(workflow_id, step_name).await
+            Ok(proto::State::Failed) => {
+                eprintln!("❌ Failed: {}", status.error);
+                std::process::exit(1);
             }
-            PersistenceBackend::L2StateActionLog(store) => {
-                store.as_ref().get_step_result(workflow_id, step_name).await
+            Ok(proto::State::Cancelled) => {
+                eprintln!("🛑 Cancelled");
+                std::process::exit(1);
+            }
+            Ok(proto::State::Terminated) => {
+                eprintln!("⛔ Terminated: {}", status.error);
+                std::process::exit(1);
             }
+            _ => {}
         }
+        tokio::time::sleep(STATUS_WATCH_INTERVAL).await;
     }
 }
 
-#[derive(Parser, Debug)]
-#[command(name = "aether")]
-#[command(about = "Aether workflow engine CLI")]
-struct Cli {
-    #[command(subcommand)]
-    command: Commands,
+/// workflow 运行时长（秒）：已结束的 workflow 到最后更新为止，否则到现在为止
+fn summary_duration(summary: &proto::WorkflowSummary, now: i64) -> i64 {
+    let end = if is_terminal(summary.state) {
+        summary.updated_at
+    } else {
+        now
+    };
+    (end - summary.started_at).max(0)
 }
 
-#[derive(Subcommand, Debug)]
-enum Commands {
-    /// Start the Aether server
-    Serve {
-        /// Database path (default: ./data/aether.db)
-        #[arg(long, default_value = "./data/aether.db")]
-        db: PathBuf,
-        /// API port (default: 7233)
-        #[arg(long, default_value = "7233")]
-        port: u16,
-        /// Enable Dashboard (default: true)
-        #[arg(long, default_value = "true")]
-        dashboard: bool,
-        /// Dashboard WebSocket port (default: 7235)
-        #[arg(long, default_value = "7235")]
-        dashboard_port: u16,
-        /// Persistence mode (memory|snapshot|state-action-log)
-        #[arg(long, default_value = "memory")]
-        persistence: String,
-    },
-    /// Initialize a new Aether project
-    Init {
-        /// Project name
-        name: String,
-        /// Output directory
-        #[arg(short, long, default_value = ".")]
-        output: PathBuf,
-        /// Project template: ts | nestjs | python
-        #[arg(short, long, default_value = "ts")]
-        template: String,
-    },
-    /// Generate configuration
-    Gen {
-        #[command(subcommand)]
-        action: GenAction,
-    },
-    /// List workflows
-    Workflow {
-        #[command(subcommand)]
-        action: WorkflowAction,
-    },
-    /// Show workflow status
-    Status { workflow_id: String },
-    /// Cancel a workflow
-    Cancel { workflow_id: String },
+fn format_duration(seconds: i64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}h{:02}m{:02}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m{:02}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
 }
 
-#[derive(Subcommand, Debug)]
-enum GenAction {
-    /// Generate aether.config.ts from registered services
-    Config {
-        /// Configuration source: local | remote | both
-        #[arg(short = 'c', long, default_value = "both")]
-        config_source: String,
-        /// Aether server address (default: localhost:7233)
-        #[arg(short = 's', long, default_value = "localhost:7233")]
-        server: String,
-        /// Output file path (default: ./aether.config.ts)
-        #[arg(short = 'o', long)]
-        output: Option<PathBuf>,
-        /// Output format: ts | json
-        #[arg(long, default_value = "ts")]
-        format: String,
-        /// Overwrite existing file
-        #[arg(long)]
-        overwrite: bool,
-        /// Preview without writing
-        #[arg(long)]
-        dry_run: bool,
-    },
+/// 解析 `--memo KEY=VALUE`
+fn parse_memo_entry(entry: &str) -> Result<(String, String), String> {
+    match entry.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got '{}'", entry)),
+    }
 }
 
-#[derive(Subcommand, Debug)]
-enum WorkflowAction {
-    List {
-        /// Workflow type filter
-        #[arg(short, long)]
-        r#type: Option<String>,
-        /// State filter
-        #[arg(short, long)]
-        state: Option<String>,
-    },
+/// RFC 3339 时间参数转换为 unix 秒，未设置时为 0
+fn parse_time_arg(flag: &str, value: Option<String>) -> anyhow::Result<i64> {
+    value.map_or(Ok(0), |value| {
+        chrono::DateTime::parse_from_rfc3339(&value)
+            .map(|t| t.timestamp())
+            .with_context(|| format!("{} must be an RFC 3339 time, got '{}'", flag, value))
+    })
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt::init();
-
-    let cli = Cli::parse();
-
-    match cli.command {
-        Commands::Serve {
-            db,
-            port,
-            dashboard,
-            dashboard_port,
-            persistence,
-        } => {
-            serve_command(
-                db,
-                port,
-                dashboard,
-                dashboard_port,
-                persistence,
-            )
-            .await
-        }
-        Commands::Init {
-            name,
-            output,
-            template,
-        } => init_command(name, output, template).await,
-        Commands::Gen { action } => gen_command(action).await,
-        Commands::Workflow { action } => workflow_command(action).await,
-        Commands::Status { workflow_id } => status_command(workflow_id).await,
-        Commands::Cancel { workflow_id } => cancel_command(workflow_id).await,
+fn to_proto_state(kind: StateKind) -> proto::State {
+    match kind {
+        StateKind::Pending => proto::State::Pending,
+        StateKind::Running => proto::State::Running,
+        StateKind::Completed => proto::State::Completed,
+        StateKind::Failed => proto::State::Failed,
+        StateKind::Cancelled => proto::State::Cancelled,
+        StateKind::Terminated => proto::State::Terminated,
     }
 }
 
-async fn serve_command(
-    db: PathBuf,
-    port: u16,
-    dashboard: bool,
-    dashboard_port: u16,
-    persistence: String,
-) -> anyhow::Result<()> {
-    println!("Starting Aether server...");
-    println!("Database: {:?}", db);
-    println!("API Port: {}", port);
-    println!(
-        "Dashboard: {}",
-        if dashboard { "enabled" } else { "disabled" }
-    );
-    if dashboard {
-        println!("Dashboard WS Port: {}", dashboard_port);
+fn to_proto_priority(priority: Priority) -> proto::Priority {
+    match priority {
+        Priority::Low => proto::Priority::Low,
+        Priority::Normal => proto::Priority::Normal,
+        Priority::High => proto::Priority::High,
     }
-    println!("Persistence: {}", persistence);
-    println!();
+}
 
-    // 创建数据目录
-    if let Some(parent) = db.parent() {
-        if !parent.exists() {
-            tokio::fs::create_dir_all(parent).await?;
-        }
-    }
+/// `aether status --watch` 的轮询间隔
+const STATUS_WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
 
-    // 解析持久化模式（目前只支持 memory，其他模式需要后续实现文件持久化）
-    let persistence_level = match persistence.to_lowercase().as_str() {
-        "memory" => PersistenceLevel::L0Memory,
-        "snapshot" => {
-            println!("⚠️  Snapshot persistence mode not yet implemented, using memory mode.");
-            PersistenceLevel::L0Memory
-        }
-        "state-action-log" => {
-            println!(
-                "⚠️  State-Action-Log persistence mode not yet implemented, using memory mode."
-            );
-            PersistenceLevel::L0Memory
+async fn status_command(
+    workflow_id: String,
+    server: String,
+    json: bool,
+    watch: bool,
+) -> anyhow::Result<()> {
+    let mut client = ClientServiceClient::connect(doctor::grpc_endpoint(&server)).await?;
+    let mut last: Option<proto::WorkflowStatus> = None;
+    loop {
+        let status = fetch_status(&mut client, &workflow_id).await?;
+        // watch 模式下只在状态变化时输出
+        if last.as_ref() != Some(&status) {
+            if json {
+                let value = status_json(&status);
+                if watch {
+                    println!("{}", serde_json::to_string(&value)?);
+                } else {
+                    println!("{}", serde_json::to_string_pretty(&value)?);
+                }
+            } else {
+                if last.is_some() {
+                    println!();
+                }
+                print!("{}", render_status(&status));
+            }
         }
-        _ => {
-            eprintln!(
-                "Unknown persistence mode: {}. Using 'memory' instead.",
-                persistence
-            );
-            PersistenceLevel::L0Memory
+        if !watch || is_terminal(status.state) {
+            return Ok(());
         }
-    };
+        last = Some(status);
+        tokio::time::sleep(STATUS_WATCH_INTERVAL).await;
+    }
+}
 
-    // 创建持久化层 (使用 Arc 共享状态)
-    let persistence = match persistence_level {
-        PersistenceLevel::L0Memory => {
-            println!("📦 Using L0 Memory persistence (no durability)");
-            PersistenceBackend::L0Memory(Arc::new(L0MemoryStore::new()))
-        }
-        PersistenceLevel::L1Snapshot => {
-            println!("📦 Using L1 Snapshot persistence");
-            PersistenceBackend::L1Snapshot(Arc::new(L1SnapshotStore::new(100)))
-        }
-        PersistenceLevel::L2StateActionLog => {
-            println!("📦 Using L2 State-Action-Log persistence (full durability)");
-            PersistenceBackend::L2StateActionLog(Arc::new(L2StateActionStore::new()))
+async fn fetch_status(
+    client: &mut ClientServiceClient<tonic::transport::Channel>,
+    workflow_id: &str,
+) -> anyhow::Result<proto::WorkflowStatus> {
+    match client
+        .get_workflow_status(proto::GetStatusRequest {
+            workflow_id: workflow_id.to_string(),
+        })
+        .await
+    {
+        Ok(response) => Ok(response.into_inner()),
+        Err(status) if status.code() == tonic::Code::NotFound => {
+            anyhow::bail!("workflow not found: {}", workflow_id)
         }
-    };
-
-    // 创建调度器
-    let scheduler = Scheduler::new(persistence);
+        Err(status) => Err(anyhow::anyhow!(status.message().to_string())),
+    }
+}
 
-    // 启动 REST API 服务器
-    let addr = format!("0.0.0.0:{}", port);
-    println!();
-    println!("🚀 Aether server starting on {}", addr);
-    println!("📚 Swagger UI available at http://localhost:{}/swagger-ui", port);
-    println!();
-    println!("Press Ctrl+C to stop the server");
-    println!();
+fn state_name(state: i32) -> &'static str {
+    proto::State::try_from(state)
+        .map(|s| s.as_str_name())
+        .unwrap_or("UNKNOWN")
+}
 
-    // 启动 Dashboard WebSocket 服务器（如果启用）
-    if dashboard {
-        #[cfg(feature = "dashboard")]
-        {
-            let dashboard_addr = format!("0.0.0.0:{}", dashboard_port);
-            let tracker = scheduler.tracker.clone();
-            let broadcaster = scheduler.broadcaster.get_sender();
-
-            tokio::spawn(async move {
-                if let Err(e) = aetherframework_kernel::dashboard_server::start_dashboard_server(
-                    tracker,
-                    broadcaster,
-                    &dashboard_addr,
-                )
-                .await
-                {
-                    eprintln!("Dashboard server error: {}", e);
-                }
-            });
+fn is_terminal(state: i32) -> bool {
+    matches!(
+        proto::State::try_from(state),
+        Ok(proto::State::Completed
+            | proto::State::Failed
+            | proto::State::Cancelled
+            | proto::State::Terminated)
+    )
+}
 
-            println!(
-                "🎨 Dashboard WebSocket server starting on 0.0.0.0:{}",
-                dashboard_port
-            );
-        }
+/// unix 秒转换为本地时间，0 表示未发生
+fn local_time(seconds: i64) -> Option<String> {
+    if seconds == 0 {
+        return None;
+    }
+    chrono::DateTime::from_timestamp(seconds, 0).map(|t| {
+        t.with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S %:z")
+            .to_string()
+    })
+}
 
-        #[cfg(not(feature = "dashboard"))]
-        {
-            println!("⚠️  Dashboard feature not enabled. Rebuild with --features dashboard");
-        }
+fn render_status(status: &proto::WorkflowStatus) -> String {
+    let mut rows = vec![
+        ("Workflow", status.workflow_id.clone()),
+        ("State", state_name(status.state).to_string()),
+    ];
+    if !status.current_step.is_empty() {
+        rows.push(("Current step", status.current_step.clone()));
+    }
+    if let Some(started) = local_time(status.started_at) {
+        rows.push(("Started", started));
+    }
+    if let Some(completed) = local_time(status.completed_at) {
+        rows.push(("Completed", completed));
+    }
+    if !status.error.is_empty() {
+        rows.push(("Error", status.error.clone()));
+    }
+    if !status.terminated_by.is_empty() {
+        rows.push(("Requested by", status.terminated_by.clone()));
+    }
+    if !status.parent_workflow_id.is_empty() {
+        rows.push(("Parent", status.parent_workflow_id.clone()));
+    }
+    if !status.child_workflow_ids.is_empty() {
+        rows.push(("Children", status.child_workflow_ids.join(", ")));
     }
 
-    // 使用 aetherframework-kernel 的服务器启动函数
-    server::start_server(scheduler, &addr).await?;
+    rows.into_iter()
+        .map(|(label, value)| format!("{:<14}{}\n", format!("{}:", label), value))
+        .collect()
+}
 
-    Ok(())
+fn status_json(status: &proto::WorkflowStatus) -> serde_json::Value {
+    let time = |seconds: i64| {
+        chrono::DateTime::from_timestamp(seconds, 0)
+            .filter(|_| seconds != 0)
+            .map(|t| t.to_rfc3339())
+    };
+    let non_empty = |value: &str| Some(value.to_string()).filter(|v| !v.is_empty());
+    serde_json::json!({
+        "workflowId": status.workflow_id,
+        "state": state_name(status.state),
+        "currentStep": non_empty(&status.current_step),
+        "startedAt": time(status.started_at),
+        "completedAt": time(status.completed_at),
+        "error": non_empty(&status.error),
+        "terminatedBy": non_empty(&status.terminated_by),
+        "parentWorkflowId": non_empty(&status.parent_workflow_id),
+        "childWorkflowIds": status.child_workflow_ids,
+    })
 }
 
-async fn init_command(name: String, output: PathBuf, template: String) -> anyhow::Result<()> {
-    println!("Initializing Aether project: {}", name);
-    println!("Template: {}", template);
-    println!();
+/// workflow 已结束、无法取消时 `aether cancel` 的退出码
+const EXIT_NOT_CANCELLABLE: i32 = 2;
 
-    let template_type = TemplateType::from_str(&template)
-        .with_context(|| format!("Invalid template type: {}", template))?;
+async fn cancel_command(
+    workflow_id: String,
+    server: String,
+    wait: bool,
+    timeout: u64,
+) -> anyhow::Result<()> {
+    let mut client = ClientServiceClient::connect(doctor::grpc_endpoint(&server)).await?;
+    let response = match client
+        .cancel_workflow(proto::CancelRequest {
+            workflow_id: workflow_id.clone(),
+        })
+        .await
+    {
+        Ok(response) => response.into_inner(),
+        Err(status) if status.code() == tonic::Code::NotFound => {
+            anyhow::bail!("workflow not found: {}", workflow_id)
+        }
+        Err(status) if status.code() == tonic::Code::FailedPrecondition => {
+            eprintln!("❌ {}", status.message());
+            std::process::exit(EXIT_NOT_CANCELLABLE);
+        }
+        Err(status) => return Err(anyhow::anyhow!(status.message().to_string())),
+    };
 
-    let cli_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    let project_dir = output.join(&name);
+    // 旧版本的服务端以 success = false 表示 workflow 已结束
+    if !response.success {
+        let status = fetch_status(&mut client, &workflow_id).await?;
+        eprintln!(
+            "❌ Workflow {} is already {} and cannot be cancelled",
+            workflow_id,
+            state_name(status.state)
+        );
+        std::process::exit(EXIT_NOT_CANCELLABLE);
+    }
+    println!("🛑 Cancelled workflow {}", workflow_id);
 
-    if project_dir.exists() {
-        return Err(anyhow::anyhow!(
-            "Project directory already exists: {:?}",
-            project_dir
-        ));
+    if wait {
+        let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(timeout);
+        loop {
+            let status = fetch_status(&mut client, &workflow_id).await?;
+            if status.state == proto::State::Cancelled as i32 {
+                println!("Workflow {} is CANCELLED", workflow_id);
+                break;
+            }
+            if tokio::time::Instant::now() >= deadline {
+                anyhow::bail!(
+                    "workflow {} is still {} after {}s",
+                    workflow_id,
+                    state_name(status.state),
+                    timeout
+                );
+            }
+            tokio::time::sleep(STATUS_WATCH_INTERVAL).await;
+        }
     }
+    Ok(())
+}
+
+async fn terminate_command(
+    workflow_id: String,
+    reason: String,
+    requested_by: Option<String>,
+    server: String,
+) -> anyhow::Result<()> {
+    let mut client = ClientServiceClient::connect(doctor::grpc_endpoint(&server)).await?;
+    client
+        .terminate_workflow(proto::TerminateWorkflowRequest {
+            workflow_id: workflow_id.clone(),
+            reason,
+            requested_by: requested_by.unwrap_or_default(),
+        })
+        .await
+        .map_err(|status| anyhow::anyhow!(status.message().to_string()))?;
 
-    let vars = TemplateVariables::new(&name);
+    println!("⛔ Terminated workflow {}", workflow_id);
+    Ok(())
+}
 
-    render_template_dir(template_type, &cli_root, &project_dir, &vars)
+async fn delete_command(workflow_id: String, force: bool, server: String) -> anyhow::Result<()> {
+    let mut client = ClientServiceClient::connect(doctor::grpc_endpoint(&server)).await?;
+    client
+        .delete_workflow(proto::DeleteWorkflowRequest {
+            workflow_id: workflow_id.clone(),
+            force,
+        })
         .await
-        .with_context(|| format!("Failed to render template: {}", template))?;
+        .map_err(|status| anyhow::anyhow!(status.message().to_string()))?;
 
-    println!("✅ Project created at: {:?}", project_dir);
-    println!();
-    println!("Next steps:");
-    println!("  cd {}", name);
-    if template_type == TemplateType::TypeScript {
-        println!("  npm install");
-        println!("  npm run dev");
-    } else if template_type == TemplateType::NestJS {
-        println!("  npm install");
-        println!("  npm run start:dev");
-    } else if template_type == TemplateType::Python {
-        println!("  pip install -e .");
-        println!("  python -m src.main");
+    println!("🗑️  Deleted workflow {}", workflow_id);
+    Ok(())
+}
+
+fn overlap_policy_name(policy: i32) -> &'static str {
+    match proto::OverlapPolicy::try_from(policy) {
+        Ok(proto::OverlapPolicy::OverlapAllow) => "allow",
+        Ok(proto::OverlapPolicy::OverlapCancelPrevious) => "cancel_previous",
+        _ => "skip",
     }
+}
 
-    Ok(())
+fn unix_time(secs: i64) -> Option<String> {
+    Some(secs)
+        .filter(|secs| *secs > 0)
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+        .map(|t| t.to_rfc3339())
 }
 
-async fn workflow_command(action: WorkflowAction) -> anyhow::Result<()> {
+async fn schedule_command(action: ScheduleAction) -> anyhow::Result<()> {
     match action {
-        WorkflowAction::List { r#type, state } => {
-            println!("Listing workflows...");
-            if let Some(t) = r#type {
-                println!("Filter by type: {}", t);
+        ScheduleAction::List { json, server } => {
+            let mut client = ClientServiceClient::connect(doctor::grpc_endpoint(&server)).await?;
+            let schedules = client
+                .list_schedules(proto::ListSchedulesRequest {})
+                .await
+                .map_err(|status| anyhow::anyhow!(status.message().to_string()))?
+                .into_inner()
+                .schedules;
+
+            if json {
+                let schedules: Vec<_> = schedules
+                    .iter()
+                    .map(|schedule| {
+                        serde_json::json!({
+                            "scheduleId": schedule.schedule_id,
+                            "cron": schedule.cron,
+                            "workflowType": schedule.workflow_type,
+                            "overlapPolicy": overlap_policy_name(schedule.overlap_policy),
+                            "createdAt": unix_time(schedule.created_at),
+                            "lastFiredAt": unix_time(schedule.last_fired_at),
+                            "lastWorkflowId": Some(&schedule.last_workflow_id).filter(|id| !id.is_empty()),
+                            "nextFireAt": unix_time(schedule.next_fire_at),
+                        })
+                    })
+                    .collect();
+                let value = serde_json::json!({ "schedules": schedules });
+                println!("{}", serde_json::to_string_pretty(&value)?);
+                return Ok(());
+            }
+
+            println!(
+                "{:<38} {:<20} {:<20} {:<16} {:<26} LAST WORKFLOW",
+                "ID", "CRON", "TYPE", "OVERLAP", "NEXT FIRE"
+            );
+            for schedule in &schedules {
+                println!(
+                    "{:<38} {:<20} {:<20} {:<16} {:<26} {}",
+                    schedule.schedule_id,
+                    schedule.cron,
+                    schedule.workflow_type,
+                    overlap_policy_name(schedule.overlap_policy),
+                    unix_time(schedule.next_fire_at).unwrap_or_else(|| "-".to_string()),
+                    Some(schedule.last_workflow_id.as_str())
+                        .filter(|id| !id.is_empty())
+                        .unwrap_or("-"),
+                );
             }
-            if let Some(s) = state {
-                println!("Filter by state: {}", s);
+            println!("\n{} schedule(s)", schedules.len());
+        }
+        ScheduleAction::Create {
+            cron,
+            workflow_type,
+            input,
+            overlap,
+            server,
+        } => {
+            let input = input
+                .map(|json| parse_json_input("--input", &json))
+                .transpose()?
+                .unwrap_or_default();
+            let overlap_policy = match overlap {
+                OverlapPolicy::Skip => proto::OverlapPolicy::OverlapSkip,
+                OverlapPolicy::Allow => proto::OverlapPolicy::OverlapAllow,
+                OverlapPolicy::CancelPrevious => proto::OverlapPolicy::OverlapCancelPrevious,
+            };
+            let mut client = ClientServiceClient::connect(doctor::grpc_endpoint(&server)).await?;
+            let schedule = client
+                .create_schedule(proto::CreateScheduleRequest {
+                    cron,
+                    workflow_type,
+                    input,
+                    overlap_policy: overlap_policy as i32,
+                })
+                .await
+                .map_err(|status| anyhow::anyhow!(status.message().to_string()))?
+                .into_inner();
+
+            println!("📅 Created schedule {}", schedule.schedule_id);
+            if let Some(next) = unix_time(schedule.next_fire_at) {
+                println!("   Next fire: {}", next);
             }
         }
+        ScheduleAction::Delete {
+            schedule_id,
+            server,
+        } => {
+            let mut client = ClientServiceClient::connect(doctor::grpc_endpoint(&server)).await?;
+            client
+                .delete_schedule(proto::DeleteScheduleRequest {
+                    schedule_id: schedule_id.clone(),
+                })
+                .await
+                .map_err(|status| anyhow::anyhow!(status.message().to_string()))?;
+            println!("🗑️  Deleted schedule {}", schedule_id);
+        }
     }
     Ok(())
 }
 
-async fn status_command(workflow_id: String) -> anyhow::Result<()> {
-    println!("Getting status for workflow: {}", workflow_id);
-    // TODO: 实现状态查询
+async fn purge_command(
+    states: Vec<String>,
+    older_than: Option<u64>,
+    workflow_type: Option<String>,
+    server: String,
+) -> anyhow::Result<()> {
+    let states = states
+        .iter()
+        .map(|state| match state.to_lowercase().as_str() {
+            "completed" => Ok(proto::State::Completed as i32),
+            "failed" => Ok(proto::State::Failed as i32),
+            "cancelled" => Ok(proto::State::Cancelled as i32),
+            "terminated" => Ok(proto::State::Terminated as i32),
+            other => anyhow::bail!(
+                "Invalid state '{}': expected completed, failed, cancelled or terminated",
+                other
+            ),
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let completed_before = match older_than {
+        Some(secs) => {
+            let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?;
+            now.as_secs().saturating_sub(secs) as i64
+        }
+        None => 0,
+    };
+
+    let mut client = AdminServiceClient::connect(doctor::grpc_endpoint(&server)).await?;
+    let response = client
+        .purge_workflows(proto::PurgeRequest {
+            states,
+            completed_before,
+            workflow_type: workflow_type.unwrap_or_default(),
+        })
+        .await
+        .map_err(|status| anyhow::anyhow!(status.message().to_string()))?
+        .into_inner();
+
+    println!("🗑️  Purged {} workflow(s)", response.removed);
     Ok(())
 }
 
-async fn cancel_command(workflow_id: String) -> anyhow::Result<()> {
-    println!("Cancelling workflow: {}", workflow_id);
-    // TODO: 实现取消工作流
+async fn doctor_command(server: String, stuck_after: u64, json: bool) -> anyhow::Result<()> {
+    let report = doctor::run(&server, std::time::Duration::from_secs(stuck_after)).await;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{}", report.render());
+    }
+
+    if report.has_failures() {
+        std::process::exit(1);
+    }
     Ok(())
 }
 
@@ -464,37 +2261,32 @@ async fn config_gen_command(
     println!("Format: {}", format);
     println!("Dry run: {}", dry_run);
 
-    // Determine output path
+    let source: ConfigSource = source.parse().map_err(anyhow::Error::msg)?;
+    let format: ConfigFormat = format.parse().map_err(anyhow::Error::msg)?;
+
     let output_path = output
         .cloned()
-        .unwrap_or_else(|| PathBuf::from("./aether.config.ts"));
-
+        .unwrap_or_else(|| PathBuf::from(".").join(format.file_name()));
     println!("Output: {:?}", output_path);
 
-    // Validate source
-    match source {
-        "local" | "remote" | "both" => {}
-        _ => {
-            return Err(anyhow::anyhow!(
-                "Invalid source '{}'. Must be: local, remote, or both",
-                source
-            ));
-        }
-    }
-
-    // Validate format
-    match format {
-        "ts" | "json" => {}
-        _ => {
-            return Err(anyhow::anyhow!(
-                "Invalid format '{}'. Must be: ts or json",
-                format
-            ));
+    let local = if source.includes_local() {
+        let local = config_gen::load_local_config(&std::env::current_dir()?)?;
+        if let Some((path, _)) = &local {
+            println!("Merging local configuration: {:?}", path);
         }
-    }
-
-    // Generate configuration
-    let config_content = generate_config_content(source, server, format).await?;
+        local.map(|(_, value)| value)
+    } else {
+        None
+    };
+    let remote = if source.includes_remote() {
+        let remote = config_gen::fetch_remote(server).await?;
+        println!("Found {} registered service(s)", remote.services.len());
+        Some(remote)
+    } else {
+        None
+    };
+    let config = config_gen::build_config(local, remote.as_ref());
+    let config_content = config_gen::render(&config, format)?;
 
     if dry_run {
         println!("\n--- Generated Configuration (Preview) ---");
@@ -516,42 +2308,3 @@ async fn config_gen_command(
 
     Ok(())
 }
-
-#[allow(unused)]
-async fn generate_config_content(
-    source: &str,
-    server: &str,
-    format: &str,
-) -> anyhow::Result<String> {
-    // TODO: 实现真正的配置生成逻辑
-    // 目前返回模板配置
-
-    match format {
-        "ts" => Ok(r#"// Auto-generated by Aether CLI
-// Run: aether gen config --source remote --server localhost:7233
-
-export default {
-  name: 'my-workflow',
-  services: {},
-  scan: {
-    workflows: './src/workflows/**/*.{ts,js}',
-    steps: './src/steps/**/*.{ts,js}',
-    activities: './src/activities/**/*.{ts,js}'
-  }
-} as const satisfies AetherConfig;
-"#
-        .to_string()),
-        "json" => Ok(r#"{
-  "name": "my-workflow",
-  "services": {},
-  "scan": {
-    "workflows": "./src/workflows/**/*.{ts,js}",
-    "steps": "./src/steps/**/*.{ts,js}",
-    "activities": "./src/activities/**/*.{ts,js}"
-  }
-}
-"#
-        .to_string()),
-        _ => Err(anyhow::anyhow!("Unknown format: {}", format)),
-    }
-}
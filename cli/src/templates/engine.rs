@@ -0,0 +1,549 @@
+//! 模板引擎
+//!
+//! 支持的语法：
+//!
+//! - `{{ name }}`：替换为变量的值
+//! - `{{#if name}}...{{else}}...{{/if}}`：变量为真时渲染第一段，否则渲染 `{{else}}` 之后的部分
+//! - `{{#each items}}...{{else}}...{{/each}}`：对列表的每一项渲染一次，列表为空时渲染 `{{else}}` 之后的部分。
+//!   循环体内可以使用 `{{ this }}`、`{{ @index }}`、`{{ @first }}`、`{{ @last }}`，列表项为对象时可以直接使用其字段
+//! - `\{{`：输出字面量 `{{`
+//!
+//! 块标签单独占一行时，整行（含换行符）不会出现在输出中。
+//! 使用未定义的变量时渲染失败，并指出所在的行。
+//! `{{` 与 `}}` 之间不是变量名或块标签时按原样输出。
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// 模板变量的值
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplateValue {
+    String(String),
+    Bool(bool),
+    List(Vec<TemplateValue>),
+    Map(BTreeMap<String, TemplateValue>),
+}
+
+impl TemplateValue {
+    /// 非空字符串、`true`、非空列表和非空对象为真
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            TemplateValue::String(s) => !s.is_empty(),
+            TemplateValue::Bool(b) => *b,
+            TemplateValue::List(items) => !items.is_empty(),
+            TemplateValue::Map(fields) => !fields.is_empty(),
+        }
+    }
+}
+
+impl From<&str> for TemplateValue {
+    fn from(value: &str) -> Self {
+        TemplateValue::String(value.to_string())
+    }
+}
+
+impl From<String> for TemplateValue {
+    fn from(value: String) -> Self {
+        TemplateValue::String(value)
+    }
+}
+
+impl From<bool> for TemplateValue {
+    fn from(value: bool) -> Self {
+        TemplateValue::Bool(value)
+    }
+}
+
+impl<T: Into<TemplateValue>> From<Vec<T>> for TemplateValue {
+    fn from(items: Vec<T>) -> Self {
+        TemplateValue::List(items.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<BTreeMap<String, TemplateValue>> for TemplateValue {
+    fn from(fields: BTreeMap<String, TemplateValue>) -> Self {
+        TemplateValue::Map(fields)
+    }
+}
+
+/// 模板渲染错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateError {
+    /// 出错的行号，从 1 开始
+    pub line: usize,
+    pub message: String,
+}
+
+impl TemplateError {
+    fn new(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tag {
+    Var(String),
+    If(String),
+    Each(String),
+    Else,
+    EndIf,
+    EndEach,
+}
+
+impl Tag {
+    /// 解析 `{{` 与 `}}` 之间的内容，不是模板标签时返回 `None`
+    fn parse(inner: &str) -> Option<Result<Tag, String>> {
+        let (keyword, arg) = inner
+            .split_once(char::is_whitespace)
+            .map_or((inner, ""), |(keyword, arg)| (keyword, arg.trim()));
+        let block = |make: fn(String) -> Tag| {
+            if is_name(arg) {
+                Ok(make(arg.to_string()))
+            } else {
+                Err(format!(
+                    "expected a variable name after {{{{{}}}}}",
+                    keyword
+                ))
+            }
+        };
+
+        match keyword {
+            "#if" => return Some(block(Tag::If)),
+            "#each" => return Some(block(Tag::Each)),
+            _ => {}
+        }
+        match inner {
+            "else" => Some(Ok(Tag::Else)),
+            "/if" => Some(Ok(Tag::EndIf)),
+            "/each" => Some(Ok(Tag::EndEach)),
+            _ if inner.starts_with('#') || inner.starts_with('/') => {
+                Some(Err(format!("unknown block tag {{{{{}}}}}", inner)))
+            }
+            _ if is_name(inner) => Some(Ok(Tag::Var(inner.to_string()))),
+            _ => None,
+        }
+    }
+
+    fn is_block(&self) -> bool {
+        !matches!(self, Tag::Var(_))
+    }
+}
+
+fn is_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '@' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+enum Token {
+    Text(String),
+    Tag(Tag, usize),
+}
+
+/// 把模板切分为文本和标签，去掉单独占一行的块标签所在的整行
+fn tokenize(content: &str) -> Result<Vec<Token>, TemplateError> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut pos = 0;
+    let mut line = 1;
+    let mut counted = 0;
+
+    while let Some(found) = content[pos..].find("{{") {
+        let start = pos + found;
+        if content[..start].ends_with('\\') {
+            text.push_str(&content[pos..start - 1]);
+            text.push_str("{{");
+            pos = start + 2;
+            continue;
+        }
+        let Some(len) = content[start + 2..].find("}}") else {
+            break;
+        };
+        let end = start + 2 + len + 2;
+
+        line += content[counted..start].matches('\n').count();
+        counted = start;
+        let tag = match Tag::parse(content[start + 2..end - 2].trim()) {
+            None => {
+                text.push_str(&content[pos..start + 2]);
+                pos = start + 2;
+                continue;
+            }
+            Some(tag) => tag.map_err(|message| TemplateError::new(line, message))?,
+        };
+
+        text.push_str(&content[pos..start]);
+        pos = end;
+        if tag.is_block() {
+            let line_start = content[..start].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = content[end..]
+                .find('\n')
+                .map_or(content.len(), |i| end + i + 1);
+            if content[line_start..start].trim().is_empty()
+                && content[end..line_end].trim().is_empty()
+            {
+                text.truncate(text.len() - (start - line_start));
+                pos = line_end;
+            }
+        }
+        if !text.is_empty() {
+            tokens.push(Token::Text(std::mem::take(&mut text)));
+        }
+        tokens.push(Token::Tag(tag, line));
+    }
+
+    text.push_str(&content[pos..]);
+    if !text.is_empty() {
+        tokens.push(Token::Text(text));
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug)]
+enum Node {
+    Text(String),
+    Var {
+        name: String,
+        line: usize,
+    },
+    If {
+        name: String,
+        line: usize,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    Each {
+        name: String,
+        line: usize,
+        body: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+/// 块的结束位置
+enum Terminator {
+    Eof,
+    Else(usize),
+    EndIf(usize),
+    EndEach(usize),
+}
+
+fn parse_nodes(
+    tokens: &mut std::vec::IntoIter<Token>,
+) -> Result<(Vec<Node>, Terminator), TemplateError> {
+    let mut nodes = Vec::new();
+    while let Some(token) = tokens.next() {
+        let (tag, line) = match token {
+            Token::Text(text) => {
+                nodes.push(Node::Text(text));
+                continue;
+            }
+            Token::Tag(tag, line) => (tag, line),
+        };
+        match tag {
+            Tag::Var(name) => nodes.push(Node::Var { name, line }),
+            Tag::If(name) => {
+                let (then, otherwise) = parse_block(tokens, "if", line)?;
+                nodes.push(Node::If {
+                    name,
+                    line,
+                    then,
+                    otherwise,
+                });
+            }
+            Tag::Each(name) => {
+                let (body, otherwise) = parse_block(tokens, "each", line)?;
+                nodes.push(Node::Each {
+                    name,
+                    line,
+                    body,
+                    otherwise,
+                });
+            }
+            Tag::Else => return Ok((nodes, Terminator::Else(line))),
+            Tag::EndIf => return Ok((nodes, Terminator::EndIf(line))),
+            Tag::EndEach => return Ok((nodes, Terminator::EndEach(line))),
+        }
+    }
+    Ok((nodes, Terminator::Eof))
+}
+
+/// 解析 `{{#if}}` / `{{#each}}` 的内容直到对应的结束标签，返回主体和 `{{else}}` 部分
+fn parse_block(
+    tokens: &mut std::vec::IntoIter<Token>,
+    keyword: &str,
+    open_line: usize,
+) -> Result<(Vec<Node>, Vec<Node>), TemplateError> {
+    let (body, end) = parse_nodes(tokens)?;
+    let (otherwise, end) = match end {
+        Terminator::Else(_) => {
+            let (otherwise, end) = parse_nodes(tokens)?;
+            if let Terminator::Else(line) = end {
+                return Err(TemplateError::new(
+                    line,
+                    format!("duplicate {{{{else}}}} in {{{{#{}}}}}", keyword),
+                ));
+            }
+            (otherwise, end)
+        }
+        end => (Vec::new(), end),
+    };
+    match (end, keyword) {
+        (Terminator::EndIf(_), "if") | (Terminator::EndEach(_), "each") => Ok((body, otherwise)),
+        (Terminator::Eof, _) => Err(TemplateError::new(
+            open_line,
+            format!("{{{{#{}}}}} is never closed", keyword),
+        )),
+        (Terminator::EndIf(line) | Terminator::EndEach(line) | Terminator::Else(line), _) => {
+            Err(TemplateError::new(
+                line,
+                format!(
+                    "expected {{{{/{}}}}} to close the block opened on line {}",
+                    keyword, open_line
+                ),
+            ))
+        }
+    }
+}
+
+/// 变量作用域：全局变量和外层循环的当前项
+struct Scope<'a> {
+    globals: &'a BTreeMap<String, TemplateValue>,
+    frames: Vec<BTreeMap<String, TemplateValue>>,
+}
+
+impl Scope<'_> {
+    fn lookup(&self, name: &str, line: usize) -> Result<&TemplateValue, TemplateError> {
+        self.frames
+            .iter()
+            .rev()
+            .find_map(|frame| frame.get(name))
+            .or_else(|| self.globals.get(name))
+            .ok_or_else(|| TemplateError::new(line, format!("unknown variable `{}`", name)))
+    }
+
+    fn render(&mut self, nodes: &[Node], out: &mut String) -> Result<(), TemplateError> {
+        for node in nodes {
+            match node {
+                Node::Text(text) => out.push_str(text),
+                Node::Var { name, line } => match self.lookup(name, *line)? {
+                    TemplateValue::String(value) => out.push_str(value),
+                    TemplateValue::Bool(value) => {
+                        out.push_str(if *value { "true" } else { "false" })
+                    }
+                    _ => {
+                        return Err(TemplateError::new(
+                            *line,
+                            format!(
+                                "`{}` is a list or object and cannot be inserted as text",
+                                name
+                            ),
+                        ))
+                    }
+                },
+                Node::If {
+                    name,
+                    line,
+                    then,
+                    otherwise,
+                } => {
+                    let branch = if self.lookup(name, *line)?.is_truthy() {
+                        then
+                    } else {
+                        otherwise
+                    };
+                    self.render(branch, out)?;
+                }
+                Node::Each {
+                    name,
+                    line,
+                    body,
+                    otherwise,
+                } => {
+                    let items = match self.lookup(name, *line)? {
+                        TemplateValue::List(items) => items.clone(),
+                        _ => {
+                            return Err(TemplateError::new(
+                                *line,
+                                format!("`{}` is not a list", name),
+                            ))
+                        }
+                    };
+                    if items.is_empty() {
+                        self.render(otherwise, out)?;
+                    }
+                    let count = items.len();
+                    for (index, item) in items.into_iter().enumerate() {
+                        let mut frame = match &item {
+                            TemplateValue::Map(fields) => fields.clone(),
+                            _ => BTreeMap::new(),
+                        };
+                        frame.insert("this".to_string(), item);
+                        frame.insert("@index".to_string(), index.to_string().into());
+                        frame.insert("@first".to_string(), (index == 0).into());
+                        frame.insert("@last".to_string(), (index + 1 == count).into());
+                        self.frames.push(frame);
+                        let result = self.render(body, out);
+                        self.frames.pop();
+                        result?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 用 `values` 渲染模板
+pub fn render(
+    content: &str,
+    values: &BTreeMap<String, TemplateValue>,
+) -> Result<String, TemplateError> {
+    let mut tokens = tokenize(content)?.into_iter();
+    let nodes = match parse_nodes(&mut tokens)? {
+        (nodes, Terminator::Eof) => nodes,
+        (_, Terminator::Else(line)) => {
+            return Err(TemplateError::new(line, "{{else}} outside a block"))
+        }
+        (_, Terminator::EndIf(line)) => {
+            return Err(TemplateError::new(
+                line,
+                "{{/if}} without a matching {{#if}}",
+            ))
+        }
+        (_, Terminator::EndEach(line)) => {
+            return Err(TemplateError::new(
+                line,
+                "{{/each}} without a matching {{#each}}",
+            ))
+        }
+    };
+
+    let mut out = String::with_capacity(content.len());
+    Scope {
+        globals: values,
+        frames: Vec::new(),
+    }
+    .render(&nodes, &mut out)?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(pairs: Vec<(&str, TemplateValue)>) -> BTreeMap<String, TemplateValue> {
+        pairs
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect()
+    }
+
+    #[test]
+    fn test_variables_with_and_without_spaces() {
+        let values = values(vec![("name", "demo".into())]);
+        assert_eq!(
+            render("a {{ name }} b {{name}}", &values).unwrap(),
+            "a demo b demo"
+        );
+    }
+
+    #[test]
+    fn test_if_else_and_standalone_lines() {
+        let template = "start\n{{#if docker}}\nFROM node\n{{else}}\nno docker\n{{/if}}\nend\n";
+        let with = values(vec![("docker", true.into())]);
+        assert_eq!(render(template, &with).unwrap(), "start\nFROM node\nend\n");
+        let without = values(vec![("docker", false.into())]);
+        assert_eq!(
+            render(template, &without).unwrap(),
+            "start\nno docker\nend\n"
+        );
+
+        // 行内的块标签保留周围的文本
+        let inline = values(vec![("x", "".into())]);
+        assert_eq!(
+            render("a {{#if x}}yes{{else}}no{{/if}} b", &inline).unwrap(),
+            "a no b"
+        );
+    }
+
+    #[test]
+    fn test_each_over_strings_and_objects() {
+        let steps: Vec<TemplateValue> = ["fetch", "store"].into_iter().map(Into::into).collect();
+        let list = values(vec![("steps", TemplateValue::List(steps))]);
+        let template =
+            "{{#each steps}}\n{{ @index }}: {{ this }}{{#if @last}}.{{else}},{{/if}}\n{{/each}}\n";
+        assert_eq!(render(template, &list).unwrap(), "0: fetch,\n1: store.\n");
+
+        let item =
+            |name: &str| TemplateValue::Map(BTreeMap::from([("name".to_string(), name.into())]));
+        let mut objects = BTreeMap::new();
+        objects.insert("project".to_string(), "demo".into());
+        objects.insert(
+            "steps".to_string(),
+            TemplateValue::List(vec![item("a"), item("b")]),
+        );
+        assert_eq!(
+            render(
+                "{{#each steps}}{{ project }}.{{ name }} {{/each}}",
+                &objects
+            )
+            .unwrap(),
+            "demo.a demo.b "
+        );
+
+        let empty = values(vec![("steps", TemplateValue::List(Vec::new()))]);
+        assert_eq!(
+            render("{{#each steps}}x{{else}}none{{/each}}", &empty).unwrap(),
+            "none"
+        );
+    }
+
+    #[test]
+    fn test_unknown_variables_fail_with_line() {
+        let values = values(vec![("name", "demo".into())]);
+        let err = render("ok {{ name }}\n\nbad {{ typo }}\n", &values).unwrap_err();
+        assert_eq!(err.line, 3);
+        assert!(err.message.contains("`typo`"));
+
+        let err = render("{{#if missing}}x{{/if}}", &values).unwrap_err();
+        assert_eq!(err, TemplateError::new(1, "unknown variable `missing`"));
+    }
+
+    #[test]
+    fn test_unbalanced_blocks() {
+        let values = values(vec![("x", true.into())]);
+        let err = render("a\n{{#if x}}\nb\n", &values).unwrap_err();
+        assert_eq!(err.line, 2);
+        assert!(err.message.contains("never closed"));
+
+        let err = render("{{#if x}}\n{{/each}}\n", &values).unwrap_err();
+        assert_eq!(err.line, 2);
+        assert!(render("{{/if}}", &values).is_err());
+        assert!(render("{{#unless x}}{{/unless}}", &values).is_err());
+    }
+
+    #[test]
+    fn test_non_tags_and_escapes_are_literal() {
+        let values = BTreeMap::new();
+        let content = "style={{ color: 'red' }} and \\{{ name }} and {{ unterminated";
+        assert_eq!(
+            render(content, &values).unwrap(),
+            "style={{ color: 'red' }} and {{ name }} and {{ unterminated"
+        );
+    }
+}
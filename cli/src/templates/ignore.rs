@@ -0,0 +1,146 @@
+//! 模板的 `.aetherignore`
+//!
+//! 模板根目录下的 `.aetherignore` 列出不输出到项目中的文件，每行一个模式：
+//!
+//! - 空行和以 `#` 开头的行被忽略
+//! - `*` 匹配路径段中的任意字符，`?` 匹配单个字符
+//! - 不含 `/` 的模式匹配任意层级的文件名或目录名，如 `*.orig`
+//! - 含 `/` 的模式从模板根目录开始匹配，如 `docs/draft.md`
+//! - 以 `/` 结尾的模式只匹配目录，目录下的所有文件都被排除
+//!
+//! 模式匹配的是模板中的路径（渲染文件名中的变量之前）。
+
+/// 模板中忽略规则文件的路径
+pub const IGNORE_FILE: &str = ".aetherignore";
+
+/// 一条忽略模式
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Pattern {
+    /// 按 `/` 切分的路径段
+    segments: Vec<String>,
+    /// 是否从模板根目录开始匹配
+    anchored: bool,
+    /// 是否只匹配目录
+    dir_only: bool,
+}
+
+/// `.aetherignore` 中的所有模式
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IgnoreRules {
+    patterns: Vec<Pattern>,
+}
+
+impl IgnoreRules {
+    /// 解析 `.aetherignore` 的内容
+    pub fn parse(content: &str) -> Self {
+        let patterns = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let dir_only = line.ends_with('/');
+                let line = line.trim_end_matches('/');
+                let anchored = line.contains('/');
+                Pattern {
+                    segments: line
+                        .trim_start_matches('/')
+                        .split('/')
+                        .map(str::to_string)
+                        .collect(),
+                    anchored,
+                    dir_only,
+                }
+            })
+            .collect();
+        Self { patterns }
+    }
+
+    /// 模板中以 `/` 分隔的文件路径是否被忽略
+    pub fn is_ignored(&self, path: &str) -> bool {
+        let segments: Vec<&str> = path.split('/').collect();
+        self.patterns.iter().any(|pattern| {
+            // 文件本身是最后一段；只匹配目录的模式不能以最后一段结束
+            let last = if pattern.dir_only {
+                segments.len() - 1
+            } else {
+                segments.len()
+            };
+            let starts: Vec<usize> = if pattern.anchored {
+                vec![0]
+            } else {
+                (0..segments.len()).collect()
+            };
+            starts.into_iter().any(|start| {
+                let end = start + pattern.segments.len();
+                end <= last
+                    && pattern
+                        .segments
+                        .iter()
+                        .zip(&segments[start..end])
+                        .all(|(pattern, segment)| glob_match(pattern, segment))
+            })
+        })
+    }
+}
+
+/// 匹配单个路径段，支持 `*` 和 `?`
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    // 回溯到上一个 `*` 时的位置
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.orig", "main.py.orig"));
+        assert!(glob_match("step-?.ts", "step-1.ts"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("*.orig", "main.py"));
+        assert!(!glob_match("step-?.ts", "step-10.ts"));
+    }
+
+    #[test]
+    fn test_ignore_patterns() {
+        let rules = IgnoreRules::parse(
+            "# 模板开发用的文件\n\n*.orig\ndocs/draft.md\nfixtures/\n/notes.txt\n",
+        );
+        assert!(rules.is_ignored("src/main.py.orig"));
+        assert!(rules.is_ignored("docs/draft.md"));
+        assert!(rules.is_ignored("fixtures/input.json"));
+        assert!(rules.is_ignored("src/fixtures/deep/input.json"));
+        assert!(rules.is_ignored("notes.txt"));
+
+        assert!(!rules.is_ignored("src/docs/draft.md"));
+        assert!(!rules.is_ignored("src/notes.txt"));
+        // `fixtures/` 只匹配目录
+        assert!(!rules.is_ignored("fixtures"));
+        assert!(!rules.is_ignored("src/main.py"));
+    }
+}
@@ -0,0 +1,60 @@
+//! 模板的 `template.toml`
+//!
+//! 声明 `aether init --install` 运行的安装命令和生成项目后提示的后续命令。
+//! 文件内容先按模板渲染（可以使用 `{{ install_command }}` 等变量）再解析，不会输出到项目中：
+//!
+//! ```toml
+//! install = "{{ install_command }}"
+//! next_steps = ["{{ install_command }}", "{{ run_command }}"]
+//! ```
+
+use serde::Deserialize;
+
+use super::{render_template, TemplateVariables};
+
+/// 模板中配置文件的路径
+pub const MANIFEST_FILE: &str = "template.toml";
+
+/// 模板配置
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TemplateManifest {
+    /// 在项目目录中安装依赖的命令，未设置时 `--install` 跳过安装
+    pub install: Option<String>,
+    /// 进入项目目录之后要执行的命令，按顺序提示
+    pub next_steps: Vec<String>,
+}
+
+impl TemplateManifest {
+    /// 渲染并解析 `template.toml` 的内容
+    pub fn parse(content: &str, vars: &TemplateVariables) -> Result<Self, String> {
+        let rendered = render_template(content, vars).map_err(|err| err.to_string())?;
+        toml::from_str(&rendered).map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_renders_variables() {
+        let mut vars = TemplateVariables::new("demo");
+        vars.set("install_command", "uv sync")
+            .set("run_command", "uv run src/main.py");
+        let manifest = TemplateManifest::parse(
+            "install = \"{{ install_command }}\"\nnext_steps = [\"{{ run_command }}\"]\n",
+            &vars,
+        )
+        .unwrap();
+        assert_eq!(manifest.install.as_deref(), Some("uv sync"));
+        assert_eq!(manifest.next_steps, vec!["uv run src/main.py"]);
+
+        assert_eq!(
+            TemplateManifest::parse("", &vars).unwrap(),
+            TemplateManifest::default()
+        );
+        let err = TemplateManifest::parse("instal = \"npm i\"", &vars).unwrap_err();
+        assert!(err.contains("unknown field `instal`"), "{}", err);
+    }
+}
@@ -1,18 +1,33 @@
 //! 模板渲染模块
 //!
-//! 支持从模板目录渲染项目文件，处理变量替换。
+//! 从嵌入的模板（或 `AETHER_TEMPLATES_DIR`）渲染项目文件，模板语法见 [`engine`]。
+//! 文件名和目录名中的变量同样会被替换，如 `{{ workflow_name_snake }}.py`；
+//! 模板中 `.aetherignore` 列出的文件不会输出，见 [`ignore`]；
+//! `template.toml` 声明安装命令和后续步骤，见 [`manifest`]。
+
+pub mod engine;
+pub mod ignore;
+pub mod manifest;
+pub mod source;
 
 use anyhow::{Context, Result};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use tokio::fs;
 
+pub use engine::{TemplateError, TemplateValue};
+pub use ignore::IgnoreRules;
+pub use manifest::TemplateManifest;
+pub use source::{TemplateFile, TemplateSource};
+
 /// 支持的模板类型
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TemplateType {
     TypeScript,
     NestJS,
     Python,
+    Rust,
 }
 
 impl FromStr for TemplateType {
@@ -23,8 +38,9 @@ impl FromStr for TemplateType {
             "ts" | "typescript" => Ok(TemplateType::TypeScript),
             "nestjs" | "nest" => Ok(TemplateType::NestJS),
             "py" | "python" => Ok(TemplateType::Python),
+            "rs" | "rust" => Ok(TemplateType::Rust),
             _ => Err(anyhow::anyhow!(
-                "Unknown template type: {}. Supported types: ts, nestjs, python",
+                "Unknown template type: {}. Supported types: ts, nestjs, python, rust",
                 s
             )),
         }
@@ -38,32 +54,51 @@ impl TemplateType {
             TemplateType::TypeScript => "typescript",
             TemplateType::NestJS => "nestjs",
             TemplateType::Python => "python",
+            TemplateType::Rust => "rust",
         }
     }
 }
 
-/// 模板变量
-#[derive(Debug, Clone)]
+/// 模板变量，按名称保存
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct TemplateVariables {
-    /// 项目名称
-    pub project_name: String,
-    /// 工作流名称（camelCase）
-    pub workflow_name: String,
-    /// 工作流名称（snake_case）
-    pub workflow_name_snake: String,
-    /// 输入类型
-    pub input_type: String,
+    values: BTreeMap<String, TemplateValue>,
 }
 
 impl TemplateVariables {
-    /// 从项目名称创建默认变量
+    /// 从项目名称创建默认变量：
+    ///
+    /// - `project_name`：项目名称
+    /// - `workflow_name`：工作流名称（camelCase）
+    /// - `workflow_name_snake`：工作流名称（snake_case）
+    /// - `input_type`：输入类型
+    /// - `workflow_filename`：工作流文件名（不含扩展名）
+    /// - `crate_name`：Cargo 包名（snake_case）
+    /// - `aether_version`：当前 CLI 的版本，生成的项目依赖同一版本的 Aether 包
     pub fn new(project_name: &str) -> Self {
-        Self {
-            project_name: project_name.to_string(),
-            workflow_name: to_camel_case(project_name),
-            workflow_name_snake: to_snake_case(project_name),
-            input_type: format!("{}Input", to_pascal_case(project_name)),
-        }
+        let mut vars = Self::default();
+        vars.set("project_name", project_name)
+            .set("workflow_name", to_camel_case(project_name))
+            .set("workflow_name_snake", to_snake_case(project_name))
+            .set(
+                "input_type",
+                format!("{}Input", to_pascal_case(project_name)),
+            )
+            .set("workflow_filename", "workflow")
+            .set("crate_name", to_snake_case(project_name))
+            .set("aether_version", env!("CARGO_PKG_VERSION"));
+        vars
+    }
+
+    /// 设置变量，已存在时覆盖
+    pub fn set(&mut self, name: &str, value: impl Into<TemplateValue>) -> &mut Self {
+        self.values.insert(name.to_string(), value.into());
+        self
+    }
+
+    /// 获取变量
+    pub fn get(&self, name: &str) -> Option<&TemplateValue> {
+        self.values.get(name)
     }
 }
 
@@ -107,11 +142,11 @@ fn to_pascal_case(s: &str) -> String {
 }
 
 /// 将字符串转换为 snake_case
-fn to_snake_case(s: &str) -> String {
+pub(crate) fn to_snake_case(s: &str) -> String {
     let mut result = String::new();
     for c in s.chars() {
         if c.is_uppercase() {
-            if !result.is_empty() {
+            if !result.is_empty() && !result.ends_with('_') {
                 result.push('_');
             }
             result.push(c.to_ascii_lowercase());
@@ -124,96 +159,137 @@ fn to_snake_case(s: &str) -> String {
     result
 }
 
-/// 渲染模板字符串，替换所有变量
-pub fn render_template(content: &str, vars: &TemplateVariables) -> String {
-    let mut result = content.to_string();
-
-    // 替换项目名称
-    result = result.replace("{{ project_name }}", &vars.project_name);
-
-    // 替换工作流名称（camelCase）
-    result = result.replace("{{ workflow_name }}", &vars.workflow_name);
-
-    // 替换工作流名称（snake_case）
-    result = result.replace("{{ workflow_name_snake }}", &vars.workflow_name_snake);
-
-    // 替换输入类型
-    result = result.replace("{{ input_type }}", &vars.input_type);
-
-    result
-}
-
-/// 获取模板目录路径
-pub fn get_template_dir(template_type: TemplateType, cli_root: &Path) -> PathBuf {
-    cli_root.join("templates").join(template_type.dir_name())
+/// 渲染模板字符串，使用未定义的变量或块标签不匹配时返回出错的行
+pub fn render_template(content: &str, vars: &TemplateVariables) -> Result<String, TemplateError> {
+    engine::render(content, &vars.values)
 }
 
-/// 渲染模板目录中的所有文件
+/// 渲染模板的所有文件并返回模板配置，模板来源见 [`TemplateSource::from_env`]
 ///
 /// # Arguments
 ///
 /// * `template_type` - 模板类型
-/// * `cli_root` - CLI 根目录
 /// * `output_dir` - 输出目录
 /// * `vars` - 模板变量
 pub async fn render_template_dir(
     template_type: TemplateType,
-    cli_root: &Path,
     output_dir: &Path,
     vars: &TemplateVariables,
-) -> Result<()> {
-    let template_dir = get_template_dir(template_type, cli_root);
+) -> Result<TemplateManifest> {
+    render_template_from(&TemplateSource::from_env(), template_type, output_dir, vars).await
+}
+
+/// 从指定来源渲染模板的所有文件，返回模板配置；模板没有 `template.toml` 时返回默认配置
+pub async fn render_template_from(
+    source: &TemplateSource,
+    template_type: TemplateType,
+    output_dir: &Path,
+    vars: &TemplateVariables,
+) -> Result<TemplateManifest> {
+    let mut files = source.files(template_type).await?;
+    let manifest = match files
+        .iter()
+        .position(|file| file.path == manifest::MANIFEST_FILE)
+    {
+        Some(index) => {
+            let file = files.remove(index);
+            TemplateManifest::parse(&String::from_utf8_lossy(&file.data), vars).map_err(
+                |message| {
+                    anyhow::anyhow!(
+                        "{}/{}: {}",
+                        template_type.dir_name(),
+                        manifest::MANIFEST_FILE,
+                        message
+                    )
+                },
+            )?
+        }
+        None => TemplateManifest::default(),
+    };
+    let rules = match files
+        .iter()
+        .position(|file| file.path == ignore::IGNORE_FILE)
+    {
+        Some(index) => {
+            let file = files.remove(index);
+            IgnoreRules::parse(&String::from_utf8_lossy(&file.data))
+        }
+        None => IgnoreRules::default(),
+    };
+    files.retain(|file| !rules.is_ignored(&file.path));
 
-    if !template_dir.exists() {
-        return Err(anyhow::anyhow!(
-            "Template directory not found: {:?}",
-            template_dir
-        ));
+    fs::create_dir_all(output_dir).await?;
+    for file in &files {
+        render_file(template_type, file, output_dir, vars).await?;
     }
 
-    // 遍历模板目录
-    render_directory(&template_dir, output_dir, vars).await?;
+    Ok(manifest)
+}
 
-    Ok(())
+/// 原样复制、不渲染的文件：lock 文件由包管理器生成，其中的 `{{` 不是模板语法
+fn is_verbatim(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.ends_with(".lock") || matches!(name, "package-lock.json" | "pnpm-lock.yaml")
 }
 
-/// 递归渲染目录
-async fn render_directory(src: &Path, dst: &Path, vars: &TemplateVariables) -> Result<()> {
-    if src.is_dir() {
-        // 创建目标目录
-        fs::create_dir_all(dst).await?;
-
-        // 遍历源目录中的所有条目
-        let mut entries = fs::read_dir(src).await?;
-        while let Some(entry) = entries.next_entry().await? {
-            let src_path = entry.path();
-            let file_name = entry.file_name();
-            let dst_path = dst.join(&file_name);
-
-            if src_path.is_dir() {
-                // 递归处理子目录，使用 Box::pin 来避免无限大的 future
-                Box::pin(render_directory(&src_path, &dst_path, vars)).await?;
-            } else {
-                // 处理文件
-                render_file(&src_path, &dst_path, vars).await?;
-            }
+/// 渲染文件路径的每一段，返回相对于输出目录的路径
+///
+/// 渲染后为空、为 `.` / `..` 或含有路径分隔符的段会被拒绝，文件不能写到输出目录之外。
+fn render_path(path: &str, vars: &TemplateVariables) -> Result<PathBuf, String> {
+    let mut rendered = PathBuf::new();
+    for segment in path.split('/') {
+        let name = render_template(segment, vars).map_err(|err| err.message)?;
+        if name.is_empty()
+            || name == "."
+            || name == ".."
+            || name.contains(['/', '\\'])
+            || Path::new(&name).is_absolute()
+        {
+            return Err(format!(
+                "{:?} renders to the invalid name {:?}",
+                segment, name
+            ));
         }
+        rendered.push(name);
     }
-    Ok(())
+    Ok(rendered)
 }
 
-/// 渲染单个文件
-async fn render_file(src: &Path, dst: &Path, vars: &TemplateVariables) -> Result<()> {
-    // 读取源文件内容
-    let content = fs::read_to_string(src)
-        .await
-        .with_context(|| format!("Failed to read template file: {:?}", src))?;
+/// 渲染单个文件，二进制文件（非 UTF-8）和 lock 文件按字节原样复制
+async fn render_file(
+    template_type: TemplateType,
+    file: &TemplateFile,
+    output_dir: &Path,
+    vars: &TemplateVariables,
+) -> Result<()> {
+    let relative = render_path(&file.path, vars).map_err(|message| {
+        anyhow::anyhow!("{}/{}: {}", template_type.dir_name(), file.path, message)
+    })?;
+    let dst = output_dir.join(relative);
+    if let Some(parent) = dst.parent() {
+        fs::create_dir_all(parent).await?;
+    }
 
-    // 渲染模板
-    let rendered = render_template(&content, vars);
+    let content = match std::str::from_utf8(&file.data) {
+        Ok(text) if !is_verbatim(&file.path) => {
+            // 渲染模板
+            render_template(text, vars)
+                .map_err(|err| {
+                    anyhow::anyhow!(
+                        "{}/{}:{}: {}",
+                        template_type.dir_name(),
+                        file.path,
+                        err.line,
+                        err.message
+                    )
+                })?
+                .into_bytes()
+        }
+        _ => file.data.clone(),
+    };
 
     // 写入目标文件
-    fs::write(dst, rendered)
+    fs::write(&dst, content)
         .await
         .with_context(|| format!("Failed to write rendered file: {:?}", dst))?;
 
@@ -244,16 +320,23 @@ mod tests {
         assert_eq!(to_snake_case("helloWorld"), "hello_world");
         assert_eq!(to_snake_case("HelloWorld"), "hello_world");
         assert_eq!(to_snake_case("myProjectName"), "my_project_name");
+        assert_eq!(to_snake_case("Order-Service"), "order_service");
     }
 
     #[test]
     fn test_template_variables() {
         let vars = TemplateVariables::new("my-awesome-project");
 
-        assert_eq!(vars.project_name, "my-awesome-project");
-        assert_eq!(vars.workflow_name, "myAwesomeProject");
-        assert_eq!(vars.workflow_name_snake, "my_awesome_project");
-        assert_eq!(vars.input_type, "MyAwesomeProjectInput");
+        let get = |name| vars.get(name).cloned();
+        assert_eq!(get("project_name"), Some("my-awesome-project".into()));
+        assert_eq!(get("workflow_name"), Some("myAwesomeProject".into()));
+        assert_eq!(
+            get("workflow_name_snake"),
+            Some("my_awesome_project".into())
+        );
+        assert_eq!(get("input_type"), Some("MyAwesomeProjectInput".into()));
+        assert_eq!(get("crate_name"), Some("my_awesome_project".into()));
+        assert_eq!(get("unknown"), None);
     }
 
     #[test]
@@ -267,7 +350,7 @@ snake: {{ workflow_name_snake }}
 input: {{ input_type }}
 "#;
 
-        let rendered = render_template(content, &vars);
+        let rendered = render_template(content, &vars).unwrap();
 
         assert!(rendered.contains("name: my-project"));
         assert!(rendered.contains("workflow: myProject"));
@@ -275,6 +358,167 @@ input: {{ input_type }}
         assert!(rendered.contains("input: MyProjectInput"));
     }
 
+    #[tokio::test]
+    async fn test_bundled_templates_render_with_every_option() {
+        use crate::init::{Example, InitOptions};
+
+        for template_type in [
+            TemplateType::TypeScript,
+            TemplateType::NestJS,
+            TemplateType::Python,
+            TemplateType::Rust,
+        ] {
+            let files = TemplateSource::Embedded.files(template_type).await.unwrap();
+            for &package_manager in template_type.package_managers() {
+                for example in [Example::Minimal, Example::Full] {
+                    let vars = InitOptions {
+                        name: "my-project".to_string(),
+                        template: template_type,
+                        package_manager,
+                        server: "aether.example.com:7233".to_string(),
+                        example,
+                    }
+                    .variables();
+                    let manifest = files
+                        .iter()
+                        .find(|file| file.path == manifest::MANIFEST_FILE)
+                        .map(|file| {
+                            TemplateManifest::parse(std::str::from_utf8(&file.data).unwrap(), &vars)
+                                .unwrap()
+                        })
+                        .unwrap();
+                    assert_eq!(
+                        manifest.install.as_deref(),
+                        Some(package_manager.install_command())
+                    );
+                    assert!(!manifest.next_steps.is_empty());
+                    for file in &files {
+                        render_path(&file.path, &vars).unwrap();
+                        let content = std::str::from_utf8(&file.data).unwrap();
+                        let rendered = render_template(content, &vars)
+                            .unwrap_or_else(|err| panic!("{}: {}", file.path, err));
+                        assert!(!rendered.contains("{{"), "{}", file.path);
+                        assert!(!rendered.contains("localhost:7233"), "{}", file.path);
+                    }
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_render_errors_name_file_and_line() {
+        let root = tempfile::tempdir().unwrap();
+        let template_dir = root.path().join("templates").join("python");
+        std::fs::create_dir_all(&template_dir).unwrap();
+        std::fs::write(
+            template_dir.join("main.py"),
+            "# {{ project_name }}\nprint({{ workflow_nmae }})\n",
+        )
+        .unwrap();
+
+        let source = TemplateSource::Dir(root.path().join("templates"));
+        let output = root.path().join("out");
+        let vars = TemplateVariables::new("demo");
+        let err = render_template_from(&source, TemplateType::Python, &output, &vars)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("python/main.py:2:"), "{}", err);
+        assert!(err.contains("`workflow_nmae`"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_binary_and_lock_files_are_copied_verbatim() {
+        let root = tempfile::tempdir().unwrap();
+        let template_dir = root.path().join("python");
+        std::fs::create_dir_all(template_dir.join("assets")).unwrap();
+        let image = [0x89, b'P', b'N', b'G', 0xff, 0xfe, b'{', b'{'];
+        std::fs::write(template_dir.join("assets/logo.png"), image).unwrap();
+        std::fs::write(template_dir.join("uv.lock"), "{{ not_a_variable }}").unwrap();
+        std::fs::write(template_dir.join("README.md"), "# {{ project_name }}").unwrap();
+
+        let source = TemplateSource::Dir(root.path().to_path_buf());
+        let output = root.path().join("out");
+        let vars = TemplateVariables::new("demo");
+        render_template_from(&source, TemplateType::Python, &output, &vars)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            std::fs::read(output.join("assets/logo.png")).unwrap(),
+            image
+        );
+        assert_eq!(
+            std::fs::read_to_string(output.join("uv.lock")).unwrap(),
+            "{{ not_a_variable }}"
+        );
+        assert_eq!(
+            std::fs::read_to_string(output.join("README.md")).unwrap(),
+            "# demo"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_variables_in_file_and_directory_names() {
+        let root = tempfile::tempdir().unwrap();
+        let template_dir = root.path().join("python");
+        let package_dir = template_dir.join("src").join("{{ workflow_name_snake }}");
+        std::fs::create_dir_all(&package_dir).unwrap();
+        std::fs::write(package_dir.join("__init__.py"), "").unwrap();
+        std::fs::write(
+            package_dir.join("{{ workflow_name_snake }}_steps.py"),
+            "# {{ project_name }}",
+        )
+        .unwrap();
+        std::fs::write(template_dir.join("main.py.orig"), "").unwrap();
+        std::fs::create_dir_all(template_dir.join("fixtures")).unwrap();
+        std::fs::write(template_dir.join("fixtures/input.json"), "{}").unwrap();
+        std::fs::write(
+            template_dir.join(".aetherignore"),
+            "# 只在开发模板时使用\n*.orig\nfixtures/\n",
+        )
+        .unwrap();
+
+        let source = TemplateSource::Dir(root.path().to_path_buf());
+        let output = root.path().join("out");
+        let vars = TemplateVariables::new("order-service");
+        render_template_from(&source, TemplateType::Python, &output, &vars)
+            .await
+            .unwrap();
+
+        let package = output.join("src").join("order_service");
+        assert!(package.join("__init__.py").is_file());
+        assert_eq!(
+            std::fs::read_to_string(package.join("order_service_steps.py")).unwrap(),
+            "# order-service"
+        );
+        assert!(!output.join("main.py.orig").exists());
+        assert!(!output.join("fixtures").exists());
+        assert!(!output.join(".aetherignore").exists());
+    }
+
+    #[test]
+    fn test_rendered_names_cannot_escape_output_dir() {
+        let mut vars = TemplateVariables::new("demo");
+        vars.set("parent", "..")
+            .set("nested", "a/b")
+            .set("empty", "");
+        assert_eq!(
+            render_path("src/{{ workflow_name }}.py", &vars).unwrap(),
+            Path::new("src").join("demo.py")
+        );
+        for path in [
+            "{{ parent }}/x.py",
+            "src/{{ nested }}.py",
+            "{{ empty }}/x.py",
+        ] {
+            let err = render_path(path, &vars).unwrap_err();
+            assert!(err.contains("invalid name"), "{}", err);
+        }
+        let err = render_path("{{ missing }}.py", &vars).unwrap_err();
+        assert!(err.contains("`missing`"), "{}", err);
+    }
+
     #[test]
     fn test_template_type_from_str() {
         assert_eq!(
@@ -293,6 +537,7 @@ input: {{ input_type }}
             TemplateType::from_str("python").unwrap(),
             TemplateType::Python
         );
+        assert_eq!(TemplateType::from_str("rs").unwrap(), TemplateType::Rust);
         assert!(TemplateType::from_str("unknown").is_err());
     }
 }
@@ -0,0 +1,151 @@
+//! 模板来源
+//!
+//! 模板目录在编译时通过 rust-embed 嵌入二进制文件，安装后的 `aether` 不依赖源码目录。
+//! 开发模板时可以设置 `AETHER_TEMPLATES_DIR` 从文件系统读取。
+
+use anyhow::{Context, Result};
+use rust_embed::Embed;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+use super::TemplateType;
+
+/// 指定后从该目录读取模板，目录下每个模板类型一个子目录
+pub const TEMPLATES_DIR_ENV: &str = "AETHER_TEMPLATES_DIR";
+
+/// 嵌入的项目模板
+#[derive(Embed)]
+#[folder = "templates"]
+#[prefix = ""]
+pub struct TemplateAssets;
+
+/// 模板中的一个文件
+#[derive(Debug, Clone)]
+pub struct TemplateFile {
+    /// 相对于模板目录的路径，以 `/` 分隔
+    pub path: String,
+    pub data: Vec<u8>,
+}
+
+/// 模板文件的来源
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateSource {
+    /// 嵌入二进制文件的模板
+    Embedded,
+    /// 文件系统中的模板目录
+    Dir(PathBuf),
+}
+
+impl TemplateSource {
+    /// 设置了 `AETHER_TEMPLATES_DIR` 时从该目录读取，否则使用嵌入的模板
+    pub fn from_env() -> Self {
+        match std::env::var_os(TEMPLATES_DIR_ENV) {
+            Some(dir) if !dir.is_empty() => TemplateSource::Dir(PathBuf::from(dir)),
+            _ => TemplateSource::Embedded,
+        }
+    }
+
+    /// 读取模板的所有文件，按路径排序；模板不存在时返回错误
+    pub async fn files(&self, template_type: TemplateType) -> Result<Vec<TemplateFile>> {
+        let mut files = match self {
+            TemplateSource::Embedded => {
+                let prefix = format!("{}/", template_type.dir_name());
+                TemplateAssets::iter()
+                    .filter_map(|path| {
+                        let relative = path.strip_prefix(&prefix)?.to_string();
+                        let file = TemplateAssets::get(&path)?;
+                        Some(TemplateFile {
+                            path: relative,
+                            data: file.data.into_owned(),
+                        })
+                    })
+                    .collect()
+            }
+            TemplateSource::Dir(root) => {
+                let dir = root.join(template_type.dir_name());
+                if !dir.is_dir() {
+                    return Err(anyhow::anyhow!("Template directory not found: {:?}", dir));
+                }
+                let mut files = Vec::new();
+                read_dir_files(&dir, &dir, &mut files).await?;
+                files
+            }
+        };
+
+        if files.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Template not found: {}",
+                template_type.dir_name()
+            ));
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(files)
+    }
+}
+
+/// 递归读取目录中的文件
+async fn read_dir_files(root: &Path, dir: &Path, files: &mut Vec<TemplateFile>) -> Result<()> {
+    let mut entries = fs::read_dir(dir)
+        .await
+        .with_context(|| format!("Failed to read template directory: {:?}", dir))?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.is_dir() {
+            // 使用 Box::pin 来避免无限大的 future
+            Box::pin(read_dir_files(root, &path, files)).await?;
+            continue;
+        }
+        let relative = path.strip_prefix(root)?;
+        let relative = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let data = fs::read(&path)
+            .await
+            .with_context(|| format!("Failed to read template file: {:?}", path))?;
+        files.push(TemplateFile {
+            path: relative,
+            data,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_embedded_templates_include_every_type() {
+        for template_type in [
+            TemplateType::TypeScript,
+            TemplateType::NestJS,
+            TemplateType::Python,
+            TemplateType::Rust,
+        ] {
+            let files = TemplateSource::Embedded.files(template_type).await.unwrap();
+            assert!(files.iter().any(|file| file.path == ".gitignore"));
+            // rust 模板是不含 README 的最小项目
+            let has_readme = files.iter().any(|file| file.path == "README.md");
+            assert_eq!(has_readme, template_type != TemplateType::Rust);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dir_source_reads_nested_files() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("python").join("src");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.py"), "print()").unwrap();
+
+        let source = TemplateSource::Dir(root.path().to_path_buf());
+        let files = source.files(TemplateType::Python).await.unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "src/main.py");
+        assert_eq!(files[0].data, b"print()");
+
+        let err = source.files(TemplateType::NestJS).await.unwrap_err();
+        assert!(err.to_string().contains("Template directory not found"));
+    }
+}
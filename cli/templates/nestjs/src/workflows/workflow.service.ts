@@ -3,11 +3,11 @@ import { aether } from "@aetherframework.ai/nestjs";
 
 @Injectable()
 export class WorkflowService {
-	private client = aether({ serverUrl: "http://localhost:7233" });
+	private client = aether({ serverUrl: "{{ server_url }}" });
 
 	async executeWorkflow(input: any) {
 		// TODO: Implement workflow execution logic
-		// The Aether server should be running at http://localhost:7233
+		// The Aether server should be running at {{ server_url }}
 		// Use this.client to interact with workflows
 
 		console.log("Executing workflow with input:", input);
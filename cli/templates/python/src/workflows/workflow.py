@@ -13,11 +13,33 @@ async def {{ workflow_name_snake }}(ctx: "aether.WorkflowContext", input_data: d
     Returns:
         Workflow result
     """
+{{#if example_full}}
+    # 校验输入
+    async def _validate():
+        if not isinstance(input_data, dict):
+            raise ValueError("input must be an object")
+        return input_data
+
+    validated = await ctx.step("validate", _validate)
+
+    # 处理数据
+    async def _process():
+        return {**validated, "processed": True}
+
+    processed = await ctx.step("process", _process)
+
+    # 发送通知
+    async def _notify():
+        return {"message": "Done", "data": processed}
+
+    result = await ctx.step("notify", _notify)
+{{else}}
     # TODO: 实现工作流逻辑
     async def _step1():
         return {"message": "Hello"}
 
     result = await ctx.step("step_1", _step1)
+{{/if}}
 
     return result
 
@@ -29,7 +51,7 @@ if __name__ == "__main__":
     async def main():
         print("Starting Aether workflow...")
         await aether.serve([{{ workflow_name_snake }}])
-        print("Server running at http://localhost:7233")
+        print("Server running at {{ server_url }}")
 
 
     asyncio.run(main())
@@ -0,0 +1,44 @@
+//! {{ project_name }}：连接 Aether 服务器，作为 worker 执行 `{{ workflow_name }}` 工作流的 step
+mod workflow;
+
+use aetherframework_kernel::client::AetherClient;
+use aetherframework_kernel::proto;
+use futures::StreamExt;
+
+/// 服务器的 gRPC 地址，可以用 `AETHER_GRPC_URL` 环境变量覆盖
+const GRPC_URL: &str = "{{ grpc_url }}";
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let endpoint = std::env::var("AETHER_GRPC_URL").unwrap_or_else(|_| GRPC_URL.to_string());
+    let client = AetherClient::connect(endpoint.as_str()).await?;
+
+    // 声明工作流中的每个 step，服务器把这些 step 的 task 分发给本 worker
+    let definition = workflow::definition();
+    let worker = client.worker(proto::RegisterRequest {
+        worker_id: format!("{{ project_name }}-{}", std::process::id()),
+        service_name: "{{ project_name }}".to_string(),
+        language: vec!["rust".to_string()],
+        provides: definition
+            .steps
+            .iter()
+            .map(|step| proto::ServiceResource {
+                name: step.name.clone(),
+                r#type: proto::ResourceType::Step as i32,
+                metadata: None,
+            })
+            .collect(),
+        ..Default::default()
+    });
+    println!("Worker for {} connected to {}", definition.name, endpoint);
+
+    let mut tasks = worker.tasks();
+    while let Some(task) = tasks.next().await {
+        let task = task?;
+        match workflow::run_step(&task.step_name, &task.input) {
+            Ok(output) => worker.complete(&task.task_id, output).await?,
+            Err(err) => worker.fail(&task.task_id, err.to_string()).await?,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,67 @@
+//! `{{ workflow_name }}` 工作流：定义及其 step 的实现
+use aetherframework_kernel::{StepDefinition, WorkflowDefinition};
+use serde_json::{json, Value};
+
+/// 启动工作流时使用的类型名称
+pub const WORKFLOW_TYPE: &str = "{{ workflow_name }}";
+
+/// 工作流定义，step 按顺序执行
+pub fn definition() -> WorkflowDefinition {
+    WorkflowDefinition::new(
+        WORKFLOW_TYPE,
+        vec![
+{{#if example_full}}
+            StepDefinition::new("validate"),
+            StepDefinition::new("process"),
+            StepDefinition::new("notify"),
+{{else}}
+            StepDefinition::new("step-1"),
+{{/if}}
+        ],
+    )
+}
+
+/// 执行一个 step，输入和输出都是 JSON
+///
+/// 服务器没有注册该工作流的定义时，整个工作流作为单个 `start` step 分发，这里依次执行所有 step。
+pub fn run_step(name: &str, input: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let input: Value = if input.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(input)?
+    };
+    let output = match name {
+        "start" => definition()
+            .steps
+            .iter()
+            .try_fold(input, |data, step| step_fn(&step.name, data))?,
+        _ => step_fn(name, input)?,
+    };
+    Ok(serde_json::to_vec(&output)?)
+}
+
+fn step_fn(name: &str, input: Value) -> anyhow::Result<Value> {
+    match name {
+{{#if example_full}}
+        // 校验输入
+        "validate" => {
+            if !input.is_object() {
+                anyhow::bail!("input must be an object");
+            }
+            Ok(input)
+        }
+        // 处理数据
+        "process" => {
+            let mut data = input;
+            data["processed"] = json!(true);
+            Ok(data)
+        }
+        // 发送通知
+        "notify" => Ok(json!({ "message": "Done", "data": input })),
+{{else}}
+        // TODO: 实现工作流逻辑
+        "step-1" => Ok(json!({ "message": "Hello", "input": input })),
+{{/if}}
+        _ => anyhow::bail!("unknown step: {}", name),
+    }
+}
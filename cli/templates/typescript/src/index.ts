@@ -7,7 +7,7 @@ async function main() {
   // 注册工作流并启动本地服务器
   await aether.serve([{{ workflow_name }}]);
   
-  console.log('Server running at http://localhost:7233');
+  console.log('Server running at {{ server_url }}');
 }
 
 main().catch(console.error);
@@ -1,10 +1,30 @@
 import { aether } from '@aetherframework.ai/sdk';
 
 const {{ workflow_name }} = aether.workflow('{{ workflow_name }}', async (ctx, input: {{ input_type }}) => {
+{{#if example_full}}
+  // 校验输入
+  const validated = await ctx.step('validate', async () => {
+    if (typeof input !== 'object' || input === null) {
+      throw new Error('input must be an object');
+    }
+    return input;
+  });
+
+  // 处理数据
+  const processed = await ctx.step('process', async () => {
+    return { ...validated, processed: true };
+  });
+
+  // 发送通知
+  const result = await ctx.step('notify', async () => {
+    return { message: 'Done', data: processed };
+  });
+{{else}}
   // TODO: 实现工作流逻辑
   const result = await ctx.step('step-1', async () => {
     return { message: 'Hello' };
   });
+{{/if}}
 
   return result;
 });
@@ -1,7 +1,14 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 fn main() {
+    // gRPC 代码生成，同时输出 descriptor set 供 gRPC reflection 使用
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").expect("OUT_DIR is set by cargo"));
+    tonic_build::configure()
+        .file_descriptor_set_path(out_dir.join("aether_descriptor.bin"))
+        .compile(&["proto/aether.proto"], &["proto"])
+        .expect("Failed to compile proto/aether.proto");
+
     // Dashboard 构建（仅在启用 dashboard feature 时）
     #[cfg(feature = "dashboard")]
     build_dashboard();
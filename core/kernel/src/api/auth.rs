@@ -0,0 +1,194 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request, State},
+    http::{header::AUTHORIZATION, request::Parts, HeaderName},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+use crate::api::error::ApiError;
+use crate::persistence::Persistence;
+use crate::scheduler::Scheduler;
+
+/// Header carrying the API key of client requests
+pub const API_KEY_HEADER: HeaderName = HeaderName::from_static("x-api-key");
+
+/// API keys accepted by the REST API. With no keys configured, authentication is disabled.
+#[derive(Debug, Clone, Default)]
+pub struct AuthConfig {
+    api_keys: Vec<String>,
+}
+
+impl AuthConfig {
+    pub fn new(api_keys: Vec<String>) -> Self {
+        Self {
+            api_keys: api_keys.into_iter().filter(|key| !key.is_empty()).collect(),
+        }
+    }
+
+    /// Whether requests must carry an API key
+    pub fn is_enabled(&self) -> bool {
+        !self.api_keys.is_empty()
+    }
+
+    /// Check a key against every configured key without short-circuiting
+    pub fn verify(&self, key: &str) -> bool {
+        self.api_keys.iter().fold(false, |found, expected| {
+            found | constant_time_eq(expected, key)
+        })
+    }
+}
+
+/// Compare two strings in time that depends only on their lengths
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let len = a.len().max(b.len());
+    let mut diff = a.len() ^ b.len();
+    for i in 0..len {
+        let x = a.get(i).copied().unwrap_or(0);
+        let y = b.get(i).copied().unwrap_or(0);
+        diff |= usize::from(x ^ y);
+    }
+    diff == 0
+}
+
+/// The configured API key a request was authenticated with, stored in the
+/// request extensions by [`require_api_key`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthenticatedApiKey(pub String);
+
+/// Middleware rejecting requests without a configured `X-Api-Key`
+pub async fn require_api_key(
+    State(auth): State<Arc<AuthConfig>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let api_key = request
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|key| auth.verify(key))
+        .map(|key| AuthenticatedApiKey(key.to_string()));
+    let Some(api_key) = api_key else {
+        return ApiError::unauthorized("Missing or invalid API key").into_response();
+    };
+    request.extensions_mut().insert(api_key);
+    next.run(request).await
+}
+
+/// The worker that sent a request, authenticated by the session token it
+/// received from `POST /workers`, passed as `Authorization: Bearer <token>`.
+pub struct WorkerSession(pub String);
+
+#[async_trait]
+impl<P: Persistence + 'static> FromRequestParts<Arc<Scheduler<P>>> for WorkerSession {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        scheduler: &Arc<Scheduler<P>>,
+    ) -> Result<Self, Self::Rejection> {
+        let token = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| ApiError::unauthorized("Missing bearer session token"))?;
+        authenticate(scheduler, token).await.map(WorkerSession)
+    }
+}
+
+/// Resolve a session token to the registered worker it was issued to.
+///
+/// Tokens of workers evicted for missed heartbeats are rejected.
+pub(crate) async fn authenticate<P: Persistence>(
+    scheduler: &Scheduler<P>,
+    token: &str,
+) -> Result<String, ApiError> {
+    scheduler
+        .authenticate_worker(token)
+        .await
+        .ok_or_else(|| ApiError::unauthorized("Invalid or expired session token"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::handlers::workers::register_worker;
+    use crate::api::models::RegisterWorkerRequest;
+    use crate::persistence::l0_memory::L0MemoryStore;
+    use axum::extract::State;
+    use axum::http::{Request, StatusCode};
+    use axum::Json;
+    use std::time::Duration;
+
+    async fn session(
+        scheduler: &Arc<Scheduler<Arc<L0MemoryStore>>>,
+        authorization: Option<&str>,
+    ) -> Result<String, ApiError> {
+        let mut request = Request::builder().uri("/steps/task/complete");
+        if let Some(authorization) = authorization {
+            request = request.header(AUTHORIZATION, authorization);
+        }
+        let (mut parts, _) = request.body(()).unwrap().into_parts();
+        WorkerSession::from_request_parts(&mut parts, scheduler)
+            .await
+            .map(|WorkerSession(worker_id)| worker_id)
+    }
+
+    async fn register(scheduler: &Arc<Scheduler<Arc<L0MemoryStore>>>) -> (String, String) {
+        let request = RegisterWorkerRequest {
+            service_name: "shop".to_string(),
+            group: None,
+            languages: vec![],
+            endpoint: None,
+            resources: vec![],
+        };
+        let Json(response) = register_worker(State(scheduler.clone()), Json(request))
+            .await
+            .unwrap();
+        (response.worker_id, response.session_token)
+    }
+
+    #[test]
+    fn test_api_keys_are_compared_exactly() {
+        let auth = AuthConfig::new(vec!["first-key".to_string(), "second".to_string()]);
+        assert!(auth.is_enabled());
+        assert!(auth.verify("first-key"));
+        assert!(auth.verify("second"));
+        assert!(!auth.verify("first"));
+        assert!(!auth.verify("first-key2"));
+        assert!(!auth.verify(""));
+        assert!(!AuthConfig::new(vec![String::new()]).is_enabled());
+    }
+
+    #[tokio::test]
+    async fn test_stale_session_token_is_rejected() {
+        let scheduler = Arc::new(
+            Scheduler::new(Arc::new(L0MemoryStore::new()))
+                .with_worker_timeout(Duration::from_millis(50)),
+        );
+        let (stale_worker, stale_token) = register(&scheduler).await;
+        let stale = format!("Bearer {}", stale_token);
+        assert_eq!(
+            session(&scheduler, Some(&stale)).await.unwrap(),
+            stale_worker
+        );
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(scheduler.evict_stale_workers().await, vec![stale_worker]);
+        let err = session(&scheduler, Some(&stale)).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::UNAUTHORIZED);
+        assert_eq!(err.body.code, "UNAUTHORIZED");
+
+        let (worker_id, token) = register(&scheduler).await;
+        let valid = format!("Bearer {}", token);
+        assert_eq!(session(&scheduler, Some(&valid)).await.unwrap(), worker_id);
+
+        for authorization in [None, Some(token.as_str()), Some("Bearer unknown")] {
+            let err = session(&scheduler, authorization).await.unwrap_err();
+            assert_eq!(err.status, StatusCode::UNAUTHORIZED);
+        }
+    }
+}
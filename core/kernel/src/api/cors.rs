@@ -0,0 +1,78 @@
+use axum::http::{header, HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::api::auth::API_KEY_HEADER;
+use crate::telemetry::REQUEST_ID_HEADER;
+
+/// Origins allowed when none are configured: any port on the local machine,
+/// so the dashboard dev server can call the API
+pub const DEFAULT_CORS_ORIGINS: &[&str] = &["http://localhost:*", "http://127.0.0.1:*"];
+
+/// The default allowed origins as owned strings
+pub fn default_cors_origins() -> Vec<String> {
+    DEFAULT_CORS_ORIGINS.iter().map(|s| s.to_string()).collect()
+}
+
+/// Whether `origin` matches one of `allowed`.
+///
+/// `*` matches every origin and a trailing `:*` matches any port of that host.
+pub fn origin_allowed(allowed: &[String], origin: &str) -> bool {
+    allowed.iter().any(|pattern| {
+        if pattern == "*" || pattern == origin {
+            return true;
+        }
+        match pattern.strip_suffix(":*") {
+            Some(host) => origin
+                .strip_prefix(host)
+                .and_then(|rest| rest.strip_prefix(':'))
+                .is_some_and(|port| !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit())),
+            None => false,
+        }
+    })
+}
+
+/// CORS layer answering preflight requests from `allowed` origins.
+///
+/// Other origins get no `Access-Control-Allow-Origin` header, so browsers
+/// block the request.
+pub fn cors_layer(allowed: Vec<String>) -> CorsLayer {
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::predicate(
+            move |origin: &HeaderValue, _request| {
+                origin
+                    .to_str()
+                    .is_ok_and(|origin| origin_allowed(&allowed, origin))
+            },
+        ))
+        .allow_methods([Method::GET, Method::POST, Method::DELETE])
+        .allow_headers([
+            header::CONTENT_TYPE,
+            header::AUTHORIZATION,
+            API_KEY_HEADER,
+            HeaderName::from_static(REQUEST_ID_HEADER),
+        ])
+        .expose_headers([
+            header::RETRY_AFTER,
+            HeaderName::from_static(REQUEST_ID_HEADER),
+        ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_origin_patterns() {
+        let allowed = default_cors_origins();
+        assert!(origin_allowed(&allowed, "http://localhost:5173"));
+        assert!(origin_allowed(&allowed, "http://127.0.0.1:3000"));
+        assert!(!origin_allowed(&allowed, "http://localhost"));
+        assert!(!origin_allowed(&allowed, "http://localhost:5173.evil.com"));
+        assert!(!origin_allowed(&allowed, "https://example.com"));
+
+        let allowed = vec!["https://dash.example.com".to_string()];
+        assert!(origin_allowed(&allowed, "https://dash.example.com"));
+        assert!(!origin_allowed(&allowed, "http://localhost:5173"));
+        assert!(origin_allowed(&["*".to_string()], "https://anything.test"));
+    }
+}
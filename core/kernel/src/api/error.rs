@@ -5,6 +5,16 @@ use axum::{
 };
 use serde::Serialize;
 
+use crate::cancellation::{CancelError, TerminateError};
+use crate::definition::DefinitionError;
+use crate::import::ImportError;
+use crate::payload::PayloadTooLarge;
+use crate::retention::DeleteError;
+use crate::retry::RetryError;
+use crate::schedule::ScheduleError;
+use crate::signal::SignalError;
+use crate::step_lifecycle::StepLifecycleError;
+
 #[derive(Debug, Serialize)]
 pub struct ApiErrorBody {
     pub code: String,
@@ -42,6 +52,34 @@ impl ApiError {
         }
     }
 
+    pub fn unauthorized(message: &str) -> Self {
+        Self {
+            status: StatusCode::UNAUTHORIZED,
+            body: ApiErrorBody {
+                code: "UNAUTHORIZED".to_string(),
+                message: message.to_string(),
+                details: None,
+            },
+        }
+    }
+
+    pub fn conflict(code: &str, message: &str) -> Self {
+        Self {
+            status: StatusCode::CONFLICT,
+            body: ApiErrorBody {
+                code: code.to_string(),
+                message: message.to_string(),
+                details: None,
+            },
+        }
+    }
+
+    /// Attach structured details to the error body
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.body.details = Some(details);
+        self
+    }
+
     pub fn internal(message: &str) -> Self {
         Self {
             status: StatusCode::INTERNAL_SERVER_ERROR,
@@ -53,6 +91,28 @@ impl ApiError {
         }
     }
 
+    pub fn payload_too_large(message: &str) -> Self {
+        Self {
+            status: StatusCode::PAYLOAD_TOO_LARGE,
+            body: ApiErrorBody {
+                code: "PAYLOAD_TOO_LARGE".to_string(),
+                message: message.to_string(),
+                details: None,
+            },
+        }
+    }
+
+    pub fn too_many_requests(code: &str, message: &str) -> Self {
+        Self {
+            status: StatusCode::TOO_MANY_REQUESTS,
+            body: ApiErrorBody {
+                code: code.to_string(),
+                message: message.to_string(),
+                details: None,
+            },
+        }
+    }
+
     pub fn timeout(message: &str) -> Self {
         Self {
             status: StatusCode::REQUEST_TIMEOUT,
@@ -71,3 +131,162 @@ impl IntoResponse for ApiError {
         (self.status, body).into_response()
     }
 }
+
+impl From<StepLifecycleError> for ApiError {
+    fn from(e: StepLifecycleError) -> Self {
+        match &e {
+            StepLifecycleError::InvalidTaskId(_) => {
+                ApiError::bad_request("INVALID_TASK_ID", &e.to_string())
+            }
+            StepLifecycleError::WorkflowNotFound(_) => {
+                ApiError::not_found("WORKFLOW_NOT_FOUND", &e.to_string())
+            }
+            StepLifecycleError::TaskNotOwned(_) => ApiError::conflict("NOT_OWNER", &e.to_string()),
+            StepLifecycleError::AlreadyCompleted(_) => {
+                ApiError::conflict("ALREADY_COMPLETED", &e.to_string())
+            }
+            StepLifecycleError::UnknownWorkflowType(_) => {
+                ApiError::bad_request("UNKNOWN_WORKFLOW_TYPE", &e.to_string())
+            }
+            StepLifecycleError::WorkflowCancelled(_) => {
+                ApiError::conflict("CANCELLED", &e.to_string())
+            }
+            StepLifecycleError::WorkflowTerminated(_) => {
+                ApiError::conflict("TERMINATED", &e.to_string())
+            }
+            StepLifecycleError::SchemaValidation(violation) => {
+                ApiError::bad_request("SCHEMA_VALIDATION", &e.to_string())
+                    .with_details(serde_json::json!({ "violations": violation.details }))
+            }
+            StepLifecycleError::PayloadTooLarge(too_large) => too_large.clone().into(),
+            StepLifecycleError::Persistence(_) => ApiError::internal(&e.to_string()),
+        }
+    }
+}
+
+impl From<PayloadTooLarge> for ApiError {
+    fn from(e: PayloadTooLarge) -> Self {
+        ApiError::payload_too_large(&e.to_string()).with_details(serde_json::json!({
+            "size": e.size,
+            "limit": e.limit,
+        }))
+    }
+}
+
+impl From<CancelError> for ApiError {
+    fn from(e: CancelError) -> Self {
+        match &e {
+            CancelError::WorkflowNotFound(_) => {
+                ApiError::not_found("WORKFLOW_NOT_FOUND", &e.to_string())
+            }
+            CancelError::NotCancellable(..) => ApiError::conflict("INVALID_STATE", &e.to_string()),
+            CancelError::Persistence(_) => ApiError::internal(&e.to_string()),
+        }
+    }
+}
+
+impl From<TerminateError> for ApiError {
+    fn from(e: TerminateError) -> Self {
+        match &e {
+            TerminateError::EmptyReason => ApiError::bad_request("INVALID_REASON", &e.to_string()),
+            TerminateError::WorkflowNotFound(_) => {
+                ApiError::not_found("WORKFLOW_NOT_FOUND", &e.to_string())
+            }
+            TerminateError::AlreadyFinished(..) => {
+                ApiError::conflict("INVALID_STATE", &e.to_string())
+            }
+            TerminateError::Persistence(_) => ApiError::internal(&e.to_string()),
+        }
+    }
+}
+
+impl From<RetryError> for ApiError {
+    fn from(e: RetryError) -> Self {
+        match &e {
+            RetryError::WorkflowNotFound(_) => {
+                ApiError::not_found("WORKFLOW_NOT_FOUND", &e.to_string())
+            }
+            RetryError::NotFailed(..) => ApiError::conflict("INVALID_STATE", &e.to_string()),
+            RetryError::Persistence(_) => ApiError::internal(&e.to_string()),
+        }
+    }
+}
+
+impl From<ImportError> for ApiError {
+    fn from(e: ImportError) -> Self {
+        match &e {
+            ImportError::Decode(_) => ApiError::bad_request("INVALID_EXPORT", &e.to_string()),
+            ImportError::Truncated(_) => ApiError::bad_request("TRUNCATED_EXPORT", &e.to_string()),
+            ImportError::AlreadyExists(_) => ApiError::conflict("WORKFLOW_EXISTS", &e.to_string()),
+            ImportError::NotResumable { .. } => ApiError::conflict("INVALID_STATE", &e.to_string()),
+            ImportError::Persistence(_) => ApiError::internal(&e.to_string()),
+        }
+    }
+}
+
+impl From<DeleteError> for ApiError {
+    fn from(e: DeleteError) -> Self {
+        match &e {
+            DeleteError::WorkflowNotFound(_) => {
+                ApiError::not_found("WORKFLOW_NOT_FOUND", &e.to_string())
+            }
+            DeleteError::NotTerminal(_) => ApiError::bad_request("INVALID_STATE", &e.to_string()),
+            DeleteError::Persistence(_) => ApiError::internal(&e.to_string()),
+        }
+    }
+}
+
+impl From<ScheduleError> for ApiError {
+    fn from(e: ScheduleError) -> Self {
+        match &e {
+            ScheduleError::InvalidCron { .. } => {
+                ApiError::bad_request("INVALID_CRON", &e.to_string())
+            }
+            ScheduleError::UnknownWorkflowType(_) => {
+                ApiError::bad_request("UNKNOWN_WORKFLOW_TYPE", &e.to_string())
+            }
+            ScheduleError::NotFound(_) => ApiError::not_found("SCHEDULE_NOT_FOUND", &e.to_string()),
+            ScheduleError::Persistence(_) => ApiError::internal(&e.to_string()),
+        }
+    }
+}
+
+impl From<DefinitionError> for ApiError {
+    fn from(e: DefinitionError) -> Self {
+        match &e {
+            DefinitionError::Invalid(_, problems) => {
+                let errors: Vec<serde_json::Value> = problems
+                    .iter()
+                    .map(|problem| {
+                        serde_json::json!({
+                            "path": problem.path,
+                            "message": problem.error.to_string(),
+                        })
+                    })
+                    .collect();
+                ApiError::bad_request("INVALID_DEFINITION", &e.to_string())
+                    .with_details(serde_json::json!({ "errors": errors }))
+            }
+            DefinitionError::VersionConflict(..) => {
+                ApiError::conflict("DEFINITION_VERSION_CONFLICT", &e.to_string())
+            }
+            DefinitionError::Persistence(_) => ApiError::internal(&e.to_string()),
+            _ => ApiError::bad_request("INVALID_DEFINITION", &e.to_string()),
+        }
+    }
+}
+
+impl From<SignalError> for ApiError {
+    fn from(e: SignalError) -> Self {
+        match &e {
+            SignalError::EmptyName => ApiError::bad_request("INVALID_SIGNAL", &e.to_string()),
+            SignalError::WorkflowNotFound(_) => {
+                ApiError::not_found("WORKFLOW_NOT_FOUND", &e.to_string())
+            }
+            SignalError::WorkflowFinished(_) => {
+                ApiError::bad_request("INVALID_STATE", &e.to_string())
+            }
+            SignalError::Persistence(_) => ApiError::internal(&e.to_string()),
+        }
+    }
+}
@@ -2,10 +2,14 @@ use axum::{extract::State, Json};
 use std::sync::Arc;
 
 use crate::api::error::ApiError;
-use crate::api::models::MetricsResponse;
+use crate::api::models::{
+    DurationMetrics, EventMetrics, MetricsResponse, RateLimitMetrics, ReadyTaskMetrics,
+    StartedWorkflowStats, StatsResponse, StepCacheMetrics, StepDurationStats, TrackerMetrics,
+};
 use crate::persistence::Persistence;
 use crate::scheduler::Scheduler;
-use crate::state_machine::WorkflowState;
+use crate::server_info::ServerInfo;
+use crate::state_machine::{DurationStats, Priority, WorkflowState};
 
 pub type AppState<P> = Arc<Scheduler<P>>;
 
@@ -31,8 +35,10 @@ pub async fn get_metrics<P: Persistence + Clone + Send + Sync + 'static>(
     let mut active_workflows = 0u64;
     let mut completed_workflows = 0u64;
     let mut failed_workflows = 0u64;
+    let mut durations = DurationStats::default();
 
     for workflow in workflows {
+        durations.record(&workflow);
         match workflow.state {
             WorkflowState::Pending | WorkflowState::Running { .. } => {
                 active_workflows += 1;
@@ -43,15 +49,107 @@ pub async fn get_metrics<P: Persistence + Clone + Send + Sync + 'static>(
             WorkflowState::Failed { .. } => {
                 failed_workflows += 1;
             }
-            WorkflowState::Cancelled => {
-                // Cancelled workflows are counted as neither active nor failed
+            WorkflowState::Cancelled | WorkflowState::Terminated { .. } => {
+                // Cancelled and terminated workflows are counted as neither active nor failed
             }
         }
     }
 
+    let tracker = scheduler.tracker.stats().await;
+    let rate_limits = scheduler.rate_limiter.stats();
+    let step_cache = scheduler.step_cache_stats();
+    let ready = scheduler
+        .ready_task_counts()
+        .await
+        .map_err(|e| ApiError::internal(&e.to_string()))?;
     Ok(Json(MetricsResponse {
         active_workflows,
         completed_workflows,
         failed_workflows,
+        purged_workflows: scheduler.purged_workflows(),
+        tracker: TrackerMetrics {
+            active_executions: tracker.active as u64,
+            retained_executions: tracker.retained as u64,
+            evicted_executions: tracker.evicted,
+        },
+        events: EventMetrics {
+            subscribers: scheduler.broadcaster.subscriber_count() as u64,
+            lagged: scheduler.broadcaster.lagged(),
+        },
+        ready_tasks: ReadyTaskMetrics {
+            high: ready[&Priority::High] as u64,
+            normal: ready[&Priority::Normal] as u64,
+            low: ready[&Priority::Low] as u64,
+        },
+        durations: DurationMetrics {
+            workflows: durations.workflows,
+            avg_queue_ms: durations.avg_queue().as_millis() as u64,
+            avg_execution_ms: durations.avg_execution().as_millis() as u64,
+        },
+        rate_limits: RateLimitMetrics {
+            rate_limited: rate_limits.rate_limited,
+            concurrency_rejected: rate_limits.concurrency_rejected,
+            expensive_in_flight: rate_limits.expensive_in_flight,
+            tracked_api_keys: rate_limits.tracked_api_keys,
+        },
+        task_rejections: scheduler.task_rejections().into_iter().collect(),
+        step_cache: StepCacheMetrics {
+            hits: step_cache.hits,
+            misses: step_cache.misses,
+            hit_rate: step_cache.hit_rate(),
+        },
     }))
 }
+
+/// GET /stats - Get overview counters for the dashboard
+///
+/// Served from counters maintained as workflows and steps change state, so it is
+/// cheap enough to poll every few seconds.
+#[utoipa::path(
+    get,
+    path = "/stats",
+    responses(
+        (status = 200, description = "Overview counters", body = StatsResponse),
+    ),
+    tag = "admin"
+)]
+pub async fn get_stats<P: Persistence + Clone + Send + Sync + 'static>(
+    State(scheduler): State<AppState<P>>,
+) -> Json<StatsResponse> {
+    let stats = scheduler.stats();
+    let live = stats.live;
+    Json(StatsResponse {
+        active_workflows: live.workflows.active,
+        completed_workflows: live.workflows.completed,
+        failed_workflows: live.workflows.failed,
+        started: StartedWorkflowStats {
+            last_1m: live.started_last_1m,
+            last_5m: live.started_last_5m,
+            last_60m: live.started_last_60m,
+        },
+        step_durations: StepDurationStats {
+            p50_ms: live.step_p50_ms,
+            p95_ms: live.step_p95_ms,
+        },
+        workers: live.workers,
+        events: EventMetrics {
+            subscribers: stats.event_subscribers,
+            lagged: stats.events_lagged,
+        },
+    })
+}
+
+/// GET /info - Get server version, capabilities and limits
+#[utoipa::path(
+    get,
+    path = "/info",
+    responses(
+        (status = 200, description = "Server capabilities", body = ServerInfo),
+    ),
+    tag = "admin"
+)]
+pub async fn get_server_info<P: Persistence + Clone + Send + Sync + 'static>(
+    State(scheduler): State<AppState<P>>,
+) -> Json<ServerInfo> {
+    Json(scheduler.server_info())
+}
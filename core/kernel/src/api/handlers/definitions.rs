@@ -0,0 +1,244 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use std::sync::Arc;
+
+use crate::api::error::ApiError;
+use crate::api::models::WorkflowDefinitionListResponse;
+use crate::definition::{DefinitionError, DefinitionProblem, WorkflowDefinitionSpec};
+use crate::persistence::Persistence;
+use crate::scheduler::Scheduler;
+
+pub type AppState<P> = Arc<Scheduler<P>>;
+
+/// PUT /workflow-definitions/{name} - Register a version of a workflow definition
+#[utoipa::path(
+    put,
+    path = "/workflow-definitions/{name}",
+    params(("name" = String, Path, description = "Workflow type")),
+    request_body = WorkflowDefinitionSpec,
+    responses(
+        (status = 200, description = "Definition registered, or the same version was already registered with identical steps", body = WorkflowDefinitionSpec),
+        (status = 400, description = "Invalid definition, or `name` in the body differs from the path; `details.errors` lists every problem with its `path`, e.g. `steps[1].dependsOn[0]`"),
+        (status = 409, description = "The version is already registered with different steps"),
+    ),
+    tag = "definitions"
+)]
+pub async fn register_definition<P: Persistence + Clone + Send + Sync + 'static>(
+    State(scheduler): State<AppState<P>>,
+    Path(name): Path<String>,
+    Json(mut spec): Json<WorkflowDefinitionSpec>,
+) -> Result<Json<WorkflowDefinitionSpec>, ApiError> {
+    let mut problems = Vec::new();
+    if !spec.name.is_empty() && spec.name != name {
+        problems.push(DefinitionProblem::new(
+            "name",
+            DefinitionError::NameMismatch(spec.name.clone(), name.clone()),
+        ));
+    }
+    spec.name = name;
+    let (definition, conversion_problems) = spec.into_definition();
+    problems.extend(conversion_problems);
+    problems.extend(scheduler.definition_problems(&definition));
+    if !problems.is_empty() {
+        return Err(DefinitionError::Invalid(definition.name, problems).into());
+    }
+
+    scheduler.register_definition(definition.clone()).await?;
+    Ok(Json(definition.into()))
+}
+
+/// GET /workflow-definitions - List every registered definition version
+#[utoipa::path(
+    get,
+    path = "/workflow-definitions",
+    responses(
+        (status = 200, description = "Registered definitions", body = WorkflowDefinitionListResponse),
+    ),
+    tag = "definitions"
+)]
+pub async fn list_definitions<P: Persistence + Clone + Send + Sync + 'static>(
+    State(scheduler): State<AppState<P>>,
+) -> Json<WorkflowDefinitionListResponse> {
+    Json(WorkflowDefinitionListResponse {
+        definitions: scheduler
+            .definitions()
+            .await
+            .into_iter()
+            .map(WorkflowDefinitionSpec::from)
+            .collect(),
+    })
+}
+
+/// GET /workflow-definitions/{name}/{version} - Get one version of a definition
+#[utoipa::path(
+    get,
+    path = "/workflow-definitions/{name}/{version}",
+    params(
+        ("name" = String, Path, description = "Workflow type"),
+        ("version" = u32, Path, description = "Definition version"),
+    ),
+    responses(
+        (status = 200, description = "The definition", body = WorkflowDefinitionSpec),
+        (status = 404, description = "Definition not found"),
+    ),
+    tag = "definitions"
+)]
+pub async fn get_definition<P: Persistence + Clone + Send + Sync + 'static>(
+    State(scheduler): State<AppState<P>>,
+    Path((name, version)): Path<(String, u32)>,
+) -> Result<Json<WorkflowDefinitionSpec>, ApiError> {
+    let definition = scheduler
+        .definition_version(&name, version)
+        .await
+        .ok_or_else(|| {
+            ApiError::not_found(
+                "DEFINITION_NOT_FOUND",
+                &format!(
+                    "Workflow definition '{}' version {} not found",
+                    name, version
+                ),
+            )
+        })?;
+    Ok(Json(definition.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::l0_memory::L0MemoryStore;
+    use crate::task::{ResourceType, ServiceResource};
+    use std::time::Duration;
+
+    fn request(body: serde_json::Value) -> Json<WorkflowDefinitionSpec> {
+        Json(serde_json::from_value(body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_register_list_and_get_definition() {
+        let store = Arc::new(L0MemoryStore::new());
+        let scheduler = Arc::new(Scheduler::new(store.clone()));
+        scheduler.service_registry.register(
+            "billing".to_string(),
+            "worker-1".to_string(),
+            String::new(),
+            vec![],
+            vec![ServiceResource {
+                name: "charge".to_string(),
+                resource_type: ResourceType::Activity,
+                metadata: None,
+            }],
+            String::new(),
+        );
+
+        let body = serde_json::json!({
+            "version": 2,
+            "steps": [
+                { "name": "reserve", "targetResource": "reserve", "external": true },
+                {
+                    "name": "charge",
+                    "dependsOn": ["reserve"],
+                    "targetService": "billing",
+                    "targetResource": "charge",
+                    "resourceType": "activity",
+                    "retry": { "maxAttempts": 5 },
+                    "timeout": 30000
+                }
+            ]
+        });
+        let Json(registered) = register_definition(
+            State(scheduler.clone()),
+            Path("order".to_string()),
+            request(body.clone()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(registered.version, Some(2));
+        assert_eq!(
+            registered.steps[1].resource_type.as_deref(),
+            Some("ACTIVITY")
+        );
+
+        // Re-registering identical steps succeeds; the definition was persisted once
+        let Json(again) = register_definition(
+            State(scheduler.clone()),
+            Path("order".to_string()),
+            request(body),
+        )
+        .await
+        .unwrap();
+        assert_eq!(again.version, Some(2));
+        let saved = store.list_definitions().await.unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].steps[1].timeout, Some(Duration::from_secs(30)));
+
+        let Json(list) = list_definitions(State(scheduler.clone())).await;
+        assert_eq!(list.definitions.len(), 1);
+        assert_eq!(list.definitions[0].name, "order");
+
+        let Json(found) = get_definition(State(scheduler.clone()), Path(("order".to_string(), 2)))
+            .await
+            .unwrap();
+        assert_eq!(found.steps[1].depends_on, vec!["reserve"]);
+        let err = get_definition(State(scheduler.clone()), Path(("order".to_string(), 1)))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status.as_u16(), 404);
+
+        let err = register_definition(
+            State(scheduler),
+            Path("order".to_string()),
+            request(serde_json::json!({ "version": 2, "steps": [{ "name": "only" }] })),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.status.as_u16(), 409);
+        assert_eq!(err.body.code, "DEFINITION_VERSION_CONFLICT");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_definition_reports_every_problem() {
+        let scheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        let err = register_definition(
+            State(scheduler.clone()),
+            Path("order".to_string()),
+            request(serde_json::json!({
+                "steps": [
+                    { "name": "reserve", "resourceType": "job" },
+                    { "name": "charge", "dependsOn": ["pay"], "targetResource": "charge" }
+                ]
+            })),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.status.as_u16(), 400);
+        assert_eq!(err.body.code, "INVALID_DEFINITION");
+        let details = err.body.details.unwrap();
+        let paths: Vec<&str> = details["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|error| error["path"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                "steps[0].resourceType",
+                "steps[1].dependsOn[0]",
+                "steps[1].targetResource"
+            ]
+        );
+        assert!(scheduler.definitions().await.is_empty());
+
+        // 请求体中的名称可以省略，但不能与路径不同
+        let err = register_definition(
+            State(scheduler),
+            Path("order".to_string()),
+            request(serde_json::json!({ "name": "refund", "steps": [{ "name": "only" }] })),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.body.details.unwrap()["errors"][0]["path"], "name");
+    }
+}
@@ -1,4 +1,6 @@
 pub mod admin;
+pub mod definitions;
+pub mod schedules;
 pub mod steps;
 pub mod workers;
 pub mod workflows;
@@ -0,0 +1,154 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use chrono::{SecondsFormat, Utc};
+use std::sync::Arc;
+
+use crate::api::error::ApiError;
+use crate::api::models::{
+    CreateScheduleRequest, DeleteScheduleResponse, ScheduleListResponse, ScheduleResponse,
+};
+use crate::persistence::Persistence;
+use crate::schedule::{Schedule, ScheduleSpec};
+use crate::scheduler::Scheduler;
+
+pub type AppState<P> = Arc<Scheduler<P>>;
+
+fn schedule_response(schedule: Schedule) -> ScheduleResponse {
+    let rfc3339 = |time: chrono::DateTime<Utc>| time.to_rfc3339_opts(SecondsFormat::Secs, true);
+    ScheduleResponse {
+        next_fire_at: schedule.next_fire_after(Utc::now()).map(rfc3339),
+        input: serde_json::from_slice(&schedule.spec.input).ok(),
+        overlap_policy: schedule.spec.overlap_policy.as_str().to_string(),
+        created_at: rfc3339(schedule.created_at),
+        last_fired_at: schedule.last_fired_at.map(rfc3339),
+        schedule_id: schedule.id,
+        cron: schedule.spec.cron,
+        workflow_type: schedule.spec.workflow_type,
+        last_workflow_id: schedule.last_workflow_id,
+    }
+}
+
+/// POST /schedules - Start a workflow type on a cron schedule
+#[utoipa::path(
+    post,
+    path = "/schedules",
+    request_body = CreateScheduleRequest,
+    responses(
+        (status = 200, description = "Schedule created", body = ScheduleResponse),
+        (status = 400, description = "Invalid cron expression or unknown workflow type"),
+        (status = 413, description = "The input exceeds the scheduler's max input size"),
+    ),
+    tag = "schedules"
+)]
+pub async fn create_schedule<P: Persistence + Clone + Send + Sync + 'static>(
+    State(scheduler): State<AppState<P>>,
+    Json(req): Json<CreateScheduleRequest>,
+) -> Result<Json<ScheduleResponse>, ApiError> {
+    let input = serde_json::to_vec(&req.input)
+        .map_err(|e| ApiError::bad_request("INVALID_INPUT", &e.to_string()))?;
+    scheduler.check_input_size(&input)?;
+
+    let schedule = scheduler
+        .create_schedule(ScheduleSpec {
+            cron: req.cron,
+            workflow_type: req.workflow_type,
+            input,
+            overlap_policy: req.overlap_policy.unwrap_or_default(),
+        })
+        .await?;
+    Ok(Json(schedule_response(schedule)))
+}
+
+/// GET /schedules - List schedules in creation order
+#[utoipa::path(
+    get,
+    path = "/schedules",
+    responses(
+        (status = 200, description = "Registered schedules", body = ScheduleListResponse),
+    ),
+    tag = "schedules"
+)]
+pub async fn list_schedules<P: Persistence + Clone + Send + Sync + 'static>(
+    State(scheduler): State<AppState<P>>,
+) -> Result<Json<ScheduleListResponse>, ApiError> {
+    let schedules = scheduler
+        .list_schedules()
+        .await
+        .map_err(|e| ApiError::internal(&e.to_string()))?;
+    Ok(Json(ScheduleListResponse {
+        schedules: schedules.into_iter().map(schedule_response).collect(),
+    }))
+}
+
+/// DELETE /schedules/{id} - Stop a schedule; workflows it already started keep running
+#[utoipa::path(
+    delete,
+    path = "/schedules/{id}",
+    params(("id" = String, Path, description = "Schedule ID")),
+    responses(
+        (status = 200, description = "Schedule deleted", body = DeleteScheduleResponse),
+        (status = 404, description = "Schedule not found"),
+    ),
+    tag = "schedules"
+)]
+pub async fn delete_schedule<P: Persistence + Clone + Send + Sync + 'static>(
+    State(scheduler): State<AppState<P>>,
+    Path(id): Path<String>,
+) -> Result<Json<DeleteScheduleResponse>, ApiError> {
+    scheduler.delete_schedule(&id).await?;
+    Ok(Json(DeleteScheduleResponse {
+        success: true,
+        message: format!("Schedule '{}' deleted", id),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::l0_memory::L0MemoryStore;
+    use crate::schedule::OverlapPolicy;
+
+    #[tokio::test]
+    async fn test_create_list_and_delete_schedule() {
+        let scheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        let request = |cron: &str| CreateScheduleRequest {
+            cron: cron.to_string(),
+            workflow_type: "nightly-report".to_string(),
+            input: serde_json::json!({ "region": "eu" }),
+            overlap_policy: Some(OverlapPolicy::CancelPrevious),
+        };
+
+        let err = create_schedule(State(scheduler.clone()), Json(request("tomorrow")))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status.as_u16(), 400);
+        assert_eq!(err.body.code, "INVALID_CRON");
+
+        let Json(created) = create_schedule(State(scheduler.clone()), Json(request("0 2 * * *")))
+            .await
+            .unwrap();
+        assert_eq!(created.overlap_policy, "cancel_previous");
+        assert_eq!(created.input, Some(serde_json::json!({ "region": "eu" })));
+        assert!(created
+            .next_fire_at
+            .as_deref()
+            .unwrap()
+            .ends_with("T02:00:00Z"));
+
+        let Json(list) = list_schedules(State(scheduler.clone())).await.unwrap();
+        assert_eq!(list.schedules.len(), 1);
+        assert_eq!(list.schedules[0].schedule_id, created.schedule_id);
+
+        let Json(deleted) =
+            delete_schedule(State(scheduler.clone()), Path(created.schedule_id.clone()))
+                .await
+                .unwrap();
+        assert!(deleted.success);
+        let err = delete_schedule(State(scheduler), Path(created.schedule_id))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status.as_u16(), 404);
+    }
+}
@@ -2,30 +2,25 @@ use axum::{
     extract::{Path, State},
     Json,
 };
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
 use std::sync::Arc;
 
+use crate::api::auth::WorkerSession;
 use crate::api::error::ApiError;
-use crate::api::models::{CompleteStepRequest, ReportStepRequest, StepResponse};
+use crate::api::models::{
+    CompleteStepRequest, ReportStepRequest, StepHeartbeatRequest, StepHeartbeatResponse,
+    StepProgressInfo, StepRejectionInfo, StepResponse, StepStatusResponse,
+};
+use crate::child::ChildWorkflowSpec;
+use crate::payload::Payload;
 use crate::persistence::Persistence;
 use crate::scheduler::Scheduler;
+use crate::step_lifecycle::parse_task_id;
+use crate::tracker::StepProgress;
 
 pub type AppState<P> = Arc<Scheduler<P>>;
 
-/// Parse task_id to extract workflow_id and step_name.
-/// Format: workflow_id-step_name (workflow_id is UUID with dashes)
-fn parse_task_id(task_id: &str) -> Result<(&str, &str), ApiError> {
-    let parts: Vec<&str> = task_id.rsplitn(2, '-').collect();
-    if parts.len() != 2 {
-        return Err(ApiError::bad_request(
-            "INVALID_TASK_ID",
-            &format!("Invalid task_id format: {}", task_id),
-        ));
-    }
-    let step_name = parts[0];
-    let workflow_id = parts[1];
-    Ok((workflow_id, step_name))
-}
-
 /// POST /steps/{taskId}/report - Report step status
 #[utoipa::path(
     post,
@@ -35,101 +30,473 @@ fn parse_task_id(task_id: &str) -> Result<(&str, &str), ApiError> {
     responses(
         (status = 200, description = "Step status reported", body = StepResponse),
         (status = 400, description = "Invalid input"),
+        (status = 401, description = "Missing, invalid or expired session token"),
         (status = 404, description = "Task not found"),
+        (status = 409, description = "FAILED for a task whose lease expired or is held by another worker"),
+        (status = 413, description = "Output exceeds the scheduler's max output size; the step has failed"),
     ),
+    security(("session_token" = [])),
     tag = "steps"
 )]
 pub async fn report_step<P: Persistence + Clone + Send + Sync + 'static>(
     State(scheduler): State<AppState<P>>,
     Path(task_id): Path<String>,
+    WorkerSession(worker_id): WorkerSession,
     Json(req): Json<ReportStepRequest>,
 ) -> Result<Json<StepResponse>, ApiError> {
+    apply_report(&scheduler, &task_id, Some(&worker_id), req).await?;
+    Ok(Json(StepResponse { success: true }))
+}
+
+/// POST /steps/{taskId}/complete - Complete a step
+#[utoipa::path(
+    post,
+    path = "/steps/{taskId}/complete",
+    params(("taskId" = String, Path, description = "Task ID")),
+    request_body = CompleteStepRequest,
+    responses(
+        (status = 200, description = "Step completed", body = StepResponse),
+        (status = 400, description = "Invalid input"),
+        (status = 401, description = "Missing, invalid or expired session token"),
+        (status = 404, description = "Task not found"),
+        (status = 409, description = "Task lease expired or held by another worker"),
+        (status = 413, description = "Output exceeds the scheduler's max output size; the step has failed"),
+    ),
+    security(("session_token" = [])),
+    tag = "steps"
+)]
+pub async fn complete_step<P: Persistence + Clone + Send + Sync + 'static>(
+    State(scheduler): State<AppState<P>>,
+    Path(task_id): Path<String>,
+    WorkerSession(worker_id): WorkerSession,
+    Json(mut req): Json<CompleteStepRequest>,
+) -> Result<Json<StepResponse>, ApiError> {
+    // The task must still be leased to the authenticated worker
+    req.worker_id = Some(worker_id);
+    apply_complete(&scheduler, &task_id, req).await?;
+    Ok(Json(StepResponse { success: true }))
+}
+
+/// POST /steps/{taskId}/heartbeat - Heartbeat a running step
+#[utoipa::path(
+    post,
+    path = "/steps/{taskId}/heartbeat",
+    params(("taskId" = String, Path, description = "Task ID")),
+    request_body = StepHeartbeatRequest,
+    responses(
+        (status = 200, description = "Heartbeat recorded", body = StepHeartbeatResponse),
+        (status = 400, description = "Invalid input"),
+        (status = 401, description = "Missing, invalid or expired session token"),
+        (status = 404, description = "Task not found"),
+    ),
+    security(("session_token" = [])),
+    tag = "steps"
+)]
+pub async fn heartbeat_step<P: Persistence + Clone + Send + Sync + 'static>(
+    State(scheduler): State<AppState<P>>,
+    Path(task_id): Path<String>,
+    _session: WorkerSession,
+    Json(req): Json<StepHeartbeatRequest>,
+) -> Result<Json<StepHeartbeatResponse>, ApiError> {
+    apply_heartbeat(&scheduler, &task_id, req).await?;
+    Ok(Json(StepHeartbeatResponse {
+        success: true,
+        next_heartbeat: scheduler.heartbeat_interval().as_millis() as u64,
+    }))
+}
+
+/// GET /steps/{taskId} - Get step status and latest progress
+#[utoipa::path(
+    get,
+    path = "/steps/{taskId}",
+    params(("taskId" = String, Path, description = "Task ID")),
+    responses(
+        (status = 200, description = "Step status", body = StepStatusResponse),
+        (status = 400, description = "Invalid task ID"),
+        (status = 404, description = "Step not found"),
+    ),
+    tag = "steps"
+)]
+pub async fn get_step<P: Persistence + Clone + Send + Sync + 'static>(
+    State(scheduler): State<AppState<P>>,
+    Path(task_id): Path<String>,
+) -> Result<Json<StepStatusResponse>, ApiError> {
+    let id = parse_task_id(&task_id)?;
+
+    let step = scheduler
+        .tracker
+        .get_execution(&id.workflow_id)
+        .await
+        .and_then(|e| e.step_executions.get(&id.step_name).cloned())
+        .ok_or_else(|| {
+            ApiError::not_found("STEP_NOT_FOUND", &format!("Step '{}' not found", task_id))
+        })?;
+
+    let progress = step.progress.map(|p| StepProgressInfo {
+        percent: p.percent,
+        message: p.message,
+        details: p.details.and_then(|d| d.as_json()),
+    });
+
+    Ok(Json(StepStatusResponse {
+        task_id,
+        workflow_id: id.workflow_id,
+        step_name: id.step_name,
+        status: step.status.to_string().to_uppercase(),
+        attempt: step.attempt,
+        progress,
+        last_heartbeat_at: step.last_heartbeat_at.map(|t| t.timestamp()),
+        rejections: step
+            .rejections
+            .into_iter()
+            .map(|rejection| StepRejectionInfo {
+                worker_id: rejection.worker_id,
+                reason: rejection.reason,
+                rejected_at: rejection.rejected_at.timestamp(),
+            })
+            .collect(),
+        cache_hit: step.cache_hit,
+    }))
+}
+
+/// Apply a step status report. Shared by the REST handler and the worker WebSocket.
+///
+/// A FAILED report is checked like a failed completion: the task must still be
+/// leased to `worker_id` and its workflow must not be cancelled.
+pub(crate) async fn apply_report<P: Persistence>(
+    scheduler: &Scheduler<P>,
+    task_id: &str,
+    worker_id: Option<&str>,
+    req: ReportStepRequest,
+) -> Result<(), ApiError> {
     // Validate status
     let status_upper = req.status.to_uppercase();
-    if !["STARTED", "RUNNING", "COMPLETED", "FAILED"].contains(&status_upper.as_str()) {
+    if !["STARTED", "RUNNING", "COMPLETED", "FAILED", "HEARTBEAT"].contains(&status_upper.as_str())
+    {
         return Err(ApiError::bad_request(
             "INVALID_STATUS",
             &format!("Invalid step status: {}", req.status),
         ));
     }
 
+    // A heartbeat extends the lease and step timeout and records progress
+    if status_upper == "HEARTBEAT" {
+        let progress = StepProgress {
+            percent: req.percent,
+            message: req.message,
+            details: req.details.as_ref().map(Payload::from_json),
+        };
+        scheduler.lifecycle().heartbeat(task_id, progress).await?;
+        return Ok(());
+    }
+
     // Parse task_id to get workflow_id and step_name
-    let (workflow_id, step_name) = parse_task_id(&task_id)?;
+    let task_id = parse_task_id(task_id)?;
+    let (workflow_id, step_name) = (task_id.workflow_id.as_str(), task_id.step_name.as_str());
+    let lifecycle = scheduler.lifecycle();
 
-    // Use tracker to record step status
     match status_upper.as_str() {
         "STARTED" | "RUNNING" => {
-            scheduler
-                .tracker
-                .step_started(workflow_id, step_name, vec![], vec![])
-                .await;
+            lifecycle
+                .step_started(workflow_id, step_name, vec![])
+                .await?;
         }
         "COMPLETED" => {
-            let message_bytes = req
-                .message
-                .as_ref()
-                .map(|m| m.as_bytes().to_vec())
-                .unwrap_or_default();
-            scheduler
-                .tracker
+            let message_bytes = req.message.map(|m| m.into_bytes()).unwrap_or_default();
+            lifecycle
                 .step_completed(workflow_id, step_name, message_bytes)
-                .await;
+                .await?;
         }
         "FAILED" => {
-            let error_msg = req.message.clone().unwrap_or_else(|| "Unknown error".to_string());
-            scheduler
-                .tracker
-                .step_failed(workflow_id, step_name, error_msg)
-                .await;
+            let error_msg = req.message.unwrap_or_else(|| "Unknown error".to_string());
+            lifecycle
+                .fail_task(&task_id.to_string(), worker_id, error_msg)
+                .await?;
         }
         _ => {}
     }
 
-    Ok(Json(StepResponse { success: true }))
+    Ok(())
 }
 
-/// POST /steps/{taskId}/complete - Complete a step
-#[utoipa::path(
-    post,
-    path = "/steps/{taskId}/complete",
-    params(("taskId" = String, Path, description = "Task ID")),
-    request_body = CompleteStepRequest,
-    responses(
-        (status = 200, description = "Step completed", body = StepResponse),
-        (status = 400, description = "Invalid input"),
-        (status = 404, description = "Task not found"),
-    ),
-    tag = "steps"
-)]
-pub async fn complete_step<P: Persistence + Clone + Send + Sync + 'static>(
-    State(scheduler): State<AppState<P>>,
-    Path(task_id): Path<String>,
-    Json(req): Json<CompleteStepRequest>,
-) -> Result<Json<StepResponse>, ApiError> {
-    // Convert output to bytes
-    let output_bytes = req
-        .output
-        .map(|o| serde_json::to_vec(&o))
-        .transpose()
-        .map_err(|e| ApiError::bad_request("INVALID_OUTPUT", &e.to_string()))?
-        .unwrap_or_default();
-
-    // If there's an error, mark as failed; otherwise complete
+/// Apply a step completion. Shared by the REST handler and the worker WebSocket.
+pub(crate) async fn apply_complete<P: Persistence>(
+    scheduler: &Scheduler<P>,
+    task_id: &str,
+    req: CompleteStepRequest,
+) -> Result<(), ApiError> {
+    let worker_id = req.worker_id.as_deref();
+
+    // If there's an error, fail the step (and with it the workflow)
     if let Some(error) = req.error {
-        // Parse task_id to get workflow_id and step_name for failure tracking
-        let (workflow_id, step_name) = parse_task_id(&task_id)?;
         scheduler
-            .tracker
-            .step_failed(workflow_id, step_name, error)
-            .await;
-        return Ok(Json(StepResponse { success: true }));
+            .lifecycle()
+            .fail_task(task_id, worker_id, error)
+            .await?;
+        return Ok(());
     }
 
-    // Complete the task using scheduler
+    let output_bytes = match (req.output, req.output_base64) {
+        (Some(_), Some(_)) => {
+            return Err(ApiError::bad_request(
+                "INVALID_OUTPUT",
+                "Specify either output or outputBase64, not both",
+            ))
+        }
+        (Some(output), None) => serde_json::to_vec(&output)
+            .map_err(|e| ApiError::bad_request("INVALID_OUTPUT", &e.to_string()))?,
+        (None, Some(encoded)) => BASE64.decode(encoded).map_err(|e| {
+            ApiError::bad_request(
+                "INVALID_OUTPUT",
+                &format!("outputBase64 is not valid base64: {}", e),
+            )
+        })?,
+        (None, None) => Vec::new(),
+    };
+
+    let children = req
+        .start_children
+        .into_iter()
+        .map(|child| {
+            Ok(ChildWorkflowSpec {
+                workflow_type: child.workflow_type,
+                input: serde_json::to_vec(&child.input)?,
+            })
+        })
+        .collect::<Result<Vec<_>, serde_json::Error>>()
+        .map_err(|e| ApiError::bad_request("INVALID_INPUT", &e.to_string()))?;
+
     scheduler
-        .complete_task(&task_id, output_bytes)
+        .lifecycle()
+        .complete_task_with_children(task_id, worker_id, output_bytes, children)
+        .await?;
+
+    Ok(())
+}
+
+/// Apply a step heartbeat. Shared by the REST handler and the worker WebSocket.
+pub(crate) async fn apply_heartbeat<P: Persistence>(
+    scheduler: &Scheduler<P>,
+    task_id: &str,
+    req: StepHeartbeatRequest,
+) -> Result<(), ApiError> {
+    let details = req.details.as_ref().map(Payload::from_json);
+
+    let progress = StepProgress {
+        percent: req.percent,
+        message: req.message,
+        details,
+    };
+
+    scheduler.lifecycle().heartbeat(task_id, progress).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::l0_memory::L0MemoryStore;
+    use crate::state_machine::{Workflow, WorkflowState};
+    use crate::task::TaskId;
+
+    #[tokio::test]
+    async fn test_heartbeat_progress_visible_via_get_step() {
+        let scheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        let mut workflow = Workflow::new("wf-1".to_string(), "test-type".to_string(), vec![]);
+        workflow.state = workflow.state.try_start().unwrap();
+        scheduler
+            .persistence
+            .save_workflow(&workflow)
+            .await
+            .unwrap();
+
+        let task_id = TaskId::new("wf-1", "start").to_string();
+        let report = ReportStepRequest {
+            status: "STARTED".to_string(),
+            message: None,
+            percent: None,
+            details: None,
+        };
+        apply_report(&scheduler, &task_id, None, report)
+            .await
+            .unwrap();
+
+        let heartbeat = StepHeartbeatRequest {
+            percent: Some(42.0),
+            message: Some("halfway".to_string()),
+            details: Some(serde_json::json!({ "rows": 10 })),
+        };
+        let Json(response) = heartbeat_step(
+            State(scheduler.clone()),
+            Path(task_id.clone()),
+            WorkerSession("worker-1".to_string()),
+            Json(heartbeat),
+        )
         .await
-        .map_err(|e| ApiError::internal(&e.to_string()))?;
+        .unwrap();
+        assert!(response.success);
+        assert_eq!(response.next_heartbeat, 20_000);
 
-    Ok(Json(StepResponse { success: true }))
+        let Json(step) = get_step(State(scheduler), Path(task_id)).await.unwrap();
+        assert_eq!(step.status, "RUNNING");
+        let progress = step.progress.unwrap();
+        assert_eq!(progress.percent, Some(42.0));
+        assert_eq!(progress.message.as_deref(), Some("halfway"));
+        assert_eq!(progress.details, Some(serde_json::json!({ "rows": 10 })));
+    }
+
+    #[tokio::test]
+    async fn test_report_with_garbage_task_id_is_rejected() {
+        let scheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        let mut workflow = Workflow::new("wf-1".to_string(), "test-type".to_string(), vec![]);
+        workflow.state = workflow.state.try_start().unwrap();
+        scheduler
+            .persistence
+            .save_workflow(&workflow)
+            .await
+            .unwrap();
+
+        let report = |status: &str| ReportStepRequest {
+            status: status.to_string(),
+            message: None,
+            percent: None,
+            details: None,
+        };
+        for (task_id, status) in [
+            ("", 400),
+            ("garbage", 400),
+            ("99:wf:start", 400),
+            ("7:missing:start", 404),
+            ("missing-start", 404),
+        ] {
+            let err = report_step(
+                State(scheduler.clone()),
+                Path(task_id.to_string()),
+                WorkerSession("worker-1".to_string()),
+                Json(report("STARTED")),
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(err.status.as_u16(), status, "{:?}", task_id);
+        }
+
+        // 服务仍然正常处理合法的上报
+        let task_id = TaskId::new("wf-1", "start").to_string();
+        let Json(response) = report_step(
+            State(scheduler.clone()),
+            Path(task_id),
+            WorkerSession("worker-1".to_string()),
+            Json(report("STARTED")),
+        )
+        .await
+        .unwrap();
+        assert!(response.success);
+        let execution = scheduler.tracker.get_execution("wf-1").await.unwrap();
+        assert!(execution.step_executions.contains_key("start"));
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_report_records_progress_and_time() {
+        let scheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        let mut workflow = Workflow::new("wf-1".to_string(), "test-type".to_string(), vec![]);
+        workflow.state = workflow.state.try_start().unwrap();
+        scheduler
+            .persistence
+            .save_workflow(&workflow)
+            .await
+            .unwrap();
+
+        let task_id = TaskId::new("wf-1", "start").to_string();
+        let report = |status: &str, percent: Option<f64>| ReportStepRequest {
+            status: status.to_string(),
+            message: Some("copying".to_string()),
+            percent,
+            details: None,
+        };
+        apply_report(&scheduler, &task_id, None, report("STARTED", None))
+            .await
+            .unwrap();
+        let Json(step) = get_step(State(scheduler.clone()), Path(task_id.clone()))
+            .await
+            .unwrap();
+        assert!(step.last_heartbeat_at.is_none());
+
+        let Json(response) = report_step(
+            State(scheduler.clone()),
+            Path(task_id.clone()),
+            WorkerSession("worker-1".to_string()),
+            Json(report("heartbeat", Some(75.0))),
+        )
+        .await
+        .unwrap();
+        assert!(response.success);
+
+        let Json(step) = get_step(State(scheduler), Path(task_id)).await.unwrap();
+        assert_eq!(step.status, "RUNNING");
+        assert!(step.last_heartbeat_at.is_some());
+        let progress = step.progress.unwrap();
+        assert_eq!(progress.percent, Some(75.0));
+        assert_eq!(progress.message.as_deref(), Some("copying"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_report_from_another_worker_is_rejected() {
+        let scheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        let mut workflow = Workflow::new("wf-1".to_string(), "test-type".to_string(), vec![]);
+        workflow.state = workflow.state.try_start().unwrap();
+        scheduler
+            .persistence
+            .save_workflow(&workflow)
+            .await
+            .unwrap();
+        scheduler
+            .register_worker(
+                "worker-1".to_string(),
+                "test-service".to_string(),
+                "default".to_string(),
+                vec!["test-type".to_string()],
+                vec![],
+            )
+            .await;
+        let tasks = scheduler.poll_tasks("worker-1", 1).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+
+        let failed = || ReportStepRequest {
+            status: "FAILED".to_string(),
+            message: Some("boom".to_string()),
+            percent: None,
+            details: None,
+        };
+        let err = report_step(
+            State(scheduler.clone()),
+            Path(tasks[0].task_id.clone()),
+            WorkerSession("worker-2".to_string()),
+            Json(failed()),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.status.as_u16(), 409);
+        assert_eq!(err.body.code, "NOT_OWNER");
+        let id = TaskId::parse(&tasks[0].task_id).unwrap();
+        assert_eq!(scheduler.lease(&id).await.unwrap().worker_id, "worker-1");
+        let workflow = scheduler
+            .persistence
+            .get_workflow("wf-1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(workflow.state, WorkflowState::Running { .. }));
+
+        // 租约持有者的失败上报照常生效
+        let Json(response) = report_step(
+            State(scheduler.clone()),
+            Path(tasks[0].task_id.clone()),
+            WorkerSession("worker-1".to_string()),
+            Json(failed()),
+        )
+        .await
+        .unwrap();
+        assert!(response.success);
+        assert!(scheduler.lease(&id).await.is_none());
+    }
 }
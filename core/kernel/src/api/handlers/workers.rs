@@ -5,10 +5,14 @@ use axum::{
 use std::sync::Arc;
 
 use crate::api::error::ApiError;
-use crate::api::models::{HeartbeatResponse, RegisterWorkerRequest, RegisterWorkerResponse};
+use crate::api::models::{
+    HeartbeatResponse, RegisterWorkerRequest, RegisterWorkerResponse, ResourceInfo,
+    ServiceListResponse, ServiceSummary, WorkerListResponse, WorkerSummary,
+};
 use crate::persistence::Persistence;
 use crate::scheduler::Scheduler;
-use crate::task::ResourceType;
+use crate::schema;
+use crate::task::{ResourceMetadata, ResourceType, RetryPolicy, ServiceResource};
 
 pub type AppState<P> = Arc<Scheduler<P>>;
 
@@ -19,7 +23,7 @@ pub type AppState<P> = Arc<Scheduler<P>>;
     request_body = RegisterWorkerRequest,
     responses(
         (status = 201, description = "Worker registered", body = RegisterWorkerResponse),
-        (status = 400, description = "Invalid input"),
+        (status = 400, description = "Invalid input or a malformed resource schema"),
     ),
     tag = "workers"
 )]
@@ -28,10 +32,8 @@ pub async fn register_worker<P: Persistence + Clone + Send + Sync + 'static>(
     Json(req): Json<RegisterWorkerRequest>,
 ) -> Result<Json<RegisterWorkerResponse>, ApiError> {
     let worker_id = uuid::Uuid::new_v4().to_string();
-    let session_token = uuid::Uuid::new_v4().to_string();
 
-    // Convert ResourceInfo to (String, ResourceType) tuples
-    let resources: Vec<(String, ResourceType)> = req
+    let provides: Vec<ServiceResource> = req
         .resources
         .into_iter()
         .map(|r| {
@@ -41,21 +43,75 @@ pub async fn register_worker<P: Persistence + Clone + Send + Sync + 'static>(
                 "WORKFLOW" => ResourceType::Workflow,
                 _ => ResourceType::Step, // Default to Step
             };
-            (r.name, resource_type)
+            let declared = r.max_attempts.is_some()
+                || r.timeout.is_some()
+                || r.input_schema.is_some()
+                || r.output_schema.is_some()
+                || r.cacheable;
+            let metadata = declared.then_some(ResourceMetadata {
+                max_attempts: r.max_attempts,
+                timeout: r.timeout,
+                input_schema: r.input_schema,
+                output_schema: r.output_schema,
+                cacheable: r.cacheable,
+            });
+            ServiceResource {
+                name: r.name,
+                resource_type,
+                metadata,
+            }
         })
         .collect();
+    // Reject malformed schemas before anything is registered
+    schema::check_resources(&provides).map_err(|e| ApiError::bad_request("INVALID_SCHEMA", &e))?;
+
+    for resource in &provides {
+        if let Some(max_attempts) = resource
+            .metadata
+            .as_ref()
+            .and_then(|m| m.max_attempts)
+            .filter(|n| *n > 0)
+        {
+            scheduler
+                .set_retry_policy(
+                    &resource.name,
+                    RetryPolicy {
+                        max_attempts,
+                        ..Default::default()
+                    },
+                )
+                .await;
+        }
+    }
+    let resources: Vec<(String, ResourceType)> = provides
+        .iter()
+        .map(|r| (r.name.clone(), r.resource_type))
+        .collect();
+    let group = req.group.unwrap_or_else(|| "default".to_string());
+
+    // Tasks are routed by the resources the service declares here
+    scheduler.service_registry.register(
+        req.service_name.clone(),
+        worker_id.clone(),
+        group.clone(),
+        req.languages,
+        provides,
+        req.endpoint.unwrap_or_default(),
+    );
 
     // Register worker to scheduler
-    // Note: Using empty defaults for group and workflow_types as they're not in the API request
+    // Note: REST workers declare resources rather than workflow types
     scheduler
         .register_worker(
             worker_id.clone(),
             req.service_name,
-            "default".to_string(), // default group
-            vec![],                // empty workflow_types, can be extended
+            group,
+            vec![],
             resources,
         )
         .await;
+    // Required on the task WebSocket and the step endpoints
+    let session_token = scheduler.sessions.issue(&worker_id);
 
     Ok(Json(RegisterWorkerResponse {
         worker_id,
@@ -75,13 +131,262 @@ pub async fn register_worker<P: Persistence + Clone + Send + Sync + 'static>(
     tag = "workers"
 )]
 pub async fn worker_heartbeat<P: Persistence + Clone + Send + Sync + 'static>(
-    State(_scheduler): State<AppState<P>>,
-    Path(_worker_id): Path<String>,
+    State(scheduler): State<AppState<P>>,
+    Path(worker_id): Path<String>,
 ) -> Result<Json<HeartbeatResponse>, ApiError> {
-    // TODO: Update worker last heartbeat time in scheduler
-    // For now, return a successful response
+    // Evicted workers get a 404 and must register again
+    if !scheduler.touch_worker(&worker_id).await {
+        return Err(ApiError::not_found(
+            "WORKER_NOT_FOUND",
+            &format!("Worker not registered: {}", worker_id),
+        ));
+    }
+
     Ok(Json(HeartbeatResponse {
         success: true,
-        next_heartbeat: 30, // 30 seconds until next heartbeat
+        // Seconds until the next heartbeat is expected
+        next_heartbeat: (scheduler.worker_timeout() / 3).as_secs().max(1),
     }))
 }
+
+/// GET /workers - List registered workers and their liveness
+#[utoipa::path(
+    get,
+    path = "/workers",
+    responses(
+        (status = 200, description = "Registered workers", body = WorkerListResponse),
+    ),
+    tag = "admin"
+)]
+pub async fn list_workers<P: Persistence + Clone + Send + Sync + 'static>(
+    State(scheduler): State<AppState<P>>,
+) -> Json<WorkerListResponse> {
+    let timeout = scheduler.worker_timeout();
+    let mut workers: Vec<WorkerSummary> = scheduler
+        .workers()
+        .await
+        .into_iter()
+        .map(|w| WorkerSummary {
+            alive: w.last_seen.elapsed().map_or(true, |idle| idle <= timeout),
+            last_seen: chrono::DateTime::<chrono::Utc>::from(w.last_seen).to_rfc3339(),
+            worker_id: w.id,
+            service_name: w.service_name,
+            group: w.group,
+            resources: w.resources.into_iter().map(|(name, _)| name).collect(),
+        })
+        .collect();
+    workers.sort_by(|a, b| a.worker_id.cmp(&b.worker_id));
+
+    Json(WorkerListResponse {
+        workers,
+        worker_timeout_secs: timeout.as_secs(),
+    })
+}
+
+/// GET /services - List registered service instances and the resources they provide
+#[utoipa::path(
+    get,
+    path = "/services",
+    responses(
+        (status = 200, description = "Registered services", body = ServiceListResponse),
+    ),
+    tag = "admin"
+)]
+pub async fn list_services<P: Persistence + Clone + Send + Sync + 'static>(
+    State(scheduler): State<AppState<P>>,
+) -> Json<ServiceListResponse> {
+    let mut services: Vec<ServiceSummary> = scheduler
+        .service_registry
+        .list()
+        .into_iter()
+        .map(|service| {
+            let mut provides: Vec<ResourceInfo> = service
+                .provides
+                .into_values()
+                .map(|r| ResourceInfo {
+                    name: r.name,
+                    resource_type: r.resource_type.as_str().to_string(),
+                    max_attempts: r.metadata.as_ref().and_then(|m| m.max_attempts),
+                    timeout: r.metadata.as_ref().and_then(|m| m.timeout),
+                    input_schema: r.metadata.as_ref().and_then(|m| m.input_schema.clone()),
+                    output_schema: r.metadata.as_ref().and_then(|m| m.output_schema.clone()),
+                    cacheable: r.metadata.as_ref().is_some_and(|m| m.cacheable),
+                })
+                .collect();
+            provides.sort_by(|a, b| a.name.cmp(&b.name));
+            ServiceSummary {
+                service_name: service.service_name,
+                instance_id: service.instance_id,
+                group: service.group,
+                languages: service.languages,
+                provides,
+                endpoint: service.endpoint,
+                registered_at: service.registered_at.to_rfc3339(),
+                last_heartbeat: service.last_heartbeat.to_rfc3339(),
+            }
+        })
+        .collect();
+    services
+        .sort_by(|a, b| (&a.service_name, &a.instance_id).cmp(&(&b.service_name, &b.instance_id)));
+
+    Json(ServiceListResponse { services })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::l0_memory::L0MemoryStore;
+    use axum::http::StatusCode;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_heartbeat_and_eviction() {
+        let scheduler = Arc::new(
+            Scheduler::new(Arc::new(L0MemoryStore::new()))
+                .with_worker_timeout(Duration::from_millis(100)),
+        );
+        scheduler
+            .register_worker(
+                "worker-1".to_string(),
+                "billing".to_string(),
+                "default".to_string(),
+                vec![],
+                vec![("charge".to_string(), ResourceType::Activity)],
+            )
+            .await;
+
+        let Json(heartbeat) =
+            worker_heartbeat(State(scheduler.clone()), Path("worker-1".to_string()))
+                .await
+                .unwrap();
+        assert!(heartbeat.success);
+        let Json(list) = list_workers(State(scheduler.clone())).await;
+        assert_eq!(list.workers.len(), 1);
+        assert_eq!(list.workers[0].worker_id, "worker-1");
+        assert_eq!(list.workers[0].resources, vec!["charge".to_string()]);
+        assert!(list.workers[0].alive);
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        let Json(list) = list_workers(State(scheduler.clone())).await;
+        assert!(!list.workers[0].alive);
+
+        assert_eq!(scheduler.evict_stale_workers().await, vec!["worker-1"]);
+        let Json(list) = list_workers(State(scheduler.clone())).await;
+        assert!(list.workers.is_empty());
+
+        let err = worker_heartbeat(State(scheduler), Path("worker-1".to_string()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_service_instances_are_listed_until_their_workers_are_evicted() {
+        let scheduler = Arc::new(
+            Scheduler::new(Arc::new(L0MemoryStore::new()))
+                .with_worker_timeout(Duration::from_millis(100)),
+        );
+        let request = || RegisterWorkerRequest {
+            service_name: "billing".to_string(),
+            group: Some("payments".to_string()),
+            languages: vec!["typescript".to_string()],
+            endpoint: Some("http://billing:3000".to_string()),
+            resources: vec![
+                ResourceInfo {
+                    name: "refund".to_string(),
+                    resource_type: "activity".to_string(),
+                    max_attempts: None,
+                    timeout: None,
+                    input_schema: None,
+                    output_schema: None,
+                    cacheable: false,
+                },
+                ResourceInfo {
+                    name: "charge".to_string(),
+                    resource_type: "activity".to_string(),
+                    max_attempts: Some(5),
+                    timeout: Some(30_000),
+                    input_schema: None,
+                    output_schema: None,
+                    cacheable: false,
+                },
+            ],
+        };
+        let mut worker_ids = Vec::new();
+        for _ in 0..2 {
+            let Json(registered) = register_worker(State(scheduler.clone()), Json(request()))
+                .await
+                .unwrap();
+            worker_ids.push(registered.worker_id);
+        }
+        worker_ids.sort();
+
+        // Each replica is listed as its own instance
+        let Json(list) = list_services(State(scheduler.clone())).await;
+        let instances: Vec<&str> = list
+            .services
+            .iter()
+            .map(|s| s.instance_id.as_str())
+            .collect();
+        assert_eq!(instances, worker_ids);
+        let service = &list.services[0];
+        assert_eq!(service.service_name, "billing");
+        assert_eq!(service.group, "payments");
+        assert_eq!(service.languages, vec!["typescript".to_string()]);
+        assert_eq!(service.endpoint, "http://billing:3000");
+        let names: Vec<&str> = service.provides.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["charge", "refund"]);
+        assert_eq!(service.provides[0].resource_type, "ACTIVITY");
+        assert_eq!(service.provides[0].max_attempts, Some(5));
+        assert_eq!(service.provides[0].timeout, Some(30_000));
+        let workers = scheduler.workers().await;
+        assert!(workers.iter().all(|w| w.group == "payments"));
+
+        // Evicting one worker removes only its instance
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(scheduler.touch_worker(&worker_ids[1]).await);
+        assert_eq!(
+            scheduler.evict_stale_workers().await,
+            vec![worker_ids[0].clone()]
+        );
+        let Json(list) = list_services(State(scheduler.clone())).await;
+        assert_eq!(list.services.len(), 1);
+        assert_eq!(list.services[0].instance_id, worker_ids[1]);
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(scheduler.evict_stale_workers().await.len(), 1);
+        let Json(list) = list_services(State(scheduler)).await;
+        assert!(list.services.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_malformed_resource_schema_is_rejected() {
+        let scheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        let request = RegisterWorkerRequest {
+            service_name: "billing".to_string(),
+            group: None,
+            languages: vec![],
+            endpoint: None,
+            resources: vec![ResourceInfo {
+                name: "charge".to_string(),
+                resource_type: "activity".to_string(),
+                max_attempts: None,
+                timeout: None,
+                input_schema: Some(r#"{"type": "object"}"#.to_string()),
+                output_schema: Some(r#"{"type": "no-such-type"}"#.to_string()),
+                cacheable: false,
+            }],
+        };
+        let err = register_worker(State(scheduler.clone()), Json(request))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        assert_eq!(err.body.code, "INVALID_SCHEMA");
+        assert!(err
+            .body
+            .message
+            .starts_with("output schema of resource 'charge'"));
+        assert!(scheduler.service_registry.is_empty());
+        assert!(scheduler.workers().await.is_empty());
+    }
+}
@@ -1,18 +1,33 @@
 use axum::{
     extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
     Json,
 };
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use crate::api::error::ApiError;
 use crate::api::models::{
-    CancelWorkflowResponse, CreateWorkflowRequest, CreateWorkflowResponse,
-    WorkflowResultResponse, WorkflowStatusResponse,
+    BatchCreateWorkflowResult, BatchCreateWorkflowsResponse, BatchItemError, BatchItemStatus,
+    CancelWorkflowResponse, CreateWorkflowRequest, CreateWorkflowResponse, GraphEdgeInfo,
+    GraphNodeInfo, ImportWorkflowResponse, RetryWorkflowResponse, SignalWorkflowRequest,
+    SignalWorkflowResponse, StepExecutionInfo, StepProgressInfo, TerminateWorkflowRequest,
+    TerminateWorkflowResponse, WorkflowEventInfo, WorkflowEventsResponse, WorkflowGraphResponse,
+    WorkflowListResponse, WorkflowResultResponse, WorkflowStatusResponse, WorkflowStepsResponse,
+    WorkflowSummary, WorkflowTransition,
 };
-use crate::persistence::Persistence;
-use crate::scheduler::Scheduler;
+use crate::export::{self, ExportFormat, ExportOptions};
+use crate::graph::GraphSource;
+use crate::import::ImportOptions;
+use crate::memo::validate_memo;
+use crate::persistence::{ListOptions, Persistence};
+use crate::scheduler::{Scheduler, StartOptions, WorkflowStart};
 use crate::state_machine::{Workflow, WorkflowState};
+use crate::time;
+use crate::tracker::{StepExecution, StepExecutionStatus};
 
 pub type AppState<P> = Arc<Scheduler<P>>;
 
@@ -26,6 +41,195 @@ fn default_timeout() -> u64 {
     30
 }
 
+#[derive(Debug, Default, Deserialize)]
+pub struct EventsQuery {
+    /// Only return events at or after this Unix timestamp (seconds)
+    pub since: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StepsQuery {
+    /// Include step input, output and progress details
+    #[serde(default = "default_include_payloads")]
+    pub include_payloads: bool,
+}
+
+impl Default for StepsQuery {
+    fn default() -> Self {
+        Self {
+            include_payloads: default_include_payloads(),
+        }
+    }
+}
+
+fn default_include_payloads() -> bool {
+    true
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// Truncate payloads larger than this many bytes
+    pub max_payload_bytes: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ImportQuery {
+    /// Keep the exported workflow id instead of generating a new one
+    #[serde(default)]
+    pub keep_id: bool,
+    /// Re-dispatch from the failed step instead of leaving the workflow paused
+    #[serde(default)]
+    pub resume: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct DeleteQuery {
+    /// Remove the workflow and its history instead of cancelling it
+    #[serde(default)]
+    pub purge: bool,
+    /// Allow purging a workflow that has not reached a terminal state
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ListQuery {
+    #[serde(default)]
+    pub offset: usize,
+    /// Page size, capped at the server's max batch size
+    pub limit: Option<usize>,
+    /// `started_at_asc` (default) or `started_at_desc`
+    pub order: Option<String>,
+    /// Only list workflows in this state
+    pub state: Option<String>,
+    /// Only list workflows of this type
+    #[serde(rename = "type")]
+    pub workflow_type: Option<String>,
+    /// Only list child workflows of this workflow
+    pub parent: Option<String>,
+    /// Only list workflows started at or after this RFC 3339 time
+    pub started_after: Option<String>,
+    /// Only list workflows started before this RFC 3339 time
+    pub started_before: Option<String>,
+}
+
+/// Header carrying the number of workflows matching a list query across all pages
+pub const TOTAL_COUNT_HEADER: &str = "x-total-count";
+
+/// Prefix of list query parameters that filter by memo, as in `memo.customer_id=123`
+pub const MEMO_QUERY_PREFIX: &str = "memo.";
+
+fn parse_time_query(name: &str, value: Option<&str>) -> Result<Option<DateTime<Utc>>, ApiError> {
+    value
+        .map(|value| {
+            DateTime::parse_from_rfc3339(value)
+                .map(|time| time.with_timezone(&Utc))
+                .map_err(|e| {
+                    ApiError::bad_request(
+                        "INVALID_QUERY",
+                        &format!(
+                            "Invalid {} '{}': expected an RFC 3339 time ({})",
+                            name, value, e
+                        ),
+                    )
+                })
+        })
+        .transpose()
+}
+
+/// `memo.<key>=<value>` query parameters; a repeated key keeps the last value
+fn memo_filter(pairs: Vec<(String, String)>) -> HashMap<String, String> {
+    pairs
+        .into_iter()
+        .filter_map(|(name, value)| {
+            Some((name.strip_prefix(MEMO_QUERY_PREFIX)?.to_string(), value))
+        })
+        .collect()
+}
+
+/// GET /workflows - List workflows ordered by start time
+#[utoipa::path(
+    get,
+    path = "/workflows",
+    params(
+        ("offset" = Option<usize>, Query, description = "Number of workflows to skip"),
+        ("limit" = Option<usize>, Query, description = "Page size (defaults to and is capped at the server's max batch size)"),
+        ("order" = Option<String>, Query, description = "started_at_asc (default) or started_at_desc"),
+        ("state" = Option<String>, Query, description = "pending | running | completed | failed | cancelled"),
+        ("type" = Option<String>, Query, description = "Workflow type"),
+        ("parent" = Option<String>, Query, description = "Parent workflow ID"),
+        ("started_after" = Option<String>, Query, description = "Only workflows started at or after this RFC 3339 time"),
+        ("started_before" = Option<String>, Query, description = "Only workflows started before this RFC 3339 time"),
+        ("memo.<key>" = Option<String>, Query, description = "Only workflows whose memo has this value for `<key>`; repeat for more keys"),
+    ),
+    responses(
+        (status = 200, description = "A page of workflows", body = WorkflowListResponse,
+            headers(("x-total-count" = u64, description = "Workflows matching the filters across all pages"))),
+        (status = 400, description = "Invalid query"),
+    ),
+    tag = "workflows"
+)]
+pub async fn list_workflows<P: Persistence + Clone + Send + Sync + 'static>(
+    State(scheduler): State<AppState<P>>,
+    Query(query): Query<ListQuery>,
+    Query(pairs): Query<Vec<(String, String)>>,
+) -> Result<([(&'static str, String); 1], Json<WorkflowListResponse>), ApiError> {
+    let max_limit = scheduler.limits().max_batch_size as usize;
+    let limit = query.limit.unwrap_or(max_limit).min(max_limit);
+    let options = ListOptions {
+        offset: query.offset,
+        limit: Some(limit),
+        order_by: query
+            .order
+            .as_deref()
+            .map(str::parse)
+            .transpose()
+            .map_err(|e: String| ApiError::bad_request("INVALID_QUERY", &e))?
+            .unwrap_or_default(),
+        state_filter: query
+            .state
+            .as_deref()
+            .map(str::parse)
+            .transpose()
+            .map_err(|e: String| ApiError::bad_request("INVALID_QUERY", &e))?,
+        workflow_type: query.workflow_type,
+        parent_workflow_id: query.parent,
+        started_after: parse_time_query("started_after", query.started_after.as_deref())?,
+        started_before: parse_time_query("started_before", query.started_before.as_deref())?,
+        memo: memo_filter(pairs),
+    };
+
+    let workflows = scheduler
+        .persistence
+        .list_workflows_paged(&options)
+        .await
+        .map_err(|e| ApiError::internal(&e.to_string()))?;
+    let total = scheduler
+        .persistence
+        .count_workflows(&options)
+        .await
+        .map_err(|e| ApiError::internal(&e.to_string()))?;
+
+    let page = Json(WorkflowListResponse {
+        workflows: workflows
+            .into_iter()
+            .map(|w| WorkflowSummary {
+                status: w.state.label().to_string(),
+                workflow_id: w.id,
+                workflow_type: w.workflow_type,
+                started_at: w.started_at.to_rfc3339(),
+                updated_at: w.updated_at.to_rfc3339(),
+                memo: w.memo.into_iter().collect(),
+            })
+            .collect(),
+        offset: options.offset,
+        limit,
+    });
+    Ok(([(TOTAL_COUNT_HEADER, total.to_string())], page))
+}
+
 /// POST /workflows - Create a new workflow
 #[utoipa::path(
     post,
@@ -33,7 +237,9 @@ fn default_timeout() -> u64 {
     request_body = CreateWorkflowRequest,
     responses(
         (status = 201, description = "Workflow created", body = CreateWorkflowResponse),
-        (status = 400, description = "Invalid input"),
+        (status = 400, description = "Invalid input, unknown workflow type or unknown definition version"),
+        (status = 409, description = "A workflow with the requested id already exists with a different type"),
+        (status = 413, description = "The input exceeds the scheduler's max input size"),
     ),
     tag = "workflows"
 )]
@@ -41,26 +247,195 @@ pub async fn create_workflow<P: Persistence + Clone + Send + Sync + 'static>(
     State(scheduler): State<AppState<P>>,
     Json(req): Json<CreateWorkflowRequest>,
 ) -> Result<Json<CreateWorkflowResponse>, ApiError> {
-    let workflow_id = req
-        .options
-        .and_then(|o| o.workflow_id)
-        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let workflow_type = req.workflow_type.clone();
+    let start = workflow_start(&scheduler, req).await?;
 
-    let input_bytes = serde_json::to_vec(&req.input)
+    // A caller-supplied id is an idempotency key: a retried create reports the
+    // existing workflow instead of starting a duplicate
+    let started = scheduler
+        .start_workflow(
+            start.workflow_id,
+            start.workflow_type,
+            start.input,
+            start.options,
+        )
+        .await
+        .map_err(|e| ApiError::internal(&e.to_string()))?;
+    check_workflow_type(&workflow_type, &started.workflow)?;
+
+    let workflow = started.workflow;
+    Ok(Json(CreateWorkflowResponse {
+        status: workflow.state.label().to_string(),
+        workflow_id: workflow.id,
+        already_exists: started.already_exists,
+    }))
+}
+
+/// Validate a create request and turn it into a scheduler start
+async fn workflow_start<P: Persistence + Clone + Send + Sync + 'static>(
+    scheduler: &Scheduler<P>,
+    req: CreateWorkflowRequest,
+) -> Result<WorkflowStart, ApiError> {
+    let options = req.options.unwrap_or_default();
+    if !options.dynamic && !scheduler.accepts_workflow_type(&req.workflow_type).await {
+        return Err(ApiError::bad_request(
+            "UNKNOWN_WORKFLOW_TYPE",
+            &format!(
+                "Unknown workflow type '{}': expected one of {} (set options.dynamic to start a type without a definition)",
+                req.workflow_type,
+                scheduler.defined_workflow_types().await.join(", ")
+            ),
+        ));
+    }
+    if let Some(version) = options.version {
+        if scheduler
+            .definition_version(&req.workflow_type, version)
+            .await
+            .is_none()
+        {
+            return Err(ApiError::bad_request(
+                "UNKNOWN_WORKFLOW_VERSION",
+                &format!(
+                    "Workflow type '{}' has no definition version {}",
+                    req.workflow_type, version
+                ),
+            ));
+        }
+    }
+
+    let input = serde_json::to_vec(&req.input)
         .map_err(|e| ApiError::bad_request("INVALID_INPUT", &e.to_string()))?;
+    scheduler.check_input_size(&input)?;
+    validate_memo(&options.memo)
+        .map_err(|e| ApiError::bad_request("INVALID_MEMO", &e.to_string()))?;
 
-    // Create a new workflow using the Persistence layer
-    let workflow = Workflow::new(workflow_id.clone(), req.workflow_type, input_bytes);
+    Ok(WorkflowStart {
+        workflow_id: options.workflow_id,
+        workflow_type: req.workflow_type,
+        input,
+        options: StartOptions {
+            priority: options.priority.unwrap_or_default(),
+            skip_schema_validation: options.skip_schema_validation,
+            version: options.version,
+            memo: options.memo,
+        },
+    })
+}
 
-    scheduler
-        .persistence
-        .save_workflow(&workflow)
+/// An existing workflow returned for an idempotency key must have the requested type
+fn check_workflow_type(workflow_type: &str, workflow: &Workflow) -> Result<(), ApiError> {
+    if workflow.workflow_type != workflow_type {
+        return Err(ApiError::conflict(
+            "WORKFLOW_ALREADY_EXISTS",
+            &format!(
+                "Workflow '{}' already exists with a different type",
+                workflow.id
+            ),
+        )
+        .with_details(serde_json::json!({
+            "workflowId": workflow.id,
+            "workflowType": workflow.workflow_type,
+            "status": workflow.state.label(),
+        })));
+    }
+    Ok(())
+}
+
+/// A batch item that was not started
+fn batch_error(
+    index: usize,
+    status: BatchItemStatus,
+    error: ApiError,
+) -> BatchCreateWorkflowResult {
+    BatchCreateWorkflowResult {
+        index,
+        status,
+        workflow_id: None,
+        workflow_status: None,
+        error: Some(BatchItemError {
+            code: error.body.code,
+            message: error.body.message,
+        }),
+    }
+}
+
+/// POST /workflows/batch - Create many workflows in one request
+#[utoipa::path(
+    post,
+    path = "/workflows/batch",
+    request_body = Vec<CreateWorkflowRequest>,
+    responses(
+        (status = 200, description = "Per-item results, in request order", body = BatchCreateWorkflowsResponse),
+        (status = 400, description = "More items than the server's max batch size"),
+        (status = 500, description = "An atomic batch could not be saved; no workflow was created"),
+    ),
+    tag = "workflows"
+)]
+pub async fn create_workflows<P: Persistence + Clone + Send + Sync + 'static>(
+    State(scheduler): State<AppState<P>>,
+    Json(requests): Json<Vec<CreateWorkflowRequest>>,
+) -> Result<Json<BatchCreateWorkflowsResponse>, ApiError> {
+    let max_batch_size = scheduler.limits().max_batch_size as usize;
+    if requests.len() > max_batch_size {
+        return Err(ApiError::bad_request(
+            "BATCH_TOO_LARGE",
+            &format!(
+                "Batch of {} workflows exceeds the limit of {}",
+                requests.len(),
+                max_batch_size
+            ),
+        ));
+    }
+
+    // Invalid items are rejected on their own; the rest are started together
+    let mut results = Vec::with_capacity(requests.len());
+    let mut starts = Vec::new();
+    let mut workflow_types = Vec::new();
+    for (index, req) in requests.into_iter().enumerate() {
+        let workflow_type = req.workflow_type.clone();
+        match workflow_start(&scheduler, req).await {
+            Ok(start) => {
+                starts.push(start);
+                workflow_types.push((index, workflow_type));
+                results.push(None);
+            }
+            Err(e) => results.push(Some(batch_error(index, BatchItemStatus::Rejected, e))),
+        }
+    }
+
+    let atomic = scheduler.persistence.atomic_batches();
+    let started = scheduler
+        .start_workflows(starts)
         .await
         .map_err(|e| ApiError::internal(&e.to_string()))?;
+    for ((index, workflow_type), started) in workflow_types.into_iter().zip(started) {
+        let result = match started {
+            Ok(started) => match check_workflow_type(&workflow_type, &started.workflow) {
+                Ok(()) => BatchCreateWorkflowResult {
+                    index,
+                    status: if started.already_exists {
+                        BatchItemStatus::AlreadyExists
+                    } else {
+                        BatchItemStatus::Created
+                    },
+                    workflow_id: Some(started.workflow.id.clone()),
+                    workflow_status: Some(started.workflow.state.label().to_string()),
+                    error: None,
+                },
+                Err(e) => batch_error(index, BatchItemStatus::Rejected, e),
+            },
+            Err(e) => batch_error(
+                index,
+                BatchItemStatus::Failed,
+                ApiError::internal(&e.to_string()),
+            ),
+        };
+        results[index] = Some(result);
+    }
 
-    Ok(Json(CreateWorkflowResponse {
-        workflow_id,
-        status: "PENDING".to_string(),
+    Ok(Json(BatchCreateWorkflowsResponse {
+        atomic,
+        results: results.into_iter().flatten().collect(),
     }))
 }
 
@@ -99,13 +474,45 @@ pub async fn get_workflow_status<P: Persistence + Clone + Send + Sync + 'static>
         WorkflowState::Completed { .. } => ("COMPLETED".to_string(), None, None),
         WorkflowState::Failed { error } => ("FAILED".to_string(), None, Some(error.clone())),
         WorkflowState::Cancelled => ("CANCELLED".to_string(), None, None),
+        WorkflowState::Terminated { reason, .. } => {
+            ("TERMINATED".to_string(), None, Some(reason.clone()))
+        }
+    };
+    let terminated_by = match &workflow.state {
+        WorkflowState::Terminated { requested_by, .. } => requested_by.clone(),
+        _ => None,
     };
+    let child_workflow_ids = scheduler
+        .child_workflows(&workflow.id)
+        .await
+        .map_err(|e| ApiError::internal(&e.to_string()))?
+        .into_iter()
+        .map(|child| child.id)
+        .collect();
+
+    let history = workflow
+        .history
+        .into_iter()
+        .map(|transition| WorkflowTransition {
+            from: transition.from,
+            to: transition.to,
+            at: transition.at.to_rfc3339(),
+            reason: transition.reason,
+            step: transition.step,
+        })
+        .collect();
 
     Ok(Json(WorkflowStatusResponse {
         workflow_id: workflow.id,
         status,
         current_step,
         error,
+        terminated_by,
+        parent_workflow_id: workflow.parent_workflow_id,
+        child_workflow_ids,
+        version: workflow.definition_version,
+        history,
+        memo: workflow.memo.into_iter().collect(),
     }))
 }
 
@@ -129,11 +536,116 @@ pub async fn get_workflow_result<P: Persistence + Clone + Send + Sync + 'static>
     Path(workflow_id): Path<String>,
     Query(query): Query<ResultQuery>,
 ) -> Result<Json<WorkflowResultResponse>, ApiError> {
-    let timeout_duration = std::time::Duration::from_secs(query.timeout);
-    let start = std::time::Instant::now();
+    let timeout = std::time::Duration::from_secs(query.timeout);
+    let workflow =
+        match tokio::time::timeout(timeout, scheduler.wait_for_terminal(&workflow_id)).await {
+            Ok(result) => result
+                .map_err(|e| ApiError::internal(&e.to_string()))?
+                .ok_or_else(|| {
+                    ApiError::not_found(
+                        "WORKFLOW_NOT_FOUND",
+                        &format!("Workflow '{}' not found", workflow_id),
+                    )
+                })?,
+            Err(_) => return Err(ApiError::timeout("Workflow result timeout")),
+        };
 
-    loop {
-        let workflow = scheduler
+    let (output, error) = match workflow.state {
+        WorkflowState::Completed { ref result } => (result.as_json(), None),
+        WorkflowState::Failed { ref error }
+        | WorkflowState::Terminated {
+            reason: ref error, ..
+        } => (None, Some(error.clone())),
+        _ => (None, None),
+    };
+    Ok(Json(WorkflowResultResponse {
+        status: workflow.state.label().to_string(),
+        workflow_id: workflow.id,
+        output,
+        error,
+    }))
+}
+
+/// GET /workflows/{id}/events - Replay the workflow's recent events
+///
+/// Only the most recent events across all workflows are kept, so older
+/// events of long-running workflows may no longer be available.
+#[utoipa::path(
+    get,
+    path = "/workflows/{id}/events",
+    params(
+        ("id" = String, Path, description = "Workflow ID"),
+        ("since" = Option<u64>, Query, description = "Only return events at or after this Unix timestamp (seconds)"),
+    ),
+    responses(
+        (status = 200, description = "Buffered events in broadcast order", body = WorkflowEventsResponse),
+        (status = 404, description = "Workflow not found"),
+    ),
+    tag = "workflows"
+)]
+pub async fn get_workflow_events<P: Persistence + Clone + Send + Sync + 'static>(
+    State(scheduler): State<AppState<P>>,
+    Path(workflow_id): Path<String>,
+    Query(query): Query<EventsQuery>,
+) -> Result<Json<WorkflowEventsResponse>, ApiError> {
+    scheduler
+        .persistence
+        .get_workflow(&workflow_id)
+        .await
+        .map_err(|e| ApiError::internal(&e.to_string()))?
+        .ok_or_else(|| {
+            ApiError::not_found(
+                "WORKFLOW_NOT_FOUND",
+                &format!("Workflow '{}' not found", workflow_id),
+            )
+        })?;
+
+    let events = scheduler
+        .broadcaster
+        .history(&workflow_id, query.since)
+        .into_iter()
+        .map(|event| {
+            let mut payload = serde_json::to_value(&event.payload).unwrap_or_default();
+            if let Some(fields) = payload.as_object_mut() {
+                fields.remove("event_type");
+            }
+            WorkflowEventInfo {
+                event_type: event.event_type.as_str().to_string(),
+                timestamp: event.timestamp.timestamp() as u64,
+                payload,
+            }
+        })
+        .collect();
+    Ok(Json(WorkflowEventsResponse {
+        workflow_id,
+        events,
+    }))
+}
+
+/// GET /workflows/{id}/steps - Get the execution history of the workflow's steps
+///
+/// Workflows known to persistence but no longer tracked (their history was
+/// evicted) return an empty list.
+#[utoipa::path(
+    get,
+    path = "/workflows/{id}/steps",
+    params(
+        ("id" = String, Path, description = "Workflow ID"),
+        ("include_payloads" = Option<bool>, Query, description = "Include step input, output and progress details (default true)"),
+    ),
+    responses(
+        (status = 200, description = "Step executions ordered by start time", body = WorkflowStepsResponse),
+        (status = 404, description = "Workflow not found"),
+    ),
+    tag = "workflows"
+)]
+pub async fn get_workflow_steps<P: Persistence + Clone + Send + Sync + 'static>(
+    State(scheduler): State<AppState<P>>,
+    Path(workflow_id): Path<String>,
+    Query(query): Query<StepsQuery>,
+) -> Result<Json<WorkflowStepsResponse>, ApiError> {
+    let Some(execution) = scheduler.tracker.get_execution(&workflow_id).await else {
+        scheduler
             .persistence
             .get_workflow(&workflow_id)
             .await
@@ -144,61 +656,85 @@ pub async fn get_workflow_result<P: Persistence + Clone + Send + Sync + 'static>
                     &format!("Workflow '{}' not found", workflow_id),
                 )
             })?;
+        return Ok(Json(WorkflowStepsResponse {
+            workflow_id,
+            steps: Vec::new(),
+        }));
+    };
 
-        match &workflow.state {
-            WorkflowState::Completed { result } => {
-                let output = serde_json::from_slice(result).ok();
-                return Ok(Json(WorkflowResultResponse {
-                    workflow_id: workflow.id,
-                    status: "COMPLETED".to_string(),
-                    output,
-                    error: None,
-                }));
-            }
-            WorkflowState::Failed { error } => {
-                return Ok(Json(WorkflowResultResponse {
-                    workflow_id: workflow.id,
-                    status: "FAILED".to_string(),
-                    output: None,
-                    error: Some(error.clone()),
-                }));
-            }
-            WorkflowState::Cancelled => {
-                return Ok(Json(WorkflowResultResponse {
-                    workflow_id: workflow.id,
-                    status: "CANCELLED".to_string(),
-                    output: None,
-                    error: None,
-                }));
-            }
-            _ => {
-                if start.elapsed() > timeout_duration {
-                    return Err(ApiError::timeout("Workflow result timeout"));
-                }
-                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-            }
+    let mut steps: Vec<StepExecution> = execution.step_executions.into_values().collect();
+    steps.sort_by_key(|step| {
+        (
+            step.started_at.is_none(),
+            step.started_at,
+            step.step_name.clone(),
+        )
+    });
+    Ok(Json(WorkflowStepsResponse {
+        workflow_id,
+        steps: steps
+            .into_iter()
+            .map(|step| step_execution_info(step, query.include_payloads))
+            .collect(),
+    }))
+}
+
+fn step_execution_info(step: StepExecution, include_payloads: bool) -> StepExecutionInfo {
+    let duration_ms = match (step.started_at, step.completed_at) {
+        (Some(started), Some(completed)) => {
+            time::duration_between(started, completed).map(|duration| duration.as_millis() as u64)
         }
+        _ => None,
+    };
+    let error = match &step.status {
+        StepExecutionStatus::Failed { error } => Some(error.clone()),
+        _ => None,
+    };
+    StepExecutionInfo {
+        status: step.status.to_string().to_uppercase(),
+        attempt: step.attempt,
+        error,
+        started_at: step.started_at.map(|t| t.to_rfc3339()),
+        completed_at: step.completed_at.map(|t| t.to_rfc3339()),
+        duration_ms,
+        dependencies: step.dependencies,
+        input: include_payloads.then(|| step.input.as_json()).flatten(),
+        output: include_payloads
+            .then(|| step.output.and_then(|output| output.as_json()))
+            .flatten(),
+        progress: step.progress.map(|p| StepProgressInfo {
+            percent: p.percent,
+            message: p.message,
+            details: include_payloads
+                .then(|| p.details.and_then(|d| d.as_json()))
+                .flatten(),
+        }),
+        cache_hit: step.cache_hit,
+        step_name: step.step_name,
     }
 }
 
-/// DELETE /workflows/{id} - Cancel a workflow
+/// GET /workflows/{id}/graph - Get the workflow's step dependency graph
+///
+/// Nodes come from the definition version the workflow was started with, so
+/// steps that have not run yet are included as `PENDING`, merged with the
+/// tracked execution state.
 #[utoipa::path(
-    delete,
-    path = "/workflows/{id}",
+    get,
+    path = "/workflows/{id}/graph",
     params(("id" = String, Path, description = "Workflow ID")),
     responses(
-        (status = 202, description = "Workflow cancelled", body = CancelWorkflowResponse),
+        (status = 200, description = "Steps and dependency edges", body = WorkflowGraphResponse),
         (status = 404, description = "Workflow not found"),
     ),
     tag = "workflows"
 )]
-pub async fn cancel_workflow<P: Persistence + Clone + Send + Sync + 'static>(
+pub async fn get_workflow_graph<P: Persistence + Clone + Send + Sync + 'static>(
     State(scheduler): State<AppState<P>>,
     Path(workflow_id): Path<String>,
-) -> Result<Json<CancelWorkflowResponse>, ApiError> {
-    let workflow = scheduler
-        .persistence
-        .get_workflow(&workflow_id)
+) -> Result<Json<WorkflowGraphResponse>, ApiError> {
+    let graph = scheduler
+        .workflow_graph(&workflow_id)
         .await
         .map_err(|e| ApiError::internal(&e.to_string()))?
         .ok_or_else(|| {
@@ -207,22 +743,1431 @@ pub async fn cancel_workflow<P: Persistence + Clone + Send + Sync + 'static>(
                 &format!("Workflow '{}' not found", workflow_id),
             )
         })?;
+    let rfc3339 = |seconds: u64| {
+        DateTime::from_timestamp(seconds as i64, 0).map(|time: DateTime<Utc>| time.to_rfc3339())
+    };
+    Ok(Json(WorkflowGraphResponse {
+        workflow_id: graph.workflow_id,
+        workflow_type: graph.workflow_type,
+        version: graph.version,
+        nodes: graph
+            .nodes
+            .into_iter()
+            .map(|node| GraphNodeInfo {
+                status: node.status.to_uppercase(),
+                attempt: node.attempt,
+                started_at: node.started_at.and_then(rfc3339),
+                completed_at: node.completed_at.and_then(rfc3339),
+                duration_ms: node.duration_ms,
+                step_name: node.step_name,
+            })
+            .collect(),
+        edges: graph
+            .edges
+            .into_iter()
+            .map(|edge| GraphEdgeInfo {
+                from: edge.from,
+                to: edge.to,
+            })
+            .collect(),
+    }))
+}
 
-    let cancelled_state = workflow.state.cancel().ok_or_else(|| {
-        ApiError::bad_request(
-            "INVALID_STATE",
-            "Workflow cannot be cancelled in its current state",
-        )
-    })?;
-
-    scheduler
-        .persistence
-        .update_workflow_state(&workflow_id, cancelled_state)
+/// GET /workflows/{id}/export - Export the workflow's full execution record
+///
+/// Returns workflow metadata, state transitions, every step execution with
+/// its input and output, and the workflow's events from the event journal.
+/// JSON payloads are embedded as-is and other payloads as base64.
+#[utoipa::path(
+    get,
+    path = "/workflows/{id}/export",
+    params(
+        ("id" = String, Path, description = "Workflow ID"),
+        ("format" = Option<ExportFormat>, Query, description = "`json` (default) or `jsonl`, one record per line"),
+        ("max_payload_bytes" = Option<usize>, Query, description = "Truncate payloads larger than this, keeping a base64 prefix"),
+    ),
+    responses(
+        (status = 200, description = "Execution record", body = WorkflowExport),
+        (status = 404, description = "Workflow not found"),
+    ),
+    tag = "workflows"
+)]
+pub async fn export_workflow<P: Persistence + Clone + Send + Sync + 'static>(
+    State(scheduler): State<AppState<P>>,
+    Path(workflow_id): Path<String>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, ApiError> {
+    let options = ExportOptions {
+        max_payload_bytes: query.max_payload_bytes,
+    };
+    let export = export::export_workflow(&scheduler, &workflow_id, &options)
         .await
-        .map_err(|e| ApiError::internal(&e.to_string()))?;
+        .map_err(|e| ApiError::internal(&e.to_string()))?
+        .ok_or_else(|| {
+            ApiError::not_found(
+                "WORKFLOW_NOT_FOUND",
+                &format!("Workflow '{}' not found", workflow_id),
+            )
+        })?;
+
+    Ok(match query.format {
+        ExportFormat::Json => Json(export).into_response(),
+        ExportFormat::Jsonl => {
+            let body = export
+                .to_jsonl()
+                .map_err(|e| ApiError::internal(&e.to_string()))?;
+            ([(header::CONTENT_TYPE, "application/x-ndjson")], body).into_response()
+        }
+    })
+}
+
+/// POST /workflows/import - Recreate a workflow from an export for local debugging
+///
+/// The body is a document from `GET /workflows/{id}/export`, as JSON or
+/// JSONL. Completed step results and the step history are restored. The
+/// workflow stays FAILED at the step that failed (or was running when it was
+/// exported) until it is retried, or is resumed right away with `resume=true`.
+#[utoipa::path(
+    post,
+    path = "/workflows/import",
+    params(
+        ("keep_id" = Option<bool>, Query, description = "Keep the exported workflow id instead of generating a new one"),
+        ("resume" = Option<bool>, Query, description = "Re-dispatch from the failed step instead of leaving the workflow paused"),
+    ),
+    request_body(content = WorkflowExport, description = "Export document (JSON or JSONL)"),
+    responses(
+        (status = 200, description = "Workflow imported", body = ImportWorkflowResponse),
+        (status = 400, description = "Invalid document, unsupported format version or truncated payloads"),
+        (status = 409, description = "Workflow id already exists, or the workflow finished and cannot be resumed"),
+    ),
+    tag = "workflows"
+)]
+pub async fn import_workflow<P: Persistence + Clone + Send + Sync + 'static>(
+    State(scheduler): State<AppState<P>>,
+    Query(query): Query<ImportQuery>,
+    body: axum::body::Bytes,
+) -> Result<Json<ImportWorkflowResponse>, ApiError> {
+    let options = ImportOptions {
+        keep_id: query.keep_id,
+        resume: query.resume,
+    };
+    let imported = scheduler.import_workflow(&body, &options).await?;
+
+    Ok(Json(ImportWorkflowResponse {
+        workflow_id: imported.workflow.id,
+        status: imported.workflow.state.label().to_string(),
+        resumed_steps: imported.resumed_steps,
+    }))
+}
+
+/// DELETE /workflows/{id} - Cancel a workflow, or purge it with `?purge=true`
+#[utoipa::path(
+    delete,
+    path = "/workflows/{id}",
+    params(
+        ("id" = String, Path, description = "Workflow ID"),
+        ("purge" = Option<bool>, Query, description = "Delete the workflow and its history instead of cancelling it"),
+        ("force" = Option<bool>, Query, description = "Allow purging a workflow that is still pending or running"),
+    ),
+    responses(
+        (status = 202, description = "Workflow cancelled or purged", body = CancelWorkflowResponse),
+        (status = 400, description = "Purge without `force` of a workflow that is still pending or running"),
+        (status = 404, description = "Workflow not found"),
+        (status = 409, description = "Workflow has already finished and cannot be cancelled"),
+    ),
+    tag = "workflows"
+)]
+pub async fn cancel_workflow<P: Persistence + Clone + Send + Sync + 'static>(
+    State(scheduler): State<AppState<P>>,
+    Path(workflow_id): Path<String>,
+    Query(query): Query<DeleteQuery>,
+) -> Result<Json<CancelWorkflowResponse>, ApiError> {
+    if query.purge {
+        scheduler.delete_workflow(&workflow_id, query.force).await?;
+        return Ok(Json(CancelWorkflowResponse {
+            success: true,
+            message: format!("Workflow '{}' purged", workflow_id),
+        }));
+    }
+
+    scheduler.cancel_workflow(&workflow_id).await?;
 
     Ok(Json(CancelWorkflowResponse {
         success: true,
         message: format!("Workflow '{}' cancelled", workflow_id),
     }))
 }
+
+/// POST /workflows/{id}/terminate - Force a workflow into a terminal state
+///
+/// Unlike cancellation this is meant for operators: the reason and requester are
+/// recorded on the workflow, and leased tasks are revoked regardless of the current step.
+#[utoipa::path(
+    post,
+    path = "/workflows/{id}/terminate",
+    params(("id" = String, Path, description = "Workflow ID")),
+    request_body = TerminateWorkflowRequest,
+    responses(
+        (status = 200, description = "Workflow terminated", body = TerminateWorkflowResponse),
+        (status = 400, description = "Empty reason"),
+        (status = 404, description = "Workflow not found"),
+        (status = 409, description = "Workflow already finished"),
+    ),
+    tag = "workflows"
+)]
+pub async fn terminate_workflow<P: Persistence + Clone + Send + Sync + 'static>(
+    State(scheduler): State<AppState<P>>,
+    Path(workflow_id): Path<String>,
+    Json(req): Json<TerminateWorkflowRequest>,
+) -> Result<Json<TerminateWorkflowResponse>, ApiError> {
+    scheduler
+        .terminate_workflow(&workflow_id, &req.reason, req.requested_by.as_deref())
+        .await?;
+
+    Ok(Json(TerminateWorkflowResponse {
+        success: true,
+        message: format!("Workflow '{}' terminated", workflow_id),
+    }))
+}
+
+/// POST /workflows/{id}/retry - Resume a failed workflow from its failed step
+#[utoipa::path(
+    post,
+    path = "/workflows/{id}/retry",
+    params(("id" = String, Path, description = "Workflow ID")),
+    responses(
+        (status = 202, description = "Workflow resumed; completed steps are kept", body = RetryWorkflowResponse),
+        (status = 404, description = "Workflow not found"),
+        (status = 409, description = "Workflow has not failed"),
+    ),
+    tag = "workflows"
+)]
+pub async fn retry_workflow<P: Persistence + Clone + Send + Sync + 'static>(
+    State(scheduler): State<AppState<P>>,
+    Path(workflow_id): Path<String>,
+) -> Result<Json<RetryWorkflowResponse>, ApiError> {
+    let retried_steps = scheduler.retry_workflow(&workflow_id).await?;
+
+    Ok(Json(RetryWorkflowResponse {
+        success: true,
+        message: format!("Workflow '{}' retried", workflow_id),
+        retried_steps,
+    }))
+}
+
+/// POST /workflows/{id}/signal - Send a signal to a running workflow
+#[utoipa::path(
+    post,
+    path = "/workflows/{id}/signal",
+    params(("id" = String, Path, description = "Workflow ID")),
+    request_body = SignalWorkflowRequest,
+    responses(
+        (status = 200, description = "Signal delivered", body = SignalWorkflowResponse),
+        (status = 400, description = "Empty signal name or workflow already finished"),
+        (status = 404, description = "Workflow not found"),
+    ),
+    tag = "workflows"
+)]
+pub async fn signal_workflow<P: Persistence + Clone + Send + Sync + 'static>(
+    State(scheduler): State<AppState<P>>,
+    Path(workflow_id): Path<String>,
+    Json(req): Json<SignalWorkflowRequest>,
+) -> Result<Json<SignalWorkflowResponse>, ApiError> {
+    let payload = serde_json::to_vec(&req.payload)
+        .map_err(|e| ApiError::bad_request("INVALID_INPUT", &e.to_string()))?;
+    scheduler
+        .signal_workflow(&workflow_id, &req.signal_name, payload)
+        .await?;
+
+    Ok(Json(SignalWorkflowResponse {
+        success: true,
+        message: format!(
+            "Signal '{}' delivered to workflow '{}'",
+            req.signal_name, workflow_id
+        ),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::models::WorkflowOptions;
+    use crate::definition::{StepDefinition, WorkflowDefinition};
+    use crate::payload::Payload;
+    use crate::persistence::l0_memory::L0MemoryStore;
+    use crate::scheduler::SchedulerConfig;
+    use crate::state_machine::Workflow;
+    use crate::tracker::{WorkflowExecution, WorkflowTracker};
+    use axum::http::StatusCode;
+
+    async fn scheduler_with(workflows: &[(&str, WorkflowState)]) -> AppState<Arc<L0MemoryStore>> {
+        let scheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        for (id, state) in workflows {
+            let mut workflow = Workflow::new(id.to_string(), "order".to_string(), vec![]);
+            workflow.state = state.clone();
+            scheduler
+                .persistence
+                .save_workflow(&workflow)
+                .await
+                .unwrap();
+            scheduler
+                .tracker
+                .start_workflow(id.to_string(), "order".to_string())
+                .await;
+        }
+        scheduler
+    }
+
+    fn purge(force: bool) -> Query<DeleteQuery> {
+        Query(DeleteQuery { purge: true, force })
+    }
+
+    #[tokio::test]
+    async fn test_workflow_events_replay_journal() {
+        let scheduler = scheduler_with(&[("wf-1", WorkflowState::Pending)]).await;
+        let broadcaster = &scheduler.broadcaster;
+        broadcaster
+            .broadcast_step_failed("wf-1", "order", "charge", "declined".to_string(), 2)
+            .await;
+        broadcaster
+            .broadcast_step_started("wf-2", "order", "charge", vec![], 1)
+            .await;
+        let events = |id: &str, since: Option<u64>| {
+            get_workflow_events(
+                State(scheduler.clone()),
+                Path(id.to_string()),
+                Query(EventsQuery { since }),
+            )
+        };
+
+        let Json(response) = events("wf-1", None).await.unwrap();
+        assert_eq!(response.events.len(), 1);
+        let event = &response.events[0];
+        assert_eq!(event.event_type, "step_failed");
+        assert_eq!(event.payload["step_name"], "charge");
+        assert_eq!(event.payload["attempt"], 2);
+        assert!(event.payload.get("event_type").is_none());
+
+        let Json(response) = events("wf-1", Some(u64::MAX)).await.unwrap();
+        assert!(response.events.is_empty());
+        let err = events("missing", None).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_workflow_graph_includes_pending_steps() {
+        use crate::definition::{StepDefinition, WorkflowDefinition};
+
+        let scheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        scheduler
+            .register_definition(WorkflowDefinition::new(
+                "order",
+                vec![
+                    StepDefinition::new("reserve"),
+                    StepDefinition::new("charge").depends_on(["reserve"]),
+                ],
+            ))
+            .await
+            .unwrap();
+        let workflow = Workflow::new("wf-1".to_string(), "order".to_string(), vec![])
+            .with_definition_version(1);
+        scheduler
+            .persistence
+            .save_workflow(&workflow)
+            .await
+            .unwrap();
+        scheduler
+            .tracker
+            .start_workflow("wf-1".to_string(), "order".to_string())
+            .await;
+        scheduler
+            .tracker
+            .step_started("wf-1", "reserve", vec![], vec![])
+            .await
+            .unwrap();
+
+        let Json(graph) = get_workflow_graph(State(scheduler.clone()), Path("wf-1".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(graph.version, Some(1));
+        let statuses: Vec<_> = graph
+            .nodes
+            .iter()
+            .map(|node| (node.step_name.as_str(), node.status.as_str()))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![("reserve", "RUNNING"), ("charge", "PENDING")]
+        );
+        assert!(graph.nodes[0].started_at.is_some());
+        assert_eq!(graph.edges.len(), 1);
+        assert_eq!(
+            (graph.edges[0].from.as_str(), graph.edges[0].to.as_str()),
+            ("reserve", "charge")
+        );
+
+        let err = get_workflow_graph(State(scheduler), Path("missing".to_string()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_workflow_steps_sorted_by_start_time() {
+        let store = Arc::new(L0MemoryStore::new());
+        let workflow = Workflow::new("wf-1".to_string(), "order".to_string(), vec![]);
+        store.save_workflow(&workflow).await.unwrap();
+        store
+            .save_workflow(&Workflow::new(
+                "evicted".to_string(),
+                "order".to_string(),
+                vec![],
+            ))
+            .await
+            .unwrap();
+        let at = |seconds: i64| DateTime::from_timestamp(seconds, 0).unwrap();
+        let step = |name: &str, started: Option<i64>, completed: Option<i64>| StepExecution {
+            step_name: name.to_string(),
+            status: if completed.is_some() {
+                StepExecutionStatus::Completed
+            } else if started.is_some() {
+                StepExecutionStatus::Running
+            } else {
+                StepExecutionStatus::Pending
+            },
+            started_at: started.map(at),
+            completed_at: completed.map(at),
+            input: Payload::from_json(&serde_json::json!({ "sku": "A-1" })),
+            output: completed.map(|_| Payload::from_json(&serde_json::json!({ "ok": true }))),
+            attempt: 1,
+            dependencies: vec![],
+            progress: None,
+            last_heartbeat_at: None,
+            timer_fire_at: None,
+            rejections: Vec::new(),
+            cache_hit: false,
+        };
+        store
+            .save_execution(&WorkflowExecution {
+                workflow_id: "wf-1".to_string(),
+                workflow_type: "order".to_string(),
+                step_executions: [
+                    step("ship", Some(1_700_000_030), None),
+                    step("charge", Some(1_700_000_000), Some(1_700_000_002)),
+                    step("notify", None, None),
+                ]
+                .into_iter()
+                .map(|step| (step.step_name.clone(), step))
+                .collect(),
+                started_at: at(1_700_000_000),
+                completed_at: None,
+                current_step: Some("ship".to_string()),
+                parent_workflow_id: None,
+                child_workflow_ids: vec![],
+            })
+            .await
+            .unwrap();
+        let tracker = WorkflowTracker::with_store(store.clone()).await.unwrap();
+        let scheduler = Arc::new(Scheduler::new(store).with_tracker(tracker));
+        let steps = |id: &str, include_payloads: bool| {
+            get_workflow_steps(
+                State(scheduler.clone()),
+                Path(id.to_string()),
+                Query(StepsQuery { include_payloads }),
+            )
+        };
+
+        let Json(response) = steps("wf-1", true).await.unwrap();
+        let names: Vec<_> = response
+            .steps
+            .iter()
+            .map(|s| s.step_name.as_str())
+            .collect();
+        assert_eq!(names, vec!["charge", "ship", "notify"]);
+        let charge = &response.steps[0];
+        assert_eq!(charge.status, "COMPLETED");
+        assert_eq!(charge.duration_ms, Some(2000));
+        assert_eq!(
+            charge.started_at.as_deref(),
+            Some("2023-11-14T22:13:20+00:00")
+        );
+        assert_eq!(charge.output, Some(serde_json::json!({ "ok": true })));
+        assert_eq!(response.steps[1].duration_ms, None);
+
+        let Json(response) = steps("wf-1", false).await.unwrap();
+        assert!(response
+            .steps
+            .iter()
+            .all(|s| s.input.is_none() && s.output.is_none()));
+
+        let Json(response) = steps("evicted", true).await.unwrap();
+        assert!(response.steps.is_empty());
+        let err = steps("missing", true).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_list_workflows_paginates() {
+        let scheduler = scheduler_with(&[
+            ("wf-1", WorkflowState::Pending),
+            ("wf-2", WorkflowState::Cancelled),
+            ("wf-3", WorkflowState::Pending),
+        ])
+        .await;
+
+        let ([(_, total)], Json(page)) = list_workflows(
+            State(scheduler.clone()),
+            Query(ListQuery {
+                offset: 1,
+                limit: Some(1),
+                ..Default::default()
+            }),
+            Query(Vec::new()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(page.workflows.len(), 1);
+        assert_eq!(page.workflows[0].workflow_id, "wf-2");
+        assert_eq!(page.workflows[0].status, "CANCELLED");
+        assert_eq!(total, "3");
+
+        let ([(_, total)], Json(pending)) = list_workflows(
+            State(scheduler.clone()),
+            Query(ListQuery {
+                order: Some("started_at_desc".to_string()),
+                state: Some("pending".to_string()),
+                ..Default::default()
+            }),
+            Query(Vec::new()),
+        )
+        .await
+        .unwrap();
+        let ids: Vec<_> = pending.workflows.iter().map(|w| &w.workflow_id).collect();
+        assert_eq!(ids, vec!["wf-3", "wf-1"]);
+        assert_eq!(pending.limit, scheduler.limits().max_batch_size as usize);
+        assert_eq!(total, "2");
+
+        let err = list_workflows(
+            State(scheduler),
+            Query(ListQuery {
+                state: Some("sleeping".to_string()),
+                ..Default::default()
+            }),
+            Query(Vec::new()),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        assert!(err.body.message.contains("expected pending, running"));
+    }
+
+    #[tokio::test]
+    async fn test_list_filters_compose() {
+        let scheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        let day = |d: u32| {
+            chrono::NaiveDate::from_ymd_opt(2024, 1, d)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+                .and_utc()
+        };
+        for (id, workflow_type, state, started) in [
+            (
+                "wf-1",
+                "order",
+                WorkflowState::Running { current_step: None },
+                1,
+            ),
+            (
+                "wf-2",
+                "order",
+                WorkflowState::Running { current_step: None },
+                2,
+            ),
+            ("wf-3", "order", WorkflowState::Cancelled, 2),
+            (
+                "wf-4",
+                "refund",
+                WorkflowState::Running { current_step: None },
+                2,
+            ),
+            (
+                "wf-5",
+                "order",
+                WorkflowState::Running { current_step: None },
+                3,
+            ),
+        ] {
+            let mut workflow = Workflow::new(id.to_string(), workflow_type.to_string(), vec![]);
+            workflow.state = state;
+            workflow.started_at = day(started);
+            scheduler
+                .persistence
+                .save_workflow(&workflow)
+                .await
+                .unwrap();
+        }
+        let list = |query: ListQuery| {
+            list_workflows(State(scheduler.clone()), Query(query), Query(Vec::new()))
+        };
+
+        let ([(header, total)], Json(page)) = list(ListQuery {
+            state: Some("RUNNING".to_string()),
+            workflow_type: Some("order".to_string()),
+            started_after: Some("2024-01-02T00:00:00Z".to_string()),
+            started_before: Some("2024-01-03T00:00:00+00:00".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert_eq!(header, TOTAL_COUNT_HEADER);
+        assert_eq!(total, "1");
+        let ids: Vec<_> = page.workflows.iter().map(|w| &w.workflow_id).collect();
+        assert_eq!(ids, vec!["wf-2"]);
+
+        let ([(_, total)], Json(page)) = list(ListQuery {
+            started_after: Some("2024-01-02T00:00:00Z".to_string()),
+            limit: Some(2),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert_eq!(page.workflows.len(), 2);
+        assert_eq!(total, "4");
+
+        let err = list(ListQuery {
+            started_before: Some("yesterday".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_purge_removes_workflow_everywhere() {
+        let scheduler = scheduler_with(&[(
+            "wf-1",
+            WorkflowState::Completed {
+                result: vec![].into(),
+            },
+        )])
+        .await;
+
+        let Json(response) = cancel_workflow(
+            State(scheduler.clone()),
+            Path("wf-1".to_string()),
+            purge(false),
+        )
+        .await
+        .unwrap();
+        assert!(response.success);
+
+        assert!(scheduler
+            .persistence
+            .list_workflows(None)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(scheduler.tracker.get_execution("wf-1").await.is_none());
+
+        let err = get_workflow_status(State(scheduler), Path("wf-1".to_string()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_purge_running_requires_force() {
+        let scheduler =
+            scheduler_with(&[("wf-1", WorkflowState::Running { current_step: None })]).await;
+
+        let err = cancel_workflow(
+            State(scheduler.clone()),
+            Path("wf-1".to_string()),
+            purge(false),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        assert!(
+            get_workflow_status(State(scheduler.clone()), Path("wf-1".to_string()))
+                .await
+                .is_ok()
+        );
+
+        let Json(response) = cancel_workflow(
+            State(scheduler.clone()),
+            Path("wf-1".to_string()),
+            purge(true),
+        )
+        .await
+        .unwrap();
+        assert!(response.success);
+        assert!(scheduler
+            .persistence
+            .get_workflow("wf-1")
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_delete_without_purge_cancels() {
+        let scheduler = scheduler_with(&[("wf-1", WorkflowState::Pending)]).await;
+
+        let Json(response) = cancel_workflow(
+            State(scheduler.clone()),
+            Path("wf-1".to_string()),
+            Query(DeleteQuery::default()),
+        )
+        .await
+        .unwrap();
+        assert!(response.success);
+
+        let workflow = scheduler
+            .persistence
+            .get_workflow("wf-1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(workflow.state, WorkflowState::Cancelled));
+    }
+
+    #[tokio::test]
+    async fn test_export_workflow_over_rest() {
+        let scheduler = scheduler_with(&[("wf-1", WorkflowState::Cancelled)]).await;
+        scheduler
+            .broadcaster
+            .broadcast_step_failed("wf-1", "order", "charge", "declined".to_string(), 1)
+            .await;
+        let export = |id: &str, format: ExportFormat| {
+            export_workflow(
+                State(scheduler.clone()),
+                Path(id.to_string()),
+                Query(ExportQuery {
+                    format,
+                    max_payload_bytes: None,
+                }),
+            )
+        };
+
+        let response = export("wf-1", ExportFormat::Jsonl).await.unwrap();
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "application/x-ndjson"
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let records: Vec<serde_json::Value> = body
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        let kinds: Vec<_> = records
+            .iter()
+            .map(|r| r["record"].as_str().unwrap())
+            .collect();
+        assert_eq!(kinds, ["workflow", "transition", "transition", "event"]);
+        assert_eq!(records[0]["status"], "CANCELLED");
+
+        let response = export("wf-1", ExportFormat::Json).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let err = export("missing", ExportFormat::Json).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_import_workflow_over_rest() {
+        let scheduler = scheduler_with(&[("wf-1", WorkflowState::Cancelled)]).await;
+        let response = export_workflow(
+            State(scheduler.clone()),
+            Path("wf-1".to_string()),
+            Query(ExportQuery::default()),
+        )
+        .await
+        .unwrap();
+        let document = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let import = |keep_id: bool, resume: bool| {
+            import_workflow(
+                State(scheduler.clone()),
+                Query(ImportQuery { keep_id, resume }),
+                document.clone(),
+            )
+        };
+
+        let Json(imported) = import(false, false).await.unwrap();
+        assert_ne!(imported.workflow_id, "wf-1");
+        assert_eq!(imported.status, "CANCELLED");
+        assert!(imported.resumed_steps.is_empty());
+
+        let err = import(true, false).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::CONFLICT);
+        assert_eq!(err.body.code, "WORKFLOW_EXISTS");
+        let err = import(false, true).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::CONFLICT);
+        assert_eq!(err.body.code, "INVALID_STATE");
+
+        let err = import_workflow(
+            State(scheduler.clone()),
+            Query(ImportQuery::default()),
+            axum::body::Bytes::from_static(b"not an export"),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_terminate_workflow_over_rest() {
+        let scheduler = scheduler_with(&[
+            ("wf-1", WorkflowState::Running { current_step: None }),
+            ("wf-2", WorkflowState::Cancelled),
+        ])
+        .await;
+        let terminate = |workflow_id: &str, reason: &str| {
+            terminate_workflow(
+                State(scheduler.clone()),
+                Path(workflow_id.to_string()),
+                Json(TerminateWorkflowRequest {
+                    reason: reason.to_string(),
+                    requested_by: Some("ops".to_string()),
+                }),
+            )
+        };
+
+        let err = terminate("wf-1", "").await.unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        let Json(response) = terminate("wf-1", "runaway loop").await.unwrap();
+        assert!(response.success);
+
+        let Json(status) = get_workflow_status(State(scheduler.clone()), Path("wf-1".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(status.status, "TERMINATED");
+        assert_eq!(status.error.as_deref(), Some("runaway loop"));
+        assert_eq!(status.terminated_by.as_deref(), Some("ops"));
+        let last = status.history.last().unwrap();
+        assert_eq!(
+            (last.from.as_str(), last.to.as_str()),
+            ("RUNNING", "TERMINATED")
+        );
+        assert_eq!(last.reason.as_deref(), Some("runaway loop"));
+        let (_, Json(page)) = list_workflows(
+            State(scheduler.clone()),
+            Query(ListQuery {
+                state: Some("terminated".to_string()),
+                ..Default::default()
+            }),
+            Query(Vec::new()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(page.workflows.len(), 1);
+        assert_eq!(page.workflows[0].status, "TERMINATED");
+
+        let err = terminate("wf-2", "too late").await.unwrap_err();
+        assert_eq!(err.status, StatusCode::CONFLICT);
+        let err = terminate("missing", "gone").await.unwrap_err();
+        assert_eq!(err.status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_retry_workflow_over_rest() {
+        let scheduler = scheduler_with(&[
+            (
+                "wf-1",
+                WorkflowState::Failed {
+                    error: "declined".to_string(),
+                },
+            ),
+            ("wf-2", WorkflowState::Running { current_step: None }),
+        ])
+        .await;
+        let retry = |workflow_id: &str| {
+            retry_workflow(State(scheduler.clone()), Path(workflow_id.to_string()))
+        };
+
+        let Json(response) = retry("wf-1").await.unwrap();
+        assert!(response.success);
+        let workflow = scheduler
+            .persistence
+            .get_workflow("wf-1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(workflow.state, WorkflowState::Running { .. }));
+
+        let err = retry("wf-2").await.unwrap_err();
+        assert_eq!(err.status, StatusCode::CONFLICT);
+        let err = retry("missing").await.unwrap_err();
+        assert_eq!(err.status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_signal_workflow_over_rest() {
+        let scheduler = scheduler_with(&[
+            ("wf-1", WorkflowState::Running { current_step: None }),
+            ("wf-2", WorkflowState::Cancelled),
+        ])
+        .await;
+        let signal = |workflow_id: &str, name: &str| {
+            signal_workflow(
+                State(scheduler.clone()),
+                Path(workflow_id.to_string()),
+                Json(SignalWorkflowRequest {
+                    signal_name: name.to_string(),
+                    payload: serde_json::json!({ "approver": "alice" }),
+                }),
+            )
+        };
+
+        let Json(response) = signal("wf-1", "approved").await.unwrap();
+        assert!(response.success);
+        let signals = scheduler.persistence.get_signals("wf-1").await.unwrap();
+        assert_eq!(signals.len(), 1);
+        assert_eq!(signals[0].name, "approved");
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&signals[0].payload).unwrap(),
+            serde_json::json!({ "approver": "alice" })
+        );
+
+        let err = signal("wf-1", "").await.unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        let err = signal("wf-2", "approved").await.unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        let err = signal("missing", "approved").await.unwrap_err();
+        assert_eq!(err.status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_result_carries_output_step_over_rest() {
+        use crate::api::handlers::steps::apply_complete;
+        use crate::api::models::{CompleteStepRequest, CreateWorkflowRequest};
+        use crate::definition::{StepDefinition, WorkflowDefinition};
+
+        let scheduler = scheduler_with(&[]).await;
+        scheduler
+            .register_definition(
+                WorkflowDefinition::new(
+                    "order",
+                    vec![StepDefinition::new("charge"), StepDefinition::new("notify")],
+                )
+                .with_output_step("charge"),
+            )
+            .await
+            .unwrap();
+        scheduler
+            .register_worker(
+                "worker-1".to_string(),
+                "shop".to_string(),
+                "default".to_string(),
+                vec!["order".to_string()],
+                vec![],
+            )
+            .await;
+
+        let Json(created) = create_workflow(
+            State(scheduler.clone()),
+            Json(CreateWorkflowRequest {
+                workflow_type: "order".to_string(),
+                input: serde_json::json!({ "amount": 42 }),
+                options: None,
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(created.status, "RUNNING");
+
+        for (step, output) in [
+            ("charge", serde_json::json!({ "receipt": "r-1" })),
+            ("notify", serde_json::json!({ "sent": true })),
+        ] {
+            let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+            assert_eq!(tasks.len(), 1);
+            assert_eq!(tasks[0].step_name, step);
+            let req = CompleteStepRequest {
+                output: Some(output),
+                output_base64: None,
+                error: None,
+                worker_id: Some("worker-1".to_string()),
+                start_children: vec![],
+            };
+            apply_complete(&scheduler, &tasks[0].task_id, req)
+                .await
+                .unwrap();
+        }
+
+        let Json(result) = get_workflow_result(
+            State(scheduler.clone()),
+            Path(created.workflow_id),
+            Query(ResultQuery { timeout: 1 }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.status, "COMPLETED");
+        assert_eq!(result.output, Some(serde_json::json!({ "receipt": "r-1" })));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_result_returns_on_completion_event() {
+        let scheduler =
+            scheduler_with(&[("wf-1", WorkflowState::Running { current_step: None })]).await;
+        scheduler
+            .register_worker(
+                "worker-1".to_string(),
+                "shop".to_string(),
+                "default".to_string(),
+                vec!["order".to_string()],
+                vec![],
+            )
+            .await;
+        let tasks = scheduler.poll_tasks("worker-1", 1).await.unwrap();
+
+        let started = tokio::time::Instant::now();
+        let waiter = tokio::spawn(get_workflow_result(
+            State(scheduler.clone()),
+            Path("wf-1".to_string()),
+            Query(ResultQuery { timeout: 30 }),
+        ));
+        tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+        scheduler
+            .complete_task(&tasks[0].task_id, br#""shipped""#.to_vec())
+            .await
+            .unwrap();
+
+        let Json(result) = waiter.await.unwrap().unwrap();
+        assert_eq!(result.status, "COMPLETED");
+        assert_eq!(result.output, Some(serde_json::json!("shipped")));
+        // Answered at the completion instant rather than at a later poll tick
+        assert_eq!(started.elapsed(), std::time::Duration::from_millis(250));
+
+        let err = get_workflow_result(
+            State(scheduler_with(&[("wf-2", WorkflowState::Pending)]).await),
+            Path("wf-2".to_string()),
+            Query(ResultQuery { timeout: 1 }),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.status, StatusCode::REQUEST_TIMEOUT);
+        assert_eq!(err.body.code, "TIMEOUT");
+    }
+
+    #[tokio::test]
+    async fn test_create_with_existing_id_returns_existing_workflow() {
+        let scheduler = scheduler_with(&[]).await;
+        let request = |workflow_type: &str| {
+            Json(CreateWorkflowRequest {
+                workflow_type: workflow_type.to_string(),
+                input: serde_json::json!({ "sku": "A-1" }),
+                options: Some(WorkflowOptions {
+                    workflow_id: Some("order-42".to_string()),
+                    priority: None,
+                    skip_schema_validation: false,
+                    version: None,
+                    dynamic: false,
+                    memo: HashMap::new(),
+                }),
+            })
+        };
+
+        let Json(created) = create_workflow(State(scheduler.clone()), request("order"))
+            .await
+            .unwrap();
+        assert_eq!(created.workflow_id, "order-42");
+        assert_eq!(created.status, "RUNNING");
+        assert!(!created.already_exists);
+        let execution = scheduler.tracker.get_execution("order-42").await;
+        assert!(execution.is_some());
+
+        scheduler
+            .persistence
+            .update_workflow_state("order-42", WorkflowState::Cancelled)
+            .await
+            .unwrap();
+        let Json(existing) = create_workflow(State(scheduler.clone()), request("order"))
+            .await
+            .unwrap();
+        assert_eq!(existing.workflow_id, "order-42");
+        assert_eq!(existing.status, "CANCELLED");
+        assert!(existing.already_exists);
+
+        // 同一 id 但类型不同的请求不是重试
+        let err = create_workflow(State(scheduler.clone()), request("refund"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::CONFLICT);
+        assert_eq!(err.body.code, "WORKFLOW_ALREADY_EXISTS");
+        assert_eq!(err.body.details.unwrap()["workflowType"], "order");
+        let workflow = scheduler
+            .persistence
+            .get_workflow("order-42")
+            .await
+            .unwrap();
+        assert_eq!(workflow.unwrap().state, WorkflowState::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn test_create_rejects_oversized_input() {
+        let scheduler = Arc::new(Scheduler::with_config(
+            Arc::new(L0MemoryStore::new()),
+            SchedulerConfig {
+                max_input_bytes: 16,
+                ..Default::default()
+            },
+        ));
+        let request = Json(CreateWorkflowRequest {
+            workflow_type: "order".to_string(),
+            input: serde_json::json!({ "note": "x".repeat(32) }),
+            options: None,
+        });
+
+        let err = create_workflow(State(scheduler.clone()), request)
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(err.body.code, "PAYLOAD_TOO_LARGE");
+        assert_eq!(err.body.details.unwrap()["limit"], 16);
+        assert!(scheduler
+            .persistence
+            .list_workflows(None)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_memo_is_validated_returned_and_filterable() {
+        let scheduler = scheduler_with(&[]).await;
+        let create = |id: &str, memo: &[(&str, &str)]| {
+            let request = Json(CreateWorkflowRequest {
+                workflow_type: "order".to_string(),
+                input: serde_json::json!({}),
+                options: Some(WorkflowOptions {
+                    workflow_id: Some(id.to_string()),
+                    memo: memo
+                        .iter()
+                        .map(|(key, value)| (key.to_string(), value.to_string()))
+                        .collect(),
+                    ..Default::default()
+                }),
+            });
+            create_workflow(State(scheduler.clone()), request)
+        };
+        for (id, customer) in [("wf-1", "123"), ("wf-2", "456")] {
+            let Json(created) = create(id, &[("customer_id", customer), ("region", "eu")])
+                .await
+                .unwrap();
+            assert!(!created.already_exists);
+        }
+
+        let err = create("wf-3", &[("region", &"x".repeat(1000))])
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        assert_eq!(err.body.code, "INVALID_MEMO");
+
+        let Json(status) = get_workflow_status(State(scheduler.clone()), Path("wf-1".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(status.memo["customer_id"], "123");
+        assert_eq!(status.memo["region"], "eu");
+
+        // `memo.` 前缀的参数是筛选条件，其他未知参数被忽略
+        let pairs = vec![
+            ("memo.customer_id".to_string(), "123".to_string()),
+            ("customer_id".to_string(), "456".to_string()),
+        ];
+        let ([(_, total)], Json(page)) = list_workflows(
+            State(scheduler.clone()),
+            Query(ListQuery::default()),
+            Query(pairs),
+        )
+        .await
+        .unwrap();
+        assert_eq!(total, "1");
+        assert_eq!(page.workflows[0].workflow_id, "wf-1");
+        assert_eq!(page.workflows[0].memo["region"], "eu");
+    }
+
+    #[tokio::test]
+    async fn test_child_workflows_over_rest() {
+        use crate::api::handlers::steps::apply_complete;
+        use crate::api::models::{CompleteStepRequest, StartChildWorkflow};
+
+        let scheduler = scheduler_with(&[
+            ("wf-1", WorkflowState::Running { current_step: None }),
+            ("wf-2", WorkflowState::Running { current_step: None }),
+        ])
+        .await;
+        scheduler
+            .register_worker(
+                "worker-1".to_string(),
+                "shop".to_string(),
+                "default".to_string(),
+                vec!["order".to_string()],
+                vec![],
+            )
+            .await;
+
+        let tasks = scheduler.poll_tasks("worker-1", 1).await.unwrap();
+        let parent_id = tasks[0].workflow_id.clone();
+        let req = CompleteStepRequest {
+            output: None,
+            output_base64: None,
+            error: None,
+            worker_id: Some("worker-1".to_string()),
+            start_children: vec![StartChildWorkflow {
+                workflow_type: "shipment".to_string(),
+                input: serde_json::json!({ "parcel": 1 }),
+            }],
+        };
+        apply_complete(&scheduler, &tasks[0].task_id, req)
+            .await
+            .unwrap();
+
+        let Json(parent) = get_workflow_status(State(scheduler.clone()), Path(parent_id.clone()))
+            .await
+            .unwrap();
+        assert_eq!(parent.child_workflow_ids.len(), 1);
+        assert!(parent.parent_workflow_id.is_none());
+        let Json(child) = get_workflow_status(
+            State(scheduler.clone()),
+            Path(parent.child_workflow_ids[0].clone()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(child.status, "RUNNING");
+        assert_eq!(child.parent_workflow_id, Some(parent_id));
+
+        // 取消父 workflow 级联取消子 workflow
+        let other = if parent.workflow_id == "wf-1" {
+            "wf-2"
+        } else {
+            "wf-1"
+        };
+        let children = scheduler
+            .start_child_workflows(
+                other,
+                vec![crate::child::ChildWorkflowSpec {
+                    workflow_type: "shipment".to_string(),
+                    input: vec![],
+                }],
+            )
+            .await
+            .unwrap();
+        let Json(response) = cancel_workflow(
+            State(scheduler.clone()),
+            Path(other.to_string()),
+            Query(DeleteQuery::default()),
+        )
+        .await
+        .unwrap();
+        assert!(response.success);
+        let child = scheduler
+            .persistence
+            .get_workflow(&children[0])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(child.state, WorkflowState::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn test_create_pins_definition_version() {
+        let scheduler = scheduler_with(&[]).await;
+        for (version, steps) in [(1, vec!["reserve"]), (2, vec!["reserve", "charge"])] {
+            scheduler
+                .register_definition(
+                    WorkflowDefinition::new(
+                        "order",
+                        steps.into_iter().map(StepDefinition::new).collect(),
+                    )
+                    .with_version(version),
+                )
+                .await
+                .unwrap();
+        }
+        let request = |version: Option<u32>| {
+            Json(CreateWorkflowRequest {
+                workflow_type: "order".to_string(),
+                input: serde_json::json!({}),
+                options: Some(WorkflowOptions {
+                    version,
+                    ..Default::default()
+                }),
+            })
+        };
+
+        for (requested, pinned) in [(None, 2), (Some(1), 1)] {
+            let Json(created) = create_workflow(State(scheduler.clone()), request(requested))
+                .await
+                .unwrap();
+            let Json(status) =
+                get_workflow_status(State(scheduler.clone()), Path(created.workflow_id))
+                    .await
+                    .unwrap();
+            assert_eq!(status.version, Some(pinned));
+        }
+
+        let err = create_workflow(State(scheduler), request(Some(3)))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        assert_eq!(err.body.code, "UNKNOWN_WORKFLOW_VERSION");
+    }
+
+    #[tokio::test]
+    async fn test_batch_create_reports_each_item() {
+        let scheduler = scheduler_with(&[]).await;
+        let request = |workflow_type: &str, workflow_id: Option<&str>| CreateWorkflowRequest {
+            workflow_type: workflow_type.to_string(),
+            input: serde_json::json!({ "sku": "A-1" }),
+            options: Some(WorkflowOptions {
+                workflow_id: workflow_id.map(str::to_string),
+                ..Default::default()
+            }),
+        };
+        scheduler
+            .start_workflow(
+                Some("refund-1".to_string()),
+                "refund".to_string(),
+                vec![],
+                StartOptions::default(),
+            )
+            .await
+            .unwrap();
+
+        let Json(response) = create_workflows(
+            State(scheduler.clone()),
+            Json(vec![
+                request("order", Some("order-1")),
+                request("order", None),
+                request("order", Some("order-1")),
+                request("order", Some("refund-1")),
+            ]),
+        )
+        .await
+        .unwrap();
+        assert!(!response.atomic);
+        let statuses: Vec<_> = response.results.iter().map(|r| r.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                BatchItemStatus::Created,
+                BatchItemStatus::Created,
+                BatchItemStatus::AlreadyExists,
+                BatchItemStatus::Rejected,
+            ]
+        );
+        assert_eq!(response.results[0].workflow_id.as_deref(), Some("order-1"));
+        assert_eq!(response.results[2].workflow_id.as_deref(), Some("order-1"));
+        assert_eq!(
+            response.results[0].workflow_status.as_deref(),
+            Some("RUNNING")
+        );
+        let error = response.results[3].error.as_ref().unwrap();
+        assert_eq!(error.code, "WORKFLOW_ALREADY_EXISTS");
+        let created = response.results[1].workflow_id.clone().unwrap();
+        assert!(scheduler.tracker.get_execution(&created).await.is_some());
+
+        // 定义注册后未知类型的条目单独被拒绝，其余照常启动
+        scheduler
+            .register_definition(WorkflowDefinition::new(
+                "order",
+                vec![StepDefinition::new("reserve")],
+            ))
+            .await
+            .unwrap();
+        // 设置 dynamic 的条目可以启动未定义的类型
+        let mut dynamic = request("shipment", None);
+        dynamic.options = Some(WorkflowOptions {
+            dynamic: true,
+            ..Default::default()
+        });
+        let Json(response) = create_workflows(
+            State(scheduler.clone()),
+            Json(vec![
+                request("shipment", None),
+                request("order", None),
+                dynamic,
+            ]),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.results[0].status, BatchItemStatus::Rejected);
+        assert_eq!(response.results[0].index, 0);
+        let error = response.results[0].error.as_ref().unwrap();
+        assert_eq!(error.code, "UNKNOWN_WORKFLOW_TYPE");
+        assert!(response.results[0].workflow_id.is_none());
+        assert_eq!(response.results[1].status, BatchItemStatus::Created);
+        assert_eq!(response.results[1].index, 1);
+        assert_eq!(response.results[2].status, BatchItemStatus::Created);
+    }
+
+    #[tokio::test]
+    async fn test_batch_create_is_bounded() {
+        let scheduler = scheduler_with(&[]).await;
+        let max = scheduler.limits().max_batch_size as usize;
+        let requests = (0..=max)
+            .map(|_| CreateWorkflowRequest {
+                workflow_type: "order".to_string(),
+                input: serde_json::json!({}),
+                options: None,
+            })
+            .collect();
+
+        let err = create_workflows(State(scheduler.clone()), Json(requests))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        assert_eq!(err.body.code, "BATCH_TOO_LARGE");
+        assert!(scheduler
+            .persistence
+            .list_workflows(None)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_batch_create_is_atomic_on_sqlite() {
+        use crate::persistence::sqlite::SqliteStore;
+
+        let store = SqliteStore::in_memory().await.unwrap();
+        let scheduler = Arc::new(Scheduler::new(Arc::new(store)));
+        let requests = ["order-1", "order-2"]
+            .into_iter()
+            .map(|id| CreateWorkflowRequest {
+                workflow_type: "order".to_string(),
+                input: serde_json::json!({}),
+                options: Some(WorkflowOptions {
+                    workflow_id: Some(id.to_string()),
+                    ..Default::default()
+                }),
+            })
+            .collect();
+
+        let Json(response) = create_workflows(State(scheduler.clone()), Json(requests))
+            .await
+            .unwrap();
+        assert!(response.atomic);
+        assert!(response
+            .results
+            .iter()
+            .all(|r| r.status == BatchItemStatus::Created));
+        assert_eq!(
+            scheduler
+                .persistence
+                .list_workflows(None)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+}
@@ -1,5 +1,8 @@
+pub mod auth;
+pub mod cors;
 pub mod error;
 pub mod handlers;
 pub mod models;
+pub mod rate_limit;
 pub mod routes;
 pub mod websocket;
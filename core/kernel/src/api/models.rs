@@ -1,6 +1,11 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use utoipa::ToSchema;
 
+use crate::definition::WorkflowDefinitionSpec;
+use crate::schedule::OverlapPolicy;
+use crate::state_machine::Priority;
+
 // === Workflow Models ===
 
 #[derive(Debug, Deserialize, ToSchema)]
@@ -12,10 +17,29 @@ pub struct CreateWorkflowRequest {
     pub options: Option<WorkflowOptions>,
 }
 
-#[derive(Debug, Deserialize, ToSchema)]
+#[derive(Debug, Default, Deserialize, ToSchema)]
 pub struct WorkflowOptions {
     #[serde(rename = "workflowId")]
     pub workflow_id: Option<String>,
+    /// Dispatch priority: `low`, `normal` (default) or `high`
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    pub priority: Option<Priority>,
+    /// Skip validating step input and output against the resources' JSON Schemas;
+    /// meant for emergencies only
+    #[serde(rename = "skipSchemaValidation", default)]
+    pub skip_schema_validation: bool,
+    /// Definition version to run; defaults to the latest registered version
+    #[serde(default)]
+    pub version: Option<u32>,
+    /// Start a type with no registered definition; an SDK worker runs it as a single
+    /// `start` step
+    #[serde(default)]
+    pub dynamic: bool,
+    /// Small string key-value metadata kept with the workflow, e.g. `customer_id`;
+    /// list filters match it with `memo.<key>=<value>`
+    #[serde(default)]
+    pub memo: HashMap<String, String>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -23,6 +47,53 @@ pub struct CreateWorkflowResponse {
     #[serde(rename = "workflowId")]
     pub workflow_id: String,
     pub status: String,
+    /// A workflow with the requested id already existed; it is returned unchanged
+    #[serde(rename = "alreadyExists")]
+    pub already_exists: bool,
+}
+
+/// Outcome of one item of `POST /workflows/batch`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum BatchItemStatus {
+    /// The workflow was started
+    Created,
+    /// A workflow with the requested id already existed; it is returned unchanged
+    AlreadyExists,
+    /// The item failed validation (or its id belongs to a workflow of another type)
+    Rejected,
+    /// The item was valid but could not be saved
+    Failed,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BatchItemError {
+    pub code: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BatchCreateWorkflowResult {
+    /// Position of the item in the request array
+    pub index: usize,
+    pub status: BatchItemStatus,
+    #[serde(rename = "workflowId", skip_serializing_if = "Option::is_none")]
+    pub workflow_id: Option<String>,
+    /// Status of the created or existing workflow
+    #[serde(rename = "workflowStatus", skip_serializing_if = "Option::is_none")]
+    pub workflow_status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<BatchItemError>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BatchCreateWorkflowsResponse {
+    /// Valid items were saved in one transaction: if saving had failed, none
+    /// would have been created and the whole request would have failed.
+    /// Otherwise each item is saved on its own and may report `failed`.
+    pub atomic: bool,
+    /// One result per request item, in request order
+    pub results: Vec<BatchCreateWorkflowResult>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -34,6 +105,63 @@ pub struct WorkflowStatusResponse {
     pub current_step: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// Who terminated the workflow, when its status is `TERMINATED`
+    #[serde(rename = "terminatedBy", skip_serializing_if = "Option::is_none")]
+    pub terminated_by: Option<String>,
+    /// Workflow whose step started this one
+    #[serde(rename = "parentWorkflowId", skip_serializing_if = "Option::is_none")]
+    pub parent_workflow_id: Option<String>,
+    /// Child workflows started by this workflow's steps
+    #[serde(rename = "childWorkflowIds", skip_serializing_if = "Vec::is_empty")]
+    pub child_workflow_ids: Vec<String>,
+    /// Definition version the workflow is pinned to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
+    /// State changes in order; only the most recent ones are kept for long-running loops
+    pub history: Vec<WorkflowTransition>,
+    /// Metadata given at start
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub memo: BTreeMap<String, String>,
+}
+
+/// One workflow state change
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WorkflowTransition {
+    pub from: String,
+    pub to: String,
+    /// RFC 3339 timestamp
+    pub at: String,
+    /// Failure error or termination reason
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Step the workflow started executing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub step: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WorkflowSummary {
+    #[serde(rename = "workflowId")]
+    pub workflow_id: String,
+    #[serde(rename = "workflowType")]
+    pub workflow_type: String,
+    pub status: String,
+    /// RFC 3339 timestamp
+    #[serde(rename = "startedAt")]
+    pub started_at: String,
+    /// RFC 3339 timestamp
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+    /// Metadata given at start
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub memo: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WorkflowListResponse {
+    pub workflows: Vec<WorkflowSummary>,
+    pub offset: usize,
+    pub limit: usize,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -47,18 +175,179 @@ pub struct WorkflowResultResponse {
     pub error: Option<String>,
 }
 
+/// A workflow event kept in the server's event journal
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WorkflowEventInfo {
+    /// e.g. `step_started`, `step_failed`, `workflow_completed`
+    #[serde(rename = "eventType")]
+    pub event_type: String,
+    /// Unix timestamp in seconds
+    pub timestamp: u64,
+    /// Event-specific fields such as `step_name`, `error` or `attempt`
+    pub payload: serde_json::Value,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WorkflowEventsResponse {
+    #[serde(rename = "workflowId")]
+    pub workflow_id: String,
+    /// Buffered events in the order they were broadcast
+    pub events: Vec<WorkflowEventInfo>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WorkflowStepsResponse {
+    #[serde(rename = "workflowId")]
+    pub workflow_id: String,
+    /// Step executions ordered by start time; steps that have not started come last
+    pub steps: Vec<StepExecutionInfo>,
+}
+
+/// Step dependency graph of a workflow
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WorkflowGraphResponse {
+    #[serde(rename = "workflowId")]
+    pub workflow_id: String,
+    #[serde(rename = "workflowType")]
+    pub workflow_type: String,
+    /// Definition version the graph was built from; absent for workflows without a definition
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
+    /// Steps in definition order, followed by executed steps outside the definition
+    pub nodes: Vec<GraphNodeInfo>,
+    pub edges: Vec<GraphEdgeInfo>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GraphNodeInfo {
+    #[serde(rename = "stepName")]
+    pub step_name: String,
+    /// PENDING, RUNNING, COMPLETED, FAILED or CANCELLED
+    pub status: String,
+    /// 0 until the step starts
+    pub attempt: u32,
+    /// RFC 3339 timestamp
+    #[serde(rename = "startedAt", skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<String>,
+    /// RFC 3339 timestamp
+    #[serde(rename = "completedAt", skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<String>,
+    /// Execution time; for a running step, the time it has run so far
+    #[serde(rename = "durationMs", skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+}
+
+/// `to` runs after `from` completes
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GraphEdgeInfo {
+    pub from: String,
+    pub to: String,
+}
+
+/// Execution record of one step
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StepExecutionInfo {
+    #[serde(rename = "stepName")]
+    pub step_name: String,
+    pub status: String,
+    pub attempt: u32,
+    /// Error of the last failed attempt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// RFC 3339 timestamp
+    #[serde(rename = "startedAt", skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<String>,
+    /// RFC 3339 timestamp
+    #[serde(rename = "completedAt", skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<String>,
+    /// Milliseconds from start to completion
+    #[serde(rename = "durationMs", skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// Steps this step waits for
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>,
+    /// Step input, when it is JSON
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input: Option<serde_json::Value>,
+    /// Step output, when it is JSON
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<StepProgressInfo>,
+    /// The result was served from the step result cache instead of a worker
+    #[serde(rename = "cacheHit", skip_serializing_if = "std::ops::Not::not")]
+    pub cache_hit: bool,
+}
+
 #[derive(Debug, Serialize, ToSchema)]
 pub struct CancelWorkflowResponse {
     pub success: bool,
     pub message: String,
 }
 
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TerminateWorkflowRequest {
+    /// Why the workflow is being terminated
+    pub reason: String,
+    /// Operator requesting the termination
+    #[serde(rename = "requestedBy", default)]
+    pub requested_by: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TerminateWorkflowResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RetryWorkflowResponse {
+    pub success: bool,
+    pub message: String,
+    /// Failed steps whose execution records were reset for another attempt
+    #[serde(rename = "retriedSteps")]
+    pub retried_steps: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportWorkflowResponse {
+    #[serde(rename = "workflowId")]
+    pub workflow_id: String,
+    pub status: String,
+    /// Steps re-dispatched because the import was resumed
+    #[serde(rename = "resumedSteps")]
+    pub resumed_steps: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SignalWorkflowRequest {
+    #[serde(rename = "signalName")]
+    pub signal_name: String,
+    #[serde(default)]
+    pub payload: serde_json::Value,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SignalWorkflowResponse {
+    pub success: bool,
+    pub message: String,
+}
+
 // === Worker Models ===
 
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct RegisterWorkerRequest {
     #[serde(rename = "serviceName")]
     pub service_name: String,
+    /// Worker group (defaults to "default")
+    #[serde(default)]
+    pub group: Option<String>,
+    /// Languages the service is implemented in
+    #[serde(default)]
+    pub languages: Vec<String>,
+    /// Address other services can reach this service at
+    #[serde(default)]
+    pub endpoint: Option<String>,
     #[serde(default)]
     pub resources: Vec<ResourceInfo>,
 }
@@ -68,6 +357,33 @@ pub struct ResourceInfo {
     pub name: String,
     #[serde(rename = "type")]
     pub resource_type: String,
+    /// Maximum attempts before the workflow fails (defaults to 3)
+    #[serde(
+        rename = "maxAttempts",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_attempts: Option<u32>,
+    /// Execution timeout in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// JSON Schema the task input must match before the step is dispatched
+    #[serde(
+        rename = "inputSchema",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub input_schema: Option<String>,
+    /// JSON Schema the step output must match when the step completes
+    #[serde(
+        rename = "outputSchema",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub output_schema: Option<String>,
+    /// Identical inputs produce identical outputs, so results are cached and reused
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cacheable: bool,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -85,21 +401,101 @@ pub struct HeartbeatResponse {
     pub next_heartbeat: u64,
 }
 
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WorkerSummary {
+    #[serde(rename = "workerId")]
+    pub worker_id: String,
+    #[serde(rename = "serviceName")]
+    pub service_name: String,
+    pub group: String,
+    /// Names of the resources the worker provides
+    pub resources: Vec<String>,
+    /// RFC 3339 timestamp of the last heartbeat or poll
+    #[serde(rename = "lastSeen")]
+    pub last_seen: String,
+    /// Whether the last heartbeat is within the worker timeout
+    pub alive: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WorkerListResponse {
+    pub workers: Vec<WorkerSummary>,
+    /// Seconds without a heartbeat before a worker is evicted
+    #[serde(rename = "workerTimeoutSecs")]
+    pub worker_timeout_secs: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ServiceSummary {
+    #[serde(rename = "serviceName")]
+    pub service_name: String,
+    /// Worker id of this instance; replicas of a service are listed separately
+    #[serde(rename = "instanceId")]
+    pub instance_id: String,
+    pub group: String,
+    pub languages: Vec<String>,
+    /// Resources the service declared, sorted by name
+    pub provides: Vec<ResourceInfo>,
+    pub endpoint: String,
+    /// RFC 3339 timestamp of the latest registration
+    #[serde(rename = "registeredAt")]
+    pub registered_at: String,
+    /// RFC 3339 timestamp of the instance's latest heartbeat
+    #[serde(rename = "lastHeartbeat")]
+    pub last_heartbeat: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ServiceListResponse {
+    pub services: Vec<ServiceSummary>,
+}
+
 // === Step Models ===
 
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct ReportStepRequest {
+    /// STARTED, RUNNING, COMPLETED, FAILED or HEARTBEAT
     pub status: String,
+    /// Output for COMPLETED, error for FAILED, progress message for HEARTBEAT
     #[serde(skip_serializing_if = "Option::is_none")]
     pub message: Option<String>,
+    /// HEARTBEAT only: completion percentage (0-100)
+    #[serde(default)]
+    pub percent: Option<f64>,
+    /// HEARTBEAT only: partial output
+    #[serde(default)]
+    pub details: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct CompleteStepRequest {
+    /// JSON output
     #[serde(skip_serializing_if = "Option::is_none")]
     pub output: Option<serde_json::Value>,
+    /// Non-JSON output as base64; mutually exclusive with `output`
+    #[serde(
+        rename = "outputBase64",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub output_base64: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// Worker completing the task; when set, the task must still be leased to it.
+    /// REST and WebSocket completions always use the authenticated worker.
+    #[serde(rename = "workerId", default, skip_serializing_if = "Option::is_none")]
+    pub worker_id: Option<String>,
+    /// Child workflows to start before the workflow advances
+    #[serde(rename = "startChildren", default)]
+    pub start_children: Vec<StartChildWorkflow>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct StartChildWorkflow {
+    #[serde(rename = "workflowType")]
+    pub workflow_type: String,
+    #[serde(default)]
+    pub input: serde_json::Value,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -107,6 +503,132 @@ pub struct StepResponse {
     pub success: bool,
 }
 
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct StepHeartbeatRequest {
+    /// Completion percentage (0-100)
+    #[serde(default)]
+    pub percent: Option<f64>,
+    #[serde(default)]
+    pub message: Option<String>,
+    /// Partial output
+    #[serde(default)]
+    pub details: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StepHeartbeatResponse {
+    pub success: bool,
+    /// Milliseconds until the next heartbeat is expected
+    #[serde(rename = "nextHeartbeat")]
+    pub next_heartbeat: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StepProgressInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub percent: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StepStatusResponse {
+    #[serde(rename = "taskId")]
+    pub task_id: String,
+    #[serde(rename = "workflowId")]
+    pub workflow_id: String,
+    #[serde(rename = "stepName")]
+    pub step_name: String,
+    pub status: String,
+    pub attempt: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<StepProgressInfo>,
+    /// Unix seconds of the last progress heartbeat
+    #[serde(rename = "lastHeartbeatAt", skip_serializing_if = "Option::is_none")]
+    pub last_heartbeat_at: Option<i64>,
+    /// Workers that rejected the step without running it; rejections consume no attempts
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rejections: Vec<StepRejectionInfo>,
+    /// The result was served from the step result cache instead of a worker
+    #[serde(rename = "cacheHit", skip_serializing_if = "std::ops::Not::not")]
+    pub cache_hit: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StepRejectionInfo {
+    #[serde(rename = "workerId")]
+    pub worker_id: String,
+    pub reason: String,
+    /// Unix seconds
+    #[serde(rename = "rejectedAt")]
+    pub rejected_at: i64,
+}
+
+// === Schedule Models ===
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateScheduleRequest {
+    /// `sec min hour day-of-month month day-of-week [year]` in UTC; a standard
+    /// five-field expression fires at second 0
+    pub cron: String,
+    #[serde(rename = "workflowType")]
+    pub workflow_type: String,
+    /// Input of every started workflow
+    #[serde(default)]
+    pub input: serde_json::Value,
+    /// What to do when the previous run is still active: `skip` (default), `allow`
+    /// or `cancel_previous`
+    #[serde(rename = "overlapPolicy", default)]
+    #[schema(value_type = Option<String>)]
+    pub overlap_policy: Option<OverlapPolicy>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ScheduleResponse {
+    #[serde(rename = "scheduleId")]
+    pub schedule_id: String,
+    pub cron: String,
+    #[serde(rename = "workflowType")]
+    pub workflow_type: String,
+    /// Workflow input, when it is JSON
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input: Option<serde_json::Value>,
+    #[serde(rename = "overlapPolicy")]
+    pub overlap_policy: String,
+    /// RFC 3339 timestamp
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    /// Last fire time handled, including fires skipped by the overlap policy
+    #[serde(rename = "lastFiredAt", skip_serializing_if = "Option::is_none")]
+    pub last_fired_at: Option<String>,
+    /// Workflow started by the last fire
+    #[serde(rename = "lastWorkflowId", skip_serializing_if = "Option::is_none")]
+    pub last_workflow_id: Option<String>,
+    #[serde(rename = "nextFireAt", skip_serializing_if = "Option::is_none")]
+    pub next_fire_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ScheduleListResponse {
+    pub schedules: Vec<ScheduleResponse>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeleteScheduleResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+// === Workflow Definition Models ===
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WorkflowDefinitionListResponse {
+    /// Every registered version, ordered by name and version
+    pub definitions: Vec<WorkflowDefinitionSpec>,
+}
+
 // === WebSocket Models ===
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -124,9 +646,93 @@ pub struct TaskPayload {
     pub workflow_id: String,
     #[serde(rename = "stepName")]
     pub step_name: String,
-    pub input: serde_json::Value,
+    /// JSON input exactly as the workflow received it; `null` for non-JSON input
+    #[schema(value_type = Option<Object>)]
+    pub input: Option<Box<serde_json::value::RawValue>>,
+    /// Non-JSON input as base64, set instead of `input`
+    #[serde(rename = "inputBase64", skip_serializing_if = "Option::is_none")]
+    pub input_base64: Option<String>,
     #[serde(rename = "retryPolicy", skip_serializing_if = "Option::is_none")]
     pub retry_policy: Option<RetryPolicy>,
+    /// Expected heartbeat interval in milliseconds
+    #[serde(rename = "heartbeatInterval")]
+    pub heartbeat_interval: u64,
+    /// Signal the step waited for, if its definition declares `wait_for_signal`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signal: Option<SignalInfo>,
+}
+
+/// Pushed over the task WebSocket (`type` = `task_cancelled`) when the workflow of a task
+/// the worker holds is cancelled; the worker should stop executing it
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TaskCancelledMessage {
+    #[serde(rename = "type")]
+    pub msg_type: String,
+    pub payload: TaskCancelledPayload,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TaskCancelledPayload {
+    #[serde(rename = "taskId")]
+    pub task_id: String,
+    #[serde(rename = "workflowId")]
+    pub workflow_id: String,
+    #[serde(rename = "stepName")]
+    pub step_name: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SignalInfo {
+    pub name: String,
+    pub payload: serde_json::Value,
+    /// RFC 3339 timestamp
+    #[serde(rename = "receivedAt")]
+    pub received_at: String,
+}
+
+/// Messages sent by a worker over the task WebSocket
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum WorkerMessage {
+    /// Confirms receipt of a dispatched task
+    Ack {
+        #[serde(rename = "taskId")]
+        task_id: String,
+    },
+    /// Rejects a dispatched task without running it; the lease is released at once and no
+    /// attempt is consumed
+    Nack {
+        #[serde(rename = "taskId")]
+        task_id: String,
+        /// Recorded on the step execution
+        #[serde(default)]
+        reason: String,
+        /// When false, the task is not redelivered to this worker until the reject cooldown ends
+        #[serde(default = "default_requeue")]
+        requeue: bool,
+    },
+    Report {
+        #[serde(rename = "taskId")]
+        task_id: String,
+        #[serde(flatten)]
+        request: ReportStepRequest,
+    },
+    Complete {
+        #[serde(rename = "taskId")]
+        task_id: String,
+        #[serde(flatten)]
+        request: CompleteStepRequest,
+    },
+    Heartbeat {
+        #[serde(rename = "taskId")]
+        task_id: String,
+        #[serde(flatten)]
+        request: StepHeartbeatRequest,
+    },
+}
+
+fn default_requeue() -> bool {
+    true
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -146,4 +752,139 @@ pub struct MetricsResponse {
     pub completed_workflows: u64,
     #[serde(rename = "failedWorkflows")]
     pub failed_workflows: u64,
+    #[serde(rename = "purgedWorkflows")]
+    pub purged_workflows: u64,
+    /// Executions held in memory by the dashboard tracker
+    pub tracker: TrackerMetrics,
+    /// Dashboard event broadcasting
+    pub events: EventMetrics,
+    /// Tasks waiting to be dispatched, by workflow priority
+    #[serde(rename = "readyTasks")]
+    pub ready_tasks: ReadyTaskMetrics,
+    /// Queue and execution time of finished workflows
+    pub durations: DurationMetrics,
+    /// REST API rate and concurrency limiting
+    #[serde(rename = "rateLimits")]
+    pub rate_limits: RateLimitMetrics,
+    /// Tasks rejected by each worker since the server started
+    #[serde(rename = "taskRejections")]
+    pub task_rejections: BTreeMap<String, u64>,
+    /// Step result cache lookups for cacheable resources
+    #[serde(rename = "stepCache")]
+    pub step_cache: StepCacheMetrics,
+}
+
+/// Lookups of cacheable steps since the server started
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StepCacheMetrics {
+    /// Steps completed from a cached result without running on a worker
+    pub hits: u64,
+    /// Steps dispatched to a worker because no cached result was found
+    pub misses: u64,
+    /// `hits / (hits + misses)`, 0 before the first lookup
+    #[serde(rename = "hitRate")]
+    pub hit_rate: f64,
+}
+
+/// Requests turned away by the REST API limits, all zero when limiting is disabled
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RateLimitMetrics {
+    /// Requests rejected by the global or a per-API-key rate limit since the server started
+    #[serde(rename = "rateLimited")]
+    pub rate_limited: u64,
+    /// Result and export requests rejected because too many were in flight
+    #[serde(rename = "concurrencyRejected")]
+    pub concurrency_rejected: u64,
+    /// Result and export requests currently in flight (counted only when capped)
+    #[serde(rename = "expensiveInFlight")]
+    pub expensive_in_flight: u64,
+    /// API keys with a rate limit bucket
+    #[serde(rename = "trackedApiKeys")]
+    pub tracked_api_keys: u64,
+}
+
+/// Averages over finished workflows that ran at least one step
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DurationMetrics {
+    /// Finished workflows included in the averages
+    pub workflows: u64,
+    /// Average time from start until the first step began
+    #[serde(rename = "avgQueueMs")]
+    pub avg_queue_ms: u64,
+    /// Average time from the first step until the workflow finished
+    #[serde(rename = "avgExecutionMs")]
+    pub avg_execution_ms: u64,
+}
+
+/// Depth of the dispatch queue per workflow priority
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReadyTaskMetrics {
+    pub high: u64,
+    pub normal: u64,
+    pub low: u64,
+}
+
+/// Memory usage of the workflow execution tracker
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TrackerMetrics {
+    /// Executions of workflows that have not finished
+    #[serde(rename = "activeExecutions")]
+    pub active_executions: u64,
+    /// Finished executions still kept in memory
+    #[serde(rename = "retainedExecutions")]
+    pub retained_executions: u64,
+    /// Finished executions evicted since the server started
+    #[serde(rename = "evictedExecutions")]
+    pub evicted_executions: u64,
+}
+
+/// Delivery of workflow events to dashboard subscribers
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EventMetrics {
+    /// Currently connected event subscribers
+    pub subscribers: u64,
+    /// Events dropped because a subscriber fell behind, since the server started
+    pub lagged: u64,
+}
+
+/// Overview counters for the dashboard, maintained incrementally so polling is cheap
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StatsResponse {
+    #[serde(rename = "activeWorkflows")]
+    pub active_workflows: u64,
+    /// Workflows that completed; purging does not reduce this
+    #[serde(rename = "completedWorkflows")]
+    pub completed_workflows: u64,
+    /// Workflows that failed and were not retried; purging does not reduce this
+    #[serde(rename = "failedWorkflows")]
+    pub failed_workflows: u64,
+    /// Workflows started in the last 1, 5 and 60 minutes
+    pub started: StartedWorkflowStats,
+    /// Percentiles over the most recently completed steps, absent until a step completes
+    #[serde(rename = "stepDurations")]
+    pub step_durations: StepDurationStats,
+    /// Currently registered workers
+    pub workers: u64,
+    /// Dashboard event broadcasting
+    pub events: EventMetrics,
+}
+
+/// Workflows started within each rolling window
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StartedWorkflowStats {
+    #[serde(rename = "last1m")]
+    pub last_1m: u64,
+    #[serde(rename = "last5m")]
+    pub last_5m: u64,
+    #[serde(rename = "last60m")]
+    pub last_60m: u64,
+}
+
+/// Step duration percentiles in milliseconds
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StepDurationStats {
+    #[serde(rename = "p50Ms")]
+    pub p50_ms: Option<u64>,
+    #[serde(rename = "p95Ms")]
+    pub p95_ms: Option<u64>,
 }
@@ -0,0 +1,311 @@
+use axum::{
+    extract::{Request, State},
+    http::{header::RETRY_AFTER, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+use crate::api::auth::AuthenticatedApiKey;
+use crate::api::error::ApiError;
+
+/// Per-key buckets kept before idle ones are dropped
+const MAX_TRACKED_KEYS: usize = 10_000;
+
+/// A token bucket: `burst` requests at once, refilled at `per_second`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub per_second: f64,
+    pub burst: u32,
+}
+
+impl RateLimit {
+    /// `burst` of 0 allows one second worth of requests at once
+    pub fn new(per_second: u32, burst: u32) -> Self {
+        let burst = if burst == 0 { per_second } else { burst };
+        Self {
+            per_second: f64::from(per_second),
+            burst: burst.max(1),
+        }
+    }
+}
+
+/// REST rate and concurrency limits. Everything is disabled by default.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RateLimitConfig {
+    /// Shared by every request
+    pub global: Option<RateLimit>,
+    /// Applied to each authenticated API key separately; requests without one (including
+    /// every request when API keys are not configured) only count globally
+    pub per_api_key: Option<RateLimit>,
+    /// Concurrent requests allowed on the expensive endpoints (result long-poll, export)
+    pub max_concurrent_expensive: Option<usize>,
+}
+
+impl RateLimitConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.global.is_some() || self.per_api_key.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn full(limit: &RateLimit, now: Instant) -> Self {
+        Self {
+            tokens: f64::from(limit.burst),
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, limit: &RateLimit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.per_second).min(f64::from(limit.burst));
+        self.updated = now;
+    }
+
+    /// Take a token, or return how long until one is available
+    fn try_acquire(&mut self, limit: &RateLimit, now: Instant) -> Result<(), Duration> {
+        self.available(limit, now)?;
+        self.tokens -= 1.0;
+        Ok(())
+    }
+
+    /// Check for a token without taking it
+    fn available(&mut self, limit: &RateLimit, now: Instant) -> Result<(), Duration> {
+        self.refill(limit, now);
+        if self.tokens >= 1.0 {
+            return Ok(());
+        }
+        if limit.per_second <= 0.0 {
+            return Err(Duration::MAX);
+        }
+        Err(Duration::from_secs_f64(
+            (1.0 - self.tokens) / limit.per_second,
+        ))
+    }
+
+    fn is_full(&mut self, limit: &RateLimit, now: Instant) -> bool {
+        self.refill(limit, now);
+        self.tokens >= f64::from(limit.burst)
+    }
+}
+
+/// Counters reported under `rateLimits` in `GET /metrics`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimiterStats {
+    /// Requests rejected by the global or a per-key rate limit since the server started
+    pub rate_limited: u64,
+    /// Expensive requests rejected because too many were in flight
+    pub concurrency_rejected: u64,
+    /// Expensive requests currently in flight
+    pub expensive_in_flight: u64,
+    /// API keys with a rate limit bucket
+    pub tracked_api_keys: u64,
+}
+
+/// Shared state of the rate limiting middleware
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    global: Mutex<Option<TokenBucket>>,
+    per_key: Mutex<HashMap<String, TokenBucket>>,
+    expensive: Option<Arc<Semaphore>>,
+    rate_limited: AtomicU64,
+    concurrency_rejected: AtomicU64,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            expensive: config
+                .max_concurrent_expensive
+                .map(|max| Arc::new(Semaphore::new(max))),
+            config,
+            global: Mutex::new(None),
+            per_key: Mutex::new(HashMap::new()),
+            rate_limited: AtomicU64::new(0),
+            concurrency_rejected: AtomicU64::new(0),
+        }
+    }
+
+    pub fn config(&self) -> &RateLimitConfig {
+        &self.config
+    }
+
+    /// Take a token from the global bucket and the bucket of `api_key`,
+    /// or return how long the client should wait
+    pub fn check(&self, api_key: Option<&str>, now: Instant) -> Result<(), Duration> {
+        let result = self.check_buckets(api_key, now);
+        if result.is_err() {
+            self.rate_limited.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+
+    /// Tokens are only taken when every applicable bucket has one, so a request
+    /// rejected by its key's limit does not use up global capacity
+    fn check_buckets(&self, api_key: Option<&str>, now: Instant) -> Result<(), Duration> {
+        let mut global = self.global.lock().unwrap();
+        let mut per_key = self.per_key.lock().unwrap();
+        let mut buckets = Vec::with_capacity(2);
+        if let Some(limit) = &self.config.global {
+            buckets.push((
+                limit,
+                global.get_or_insert_with(|| TokenBucket::full(limit, now)),
+            ));
+        }
+        if let (Some(limit), Some(api_key)) = (&self.config.per_api_key, api_key) {
+            if per_key.len() >= MAX_TRACKED_KEYS && !per_key.contains_key(api_key) {
+                // A full bucket is the same as a new one
+                per_key.retain(|_, bucket| !bucket.is_full(limit, now));
+            }
+            buckets.push((
+                limit,
+                per_key
+                    .entry(api_key.to_string())
+                    .or_insert_with(|| TokenBucket::full(limit, now)),
+            ));
+        }
+
+        let wait = buckets
+            .iter_mut()
+            .filter_map(|(limit, bucket)| bucket.available(limit, now).err())
+            .max();
+        if let Some(wait) = wait {
+            return Err(wait);
+        }
+        for (limit, bucket) in buckets {
+            bucket.try_acquire(limit, now)?;
+        }
+        Ok(())
+    }
+
+    pub fn stats(&self) -> RateLimiterStats {
+        let expensive_in_flight = match (&self.expensive, self.config.max_concurrent_expensive) {
+            (Some(semaphore), Some(max)) => max.saturating_sub(semaphore.available_permits()),
+            _ => 0,
+        };
+        RateLimiterStats {
+            rate_limited: self.rate_limited.load(Ordering::Relaxed),
+            concurrency_rejected: self.concurrency_rejected.load(Ordering::Relaxed),
+            expensive_in_flight: expensive_in_flight as u64,
+            tracked_api_keys: self.per_key.lock().unwrap().len() as u64,
+        }
+    }
+}
+
+/// 429 with a `Retry-After` header in whole seconds
+fn too_many_requests(code: &str, message: &str, retry_after: Duration) -> Response {
+    let secs = retry_after.as_secs_f64().ceil().clamp(1.0, 3600.0) as u64;
+    let mut response = ApiError::too_many_requests(code, message).into_response();
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from(secs));
+    response
+}
+
+/// Middleware applying the global and per-API-key rate limits
+pub async fn rate_limit(
+    State(limiter): State<Arc<RateLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    // Unauthenticated keys are not limited separately, so that made-up keys cannot
+    // sidestep the per-key limit or crowd out real ones
+    let api_key = request
+        .extensions()
+        .get::<AuthenticatedApiKey>()
+        .map(|key| key.0.as_str());
+    if let Err(retry_after) = limiter.check(api_key, Instant::now()) {
+        return too_many_requests("RATE_LIMITED", "Too many requests", retry_after);
+    }
+    next.run(request).await
+}
+
+/// Middleware capping concurrent requests on an expensive endpoint
+pub async fn limit_concurrency(
+    State(limiter): State<Arc<RateLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(semaphore) = &limiter.expensive else {
+        return next.run(request).await;
+    };
+    let Ok(_permit) = semaphore.clone().try_acquire_owned() else {
+        limiter.concurrency_rejected.fetch_add(1, Ordering::Relaxed);
+        return too_many_requests(
+            "TOO_MANY_CONCURRENT_REQUESTS",
+            "Too many concurrent requests to this endpoint",
+            Duration::from_secs(1),
+        );
+    };
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_refills_over_time() {
+        let limit = RateLimit::new(2, 4);
+        let start = Instant::now();
+        let mut bucket = TokenBucket::full(&limit, start);
+        for _ in 0..4 {
+            assert!(bucket.try_acquire(&limit, start).is_ok());
+        }
+        let wait = bucket.try_acquire(&limit, start).unwrap_err();
+        assert_eq!(wait, Duration::from_millis(500));
+
+        let later = start + Duration::from_millis(500);
+        assert!(bucket.try_acquire(&limit, later).is_ok());
+        assert!(bucket.try_acquire(&limit, later).is_err());
+        // Idle buckets never hold more than the burst
+        assert!(bucket.is_full(&limit, start + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_api_keys_are_limited_separately() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            per_api_key: Some(RateLimit::new(1, 1)),
+            ..Default::default()
+        });
+        let now = Instant::now();
+        assert!(limiter.check(Some("a"), now).is_ok());
+        assert!(limiter.check(Some("a"), now).is_err());
+        assert!(limiter.check(Some("b"), now).is_ok());
+        // Without a key only the global limit applies, and it is disabled
+        assert!(limiter.check(None, now).is_ok());
+        assert!(limiter.check(None, now).is_ok());
+
+        let stats = limiter.stats();
+        assert_eq!(stats.rate_limited, 1);
+        assert_eq!(stats.tracked_api_keys, 2);
+    }
+
+    #[test]
+    fn test_rejected_requests_do_not_use_up_global_capacity() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            global: Some(RateLimit::new(1, 2)),
+            per_api_key: Some(RateLimit::new(1, 1)),
+            ..Default::default()
+        });
+        let now = Instant::now();
+        assert!(limiter.check(Some("a"), now).is_ok());
+        for _ in 0..3 {
+            assert!(limiter.check(Some("a"), now).is_err());
+        }
+        // The key's rejections left the second global token in place
+        assert!(limiter.check(Some("b"), now).is_ok());
+        assert!(limiter.check(Some("c"), now).is_err());
+        assert_eq!(limiter.stats().rate_limited, 4);
+    }
+}
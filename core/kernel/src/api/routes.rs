@@ -1,57 +1,170 @@
 use axum::{
-    routing::{delete, get, post},
+    extract::DefaultBodyLimit,
+    http::StatusCode,
+    middleware,
+    routing::{delete, get, post, put},
     Router,
 };
 use std::sync::Arc;
-use utoipa::OpenApi;
+use tower_http::compression::predicate::{DefaultPredicate, Predicate};
+use tower_http::compression::CompressionLayer;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
 use utoipa_swagger_ui::SwaggerUi;
 
-use crate::api::handlers::{admin, steps, workers, workflows};
+use crate::api::auth::{require_api_key, AuthConfig};
+use crate::api::cors::cors_layer;
+use crate::api::handlers::{admin, definitions, schedules, steps, workers, workflows};
 use crate::api::models::{
-    CancelWorkflowResponse, CompleteStepRequest, CreateWorkflowRequest, CreateWorkflowResponse,
-    HeartbeatResponse, MetricsResponse, RegisterWorkerRequest, RegisterWorkerResponse,
-    ReportStepRequest, ResourceInfo, RetryPolicy, StepResponse, TaskMessage, TaskPayload,
-    WorkflowOptions, WorkflowResultResponse, WorkflowStatusResponse,
+    BatchCreateWorkflowResult, BatchCreateWorkflowsResponse, BatchItemError, BatchItemStatus,
+    CancelWorkflowResponse, CompleteStepRequest, CreateScheduleRequest, CreateWorkflowRequest,
+    CreateWorkflowResponse, DeleteScheduleResponse, DurationMetrics, EventMetrics, GraphEdgeInfo,
+    GraphNodeInfo, HeartbeatResponse, ImportWorkflowResponse, MetricsResponse, RateLimitMetrics,
+    ReadyTaskMetrics, RegisterWorkerRequest, RegisterWorkerResponse, ReportStepRequest,
+    ResourceInfo, RetryPolicy, RetryWorkflowResponse, ScheduleListResponse, ScheduleResponse,
+    ServiceListResponse, ServiceSummary, SignalInfo, SignalWorkflowRequest, SignalWorkflowResponse,
+    StartChildWorkflow, StartedWorkflowStats, StatsResponse, StepCacheMetrics, StepDurationStats,
+    StepExecutionInfo, StepHeartbeatRequest, StepHeartbeatResponse, StepProgressInfo,
+    StepRejectionInfo, StepResponse, StepStatusResponse, TaskCancelledMessage,
+    TaskCancelledPayload, TaskMessage, TaskPayload, TerminateWorkflowRequest,
+    TerminateWorkflowResponse, TrackerMetrics, WorkerListResponse, WorkerSummary,
+    WorkflowDefinitionListResponse, WorkflowEventInfo, WorkflowEventsResponse,
+    WorkflowGraphResponse, WorkflowListResponse, WorkflowOptions, WorkflowResultResponse,
+    WorkflowStatusResponse, WorkflowStepsResponse, WorkflowSummary, WorkflowTransition,
 };
+use crate::api::rate_limit::{limit_concurrency, rate_limit};
 use crate::api::websocket;
+use crate::definition::{RetryPolicySpec, StepDefinitionSpec, WorkflowDefinitionSpec};
+use crate::export::{
+    ExportFormat, ExportedEvent, ExportedPayload, ExportedProgress, ExportedStep, ExportedWorkflow,
+    StateTransition, WorkflowExport,
+};
 use crate::persistence::Persistence;
 use crate::scheduler::Scheduler;
+use crate::server_info::{ServerInfo, ServerLimits, Subsystems};
+use crate::telemetry::trace_request;
 
 /// OpenAPI documentation for the Aether Kernel REST API.
 #[derive(OpenApi)]
 #[openapi(
     paths(
         workflows::create_workflow,
+        workflows::create_workflows,
+        workflows::list_workflows,
         workflows::get_workflow_status,
         workflows::get_workflow_result,
+        workflows::get_workflow_events,
+        workflows::get_workflow_steps,
+        workflows::get_workflow_graph,
+        workflows::export_workflow,
+        workflows::import_workflow,
         workflows::cancel_workflow,
+        workflows::terminate_workflow,
+        workflows::retry_workflow,
+        workflows::signal_workflow,
+        schedules::create_schedule,
+        schedules::list_schedules,
+        schedules::delete_schedule,
+        definitions::register_definition,
+        definitions::list_definitions,
+        definitions::get_definition,
         workers::register_worker,
         workers::worker_heartbeat,
+        workers::list_workers,
+        workers::list_services,
         steps::report_step,
         steps::complete_step,
+        steps::heartbeat_step,
+        steps::get_step,
         admin::get_metrics,
+        admin::get_stats,
+        admin::get_server_info,
     ),
     components(schemas(
         CreateWorkflowRequest,
         WorkflowOptions,
         CreateWorkflowResponse,
+        BatchItemStatus,
+        BatchItemError,
+        BatchCreateWorkflowResult,
+        BatchCreateWorkflowsResponse,
         WorkflowStatusResponse,
+        WorkflowTransition,
+        WorkflowSummary,
+        WorkflowListResponse,
         WorkflowResultResponse,
+        WorkflowEventInfo,
+        WorkflowEventsResponse,
+        WorkflowStepsResponse,
+        StepExecutionInfo,
+        WorkflowGraphResponse,
+        GraphNodeInfo,
+        GraphEdgeInfo,
+        ExportFormat,
+        WorkflowExport,
+        ExportedWorkflow,
+        StateTransition,
+        ExportedStep,
+        ExportedProgress,
+        ExportedEvent,
+        ExportedPayload,
+        ImportWorkflowResponse,
         CancelWorkflowResponse,
+        TerminateWorkflowRequest,
+        TerminateWorkflowResponse,
+        RetryWorkflowResponse,
+        SignalWorkflowRequest,
+        SignalWorkflowResponse,
+        CreateScheduleRequest,
+        ScheduleResponse,
+        ScheduleListResponse,
+        DeleteScheduleResponse,
+        WorkflowDefinitionSpec,
+        StepDefinitionSpec,
+        RetryPolicySpec,
+        WorkflowDefinitionListResponse,
         RegisterWorkerRequest,
         ResourceInfo,
         RegisterWorkerResponse,
         HeartbeatResponse,
+        WorkerSummary,
+        WorkerListResponse,
+        ServiceSummary,
+        ServiceListResponse,
         ReportStepRequest,
         CompleteStepRequest,
+        StartChildWorkflow,
         StepResponse,
+        StepHeartbeatRequest,
+        StepHeartbeatResponse,
+        StepProgressInfo,
+        StepStatusResponse,
+        StepRejectionInfo,
         TaskMessage,
         TaskPayload,
+        TaskCancelledMessage,
+        TaskCancelledPayload,
+        SignalInfo,
         RetryPolicy,
         MetricsResponse,
+        TrackerMetrics,
+        EventMetrics,
+        ReadyTaskMetrics,
+        DurationMetrics,
+        RateLimitMetrics,
+        StepCacheMetrics,
+        StatsResponse,
+        StartedWorkflowStats,
+        StepDurationStats,
+        ServerInfo,
+        Subsystems,
+        ServerLimits,
     )),
+    modifiers(&SessionTokenAuth),
     tags(
         (name = "workflows", description = "Workflow management"),
+        (name = "schedules", description = "Cron-triggered workflows"),
+        (name = "definitions", description = "Workflow definitions pushed by SDKs"),
         (name = "workers", description = "Worker management"),
         (name = "steps", description = "Step execution"),
         (name = "admin", description = "Administration"),
@@ -59,48 +172,165 @@ use crate::scheduler::Scheduler;
 )]
 pub struct ApiDoc;
 
+/// Documents the bearer session token required by the step endpoints.
+struct SessionTokenAuth;
+
+impl Modify for SessionTokenAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "session_token",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some("The sessionToken returned by POST /workers"))
+                    .build(),
+            ),
+        );
+    }
+}
+
 /// Create the Axum router with all API routes.
 ///
 /// # Routes
 ///
 /// ## Workflows
 /// - `POST /workflows` - Create a new workflow
+/// - `POST /workflows/batch` - Create many workflows, with per-item results
+/// - `GET /workflows` - List workflows (`offset`, `limit`, `order`, `state`, `type`)
 /// - `GET /workflows/{id}` - Get workflow status
 /// - `GET /workflows/{id}/result` - Wait for and get workflow result
-/// - `DELETE /workflows/{id}` - Cancel a workflow
+/// - `GET /workflows/{id}/graph` - Step dependency graph merged with execution state
+/// - `GET /workflows/{id}/export` - Export the full execution record (`format`, `max_payload_bytes`)
+/// - `POST /workflows/import` - Recreate a workflow from an export (`keep_id`, `resume`)
+/// - `DELETE /workflows/{id}` - Cancel a workflow (`?purge=true` deletes it instead)
+/// - `POST /workflows/{id}/signal` - Send a signal to a running workflow
+///
+/// ## Schedules
+/// - `POST /schedules` - Start a workflow type on a cron schedule
+/// - `GET /schedules` - List schedules with their next fire time
+/// - `DELETE /schedules/{id}` - Delete a schedule
+///
+/// ## Workflow definitions
+/// - `PUT /workflow-definitions/{name}` - Register a definition version (steps, dependencies, targets)
+/// - `GET /workflow-definitions` - List every registered definition version
+/// - `GET /workflow-definitions/{name}/{version}` - Get one definition version
 ///
 /// ## Workers
 /// - `POST /workers` - Register a new worker
-/// - `GET /workers/{id}/tasks` - WebSocket task streaming
+/// - `GET /workers/{id}/tasks` - WebSocket task streaming (`?token=<sessionToken>`)
 /// - `POST /workers/{id}/heartbeat` - Worker heartbeat
 ///
 /// ## Steps
+/// Report, complete and heartbeat require `Authorization: Bearer <sessionToken>`.
 /// - `POST /steps/{taskId}/report` - Report step status
 /// - `POST /steps/{taskId}/complete` - Complete a step
+/// - `POST /steps/{taskId}/heartbeat` - Heartbeat a running step
+/// - `GET /steps/{taskId}` - Get step status and progress
 ///
 /// ## Admin
 /// - `GET /metrics` - Get system metrics
+/// - `GET /stats` - Get overview counters for the dashboard
+/// - `GET /info` - Get server version, capabilities and limits
+/// - `GET /workers` - List registered workers and whether they are alive
+/// - `GET /services` - List registered services and the resources they provide
 ///
 /// ## Swagger UI
+/// Always public, even when API keys are required.
 /// - `/swagger-ui` - Interactive API documentation
 /// - `/api-docs/openapi.json` - OpenAPI JSON specification
 pub fn create_router<P: Persistence + Clone + Send + Sync + 'static>(
     scheduler: Arc<Scheduler<P>>,
 ) -> Router {
-    Router::new()
+    create_router_with_auth(scheduler, AuthConfig::default())
+}
+
+/// Create the router, requiring an `X-Api-Key` header when `auth` has keys.
+///
+/// The rate and concurrency limits in the scheduler's `rate_limits` config answer
+/// 429 with `Retry-After` once exceeded. The Swagger UI and the OpenAPI document
+/// stay public and unlimited.
+///
+/// Cross-origin requests are allowed from the scheduler's `cors_origins`, and
+/// responses are compressed with gzip or brotli when the client accepts it,
+/// except for the worker task WebSocket upgrade.
+pub fn create_router_with_auth<P: Persistence + Clone + Send + Sync + 'static>(
+    scheduler: Arc<Scheduler<P>>,
+    auth: AuthConfig,
+) -> Router {
+    let max_payload = scheduler.limits().max_payload_bytes as usize;
+    let limiter = scheduler.rate_limiter.clone();
+    let cors = cors_layer(scheduler.config().cors_origins.clone());
+
+    let mut api = Router::new()
         // Workflow routes
-        .route("/workflows", post(workflows::create_workflow::<P>))
+        .route(
+            "/workflows",
+            post(workflows::create_workflow::<P>).get(workflows::list_workflows::<P>),
+        )
+        .route("/workflows/batch", post(workflows::create_workflows::<P>))
+        .route("/workflows/import", post(workflows::import_workflow::<P>))
         .route("/workflows/:id", get(workflows::get_workflow_status::<P>))
         .route(
             "/workflows/:id/result",
-            get(workflows::get_workflow_result::<P>),
+            get(workflows::get_workflow_result::<P>).layer(middleware::from_fn_with_state(
+                limiter.clone(),
+                limit_concurrency,
+            )),
+        )
+        .route(
+            "/workflows/:id/events",
+            get(workflows::get_workflow_events::<P>),
+        )
+        .route(
+            "/workflows/:id/steps",
+            get(workflows::get_workflow_steps::<P>),
+        )
+        .route(
+            "/workflows/:id/graph",
+            get(workflows::get_workflow_graph::<P>),
+        )
+        .route(
+            "/workflows/:id/export",
+            get(workflows::export_workflow::<P>).layer(middleware::from_fn_with_state(
+                limiter.clone(),
+                limit_concurrency,
+            )),
+        )
+        .route("/workflows/:id", delete(workflows::cancel_workflow::<P>))
+        .route(
+            "/workflows/:id/terminate",
+            post(workflows::terminate_workflow::<P>),
+        )
+        .route("/workflows/:id/retry", post(workflows::retry_workflow::<P>))
+        .route(
+            "/workflows/:id/signal",
+            post(workflows::signal_workflow::<P>),
+        )
+        // Schedule routes
+        .route(
+            "/schedules",
+            post(schedules::create_schedule::<P>).get(schedules::list_schedules::<P>),
+        )
+        .route("/schedules/:id", delete(schedules::delete_schedule::<P>))
+        // Workflow definition routes
+        .route(
+            "/workflow-definitions",
+            get(definitions::list_definitions::<P>),
         )
         .route(
-            "/workflows/:id",
-            delete(workflows::cancel_workflow::<P>),
+            "/workflow-definitions/:name",
+            put(definitions::register_definition::<P>),
+        )
+        .route(
+            "/workflow-definitions/:name/:version",
+            get(definitions::get_definition::<P>),
         )
         // Worker routes
-        .route("/workers", post(workers::register_worker::<P>))
+        .route(
+            "/workers",
+            post(workers::register_worker::<P>).get(workers::list_workers::<P>),
+        )
         .route("/workers/:id/tasks", get(websocket::worker_tasks_ws::<P>))
         .route(
             "/workers/:id/heartbeat",
@@ -108,14 +338,37 @@ pub fn create_router<P: Persistence + Clone + Send + Sync + 'static>(
         )
         // Step routes
         .route("/steps/:taskId/report", post(steps::report_step::<P>))
-        .route(
-            "/steps/:taskId/complete",
-            post(steps::complete_step::<P>),
-        )
+        .route("/steps/:taskId/complete", post(steps::complete_step::<P>))
+        .route("/steps/:taskId/heartbeat", post(steps::heartbeat_step::<P>))
+        .route("/steps/:taskId", get(steps::get_step::<P>))
         // Admin routes
         .route("/metrics", get(admin::get_metrics::<P>))
+        .route("/stats", get(admin::get_stats::<P>))
+        .route("/info", get(admin::get_server_info::<P>))
+        .route("/services", get(workers::list_services::<P>));
+    // Added before auth so that unauthenticated requests are rejected before using up tokens
+    if limiter.config().is_enabled() {
+        api = api.route_layer(middleware::from_fn_with_state(limiter, rate_limit));
+    }
+    if auth.is_enabled() {
+        api = api.route_layer(middleware::from_fn_with_state(
+            Arc::new(auth),
+            require_api_key,
+        ));
+    }
+
+    api
         // Swagger UI
         .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
+        .layer(DefaultBodyLimit::max(max_payload))
+        .layer(CompressionLayer::new().compress_when(
+            DefaultPredicate::new().and(|status: StatusCode, _, _: &_, _: &_| {
+                status != StatusCode::SWITCHING_PROTOCOLS
+            }),
+        ))
+        // Preflight requests are answered here, before auth and rate limits
+        .layer(cors)
+        .layer(middleware::from_fn(trace_request))
         // State
         .with_state(scheduler)
 }
@@ -123,6 +376,329 @@ pub fn create_router<P: Persistence + Clone + Send + Sync + 'static>(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::api::rate_limit::{RateLimit, RateLimitConfig};
+    use crate::persistence::l0_memory::L0MemoryStore;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    async fn get(router: &Router, uri: &str, api_key: Option<&str>) -> (StatusCode, String) {
+        let mut request = Request::builder().uri(uri);
+        if let Some(api_key) = api_key {
+            request = request.header("X-Api-Key", api_key);
+        }
+        let response = router
+            .clone()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8_lossy(&body).to_string())
+    }
+
+    #[tokio::test]
+    async fn test_api_key_required_except_for_docs() {
+        let scheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        let router = create_router_with_auth(scheduler, AuthConfig::new(vec!["secret".into()]));
+
+        let (status, body) = get(&router, "/workflows", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["error"]["code"], "UNAUTHORIZED");
+        let (status, _) = get(&router, "/workflows", Some("wrong")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = get(&router, "/workflows", Some("secret")).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, _) = get(&router, "/api-docs/openapi.json", None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_router_without_keys_is_open() {
+        let scheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        let (status, _) = get(&create_router(scheduler), "/workflows", None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_request_id_is_echoed() {
+        let scheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        let router = create_router(scheduler);
+        let request_id = |response: &axum::response::Response| {
+            response.headers()["x-request-id"]
+                .to_str()
+                .unwrap()
+                .to_string()
+        };
+
+        let request = Request::builder()
+            .uri("/workflows")
+            .header("X-Request-Id", "req-42")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(request_id(&response), "req-42");
+
+        let request = Request::builder()
+            .uri("/missing")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(uuid::Uuid::parse_str(&request_id(&response)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_stats_follow_workflow_and_worker_changes() {
+        let scheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        for _ in 0..3 {
+            scheduler
+                .start_workflow(
+                    None,
+                    "order".to_string(),
+                    b"{}".to_vec(),
+                    crate::scheduler::StartOptions::default(),
+                )
+                .await
+                .unwrap();
+        }
+        scheduler
+            .register_worker(
+                "worker-1".to_string(),
+                "svc".to_string(),
+                "default".to_string(),
+                vec!["order".to_string()],
+                vec![],
+            )
+            .await;
+
+        let (status, body) = get(&create_router(scheduler), "/stats", None).await;
+        assert_eq!(status, StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["activeWorkflows"], 3);
+        assert_eq!(body["completedWorkflows"], 0);
+        assert_eq!(body["started"]["last1m"], 3);
+        assert_eq!(body["started"]["last60m"], 3);
+        assert_eq!(body["workers"], 1);
+        assert!(body["stepDurations"]["p50Ms"].is_null());
+        assert_eq!(body["events"]["lagged"], 0);
+    }
+
+    fn limited_scheduler(rate_limits: RateLimitConfig) -> Arc<Scheduler<Arc<L0MemoryStore>>> {
+        let config = crate::scheduler::SchedulerConfig {
+            rate_limits,
+            ..Default::default()
+        };
+        Arc::new(Scheduler::with_config(
+            Arc::new(L0MemoryStore::new()),
+            config,
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_requests_get_429_with_retry_after() {
+        let scheduler = limited_scheduler(RateLimitConfig {
+            per_api_key: Some(RateLimit::new(1, 2)),
+            ..Default::default()
+        });
+        let router = create_router_with_auth(
+            scheduler,
+            AuthConfig::new(vec!["a".to_string(), "b".to_string()]),
+        );
+        let request = |api_key: &str| {
+            Request::builder()
+                .uri("/workflows")
+                .header("X-Api-Key", api_key)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        for _ in 0..2 {
+            let response = router.clone().oneshot(request("a")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        let response = router.clone().oneshot(request("a")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()["retry-after"], "1");
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["code"], "RATE_LIMITED");
+
+        // Other keys have their own bucket
+        let response = router.clone().oneshot(request("b")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let (_, body) = get(&router, "/metrics", Some("b")).await;
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["rateLimits"]["rateLimited"], 1);
+        assert_eq!(body["rateLimits"]["trackedApiKeys"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_unauthenticated_api_keys_are_not_limited_per_key() {
+        let scheduler = limited_scheduler(RateLimitConfig {
+            per_api_key: Some(RateLimit::new(1, 1)),
+            ..Default::default()
+        });
+        // Without configured API keys the header is not checked, so it gets no bucket
+        let router = create_router(scheduler);
+        for _ in 0..3 {
+            let (status, _) = get(&router, "/workflows", Some("made-up")).await;
+            assert_eq!(status, StatusCode::OK);
+        }
+        let (_, body) = get(&router, "/metrics", None).await;
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["rateLimits"]["trackedApiKeys"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_expensive_endpoints_are_capped() {
+        let scheduler = limited_scheduler(RateLimitConfig {
+            max_concurrent_expensive: Some(1),
+            ..Default::default()
+        });
+        let workflow = scheduler
+            .start_workflow(
+                None,
+                "order".to_string(),
+                b"{}".to_vec(),
+                crate::scheduler::StartOptions::default(),
+            )
+            .await
+            .unwrap()
+            .workflow;
+        let router = create_router(scheduler);
+
+        // The first long-poll holds the only slot until it times out
+        let uri = format!("/workflows/{}/result?timeout=1", workflow.id);
+        let waiting = tokio::spawn({
+            let router = router.clone();
+            let uri = uri.clone();
+            async move { get(&router, &uri, None).await.0 }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let (status, body) = get(&router, &uri, None).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert!(body.contains("TOO_MANY_CONCURRENT_REQUESTS"));
+        let (status, _) = get(&router, "/workflows", None).await;
+        assert_eq!(status, StatusCode::OK);
+
+        assert_ne!(waiting.await.unwrap(), StatusCode::TOO_MANY_REQUESTS);
+        let (_, body) = get(&router, "/metrics", None).await;
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["rateLimits"]["concurrencyRejected"], 1);
+        assert_eq!(body["rateLimits"]["expensiveInFlight"], 0);
+    }
+
+    fn preflight(origin: &str) -> Request<Body> {
+        Request::builder()
+            .method("OPTIONS")
+            .uri("/workflows")
+            .header("Origin", origin)
+            .header("Access-Control-Request-Method", "POST")
+            .header("Access-Control-Request-Headers", "content-type,x-api-key")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_cors_preflight_allows_configured_origins() {
+        let scheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        // Preflight requests carry no API key
+        let router = create_router_with_auth(scheduler, AuthConfig::new(vec!["secret".into()]));
+
+        let response = router
+            .clone()
+            .oneshot(preflight("http://localhost:5173"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers();
+        assert_eq!(
+            headers["access-control-allow-origin"],
+            "http://localhost:5173"
+        );
+        assert!(headers["access-control-allow-methods"]
+            .to_str()
+            .unwrap()
+            .contains("POST"));
+        assert!(headers["access-control-allow-headers"]
+            .to_str()
+            .unwrap()
+            .contains("x-api-key"));
+
+        let response = router
+            .clone()
+            .oneshot(preflight("https://evil.example.com"))
+            .await
+            .unwrap();
+        assert!(!response
+            .headers()
+            .contains_key("access-control-allow-origin"));
+    }
+
+    #[tokio::test]
+    async fn test_cors_origins_come_from_config() {
+        let config = crate::scheduler::SchedulerConfig {
+            cors_origins: vec!["https://dash.example.com".to_string()],
+            ..Default::default()
+        };
+        let scheduler = Arc::new(Scheduler::with_config(
+            Arc::new(L0MemoryStore::new()),
+            config,
+        ));
+        let router = create_router(scheduler);
+
+        let response = router
+            .clone()
+            .oneshot(preflight("https://dash.example.com"))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()["access-control-allow-origin"],
+            "https://dash.example.com"
+        );
+        let response = router
+            .clone()
+            .oneshot(preflight("http://localhost:5173"))
+            .await
+            .unwrap();
+        assert!(!response
+            .headers()
+            .contains_key("access-control-allow-origin"));
+    }
+
+    #[tokio::test]
+    async fn test_responses_are_compressed_when_accepted() {
+        let scheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        let router = create_router(scheduler);
+        let request = |encoding: Option<&str>| {
+            let mut request = Request::builder().uri("/api-docs/openapi.json");
+            if let Some(encoding) = encoding {
+                request = request.header("Accept-Encoding", encoding);
+            }
+            request.body(Body::empty()).unwrap()
+        };
+
+        let response = router.clone().oneshot(request(None)).await.unwrap();
+        assert!(!response.headers().contains_key("content-encoding"));
+        let plain = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        for encoding in ["gzip", "br"] {
+            let response = router
+                .clone()
+                .oneshot(request(Some(encoding)))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()["content-encoding"], encoding);
+            let compressed = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            assert!(compressed.len() < plain.len());
+        }
+    }
 
     #[test]
     fn test_openapi_spec_generation() {
@@ -133,5 +709,7 @@ mod tests {
         assert!(json.contains("workers"));
         assert!(json.contains("steps"));
         assert!(json.contains("admin"));
+        assert!(json.contains("/info"));
+        assert!(json.contains("protocolVersion"));
     }
 }
@@ -1,23 +1,29 @@
 use axum::{
     extract::{
-        ws::{Message, WebSocket, WebSocketUpgrade},
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
         Path, Query, State,
     },
-    response::Response,
+    response::{IntoResponse, Response},
 };
-use futures_util::{SinkExt, StreamExt};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
 use serde::Deserialize;
-use std::collections::HashSet;
+use serde_json::value::RawValue;
 use std::sync::Arc;
-use tokio::sync::Mutex;
-use tokio::time::{interval, Duration};
+use tokio::sync::broadcast;
 
-use crate::api::models::{TaskMessage, TaskPayload};
+use crate::api::auth::authenticate;
+use crate::api::error::ApiError;
+use crate::api::handlers::steps::{apply_complete, apply_heartbeat, apply_report};
+use crate::api::models::{
+    SignalInfo, TaskCancelledMessage, TaskCancelledPayload, TaskMessage, TaskPayload, WorkerMessage,
+};
+use crate::cancellation::TaskCancellation;
 use crate::persistence::Persistence;
 use crate::scheduler::Scheduler;
-
-/// Maximum number of tasks to poll in a single request
-const POLL_TASKS_LIMIT: usize = 10;
+use crate::shutdown::ShutdownState;
+use crate::task::{Task, TaskId};
 
 pub type AppState<P> = Arc<Scheduler<P>>;
 
@@ -29,15 +35,24 @@ pub struct WsQuery {
 /// WS /workers/{id}/tasks - WebSocket task streaming
 ///
 /// Establishes a WebSocket connection for streaming tasks to a worker.
-/// Uses polling internally to check for available tasks.
+/// Uses polling internally to check for available tasks. The `token` query
+/// parameter must be the session token issued to this worker, otherwise the
+/// upgrade is refused with 401.
 pub async fn worker_tasks_ws<P: Persistence + Clone + Send + Sync + 'static>(
     ws: WebSocketUpgrade,
     State(scheduler): State<AppState<P>>,
     Path(worker_id): Path<String>,
     Query(query): Query<WsQuery>,
 ) -> Response {
-    // TODO: Validate token
-    let _ = query.token;
+    // The token must have been issued to the worker named in the path
+    match authenticate(&scheduler, &query.token).await {
+        Ok(owner) if owner == worker_id => {}
+        Ok(_) => {
+            return ApiError::unauthorized("Session token belongs to another worker")
+                .into_response()
+        }
+        Err(e) => return e.into_response(),
+    }
 
     ws.on_upgrade(move |socket| handle_worker_socket(socket, scheduler, worker_id))
 }
@@ -47,90 +62,127 @@ async fn handle_worker_socket<P: Persistence + Clone + Send + Sync + 'static>(
     scheduler: Arc<Scheduler<P>>,
     worker_id: String,
 ) {
-    let (mut sender, mut receiver) = socket.split();
-
-    // Task polling interval
-    let poll_interval = Duration::from_millis(100);
-    let mut poll_timer = interval(poll_interval);
+    let (sender, receiver) = socket.split();
+    serve_worker(sender, receiver, scheduler, worker_id).await;
+}
 
-    // Track sent task IDs to avoid duplicates (shared between send and recv tasks)
-    let sent_tasks: Arc<Mutex<HashSet<String>>> = Arc::new(Mutex::new(HashSet::new()));
-    let sent_tasks_for_recv = Arc::clone(&sent_tasks);
+/// Stream tasks claimed for a worker and handle its replies until either side stops.
+///
+/// Deduplication lives in the scheduler: `poll_tasks` leases every task it returns to this
+/// worker, so the loop only forwards claims. Claims that cannot be delivered are handed back.
+async fn serve_worker<P, S, R, E>(
+    mut sender: S,
+    mut receiver: R,
+    scheduler: Arc<Scheduler<P>>,
+    worker_id: String,
+) where
+    P: Persistence,
+    S: Sink<Message> + Unpin,
+    R: Stream<Item = Result<Message, E>> + Unpin,
+    E: std::fmt::Display,
+{
+    // Wait for task-ready notifications instead of polling on a fixed interval;
+    // the periodic wake-up also keeps the worker alive while idle
+    let max_wait = scheduler.poll_interval();
+    let mut ready = scheduler.subscribe_tasks();
+    let mut cancellations = scheduler.subscribe_cancellations();
 
     // Task sending loop (polls for tasks)
     let send_task = async {
         loop {
-            poll_timer.tick().await;
-
-            // Poll for available tasks
-            let tasks = scheduler.poll_tasks(&worker_id, POLL_TASKS_LIMIT).await;
-
-            for task in tasks {
-                // Skip if already sent
-                {
-                    let guard = sent_tasks.lock().await;
-                    if guard.contains(&task.task_id) {
-                        continue;
-                    }
-                }
-
-                // Convert input to JSON Value
-                let input_value = match serde_json::from_slice(&task.input) {
-                    Ok(v) => v,
-                    Err(_) => {
-                        // If not valid JSON, wrap as string
-                        serde_json::Value::String(
-                            String::from_utf8_lossy(&task.input).to_string(),
-                        )
-                    }
-                };
+            ready.borrow_and_update();
 
-                let payload = TaskPayload {
-                    task_id: task.task_id.clone(),
-                    workflow_id: task.workflow_id.clone(),
-                    step_name: task.step_name.clone(),
-                    input: input_value,
-                    retry_policy: None,
+            // The server is going away; workers should reconnect elsewhere or later
+            if scheduler.shutdown_state() == ShutdownState::Stopped {
+                tracing::info!(
+                    "Closing task stream for worker {}: server shutting down",
+                    worker_id
+                );
+                let frame = CloseFrame {
+                    code: close_code::AWAY,
+                    reason: "server shutting down".into(),
                 };
+                let _ = sender.send(Message::Close(Some(frame))).await;
+                return;
+            }
 
-                let msg = TaskMessage {
-                    msg_type: "task".to_string(),
-                    payload,
-                };
+            // Unknown or evicted workers must register again
+            if !scheduler.touch_worker(&worker_id).await {
+                tracing::info!("Closing task stream for unregistered worker {}", worker_id);
+                let _ = sender.send(Message::Close(None)).await;
+                return;
+            }
 
-                let json = match serde_json::to_string(&msg) {
+            let mut tasks = match scheduler
+                .poll_tasks(&worker_id, scheduler.poll_tasks_limit())
+                .await
+            {
+                Ok(tasks) => tasks.into_iter(),
+                Err(e) => {
+                    // Retries inside the scheduler are exhausted; the worker should reconnect
+                    tracing::error!("Task dispatch failed for worker {}: {:#}", worker_id, e);
+                    let frame = CloseFrame {
+                        code: close_code::ERROR,
+                        reason: "task dispatch failed".into(),
+                    };
+                    let _ = sender.send(Message::Close(Some(frame))).await;
+                    return;
+                }
+            };
+            while let Some(task) = tasks.next() {
+                let task_id = TaskId::new(&task.workflow_id, &task.step_name);
+                let json = match serde_json::to_string(&task_message(task)) {
                     Ok(j) => j,
                     Err(e) => {
                         tracing::error!("Failed to serialize task: {}", e);
+                        scheduler.return_task(&task_id, &worker_id).await;
                         continue;
                     }
                 };
 
-                if sender.send(Message::Text(json.into())).await.is_err() {
+                if sender.send(Message::Text(json)).await.is_err() {
                     tracing::debug!("WebSocket send failed for worker {}", worker_id);
+                    // Hand back this task and the rest of the batch
+                    scheduler.return_task(&task_id, &worker_id).await;
+                    for task in tasks {
+                        let task_id = TaskId::new(&task.workflow_id, &task.step_name);
+                        scheduler.return_task(&task_id, &worker_id).await;
+                    }
                     return;
                 }
+            }
 
-                sent_tasks.lock().await.insert(task.task_id);
+            let cancellation = tokio::select! {
+                _ = scheduler.wait_for_tasks(&mut ready, max_wait) => continue,
+                _ = scheduler.stopped() => continue,
+                received = cancellations.recv() => received,
+            };
+            // Tell the worker to stop executing tasks of cancelled workflows
+            let cancellation = match cancellation {
+                Ok(cancellation) if cancellation.worker_id == worker_id => cancellation,
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Worker {} missed {} cancellations", worker_id, skipped);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            };
+            let Ok(json) = serde_json::to_string(&task_cancelled_message(cancellation)) else {
+                continue;
+            };
+            if sender.send(Message::Text(json)).await.is_err() {
+                tracing::debug!("WebSocket send failed for worker {}", worker_id);
+                return;
             }
         }
     };
 
-    // ACK receiving loop
+    // Worker message receiving loop
     let recv_task = async {
         while let Some(result) = receiver.next().await {
             match result {
                 Ok(Message::Text(text)) => {
-                    // Handle ACK messages
-                    if let Ok(ack) = serde_json::from_str::<serde_json::Value>(&text) {
-                        if ack.get("type").and_then(|t| t.as_str()) == Some("ack") {
-                            if let Some(task_id) = ack.get("taskId").and_then(|t| t.as_str()) {
-                                tracing::debug!("Received ACK for task: {}", task_id);
-                                // Remove from sent_tasks to free memory
-                                sent_tasks_for_recv.lock().await.remove(task_id);
-                            }
-                        }
-                    }
+                    handle_worker_message(&scheduler, &worker_id, &text).await;
                 }
                 Ok(Message::Close(_)) => {
                     tracing::debug!("WebSocket closed by worker {}", worker_id);
@@ -162,9 +214,381 @@ async fn handle_worker_socket<P: Persistence + Clone + Send + Sync + 'static>(
     tracing::info!("WebSocket connection closed for worker {}", worker_id);
 }
 
+fn task_message(task: Task) -> TaskMessage {
+    // JSON input is forwarded byte for byte; anything else is sent as base64
+    let raw_input = task
+        .input
+        .is_json()
+        .then(|| String::from_utf8(task.input.as_bytes().to_vec()).ok())
+        .flatten()
+        .and_then(|json| RawValue::from_string(json).ok());
+    let input_base64 = match raw_input {
+        Some(_) => None,
+        None => Some(BASE64.encode(task.input.as_bytes())),
+    };
+
+    TaskMessage {
+        msg_type: "task".to_string(),
+        payload: TaskPayload {
+            task_id: task.task_id,
+            workflow_id: task.workflow_id,
+            step_name: task.step_name,
+            input: raw_input,
+            input_base64,
+            retry_policy: None,
+            heartbeat_interval: task.heartbeat_interval,
+            signal: task.signal.map(|signal| SignalInfo {
+                name: signal.name,
+                payload: serde_json::from_slice(&signal.payload).unwrap_or_else(|_| {
+                    serde_json::Value::String(String::from_utf8_lossy(&signal.payload).to_string())
+                }),
+                received_at: signal.received_at.to_rfc3339(),
+            }),
+        },
+    }
+}
+
+fn task_cancelled_message(cancellation: TaskCancellation) -> TaskCancelledMessage {
+    TaskCancelledMessage {
+        msg_type: "task_cancelled".to_string(),
+        payload: TaskCancelledPayload {
+            task_id: cancellation.task_id.to_string(),
+            workflow_id: cancellation.task_id.workflow_id,
+            step_name: cancellation.task_id.step_name,
+        },
+    }
+}
+
+/// Handle a text message received from a worker.
+///
+/// Step reports and completions go through the same code path as the REST
+/// step endpoints. Acks confirm the worker's lease on a task and nacks reject
+/// it, handing it back to the scheduler without consuming an attempt.
+pub(crate) async fn handle_worker_message<P: Persistence>(
+    scheduler: &Scheduler<P>,
+    worker_id: &str,
+    text: &str,
+) {
+    let message = match serde_json::from_str::<WorkerMessage>(text) {
+        Ok(message) => message,
+        Err(e) => {
+            tracing::debug!("Ignoring message from worker {}: {}", worker_id, e);
+            return;
+        }
+    };
+
+    match message {
+        WorkerMessage::Ack { task_id } => {
+            tracing::debug!("Received ACK for task: {}", task_id);
+            let confirmed = match TaskId::parse(&task_id) {
+                Some(id) => scheduler.confirm_lease(&id, worker_id).await,
+                None => false,
+            };
+            if !confirmed {
+                tracing::warn!(
+                    "Worker {} acknowledged task {} it does not hold",
+                    worker_id,
+                    task_id
+                );
+            }
+        }
+        WorkerMessage::Nack {
+            task_id,
+            reason,
+            requeue,
+        } => {
+            tracing::debug!("Received NACK for task: {}", task_id);
+            let released = match TaskId::parse(&task_id) {
+                Some(id) => {
+                    scheduler
+                        .reject_task(&id, worker_id, &reason, requeue)
+                        .await
+                }
+                None => false,
+            };
+            if !released {
+                tracing::warn!(
+                    "Worker {} rejected task {} it does not hold",
+                    worker_id,
+                    task_id
+                );
+            }
+        }
+        WorkerMessage::Report { task_id, request } => {
+            if let Err(e) = apply_report(scheduler, &task_id, Some(worker_id), request).await {
+                tracing::warn!(
+                    "Step report for task {} from worker {} failed: {}",
+                    task_id,
+                    worker_id,
+                    e.body.message
+                );
+            }
+        }
+        WorkerMessage::Complete {
+            task_id,
+            mut request,
+        } => {
+            // 以连接所属的 worker 校验租约
+            request.worker_id = Some(worker_id.to_string());
+            if let Err(e) = apply_complete(scheduler, &task_id, request).await {
+                tracing::warn!(
+                    "Step completion for task {} from worker {} failed: {}",
+                    task_id,
+                    worker_id,
+                    e.body.message
+                );
+            }
+        }
+        WorkerMessage::Heartbeat { task_id, request } => {
+            if let Err(e) = apply_heartbeat(scheduler, &task_id, request).await {
+                tracing::warn!(
+                    "Step heartbeat for task {} from worker {} failed: {}",
+                    task_id,
+                    worker_id,
+                    e.body.message
+                );
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::payload::Payload;
+    use crate::persistence::l0_memory::L0MemoryStore;
+    use crate::state_machine::{Workflow, WorkflowState};
+    use futures::channel::mpsc;
+    use std::collections::HashSet;
+    use tokio::time::Duration;
+
+    /// An in-memory stand-in for a worker's WebSocket connection
+    struct FakeSocket {
+        received: mpsc::UnboundedReceiver<Message>,
+        replies: mpsc::UnboundedSender<Result<Message, String>>,
+    }
+
+    impl FakeSocket {
+        fn connect(scheduler: &Arc<Scheduler<L0MemoryStore>>, worker_id: &str) -> Self {
+            let (outgoing, received) = mpsc::unbounded();
+            let (replies, incoming) = mpsc::unbounded();
+            tokio::spawn(serve_worker(
+                outgoing,
+                incoming,
+                Arc::clone(scheduler),
+                worker_id.to_string(),
+            ));
+            FakeSocket { received, replies }
+        }
+
+        /// Task ids delivered since the last call
+        fn drain_tasks(&mut self) -> Vec<String> {
+            let mut task_ids = Vec::new();
+            while let Ok(Some(message)) = self.received.try_next() {
+                let Message::Text(text) = message else {
+                    continue;
+                };
+                let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+                if value["type"] == "task" {
+                    task_ids.push(value["payload"]["taskId"].as_str().unwrap().to_string());
+                }
+            }
+            task_ids
+        }
+
+        fn reply(&self, message_type: &str, task_id: &str) {
+            let text = serde_json::json!({ "type": message_type, "taskId": task_id });
+            self.replies
+                .unbounded_send(Ok(Message::Text(text.to_string())))
+                .unwrap();
+        }
+    }
+
+    async fn scheduler_with_workers() -> Arc<Scheduler<L0MemoryStore>> {
+        let store = L0MemoryStore::new();
+        for i in 0..3 {
+            let mut workflow = Workflow::new(format!("wf-{}", i), "order".to_string(), vec![]);
+            workflow.state = workflow.state.try_start().unwrap();
+            store.save_workflow(&workflow).await.unwrap();
+        }
+
+        let scheduler = Arc::new(Scheduler::new(store));
+        for worker_id in ["worker-1", "worker-2"] {
+            scheduler
+                .register_worker(
+                    worker_id.to_string(),
+                    "shop".to_string(),
+                    "default".to_string(),
+                    vec!["order".to_string()],
+                    vec![],
+                )
+                .await;
+        }
+        scheduler
+    }
+
+    async fn settle() {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    #[tokio::test]
+    async fn test_each_task_is_delivered_to_exactly_one_socket() {
+        let scheduler = scheduler_with_workers().await;
+        let mut first = FakeSocket::connect(&scheduler, "worker-1");
+        let mut second = FakeSocket::connect(&scheduler, "worker-2");
+        settle().await;
+
+        let first_tasks = first.drain_tasks();
+        let mut delivered = first_tasks.clone();
+        delivered.extend(second.drain_tasks());
+        assert_eq!(delivered.len(), 3);
+        assert_eq!(delivered.iter().collect::<HashSet<_>>().len(), 3);
+
+        // Ack confirms the lease held by the sending worker
+        let task_id = first_tasks[0].clone();
+        first.reply("ack", &task_id);
+        settle().await;
+        let lease = scheduler
+            .lease(&TaskId::parse(&task_id).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(lease.worker_id, "worker-1");
+        assert!(lease.confirmed);
+
+        // Nack hands the task back, and it is delivered exactly once more
+        first.reply("nack", &task_id);
+        settle().await;
+        let mut redelivered = first.drain_tasks();
+        redelivered.extend(second.drain_tasks());
+        assert_eq!(redelivered, vec![task_id]);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_closes_socket_with_going_away() {
+        let scheduler = scheduler_with_workers().await;
+        let mut socket = FakeSocket::connect(&scheduler, "worker-1");
+        settle().await;
+        assert_eq!(socket.drain_tasks().len(), 3);
+
+        let remaining = scheduler.shutdown(Duration::from_millis(50)).await;
+        assert_eq!(remaining.len(), 3);
+        settle().await;
+
+        let mut close = None;
+        while let Ok(Some(message)) = socket.received.try_next() {
+            if let Message::Close(frame) = message {
+                close = frame;
+            }
+        }
+        let frame = close.expect("close frame");
+        assert_eq!(frame.code, close_code::AWAY);
+    }
+
+    /// Task messages delivered since the last call
+    fn drain_messages(socket: &mut FakeSocket) -> Vec<serde_json::Value> {
+        let mut messages = Vec::new();
+        while let Ok(Some(message)) = socket.received.try_next() {
+            if let Message::Text(text) = message {
+                let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+                if value["type"] == "task" {
+                    messages.push(value);
+                }
+            }
+        }
+        messages
+    }
+
+    #[tokio::test]
+    async fn test_binary_input_and_output_survive_round_trip() {
+        let store = L0MemoryStore::new();
+        let input = vec![0xff, 0x00, 0xfe, 0x80];
+        let mut workflow = Workflow::new("wf-1".to_string(), "order".to_string(), input.clone());
+        workflow.state = workflow.state.try_start().unwrap();
+        store.save_workflow(&workflow).await.unwrap();
+        let scheduler = Arc::new(Scheduler::new(store));
+        scheduler
+            .register_worker(
+                "worker-1".to_string(),
+                "shop".to_string(),
+                "default".to_string(),
+                vec!["order".to_string()],
+                vec![],
+            )
+            .await;
+
+        let mut socket = FakeSocket::connect(&scheduler, "worker-1");
+        settle().await;
+        let messages = drain_messages(&mut socket);
+        assert_eq!(messages.len(), 1);
+        let payload = &messages[0]["payload"];
+        assert!(payload["input"].is_null());
+        let encoded = payload["inputBase64"].as_str().unwrap();
+        assert_eq!(BASE64.decode(encoded).unwrap(), input);
+
+        let output = vec![0x00, 0x9f, 0x92, 0x96];
+        let reply = serde_json::json!({
+            "type": "complete",
+            "taskId": payload["taskId"],
+            "outputBase64": BASE64.encode(&output),
+        });
+        socket
+            .replies
+            .unbounded_send(Ok(Message::Text(reply.to_string())))
+            .unwrap();
+        settle().await;
+
+        let workflow = scheduler
+            .persistence
+            .get_workflow("wf-1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            workflow.state,
+            WorkflowState::Completed { ref result } if result.as_bytes() == output.as_slice()
+        ));
+    }
+
+    #[test]
+    fn test_json_input_is_forwarded_byte_for_byte() {
+        let raw = br#"{"b": 1, "a": [1.50, "x"]}"#.to_vec();
+        let task = Task {
+            task_id: TaskId::new("wf-1", "start").to_string(),
+            workflow_id: "wf-1".to_string(),
+            step_name: "start".to_string(),
+            target_service: None,
+            target_resource: None,
+            resource_type: crate::task::ResourceType::Step,
+            input: Payload::from_bytes(raw.clone()),
+            retry: None,
+            workflow_type: "order".to_string(),
+            heartbeat_interval: 0,
+            signal: None,
+            timeout: None,
+        };
+        let text = serde_json::to_string(&task_message(task)).unwrap();
+        assert!(text.contains(r#""input":{"b": 1, "a": [1.50, "x"]}"#));
+        assert!(!text.contains("inputBase64"));
+    }
+
+    #[test]
+    fn test_nack_reason_and_requeue_are_optional() {
+        let message: WorkerMessage =
+            serde_json::from_str(r#"{"type": "nack", "taskId": "wf-1:start"}"#).unwrap();
+        assert!(matches!(
+            message,
+            WorkerMessage::Nack { ref reason, requeue: true, .. } if reason.is_empty()
+        ));
+
+        let message: WorkerMessage = serde_json::from_str(
+            r#"{"type": "nack", "taskId": "wf-1:start", "reason": "no GPU", "requeue": false}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            message,
+            WorkerMessage::Nack { ref reason, requeue: false, .. } if reason == "no GPU"
+        ));
+    }
 
     #[test]
     fn test_ws_query_deserialize() {
@@ -1,6 +1,14 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use tokio::sync::broadcast;
 
+use crate::payload::Payload;
+use crate::tracker::StepProgress;
+
 /// WebSocket 事件类型
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub enum EventType {
@@ -8,22 +16,68 @@ pub enum EventType {
     StepStarted,
     StepCompleted,
     StepFailed,
+    StepProgress,
     WorkflowCompleted,
     WorkflowFailed,
     WorkflowCancelled,
+    WorkflowTerminated,
+    SignalReceived,
+}
+
+impl EventType {
+    /// 全部事件类型
+    pub const ALL: [EventType; 9] = [
+        EventType::StepStarted,
+        EventType::StepCompleted,
+        EventType::StepFailed,
+        EventType::StepProgress,
+        EventType::WorkflowCompleted,
+        EventType::WorkflowFailed,
+        EventType::WorkflowCancelled,
+        EventType::WorkflowTerminated,
+        EventType::SignalReceived,
+    ];
+
+    /// 事件 JSON 中 `event_type` 字段的取值
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventType::StepStarted => "step_started",
+            EventType::StepCompleted => "step_completed",
+            EventType::StepFailed => "step_failed",
+            EventType::StepProgress => "step_progress",
+            EventType::WorkflowCompleted => "workflow_completed",
+            EventType::WorkflowFailed => "workflow_failed",
+            EventType::WorkflowCancelled => "workflow_cancelled",
+            EventType::WorkflowTerminated => "workflow_terminated",
+            EventType::SignalReceived => "signal_received",
+        }
+    }
+
+    /// 是否是 workflow 进入终态的事件
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            EventType::WorkflowCompleted
+                | EventType::WorkflowFailed
+                | EventType::WorkflowCancelled
+                | EventType::WorkflowTerminated
+        )
+    }
 }
 
 /// WebSocket 事件负载
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StepStartedPayload {
     pub step_name: String,
-    pub input: Vec<u8>,
+    pub input: Payload,
+    /// 第几次尝试，从 1 开始
+    pub attempt: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StepCompletedPayload {
     pub step_name: String,
-    pub output: Vec<u8>,
+    pub output: Payload,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,9 +87,17 @@ pub struct StepFailedPayload {
     pub attempt: u32,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepProgressPayload {
+    pub step_name: String,
+    pub percent: Option<f64>,
+    pub message: Option<String>,
+    pub details: Option<Payload>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkflowCompletedPayload {
-    pub result: Vec<u8>,
+    pub result: Payload,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,6 +108,18 @@ pub struct WorkflowFailedPayload {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkflowCancelledPayload {}
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowTerminatedPayload {
+    pub reason: String,
+    pub requested_by: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalReceivedPayload {
+    pub signal_name: String,
+    pub payload: Payload,
+}
+
 /// WebSocket 事件
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkflowEvent {
@@ -53,7 +127,9 @@ pub struct WorkflowEvent {
     pub event_type: EventType,
     pub workflow_id: String,
     pub workflow_type: String,
-    pub timestamp: u64,
+    /// 序列化为 Unix 时间戳（秒）
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub timestamp: DateTime<Utc>,
     #[serde(flatten)]
     pub payload: EventPayload,
 }
@@ -61,12 +137,16 @@ pub struct WorkflowEvent {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "event_type", rename_all = "snake_case")]
 pub enum EventPayload {
-    StepStarted(StepStartedPayload),
-    StepCompleted(StepCompletedPayload),
+    // 带负载的变体装箱以控制事件体积
+    StepStarted(Box<StepStartedPayload>),
+    StepCompleted(Box<StepCompletedPayload>),
     StepFailed(StepFailedPayload),
+    StepProgress(Box<StepProgressPayload>),
     WorkflowCompleted(WorkflowCompletedPayload),
     WorkflowFailed(WorkflowFailedPayload),
     WorkflowCancelled(WorkflowCancelledPayload),
+    WorkflowTerminated(WorkflowTerminatedPayload),
+    SignalReceived(Box<SignalReceivedPayload>),
 }
 
 impl WorkflowEvent {
@@ -80,10 +160,7 @@ impl WorkflowEvent {
             event_type,
             workflow_id,
             workflow_type,
-            timestamp: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
+            timestamp: Utc::now(),
             payload,
         }
     }
@@ -99,20 +176,100 @@ impl WorkflowEvent {
     }
 }
 
+/// 默认的广播通道容量
+pub const DEFAULT_BROADCAST_CAPACITY: usize = 1000;
+
+/// 默认保留的最近事件数量
+pub const DEFAULT_EVENT_JOURNAL_CAPACITY: usize = 1000;
+
+/// 单个 workflow 订阅通道的容量
+pub const WORKFLOW_CHANNEL_CAPACITY: usize = 256;
+
+/// 最近事件的环形缓冲，满了以后丢弃最早的事件
+struct EventJournal {
+    events: VecDeque<WorkflowEvent>,
+    capacity: usize,
+}
+
+impl EventJournal {
+    fn record(&mut self, event: &WorkflowEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event.clone());
+    }
+}
+
+/// 一次广播的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BroadcastResult {
+    /// 收到事件的订阅者数量，没有订阅者时为 0
+    pub delivered: usize,
+}
+
 /// 事件广播器
 ///
 /// 使用 tokio::sync::broadcast 实现多客户端事件广播。
-/// 所有订阅者会收到相同的事件，支持背压处理。
+/// 全局通道的订阅者会收到所有事件，支持背压处理；只关心单个 workflow 的订阅者使用
+/// [`EventBroadcaster::subscribe_workflow`]，不会因其他 workflow 的事件而滞后。
+/// 最近的事件保存在有界日志中，供晚连接的客户端回放（见 [`EventBroadcaster::history`]）。
 #[derive(Clone)]
 pub struct EventBroadcaster {
     tx: broadcast::Sender<WorkflowEvent>,
+    /// 按 workflow 划分的订阅通道，workflow 进入终态或没有订阅者时移除
+    workflow_channels: Arc<Mutex<HashMap<String, broadcast::Sender<WorkflowEvent>>>>,
+    journal: Arc<Mutex<EventJournal>>,
+    lagged: Arc<AtomicU64>,
 }
 
 impl EventBroadcaster {
     /// 创建新的广播器
     pub fn new() -> Self {
-        let (tx, _rx) = broadcast::channel(1000);
-        Self { tx }
+        Self::with_journal_capacity(DEFAULT_EVENT_JOURNAL_CAPACITY)
+    }
+
+    /// 创建新的广播器，事件日志最多保留 `capacity` 个事件，为 0 时不保留
+    pub fn with_journal_capacity(capacity: usize) -> Self {
+        Self::with_capacity(DEFAULT_BROADCAST_CAPACITY, capacity)
+    }
+
+    /// 创建新的广播器，广播通道容量为 `channel_capacity`（至少为 1），事件日志最多保留 `capacity` 个事件
+    pub fn with_capacity(channel_capacity: usize, capacity: usize) -> Self {
+        let (tx, _rx) = broadcast::channel(channel_capacity.max(1));
+        Self {
+            tx,
+            workflow_channels: Arc::new(Mutex::new(HashMap::new())),
+            journal: Arc::new(Mutex::new(EventJournal {
+                events: VecDeque::with_capacity(capacity),
+                capacity,
+            })),
+            lagged: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// 事件日志的容量
+    pub fn journal_capacity(&self) -> usize {
+        self.journal.lock().unwrap().capacity
+    }
+
+    /// 事件日志中某个 workflow 的事件，按广播顺序排列
+    ///
+    /// 指定 `since_timestamp` 时只返回该时间（秒，含）之后的事件。
+    pub fn history(&self, workflow_id: &str, since_timestamp: Option<u64>) -> Vec<WorkflowEvent> {
+        let since = since_timestamp.unwrap_or(0);
+        self.journal
+            .lock()
+            .unwrap()
+            .events
+            .iter()
+            .filter(|event| {
+                event.workflow_id == workflow_id && event.timestamp.timestamp() as u64 >= since
+            })
+            .cloned()
+            .collect()
     }
 
     /// 获取内部的广播 Sender
@@ -120,22 +277,65 @@ impl EventBroadcaster {
         self.tx.clone()
     }
 
-    /// 订阅事件
+    /// 订阅所有 workflow 的事件
     pub fn subscribe(&self) -> broadcast::Receiver<WorkflowEvent> {
         self.tx.subscribe()
     }
 
-    /// 广播事件给所有订阅者
-    pub fn broadcast(
-        &self,
-        event: WorkflowEvent,
-    ) -> Result<usize, broadcast::error::SendError<WorkflowEvent>> {
-        self.tx.send(event)
+    /// 只订阅某个 workflow 的事件
+    ///
+    /// 该 workflow 的终态事件送达后通道关闭，接收端读完缓冲的事件后收到 `RecvError::Closed`；
+    /// workflow 重新开始（例如重试）后需要重新订阅。
+    pub fn subscribe_workflow(&self, workflow_id: &str) -> broadcast::Receiver<WorkflowEvent> {
+        self.workflow_channels
+            .lock()
+            .unwrap()
+            .entry(workflow_id.to_string())
+            .or_insert_with(|| broadcast::channel(WORKFLOW_CHANNEL_CAPACITY).0)
+            .subscribe()
+    }
+
+    /// 记录事件并广播给所有订阅者
+    ///
+    /// 没有订阅者不算失败：事件仍写入事件日志，供之后连接的客户端回放。
+    pub fn broadcast(&self, event: WorkflowEvent) -> BroadcastResult {
+        // 持锁发送，保证日志顺序与广播顺序一致
+        let mut journal = self.journal.lock().unwrap();
+        journal.record(&event);
+        let mut delivered = 0;
+        {
+            let mut channels = self.workflow_channels.lock().unwrap();
+            if let Some(tx) = channels.get(&event.workflow_id) {
+                delivered += tx.send(event.clone()).unwrap_or(0);
+                if event.event_type.is_terminal() || tx.receiver_count() == 0 {
+                    channels.remove(&event.workflow_id);
+                }
+            }
+        }
+        delivered += self.tx.send(event).unwrap_or(0);
+        BroadcastResult { delivered }
     }
 
-    /// 获取当前订阅者数量
+    /// 记录订阅者因处理过慢而丢失的事件数量（`RecvError::Lagged`）
+    pub fn record_lagged(&self, skipped: u64) {
+        self.lagged.fetch_add(skipped, Ordering::Relaxed);
+    }
+
+    /// 启动以来订阅者丢失的事件总数
+    pub fn lagged(&self) -> u64 {
+        self.lagged.load(Ordering::Relaxed)
+    }
+
+    /// 获取当前订阅者数量，包括只订阅单个 workflow 的订阅者
     pub fn subscriber_count(&self) -> usize {
-        self.tx.receiver_count()
+        let scoped: usize = self
+            .workflow_channels
+            .lock()
+            .unwrap()
+            .values()
+            .map(broadcast::Sender::receiver_count)
+            .sum();
+        self.tx.receiver_count() + scoped
     }
 
     /// 广播 step 开始事件
@@ -144,12 +344,14 @@ impl EventBroadcaster {
         workflow_id: &str,
         workflow_type: &str,
         step_name: &str,
-        input: Vec<u8>,
-    ) -> Result<usize, broadcast::error::SendError<WorkflowEvent>> {
-        let payload = EventPayload::StepStarted(StepStartedPayload {
+        input: impl Into<Payload>,
+        attempt: u32,
+    ) -> BroadcastResult {
+        let payload = EventPayload::StepStarted(Box::new(StepStartedPayload {
             step_name: step_name.to_string(),
-            input,
-        });
+            input: input.into(),
+            attempt,
+        }));
         let event = WorkflowEvent::new(
             EventType::StepStarted,
             workflow_id.to_string(),
@@ -165,12 +367,12 @@ impl EventBroadcaster {
         workflow_id: &str,
         workflow_type: &str,
         step_name: &str,
-        output: Vec<u8>,
-    ) -> Result<usize, broadcast::error::SendError<WorkflowEvent>> {
-        let payload = EventPayload::StepCompleted(StepCompletedPayload {
+        output: impl Into<Payload>,
+    ) -> BroadcastResult {
+        let payload = EventPayload::StepCompleted(Box::new(StepCompletedPayload {
             step_name: step_name.to_string(),
-            output,
-        });
+            output: output.into(),
+        }));
         let event = WorkflowEvent::new(
             EventType::StepCompleted,
             workflow_id.to_string(),
@@ -188,7 +390,7 @@ impl EventBroadcaster {
         step_name: &str,
         error: String,
         attempt: u32,
-    ) -> Result<usize, broadcast::error::SendError<WorkflowEvent>> {
+    ) -> BroadcastResult {
         let payload = EventPayload::StepFailed(StepFailedPayload {
             step_name: step_name.to_string(),
             error,
@@ -203,14 +405,39 @@ impl EventBroadcaster {
         self.broadcast(event)
     }
 
+    /// 广播 step 进度事件
+    pub async fn broadcast_step_progress(
+        &self,
+        workflow_id: &str,
+        workflow_type: &str,
+        step_name: &str,
+        progress: StepProgress,
+    ) -> BroadcastResult {
+        let payload = EventPayload::StepProgress(Box::new(StepProgressPayload {
+            step_name: step_name.to_string(),
+            percent: progress.percent,
+            message: progress.message,
+            details: progress.details,
+        }));
+        let event = WorkflowEvent::new(
+            EventType::StepProgress,
+            workflow_id.to_string(),
+            workflow_type.to_string(),
+            payload,
+        );
+        self.broadcast(event)
+    }
+
     /// 广播 workflow 完成事件
     pub async fn broadcast_workflow_completed(
         &self,
         workflow_id: &str,
         workflow_type: &str,
-        result: Vec<u8>,
-    ) -> Result<usize, broadcast::error::SendError<WorkflowEvent>> {
-        let payload = EventPayload::WorkflowCompleted(WorkflowCompletedPayload { result });
+        result: impl Into<Payload>,
+    ) -> BroadcastResult {
+        let payload = EventPayload::WorkflowCompleted(WorkflowCompletedPayload {
+            result: result.into(),
+        });
         let event = WorkflowEvent::new(
             EventType::WorkflowCompleted,
             workflow_id.to_string(),
@@ -226,7 +453,7 @@ impl EventBroadcaster {
         workflow_id: &str,
         workflow_type: &str,
         error: String,
-    ) -> Result<usize, broadcast::error::SendError<WorkflowEvent>> {
+    ) -> BroadcastResult {
         let payload = EventPayload::WorkflowFailed(WorkflowFailedPayload { error });
         let event = WorkflowEvent::new(
             EventType::WorkflowFailed,
@@ -236,6 +463,64 @@ impl EventBroadcaster {
         );
         self.broadcast(event)
     }
+
+    /// 广播 workflow 取消事件
+    pub async fn broadcast_workflow_cancelled(
+        &self,
+        workflow_id: &str,
+        workflow_type: &str,
+    ) -> BroadcastResult {
+        let payload = EventPayload::WorkflowCancelled(WorkflowCancelledPayload {});
+        let event = WorkflowEvent::new(
+            EventType::WorkflowCancelled,
+            workflow_id.to_string(),
+            workflow_type.to_string(),
+            payload,
+        );
+        self.broadcast(event)
+    }
+
+    /// 广播 workflow 被强制结束事件
+    pub async fn broadcast_workflow_terminated(
+        &self,
+        workflow_id: &str,
+        workflow_type: &str,
+        reason: String,
+        requested_by: Option<String>,
+    ) -> BroadcastResult {
+        let payload = EventPayload::WorkflowTerminated(WorkflowTerminatedPayload {
+            reason,
+            requested_by,
+        });
+        let event = WorkflowEvent::new(
+            EventType::WorkflowTerminated,
+            workflow_id.to_string(),
+            workflow_type.to_string(),
+            payload,
+        );
+        self.broadcast(event)
+    }
+
+    /// 广播 workflow 收到 signal 事件
+    pub async fn broadcast_signal_received(
+        &self,
+        workflow_id: &str,
+        workflow_type: &str,
+        signal_name: &str,
+        payload: impl Into<Payload>,
+    ) -> BroadcastResult {
+        let payload = EventPayload::SignalReceived(Box::new(SignalReceivedPayload {
+            signal_name: signal_name.to_string(),
+            payload: payload.into(),
+        }));
+        let event = WorkflowEvent::new(
+            EventType::SignalReceived,
+            workflow_id.to_string(),
+            workflow_type.to_string(),
+            payload,
+        );
+        self.broadcast(event)
+    }
 }
 
 impl Default for EventBroadcaster {
@@ -254,12 +539,11 @@ mod tests {
         let mut rx = broadcaster.subscribe();
 
         // 广播事件
-        let count = broadcaster
-            .broadcast_step_started("wf-1", "test-type", "step-1", vec![1, 2, 3])
-            .await
-            .unwrap();
+        let result = broadcaster
+            .broadcast_step_started("wf-1", "test-type", "step-1", vec![1, 2, 3], 1)
+            .await;
 
-        assert_eq!(count, 1);
+        assert_eq!(result.delivered, 1);
 
         // 接收事件
         let event = rx.recv().await.unwrap();
@@ -268,12 +552,25 @@ mod tests {
 
         if let EventPayload::StepStarted(payload) = event.payload {
             assert_eq!(payload.step_name, "step-1");
-            assert_eq!(payload.input, vec![1, 2, 3]);
+            assert_eq!(payload.input.as_bytes(), [1, 2, 3]);
         } else {
             panic!("Expected StepStarted payload");
         }
     }
 
+    #[tokio::test]
+    async fn test_event_type_name_matches_json() {
+        let broadcaster = EventBroadcaster::new();
+        let mut rx = broadcaster.subscribe();
+        broadcaster
+            .broadcast_step_failed("wf-1", "test", "step-1", "boom".to_string(), 1)
+            .await;
+
+        let event = rx.recv().await.unwrap();
+        let json: serde_json::Value = serde_json::from_str(&event.to_json().unwrap()).unwrap();
+        assert_eq!(json["event_type"], event.event_type.as_str());
+    }
+
     #[tokio::test]
     async fn test_multiple_subscribers() {
         let broadcaster = EventBroadcaster::new();
@@ -283,8 +580,7 @@ mod tests {
         // 广播事件
         broadcaster
             .broadcast_step_completed("wf-1", "test", "step-1", vec![4, 5, 6])
-            .await
-            .unwrap();
+            .await;
 
         // 两个订阅者都应该收到事件
         let event1 = rx1.recv().await.unwrap();
@@ -294,6 +590,98 @@ mod tests {
         assert_eq!(event2.event_type, EventType::StepCompleted);
     }
 
+    #[tokio::test]
+    async fn test_broadcast_without_subscribers_is_journaled() {
+        let broadcaster = EventBroadcaster::new();
+        assert_eq!(broadcaster.subscriber_count(), 0);
+
+        let result = broadcaster
+            .broadcast_workflow_completed("wf-1", "test", vec![])
+            .await;
+        assert_eq!(result, BroadcastResult { delivered: 0 });
+        assert_eq!(broadcaster.history("wf-1", None).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_lagged_receivers_are_counted() {
+        let broadcaster = EventBroadcaster::new();
+        let mut rx = broadcaster.subscribe();
+        // 超过通道容量，最早的事件被覆盖
+        for i in 0..2000 {
+            broadcaster
+                .broadcast_step_started("wf-1", "test", &format!("step-{}", i), vec![], 1)
+                .await;
+        }
+
+        let skipped = match rx.recv().await {
+            Err(broadcast::error::RecvError::Lagged(skipped)) => skipped,
+            other => panic!("expected lag, got {:?}", other),
+        };
+        broadcaster.record_lagged(skipped);
+        assert!(skipped > 0);
+        assert_eq!(broadcaster.lagged(), skipped);
+    }
+
+    #[tokio::test]
+    async fn test_workflow_subscription_is_not_flooded_by_other_workflows() {
+        let broadcaster = EventBroadcaster::new();
+        let mut firehose = broadcaster.subscribe();
+        let mut scoped = broadcaster.subscribe_workflow("wf-target");
+
+        // 大量无关 workflow 的事件远超全局通道容量
+        for i in 0..10_000 {
+            broadcaster
+                .broadcast_step_started(&format!("wf-{}", i), "test", "step", vec![], 1)
+                .await;
+            if i % 2_500 == 0 {
+                broadcaster
+                    .broadcast_step_started("wf-target", "test", &format!("step-{}", i), vec![], 1)
+                    .await;
+            }
+        }
+        broadcaster
+            .broadcast_workflow_completed("wf-target", "test", vec![])
+            .await;
+
+        let mut received = Vec::new();
+        loop {
+            match scoped.recv().await {
+                Ok(event) => received.push(event),
+                Err(broadcast::error::RecvError::Closed) => break,
+                Err(e) => panic!("scoped subscriber should not lag: {:?}", e),
+            }
+        }
+        assert_eq!(received.len(), 5);
+        assert!(received
+            .iter()
+            .all(|event| event.workflow_id == "wf-target"));
+        assert_eq!(
+            received.last().unwrap().event_type,
+            EventType::WorkflowCompleted
+        );
+        // 终态事件之后通道被移除
+        assert_eq!(broadcaster.workflow_channels.lock().unwrap().len(), 0);
+
+        assert!(matches!(
+            firehose.recv().await,
+            Err(broadcast::error::RecvError::Lagged(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unused_workflow_channels_are_removed() {
+        let broadcaster = EventBroadcaster::new();
+        let rx = broadcaster.subscribe_workflow("wf-1");
+        assert_eq!(broadcaster.subscriber_count(), 1);
+        drop(rx);
+
+        broadcaster
+            .broadcast_step_started("wf-1", "test", "step", vec![], 1)
+            .await;
+        assert!(broadcaster.workflow_channels.lock().unwrap().is_empty());
+        assert_eq!(broadcaster.subscriber_count(), 0);
+    }
+
     #[tokio::test]
     async fn test_serialize_deserialize() {
         let event = WorkflowEvent::new(
@@ -317,4 +705,79 @@ mod tests {
         // 验证 payload 正确反序列化（这包含了事件类型信息）
         assert!(matches!(decoded.payload, EventPayload::StepFailed(_)));
     }
+
+    fn step_names(events: &[WorkflowEvent]) -> Vec<String> {
+        events
+            .iter()
+            .map(|event| match &event.payload {
+                EventPayload::StepStarted(payload) => payload.step_name.clone(),
+                payload => panic!("unexpected payload {:?}", payload),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_journal_keeps_latest_events_in_order() {
+        let broadcaster = EventBroadcaster::with_journal_capacity(3);
+        for i in 0..5 {
+            let workflow_id = if i == 3 { "wf-2" } else { "wf-1" };
+            broadcaster
+                .broadcast_step_started(workflow_id, "test", &format!("step-{}", i), vec![], 1)
+                .await;
+        }
+
+        // 容量为 3：step-0、step-1 已被丢弃
+        assert_eq!(
+            step_names(&broadcaster.history("wf-1", None)),
+            vec!["step-2", "step-4"]
+        );
+        assert_eq!(
+            step_names(&broadcaster.history("wf-2", None)),
+            vec!["step-3"]
+        );
+        assert!(broadcaster.history("wf-1", Some(u64::MAX)).is_empty());
+        assert!(EventBroadcaster::with_journal_capacity(0)
+            .history("wf-1", None)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_journal_is_bounded_under_concurrent_broadcasts() {
+        let broadcaster = EventBroadcaster::with_journal_capacity(50);
+        let threads: Vec<_> = (0..4)
+            .map(|t| {
+                let broadcaster = broadcaster.clone();
+                std::thread::spawn(move || {
+                    for i in 0..100 {
+                        let event = WorkflowEvent::new(
+                            EventType::StepStarted,
+                            "wf-1".to_string(),
+                            "test".to_string(),
+                            EventPayload::StepStarted(Box::new(StepStartedPayload {
+                                step_name: format!("{}-{}", t, i),
+                                input: Payload::default(),
+                                attempt: 1,
+                            })),
+                        );
+                        broadcaster.broadcast(event);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let history = broadcaster.history("wf-1", None);
+        assert_eq!(history.len(), 50);
+        // 每个线程的事件在日志中保持发送顺序
+        for t in 0..4 {
+            let prefix = format!("{}-", t);
+            let indices: Vec<u32> = step_names(&history)
+                .iter()
+                .filter_map(|name| name.strip_prefix(&prefix)?.parse().ok())
+                .collect();
+            assert!(indices.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+    }
 }
@@ -0,0 +1,349 @@
+//! Workflow 取消
+//!
+//! 取消 workflow 时撤销它的全部 task 租约，并通知持有这些 task 的 worker：
+//! REST WebSocket 推送 `task_cancelled` 消息，gRPC 通过 `WatchCancellations` 流推送。
+//! 此后对这些 task 的完成、失败和心跳上报都以 CANCELLED 错误拒绝。
+//! workflow 的定时器和子 workflow 随之取消。
+//!
+//! 强制结束（terminate）面向运维：不论 workflow 执行到哪个 step，立即进入
+//! `Terminated` 终态并记录原因和发起人，task 租约的撤销和通知与取消相同。
+
+use std::fmt;
+
+use crate::persistence::Persistence;
+use crate::scheduler::Scheduler;
+use crate::state_machine::{InvalidTransition, Workflow};
+use crate::task::TaskId;
+
+/// 被取消的 task 及持有它的 worker
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskCancellation {
+    pub task_id: TaskId,
+    pub worker_id: String,
+}
+
+/// 取消 workflow 的错误
+#[derive(Debug)]
+pub enum CancelError {
+    /// workflow 不存在
+    WorkflowNotFound(String),
+    /// workflow 已经结束，无法取消
+    NotCancellable(String, InvalidTransition),
+    /// 持久化层错误
+    Persistence(anyhow::Error),
+}
+
+impl fmt::Display for CancelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CancelError::WorkflowNotFound(workflow_id) => {
+                write!(f, "Workflow not found: {}", workflow_id)
+            }
+            CancelError::NotCancellable(workflow_id, transition) => write!(
+                f,
+                "Workflow {} is {} and cannot be cancelled",
+                workflow_id, transition.from
+            ),
+            CancelError::Persistence(e) => write!(f, "Persistence error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CancelError {}
+
+impl From<anyhow::Error> for CancelError {
+    fn from(e: anyhow::Error) -> Self {
+        CancelError::Persistence(e)
+    }
+}
+
+/// 强制结束 workflow 的错误
+#[derive(Debug)]
+pub enum TerminateError {
+    /// 未提供原因
+    EmptyReason,
+    /// workflow 不存在
+    WorkflowNotFound(String),
+    /// workflow 已经结束
+    AlreadyFinished(String, InvalidTransition),
+    /// 持久化层错误
+    Persistence(anyhow::Error),
+}
+
+impl fmt::Display for TerminateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TerminateError::EmptyReason => write!(f, "A termination reason is required"),
+            TerminateError::WorkflowNotFound(workflow_id) => {
+                write!(f, "Workflow not found: {}", workflow_id)
+            }
+            TerminateError::AlreadyFinished(workflow_id, transition) => write!(
+                f,
+                "Workflow {} has already finished as {}",
+                workflow_id, transition.from
+            ),
+            TerminateError::Persistence(e) => write!(f, "Persistence error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for TerminateError {}
+
+impl From<anyhow::Error> for TerminateError {
+    fn from(e: anyhow::Error) -> Self {
+        TerminateError::Persistence(e)
+    }
+}
+
+impl<P: Persistence> Scheduler<P> {
+    /// 取消未结束的 workflow 及其子 workflow
+    pub async fn cancel_workflow(&self, workflow_id: &str) -> Result<(), CancelError> {
+        let workflow = self
+            .persistence
+            .get_workflow(workflow_id)
+            .await?
+            .ok_or_else(|| CancelError::WorkflowNotFound(workflow_id.to_string()))?;
+        self.cancel_running(&workflow).await?;
+        self.cancel_children(workflow_id).await?;
+        Ok(())
+    }
+
+    /// 强制结束未结束的 workflow，记录原因和发起人；子 workflow 随之取消
+    pub async fn terminate_workflow(
+        &self,
+        workflow_id: &str,
+        reason: &str,
+        requested_by: Option<&str>,
+    ) -> Result<(), TerminateError> {
+        let reason = reason.trim();
+        if reason.is_empty() {
+            return Err(TerminateError::EmptyReason);
+        }
+        let workflow = self
+            .persistence
+            .get_workflow(workflow_id)
+            .await?
+            .ok_or_else(|| TerminateError::WorkflowNotFound(workflow_id.to_string()))?;
+        let requested_by = requested_by.map(str::to_string);
+        let terminated_state = workflow
+            .state
+            .try_terminate(reason.to_string(), requested_by.clone())
+            .map_err(|e| TerminateError::AlreadyFinished(workflow_id.to_string(), e))?;
+        self.persistence
+            .update_workflow_state(workflow_id, terminated_state)
+            .await?;
+        self.stop_execution(&workflow.id).await?;
+        self.tracker.workflow_terminated(workflow_id).await;
+        tracing::warn!(
+            workflow_id,
+            reason,
+            requested_by = requested_by.as_deref(),
+            "workflow terminated"
+        );
+        self.broadcaster
+            .broadcast_workflow_terminated(
+                workflow_id,
+                &workflow.workflow_type,
+                reason.to_string(),
+                requested_by,
+            )
+            .await;
+        self.cancel_children(workflow_id).await?;
+        // 父 workflow 可能在等待该 workflow 结束
+        self.notify_workflow_changed(workflow_id);
+        Ok(())
+    }
+
+    /// 把 workflow 标记为取消并撤销它的 task 和定时器，workflow 已结束时返回 `NotCancellable`
+    pub(crate) async fn cancel_running(&self, workflow: &Workflow) -> Result<(), CancelError> {
+        let cancelled_state = workflow
+            .state
+            .try_cancel()
+            .map_err(|e| CancelError::NotCancellable(workflow.id.clone(), e))?;
+        self.persistence
+            .update_workflow_state(&workflow.id, cancelled_state)
+            .await?;
+        self.stop_execution(&workflow.id).await?;
+        self.tracker.workflow_cancelled(&workflow.id).await;
+        self.broadcaster
+            .broadcast_workflow_cancelled(&workflow.id, &workflow.workflow_type)
+            .await;
+        // 父 workflow 可能在等待该 workflow 结束
+        self.notify_workflow_changed(&workflow.id);
+        Ok(())
+    }
+
+    /// 撤销已结束 workflow 的定时器和 task 租约，并通知持有 task 的 worker
+    async fn stop_execution(&self, workflow_id: &str) -> anyhow::Result<()> {
+        self.cancel_timers(workflow_id).await?;
+        for cancellation in self.revoke_workflow_leases(workflow_id).await {
+            tracing::debug!(
+                task_id = %cancellation.task_id,
+                worker_id = %cancellation.worker_id,
+                "task cancelled"
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::broadcaster::EventType;
+    use crate::persistence::l0_memory::L0MemoryStore;
+    use crate::step_lifecycle::StepLifecycleError;
+    use crate::tracker::StepProgress;
+
+    async fn leased_scheduler() -> Scheduler<L0MemoryStore> {
+        let store = L0MemoryStore::new();
+        let mut workflow = Workflow::new("wf-1".to_string(), "order".to_string(), vec![]);
+        workflow.state = workflow.state.try_start().unwrap();
+        store.save_workflow(&workflow).await.unwrap();
+
+        let scheduler = Scheduler::new(store);
+        scheduler
+            .register_worker(
+                "worker-1".to_string(),
+                "shop".to_string(),
+                "default".to_string(),
+                vec!["order".to_string()],
+                vec![],
+            )
+            .await;
+        scheduler
+    }
+
+    #[tokio::test]
+    async fn test_cancel_revokes_leases_and_notifies_holder() {
+        let scheduler = leased_scheduler().await;
+        let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        let mut cancellations = scheduler.subscribe_cancellations();
+        let mut events = scheduler.broadcaster.subscribe();
+
+        scheduler.cancel_workflow("wf-1").await.unwrap();
+
+        let cancellation = cancellations.try_recv().unwrap();
+        assert_eq!(cancellation.task_id, TaskId::new("wf-1", "start"));
+        assert_eq!(cancellation.worker_id, "worker-1");
+        assert!(scheduler.lease(&cancellation.task_id).await.is_none());
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.event_type, EventType::WorkflowCancelled);
+        assert!(scheduler
+            .poll_tasks("worker-1", 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let err = scheduler.cancel_workflow("wf-1").await.unwrap_err();
+        assert!(
+            matches!(&err, CancelError::NotCancellable(_, transition) if transition.from == "CANCELLED")
+        );
+        assert_eq!(
+            err.to_string(),
+            "Workflow wf-1 is CANCELLED and cannot be cancelled"
+        );
+        assert!(matches!(
+            scheduler.cancel_workflow("missing").await,
+            Err(CancelError::WorkflowNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_reports_for_cancelled_workflow_are_rejected() {
+        let scheduler = leased_scheduler().await;
+        let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        let task_id = tasks[0].task_id.clone();
+        scheduler.cancel_workflow("wf-1").await.unwrap();
+
+        let lifecycle = scheduler.lifecycle();
+        let completed = lifecycle
+            .complete_task(&task_id, Some("worker-1"), vec![])
+            .await;
+        assert!(matches!(
+            completed,
+            Err(StepLifecycleError::WorkflowCancelled(_))
+        ));
+        let failed = lifecycle
+            .fail_task(&task_id, Some("worker-1"), "boom".to_string())
+            .await;
+        assert!(matches!(
+            failed,
+            Err(StepLifecycleError::WorkflowCancelled(_))
+        ));
+        let heartbeat = lifecycle.heartbeat(&task_id, StepProgress::default()).await;
+        assert!(matches!(
+            heartbeat,
+            Err(StepLifecycleError::WorkflowCancelled(_))
+        ));
+
+        let workflow = scheduler.persistence.get_workflow("wf-1").await.unwrap();
+        assert_eq!(workflow.unwrap().state, crate::WorkflowState::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn test_terminate_records_reason_and_revokes_leases() {
+        let scheduler = leased_scheduler().await;
+        scheduler
+            .workflow_started(&Workflow::new(
+                "wf-1".to_string(),
+                "order".to_string(),
+                vec![],
+            ))
+            .await;
+        let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        let task_id = tasks[0].task_id.clone();
+        scheduler
+            .lifecycle()
+            .step_started("wf-1", "start", vec![])
+            .await
+            .unwrap();
+        let mut cancellations = scheduler.subscribe_cancellations();
+        let mut events = scheduler.broadcaster.subscribe();
+
+        assert!(matches!(
+            scheduler.terminate_workflow("wf-1", " ", None).await,
+            Err(TerminateError::EmptyReason)
+        ));
+        scheduler
+            .terminate_workflow("wf-1", "stuck on a bad deploy", Some("ops@example.com"))
+            .await
+            .unwrap();
+
+        let workflow = scheduler.persistence.get_workflow("wf-1").await.unwrap();
+        assert_eq!(
+            workflow.unwrap().state,
+            crate::WorkflowState::Terminated {
+                reason: "stuck on a bad deploy".to_string(),
+                requested_by: Some("ops@example.com".to_string()),
+            }
+        );
+        assert_eq!(cancellations.try_recv().unwrap().worker_id, "worker-1");
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.event_type, EventType::WorkflowTerminated);
+        let execution = scheduler.tracker.get_execution("wf-1").await.unwrap();
+        assert!(execution.completed_at.is_some());
+        assert_eq!(
+            execution.step_executions["start"].status,
+            crate::StepExecutionStatus::Cancelled
+        );
+
+        let completed = scheduler
+            .lifecycle()
+            .complete_task(&task_id, Some("worker-1"), vec![])
+            .await;
+        assert!(matches!(
+            completed,
+            Err(StepLifecycleError::WorkflowTerminated(_))
+        ));
+        assert!(matches!(
+            scheduler.terminate_workflow("wf-1", "again", None).await,
+            Err(TerminateError::AlreadyFinished(_, transition)) if transition.from == "TERMINATED"
+        ));
+        assert!(matches!(
+            scheduler.terminate_workflow("missing", "gone", None).await,
+            Err(TerminateError::WorkflowNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,368 @@
+//! 子 workflow
+//!
+//! step 完成时可以请求启动子 workflow，子 workflow 记录启动它的父 workflow。
+//! 定义中的 `await_children` step 不分发给 worker：父 workflow 的全部子 workflow 结束后，
+//! 后台任务完成该 step；任一子 workflow 失败或被取消时父 workflow 随之失败。
+//! 取消父 workflow 会级联取消仍在运行的子 workflow。
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+
+use crate::cancellation::CancelError;
+use crate::persistence::{ListOptions, Persistence, StateKind};
+use crate::scheduler::Scheduler;
+use crate::state_machine::{Workflow, WorkflowState};
+
+/// 子 workflow 等待任务在没有就绪通知时的检查间隔
+pub const CHILD_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// step 完成时请求启动的子 workflow
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChildWorkflowSpec {
+    pub workflow_type: String,
+    pub input: Vec<u8>,
+}
+
+impl<P: Persistence> Scheduler<P> {
+    /// 为父 workflow 启动子 workflow，返回子 workflow 的 id
+    pub async fn start_child_workflows(
+        &self,
+        parent_id: &str,
+        children: Vec<ChildWorkflowSpec>,
+    ) -> anyhow::Result<Vec<String>> {
+        let mut ids = Vec::with_capacity(children.len());
+        for child in children {
+            let workflow_id = uuid::Uuid::new_v4().to_string();
+            // 子 workflow 同样固定使用启动时的最新版本
+            let version = self
+                .definition(&child.workflow_type)
+                .await
+                .map(|definition| definition.version);
+            let mut workflow = Workflow::new(workflow_id.clone(), child.workflow_type, child.input)
+                .with_parent(parent_id);
+            if let Some(version) = version {
+                workflow = workflow.with_definition_version(version);
+            }
+            if let Ok(running) = workflow.state.try_start() {
+                let started_at = workflow.started_at;
+                workflow.transition(running, started_at);
+            }
+            self.persistence.save_workflow(&workflow).await?;
+
+            self.workflow_started(&workflow).await;
+            self.tracker.child_started(parent_id, &workflow_id).await;
+            ids.push(workflow_id);
+        }
+        self.notify_tasks_ready();
+        Ok(ids)
+    }
+
+    /// workflow 的全部子 workflow，按启动时间排序
+    pub async fn child_workflows(&self, parent_id: &str) -> anyhow::Result<Vec<Workflow>> {
+        let options = ListOptions {
+            parent_workflow_id: Some(parent_id.to_string()),
+            ..Default::default()
+        };
+        self.persistence.list_workflows_paged(&options).await
+    }
+
+    /// 检查等待子 workflow 的 step，返回完成或失败的 step 数量
+    pub async fn process_child_waits(&self) -> anyhow::Result<usize> {
+        let options = ListOptions {
+            state_filter: Some(StateKind::Running),
+            ..Default::default()
+        };
+        let running = self.persistence.list_workflows_paged(&options).await?;
+
+        let mut settled = 0;
+        for workflow in &running {
+            let Some(definition) = self.workflow_definition(workflow).await else {
+                continue;
+            };
+            for step in definition.ready_steps(workflow) {
+                if !step.await_children {
+                    continue;
+                }
+                let started = self
+                    .tracker
+                    .get_execution(&workflow.id)
+                    .await
+                    .is_some_and(|e| e.step_executions.contains_key(&step.name));
+                if !started {
+                    self.lifecycle()
+                        .step_started(&workflow.id, &step.name, Vec::new())
+                        .await?;
+                }
+
+                let children = self.child_workflows(&workflow.id).await?;
+                if !children.iter().all(|child| child.state.is_terminal()) {
+                    continue;
+                }
+                let failure = children.iter().find_map(|child| match &child.state {
+                    WorkflowState::Failed { error } => {
+                        Some(format!("Child workflow {} failed: {}", child.id, error))
+                    }
+                    WorkflowState::Cancelled => {
+                        Some(format!("Child workflow {} was cancelled", child.id))
+                    }
+                    WorkflowState::Terminated { reason, .. } => Some(format!(
+                        "Child workflow {} was terminated: {}",
+                        child.id, reason
+                    )),
+                    _ => None,
+                });
+                self.lifecycle()
+                    .children_settled(&workflow.id, &step.name, failure)
+                    .await?;
+                settled += 1;
+            }
+        }
+        Ok(settled)
+    }
+
+    /// 级联取消未结束的子 workflow（包括子 workflow 的子 workflow），返回被取消的 id
+    pub async fn cancel_children(&self, parent_id: &str) -> anyhow::Result<Vec<String>> {
+        let mut cancelled = Vec::new();
+        let mut parents = vec![parent_id.to_string()];
+        while let Some(parent) = parents.pop() {
+            for child in self.child_workflows(&parent).await? {
+                parents.push(child.id.clone());
+                match self.cancel_running(&child).await {
+                    Ok(()) => cancelled.push(child.id),
+                    Err(CancelError::NotCancellable(..)) => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+        Ok(cancelled)
+    }
+}
+
+/// 启动后台子 workflow 等待任务
+///
+/// step 完成或 workflow 结束时立即检查，否则每 [`CHILD_CHECK_INTERVAL`] 检查一次。
+pub fn spawn_child_wait_task<P: Persistence + 'static>(
+    scheduler: Arc<Scheduler<P>>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ready = scheduler.subscribe_tasks();
+        loop {
+            ready.borrow_and_update();
+            match scheduler.process_child_waits().await {
+                Ok(0) => {}
+                Ok(settled) => tracing::info!(settled, "child workflow waits settled"),
+                Err(e) => tracing::warn!("child workflow wait processing failed: {}", e),
+            }
+            tokio::select! {
+                _ = ready.changed() => {}
+                _ = scheduler.stopped() => break,
+                _ = tokio::time::sleep(CHILD_CHECK_INTERVAL) => {}
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::{StepDefinition, WorkflowDefinition};
+    use crate::persistence::l0_memory::L0MemoryStore;
+    use crate::step_lifecycle::StepLifecycleError;
+
+    async fn scheduler() -> Scheduler<L0MemoryStore> {
+        let store = L0MemoryStore::new();
+        let mut workflow = Workflow::new("parent".to_string(), "order".to_string(), vec![]);
+        workflow.state = workflow.state.try_start().unwrap();
+        store.save_workflow(&workflow).await.unwrap();
+
+        let scheduler = Scheduler::new(store);
+        scheduler
+            .register_definition(WorkflowDefinition::new(
+                "order",
+                vec![
+                    StepDefinition::new("fan-out"),
+                    StepDefinition::await_children("join"),
+                    StepDefinition::new("ship"),
+                ],
+            ))
+            .await
+            .unwrap();
+        scheduler
+            .register_definition(WorkflowDefinition::new(
+                "shipment",
+                vec![StepDefinition::new("pack")],
+            ))
+            .await
+            .unwrap();
+        scheduler
+            .register_worker(
+                "worker-1".to_string(),
+                "shop".to_string(),
+                "default".to_string(),
+                vec!["order".to_string(), "shipment".to_string()],
+                vec![],
+            )
+            .await;
+        scheduler
+    }
+
+    fn shipments(count: usize) -> Vec<ChildWorkflowSpec> {
+        (0..count)
+            .map(|i| ChildWorkflowSpec {
+                workflow_type: "shipment".to_string(),
+                input: vec![i as u8],
+            })
+            .collect()
+    }
+
+    /// 完成 fan-out step 并启动子 workflow，返回子 workflow 的 id
+    async fn fan_out(scheduler: &Scheduler<L0MemoryStore>, count: usize) -> Vec<String> {
+        let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].step_name, "fan-out");
+        scheduler
+            .lifecycle()
+            .complete_task_with_children(
+                &tasks[0].task_id,
+                Some("worker-1"),
+                vec![],
+                shipments(count),
+            )
+            .await
+            .unwrap();
+        scheduler
+            .child_workflows("parent")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|child| child.id)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_await_children_completes_when_children_finish() {
+        let scheduler = scheduler().await;
+        let children = fan_out(&scheduler, 2).await;
+        assert_eq!(children.len(), 2);
+        let execution = scheduler.tracker.get_execution("parent").await.unwrap();
+        assert_eq!(execution.child_workflow_ids.len(), 2);
+
+        // join 不分发，只分发子 workflow 的 step
+        let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert!(tasks.iter().all(|task| task.step_name == "pack"));
+        assert_eq!(scheduler.process_child_waits().await.unwrap(), 0);
+
+        for task in &tasks {
+            scheduler
+                .complete_task(&task.task_id, vec![])
+                .await
+                .unwrap();
+        }
+        assert_eq!(scheduler.process_child_waits().await.unwrap(), 1);
+
+        let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].step_name, "ship");
+    }
+
+    #[tokio::test]
+    async fn test_failed_child_fails_parent() {
+        let scheduler = scheduler().await;
+        let children = fan_out(&scheduler, 2).await;
+        scheduler
+            .persistence
+            .update_workflow_state(
+                &children[0],
+                WorkflowState::Failed {
+                    error: "out of stock".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+        scheduler
+            .persistence
+            .update_workflow_state(
+                &children[1],
+                WorkflowState::Completed {
+                    result: vec![].into(),
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(scheduler.process_child_waits().await.unwrap(), 1);
+        let parent = scheduler
+            .persistence
+            .get_workflow("parent")
+            .await
+            .unwrap()
+            .unwrap();
+        match parent.state {
+            WorkflowState::Failed { error } => assert!(error.contains("out of stock")),
+            state => panic!("unexpected state {:?}", state),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancel_children_cascades() {
+        let scheduler = scheduler().await;
+        let children = fan_out(&scheduler, 2).await;
+        let grandchildren = scheduler
+            .start_child_workflows(&children[0], shipments(1))
+            .await
+            .unwrap();
+        scheduler
+            .persistence
+            .update_workflow_state(
+                &children[1],
+                WorkflowState::Completed {
+                    result: vec![].into(),
+                },
+            )
+            .await
+            .unwrap();
+
+        let mut cancelled = scheduler.cancel_children("parent").await.unwrap();
+        cancelled.sort();
+        let mut expected = vec![children[0].clone(), grandchildren[0].clone()];
+        expected.sort();
+        assert_eq!(cancelled, expected);
+        let finished = scheduler
+            .persistence
+            .get_workflow(&children[1])
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(finished.is_complete());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_child_type_is_rejected() {
+        let scheduler = scheduler().await;
+        let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        let err = scheduler
+            .lifecycle()
+            .complete_task_with_children(
+                &tasks[0].task_id,
+                Some("worker-1"),
+                vec![],
+                vec![ChildWorkflowSpec {
+                    workflow_type: "refund".to_string(),
+                    input: vec![],
+                }],
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, StepLifecycleError::UnknownWorkflowType(_)));
+        assert!(scheduler
+            .child_workflows("parent")
+            .await
+            .unwrap()
+            .is_empty());
+        let parent = scheduler.persistence.get_workflow("parent").await.unwrap();
+        assert!(parent.unwrap().completed_steps.is_empty());
+    }
+}
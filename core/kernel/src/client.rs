@@ -0,0 +1,1070 @@
+//! Rust gRPC 客户端
+//!
+//! [`AetherClient`] 封装生成的 `ClientService` 和 `WorkerService` stub，Rust 程序不必手写
+//! tonic 调用即可启动、等待 workflow，或作为远程 worker 执行 step：
+//!
+//! ```
+//! # use aetherframework_kernel::{AetherKernel, StepDefinition, WorkflowDefinition};
+//! # #[tokio::main]
+//! # async fn main() -> anyhow::Result<()> {
+//! # let kernel = AetherKernel::builder()
+//! #     .http_addr("127.0.0.1:0")
+//! #     .grpc_addr("127.0.0.1:0")
+//! #     .build();
+//! # kernel
+//! #     .scheduler()
+//! #     .register_definition(WorkflowDefinition::new("order", vec![StepDefinition::new("charge")]))
+//! #     .await?;
+//! # let addrs = kernel.start().await?;
+//! use aetherframework_kernel::client::AetherClient;
+//! use aetherframework_kernel::proto;
+//! use futures::StreamExt;
+//!
+//! let client = AetherClient::builder(addrs.grpc.to_string())
+//!     .api_key("secret")
+//!     .connect()
+//!     .await?;
+//! let handle = client.start_workflow("order", br#"{"id":1}"#.to_vec()).await?;
+//!
+//! // 连接同一服务器的 worker，断线后自动重连并重新注册
+//! let worker = client.worker(proto::RegisterRequest {
+//!     worker_id: "worker-1".to_string(),
+//!     service_name: "shop".to_string(),
+//!     provides: vec![proto::ServiceResource {
+//!         name: "charge".to_string(),
+//!         r#type: proto::ResourceType::Step as i32,
+//!         metadata: None,
+//!     }],
+//!     ..Default::default()
+//! });
+//! let mut tasks = worker.tasks();
+//! let task = tasks.next().await.unwrap()?;
+//! worker.complete(&task.task_id, br#"{"charged":true}"#.to_vec()).await?;
+//!
+//! assert_eq!(handle.result().await?, br#"{"charged":true}"#);
+//! # kernel.shutdown().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! 服务器启用 gRPC 认证时通过 [`AetherClientBuilder::auth_token`] 传入令牌。[`WorkerClient`]
+//! 用该令牌（静态令牌或引导令牌）注册，之后的调用携带 `Register` 返回的会话令牌。
+//! API key 和自定义 metadata 附加在每个请求上，供服务器前面的代理或网关使用。
+//! TLS 连接需要启用 `tls` feature。
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::metadata::{AsciiMetadataKey, AsciiMetadataValue};
+use tonic::service::interceptor::InterceptedService;
+use tonic::service::Interceptor;
+use tonic::transport::{Channel, Endpoint};
+use tonic::{Code, Request, Status};
+
+use crate::definition::WorkflowDefinition;
+use crate::grpc_auth::AUTHORIZATION_METADATA;
+use crate::grpc_server::to_proto_definition;
+use crate::proto;
+use crate::proto::client_service_client::ClientServiceClient;
+use crate::proto::worker_service_client::WorkerServiceClient;
+
+/// 携带 API key 的 metadata，与 REST 的 `X-Api-Key` 请求头同名
+pub const API_KEY_METADATA: &str = "x-api-key";
+
+/// 任务流打开期间 worker 心跳的默认间隔
+pub const DEFAULT_WORKER_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// 任务流断开后第一次重连前的等待
+const RECONNECT_INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+
+/// 重连等待的上限，每次失败后等待时间翻倍
+const RECONNECT_MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+/// [`AetherClient`] 及其创建的句柄返回的错误
+#[derive(Debug)]
+pub enum ClientError {
+    /// 地址无效或连接失败
+    Transport(tonic::transport::Error),
+    /// metadata 的键或值不是合法的 ASCII metadata
+    InvalidMetadata(String),
+    /// 服务器拒绝了调用
+    Status(Box<Status>),
+    /// workflow 结束但没有完成
+    WorkflowFailed {
+        workflow_id: String,
+        state: proto::State,
+        error: String,
+    },
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Transport(e) => write!(f, "Transport error: {}", e),
+            ClientError::InvalidMetadata(entry) => write!(f, "Invalid metadata: {}", entry),
+            ClientError::Status(status) => write!(
+                f,
+                "Server returned {:?}: {}",
+                status.code(),
+                status.message()
+            ),
+            ClientError::WorkflowFailed {
+                workflow_id,
+                state,
+                error,
+            } => write!(
+                f,
+                "Workflow {} finished as {}: {}",
+                workflow_id,
+                state.as_str_name(),
+                error
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Transport(e) => Some(e),
+            ClientError::Status(status) => Some(status.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<tonic::transport::Error> for ClientError {
+    fn from(e: tonic::transport::Error) -> Self {
+        ClientError::Transport(e)
+    }
+}
+
+impl From<Status> for ClientError {
+    fn from(status: Status) -> Self {
+        ClientError::Status(Box::new(status))
+    }
+}
+
+/// 为每个请求添加配置的 metadata，请求中已有的条目保持不变
+#[derive(Clone, Default)]
+pub struct MetadataInterceptor {
+    entries: Arc<Vec<(AsciiMetadataKey, AsciiMetadataValue)>>,
+}
+
+impl Interceptor for MetadataInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        for (key, value) in self.entries.iter() {
+            if !request.metadata().contains_key(key) {
+                request.metadata_mut().insert(key.clone(), value.clone());
+            }
+        }
+        Ok(request)
+    }
+}
+
+/// 封装的 stub 使用的 channel
+pub type ClientChannel = InterceptedService<Channel, MetadataInterceptor>;
+
+/// [`AetherClient`] 的构建器，由 [`AetherClient::builder`] 创建
+pub struct AetherClientBuilder {
+    endpoint: String,
+    metadata: Vec<(String, String)>,
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    #[cfg(feature = "tls")]
+    tls: Option<tonic::transport::ClientTlsConfig>,
+}
+
+impl AetherClientBuilder {
+    /// 每次调用都以 `x-api-key` metadata 发送 `key`
+    pub fn api_key(self, key: impl Into<String>) -> Self {
+        self.metadata(API_KEY_METADATA, key)
+    }
+
+    /// 每次调用都以 `authorization: Bearer <token>` 发送 `token`，用于启用了 gRPC 认证的服务器
+    pub fn auth_token(self, token: impl AsRef<str>) -> Self {
+        self.metadata(AUTHORIZATION_METADATA, format!("Bearer {}", token.as_ref()))
+    }
+
+    /// 每次调用都发送该 metadata，替换之前设置的同名条目
+    pub fn metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        let key = key.into().to_ascii_lowercase();
+        self.metadata.retain(|(existing, _)| *existing != key);
+        self.metadata.push((key, value.into()));
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// 每个 unary 调用的超时，流式调用和 [`WorkflowHandle::result`] 不受限制
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// 通过 TLS 连接，如 `ClientTlsConfig::new().ca_certificate(ca).domain_name("aether")`
+    #[cfg(feature = "tls")]
+    pub fn tls(mut self, config: tonic::transport::ClientTlsConfig) -> Self {
+        self.tls = Some(config);
+        self
+    }
+
+    pub async fn connect(self) -> Result<AetherClient, ClientError> {
+        let entries = self
+            .metadata
+            .into_iter()
+            .map(|(key, value)| {
+                let parsed_key = AsciiMetadataKey::from_str(&key)
+                    .map_err(|_| ClientError::InvalidMetadata(format!("key {:?}", key)))?;
+                let parsed_value = AsciiMetadataValue::try_from(value.as_str())
+                    .map_err(|_| ClientError::InvalidMetadata(format!("value of {:?}", key)))?;
+                Ok((parsed_key, parsed_value))
+            })
+            .collect::<Result<Vec<_>, ClientError>>()?;
+
+        let mut endpoint = Endpoint::from_shared(endpoint_uri(&self.endpoint))?;
+        if let Some(timeout) = self.connect_timeout {
+            endpoint = endpoint.connect_timeout(timeout);
+        }
+        #[cfg(feature = "tls")]
+        if let Some(tls) = self.tls {
+            endpoint = endpoint.tls_config(tls)?;
+        }
+        let channel = endpoint.connect().await?;
+        Ok(AetherClient::from_channel(
+            channel,
+            MetadataInterceptor {
+                entries: Arc::new(entries),
+            },
+            self.timeout,
+        ))
+    }
+}
+
+/// 不带 scheme 的 `host:port` 使用明文 HTTP/2，与 CLI 的 `--server` 一致
+fn endpoint_uri(endpoint: &str) -> String {
+    if endpoint.starts_with("http://") || endpoint.starts_with("https://") {
+        endpoint.to_string()
+    } else {
+        format!("http://{}", endpoint)
+    }
+}
+
+/// 到 Aether gRPC 服务器的连接，clone 开销很小
+#[derive(Clone)]
+pub struct AetherClient {
+    client: ClientServiceClient<ClientChannel>,
+    worker: WorkerServiceClient<ClientChannel>,
+    timeout: Option<Duration>,
+}
+
+impl AetherClient {
+    /// 不带认证 metadata 和 TLS 直接连接
+    pub async fn connect(endpoint: impl Into<String>) -> Result<Self, ClientError> {
+        Self::builder(endpoint).connect().await
+    }
+
+    /// `endpoint` 是 `http://localhost:7234` 这样的 URI，或 `host:port`
+    pub fn builder(endpoint: impl Into<String>) -> AetherClientBuilder {
+        AetherClientBuilder {
+            endpoint: endpoint.into(),
+            metadata: Vec::new(),
+            connect_timeout: None,
+            timeout: None,
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+
+    fn from_channel(
+        channel: Channel,
+        interceptor: MetadataInterceptor,
+        timeout: Option<Duration>,
+    ) -> Self {
+        AetherClient {
+            client: ClientServiceClient::with_interceptor(channel.clone(), interceptor.clone()),
+            worker: WorkerServiceClient::with_interceptor(channel, interceptor),
+            timeout,
+        }
+    }
+
+    /// 包装请求消息并设置 unary 调用超时
+    fn request<T>(&self, message: T) -> Request<T> {
+        let mut request = Request::new(message);
+        if let Some(timeout) = self.timeout {
+            request.set_timeout(timeout);
+        }
+        request
+    }
+
+    /// 生成的 `ClientService` stub，用于这里没有封装的调用
+    pub fn client_service(&self) -> ClientServiceClient<ClientChannel> {
+        self.client.clone()
+    }
+
+    /// 生成的 `WorkerService` stub，用于这里没有封装的调用
+    pub fn worker_service(&self) -> WorkerServiceClient<ClientChannel> {
+        self.worker.clone()
+    }
+
+    pub async fn start_workflow(
+        &self,
+        workflow_type: impl Into<String>,
+        input: Vec<u8>,
+    ) -> Result<WorkflowHandle, ClientError> {
+        self.start_workflow_with(proto::StartWorkflowRequest {
+            workflow_type: workflow_type.into(),
+            input,
+            ..Default::default()
+        })
+        .await
+    }
+
+    /// 启动 workflow，可指定幂等键、优先级或固定的定义版本
+    pub async fn start_workflow_with(
+        &self,
+        request: proto::StartWorkflowRequest,
+    ) -> Result<WorkflowHandle, ClientError> {
+        let response = self
+            .client
+            .clone()
+            .start_workflow(self.request(request))
+            .await?
+            .into_inner();
+        Ok(WorkflowHandle {
+            client: self.clone(),
+            workflow_id: response.workflow_id,
+            already_exists: response.already_exists,
+        })
+    }
+
+    /// 在服务器上注册一个版本的 workflow 定义
+    ///
+    /// 以相同的 step 重复注册同一版本会成功。定义无效时返回 `InvalidArgument`，
+    /// 错误信息列出每个问题及其位置，如 `steps[1].dependsOn[0]`。
+    pub async fn register_definition(
+        &self,
+        definition: &WorkflowDefinition,
+    ) -> Result<(), ClientError> {
+        self.client
+            .clone()
+            .register_workflow_definition(self.request(proto::RegisterWorkflowDefinitionRequest {
+                definition: Some(to_proto_definition(definition)),
+            }))
+            .await?;
+        Ok(())
+    }
+
+    /// 已有 workflow 的句柄，使用句柄时才检查 id 是否存在
+    pub fn workflow(&self, workflow_id: impl Into<String>) -> WorkflowHandle {
+        WorkflowHandle {
+            client: self.clone(),
+            workflow_id: workflow_id.into(),
+            already_exists: true,
+        }
+    }
+
+    /// 一页 workflow，传入返回的 `next_page_token` 获取下一页
+    pub async fn list_workflows(
+        &self,
+        filter: proto::ListWorkflowsRequest,
+    ) -> Result<proto::ListWorkflowsResponse, ClientError> {
+        Ok(self
+            .client
+            .clone()
+            .list_workflows(self.request(filter))
+            .await?
+            .into_inner())
+    }
+
+    pub async fn server_info(&self) -> Result<proto::ServerInfo, ClientError> {
+        Ok(self
+            .client
+            .clone()
+            .get_server_info(self.request(proto::GetServerInfoRequest {}))
+            .await?
+            .into_inner())
+    }
+
+    /// 以 `registration` 注册的 worker 端客户端
+    pub fn worker(&self, registration: proto::RegisterRequest) -> WorkerClient {
+        WorkerClient {
+            client: self.clone(),
+            registration,
+            max_tasks: 0,
+            heartbeat_interval: DEFAULT_WORKER_HEARTBEAT_INTERVAL,
+            session: Arc::new(RwLock::new(None)),
+        }
+    }
+}
+
+/// 已启动的 workflow
+#[derive(Clone)]
+pub struct WorkflowHandle {
+    client: AetherClient,
+    workflow_id: String,
+    already_exists: bool,
+}
+
+impl WorkflowHandle {
+    pub fn id(&self) -> &str {
+        &self.workflow_id
+    }
+
+    /// 启动时是否找到了幂等键相同的已有 workflow
+    pub fn already_exists(&self) -> bool {
+        self.already_exists
+    }
+
+    pub async fn status(&self) -> Result<proto::WorkflowStatus, ClientError> {
+        let client = &self.client;
+        Ok(client
+            .client
+            .clone()
+            .get_workflow_status(client.request(proto::GetStatusRequest {
+                workflow_id: self.workflow_id.clone(),
+            }))
+            .await?
+            .into_inner())
+    }
+
+    /// 等待 workflow 结束并返回结果
+    ///
+    /// workflow 失败、被取消或被强制结束时返回 [`ClientError::WorkflowFailed`]。
+    pub async fn result(&self) -> Result<Vec<u8>, ClientError> {
+        let response = self
+            .client
+            .client
+            .clone()
+            .await_result(proto::AwaitResultRequest {
+                workflow_id: self.workflow_id.clone(),
+                timeout_seconds: 0,
+            })
+            .await?
+            .into_inner();
+        match proto::State::try_from(response.state) {
+            Ok(proto::State::Completed) => Ok(response.result),
+            state => Err(ClientError::WorkflowFailed {
+                workflow_id: self.workflow_id.clone(),
+                state: state.unwrap_or(proto::State::Failed),
+                error: response.error,
+            }),
+        }
+    }
+
+    /// 取消 workflow，已经结束时返回 `false`
+    pub async fn cancel(&self) -> Result<bool, ClientError> {
+        let client = &self.client;
+        let response = client
+            .client
+            .clone()
+            .cancel_workflow(client.request(proto::CancelRequest {
+                workflow_id: self.workflow_id.clone(),
+            }))
+            .await;
+        match response {
+            Ok(response) => Ok(response.into_inner().success),
+            Err(status) if status.code() == tonic::Code::FailedPrecondition => Ok(false),
+            Err(status) => Err(status.into()),
+        }
+    }
+
+    pub async fn signal(
+        &self,
+        name: impl Into<String>,
+        payload: Vec<u8>,
+    ) -> Result<(), ClientError> {
+        let client = &self.client;
+        client
+            .client
+            .clone()
+            .signal_workflow(client.request(proto::SignalWorkflowRequest {
+                workflow_id: self.workflow_id.clone(),
+                signal_name: name.into(),
+                payload,
+            }))
+            .await?;
+        Ok(())
+    }
+
+    /// 从失败的 step 重新执行失败的 workflow，返回重试的 step 名称
+    pub async fn retry(&self) -> Result<Vec<String>, ClientError> {
+        let client = &self.client;
+        Ok(client
+            .client
+            .clone()
+            .retry_workflow(client.request(proto::RetryWorkflowRequest {
+                workflow_id: self.workflow_id.clone(),
+            }))
+            .await?
+            .into_inner()
+            .retried_steps)
+    }
+
+    /// 强制结束 workflow（TERMINATED），记录原因和请求者
+    pub async fn terminate(
+        &self,
+        reason: impl Into<String>,
+        requested_by: impl Into<String>,
+    ) -> Result<(), ClientError> {
+        let client = &self.client;
+        client
+            .client
+            .clone()
+            .terminate_workflow(client.request(proto::TerminateWorkflowRequest {
+                workflow_id: self.workflow_id.clone(),
+                reason: reason.into(),
+                requested_by: requested_by.into(),
+            }))
+            .await?;
+        Ok(())
+    }
+}
+
+/// worker 端客户端，由 [`AetherClient::worker`] 创建
+#[derive(Clone)]
+pub struct WorkerClient {
+    client: AetherClient,
+    registration: proto::RegisterRequest,
+    max_tasks: i32,
+    heartbeat_interval: Duration,
+    /// 携带最近一次注册返回的会话令牌的 `authorization` 值
+    session: Arc<RwLock<Option<AsciiMetadataValue>>>,
+}
+
+impl WorkerClient {
+    /// 每个 poll 流请求的 task 数，0 表示使用服务器的上限
+    pub fn with_max_tasks(mut self, max_tasks: i32) -> Self {
+        self.max_tasks = max_tasks;
+        self
+    }
+
+    pub fn with_heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.heartbeat_interval = interval;
+        self
+    }
+
+    pub fn worker_id(&self) -> &str {
+        &self.registration.worker_id
+    }
+
+    /// 注册 worker，之后的调用携带响应中的会话令牌
+    ///
+    /// 重新注册时发送当前的会话令牌，即使构建器中的令牌是引导令牌，
+    /// 仍在线的 worker 也能保留原来的 id。
+    pub async fn register(&self) -> Result<proto::RegisterResponse, ClientError> {
+        let response = self
+            .client
+            .worker
+            .clone()
+            .register(self.request(self.registration.clone()))
+            .await?
+            .into_inner();
+        let session = Some(response.session_token.as_str())
+            .filter(|token| !token.is_empty())
+            .and_then(|token| AsciiMetadataValue::try_from(format!("Bearer {}", token)).ok());
+        *self.session.write().unwrap() = session;
+        Ok(response)
+    }
+
+    /// 附加会话令牌，优先于构建器中的令牌
+    fn with_session<T>(&self, mut request: Request<T>) -> Request<T> {
+        if let Some(session) = self.session.read().unwrap().clone() {
+            request
+                .metadata_mut()
+                .insert(AUTHORIZATION_METADATA, session);
+        }
+        request
+    }
+
+    /// 携带会话令牌的 worker unary 调用
+    fn request<T>(&self, message: T) -> Request<T> {
+        self.with_session(self.client.request(message))
+    }
+
+    /// 分发给该 worker 的 task 流
+    ///
+    /// 注册 worker，保持 poll 流打开并发送 worker 心跳。连接断开后按退避重新打开，
+    /// worker 被移除后重新注册。只有服务器永久拒绝该 worker（如注册信息无效）时流才以错误结束；
+    /// 流被丢弃后停止轮询。
+    pub fn tasks(&self) -> ReceiverStream<Result<proto::Task, ClientError>> {
+        let (tx, rx) = mpsc::channel(1);
+        tokio::spawn(self.clone().poll_loop(tx));
+        ReceiverStream::new(rx)
+    }
+
+    async fn poll_loop(self, tx: mpsc::Sender<Result<proto::Task, ClientError>>) {
+        let mut backoff = RECONNECT_INITIAL_BACKOFF;
+        let mut registered = false;
+        loop {
+            let opened = async {
+                if !registered {
+                    self.register().await?;
+                    registered = true;
+                }
+                let request = proto::PollRequest {
+                    worker_id: self.worker_id().to_string(),
+                    max_tasks: self.max_tasks,
+                };
+                let request = self.with_session(Request::new(request));
+                Ok::<_, ClientError>(self.client.worker.clone().poll_tasks(request).await?)
+            };
+            let mut stream = match opened.await {
+                Ok(response) => response.into_inner(),
+                Err(e) => {
+                    if !self.recoverable(&e, &mut registered) {
+                        let _ = tx.send(Err(e)).await;
+                        return;
+                    }
+                    tracing::warn!(worker_id = %self.worker_id(), "task stream unavailable: {}", e);
+                    tokio::select! {
+                        _ = tokio::time::sleep(backoff) => {}
+                        _ = tx.closed() => return,
+                    }
+                    backoff = (backoff * 2).min(RECONNECT_MAX_BACKOFF);
+                    continue;
+                }
+            };
+            backoff = RECONNECT_INITIAL_BACKOFF;
+
+            let mut heartbeat = tokio::time::interval_at(
+                tokio::time::Instant::now() + self.heartbeat_interval,
+                self.heartbeat_interval,
+            );
+            loop {
+                tokio::select! {
+                    message = stream.message() => match message {
+                        Ok(Some(task)) => {
+                            if tx.send(Ok(task)).await.is_err() {
+                                return;
+                            }
+                        }
+                        // 服务器分发 max_tasks 个 task 后结束流，立即重新打开
+                        Ok(None) => break,
+                        Err(status) => {
+                            let e = ClientError::from(status);
+                            if !self.recoverable(&e, &mut registered) {
+                                let _ = tx.send(Err(e)).await;
+                                return;
+                            }
+                            tracing::warn!(worker_id = %self.worker_id(), "task stream dropped: {}", e);
+                            break;
+                        }
+                    },
+                    _ = heartbeat.tick() => {
+                        if !self.heartbeat().await {
+                            registered = false;
+                            break;
+                        }
+                    }
+                    _ = tx.closed() => return,
+                }
+            }
+        }
+    }
+
+    /// 出现 `e` 后轮询循环是否重试；服务器已不认识该 worker 或其会话令牌时清除 `registered`
+    fn recoverable(&self, e: &ClientError, registered: &mut bool) -> bool {
+        match e {
+            ClientError::Transport(_) => true,
+            ClientError::Status(status) => match status.code() {
+                Code::NotFound => {
+                    *registered = false;
+                    true
+                }
+                // 会话令牌随 worker 被移除而失效，用构建器中的令牌重新注册；
+                // 该令牌也被拒绝时不再重试
+                Code::Unauthenticated => {
+                    *registered = false;
+                    self.session.write().unwrap().take().is_some()
+                }
+                Code::Unavailable
+                | Code::Internal
+                | Code::Unknown
+                | Code::DeadlineExceeded
+                | Code::ResourceExhausted
+                | Code::Aborted
+                | Code::Cancelled => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// worker 心跳，服务器已移除该 worker 时返回 `false`
+    async fn heartbeat(&self) -> bool {
+        let request = proto::HeartbeatRequest {
+            task_id: String::new(),
+            worker_id: self.worker_id().to_string(),
+        };
+        match self
+            .client
+            .worker
+            .clone()
+            .heartbeat(self.request(request))
+            .await
+        {
+            Ok(response) => response.into_inner().ok,
+            // worker 被移除后会话令牌失效
+            Err(status) if status.code() == Code::Unauthenticated => false,
+            // 连接问题由 poll 流处理
+            Err(_) => true,
+        }
+    }
+
+    /// 记录 worker 开始执行 `task`
+    pub async fn step_started(&self, task: &proto::Task) -> Result<(), ClientError> {
+        let request = proto::ReportStepRequest {
+            workflow_id: task.workflow_id.clone(),
+            step_name: task.step_name.clone(),
+            status: proto::StepStatus::StepStarted as i32,
+            input: task.input.clone(),
+            ..Default::default()
+        };
+        self.client
+            .worker
+            .clone()
+            .report_step(self.request(request))
+            .await?;
+        Ok(())
+    }
+
+    /// 续期执行中 task 的租约，worker 已不持有租约时返回 `false`
+    pub async fn heartbeat_task(&self, task_id: &str) -> Result<bool, ClientError> {
+        let request = proto::HeartbeatRequest {
+            task_id: task_id.to_string(),
+            worker_id: self.worker_id().to_string(),
+        };
+        Ok(self
+            .client
+            .worker
+            .clone()
+            .heartbeat(self.request(request))
+            .await?
+            .into_inner()
+            .ok)
+    }
+
+    pub async fn complete(&self, task_id: &str, result: Vec<u8>) -> Result<(), ClientError> {
+        self.finish(task_id, result, String::new()).await
+    }
+
+    /// 上报 task 失败，服务器按重试策略重试
+    pub async fn fail(&self, task_id: &str, error: impl Into<String>) -> Result<(), ClientError> {
+        self.finish(task_id, Vec::new(), error.into()).await
+    }
+
+    /// 不执行，交还 task，不消耗尝试次数
+    ///
+    /// `requeue` 为 `false` 时，拒绝冷却结束前服务器不再把它分发给该 worker。
+    /// worker 已不持有该 task 时返回 `false`。
+    pub async fn reject(
+        &self,
+        task_id: &str,
+        reason: impl Into<String>,
+        requeue: bool,
+    ) -> Result<bool, ClientError> {
+        let request = proto::RejectTaskRequest {
+            task_id: task_id.to_string(),
+            worker_id: self.worker_id().to_string(),
+            reason: reason.into(),
+            requeue: Some(requeue),
+        };
+        Ok(self
+            .client
+            .worker
+            .clone()
+            .reject_task(self.request(request))
+            .await?
+            .into_inner()
+            .ok)
+    }
+
+    async fn finish(
+        &self,
+        task_id: &str,
+        result: Vec<u8>,
+        error: String,
+    ) -> Result<(), ClientError> {
+        let request = proto::CompleteStepRequest {
+            task_id: task_id.to_string(),
+            result,
+            error,
+            worker_id: self.worker_id().to_string(),
+            start_children: Vec::new(),
+        };
+        self.client
+            .worker
+            .clone()
+            .complete_step(self.request(request))
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::{StepDefinition, WorkflowDefinition};
+    use crate::grpc_auth::GrpcAuthConfig;
+    use crate::kernel::AetherKernel;
+    use crate::task::RetryPolicy;
+    use futures::StreamExt;
+
+    async fn kernel(steps: Vec<StepDefinition>) -> (AetherKernel, AetherClient) {
+        let kernel = AetherKernel::builder()
+            .http_addr("127.0.0.1:0")
+            .grpc_addr("127.0.0.1:0")
+            .build();
+        kernel
+            .scheduler()
+            .register_definition(WorkflowDefinition::new("order", steps))
+            .await
+            .unwrap();
+        let addrs = kernel.start().await.unwrap();
+        let client = AetherClient::connect(addrs.grpc.to_string()).await.unwrap();
+        (kernel, client)
+    }
+
+    fn registration() -> proto::RegisterRequest {
+        proto::RegisterRequest {
+            worker_id: "worker-1".to_string(),
+            service_name: "shop".to_string(),
+            provides: ["charge", "ship"]
+                .into_iter()
+                .map(|name| proto::ServiceResource {
+                    name: name.to_string(),
+                    r#type: proto::ResourceType::Step as i32,
+                    metadata: None,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_workflow_runs_through_client_and_worker() {
+        let (kernel, client) = kernel(vec![
+            StepDefinition::new("charge"),
+            StepDefinition::new("ship").depends_on(["charge"]),
+        ])
+        .await;
+        let handle = client
+            .start_workflow("order", b"{}".to_vec())
+            .await
+            .unwrap();
+        assert!(!handle.already_exists());
+
+        let worker = client.worker(registration()).with_max_tasks(1);
+        let mut tasks = worker.tasks();
+        for step in ["charge", "ship"] {
+            let task = tasks.next().await.unwrap().unwrap();
+            assert_eq!(task.step_name, step);
+            worker.step_started(&task).await.unwrap();
+            assert!(worker.heartbeat_task(&task.task_id).await.unwrap());
+            worker
+                .complete(&task.task_id, step.as_bytes().to_vec())
+                .await
+                .unwrap();
+        }
+        assert_eq!(handle.result().await.unwrap(), b"ship");
+
+        let page = client
+            .list_workflows(proto::ListWorkflowsRequest {
+                workflow_type: "order".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(page.workflows.len(), 1);
+        assert_eq!(page.workflows[0].workflow_id, handle.id());
+        // 已结束的 workflow 不能再取消
+        assert!(!handle.cancel().await.unwrap());
+        kernel.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failed_workflow_result_is_an_error() {
+        let no_retry = RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        };
+        let (kernel, client) =
+            kernel(vec![StepDefinition::new("charge").with_retry(no_retry)]).await;
+        let handle = client
+            .start_workflow("order", b"{}".to_vec())
+            .await
+            .unwrap();
+
+        let worker = client.worker(registration());
+        let task = worker.tasks().next().await.unwrap().unwrap();
+        worker.fail(&task.task_id, "card declined").await.unwrap();
+
+        match handle.result().await {
+            Err(ClientError::WorkflowFailed { state, error, .. }) => {
+                assert_eq!(state, proto::State::Failed);
+                assert!(error.contains("card declined"), "{}", error);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        kernel.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_definitions_pushed_over_grpc_gate_workflow_types() {
+        let (kernel, client) = kernel(vec![StepDefinition::new("charge")]).await;
+        let refund = WorkflowDefinition::new(
+            "refund",
+            vec![
+                StepDefinition::new("check"),
+                StepDefinition::new("pay")
+                    .depends_on(["check"])
+                    .with_target("billing", "pay")
+                    .external(),
+            ],
+        );
+        client.register_definition(&refund).await.unwrap();
+        // 相同定义重复注册会被接受
+        client.register_definition(&refund).await.unwrap();
+        assert_eq!(
+            kernel.scheduler().definition("refund").await.as_ref(),
+            Some(&refund)
+        );
+
+        let invalid = WorkflowDefinition::new(
+            "broken",
+            vec![
+                StepDefinition::new("a").depends_on(["missing"]),
+                StepDefinition::new("b").with_target("billing", "pay"),
+            ],
+        );
+        match client.register_definition(&invalid).await {
+            Err(ClientError::Status(status)) => {
+                assert_eq!(status.code(), Code::InvalidArgument);
+                assert!(
+                    status.message().contains("steps[0].dependsOn[0]")
+                        && status.message().contains("steps[1].targetResource"),
+                    "{}",
+                    status.message()
+                );
+            }
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+
+        // 没有定义的类型需要 dynamic 标记
+        assert!(client.start_workflow("ad-hoc", Vec::new()).await.is_err());
+        client
+            .start_workflow_with(proto::StartWorkflowRequest {
+                workflow_type: "ad-hoc".to_string(),
+                dynamic: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        kernel.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_grpc_auth_tokens_and_worker_sessions() {
+        let kernel = AetherKernel::builder()
+            .http_addr("127.0.0.1:0")
+            .grpc_addr("127.0.0.1:0")
+            .grpc_auth(
+                GrpcAuthConfig::new(vec!["admin-token".to_string()])
+                    .with_bootstrap_tokens(vec!["join-token".to_string()]),
+            )
+            .build();
+        kernel
+            .scheduler()
+            .register_definition(WorkflowDefinition::new(
+                "order",
+                vec![StepDefinition::new("charge")],
+            ))
+            .await
+            .unwrap();
+        let grpc = kernel.start().await.unwrap().grpc.to_string();
+        let connect = |token: Option<&str>| {
+            let mut builder = AetherClient::builder(grpc.clone());
+            if let Some(token) = token {
+                builder = builder.auth_token(token);
+            }
+            builder.connect()
+        };
+        let status = |result: Result<WorkflowHandle, ClientError>| match result {
+            Err(ClientError::Status(status)) => status.code(),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        };
+
+        for token in [None, Some("wrong"), Some("join-token")] {
+            let client = connect(token).await.unwrap();
+            let result = client.start_workflow("order", b"{}".to_vec()).await;
+            assert_eq!(status(result), Code::Unauthenticated);
+        }
+        let admin = connect(Some("admin-token")).await.unwrap();
+        let handle = admin.start_workflow("order", b"{}".to_vec()).await.unwrap();
+
+        // worker 用引导令牌加入，之后以各自的会话身份操作
+        let joiner = connect(Some("join-token")).await.unwrap();
+        let worker = joiner.worker(registration());
+        let task = worker.tasks().next().await.unwrap().unwrap();
+        let mut other = registration();
+        other.worker_id = "worker-2".to_string();
+        let session = joiner.worker(other).register().await.unwrap().session_token;
+
+        // 不能通过重新注册接管仍在线的 worker id
+        match joiner.worker(registration()).register().await {
+            Err(ClientError::Status(status)) => assert_eq!(status.code(), Code::AlreadyExists),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+        let mut takeover = Request::new(registration());
+        takeover.metadata_mut().insert(
+            AUTHORIZATION_METADATA,
+            format!("Bearer {}", session).parse().unwrap(),
+        );
+        let err = joiner
+            .worker_service()
+            .register(takeover)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+
+        let mut steal = Request::new(proto::CompleteStepRequest {
+            task_id: task.task_id.clone(),
+            worker_id: "worker-1".to_string(),
+            ..Default::default()
+        });
+        steal.metadata_mut().insert(
+            AUTHORIZATION_METADATA,
+            format!("Bearer {}", session).parse().unwrap(),
+        );
+        let err = joiner
+            .worker_service()
+            .complete_step(steal)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+
+        worker
+            .complete(&task.task_id, b"ok".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(handle.result().await.unwrap(), b"ok");
+        kernel.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_invalid_metadata_is_rejected_before_connecting() {
+        let result = AetherClient::builder("127.0.0.1:1")
+            .metadata("bad key", "value")
+            .connect()
+            .await;
+        assert!(matches!(result, Err(ClientError::InvalidMetadata(_))));
+    }
+}
@@ -0,0 +1,1005 @@
+//! 服务器配置
+//!
+//! 优先级：命令行参数 > 环境变量 (`AETHER_` 前缀) > 配置文件 > 默认值。
+//! 命令行参数由调用方在 [`ServerConfig::load`] 之后覆盖。
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::api::rate_limit::{RateLimit, RateLimitConfig};
+use crate::retention::RetentionPolicy;
+use crate::scheduler::SchedulerConfig;
+use crate::server_info::ServerLimits;
+use crate::task::RetryPolicy;
+
+/// 服务器配置
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub server: ServerSection,
+    pub persistence: PersistenceSection,
+    pub dashboard: DashboardSection,
+    pub scheduler: SchedulerSection,
+    pub retention: RetentionSection,
+    pub limits: LimitsSection,
+    pub rate_limit: RateLimitSection,
+}
+
+/// 监听地址与数据目录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerSection {
+    pub host: String,
+    pub port: u16,
+    pub grpc_port: u16,
+    pub db_path: PathBuf,
+    /// 允许跨域调用 REST API 的 origin，`*` 允许所有 origin，`:*` 结尾时允许该主机的任意端口
+    pub cors_origins: Vec<String>,
+}
+
+impl Default for ServerSection {
+    fn default() -> Self {
+        ServerSection {
+            host: "0.0.0.0".to_string(),
+            port: 7233,
+            grpc_port: 7234,
+            db_path: PathBuf::from("./data/aether.db"),
+            cors_origins: crate::api::cors::default_cors_origins(),
+        }
+    }
+}
+
+/// 持久化模式 (memory | snapshot | state-action-log | sqlite)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PersistenceSection {
+    pub mode: String,
+    /// 设置后，文件存储把超过 `blob_threshold_bytes` 的负载按内容哈希保存到该目录，只保存引用
+    pub blob_dir: Option<PathBuf>,
+    /// 按引用保存的负载大小阈值
+    pub blob_threshold_bytes: usize,
+}
+
+impl Default for PersistenceSection {
+    fn default() -> Self {
+        PersistenceSection {
+            mode: "memory".to_string(),
+            blob_dir: None,
+            blob_threshold_bytes: crate::persistence::blob::DEFAULT_BLOB_THRESHOLD_BYTES,
+        }
+    }
+}
+
+/// Dashboard WebSocket 服务
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DashboardSection {
+    pub enabled: bool,
+    pub port: u16,
+    /// 保留供回放的最近事件数量，为 0 时不保留
+    pub event_history: usize,
+    /// 内存中保留的已结束 workflow 执行记录数量上限
+    pub max_retained_executions: usize,
+    /// 已结束 workflow 执行记录的保留时长（秒），为 0 时只按数量淘汰
+    pub execution_ttl_secs: u64,
+}
+
+impl Default for DashboardSection {
+    fn default() -> Self {
+        DashboardSection {
+            enabled: true,
+            port: 7235,
+            event_history: crate::broadcaster::DEFAULT_EVENT_JOURNAL_CAPACITY,
+            max_retained_executions: crate::tracker::DEFAULT_MAX_RETAINED_EXECUTIONS,
+            execution_ttl_secs: 0,
+        }
+    }
+}
+
+/// 调度器参数
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SchedulerSection {
+    /// task 租约时长（秒），超时未心跳的 task 会被重新分发
+    pub task_timeout_secs: u64,
+    /// 上报过进度心跳的 step 连续错过该次数的心跳后按超时失败
+    pub max_missed_heartbeats: u32,
+    /// worker 存活超时（秒），超时未心跳或轮询的 worker 会被移除
+    pub worker_timeout_secs: u64,
+    /// 粘性超时（秒），粘性 worker 超时未领取就绪的 step 时改由其他 worker 领取
+    pub sticky_timeout_secs: u64,
+    /// 拒绝冷却期（秒），worker 拒绝 task 且不要求重新收到时，该时长内不再分发给它
+    pub reject_cooldown_secs: u64,
+    /// 停机时等待已分发 step 完成的宽限期（秒）
+    pub shutdown_grace_secs: u64,
+    /// 空闲的 task 流重新检查的间隔（毫秒），为 0 时取 worker 存活超时的 1/3
+    pub poll_interval_ms: u64,
+    /// 单次分发给一个 task 流的最多 task 数量
+    pub poll_tasks_limit: usize,
+    /// 事件广播通道的容量
+    pub broadcast_capacity: usize,
+    /// workflow 输入的最大字节数
+    pub max_input_bytes: usize,
+    /// step 输出的最大字节数
+    pub max_output_bytes: usize,
+    /// 按 workflow 类型限制同时执行的 task 数量
+    pub max_concurrent: BTreeMap<String, usize>,
+    /// 资源和定义都没有指定时使用的重试策略
+    pub retry: RetrySection,
+    /// cron 调度的追赶窗口（秒），停机期间错过的触发时间在该时长之内时补发
+    pub schedule_catchup_window_secs: u64,
+    /// step 结果缓存的有效期（秒）
+    pub step_cache_ttl_secs: u64,
+    /// 最多缓存的 step 结果数量，为 0 时不缓存
+    pub step_cache_max_entries: usize,
+}
+
+impl Default for SchedulerSection {
+    fn default() -> Self {
+        SchedulerSection {
+            task_timeout_secs: crate::scheduler::DEFAULT_TASK_TIMEOUT.as_secs(),
+            max_missed_heartbeats: crate::scheduler::DEFAULT_MAX_MISSED_HEARTBEATS,
+            worker_timeout_secs: crate::scheduler::DEFAULT_WORKER_TIMEOUT.as_secs(),
+            sticky_timeout_secs: crate::sticky::DEFAULT_STICKY_TIMEOUT.as_secs(),
+            reject_cooldown_secs: crate::scheduler::DEFAULT_REJECT_COOLDOWN.as_secs(),
+            shutdown_grace_secs: crate::shutdown::DEFAULT_SHUTDOWN_GRACE.as_secs(),
+            poll_interval_ms: 0,
+            poll_tasks_limit: crate::scheduler::DEFAULT_POLL_TASKS_LIMIT,
+            broadcast_capacity: crate::broadcaster::DEFAULT_BROADCAST_CAPACITY,
+            max_input_bytes: crate::scheduler::DEFAULT_MAX_INPUT_BYTES,
+            max_output_bytes: crate::scheduler::DEFAULT_MAX_OUTPUT_BYTES,
+            max_concurrent: BTreeMap::new(),
+            retry: RetrySection::default(),
+            schedule_catchup_window_secs: crate::schedule::DEFAULT_SCHEDULE_CATCHUP_WINDOW
+                .as_secs(),
+            step_cache_ttl_secs: crate::step_cache::DEFAULT_STEP_CACHE_TTL.as_secs(),
+            step_cache_max_entries: crate::step_cache::DEFAULT_STEP_CACHE_MAX_ENTRIES,
+        }
+    }
+}
+
+/// 默认重试策略
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetrySection {
+    /// 最多尝试次数（含第一次）
+    pub max_attempts: u32,
+    /// 第一次重试前的等待时间（毫秒）
+    pub initial_interval_ms: u64,
+    /// 每次重试等待时间的倍数
+    pub backoff_multiplier: f64,
+}
+
+impl Default for RetrySection {
+    fn default() -> Self {
+        let policy = RetryPolicy::default();
+        RetrySection {
+            max_attempts: policy.max_attempts,
+            initial_interval_ms: policy.initial_interval,
+            backoff_multiplier: policy.backoff_multiplier,
+        }
+    }
+}
+
+/// 终态 workflow 的保留策略，默认关闭
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionSection {
+    pub enabled: bool,
+    /// 清理任务的运行间隔（秒）
+    pub interval_secs: u64,
+    /// 默认保留时长（秒），未设置时只清理 `per_type` 中列出的类型
+    pub max_age_secs: Option<u64>,
+    /// 按 workflow 类型设置的保留时长（秒）
+    pub per_type: BTreeMap<String, u64>,
+}
+
+impl Default for RetentionSection {
+    fn default() -> Self {
+        RetentionSection {
+            enabled: false,
+            interval_secs: 3600,
+            max_age_secs: None,
+            per_type: BTreeMap::new(),
+        }
+    }
+}
+
+/// 请求限制，通过 `GetServerInfo` / `GET /info` 告知 SDK
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LimitsSection {
+    /// 单个请求体的最大字节数
+    pub max_payload_bytes: u64,
+    /// 单次批量操作的最大条目数
+    pub max_batch_size: u32,
+}
+
+impl Default for LimitsSection {
+    fn default() -> Self {
+        LimitsSection {
+            max_payload_bytes: crate::server_info::DEFAULT_MAX_PAYLOAD_BYTES,
+            max_batch_size: crate::server_info::DEFAULT_MAX_BATCH_SIZE,
+        }
+    }
+}
+
+/// REST API 限流，默认关闭；各项为 0 表示不限制
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitSection {
+    /// 所有请求合计的每秒请求数
+    pub global_per_sec: u32,
+    /// 全局允许的突发请求数，为 0 时等于每秒请求数
+    pub global_burst: u32,
+    /// 每个通过认证的 API key 的每秒请求数
+    pub per_key_per_sec: u32,
+    /// 每个 API key 允许的突发请求数，为 0 时等于每秒请求数
+    pub per_key_burst: u32,
+    /// 结果长轮询和导出同时处理的请求数
+    pub max_concurrent_expensive: usize,
+}
+
+/// 配置加载错误
+#[derive(Debug)]
+pub enum ConfigError {
+    /// 配置文件无法读取
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// 配置文件格式或类型错误
+    Parse { path: PathBuf, message: String },
+    /// 环境变量的值无法转换为目标类型
+    Env {
+        var: String,
+        value: String,
+        expected: &'static str,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => {
+                write!(
+                    f,
+                    "Failed to read config file {}: {}",
+                    path.display(),
+                    source
+                )
+            }
+            ConfigError::Parse { path, message } => {
+                write!(f, "Invalid config file {}: {}", path.display(), message)
+            }
+            ConfigError::Env {
+                var,
+                value,
+                expected,
+            } => write!(
+                f,
+                "Invalid value {:?} for environment variable {}: expected {}",
+                value, var, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// 命令行参数覆盖项，`None` 表示未指定
+#[derive(Debug, Clone, Default)]
+pub struct ServerOverrides {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub grpc_port: Option<u16>,
+    pub db_path: Option<PathBuf>,
+    pub cors_origins: Option<Vec<String>>,
+    pub persistence_mode: Option<String>,
+    pub dashboard_enabled: Option<bool>,
+    pub dashboard_port: Option<u16>,
+    pub task_timeout_secs: Option<u64>,
+    pub worker_timeout_secs: Option<u64>,
+    pub shutdown_grace_secs: Option<u64>,
+    pub poll_interval_ms: Option<u64>,
+    pub broadcast_capacity: Option<usize>,
+    /// 设置后启用保留任务，并以此作为默认保留时长（秒）
+    pub retention_max_age_secs: Option<u64>,
+}
+
+/// 加载结果：合并后的配置及未识别的配置项
+#[derive(Debug, Clone)]
+pub struct LoadedConfig {
+    pub config: ServerConfig,
+    pub unknown_keys: Vec<String>,
+}
+
+impl ServerConfig {
+    /// 加载配置：默认值 ← 配置文件 ← 环境变量
+    pub fn load(path: Option<&Path>) -> Result<LoadedConfig, ConfigError> {
+        Self::load_with_env(path, |var| std::env::var(var).ok())
+    }
+
+    /// 同 [`ServerConfig::load`]，环境变量由 `env` 提供
+    pub fn load_with_env(
+        path: Option<&Path>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<LoadedConfig, ConfigError> {
+        let (mut config, unknown_keys) = match path {
+            Some(path) => {
+                let content = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+                    path: path.to_path_buf(),
+                    source,
+                })?;
+                Self::from_toml(&content).map_err(|message| ConfigError::Parse {
+                    path: path.to_path_buf(),
+                    message,
+                })?
+            }
+            None => (ServerConfig::default(), vec![]),
+        };
+
+        config.apply_env(env)?;
+
+        Ok(LoadedConfig {
+            config,
+            unknown_keys,
+        })
+    }
+
+    /// 解析 TOML，返回配置和未识别的配置项路径
+    pub fn from_toml(content: &str) -> Result<(ServerConfig, Vec<String>), String> {
+        let mut unknown_keys = Vec::new();
+        let deserializer = toml::Deserializer::new(content);
+        let config = serde_ignored::deserialize(deserializer, |path| {
+            unknown_keys.push(path.to_string());
+        })
+        .map_err(|e| e.to_string())?;
+        Ok((config, unknown_keys))
+    }
+
+    /// 序列化为 TOML
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).expect("ServerConfig is always serializable")
+    }
+
+    /// 应用 `AETHER_` 前缀的环境变量
+    pub fn apply_env(&mut self, env: impl Fn(&str) -> Option<String>) -> Result<(), ConfigError> {
+        override_from_env(
+            &env,
+            "AETHER_SERVER_HOST",
+            "a string",
+            &mut self.server.host,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_SERVER_PORT",
+            "a port number",
+            &mut self.server.port,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_SERVER_GRPC_PORT",
+            "a port number",
+            &mut self.server.grpc_port,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_SERVER_DB_PATH",
+            "a path",
+            &mut self.server.db_path,
+        )?;
+        // 逗号分隔的列表
+        if let Some(origins) = env("AETHER_SERVER_CORS_ORIGINS") {
+            self.server.cors_origins = origins
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+        override_from_env(
+            &env,
+            "AETHER_PERSISTENCE_MODE",
+            "a string",
+            &mut self.persistence.mode,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_PERSISTENCE_BLOB_THRESHOLD_BYTES",
+            "a number of bytes",
+            &mut self.persistence.blob_threshold_bytes,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_DASHBOARD_ENABLED",
+            "true or false",
+            &mut self.dashboard.enabled,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_DASHBOARD_PORT",
+            "a port number",
+            &mut self.dashboard.port,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_DASHBOARD_EVENT_HISTORY",
+            "a number of events",
+            &mut self.dashboard.event_history,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_DASHBOARD_MAX_RETAINED_EXECUTIONS",
+            "a number of executions",
+            &mut self.dashboard.max_retained_executions,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_DASHBOARD_EXECUTION_TTL_SECS",
+            "a number of seconds",
+            &mut self.dashboard.execution_ttl_secs,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_SCHEDULER_TASK_TIMEOUT_SECS",
+            "a number of seconds",
+            &mut self.scheduler.task_timeout_secs,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_SCHEDULER_MAX_MISSED_HEARTBEATS",
+            "a positive number",
+            &mut self.scheduler.max_missed_heartbeats,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_SCHEDULER_WORKER_TIMEOUT_SECS",
+            "a number of seconds",
+            &mut self.scheduler.worker_timeout_secs,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_SCHEDULER_STICKY_TIMEOUT_SECS",
+            "a number of seconds",
+            &mut self.scheduler.sticky_timeout_secs,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_SCHEDULER_REJECT_COOLDOWN_SECS",
+            "a number of seconds",
+            &mut self.scheduler.reject_cooldown_secs,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_SCHEDULER_SHUTDOWN_GRACE_SECS",
+            "a number of seconds",
+            &mut self.scheduler.shutdown_grace_secs,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_SCHEDULER_POLL_INTERVAL_MS",
+            "a number of milliseconds",
+            &mut self.scheduler.poll_interval_ms,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_SCHEDULER_POLL_TASKS_LIMIT",
+            "a positive number",
+            &mut self.scheduler.poll_tasks_limit,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_SCHEDULER_BROADCAST_CAPACITY",
+            "a positive number",
+            &mut self.scheduler.broadcast_capacity,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_SCHEDULER_MAX_INPUT_BYTES",
+            "a number of bytes",
+            &mut self.scheduler.max_input_bytes,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_SCHEDULER_MAX_OUTPUT_BYTES",
+            "a number of bytes",
+            &mut self.scheduler.max_output_bytes,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_SCHEDULER_SCHEDULE_CATCHUP_WINDOW_SECS",
+            "a number of seconds",
+            &mut self.scheduler.schedule_catchup_window_secs,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_SCHEDULER_STEP_CACHE_TTL_SECS",
+            "a number of seconds",
+            &mut self.scheduler.step_cache_ttl_secs,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_SCHEDULER_STEP_CACHE_MAX_ENTRIES",
+            "a number of entries",
+            &mut self.scheduler.step_cache_max_entries,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_RETENTION_ENABLED",
+            "true or false",
+            &mut self.retention.enabled,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_RETENTION_INTERVAL_SECS",
+            "a number of seconds",
+            &mut self.retention.interval_secs,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_LIMITS_MAX_PAYLOAD_BYTES",
+            "a number of bytes",
+            &mut self.limits.max_payload_bytes,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_LIMITS_MAX_BATCH_SIZE",
+            "a positive number",
+            &mut self.limits.max_batch_size,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_RATE_LIMIT_GLOBAL_PER_SEC",
+            "a number of requests",
+            &mut self.rate_limit.global_per_sec,
+        )?;
+        override_from_env(
+            &env,
+            "AETHER_RATE_LIMIT_PER_KEY_PER_SEC",
+            "a number of requests",
+            &mut self.rate_limit.per_key_per_sec,
+        )?;
+        Ok(())
+    }
+
+    /// 应用命令行参数（最高优先级）
+    pub fn apply_overrides(&mut self, overrides: ServerOverrides) {
+        fn set<T>(target: &mut T, value: Option<T>) {
+            if let Some(value) = value {
+                *target = value;
+            }
+        }
+
+        set(&mut self.server.host, overrides.host);
+        set(&mut self.server.port, overrides.port);
+        set(&mut self.server.grpc_port, overrides.grpc_port);
+        set(&mut self.server.db_path, overrides.db_path);
+        set(&mut self.server.cors_origins, overrides.cors_origins);
+        set(&mut self.persistence.mode, overrides.persistence_mode);
+        set(&mut self.dashboard.enabled, overrides.dashboard_enabled);
+        set(&mut self.dashboard.port, overrides.dashboard_port);
+        set(
+            &mut self.scheduler.task_timeout_secs,
+            overrides.task_timeout_secs,
+        );
+        set(
+            &mut self.scheduler.worker_timeout_secs,
+            overrides.worker_timeout_secs,
+        );
+        set(
+            &mut self.scheduler.shutdown_grace_secs,
+            overrides.shutdown_grace_secs,
+        );
+        set(
+            &mut self.scheduler.poll_interval_ms,
+            overrides.poll_interval_ms,
+        );
+        set(
+            &mut self.scheduler.broadcast_capacity,
+            overrides.broadcast_capacity,
+        );
+        if let Some(max_age_secs) = overrides.retention_max_age_secs {
+            self.retention.enabled = true;
+            self.retention.max_age_secs = Some(max_age_secs);
+        }
+    }
+
+    /// REST API 监听地址
+    pub fn rest_addr(&self) -> String {
+        format!("{}:{}", self.server.host, self.server.port)
+    }
+
+    /// gRPC 监听地址
+    pub fn grpc_addr(&self) -> String {
+        format!("{}:{}", self.server.host, self.server.grpc_port)
+    }
+
+    /// Dashboard WebSocket 监听地址
+    pub fn dashboard_addr(&self) -> String {
+        format!("{}:{}", self.server.host, self.dashboard.port)
+    }
+
+    /// task 租约时长
+    pub fn task_timeout(&self) -> Duration {
+        Duration::from_secs(self.scheduler.task_timeout_secs)
+    }
+
+    /// worker 存活超时
+    pub fn worker_timeout(&self) -> Duration {
+        Duration::from_secs(self.scheduler.worker_timeout_secs)
+    }
+
+    /// 粘性超时
+    pub fn sticky_timeout(&self) -> Duration {
+        Duration::from_secs(self.scheduler.sticky_timeout_secs)
+    }
+
+    /// 拒绝冷却期
+    pub fn reject_cooldown(&self) -> Duration {
+        Duration::from_secs(self.scheduler.reject_cooldown_secs)
+    }
+
+    /// 停机宽限期
+    pub fn shutdown_grace(&self) -> Duration {
+        Duration::from_secs(self.scheduler.shutdown_grace_secs)
+    }
+
+    /// 事件日志保留的最近事件数量
+    pub fn event_journal_capacity(&self) -> usize {
+        self.dashboard.event_history
+    }
+
+    /// 已结束 workflow 执行记录的保留时长，未配置时返回 `None`
+    pub fn execution_ttl(&self) -> Option<Duration> {
+        match self.dashboard.execution_ttl_secs {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        }
+    }
+
+    /// 请求限制
+    pub fn limits(&self) -> ServerLimits {
+        ServerLimits {
+            max_payload_bytes: self.limits.max_payload_bytes,
+            max_batch_size: self.limits.max_batch_size,
+        }
+    }
+
+    /// 调度器配置
+    pub fn scheduler_config(&self) -> SchedulerConfig {
+        let retry = &self.scheduler.retry;
+        SchedulerConfig {
+            task_timeout: self.task_timeout(),
+            max_missed_heartbeats: self.scheduler.max_missed_heartbeats,
+            worker_timeout: self.worker_timeout(),
+            sticky_timeout: self.sticky_timeout(),
+            reject_cooldown: self.reject_cooldown(),
+            shutdown_grace: self.shutdown_grace(),
+            poll_interval: match self.scheduler.poll_interval_ms {
+                0 => None,
+                ms => Some(Duration::from_millis(ms)),
+            },
+            poll_tasks_limit: self.scheduler.poll_tasks_limit,
+            broadcast_capacity: self.scheduler.broadcast_capacity,
+            event_journal_capacity: self.event_journal_capacity(),
+            default_retry: RetryPolicy {
+                max_attempts: retry.max_attempts,
+                initial_interval: retry.initial_interval_ms,
+                backoff_multiplier: retry.backoff_multiplier,
+            },
+            max_input_bytes: self.scheduler.max_input_bytes,
+            max_output_bytes: self.scheduler.max_output_bytes,
+            retention: self.retention_policy(),
+            schedule_catchup_window: Duration::from_secs(
+                self.scheduler.schedule_catchup_window_secs,
+            ),
+            rate_limits: self.rate_limits(),
+            cors_origins: self.server.cors_origins.clone(),
+            step_cache_ttl: Duration::from_secs(self.scheduler.step_cache_ttl_secs),
+            step_cache_max_entries: self.scheduler.step_cache_max_entries,
+        }
+    }
+
+    /// REST API 限流配置
+    pub fn rate_limits(&self) -> RateLimitConfig {
+        let limit = |per_sec, burst| (per_sec > 0).then(|| RateLimit::new(per_sec, burst));
+        let section = &self.rate_limit;
+        RateLimitConfig {
+            global: limit(section.global_per_sec, section.global_burst),
+            per_api_key: limit(section.per_key_per_sec, section.per_key_burst),
+            max_concurrent_expensive: (section.max_concurrent_expensive > 0)
+                .then_some(section.max_concurrent_expensive),
+        }
+    }
+
+    /// 保留策略，未启用时返回 `None`
+    pub fn retention_policy(&self) -> Option<RetentionPolicy> {
+        if !self.retention.enabled {
+            return None;
+        }
+
+        Some(RetentionPolicy {
+            interval: Duration::from_secs(self.retention.interval_secs),
+            max_age: self.retention.max_age_secs.map(Duration::from_secs),
+            per_type: self
+                .retention
+                .per_type
+                .iter()
+                .map(|(wf_type, secs)| (wf_type.clone(), Duration::from_secs(*secs)))
+                .collect(),
+        })
+    }
+}
+
+fn override_from_env<T: FromStr>(
+    env: &impl Fn(&str) -> Option<String>,
+    var: &str,
+    expected: &'static str,
+    target: &mut T,
+) -> Result<(), ConfigError> {
+    if let Some(value) = env(var) {
+        *target = value.parse().map_err(|_| ConfigError::Env {
+            var: var.to_string(),
+            value,
+            expected,
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::io::Write;
+
+    fn env_of(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |var| vars.get(var).cloned()
+    }
+
+    fn write_config(content: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_defaults() {
+        let loaded = ServerConfig::load_with_env(None, env_of(&[])).unwrap();
+        assert_eq!(loaded.config, ServerConfig::default());
+        assert_eq!(loaded.config.rest_addr(), "0.0.0.0:7233");
+        assert_eq!(loaded.config.grpc_addr(), "0.0.0.0:7234");
+    }
+
+    #[test]
+    fn test_file_overrides_defaults() {
+        let file = write_config("[server]\nport = 8080\n\n[persistence]\nmode = \"snapshot\"\n");
+        let loaded = ServerConfig::load_with_env(Some(file.path()), env_of(&[])).unwrap();
+
+        assert_eq!(loaded.config.server.port, 8080);
+        assert_eq!(loaded.config.server.grpc_port, 7234);
+        assert_eq!(loaded.config.persistence.mode, "snapshot");
+        assert!(loaded.unknown_keys.is_empty());
+    }
+
+    #[test]
+    fn test_env_overrides_file() {
+        let file = write_config("[server]\nport = 8080\ngrpc_port = 8081\n");
+        let env = env_of(&[
+            ("AETHER_SERVER_PORT", "9090"),
+            ("AETHER_DASHBOARD_ENABLED", "false"),
+            ("AETHER_SCHEDULER_WORKER_TIMEOUT_SECS", "30"),
+            ("AETHER_SCHEDULER_SHUTDOWN_GRACE_SECS", "5"),
+            ("AETHER_SCHEDULER_STICKY_TIMEOUT_SECS", "3"),
+        ]);
+        let loaded = ServerConfig::load_with_env(Some(file.path()), env).unwrap();
+
+        assert_eq!(loaded.config.server.port, 9090);
+        assert_eq!(loaded.config.server.grpc_port, 8081);
+        assert!(!loaded.config.dashboard.enabled);
+        assert_eq!(loaded.config.worker_timeout(), Duration::from_secs(30));
+        assert_eq!(loaded.config.shutdown_grace(), Duration::from_secs(5));
+        assert_eq!(loaded.config.sticky_timeout(), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_cli_overrides_env_and_file() {
+        let file = write_config("[server]\nport = 8080\ngrpc_port = 8081\nhost = \"127.0.0.1\"\n");
+        let env = env_of(&[
+            ("AETHER_SERVER_PORT", "9090"),
+            ("AETHER_SERVER_GRPC_PORT", "9091"),
+        ]);
+        let mut config = ServerConfig::load_with_env(Some(file.path()), env)
+            .unwrap()
+            .config;
+
+        config.apply_overrides(ServerOverrides {
+            port: Some(10000),
+            ..Default::default()
+        });
+
+        assert_eq!(config.server.port, 10000); // 命令行
+        assert_eq!(config.server.grpc_port, 9091); // 环境变量
+        assert_eq!(config.server.host, "127.0.0.1"); // 配置文件
+        assert_eq!(config.dashboard.port, 7235); // 默认值
+    }
+
+    #[test]
+    fn test_unknown_keys_reported() {
+        let (_, unknown) =
+            ServerConfig::from_toml("[server]\nprot = 1\n\n[metrics]\nenabled = true\n").unwrap();
+        assert_eq!(unknown, vec!["server.prot", "metrics"]);
+    }
+
+    #[test]
+    fn test_type_mismatch_in_file() {
+        let file = write_config("[server]\nport = \"eighty\"\n");
+        let err = ServerConfig::load_with_env(Some(file.path()), env_of(&[])).unwrap_err();
+        let message = err.to_string();
+
+        assert!(matches!(err, ConfigError::Parse { .. }));
+        assert!(message.contains("line 2"), "{}", message);
+        assert!(message.contains("expected u16"), "{}", message);
+    }
+
+    #[test]
+    fn test_type_mismatch_in_env() {
+        let err = ServerConfig::load_with_env(None, env_of(&[("AETHER_SERVER_PORT", "http")]))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid value \"http\" for environment variable AETHER_SERVER_PORT: expected a port number"
+        );
+    }
+
+    #[test]
+    fn test_concurrency_limits() {
+        let file = write_config("[scheduler.max_concurrent]\nbulk-import = 4\n");
+        let loaded = ServerConfig::load_with_env(Some(file.path()), env_of(&[])).unwrap();
+        assert!(loaded.unknown_keys.is_empty());
+        assert_eq!(loaded.config.scheduler.max_concurrent["bulk-import"], 4);
+    }
+
+    #[test]
+    fn test_payload_limits_and_blob_dir() {
+        let file = write_config(
+            "[scheduler]\nmax_input_bytes = 1024\n\n[persistence]\nmode = \"snapshot\"\nblob_dir = \"/tmp/blobs\"\n",
+        );
+        let env = env_of(&[("AETHER_SCHEDULER_MAX_OUTPUT_BYTES", "2048")]);
+        let loaded = ServerConfig::load_with_env(Some(file.path()), env).unwrap();
+        assert!(loaded.unknown_keys.is_empty());
+
+        let scheduler = loaded.config.scheduler_config();
+        assert_eq!(scheduler.max_input_bytes, 1024);
+        assert_eq!(scheduler.max_output_bytes, 2048);
+        assert_eq!(
+            loaded.config.persistence.blob_dir.as_deref(),
+            Some(Path::new("/tmp/blobs"))
+        );
+        assert_eq!(
+            ServerConfig::default().scheduler_config(),
+            SchedulerConfig::default()
+        );
+    }
+
+    #[test]
+    fn test_scheduler_config_from_file() {
+        let file = write_config(
+            "[scheduler]\ntask_timeout_secs = 20\npoll_interval_ms = 500\npoll_tasks_limit = 4\nstep_cache_ttl_secs = 600\n\n[scheduler.retry]\nmax_attempts = 5\n\n[dashboard]\nevent_history = 50\n\n[retention]\nenabled = true\n",
+        );
+        let env = env_of(&[
+            ("AETHER_SCHEDULER_BROADCAST_CAPACITY", "64"),
+            ("AETHER_SCHEDULER_STEP_CACHE_MAX_ENTRIES", "500"),
+        ]);
+        let mut config = ServerConfig::load_with_env(Some(file.path()), env)
+            .unwrap()
+            .config;
+        config.apply_overrides(ServerOverrides {
+            poll_interval_ms: Some(200),
+            ..Default::default()
+        });
+
+        let scheduler = config.scheduler_config();
+        assert_eq!(scheduler.task_timeout, Duration::from_secs(20));
+        assert_eq!(scheduler.poll_interval, Some(Duration::from_millis(200)));
+        assert_eq!(scheduler.poll_tasks_limit, 4);
+        assert_eq!(scheduler.broadcast_capacity, 64);
+        assert_eq!(scheduler.event_journal_capacity, 50);
+        assert_eq!(scheduler.default_retry.max_attempts, 5);
+        assert_eq!(scheduler.default_retry.initial_interval, 1000);
+        assert!(scheduler.retention.is_some());
+        assert_eq!(scheduler.step_cache_ttl, Duration::from_secs(600));
+        assert_eq!(scheduler.step_cache_max_entries, 500);
+    }
+
+    #[test]
+    fn test_rate_limits() {
+        assert!(!ServerConfig::default().rate_limits().is_enabled());
+
+        let file = write_config(
+            "[rate_limit]\nglobal_per_sec = 100\nper_key_per_sec = 10\nper_key_burst = 20\nmax_concurrent_expensive = 8\n",
+        );
+        let env = env_of(&[("AETHER_RATE_LIMIT_GLOBAL_PER_SEC", "50")]);
+        let loaded = ServerConfig::load_with_env(Some(file.path()), env).unwrap();
+        assert!(loaded.unknown_keys.is_empty());
+        let limits = loaded.config.scheduler_config().rate_limits;
+        assert_eq!(limits.global, Some(RateLimit::new(50, 50)));
+        assert_eq!(limits.per_api_key, Some(RateLimit::new(10, 20)));
+        assert_eq!(limits.max_concurrent_expensive, Some(8));
+    }
+
+    #[test]
+    fn test_cors_origins() {
+        let defaults = ServerConfig::default().scheduler_config().cors_origins;
+        assert_eq!(defaults, crate::api::cors::default_cors_origins());
+
+        let file = write_config("[server]\ncors_origins = [\"https://dash.example.com\"]\n");
+        let loaded = ServerConfig::load_with_env(Some(file.path()), env_of(&[])).unwrap();
+        assert!(loaded.unknown_keys.is_empty());
+        assert_eq!(
+            loaded.config.server.cors_origins,
+            vec!["https://dash.example.com"]
+        );
+
+        let env = env_of(&[(
+            "AETHER_SERVER_CORS_ORIGINS",
+            "https://a.test, https://b.test",
+        )]);
+        let mut config = ServerConfig::load_with_env(Some(file.path()), env)
+            .unwrap()
+            .config;
+        assert_eq!(
+            config.server.cors_origins,
+            vec!["https://a.test", "https://b.test"]
+        );
+
+        config.apply_overrides(ServerOverrides {
+            cors_origins: Some(vec!["*".to_string()]),
+            ..Default::default()
+        });
+        assert_eq!(config.scheduler_config().cors_origins, vec!["*"]);
+    }
+
+    #[test]
+    fn test_retention_policy() {
+        let file = write_config(
+            "[retention]\nenabled = true\nmax_age_secs = 600\n\n[retention.per_type]\nreport = 60\n",
+        );
+        let loaded = ServerConfig::load_with_env(Some(file.path()), env_of(&[])).unwrap();
+        assert!(loaded.unknown_keys.is_empty());
+
+        let policy = loaded.config.retention_policy().unwrap();
+        assert_eq!(policy.interval, Duration::from_secs(3600));
+        assert_eq!(policy.max_age_for("report"), Some(Duration::from_secs(60)));
+        assert_eq!(policy.max_age_for("order"), Some(Duration::from_secs(600)));
+
+        assert!(ServerConfig::default().retention_policy().is_none());
+
+        let mut config = ServerConfig::default();
+        config.apply_overrides(ServerOverrides {
+            retention_max_age_secs: Some(7200),
+            ..Default::default()
+        });
+        let policy = config.retention_policy().unwrap();
+        assert_eq!(policy.max_age, Some(Duration::from_secs(7200)));
+        assert_eq!(policy.interval, Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn test_toml_roundtrip() {
+        let config = ServerConfig::default();
+        let (parsed, unknown) = ServerConfig::from_toml(&config.to_toml()).unwrap();
+        assert_eq!(parsed, config);
+        assert!(unknown.is_empty());
+    }
+}
@@ -14,11 +14,14 @@ use rust_embed::Embed;
 pub struct DashboardAssets;
 
 impl DashboardAssets {
-    /// 获取文件内容，支持 SPA fallback
+    /// 获取文件内容及实际返回的文件路径，支持 SPA fallback
     ///
     /// 如果请求的路径不存在，返回 index.html（用于前端路由）
-    pub fn get_or_index(path: &str) -> Option<rust_embed::EmbeddedFile> {
-        Self::get(path).or_else(|| Self::get("index.html"))
+    pub fn get_or_index(path: &str) -> Option<(&str, rust_embed::EmbeddedFile)> {
+        match Self::get(path) {
+            Some(file) => Some((path, file)),
+            None => Self::get("index.html").map(|file| ("index.html", file)),
+        }
     }
 }
 
@@ -30,4 +33,10 @@ mod tests {
     fn test_index_html_exists() {
         assert!(DashboardAssets::get("index.html").is_some());
     }
+
+    #[test]
+    fn test_unknown_path_falls_back_to_index() {
+        let (path, _) = DashboardAssets::get_or_index("workflows/wf-1").unwrap();
+        assert_eq!(path, "index.html");
+    }
 }
@@ -3,29 +3,38 @@
 //! 提供 HTTP 静态文件服务和 WebSocket 实时事件推送。
 //! 使用 axum 框架，在单个端口同时处理 HTTP 和 WebSocket 请求。
 
+use std::collections::HashSet;
+use std::future::Future;
 use std::sync::Arc;
 
 use axum::{
     extract::{
-        ws::{Message, WebSocket},
+        ws::{close_code, CloseFrame, Message, WebSocket},
         State, WebSocketUpgrade,
     },
     http::{header, StatusCode, Uri},
-    response::{Html, IntoResponse, Response},
+    response::{IntoResponse, Response},
     routing::get,
-    Router,
+    Json, Router,
 };
 use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, watch};
 
-use crate::broadcaster::WorkflowEvent;
+use crate::broadcaster::{EventBroadcaster, EventPayload, EventType, WorkflowEvent};
 use crate::dashboard_assets::DashboardAssets;
-use crate::tracker::WorkflowTracker;
+use crate::graph::{GraphNode, GraphSource, WorkflowGraph};
+use crate::payload::Payload;
+use crate::stats::SystemStats;
+use crate::time;
+use crate::tracker::{StepExecutionStatus, StepProgress, WorkflowTracker};
 
 // ========== DTO 定义 ==========
 
 /// Dashboard HTTP API 请求
+///
+/// 请求对象可以额外带一个字符串字段 `request_id`，对应的响应会原样带回，
+/// 便于客户端在同一个 WebSocket 上区分响应。
 #[derive(Debug, Deserialize, Serialize)]
 pub enum ApiRequest {
     /// 获取所有正在运行的 workflow
@@ -36,6 +45,29 @@ pub enum ApiRequest {
     GetWorkflow { workflow_id: String },
     /// 获取指定 workflow 的执行历史
     GetWorkflowHistory { workflow_id: String },
+    /// 获取指定 workflow 的 step 依赖图
+    ///
+    /// 之后该 workflow 的 step 状态变化时，连接会收到 `GraphNodeUpdated`，直到 workflow 结束。
+    GetWorkflowGraph { workflow_id: String },
+    /// 回放事件日志中指定 workflow 的事件，可只取某个时间（秒，含）之后的事件
+    GetEventHistory {
+        workflow_id: String,
+        #[serde(default)]
+        since_timestamp: Option<u64>,
+    },
+    /// 只接收匹配条件的实时事件，空列表表示不限制该项
+    Subscribe {
+        #[serde(default)]
+        workflow_ids: Vec<String>,
+        #[serde(default)]
+        workflow_types: Vec<String>,
+        #[serde(default)]
+        event_types: Vec<String>,
+    },
+    /// 清除过滤条件，重新接收全部实时事件
+    Unsubscribe,
+    /// 获取系统概览统计，开销很小，可以每隔几秒轮询
+    GetStats,
 }
 
 /// Dashboard HTTP API 响应
@@ -47,8 +79,84 @@ pub enum ApiResponse {
     WorkflowDetail { detail: WorkflowDetailDto },
     /// Workflow 历史响应
     WorkflowHistory { history: Vec<StepHistoryDto> },
+    /// Workflow 依赖图响应
+    WorkflowGraph { graph: WorkflowGraph },
+    /// 请求过依赖图的 workflow 中某个 step 的状态变化，不带 `request_id`
+    GraphNodeUpdated {
+        workflow_id: String,
+        node: GraphNode,
+    },
+    /// 事件回放响应，按广播顺序排列
+    EventHistory {
+        workflow_id: String,
+        events: Vec<WorkflowEvent>,
+    },
+    /// 订阅确认，包含当前生效的过滤条件
+    Subscribed { filter: EventFilter },
+    /// 系统概览统计
+    Stats { stats: SystemStats },
     /// 错误响应
-    Error { message: String },
+    Error { code: ApiErrorCode, message: String },
+}
+
+impl ApiResponse {
+    fn error(code: ApiErrorCode, message: impl Into<String>) -> Self {
+        ApiResponse::Error {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Dashboard API 错误码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ApiErrorCode {
+    /// 请求不是合法的 JSON 或不是已知的请求
+    InvalidRequest,
+    /// 请求的 workflow 不存在
+    NotFound,
+    /// 服务器处理请求时出错
+    Internal,
+}
+
+/// 发回客户端的响应，带回请求中的 `request_id`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ApiResponseEnvelope {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(flatten)]
+    pub response: ApiResponse,
+}
+
+/// 单个 WebSocket 连接的状态
+#[derive(Debug, Default)]
+struct Connection {
+    filter: EventFilter,
+    /// 请求过依赖图、需要推送 `GraphNodeUpdated` 的 workflow
+    graph_workflows: HashSet<String>,
+}
+
+/// Dashboard WebSocket 连接的实时事件过滤条件
+///
+/// 各项之间是“且”的关系，同一项内是“或”的关系；空列表表示不限制该项。
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct EventFilter {
+    pub workflow_ids: Vec<String>,
+    pub workflow_types: Vec<String>,
+    pub event_types: Vec<String>,
+}
+
+impl EventFilter {
+    /// 事件是否满足过滤条件
+    pub fn matches(&self, event: &WorkflowEvent) -> bool {
+        fn allows(allowed: &[String], value: &str) -> bool {
+            allowed.is_empty() || allowed.iter().any(|a| a == value)
+        }
+        allows(&self.workflow_ids, &event.workflow_id)
+            && allows(&self.workflow_types, &event.workflow_type)
+            && allows(&self.event_types, event.event_type.as_str())
+    }
 }
 
 /// Workflow 简要信息 DTO
@@ -70,6 +178,20 @@ pub struct WorkflowDetailDto {
     pub step_executions: Vec<StepExecutionDto>,
     pub started_at: u64,
     pub completed_at: Option<u64>,
+    #[serde(default)]
+    pub pending_timers: Vec<PendingTimerDto>,
+    #[serde(default)]
+    pub parent_workflow_id: Option<String>,
+    #[serde(default)]
+    pub child_workflow_ids: Vec<String>,
+}
+
+/// 等待中的定时器 DTO，供 dashboard 显示倒计时
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PendingTimerDto {
+    pub step_name: String,
+    pub fire_at: u64,
+    pub remaining_ms: u64,
 }
 
 /// Step 执行信息 DTO
@@ -80,6 +202,22 @@ pub struct StepExecutionDto {
     pub started_at: Option<u64>,
     pub completed_at: Option<u64>,
     pub attempt: u32,
+    pub progress: Option<StepProgress>,
+    /// 最近一次进度心跳的时间
+    #[serde(default)]
+    pub last_heartbeat_at: Option<u64>,
+    /// JSON 内容解码为 JSON 值，其他内容为 base64，见 [`Payload`]
+    #[serde(default)]
+    pub input: Payload,
+    #[serde(default)]
+    pub output: Option<Payload>,
+}
+
+/// Dashboard 前端配置，由 `/dashboard/config.json` 返回，前端据此连接 WebSocket
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DashboardConfigDto {
+    pub ws_port: u16,
+    pub ws_path: String,
 }
 
 /// Step 历史记录 DTO
@@ -97,11 +235,23 @@ pub struct StepHistoryDto {
 #[derive(Clone)]
 pub struct AppState {
     pub tracker: WorkflowTracker,
-    pub broadcaster: broadcast::Sender<WorkflowEvent>,
+    pub broadcaster: EventBroadcaster,
+    /// 生成 workflow 依赖图
+    pub graphs: Arc<dyn GraphSource>,
+    /// WebSocket 实际监听的端口
+    pub ws_port: u16,
+    /// 服务器停止时发出通知，WebSocket 连接收到后发送 Close 帧并断开
+    pub shutdown: watch::Receiver<()>,
 }
 
 // ========== 路由处理 ==========
 
+/// WebSocket 路径
+const WS_PATH: &str = "/ws";
+
+/// 带内容哈希的构建产物目录，文件名变化即内容变化，可以长期缓存
+const HASHED_ASSETS_PREFIX: &str = "assets/";
+
 /// 静态文件处理器
 ///
 /// 处理所有非 WebSocket 的 HTTP 请求，返回嵌入的静态文件。
@@ -110,24 +260,42 @@ async fn static_handler(uri: Uri) -> Response {
     let path = uri.path().trim_start_matches('/');
     let path = if path.is_empty() { "index.html" } else { path };
 
-    match DashboardAssets::get(path) {
-        Some(content) => {
-            let mime = mime_guess::from_path(path).first_or_octet_stream();
-            (
-                StatusCode::OK,
-                [(header::CONTENT_TYPE, mime.as_ref())],
-                content.data.into_owned(),
-            )
-                .into_response()
-        }
-        None => {
-            // SPA fallback: 返回 index.html
-            match DashboardAssets::get("index.html") {
-                Some(content) => Html(content.data.into_owned()).into_response(),
-                None => (StatusCode::NOT_FOUND, "Dashboard not found").into_response(),
-            }
-        }
-    }
+    let Some((served, content)) = DashboardAssets::get_or_index(path) else {
+        return (StatusCode::NOT_FOUND, "Dashboard not found").into_response();
+    };
+    let mime = mime_guess::from_path(served).first_or_octet_stream();
+    let cache_control = if served.starts_with(HASHED_ASSETS_PREFIX) {
+        "public, max-age=31536000, immutable"
+    } else {
+        // index.html 引用的资源随构建变化，每次都要重新验证
+        "no-cache"
+    };
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, mime.as_ref()),
+            (header::CACHE_CONTROL, cache_control),
+        ],
+        content.data.into_owned(),
+    )
+        .into_response()
+}
+
+/// 前端配置处理器，返回 WebSocket 的端口和路径
+async fn config_handler(State(state): State<Arc<AppState>>) -> Json<DashboardConfigDto> {
+    Json(DashboardConfigDto {
+        ws_port: state.ws_port,
+        ws_path: WS_PATH.to_string(),
+    })
+}
+
+/// Dashboard 路由：WebSocket、前端配置和静态文件
+fn router(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route(WS_PATH, get(ws_handler))
+        .route("/dashboard/config.json", get(config_handler))
+        .fallback(static_handler)
+        .with_state(state)
 }
 
 /// WebSocket 升级处理器
@@ -139,28 +307,39 @@ async fn ws_handler(ws: WebSocketUpgrade, State(state): State<Arc<AppState>>) ->
 async fn handle_websocket(socket: WebSocket, state: Arc<AppState>) {
     let (mut sender, mut receiver) = socket.split();
     let mut broadcast_rx = state.broadcaster.subscribe();
+    let mut shutdown = state.shutdown.clone();
+    let mut connection = Connection::default();
 
-    println!("[Dashboard] WebSocket client connected");
+    tracing::info!("dashboard client connected");
 
     loop {
         tokio::select! {
+            // 服务器停止
+            _ = shutdown.changed() => {
+                let frame = CloseFrame {
+                    code: close_code::AWAY,
+                    reason: "server shutting down".into(),
+                };
+                let _ = sender.send(Message::Close(Some(frame))).await;
+                break;
+            }
+
             // 处理客户端消息
             msg = receiver.next() => {
                 match msg {
                     Some(Ok(Message::Text(text))) => {
-                        if let Some(response) = handle_api_request(&text, &state).await {
-                            let json = serde_json::to_string(&response).unwrap_or_default();
-                            if sender.send(Message::Text(json)).await.is_err() {
-                                break;
-                            }
+                        let response = handle_api_request(&text, &state, &mut connection).await;
+                        let json = serde_json::to_string(&response).unwrap_or_default();
+                        if sender.send(Message::Text(json)).await.is_err() {
+                            break;
                         }
                     }
                     Some(Ok(Message::Close(_))) | None => {
-                        println!("[Dashboard] WebSocket client disconnected");
+                        tracing::info!("dashboard client disconnected");
                         break;
                     }
                     Some(Err(e)) => {
-                        eprintln!("[Dashboard] WebSocket error: {}", e);
+                        tracing::warn!(error = %e, "dashboard WebSocket error");
                         break;
                     }
                     _ => {}
@@ -171,17 +350,31 @@ async fn handle_websocket(socket: WebSocket, state: Arc<AppState>) {
             event = broadcast_rx.recv() => {
                 match event {
                     Ok(event) => {
-                        let json = serde_json::to_string(&event).unwrap_or_default();
-                        if sender.send(Message::Text(json)).await.is_err() {
+                        let mut messages = Vec::new();
+                        if connection.filter.matches(&event) {
+                            messages.push(serde_json::to_string(&event).unwrap_or_default());
+                        }
+                        for update in graph_updates(&state, &mut connection, &event).await {
+                            messages.push(serde_json::to_string(&update).unwrap_or_default());
+                        }
+                        let mut closed = false;
+                        for json in messages {
+                            if sender.send(Message::Text(json)).await.is_err() {
+                                closed = true;
+                                break;
+                            }
+                        }
+                        if closed {
                             break;
                         }
                     }
-                    Err(broadcast::error::RecvError::Lagged(_)) => {
-                        // 跳过丢失的消息
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        // 跳过丢失的消息，计入丢失事件指标
+                        state.broadcaster.record_lagged(skipped);
                         continue;
                     }
                     Err(broadcast::error::RecvError::Closed) => {
-                        println!("[Dashboard] Broadcast channel closed");
+                        tracing::warn!("dashboard broadcast channel closed");
                         break;
                     }
                 }
@@ -190,23 +383,162 @@ async fn handle_websocket(socket: WebSocket, state: Arc<AppState>) {
     }
 }
 
-/// 处理 API 请求
-async fn handle_api_request(text: &str, state: &AppState) -> Option<ApiResponse> {
-    let request: Result<ApiRequest, _> = serde_json::from_str(text);
+/// 处理 API 请求，无效的请求也会收到带 `request_id` 的错误响应
+async fn handle_api_request(
+    text: &str,
+    state: &AppState,
+    connection: &mut Connection,
+) -> ApiResponseEnvelope {
+    let (request_id, request) = parse_api_request(text);
+    let response = match request {
+        Ok(request) => dispatch_api_request(request, state, connection).await,
+        Err(message) => ApiResponse::error(ApiErrorCode::InvalidRequest, message),
+    };
+    ApiResponseEnvelope {
+        request_id,
+        response,
+    }
+}
 
+/// 解析请求，先取出 `request_id`，这样请求本身无效时也能带回
+fn parse_api_request(text: &str) -> (Option<String>, Result<ApiRequest, String>) {
+    let mut value: serde_json::Value = match serde_json::from_str(text) {
+        Ok(value) => value,
+        Err(e) => return (None, Err(format!("Invalid request: {}", e))),
+    };
+    let request_id = value
+        .as_object_mut()
+        .and_then(|object| object.remove("request_id"))
+        .and_then(|id| id.as_str().map(str::to_string));
+    let request = serde_json::from_value(value).map_err(|e| format!("Invalid request: {}", e));
+    (request_id, request)
+}
+
+/// 执行请求
+///
+/// `Subscribe` 和 `Unsubscribe` 修改当前连接的事件过滤条件，`GetWorkflowGraph` 开始推送该
+/// workflow 的 `GraphNodeUpdated`。
+async fn dispatch_api_request(
+    request: ApiRequest,
+    state: &AppState,
+    connection: &mut Connection,
+) -> ApiResponse {
     match request {
-        Ok(ApiRequest::ListActiveWorkflows) => Some(get_workflow_list(state, false).await),
-        Ok(ApiRequest::ListAllWorkflows) => Some(get_workflow_list(state, true).await),
-        Ok(ApiRequest::GetWorkflow { workflow_id }) => {
-            Some(get_workflow_detail(state, &workflow_id).await)
+        ApiRequest::ListActiveWorkflows => get_workflow_list(state, false).await,
+        ApiRequest::ListAllWorkflows => get_workflow_list(state, true).await,
+        ApiRequest::GetWorkflow { workflow_id } => get_workflow_detail(state, &workflow_id).await,
+        ApiRequest::GetWorkflowHistory { workflow_id } => {
+            get_workflow_history(state, &workflow_id).await
         }
-        Ok(ApiRequest::GetWorkflowHistory { workflow_id }) => {
-            Some(get_workflow_history(state, &workflow_id).await)
+        ApiRequest::GetWorkflowGraph { workflow_id } => {
+            match state.graphs.workflow_graph(&workflow_id).await {
+                Ok(Some(graph)) => {
+                    connection.graph_workflows.insert(workflow_id);
+                    ApiResponse::WorkflowGraph { graph }
+                }
+                Ok(None) => ApiResponse::error(
+                    ApiErrorCode::NotFound,
+                    format!("Workflow not found: {}", workflow_id),
+                ),
+                Err(e) => ApiResponse::error(ApiErrorCode::Internal, e.to_string()),
+            }
+        }
+        ApiRequest::GetEventHistory {
+            workflow_id,
+            since_timestamp,
+        } => {
+            let events = state.broadcaster.history(&workflow_id, since_timestamp);
+            ApiResponse::EventHistory {
+                workflow_id,
+                events,
+            }
+        }
+        ApiRequest::Subscribe {
+            workflow_ids,
+            workflow_types,
+            event_types,
+        } => {
+            if let Some(unknown) = event_types
+                .iter()
+                .find(|name| !EventType::ALL.iter().any(|t| t.as_str() == name.as_str()))
+            {
+                return ApiResponse::error(
+                    ApiErrorCode::InvalidRequest,
+                    format!("Unknown event type: {}", unknown),
+                );
+            }
+            connection.filter = EventFilter {
+                workflow_ids,
+                workflow_types,
+                event_types,
+            };
+            ApiResponse::Subscribed {
+                filter: connection.filter.clone(),
+            }
+        }
+        ApiRequest::Unsubscribe => {
+            connection.filter = EventFilter::default();
+            ApiResponse::Subscribed {
+                filter: connection.filter.clone(),
+            }
         }
-        Err(e) => Some(ApiResponse::Error {
-            message: format!("Invalid request: {}", e),
-        }),
+        ApiRequest::GetStats => ApiResponse::Stats {
+            stats: SystemStats::collect(&state.tracker, &state.broadcaster),
+        },
+    }
+}
+
+/// 事件改变了连接请求过依赖图的 workflow 中 step 的状态时，生成对应的 `GraphNodeUpdated`
+///
+/// workflow 结束时推送被取消的 step，之后不再推送该 workflow。
+async fn graph_updates(
+    state: &AppState,
+    connection: &mut Connection,
+    event: &WorkflowEvent,
+) -> Vec<ApiResponseEnvelope> {
+    if !connection.graph_workflows.contains(&event.workflow_id) {
+        return Vec::new();
     }
+    let step_name = match &event.payload {
+        EventPayload::StepStarted(payload) => Some(payload.step_name.as_str()),
+        EventPayload::StepCompleted(payload) => Some(payload.step_name.as_str()),
+        EventPayload::StepFailed(payload) => Some(payload.step_name.as_str()),
+        _ => None,
+    };
+    if step_name.is_none() && !event.event_type.is_terminal() {
+        return Vec::new();
+    }
+    let Some(execution) = state.tracker.get_execution(&event.workflow_id).await else {
+        return Vec::new();
+    };
+
+    let nodes: Vec<GraphNode> = match step_name {
+        Some(step_name) => vec![GraphNode::from_execution(
+            step_name,
+            execution.step_executions.get(step_name),
+        )],
+        None => {
+            connection.graph_workflows.remove(&event.workflow_id);
+            let mut cancelled: Vec<GraphNode> = execution
+                .step_executions
+                .iter()
+                .filter(|(_, step)| step.status == StepExecutionStatus::Cancelled)
+                .map(|(name, step)| GraphNode::from_execution(name, Some(step)))
+                .collect();
+            cancelled.sort_by(|a, b| a.step_name.cmp(&b.step_name));
+            cancelled
+        }
+    };
+    nodes
+        .into_iter()
+        .map(|node| ApiResponseEnvelope {
+            request_id: None,
+            response: ApiResponse::GraphNodeUpdated {
+                workflow_id: event.workflow_id.clone(),
+                node,
+            },
+        })
+        .collect()
 }
 
 /// 获取 workflow 列表
@@ -223,8 +555,8 @@ async fn get_workflow_list(state: &AppState, include_all: bool) -> ApiResponse {
             workflow_id: w.workflow_id.clone(),
             workflow_type: w.workflow_type.clone(),
             current_step: w.current_step.clone(),
-            started_at: w.started_at.seconds as u64,
-            completed_at: w.completed_at.as_ref().map(|t| t.seconds as u64),
+            started_at: w.started_at.timestamp() as u64,
+            completed_at: w.completed_at.map(|t| t.timestamp() as u64),
         })
         .collect();
 
@@ -243,26 +575,50 @@ async fn get_workflow_detail(state: &AppState, workflow_id: &str) -> ApiResponse
                 .map(|(name, step)| StepExecutionDto {
                     step_name: name.clone(),
                     status: step.status.to_string(),
-                    started_at: step.started_at.as_ref().map(|t| t.seconds as u64),
-                    completed_at: step.completed_at.as_ref().map(|t| t.seconds as u64),
+                    started_at: step.started_at.map(|t| t.timestamp() as u64),
+                    completed_at: step.completed_at.map(|t| t.timestamp() as u64),
                     attempt: step.attempt,
+                    progress: step.progress.clone(),
+                    last_heartbeat_at: step.last_heartbeat_at.map(|t| t.timestamp() as u64),
+                    input: step.input.clone(),
+                    output: step.output.clone(),
+                })
+                .collect();
+
+            let now = chrono::Utc::now();
+            let mut pending_timers: Vec<PendingTimerDto> = w
+                .step_executions
+                .iter()
+                .filter(|(_, step)| step.status == StepExecutionStatus::Running)
+                .filter_map(|(name, step)| {
+                    let fire_at = step.timer_fire_at?;
+                    Some(PendingTimerDto {
+                        step_name: name.clone(),
+                        fire_at: fire_at.timestamp() as u64,
+                        remaining_ms: (fire_at - now).num_milliseconds().max(0) as u64,
+                    })
                 })
                 .collect();
+            pending_timers.sort_by_key(|t| t.fire_at);
 
             let detail = WorkflowDetailDto {
                 workflow_id: w.workflow_id,
                 workflow_type: w.workflow_type,
                 current_step: w.current_step,
                 step_executions,
-                started_at: w.started_at.seconds as u64,
-                completed_at: w.completed_at.as_ref().map(|t| t.seconds as u64),
+                started_at: w.started_at.timestamp() as u64,
+                completed_at: w.completed_at.map(|t| t.timestamp() as u64),
+                pending_timers,
+                parent_workflow_id: w.parent_workflow_id,
+                child_workflow_ids: w.child_workflow_ids,
             };
 
             ApiResponse::WorkflowDetail { detail }
         }
-        None => ApiResponse::Error {
-            message: format!("Workflow not found: {}", workflow_id),
-        },
+        None => ApiResponse::error(
+            ApiErrorCode::NotFound,
+            format!("Workflow not found: {}", workflow_id),
+        ),
     }
 }
 
@@ -274,21 +630,16 @@ async fn get_workflow_history(state: &AppState, workflow_id: &str) -> ApiRespons
                 .step_executions
                 .iter()
                 .map(|(name, step)| {
-                    let duration_ms = match (&step.started_at, &step.completed_at) {
-                        (Some(start), Some(end)) => {
-                            Some(end.seconds.saturating_sub(start.seconds) as u64 * 1000)
-                        }
+                    let duration_ms = match (step.started_at, step.completed_at) {
+                        (Some(start), Some(end)) => time::duration_between(start, end)
+                            .map(|duration| duration.as_millis() as u64),
                         _ => None,
                     };
 
                     StepHistoryDto {
                         step_name: name.clone(),
                         status: step.status.to_string(),
-                        timestamp: step
-                            .started_at
-                            .as_ref()
-                            .map(|t| t.seconds as u64)
-                            .unwrap_or(0),
+                        timestamp: step.started_at.map(|t| t.timestamp() as u64).unwrap_or(0),
                         duration_ms,
                     }
                 })
@@ -298,9 +649,10 @@ async fn get_workflow_history(state: &AppState, workflow_id: &str) -> ApiRespons
 
             ApiResponse::WorkflowHistory { history }
         }
-        None => ApiResponse::Error {
-            message: format!("Workflow not found: {}", workflow_id),
-        },
+        None => ApiResponse::error(
+            ApiErrorCode::NotFound,
+            format!("Workflow not found: {}", workflow_id),
+        ),
     }
 }
 
@@ -309,34 +661,65 @@ async fn get_workflow_history(state: &AppState, workflow_id: &str) -> ApiRespons
 /// Dashboard 服务器
 pub struct DashboardServer {
     tracker: WorkflowTracker,
-    broadcaster: broadcast::Sender<WorkflowEvent>,
+    broadcaster: EventBroadcaster,
+    graphs: Arc<dyn GraphSource>,
 }
 
 impl DashboardServer {
-    /// 创建新的 Dashboard 服务器实例
-    pub fn new(tracker: WorkflowTracker, broadcaster: broadcast::Sender<WorkflowEvent>) -> Self {
+    /// 创建新的 Dashboard 服务器实例，依赖图只包含追踪器中已执行的 step
+    pub fn new(tracker: WorkflowTracker, broadcaster: EventBroadcaster) -> Self {
         Self {
+            graphs: Arc::new(tracker.clone()),
             tracker,
             broadcaster,
         }
     }
 
+    /// 使用 `graphs`（通常是调度器）生成依赖图，图中包含定义中尚未执行的 step
+    pub fn with_graph_source(mut self, graphs: Arc<dyn GraphSource>) -> Self {
+        self.graphs = graphs;
+        self
+    }
+
     /// 启动 Dashboard 服务器
     pub async fn start(&self, listen_addr: &str) -> anyhow::Result<()> {
+        self.start_with_shutdown(listen_addr, std::future::pending())
+            .await
+    }
+
+    /// 启动 Dashboard 服务器，`shutdown` 完成后关闭 WebSocket 连接并停止
+    pub async fn start_with_shutdown(
+        &self,
+        listen_addr: &str,
+        shutdown: impl Future<Output = ()> + Send + 'static,
+    ) -> anyhow::Result<()> {
+        let listener = tokio::net::TcpListener::bind(listen_addr).await?;
+        self.serve_with_shutdown(listener, shutdown).await
+    }
+
+    /// 在已绑定的 `listener` 上提供服务，`shutdown` 完成后停止
+    pub async fn serve_with_shutdown(
+        &self,
+        listener: tokio::net::TcpListener,
+        shutdown: impl Future<Output = ()> + Send + 'static,
+    ) -> anyhow::Result<()> {
+        let listen_addr = listener.local_addr()?;
+        let (stop, stopped) = watch::channel(());
         let state = Arc::new(AppState {
             tracker: self.tracker.clone(),
             broadcaster: self.broadcaster.clone(),
+            graphs: self.graphs.clone(),
+            ws_port: listener.local_addr()?.port(),
+            shutdown: stopped,
         });
+        tracing::info!(%listen_addr, "dashboard server listening");
 
-        let app = Router::new()
-            .route("/ws", get(ws_handler))
-            .fallback(static_handler)
-            .with_state(state);
-
-        let listener = tokio::net::TcpListener::bind(listen_addr).await?;
-        println!("[Dashboard] Server listening on http://{}", listen_addr);
-
-        axum::serve(listener, app).await?;
+        axum::serve(listener, router(state))
+            .with_graceful_shutdown(async move {
+                shutdown.await;
+                let _ = stop.send(());
+            })
+            .await?;
         Ok(())
     }
 }
@@ -344,9 +727,324 @@ impl DashboardServer {
 /// 启动 Dashboard 服务器
 pub async fn start_dashboard_server(
     tracker: WorkflowTracker,
-    broadcaster: broadcast::Sender<WorkflowEvent>,
+    broadcaster: EventBroadcaster,
     listen_addr: &str,
 ) -> anyhow::Result<()> {
     let server = DashboardServer::new(tracker, broadcaster);
     server.start(listen_addr).await
 }
+
+/// 启动 Dashboard 服务器，`shutdown` 完成后停止
+pub async fn start_dashboard_server_with_shutdown(
+    tracker: WorkflowTracker,
+    broadcaster: EventBroadcaster,
+    listen_addr: &str,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> anyhow::Result<()> {
+    let server = DashboardServer::new(tracker, broadcaster);
+    server.start_with_shutdown(listen_addr, shutdown).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::broadcaster::{StepCompletedPayload, WorkflowCompletedPayload};
+    use crate::persistence::l1_snapshot::L1SnapshotStore;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    fn test_state() -> Arc<AppState> {
+        let (_, shutdown) = watch::channel(());
+        let tracker = WorkflowTracker::new();
+        Arc::new(AppState {
+            graphs: Arc::new(tracker.clone()),
+            tracker,
+            broadcaster: EventBroadcaster::new(),
+            ws_port: 7235,
+            shutdown,
+        })
+    }
+
+    fn event(workflow_id: &str, workflow_type: &str, event_type: EventType) -> WorkflowEvent {
+        let payload = EventPayload::WorkflowCompleted(WorkflowCompletedPayload {
+            result: Payload::default(),
+        });
+        WorkflowEvent::new(
+            event_type,
+            workflow_id.to_string(),
+            workflow_type.to_string(),
+            payload,
+        )
+    }
+
+    async fn get(uri: &str) -> Response {
+        router(test_state())
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_config_reports_ws_port() {
+        let response = get("/dashboard/config.json").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let config: DashboardConfigDto = serde_json::from_slice(&body).unwrap();
+        assert_eq!(config.ws_port, 7235);
+        assert_eq!(config.ws_path, "/ws");
+    }
+
+    #[tokio::test]
+    async fn test_spa_routes_serve_index_without_caching() {
+        for uri in ["/", "/workflows/wf-1", "/assets/missing.js"] {
+            let response = get(uri).await;
+            assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+            assert_eq!(
+                response.headers()[header::CONTENT_TYPE],
+                "text/html",
+                "{}",
+                uri
+            );
+            assert_eq!(
+                response.headers()[header::CACHE_CONTROL],
+                "no-cache",
+                "{}",
+                uri
+            );
+        }
+    }
+
+    #[test]
+    fn test_empty_filter_matches_everything() {
+        let filter = EventFilter::default();
+        assert!(filter.matches(&event("wf-1", "order", EventType::StepStarted)));
+        assert!(filter.matches(&event("wf-2", "refund", EventType::SignalReceived)));
+    }
+
+    #[test]
+    fn test_filter_requires_every_set_criterion() {
+        let filter = EventFilter {
+            workflow_ids: vec!["wf-1".to_string(), "wf-2".to_string()],
+            workflow_types: vec![],
+            event_types: vec!["workflow_completed".to_string()],
+        };
+        assert!(filter.matches(&event("wf-1", "order", EventType::WorkflowCompleted)));
+        assert!(filter.matches(&event("wf-2", "refund", EventType::WorkflowCompleted)));
+        assert!(!filter.matches(&event("wf-3", "order", EventType::WorkflowCompleted)));
+        assert!(!filter.matches(&event("wf-1", "order", EventType::StepStarted)));
+
+        let filter = EventFilter {
+            workflow_types: vec!["order".to_string()],
+            ..Default::default()
+        };
+        assert!(filter.matches(&event("wf-3", "order", EventType::StepFailed)));
+        assert!(!filter.matches(&event("wf-3", "refund", EventType::StepFailed)));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_sets_and_unsubscribe_clears_filter() {
+        let state = test_state();
+        let mut connection = Connection::default();
+
+        let request =
+            r#"{"Subscribe": {"workflow_types": ["order"], "event_types": ["step_failed"]}}"#;
+        let response = handle_api_request(request, &state, &mut connection).await;
+        let ApiResponse::Subscribed { filter: active } = response.response else {
+            panic!("unexpected response {:?}", response);
+        };
+        assert_eq!(active, connection.filter);
+        assert_eq!(connection.filter.workflow_types, vec!["order"]);
+        assert!(connection.filter.workflow_ids.is_empty());
+
+        // 未知事件类型被拒绝，原过滤条件保持不变
+        let request = r#"{"Subscribe": {"event_types": ["step_exploded"]}}"#;
+        let response = handle_api_request(request, &state, &mut connection).await;
+        assert!(matches!(response.response, ApiResponse::Error { .. }));
+        assert_eq!(connection.filter.event_types, vec!["step_failed"]);
+
+        let response = handle_api_request(r#""Unsubscribe""#, &state, &mut connection).await;
+        assert!(matches!(response.response, ApiResponse::Subscribed { .. }));
+        assert_eq!(connection.filter, EventFilter::default());
+    }
+
+    /// 发送请求并以 JSON 形式返回客户端收到的响应
+    async fn reply(text: &str) -> serde_json::Value {
+        let response = handle_api_request(text, &test_state(), &mut Connection::default()).await;
+        serde_json::to_value(response).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_malformed_request_gets_invalid_request_error() {
+        let response = reply("{not json").await;
+        assert_eq!(response["Error"]["code"], "INVALID_REQUEST");
+        assert!(response.get("request_id").is_none());
+
+        let response = reply(r#"{"request_id": "req-1", "DropTables": null}"#).await;
+        assert_eq!(response["request_id"], "req-1");
+        assert_eq!(response["Error"]["code"], "INVALID_REQUEST");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_workflow_gets_correlated_not_found() {
+        let response =
+            reply(r#"{"request_id": "req-2", "GetWorkflow": {"workflow_id": "missing"}}"#).await;
+        assert_eq!(response["request_id"], "req-2");
+        assert_eq!(response["Error"]["code"], "NOT_FOUND");
+        assert!(response["Error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("missing"));
+
+        let response = reply(r#"{"request_id": "req-3", "ListAllWorkflows": null}"#).await;
+        assert_eq!(response["request_id"], "req-3");
+        assert_eq!(response["WorkflowList"]["workflows"], serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_event_history_replays_journal() {
+        let state = test_state();
+        for workflow_id in ["wf-1", "wf-2", "wf-1"] {
+            state
+                .broadcaster
+                .broadcast_workflow_completed(workflow_id, "order", vec![])
+                .await;
+        }
+
+        let request = r#"{"request_id": "h", "GetEventHistory": {"workflow_id": "wf-1"}}"#;
+        let response = handle_api_request(request, &state, &mut Connection::default()).await;
+        assert_eq!(response.request_id.as_deref(), Some("h"));
+        let ApiResponse::EventHistory { events, .. } = response.response else {
+            panic!("unexpected response {:?}", response.response);
+        };
+        assert_eq!(events.len(), 2);
+        assert!(events.iter().all(|event| event.workflow_id == "wf-1"));
+    }
+
+    #[tokio::test]
+    async fn test_get_stats_reports_live_counters() {
+        let state = test_state();
+        for workflow_id in ["wf-1", "wf-2"] {
+            state
+                .tracker
+                .start_workflow(workflow_id.to_string(), "order".to_string())
+                .await;
+        }
+        state.tracker.workflow_failed("wf-2").await;
+
+        let request = r#"{"request_id": "s", "GetStats": null}"#;
+        let response = handle_api_request(request, &state, &mut Connection::default()).await;
+        let ApiResponse::Stats { stats } = response.response else {
+            panic!("unexpected response {:?}", response.response);
+        };
+        assert_eq!(stats.live.workflows.active, 1);
+        assert_eq!(stats.live.workflows.failed, 1);
+        assert_eq!(stats.live.started_last_1m, 2);
+
+        let response = reply(r#""GetStats""#).await;
+        assert_eq!(response["Stats"]["stats"]["started_last_60m"], 0);
+        assert!(response["Stats"]["stats"]["step_p95_ms"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_history_survives_tracker_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot.json");
+        {
+            let store = Arc::new(L1SnapshotStore::new(&path, 100).unwrap());
+            let tracker = WorkflowTracker::with_store(store.clone()).await.unwrap();
+            tracker
+                .start_workflow("wf-1".to_string(), "order".to_string())
+                .await;
+            tracker
+                .step_started("wf-1", "charge", vec![], vec![])
+                .await
+                .unwrap();
+            tracker.step_completed("wf-1", "charge", vec![]).await;
+            store.flush().await.unwrap();
+        }
+
+        let store = Arc::new(L1SnapshotStore::new(&path, 100).unwrap());
+        let (_, shutdown) = watch::channel(());
+        let tracker = WorkflowTracker::with_store(store).await.unwrap();
+        let state = AppState {
+            graphs: Arc::new(tracker.clone()),
+            tracker,
+            broadcaster: EventBroadcaster::new(),
+            ws_port: 7235,
+            shutdown,
+        };
+        let request = r#"{"GetWorkflowHistory": {"workflow_id": "wf-1"}}"#;
+        let response = handle_api_request(request, &state, &mut Connection::default()).await;
+        let ApiResponse::WorkflowHistory { history } = response.response else {
+            panic!("unexpected response {:?}", response.response);
+        };
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].step_name, "charge");
+        assert_eq!(history[0].status, "completed");
+    }
+
+    #[tokio::test]
+    async fn test_graph_request_enables_node_updates() {
+        let state = test_state();
+        state
+            .tracker
+            .start_workflow("wf-1".to_string(), "order".to_string())
+            .await;
+        state
+            .tracker
+            .step_started("wf-1", "reserve", vec![], vec![])
+            .await
+            .unwrap();
+        let mut connection = Connection::default();
+
+        // 请求依赖图之前不推送
+        state
+            .tracker
+            .step_completed("wf-1", "reserve", vec![])
+            .await;
+        let completed = WorkflowEvent::new(
+            EventType::StepCompleted,
+            "wf-1".to_string(),
+            "order".to_string(),
+            EventPayload::StepCompleted(Box::new(StepCompletedPayload {
+                step_name: "reserve".to_string(),
+                output: Payload::default(),
+            })),
+        );
+        assert!(graph_updates(&state, &mut connection, &completed)
+            .await
+            .is_empty());
+
+        let request = r#"{"request_id": "g", "GetWorkflowGraph": {"workflow_id": "wf-1"}}"#;
+        let response = handle_api_request(request, &state, &mut connection).await;
+        assert_eq!(response.request_id.as_deref(), Some("g"));
+        let ApiResponse::WorkflowGraph { graph } = response.response else {
+            panic!("unexpected response {:?}", response.response);
+        };
+        assert_eq!(graph.nodes.len(), 1);
+        assert_eq!(graph.nodes[0].status, "completed");
+
+        let updates = graph_updates(&state, &mut connection, &completed).await;
+        let json = serde_json::to_value(&updates).unwrap();
+        assert!(json[0].get("request_id").is_none());
+        assert_eq!(json[0]["GraphNodeUpdated"]["workflow_id"], "wf-1");
+        assert_eq!(json[0]["GraphNodeUpdated"]["node"]["step_name"], "reserve");
+        assert_eq!(json[0]["GraphNodeUpdated"]["node"]["status"], "completed");
+        assert_eq!(json[0]["GraphNodeUpdated"]["node"]["attempt"], 1);
+
+        // 进度事件不改变状态；workflow 结束后不再推送
+        let progress = event("wf-1", "order", EventType::StepProgress);
+        assert!(graph_updates(&state, &mut connection, &progress)
+            .await
+            .is_empty());
+        let done = event("wf-1", "order", EventType::WorkflowCompleted);
+        assert!(graph_updates(&state, &mut connection, &done)
+            .await
+            .is_empty());
+        assert!(connection.graph_workflows.is_empty());
+
+        let response = reply(r#"{"GetWorkflowGraph": {"workflow_id": "missing"}}"#).await;
+        assert_eq!(response["Error"]["code"], "NOT_FOUND");
+    }
+}
@@ -0,0 +1,929 @@
+//! Workflow 定义
+//!
+//! 定义描述一个 workflow 类型包含的 step 及其依赖。调度器根据
+//! `Workflow::completed_steps` 找出依赖已满足的 step 并分发，互不依赖的 step 可以并行执行；
+//! 全部 step 完成后 workflow 完成，结果为输出 step（默认是最后一个 step）的输出。
+//!
+//! 没有任何 step 声明 `depends_on` 时，step 按列出的顺序依次执行。
+//!
+//! 同一类型可以注册多个版本的定义。workflow 启动时固定使用最新（或指定）的版本，
+//! 之后注册的新版本只影响新启动的 workflow。
+//!
+//! 定义可以在代码中通过 `Scheduler::register_definition` 注册，也可以由其他语言的 SDK 通过
+//! `RegisterWorkflowDefinition` RPC 或 `PUT /workflow-definitions/{name}` 推送；注册的定义会持久化，
+//! 服务器重启后重新加载。
+//!
+//! [`WorkflowDefinitionSpec`] 是定义的声明式（JSON/YAML）表示，REST API 和 `aether workflow register`
+//! 读取的定义文件共用这一格式。
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::state_machine::Workflow;
+use crate::task::{ResourceType, RetryPolicy};
+
+/// 定义校验错误
+#[derive(Debug, Clone, PartialEq)]
+pub enum DefinitionError {
+    /// 定义没有名称
+    MissingName,
+    /// 请求体中的名称与路径中的名称不同：(请求体, 路径)
+    NameMismatch(String, String),
+    /// 定义中没有 step
+    Empty(String),
+    /// step 名称重复
+    DuplicateStep(String),
+    /// 依赖了不存在的 step：(step, 依赖)
+    UnknownDependency(String, String),
+    /// 依赖图中存在环，包含环上的 step
+    Cycle(Vec<String>),
+    /// 输出 step 不存在
+    UnknownOutputStep(String),
+    /// 同名同版本的定义已注册且内容不同：(名称, 版本)
+    VersionConflict(String, u32),
+    /// step 的目标资源没有在服务注册表中找到：(step, 服务, 资源)
+    UnknownResource(String, Option<String>, String),
+    /// 无法识别的资源类型：(step, 类型)
+    UnknownResourceType(String, String),
+    /// 通过 API 注册的定义有一处或多处错误：(名称, 错误及位置)
+    Invalid(String, Vec<DefinitionProblem>),
+    /// 保存定义失败
+    Persistence(String),
+}
+
+impl fmt::Display for DefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DefinitionError::MissingName => write!(f, "Workflow definition has no name"),
+            DefinitionError::NameMismatch(body, path) => write!(
+                f,
+                "Workflow definition name '{}' does not match '{}'",
+                body, path
+            ),
+            DefinitionError::Empty(name) => {
+                write!(f, "Workflow definition '{}' has no steps", name)
+            }
+            DefinitionError::DuplicateStep(step) => write!(f, "Duplicate step '{}'", step),
+            DefinitionError::UnknownDependency(step, dependency) => write!(
+                f,
+                "Step '{}' depends on unknown step '{}'",
+                step, dependency
+            ),
+            DefinitionError::Cycle(steps) => {
+                write!(f, "Dependency cycle between steps: {}", steps.join(" -> "))
+            }
+            DefinitionError::UnknownOutputStep(step) => {
+                write!(f, "Output step '{}' is not defined", step)
+            }
+            DefinitionError::VersionConflict(name, version) => write!(
+                f,
+                "Workflow definition '{}' version {} is already registered with different steps",
+                name, version
+            ),
+            DefinitionError::UnknownResource(step, Some(service), resource) => write!(
+                f,
+                "Step '{}' targets resource '{}' which service '{}' has not registered",
+                step, resource, service
+            ),
+            DefinitionError::UnknownResource(step, None, resource) => write!(
+                f,
+                "Step '{}' targets resource '{}' which no service has registered",
+                step, resource
+            ),
+            DefinitionError::UnknownResourceType(step, resource_type) => write!(
+                f,
+                "Step '{}' has unknown resource type '{}': expected STEP, ACTIVITY or WORKFLOW",
+                step, resource_type
+            ),
+            DefinitionError::Invalid(name, problems) => {
+                let problems: Vec<String> = problems.iter().map(ToString::to_string).collect();
+                write!(
+                    f,
+                    "Workflow definition '{}' is invalid: {}",
+                    name,
+                    problems.join("; ")
+                )
+            }
+            DefinitionError::Persistence(message) => {
+                write!(f, "Failed to save workflow definition: {}", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DefinitionError {}
+
+/// 定义中的一处错误及其位置
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefinitionProblem {
+    /// 出错字段的路径，如 `steps[1].dependsOn[0]`，与 REST 请求体的字段名一致
+    pub path: String,
+    pub error: DefinitionError,
+}
+
+impl DefinitionProblem {
+    pub fn new(path: impl Into<String>, error: DefinitionError) -> Self {
+        DefinitionProblem {
+            path: path.into(),
+            error,
+        }
+    }
+}
+
+impl fmt::Display for DefinitionProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.error)
+    }
+}
+
+/// 单个 step 的定义
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub target_service: Option<String>,
+    #[serde(default)]
+    pub target_resource: Option<String>,
+    #[serde(default = "default_resource_type")]
+    pub resource_type: ResourceType,
+    /// 未设置时使用资源注册的重试策略
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+    /// 执行超时，未设置时使用资源注册的超时
+    #[serde(default)]
+    pub timeout: Option<Duration>,
+    /// 目标资源由尚未连接的外部 worker 提供，注册定义时不检查服务注册表
+    #[serde(default)]
+    pub external: bool,
+    /// 必须先完成的 step
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// 在收到该名称的 signal 之前不分发
+    #[serde(default)]
+    pub wait_for_signal: Option<String>,
+    /// 定时器 step：不分发给 worker，就绪后等待该时长自动完成
+    #[serde(default)]
+    pub timer: Option<Duration>,
+    /// 不分发给 worker，等待该 workflow 启动的全部子 workflow 结束
+    #[serde(default)]
+    pub await_children: bool,
+    /// 相同输入的结果可以复用，目标资源未声明 `cacheable` 时同样缓存，见 [`crate::step_cache`]
+    #[serde(default)]
+    pub cacheable: bool,
+}
+
+fn default_resource_type() -> ResourceType {
+    ResourceType::Step
+}
+
+impl StepDefinition {
+    /// 不指定目标服务的普通 step
+    pub fn new(name: impl Into<String>) -> Self {
+        StepDefinition {
+            name: name.into(),
+            target_service: None,
+            target_resource: None,
+            resource_type: ResourceType::Step,
+            retry: None,
+            timeout: None,
+            external: false,
+            depends_on: Vec::new(),
+            wait_for_signal: None,
+            timer: None,
+            await_children: false,
+            cacheable: false,
+        }
+    }
+
+    /// 定时器 step，就绪后等待 `duration` 自动完成，不占用 worker
+    pub fn timer(name: impl Into<String>, duration: Duration) -> Self {
+        StepDefinition {
+            timer: Some(duration),
+            ..Self::new(name)
+        }
+    }
+
+    /// 等待子 workflow 的 step，全部子 workflow 结束后完成，任一子 workflow 失败则 workflow 失败
+    pub fn await_children(name: impl Into<String>) -> Self {
+        StepDefinition {
+            await_children: true,
+            ..Self::new(name)
+        }
+    }
+
+    /// 是否分发给 worker 执行；定时器 step 和等待子 workflow 的 step 由服务器完成
+    pub fn is_dispatched(&self) -> bool {
+        self.timer.is_none() && !self.await_children
+    }
+
+    /// 由指定服务的资源执行
+    pub fn with_target(mut self, service: impl Into<String>, resource: impl Into<String>) -> Self {
+        self.target_service = Some(service.into());
+        self.target_resource = Some(resource.into());
+        self
+    }
+
+    /// 设置资源类型
+    pub fn with_resource_type(mut self, resource_type: ResourceType) -> Self {
+        self.resource_type = resource_type;
+        self
+    }
+
+    /// 设置重试策略
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// 设置执行超时
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// 目标资源由外部 worker 提供，注册时不要求已在服务注册表中
+    pub fn external(mut self) -> Self {
+        self.external = true;
+        self
+    }
+
+    /// 声明依赖的 step
+    pub fn depends_on<I, S>(mut self, steps: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.depends_on.extend(steps.into_iter().map(Into::into));
+        self
+    }
+
+    /// 等待指定 signal 到达后才分发
+    pub fn wait_for_signal(mut self, signal_name: impl Into<String>) -> Self {
+        self.wait_for_signal = Some(signal_name.into());
+        self
+    }
+
+    /// 结果按输入缓存，相同输入的 step 直接以缓存的结果完成
+    pub fn cacheable(mut self) -> Self {
+        self.cacheable = true;
+        self
+    }
+}
+
+/// workflow 类型的定义，step 按顺序执行
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkflowDefinition {
+    /// 对应 `StartWorkflowRequest.workflow_type`
+    pub name: String,
+    /// 定义版本，默认为 1
+    pub version: u32,
+    pub steps: Vec<StepDefinition>,
+    /// 结果作为 workflow 输出的 step，未设置时为最后一个 step
+    #[serde(default)]
+    pub output_step: Option<String>,
+    /// 同一 workflow 的 step 都分发给领取第一个 step 的 worker，见 [`crate::sticky`]
+    #[serde(default)]
+    pub sticky: bool,
+}
+
+impl WorkflowDefinition {
+    pub fn new(name: impl Into<String>, steps: Vec<StepDefinition>) -> Self {
+        WorkflowDefinition {
+            name: name.into(),
+            version: 1,
+            steps,
+            output_step: None,
+            sticky: false,
+        }
+    }
+
+    /// 设置定义版本
+    pub fn with_version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// 启用粘性路由
+    pub fn with_sticky_routing(mut self) -> Self {
+        self.sticky = true;
+        self
+    }
+
+    /// 指定输出 step
+    pub fn with_output_step(mut self, step: impl Into<String>) -> Self {
+        self.output_step = Some(step.into());
+        self
+    }
+
+    /// workflow 完成时取其结果作为输出的 step
+    pub fn output_step_name(&self) -> Option<&str> {
+        self.output_step
+            .as_deref()
+            .or_else(|| self.steps.last().map(|step| step.name.as_str()))
+    }
+
+    /// 全部 step 完成后的 workflow 输出
+    pub fn output(&self, workflow: &Workflow) -> Vec<u8> {
+        self.output_step_name()
+            .and_then(|step| workflow.completed_steps.get(step))
+            .map(|output| output.data.clone())
+            .unwrap_or_default()
+    }
+
+    /// 名为 `name` 的 step
+    pub fn step(&self, name: &str) -> Option<&StepDefinition> {
+        self.steps.iter().find(|step| step.name == name)
+    }
+
+    /// step 实际的依赖：顺序定义中是前一个 step，否则是声明的 `depends_on`
+    pub fn dependencies(&self, step_name: &str) -> Vec<String> {
+        let Some(index) = self.steps.iter().position(|s| s.name == step_name) else {
+            return Vec::new();
+        };
+        if self.is_sequential() {
+            index
+                .checked_sub(1)
+                .map(|prev| vec![self.steps[prev].name.clone()])
+                .unwrap_or_default()
+        } else {
+            self.steps[index].depends_on.clone()
+        }
+    }
+
+    /// 依赖已全部完成、自身尚未完成的 step，按定义顺序
+    pub fn ready_steps(&self, workflow: &Workflow) -> Vec<&StepDefinition> {
+        self.steps
+            .iter()
+            .filter(|step| !workflow.completed_steps.contains_key(&step.name))
+            .filter(|step| {
+                self.dependencies(&step.name)
+                    .iter()
+                    .all(|dependency| workflow.completed_steps.contains_key(dependency))
+            })
+            .collect()
+    }
+
+    /// 是否所有 step 都已完成
+    pub fn is_complete(&self, workflow: &Workflow) -> bool {
+        self.steps
+            .iter()
+            .all(|step| workflow.completed_steps.contains_key(&step.name))
+    }
+
+    /// 校验 step 名称唯一、依赖存在且依赖图无环，返回发现的第一个错误
+    pub fn validate(&self) -> Result<(), DefinitionError> {
+        match self.problems().into_iter().next() {
+            Some(problem) => Err(problem.error),
+            None => Ok(()),
+        }
+    }
+
+    /// 定义中的全部错误及其位置
+    ///
+    /// 依次检查 step 为空、名称重复、输出 step、未知依赖；前面的检查都通过后才检查依赖环。
+    pub fn problems(&self) -> Vec<DefinitionProblem> {
+        if self.steps.is_empty() {
+            return vec![DefinitionProblem::new(
+                "steps",
+                DefinitionError::Empty(self.name.clone()),
+            )];
+        }
+
+        let mut problems = Vec::new();
+        let mut names = HashSet::new();
+        for (index, step) in self.steps.iter().enumerate() {
+            if !names.insert(step.name.as_str()) {
+                problems.push(DefinitionProblem::new(
+                    format!("steps[{}].name", index),
+                    DefinitionError::DuplicateStep(step.name.clone()),
+                ));
+            }
+        }
+        if let Some(output_step) = &self.output_step {
+            if !names.contains(output_step.as_str()) {
+                problems.push(DefinitionProblem::new(
+                    "outputStep",
+                    DefinitionError::UnknownOutputStep(output_step.clone()),
+                ));
+            }
+        }
+        for (index, step) in self.steps.iter().enumerate() {
+            for (dependency_index, dependency) in step.depends_on.iter().enumerate() {
+                if !names.contains(dependency.as_str()) {
+                    problems.push(DefinitionProblem::new(
+                        format!("steps[{}].dependsOn[{}]", index, dependency_index),
+                        DefinitionError::UnknownDependency(step.name.clone(), dependency.clone()),
+                    ));
+                }
+            }
+        }
+
+        // 名称重复或依赖不存在时无法构建依赖图
+        if problems.is_empty() {
+            if let Some(cycle) = self.find_cycle() {
+                let index = self
+                    .steps
+                    .iter()
+                    .position(|step| step.name == cycle[0])
+                    .unwrap_or(0);
+                problems.push(DefinitionProblem::new(
+                    format!("steps[{}].dependsOn", index),
+                    DefinitionError::Cycle(cycle),
+                ));
+            }
+        }
+        problems
+    }
+
+    /// 依赖图中的一个环，首尾是同一个 step
+    fn find_cycle(&self) -> Option<Vec<String>> {
+        // 深度优先搜索，遇到仍在栈中的 step 即为环
+        #[derive(Clone, Copy, PartialEq)]
+        enum Mark {
+            Visiting,
+            Done,
+        }
+
+        fn visit<'a>(
+            step: &'a str,
+            edges: &HashMap<&'a str, &'a [String]>,
+            marks: &mut HashMap<&'a str, Mark>,
+            stack: &mut Vec<&'a str>,
+        ) -> Option<Vec<String>> {
+            match marks.get(step) {
+                Some(Mark::Done) => return None,
+                Some(Mark::Visiting) => {
+                    let start = stack.iter().position(|s| *s == step).unwrap_or(0);
+                    let mut cycle: Vec<String> =
+                        stack[start..].iter().map(|s| s.to_string()).collect();
+                    cycle.push(step.to_string());
+                    return Some(cycle);
+                }
+                None => {}
+            }
+            marks.insert(step, Mark::Visiting);
+            stack.push(step);
+            for dependency in edges[step] {
+                if let Some(cycle) = visit(dependency, edges, marks, stack) {
+                    return Some(cycle);
+                }
+            }
+            stack.pop();
+            marks.insert(step, Mark::Done);
+            None
+        }
+
+        let edges: HashMap<&str, &[String]> = self
+            .steps
+            .iter()
+            .map(|step| (step.name.as_str(), step.depends_on.as_slice()))
+            .collect();
+        let mut marks = HashMap::new();
+        self.steps
+            .iter()
+            .find_map(|step| visit(&step.name, &edges, &mut marks, &mut Vec::new()))
+    }
+
+    fn is_sequential(&self) -> bool {
+        self.steps.iter().all(|step| step.depends_on.is_empty())
+    }
+}
+
+/// 定义的声明式表示，字段名为 camelCase，也接受 snake_case 写法
+///
+/// ```yaml
+/// name: etl
+/// version: 2
+/// steps:
+///   - name: extract
+///     targetService: warehouse
+///     targetResource: extract
+///     timeout: 30000
+///   - name: load
+///     dependsOn: [extract]
+///     retry: { maxAttempts: 5 }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct WorkflowDefinitionSpec {
+    /// workflow 类型；通过 `PUT /workflow-definitions/{name}` 注册时可以省略
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    /// 默认为 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
+    pub steps: Vec<StepDefinitionSpec>,
+    /// 输出作为 workflow 输出的 step，默认为最后一个 step
+    #[serde(
+        default,
+        alias = "output_step",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub output_step: Option<String>,
+    /// 同一 workflow 的 step 都分发给领取第一个 step 的 worker
+    #[serde(default)]
+    pub sticky: bool,
+}
+
+/// step 的声明式表示
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct StepDefinitionSpec {
+    pub name: String,
+    /// 必须先完成的 step；没有任何 step 声明依赖时按列出的顺序执行
+    #[serde(default, alias = "depends_on", skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    #[serde(
+        default,
+        alias = "target_service",
+        alias = "service",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub target_service: Option<String>,
+    #[serde(
+        default,
+        alias = "target_resource",
+        alias = "resource",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub target_resource: Option<String>,
+    /// `STEP`（默认）、`ACTIVITY` 或 `WORKFLOW`，不区分大小写
+    #[serde(
+        default,
+        alias = "resource_type",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub resource_type: Option<String>,
+    /// 覆盖目标资源注册的重试策略
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryPolicySpec>,
+    /// 执行超时（毫秒），默认使用目标资源声明的超时
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// 目标资源由尚未注册的 worker 提供，注册定义时不检查服务注册表
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub external: bool,
+    /// 收到该 signal 之前不分发
+    #[serde(
+        default,
+        alias = "wait_for_signal",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub wait_for_signal: Option<String>,
+    /// 定时器 step 的等待时长（毫秒），不分发给 worker
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timer: Option<u64>,
+    /// 等待 workflow 启动的全部子 workflow 结束，不分发给 worker
+    #[serde(default, alias = "await_children", skip_serializing_if = "is_false")]
+    pub await_children: bool,
+    /// 相同输入的结果可以复用
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub cacheable: bool,
+}
+
+/// 重试策略的声明式表示
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RetryPolicySpec {
+    #[serde(alias = "max_attempts")]
+    pub max_attempts: u32,
+    /// 第一次重试前的等待时间（毫秒），默认 1000
+    #[serde(default, alias = "initial_interval")]
+    pub initial_interval: Option<u64>,
+    /// 默认 2
+    #[serde(default, alias = "backoff_multiplier")]
+    pub backoff_multiplier: Option<f64>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+impl WorkflowDefinitionSpec {
+    /// 转换为定义，同时返回无法转换的字段（如无法识别的资源类型）
+    ///
+    /// 返回的错误不包括定义本身的校验结果，见 [`WorkflowDefinition::problems`]。
+    pub fn into_definition(self) -> (WorkflowDefinition, Vec<DefinitionProblem>) {
+        let mut problems = Vec::new();
+        let steps = self
+            .steps
+            .into_iter()
+            .enumerate()
+            .map(|(index, step)| {
+                let resource_type = match step.resource_type.as_deref().map(str::to_uppercase) {
+                    None => ResourceType::Step,
+                    Some(value) => match value.as_str() {
+                        "STEP" => ResourceType::Step,
+                        "ACTIVITY" => ResourceType::Activity,
+                        "WORKFLOW" => ResourceType::Workflow,
+                        _ => {
+                            problems.push(DefinitionProblem::new(
+                                format!("steps[{}].resourceType", index),
+                                DefinitionError::UnknownResourceType(step.name.clone(), value),
+                            ));
+                            ResourceType::Step
+                        }
+                    },
+                };
+                let defaults = RetryPolicy::default();
+                StepDefinition {
+                    resource_type,
+                    retry: step.retry.map(|retry| RetryPolicy {
+                        max_attempts: retry.max_attempts,
+                        initial_interval: retry
+                            .initial_interval
+                            .unwrap_or(defaults.initial_interval),
+                        backoff_multiplier: retry
+                            .backoff_multiplier
+                            .unwrap_or(defaults.backoff_multiplier),
+                    }),
+                    timeout: step.timeout.map(Duration::from_millis),
+                    external: step.external,
+                    depends_on: step.depends_on,
+                    wait_for_signal: step.wait_for_signal,
+                    timer: step.timer.map(Duration::from_millis),
+                    await_children: step.await_children,
+                    cacheable: step.cacheable,
+                    target_service: step.target_service,
+                    target_resource: step.target_resource,
+                    name: step.name,
+                }
+            })
+            .collect();
+        let definition = WorkflowDefinition {
+            name: self.name,
+            version: self.version.unwrap_or(1),
+            steps,
+            output_step: self.output_step,
+            sticky: self.sticky,
+        };
+        (definition, problems)
+    }
+}
+
+impl From<WorkflowDefinition> for WorkflowDefinitionSpec {
+    fn from(definition: WorkflowDefinition) -> Self {
+        let millis = |duration: Duration| duration.as_millis() as u64;
+        WorkflowDefinitionSpec {
+            steps: definition
+                .steps
+                .into_iter()
+                .map(|step| StepDefinitionSpec {
+                    resource_type: Some(step.resource_type.as_str().to_string()),
+                    retry: step.retry.map(|retry| RetryPolicySpec {
+                        max_attempts: retry.max_attempts,
+                        initial_interval: Some(retry.initial_interval),
+                        backoff_multiplier: Some(retry.backoff_multiplier),
+                    }),
+                    timeout: step.timeout.map(millis),
+                    external: step.external,
+                    depends_on: step.depends_on,
+                    wait_for_signal: step.wait_for_signal,
+                    timer: step.timer.map(millis),
+                    await_children: step.await_children,
+                    cacheable: step.cacheable,
+                    target_service: step.target_service,
+                    target_resource: step.target_resource,
+                    name: step.name,
+                })
+                .collect(),
+            name: definition.name,
+            version: Some(definition.version),
+            output_step: definition.output_step,
+            sticky: definition.sticky,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequential_steps_become_ready_in_order() {
+        let definition = WorkflowDefinition::new(
+            "order",
+            vec![
+                StepDefinition::new("reserve"),
+                StepDefinition::new("charge").with_target("billing", "charge"),
+                StepDefinition::new("ship"),
+            ],
+        );
+        let mut workflow = Workflow::new("wf-1".to_string(), "order".to_string(), vec![]);
+
+        assert_eq!(definition.ready_steps(&workflow)[0].name, "reserve");
+        workflow.record_completion("reserve", vec![]);
+        let ready = definition.ready_steps(&workflow);
+        assert_eq!(ready.len(), 1);
+        let next = ready[0];
+        assert_eq!(next.name, "charge");
+        assert_eq!(next.target_service.as_deref(), Some("billing"));
+        workflow.record_completion("charge", vec![]);
+        workflow.record_completion("ship", vec![]);
+        assert!(definition.ready_steps(&workflow).is_empty());
+        assert!(definition.is_complete(&workflow));
+    }
+
+    fn diamond() -> WorkflowDefinition {
+        WorkflowDefinition::new(
+            "diamond",
+            vec![
+                StepDefinition::new("a"),
+                StepDefinition::new("b").depends_on(["a"]),
+                StepDefinition::new("c").depends_on(["a"]),
+                StepDefinition::new("d").depends_on(["b", "c"]),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_dependencies_gate_ready_steps() {
+        let definition = diamond();
+        assert!(definition.validate().is_ok());
+        assert_eq!(definition.dependencies("d"), vec!["b", "c"]);
+
+        let mut workflow = Workflow::new("wf-1".to_string(), "diamond".to_string(), vec![]);
+        let names = |workflow: &Workflow| -> Vec<String> {
+            definition
+                .ready_steps(workflow)
+                .iter()
+                .map(|s| s.name.clone())
+                .collect()
+        };
+        assert_eq!(names(&workflow), vec!["a"]);
+        workflow.record_completion("a", vec![]);
+        assert_eq!(names(&workflow), vec!["b", "c"]);
+        workflow.record_completion("b", vec![]);
+        assert_eq!(names(&workflow), vec!["c"]);
+        workflow.record_completion("c", vec![]);
+        assert_eq!(names(&workflow), vec!["d"]);
+    }
+
+    #[test]
+    fn test_validate_rejects_invalid_graphs() {
+        let cyclic = WorkflowDefinition::new(
+            "cyclic",
+            vec![
+                StepDefinition::new("a").depends_on(["c"]),
+                StepDefinition::new("b").depends_on(["a"]),
+                StepDefinition::new("c").depends_on(["b"]),
+            ],
+        );
+        assert!(matches!(cyclic.validate(), Err(DefinitionError::Cycle(_))));
+
+        let self_loop =
+            WorkflowDefinition::new("loop", vec![StepDefinition::new("a").depends_on(["a"])]);
+        assert_eq!(
+            self_loop.validate(),
+            Err(DefinitionError::Cycle(vec![
+                "a".to_string(),
+                "a".to_string()
+            ]))
+        );
+
+        let unknown =
+            WorkflowDefinition::new("unknown", vec![StepDefinition::new("a").depends_on(["x"])]);
+        assert_eq!(
+            unknown.validate(),
+            Err(DefinitionError::UnknownDependency(
+                "a".to_string(),
+                "x".to_string()
+            ))
+        );
+
+        let duplicate = WorkflowDefinition::new(
+            "duplicate",
+            vec![StepDefinition::new("a"), StepDefinition::new("a")],
+        );
+        assert_eq!(
+            duplicate.validate(),
+            Err(DefinitionError::DuplicateStep("a".to_string()))
+        );
+
+        let output =
+            WorkflowDefinition::new("output", vec![StepDefinition::new("a")]).with_output_step("b");
+        assert_eq!(
+            output.validate(),
+            Err(DefinitionError::UnknownOutputStep("b".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_problems_report_every_error_with_its_path() {
+        let definition = WorkflowDefinition::new(
+            "broken",
+            vec![
+                StepDefinition::new("a"),
+                StepDefinition::new("b").depends_on(["a", "x"]),
+                StepDefinition::new("a"),
+            ],
+        )
+        .with_output_step("z");
+        let problems: Vec<String> = definition
+            .problems()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            problems,
+            vec![
+                "steps[2].name: Duplicate step 'a'",
+                "outputStep: Output step 'z' is not defined",
+                "steps[1].dependsOn[1]: Step 'b' depends on unknown step 'x'",
+            ]
+        );
+
+        let cyclic = WorkflowDefinition::new(
+            "cyclic",
+            vec![
+                StepDefinition::new("a"),
+                StepDefinition::new("b").depends_on(["c"]),
+                StepDefinition::new("c").depends_on(["b"]),
+            ],
+        );
+        assert_eq!(cyclic.problems()[0].path, "steps[1].dependsOn");
+    }
+
+    #[test]
+    fn test_definition_round_trips_through_json() {
+        let definition = WorkflowDefinition::new(
+            "order",
+            vec![
+                StepDefinition::new("charge")
+                    .with_target("billing", "charge")
+                    .with_timeout(Duration::from_secs(30))
+                    .external(),
+                StepDefinition::timer("cool-down", Duration::from_secs(5)).depends_on(["charge"]),
+            ],
+        )
+        .with_version(2);
+        let json = serde_json::to_string(&definition).unwrap();
+        assert_eq!(
+            serde_json::from_str::<WorkflowDefinition>(&json).unwrap(),
+            definition
+        );
+
+        // 省略的字段使用默认值
+        let minimal: WorkflowDefinition =
+            serde_json::from_str(r#"{"name":"n","version":1,"steps":[{"name":"a"}]}"#).unwrap();
+        assert_eq!(
+            minimal,
+            WorkflowDefinition::new("n", vec![StepDefinition::new("a")])
+        );
+    }
+
+    #[test]
+    fn test_spec_converts_to_definition() {
+        let spec: WorkflowDefinitionSpec = serde_json::from_value(serde_json::json!({
+            "name": "etl",
+            "version": 2,
+            "steps": [
+                { "name": "extract", "service": "warehouse", "resource": "extract", "timeout": 30000 },
+                { "name": "load", "depends_on": ["extract"], "resourceType": "activity",
+                  "retry": { "maxAttempts": 5 } },
+                { "name": "archive", "resourceType": "job" }
+            ]
+        }))
+        .unwrap();
+        let (definition, problems) = spec.clone().into_definition();
+        assert_eq!(definition.version, 2);
+        assert_eq!(
+            definition.steps[0].target_service.as_deref(),
+            Some("warehouse")
+        );
+        assert_eq!(definition.steps[0].timeout, Some(Duration::from_secs(30)));
+        assert_eq!(definition.steps[1].resource_type, ResourceType::Activity);
+        assert_eq!(
+            definition.steps[1].retry,
+            Some(RetryPolicy {
+                max_attempts: 5,
+                ..RetryPolicy::default()
+            })
+        );
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].path, "steps[2].resourceType");
+
+        // 转换回声明式表示后再转换，得到相同的定义
+        let valid = WorkflowDefinition {
+            steps: definition.steps[..2].to_vec(),
+            ..definition
+        };
+        let (again, problems) = WorkflowDefinitionSpec::from(valid.clone()).into_definition();
+        assert!(problems.is_empty());
+        assert_eq!(again, valid);
+
+        let err = serde_json::from_value::<WorkflowDefinitionSpec>(serde_json::json!({
+            "steps": [{ "name": "a", "dependson": [] }]
+        }))
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("unknown field `dependson`"),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! 服务器诊断信息
+//!
+//! 为 `aether doctor` 提供存储探测、worker 列表和卡住的 workflow。
+//! 服务器版本与能力见 [`crate::server_info`]。
+
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+
+use crate::persistence::Persistence;
+use crate::scheduler::{Scheduler, WorkerInfo};
+use crate::state_machine::{Workflow, WorkflowState};
+
+/// 调度器运行状态快照
+#[derive(Debug, Clone)]
+pub struct Diagnostics {
+    pub persistence_backend: String,
+    pub persistence_probe: Duration,
+    pub workers: Vec<WorkerInfo>,
+    pub stuck_workflows: Vec<Workflow>,
+    pub subscriber_count: usize,
+}
+
+impl<P: Persistence> Scheduler<P> {
+    /// 收集诊断信息
+    ///
+    /// 处于 Pending 或 Running、没有有效租约且超过 `stuck_after` 未更新的 workflow 视为卡住。
+    pub async fn diagnostics(&self, stuck_after: Duration) -> anyhow::Result<Diagnostics> {
+        // 探测存储延迟
+        let probe_start = Instant::now();
+        let workflows = self.persistence.list_workflows(None).await?;
+        let persistence_probe = probe_start.elapsed();
+
+        let leased = self.leased_workflow_ids().await;
+        let cutoff = Utc::now() - chrono::Duration::from_std(stuck_after)?;
+
+        let stuck_workflows = workflows
+            .into_iter()
+            .filter(|w| {
+                matches!(
+                    w.state,
+                    WorkflowState::Pending | WorkflowState::Running { .. }
+                )
+            })
+            .filter(|w| w.updated_at < cutoff && !leased.contains(&w.id))
+            .collect();
+
+        Ok(Diagnostics {
+            persistence_backend: self.persistence.backend_name().to_string(),
+            persistence_probe,
+            workers: self.workers().await,
+            stuck_workflows,
+            subscriber_count: self.broadcaster.subscriber_count(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::l0_memory::L0MemoryStore;
+
+    #[tokio::test]
+    async fn test_stuck_workflows() {
+        let store = L0MemoryStore::new();
+
+        let mut stale = Workflow::new("stale".to_string(), "test-type".to_string(), vec![]);
+        stale.updated_at = Utc::now() - chrono::Duration::minutes(10);
+        store.save_workflow(&stale).await.unwrap();
+
+        let fresh = Workflow::new("fresh".to_string(), "test-type".to_string(), vec![]);
+        store.save_workflow(&fresh).await.unwrap();
+
+        let mut done = Workflow::new("done".to_string(), "test-type".to_string(), vec![]);
+        done.state = WorkflowState::Completed {
+            result: vec![].into(),
+        };
+        done.updated_at = stale.updated_at;
+        store.save_workflow(&done).await.unwrap();
+
+        let scheduler = Scheduler::new(store);
+        let diagnostics = scheduler
+            .diagnostics(Duration::from_secs(60))
+            .await
+            .unwrap();
+
+        assert_eq!(diagnostics.persistence_backend, "memory");
+        assert_eq!(diagnostics.stuck_workflows.len(), 1);
+        assert_eq!(diagnostics.stuck_workflows[0].id, "stale");
+    }
+
+    #[tokio::test]
+    async fn test_leased_workflow_not_stuck() {
+        let store = L0MemoryStore::new();
+        let mut workflow = Workflow::new("wf-1".to_string(), "test-type".to_string(), vec![]);
+        workflow.state = workflow.state.try_start().unwrap();
+        workflow.updated_at = Utc::now() - chrono::Duration::minutes(10);
+        store.save_workflow(&workflow).await.unwrap();
+
+        let scheduler = Scheduler::new(store);
+        scheduler
+            .register_worker(
+                "worker-1".to_string(),
+                "test-service".to_string(),
+                "test-group".to_string(),
+                vec!["test-type".to_string()],
+                vec![],
+            )
+            .await;
+        assert_eq!(scheduler.poll_tasks("worker-1", 1).await.unwrap().len(), 1);
+
+        let diagnostics = scheduler
+            .diagnostics(Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert!(diagnostics.stuck_workflows.is_empty());
+        assert_eq!(diagnostics.workers.len(), 1);
+    }
+}
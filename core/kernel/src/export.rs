@@ -0,0 +1,712 @@
+//! workflow 执行记录导出
+//!
+//! 把 workflow 的元数据、状态变化、step 执行记录和事件日志中的事件汇总为一个自包含的文档，
+//! 供附在问题报告中或审计使用。REST（`GET /workflows/{id}/export`）、gRPC（`ExportWorkflow`）
+//! 和 CLI（`aether workflow export`）输出同一个文档；它的 JSON 字段名属于对外格式。
+//! 导出的文档可以通过 [`WorkflowExport::decode`] 读回，导入本地服务器重放（见 [`crate::import`]）。
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use crate::broadcaster::{EventPayload, EventType, WorkflowEvent};
+use crate::payload::Payload;
+use crate::persistence::Persistence;
+use crate::scheduler::Scheduler;
+use crate::state_machine::{Workflow, WorkflowState};
+use crate::tracker::{StepExecution, StepExecutionStatus};
+
+/// 导出文档的格式版本，字段含义变化时递增
+///
+/// 新增字段必须带 `#[serde(default)]`，这样旧文件仍能读取；改变已有字段的含义时递增版本，
+/// 并在 [`WorkflowExport::decode`] 中把旧版本的文档转换为当前结构。
+pub const EXPORT_FORMAT_VERSION: u32 = 1;
+
+/// 导出文档的编码
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// 单个 JSON 文档
+    #[default]
+    Json,
+    /// 每行一条记录，`record` 字段区分记录类型
+    Jsonl,
+}
+
+/// 导出选项
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// 单个负载保留的最大字节数，超过的负载截断为前缀的 base64，`None` 表示不截断
+    pub max_payload_bytes: Option<usize>,
+}
+
+/// workflow 的完整执行记录
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkflowExport {
+    pub format_version: u32,
+    /// RFC 3339 时间
+    pub exported_at: String,
+    /// 是否有负载因超过 `max_payload_bytes` 被截断
+    pub truncated: bool,
+    pub workflow: ExportedWorkflow,
+    /// 由启动时间、事件日志和当前状态推出的状态变化，按时间排列
+    pub transitions: Vec<StateTransition>,
+    /// step 执行记录，按开始时间排列，未开始的 step 在最后
+    pub steps: Vec<ExportedStep>,
+    /// 事件日志中该 workflow 的事件；事件日志有界，长时间运行的 workflow 的早期事件可能缺失
+    pub events: Vec<ExportedEvent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedWorkflow {
+    pub workflow_id: String,
+    pub workflow_type: String,
+    pub status: String,
+    /// 失败原因或强制结束的原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub terminated_by: Option<String>,
+    pub priority: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_workflow_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub child_workflow_ids: Vec<String>,
+    pub started_at: String,
+    pub updated_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<String>,
+    pub input: ExportedPayload,
+    /// 完成的 workflow 的结果
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<ExportedPayload>,
+}
+
+/// 一次状态变化
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StateTransition {
+    pub status: String,
+    /// RFC 3339 时间
+    pub at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedStep {
+    pub step_name: String,
+    pub status: String,
+    /// 尝试次数，大于 1 表示重试过
+    pub attempt: u32,
+    /// 最后一次失败的错误
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input: Option<ExportedPayload>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<ExportedPayload>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<ExportedProgress>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_heartbeat_at: Option<String>,
+    /// 结果取自 step 结果缓存
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cache_hit: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedProgress {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub percent: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<ExportedPayload>,
+}
+
+/// 事件日志中的一个事件，`payload` 同 `GET /workflows/{id}/events`
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedEvent {
+    pub event_type: String,
+    /// Unix 时间戳（秒）
+    pub timestamp: u64,
+    pub payload: Value,
+}
+
+/// 导出的负载：JSON 内容原样输出，其余内容为 base64
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedPayload {
+    pub content_type: String,
+    /// 原始字节数
+    pub size: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub json: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base64: Option<String>,
+    /// 截断后 `base64` 只包含前 `max_payload_bytes` 个字节
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+impl WorkflowExport {
+    /// 按 `format` 编码
+    pub fn encode(&self, format: ExportFormat) -> serde_json::Result<Vec<u8>> {
+        match format {
+            ExportFormat::Json => serde_json::to_vec_pretty(self),
+            ExportFormat::Jsonl => self.to_jsonl().map(String::into_bytes),
+        }
+    }
+
+    /// 每行一条记录：先是 workflow，然后依次是状态变化、step 和事件
+    pub fn to_jsonl(&self) -> serde_json::Result<String> {
+        let mut header = serde_json::to_value(&self.workflow)?;
+        if let Some(fields) = header.as_object_mut() {
+            fields.insert("formatVersion".to_string(), self.format_version.into());
+            fields.insert("exportedAt".to_string(), self.exported_at.clone().into());
+            fields.insert("truncated".to_string(), self.truncated.into());
+        }
+
+        let mut records = vec![("workflow", header)];
+        for transition in &self.transitions {
+            records.push(("transition", serde_json::to_value(transition)?));
+        }
+        for step in &self.steps {
+            records.push(("step", serde_json::to_value(step)?));
+        }
+        for event in &self.events {
+            records.push(("event", serde_json::to_value(event)?));
+        }
+
+        let mut out = String::new();
+        for (record, mut value) in records {
+            if let Some(fields) = value.as_object_mut() {
+                fields.insert("record".to_string(), record.into());
+            }
+            out.push_str(&serde_json::to_string(&value)?);
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
+    /// 读取 JSON 或 JSONL 格式的导出文档
+    ///
+    /// 不认识的格式版本（由更新的服务器导出）返回错误，不按当前结构猜测字段含义。
+    pub fn decode(document: &[u8]) -> Result<Self, DecodeError> {
+        let value = match serde_json::from_slice::<Value>(document) {
+            Ok(value) => value,
+            Err(json_error) => {
+                from_jsonl(document).map_err(|_| DecodeError::Invalid(json_error.to_string()))?
+            }
+        };
+        let version = value
+            .get("formatVersion")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| DecodeError::Invalid("missing formatVersion".to_string()))?;
+        match version {
+            1 => serde_json::from_value(value).map_err(|e| DecodeError::Invalid(e.to_string())),
+            version => Err(DecodeError::UnsupportedVersion(version)),
+        }
+    }
+}
+
+/// 导出文档无法读取
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeError {
+    /// 不是合法的导出文档
+    Invalid(String),
+    /// 格式版本高于 [`EXPORT_FORMAT_VERSION`]
+    UnsupportedVersion(u64),
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::Invalid(e) => write!(f, "Invalid workflow export: {}", e),
+            DecodeError::UnsupportedVersion(version) => write!(
+                f,
+                "Unsupported export format version {} (this server reads up to {})",
+                version, EXPORT_FORMAT_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// 把 JSONL 的各行记录重新组合为 JSON 文档的结构，版本检查由调用方完成
+fn from_jsonl(document: &[u8]) -> serde_json::Result<Value> {
+    let text = std::str::from_utf8(document).map_err(serde::de::Error::custom)?;
+    let mut workflow = None;
+    let mut doc = serde_json::Map::new();
+    let (mut transitions, mut steps, mut events) = (Vec::new(), Vec::new(), Vec::new());
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        let mut value: Value = serde_json::from_str(line)?;
+        let fields = value
+            .as_object_mut()
+            .ok_or_else(|| serde::de::Error::custom("record is not an object"))?;
+        let record = fields.remove("record");
+        match record.as_ref().and_then(Value::as_str) {
+            Some("workflow") => {
+                for key in ["formatVersion", "exportedAt", "truncated"] {
+                    if let Some(field) = fields.remove(key) {
+                        doc.insert(key.to_string(), field);
+                    }
+                }
+                workflow = Some(value);
+            }
+            Some("transition") => transitions.push(value),
+            Some("step") => steps.push(value),
+            Some("event") => events.push(value),
+            _ => return Err(serde::de::Error::custom("unknown record type")),
+        }
+    }
+    let workflow = workflow.ok_or_else(|| serde::de::Error::custom("missing workflow record"))?;
+    doc.insert("workflow".to_string(), workflow);
+    doc.insert("transitions".to_string(), transitions.into());
+    doc.insert("steps".to_string(), steps.into());
+    doc.insert("events".to_string(), events.into());
+    Ok(Value::Object(doc))
+}
+
+impl ExportedPayload {
+    /// 还原原始字节，截断的负载返回 `None`
+    pub fn to_payload(&self) -> Option<Payload> {
+        if self.truncated {
+            return None;
+        }
+        let data = match (&self.json, &self.base64) {
+            (Some(json), _) => serde_json::to_vec(json).ok()?,
+            (None, Some(encoded)) => BASE64.decode(encoded).ok()?,
+            (None, None) => Vec::new(),
+        };
+        Some(Payload::new(data, self.content_type.clone()))
+    }
+}
+
+/// 导出 workflow 的执行记录，workflow 不存在时返回 `None`
+///
+/// step 记录来自追踪器；追踪器已淘汰该 workflow 时，从持久化的 step 结果生成已完成的 step。
+pub async fn export_workflow<P: Persistence>(
+    scheduler: &Scheduler<P>,
+    workflow_id: &str,
+    options: &ExportOptions,
+) -> anyhow::Result<Option<WorkflowExport>> {
+    let Some(workflow) = scheduler.persistence.get_workflow(workflow_id).await? else {
+        return Ok(None);
+    };
+    let execution = scheduler.tracker.get_execution(workflow_id).await;
+    let child_workflow_ids = scheduler
+        .child_workflows(workflow_id)
+        .await?
+        .into_iter()
+        .map(|child| child.id)
+        .collect();
+    let history = scheduler.broadcaster.history(workflow_id, None);
+    let mut exporter = Exporter {
+        max_payload_bytes: options.max_payload_bytes,
+        truncated: false,
+    };
+
+    let mut steps: Vec<StepExecution> = execution
+        .as_ref()
+        .map(|execution| execution.step_executions.values().cloned().collect())
+        .unwrap_or_default();
+    for (step_name, output) in &workflow.completed_steps {
+        if !steps.iter().any(|step| step.step_name == *step_name) {
+            steps.push(StepExecution {
+                step_name: step_name.clone(),
+                status: StepExecutionStatus::Completed,
+                started_at: None,
+                completed_at: None,
+                input: Payload::default(),
+                output: Some(output.clone()),
+                attempt: 1,
+                dependencies: Vec::new(),
+                progress: None,
+                last_heartbeat_at: None,
+                timer_fire_at: None,
+                rejections: Vec::new(),
+                cache_hit: false,
+            });
+        }
+    }
+    steps.sort_by_key(|step| {
+        (
+            step.started_at.is_none(),
+            step.started_at,
+            step.step_name.clone(),
+        )
+    });
+    let steps = steps.into_iter().map(|step| exporter.step(step)).collect();
+
+    let completed_at = execution
+        .as_ref()
+        .and_then(|execution| execution.completed_at)
+        .map(|time| time.to_rfc3339())
+        .or_else(|| {
+            workflow
+                .state
+                .is_terminal()
+                .then(|| workflow.updated_at.to_rfc3339())
+        });
+    let transitions = if workflow.history.is_empty() {
+        transitions(
+            &workflow.state,
+            workflow.started_at,
+            completed_at.as_deref(),
+            &history,
+        )
+    } else {
+        recorded_transitions(&workflow)
+    };
+    let events = history
+        .into_iter()
+        .map(|event| exporter.event(event))
+        .collect();
+
+    let (error, terminated_by, output) = match &workflow.state {
+        WorkflowState::Completed { result } => (None, None, Some(exporter.payload(result))),
+        WorkflowState::Failed { error } => (Some(error.clone()), None, None),
+        WorkflowState::Terminated {
+            reason,
+            requested_by,
+        } => (Some(reason.clone()), requested_by.clone(), None),
+        _ => (None, None, None),
+    };
+    let input = exporter.payload(&Payload::from_bytes(workflow.input.clone()));
+
+    Ok(Some(WorkflowExport {
+        format_version: EXPORT_FORMAT_VERSION,
+        exported_at: Utc::now().to_rfc3339(),
+        truncated: exporter.truncated,
+        workflow: ExportedWorkflow {
+            status: workflow.state.label().to_string(),
+            workflow_id: workflow.id,
+            workflow_type: workflow.workflow_type,
+            error,
+            terminated_by,
+            priority: workflow.priority.as_str().to_string(),
+            version: workflow.definition_version,
+            parent_workflow_id: workflow.parent_workflow_id,
+            child_workflow_ids,
+            started_at: workflow.started_at.to_rfc3339(),
+            updated_at: workflow.updated_at.to_rfc3339(),
+            completed_at,
+            input,
+            output,
+        },
+        transitions,
+        steps,
+        events,
+    }))
+}
+
+/// 使用 workflow 记录的状态变化，同为 RUNNING 的 step 切换不导出
+fn recorded_transitions(workflow: &Workflow) -> Vec<StateTransition> {
+    let mut transitions = vec![StateTransition {
+        status: "RUNNING".to_string(),
+        at: workflow.started_at.to_rfc3339(),
+    }];
+    transitions.extend(
+        workflow
+            .history
+            .iter()
+            .filter(|t| t.from != t.to && t.from != "PENDING")
+            .map(|t| StateTransition {
+                status: t.to.clone(),
+                at: t.at.to_rfc3339(),
+            }),
+    );
+    transitions
+}
+
+/// 没有状态变化记录（旧版本创建）的 workflow 由事件推算：启动时进入 RUNNING；事件日志中的 workflow 结束事件各对应一次状态变化，
+/// 之后又有 step 开始说明 workflow 被重试，重新进入 RUNNING
+fn transitions(
+    state: &WorkflowState,
+    started_at: DateTime<Utc>,
+    completed_at: Option<&str>,
+    history: &[WorkflowEvent],
+) -> Vec<StateTransition> {
+    let mut transitions = vec![StateTransition {
+        status: "RUNNING".to_string(),
+        at: started_at.to_rfc3339(),
+    }];
+    for event in history {
+        let status = match event.event_type {
+            EventType::WorkflowCompleted => "COMPLETED",
+            EventType::WorkflowFailed => "FAILED",
+            EventType::WorkflowCancelled => "CANCELLED",
+            EventType::WorkflowTerminated => "TERMINATED",
+            EventType::StepStarted if transitions.last().is_some_and(|t| t.status != "RUNNING") => {
+                "RUNNING"
+            }
+            _ => continue,
+        };
+        let at = event.timestamp.to_rfc3339();
+        transitions.push(StateTransition {
+            status: status.to_string(),
+            at,
+        });
+    }
+
+    // 事件日志已丢弃结束事件时，用当前状态补上
+    let current = state.label();
+    if state.is_terminal() && transitions.last().is_some_and(|t| t.status != current) {
+        transitions.push(StateTransition {
+            status: current.to_string(),
+            at: completed_at.unwrap_or_default().to_string(),
+        });
+    }
+    transitions
+}
+
+/// 转换负载并记录是否截断
+struct Exporter {
+    max_payload_bytes: Option<usize>,
+    truncated: bool,
+}
+
+impl Exporter {
+    fn payload(&mut self, payload: &Payload) -> ExportedPayload {
+        let size = payload.data.len();
+        let mut exported = ExportedPayload {
+            content_type: payload.content_type.clone(),
+            size,
+            json: None,
+            base64: None,
+            truncated: false,
+        };
+        match self.max_payload_bytes {
+            Some(max) if size > max => {
+                self.truncated = true;
+                exported.base64 = Some(BASE64.encode(&payload.data[..max]));
+                exported.truncated = true;
+            }
+            _ => match payload.as_json() {
+                Some(value) => exported.json = Some(value),
+                None => exported.base64 = Some(BASE64.encode(&payload.data)),
+            },
+        }
+        exported
+    }
+
+    fn step(&mut self, step: StepExecution) -> ExportedStep {
+        let error = match &step.status {
+            StepExecutionStatus::Failed { error } => Some(error.clone()),
+            _ => None,
+        };
+        // 未记录开始的 step 没有输入
+        let input = step.started_at.map(|_| self.payload(&step.input));
+        ExportedStep {
+            status: step.status.to_string().to_uppercase(),
+            attempt: step.attempt,
+            error,
+            started_at: step.started_at.map(|t| t.to_rfc3339()),
+            completed_at: step.completed_at.map(|t| t.to_rfc3339()),
+            dependencies: step.dependencies,
+            input,
+            output: step.output.map(|output| self.payload(&output)),
+            progress: step.progress.map(|progress| ExportedProgress {
+                percent: progress.percent,
+                message: progress.message,
+                details: progress.details.map(|details| self.payload(&details)),
+            }),
+            last_heartbeat_at: step.last_heartbeat_at.map(|t| t.to_rfc3339()),
+            cache_hit: step.cache_hit,
+            step_name: step.step_name,
+        }
+    }
+
+    fn event(&mut self, mut event: WorkflowEvent) -> ExportedEvent {
+        let payload = match &mut event.payload {
+            EventPayload::StepStarted(started) => Some(&mut started.input),
+            EventPayload::StepCompleted(completed) => Some(&mut completed.output),
+            EventPayload::StepProgress(progress) => progress.details.as_mut(),
+            EventPayload::WorkflowCompleted(completed) => Some(&mut completed.result),
+            EventPayload::SignalReceived(signal) => Some(&mut signal.payload),
+            _ => None,
+        };
+        if let (Some(payload), Some(max)) = (payload, self.max_payload_bytes) {
+            if payload.data.len() > max {
+                self.truncated = true;
+                *payload = Payload::new(payload.data[..max].to_vec(), payload.content_type.clone());
+            }
+        }
+
+        let mut payload = serde_json::to_value(&event.payload).unwrap_or_default();
+        if let Some(fields) = payload.as_object_mut() {
+            fields.remove("event_type");
+        }
+        ExportedEvent {
+            event_type: event.event_type.as_str().to_string(),
+            timestamp: event.timestamp.timestamp() as u64,
+            payload,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::l0_memory::L0MemoryStore;
+    use crate::scheduler::StartOptions;
+    use crate::task::RetryPolicy;
+
+    async fn scheduler() -> Scheduler<L0MemoryStore> {
+        let scheduler = Scheduler::new(L0MemoryStore::new());
+        scheduler
+            .register_definition(crate::definition::WorkflowDefinition::new(
+                "order",
+                vec![
+                    crate::definition::StepDefinition::new("charge").with_retry(RetryPolicy {
+                        max_attempts: 2,
+                        initial_interval: 0,
+                        backoff_multiplier: 1.0,
+                    }),
+                ],
+            ))
+            .await
+            .unwrap();
+        scheduler
+            .register_worker(
+                "worker-1".to_string(),
+                "shop".to_string(),
+                "default".to_string(),
+                vec!["order".to_string()],
+                vec![],
+            )
+            .await;
+        scheduler
+    }
+
+    #[tokio::test]
+    async fn test_export_records_retries_events_and_output() {
+        let scheduler = scheduler().await;
+        let workflow_id = scheduler
+            .start_workflow(
+                None,
+                "order".to_string(),
+                br#"{"id":1}"#.to_vec(),
+                StartOptions::default(),
+            )
+            .await
+            .unwrap()
+            .workflow
+            .id;
+
+        // 第一次失败，重试后完成
+        for outcome in [Err("card declined"), Ok(br#"{"charged":true}"#.to_vec())] {
+            let task = scheduler.poll_tasks("worker-1", 1).await.unwrap().remove(0);
+            let lifecycle = scheduler.lifecycle();
+            lifecycle
+                .step_started(&workflow_id, "charge", task.input.data.clone())
+                .await
+                .unwrap();
+            match outcome {
+                Ok(output) => {
+                    lifecycle
+                        .complete_task(&task.task_id, Some("worker-1"), output)
+                        .await
+                }
+                Err(error) => {
+                    lifecycle
+                        .fail_task(&task.task_id, Some("worker-1"), error.to_string())
+                        .await
+                }
+            }
+            .unwrap();
+        }
+
+        let export = export_workflow(&scheduler, &workflow_id, &ExportOptions::default())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!export.truncated);
+        assert_eq!(export.workflow.status, "COMPLETED");
+        assert_eq!(
+            export.workflow.input.json,
+            Some(serde_json::json!({"id": 1}))
+        );
+        assert_eq!(
+            export.workflow.output.as_ref().unwrap().json,
+            Some(serde_json::json!({"charged": true}))
+        );
+        let statuses: Vec<_> = export
+            .transitions
+            .iter()
+            .map(|t| t.status.as_str())
+            .collect();
+        assert_eq!(statuses, ["RUNNING", "COMPLETED"]);
+        assert_eq!(export.steps.len(), 1);
+        assert_eq!(export.steps[0].attempt, 2);
+        let events: Vec<_> = export
+            .events
+            .iter()
+            .map(|e| e.event_type.as_str())
+            .collect();
+        assert_eq!(
+            events,
+            [
+                "step_started",
+                "step_failed",
+                "step_started",
+                "step_completed",
+                "workflow_completed"
+            ]
+        );
+
+        // JSONL：workflow、状态变化、step、事件各一行
+        let jsonl = export.to_jsonl().unwrap();
+        assert_eq!(jsonl.lines().count(), 1 + 2 + 1 + 5);
+        let header: Value = serde_json::from_str(jsonl.lines().next().unwrap()).unwrap();
+        assert_eq!(header["record"], "workflow");
+        assert_eq!(header["formatVersion"], EXPORT_FORMAT_VERSION);
+    }
+
+    #[tokio::test]
+    async fn test_large_payloads_are_truncated() {
+        let scheduler = scheduler().await;
+        let input = vec![7u8; 100];
+        let workflow_id = scheduler
+            .start_workflow(None, "order".to_string(), input, StartOptions::default())
+            .await
+            .unwrap()
+            .workflow
+            .id;
+
+        let options = ExportOptions {
+            max_payload_bytes: Some(10),
+        };
+        let export = export_workflow(&scheduler, &workflow_id, &options)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(export.truncated);
+        let input = export.workflow.input;
+        assert!(input.truncated);
+        assert_eq!(input.size, 100);
+        assert_eq!(BASE64.decode(input.base64.unwrap()).unwrap(), vec![7u8; 10]);
+
+        assert!(export_workflow(&scheduler, "missing", &options)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
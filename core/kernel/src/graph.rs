@@ -0,0 +1,298 @@
+//! Workflow 拓扑图
+//!
+//! 由 workflow 启动时固定的定义版本给出全部 step 和依赖边，再合并追踪器中的执行状态：
+//! 尚未开始的 step 状态为 `pending`，定义之外执行过的 step（如动态 workflow 的 `start`）
+//! 按追踪器记录的依赖加入图中。dashboard 的 `GetWorkflowGraph` 和 `GET /workflows/{id}/graph`
+//! 都返回这里生成的图。
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::definition::WorkflowDefinition;
+use crate::persistence::Persistence;
+use crate::scheduler::Scheduler;
+use crate::time;
+use crate::tracker::{StepExecution, StepExecutionStatus, WorkflowExecution, WorkflowTracker};
+
+/// 图中的一个 step
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct GraphNode {
+    pub step_name: String,
+    /// `pending`、`running`、`completed`、`failed` 或 `cancelled`
+    pub status: String,
+    /// 第几次尝试，尚未开始时为 0
+    pub attempt: u32,
+    pub started_at: Option<u64>,
+    pub completed_at: Option<u64>,
+    /// 已结束的 step 为执行耗时，运行中的 step 为目前已运行的时长
+    pub duration_ms: Option<u64>,
+}
+
+impl GraphNode {
+    /// 由追踪器中的执行记录生成节点，没有记录时为尚未开始的节点
+    pub fn from_execution(step_name: &str, execution: Option<&StepExecution>) -> Self {
+        let Some(step) = execution else {
+            return GraphNode {
+                step_name: step_name.to_string(),
+                status: StepExecutionStatus::Pending.to_string(),
+                attempt: 0,
+                started_at: None,
+                completed_at: None,
+                duration_ms: None,
+            };
+        };
+        let end = match step.status {
+            StepExecutionStatus::Running => Some(Utc::now()),
+            _ => step.completed_at,
+        };
+        let duration_ms = match (step.started_at, end) {
+            (Some(started), Some(end)) => {
+                time::duration_between(started, end).map(|duration| duration.as_millis() as u64)
+            }
+            _ => None,
+        };
+        GraphNode {
+            step_name: step_name.to_string(),
+            status: step.status.to_string(),
+            attempt: step.attempt,
+            started_at: step.started_at.map(|t| t.timestamp() as u64),
+            completed_at: step.completed_at.map(|t| t.timestamp() as u64),
+            duration_ms,
+        }
+    }
+}
+
+/// 依赖边：`to` 在 `from` 完成之后执行
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct GraphEdge {
+    pub from: String,
+    pub to: String,
+}
+
+/// Workflow 的 step 依赖图
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct WorkflowGraph {
+    pub workflow_id: String,
+    pub workflow_type: String,
+    /// 生成图所用的定义版本，没有定义时为 `None`
+    pub version: Option<u32>,
+    /// 先按定义中的顺序，再按开始时间排列定义之外的 step
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+impl WorkflowGraph {
+    /// 合并定义和执行记录生成图
+    pub fn build(
+        workflow_id: &str,
+        workflow_type: &str,
+        definition: Option<&WorkflowDefinition>,
+        execution: Option<&WorkflowExecution>,
+    ) -> Self {
+        let executed = |name: &str| execution.and_then(|e| e.step_executions.get(name));
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        if let Some(definition) = definition {
+            for step in &definition.steps {
+                nodes.push(GraphNode::from_execution(&step.name, executed(&step.name)));
+                edges.extend(
+                    definition
+                        .dependencies(&step.name)
+                        .into_iter()
+                        .map(|dependency| GraphEdge {
+                            from: dependency,
+                            to: step.name.clone(),
+                        }),
+                );
+            }
+        }
+
+        let mut extra: Vec<&StepExecution> = execution
+            .iter()
+            .flat_map(|e| e.step_executions.values())
+            .filter(|step| definition.and_then(|d| d.step(&step.step_name)).is_none())
+            .collect();
+        extra.sort_by_key(|step| (step.started_at.is_none(), step.started_at, &step.step_name));
+        for step in extra {
+            nodes.push(GraphNode::from_execution(&step.step_name, Some(step)));
+            edges.extend(step.dependencies.iter().map(|dependency| GraphEdge {
+                from: dependency.clone(),
+                to: step.step_name.clone(),
+            }));
+        }
+
+        WorkflowGraph {
+            workflow_id: workflow_id.to_string(),
+            workflow_type: workflow_type.to_string(),
+            version: definition.map(|d| d.version),
+            nodes,
+            edges,
+        }
+    }
+}
+
+/// 按 workflow id 生成拓扑图
+///
+/// 调度器使用 workflow 固定的定义版本；只有追踪器时（如单独运行的 dashboard 服务器）
+/// 图中只包含已执行的 step。
+#[async_trait::async_trait]
+pub trait GraphSource: Send + Sync {
+    /// workflow 不存在时返回 `None`
+    async fn workflow_graph(&self, workflow_id: &str) -> anyhow::Result<Option<WorkflowGraph>>;
+}
+
+#[async_trait::async_trait]
+impl GraphSource for WorkflowTracker {
+    async fn workflow_graph(&self, workflow_id: &str) -> anyhow::Result<Option<WorkflowGraph>> {
+        Ok(self.get_execution(workflow_id).await.map(|execution| {
+            WorkflowGraph::build(
+                workflow_id,
+                &execution.workflow_type,
+                None,
+                Some(&execution),
+            )
+        }))
+    }
+}
+
+#[async_trait::async_trait]
+impl<P: Persistence + 'static> GraphSource for Scheduler<P> {
+    async fn workflow_graph(&self, workflow_id: &str) -> anyhow::Result<Option<WorkflowGraph>> {
+        let execution = self.tracker.get_execution(workflow_id).await;
+        let (workflow_type, definition) = match self.persistence.get_workflow(workflow_id).await? {
+            Some(workflow) => {
+                let definition = self.workflow_definition(&workflow).await;
+                (workflow.workflow_type, definition)
+            }
+            None => match &execution {
+                Some(execution) => (
+                    execution.workflow_type.clone(),
+                    self.definition(&execution.workflow_type).await,
+                ),
+                None => return Ok(None),
+            },
+        };
+        Ok(Some(WorkflowGraph::build(
+            workflow_id,
+            &workflow_type,
+            definition.as_ref(),
+            execution.as_ref(),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::StepDefinition;
+    use crate::persistence::l0_memory::L0MemoryStore;
+    use crate::state_machine::Workflow;
+    use std::sync::Arc;
+
+    fn edges(graph: &WorkflowGraph) -> Vec<(&str, &str)> {
+        graph
+            .edges
+            .iter()
+            .map(|edge| (edge.from.as_str(), edge.to.as_str()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_graph_merges_pinned_definition_with_tracker_state() {
+        let scheduler = Scheduler::new(Arc::new(L0MemoryStore::new()));
+        scheduler
+            .register_definition(WorkflowDefinition::new(
+                "order",
+                vec![
+                    StepDefinition::new("reserve"),
+                    StepDefinition::new("charge").depends_on(["reserve"]),
+                    StepDefinition::new("ship").depends_on(["reserve"]),
+                    StepDefinition::new("notify").depends_on(["charge", "ship"]),
+                ],
+            ))
+            .await
+            .unwrap();
+        let workflow = Workflow::new("wf-1".to_string(), "order".to_string(), vec![])
+            .with_definition_version(1);
+        scheduler
+            .persistence
+            .save_workflow(&workflow)
+            .await
+            .unwrap();
+        scheduler
+            .tracker
+            .start_workflow("wf-1".to_string(), "order".to_string())
+            .await;
+        scheduler
+            .tracker
+            .step_started("wf-1", "reserve", vec![], vec![])
+            .await
+            .unwrap();
+        scheduler
+            .tracker
+            .step_completed("wf-1", "reserve", vec![])
+            .await;
+        scheduler
+            .tracker
+            .step_started("wf-1", "charge", vec![], vec!["reserve".to_string()])
+            .await
+            .unwrap();
+
+        // 新版本不影响已启动的 workflow
+        scheduler
+            .register_definition(
+                WorkflowDefinition::new("order", vec![StepDefinition::new("only")]).with_version(2),
+            )
+            .await
+            .unwrap();
+
+        let graph = scheduler.workflow_graph("wf-1").await.unwrap().unwrap();
+        assert_eq!(graph.version, Some(1));
+        let statuses: Vec<(&str, &str, u32)> = graph
+            .nodes
+            .iter()
+            .map(|node| (node.step_name.as_str(), node.status.as_str(), node.attempt))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("reserve", "completed", 1),
+                ("charge", "running", 1),
+                ("ship", "pending", 0),
+                ("notify", "pending", 0),
+            ]
+        );
+        assert!(graph.nodes[0].duration_ms.is_some());
+        assert!(graph.nodes[2].duration_ms.is_none());
+        assert_eq!(
+            edges(&graph),
+            vec![
+                ("reserve", "charge"),
+                ("reserve", "ship"),
+                ("charge", "notify"),
+                ("ship", "notify"),
+            ]
+        );
+
+        assert!(scheduler.workflow_graph("missing").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_graph_without_definition_uses_tracked_steps() {
+        let tracker = WorkflowTracker::new();
+        tracker
+            .start_workflow("wf-1".to_string(), "dynamic".to_string())
+            .await;
+        tracker
+            .step_started("wf-1", "start", vec![], vec![])
+            .await
+            .unwrap();
+
+        let graph = tracker.workflow_graph("wf-1").await.unwrap().unwrap();
+        assert_eq!(graph.version, None);
+        assert_eq!(graph.nodes.len(), 1);
+        assert_eq!(graph.nodes[0].step_name, "start");
+        assert_eq!(graph.nodes[0].status, "running");
+        assert!(graph.edges.is_empty());
+    }
+}
@@ -0,0 +1,349 @@
+//! gRPC 请求认证
+//!
+//! 请求在 `authorization` metadata 中携带 `Bearer <token>`，令牌可以是：
+//! - 配置的静态令牌，可访问全部服务
+//! - worker 通过 `Register` 获得的会话令牌，只能访问 `WorkerService`，并且只能以该 worker 的身份操作
+//! - 配置的引导令牌，只能调用 `Register` 换取会话令牌
+//!
+//! 未配置任何令牌时不做认证。只配置引导令牌时 client/admin 服务没有可用的令牌，服务器拒绝启动。
+//! 认证通过的身份以 [`Principal`] 写入请求 extensions。
+
+// tonic::Status 体积较大，但拦截器和 handler 都直接返回它
+#![allow(clippy::result_large_err)]
+
+use std::fmt;
+use std::sync::Arc;
+
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+
+use crate::api::auth::AuthConfig;
+use crate::session::SessionStore;
+
+/// 携带令牌的 metadata 键
+pub const AUTHORIZATION_METADATA: &str = "authorization";
+
+/// gRPC 接受的令牌，未配置任何令牌时不做认证
+#[derive(Debug, Clone, Default)]
+pub struct GrpcAuthConfig {
+    tokens: AuthConfig,
+    bootstrap_tokens: AuthConfig,
+}
+
+impl GrpcAuthConfig {
+    pub fn new(tokens: Vec<String>) -> Self {
+        Self {
+            tokens: AuthConfig::new(tokens),
+            bootstrap_tokens: AuthConfig::default(),
+        }
+    }
+
+    /// 额外接受的引导令牌，只能用于 worker 注册
+    pub fn with_bootstrap_tokens(mut self, tokens: Vec<String>) -> Self {
+        self.bootstrap_tokens = AuthConfig::new(tokens);
+        self
+    }
+
+    /// 请求是否必须携带令牌
+    pub fn is_enabled(&self) -> bool {
+        self.tokens.is_enabled() || self.bootstrap_tokens.is_enabled()
+    }
+
+    /// 检查配置：引导令牌必须和静态令牌一起配置，否则 client/admin 服务会拒绝所有请求
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.bootstrap_tokens.is_enabled() && !self.tokens.is_enabled() {
+            anyhow::bail!(
+                "gRPC bootstrap tokens require at least one gRPC auth token; \
+                 without one no ClientService or AdminService call could authenticate"
+            );
+        }
+        Ok(())
+    }
+}
+
+/// 认证通过的调用方
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Principal {
+    /// 静态令牌
+    Token,
+    /// 持有会话令牌的 worker
+    Worker(String),
+    /// 引导令牌，只能注册 worker
+    Bootstrap,
+}
+
+impl fmt::Display for Principal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Principal::Token => write!(f, "token"),
+            Principal::Worker(worker_id) => write!(f, "worker:{}", worker_id),
+            Principal::Bootstrap => write!(f, "bootstrap"),
+        }
+    }
+}
+
+/// 校验 bearer 令牌的拦截器，每个服务一个实例
+#[derive(Clone)]
+pub struct AuthInterceptor {
+    config: Arc<GrpcAuthConfig>,
+    sessions: Arc<SessionStore>,
+    /// 是否接受 worker 会话令牌和引导令牌，只有 `WorkerService` 开启
+    workers: bool,
+}
+
+impl AuthInterceptor {
+    /// `ClientService` 和 `AdminService` 用，只接受静态令牌
+    pub fn new(config: Arc<GrpcAuthConfig>, sessions: Arc<SessionStore>) -> Self {
+        Self {
+            config,
+            sessions,
+            workers: false,
+        }
+    }
+
+    /// `WorkerService` 用，同时接受会话令牌和引导令牌
+    pub fn for_workers(config: Arc<GrpcAuthConfig>, sessions: Arc<SessionStore>) -> Self {
+        Self {
+            workers: true,
+            ..Self::new(config, sessions)
+        }
+    }
+
+    fn authenticate(&self, token: &str) -> Option<Principal> {
+        if self.config.tokens.verify(token) {
+            return Some(Principal::Token);
+        }
+        if !self.workers {
+            return None;
+        }
+        // 被移除的 worker 的令牌已在移除时吊销
+        if let Some(worker_id) = self.sessions.worker_id(token) {
+            return Some(Principal::Worker(worker_id));
+        }
+        self.config
+            .bootstrap_tokens
+            .verify(token)
+            .then_some(Principal::Bootstrap)
+    }
+}
+
+impl Interceptor for AuthInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        if !self.config.is_enabled() {
+            return Ok(request);
+        }
+        let token = request
+            .metadata()
+            .get(AUTHORIZATION_METADATA)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| Status::unauthenticated("Missing bearer token"))?;
+        let principal = self
+            .authenticate(token)
+            .ok_or_else(|| Status::unauthenticated("Invalid or expired token"))?;
+        request.extensions_mut().insert(principal);
+        Ok(request)
+    }
+}
+
+/// 请求的调用方，并记录到当前 `grpc_request` span；未启用认证时为 `None`
+pub fn principal<T>(request: &Request<T>) -> Option<Principal> {
+    let principal = request.extensions().get::<Principal>().cloned();
+    if let Some(principal) = &principal {
+        tracing::Span::current().record("principal", tracing::field::display(principal));
+    }
+    principal
+}
+
+/// 拒绝引导令牌，用于 `Register` 以外的 worker 请求
+pub fn reject_bootstrap(principal: Option<&Principal>) -> Result<(), Status> {
+    match principal {
+        Some(Principal::Bootstrap) => Err(Status::permission_denied(
+            "Bootstrap token may only register workers",
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// 请求代表的 worker id
+///
+/// 会话令牌只能以签发时的 worker 身份操作，请求未填写 `worker_id` 时使用会话的 worker id。
+pub fn authorize_worker(principal: Option<&Principal>, worker_id: &str) -> Result<String, Status> {
+    reject_bootstrap(principal)?;
+    match principal {
+        Some(Principal::Worker(session)) if worker_id.is_empty() => Ok(session.clone()),
+        Some(Principal::Worker(session)) if session != worker_id => {
+            Err(Status::permission_denied(format!(
+                "Session token belongs to worker {}, not {}",
+                session, worker_id
+            )))
+        }
+        _ => Ok(worker_id.to_string()),
+    }
+}
+
+/// `Register` 注册的 worker id
+///
+/// 会话令牌只能重新注册自己的 worker；引导令牌不能占用仍在使用的 worker id（`in_use`），
+/// 否则持有它的调用方可以换取该 worker 的会话令牌。未填写 `worker_id` 时由服务器生成。
+pub fn authorize_registration(
+    principal: Option<&Principal>,
+    worker_id: &str,
+    in_use: bool,
+) -> Result<String, Status> {
+    match principal {
+        Some(Principal::Worker(session)) if worker_id.is_empty() || session == worker_id => {
+            Ok(session.clone())
+        }
+        Some(Principal::Worker(session)) => Err(Status::permission_denied(format!(
+            "Session token belongs to worker {}, not {}",
+            session, worker_id
+        ))),
+        Some(Principal::Bootstrap) if in_use => Err(Status::already_exists(format!(
+            "Worker {} is already registered; re-register with its session token",
+            worker_id
+        ))),
+        _ if worker_id.is_empty() => Ok(uuid::Uuid::new_v4().to_string()),
+        _ => Ok(worker_id.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Code;
+
+    fn call(interceptor: &mut AuthInterceptor, token: Option<&str>) -> Result<Principal, Status> {
+        let mut request = Request::new(());
+        if let Some(token) = token {
+            request.metadata_mut().insert(
+                AUTHORIZATION_METADATA,
+                format!("Bearer {}", token).parse().unwrap(),
+            );
+        }
+        interceptor
+            .call(request)
+            .map(|request| request.extensions().get::<Principal>().cloned().unwrap())
+    }
+
+    #[test]
+    fn test_interceptor_resolves_principals() {
+        let config = Arc::new(
+            GrpcAuthConfig::new(vec!["static".to_string()])
+                .with_bootstrap_tokens(vec!["boot".to_string()]),
+        );
+        let sessions = Arc::new(SessionStore::new());
+        let session = sessions.issue("worker-1");
+        let mut workers = AuthInterceptor::for_workers(config.clone(), sessions.clone());
+        let mut clients = AuthInterceptor::new(config, sessions.clone());
+
+        assert_eq!(
+            call(&mut workers, Some("static")).unwrap(),
+            Principal::Token
+        );
+        assert_eq!(
+            call(&mut workers, Some(&session)).unwrap(),
+            Principal::Worker("worker-1".to_string())
+        );
+        assert_eq!(
+            call(&mut workers, Some("boot")).unwrap(),
+            Principal::Bootstrap
+        );
+        assert_eq!(
+            call(&mut clients, Some("static")).unwrap(),
+            Principal::Token
+        );
+
+        // 会话令牌和引导令牌不能访问 client/admin 服务
+        for token in [None, Some("unknown"), Some(session.as_str()), Some("boot")] {
+            let err = call(&mut clients, token).unwrap_err();
+            assert_eq!(err.code(), Code::Unauthenticated);
+        }
+
+        sessions.revoke("worker-1");
+        let err = call(&mut workers, Some(&session)).unwrap_err();
+        assert_eq!(err.code(), Code::Unauthenticated);
+    }
+
+    #[test]
+    fn test_bootstrap_tokens_require_a_static_token() {
+        let bootstrap_only = GrpcAuthConfig::default().with_bootstrap_tokens(vec!["boot".into()]);
+        let err = bootstrap_only.validate().unwrap_err();
+        assert!(
+            err.to_string().contains("at least one gRPC auth token"),
+            "{}",
+            err
+        );
+        assert!(GrpcAuthConfig::default().validate().is_ok());
+        assert!(GrpcAuthConfig::new(vec!["static".into()])
+            .with_bootstrap_tokens(vec!["boot".into()])
+            .validate()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_disabled_auth_passes_requests_through() {
+        let mut interceptor = AuthInterceptor::for_workers(
+            Arc::new(GrpcAuthConfig::new(vec![String::new()])),
+            Arc::new(SessionStore::new()),
+        );
+        let request = interceptor.call(Request::new(())).unwrap();
+        assert!(request.extensions().get::<Principal>().is_none());
+    }
+
+    #[test]
+    fn test_worker_session_is_bound_to_its_worker_id() {
+        let worker = Principal::Worker("worker-1".to_string());
+        assert_eq!(authorize_worker(Some(&worker), "").unwrap(), "worker-1");
+        assert_eq!(
+            authorize_worker(Some(&worker), "worker-1").unwrap(),
+            "worker-1"
+        );
+        let err = authorize_worker(Some(&worker), "worker-2").unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+
+        assert_eq!(
+            authorize_worker(Some(&Principal::Token), "worker-2").unwrap(),
+            "worker-2"
+        );
+        assert_eq!(authorize_worker(None, "worker-2").unwrap(), "worker-2");
+        let err = authorize_worker(Some(&Principal::Bootstrap), "worker-1").unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+    }
+
+    #[test]
+    fn test_registration_cannot_take_over_a_live_worker_id() {
+        let worker = Principal::Worker("worker-1".to_string());
+        assert_eq!(
+            authorize_registration(Some(&worker), "", true).unwrap(),
+            "worker-1"
+        );
+        assert_eq!(
+            authorize_registration(Some(&worker), "worker-1", true).unwrap(),
+            "worker-1"
+        );
+        let err = authorize_registration(Some(&worker), "worker-2", true).unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+
+        let bootstrap = Principal::Bootstrap;
+        let err = authorize_registration(Some(&bootstrap), "worker-1", true).unwrap_err();
+        assert_eq!(err.code(), Code::AlreadyExists);
+        assert_eq!(
+            authorize_registration(Some(&bootstrap), "worker-1", false).unwrap(),
+            "worker-1"
+        );
+        assert!(!authorize_registration(Some(&bootstrap), "", false)
+            .unwrap()
+            .is_empty());
+
+        // 静态令牌和未启用认证时可以重新注册任意 worker
+        assert_eq!(
+            authorize_registration(Some(&Principal::Token), "worker-1", true).unwrap(),
+            "worker-1"
+        );
+        assert_eq!(
+            authorize_registration(None, "worker-1", true).unwrap(),
+            "worker-1"
+        );
+    }
+}
@@ -0,0 +1,2572 @@
+//! gRPC 服务器
+//!
+//! 实现 `proto/aether.proto` 中定义的 ClientService、WorkerService 和 AdminService。
+//! 所有 step 状态变更都交给 [`StepLifecycle`](crate::step_lifecycle::StepLifecycle) 处理。
+
+// tonic::Status 体积较大，但 tonic 的接口要求直接返回它
+#![allow(clippy::result_large_err)]
+
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+use tonic::metadata::{MetadataMap, MetadataValue};
+use tonic::server::NamedService;
+use tonic::service::interceptor::InterceptedService;
+use tonic::transport::server::TcpIncoming;
+use tonic::{Code, Request, Response, Status};
+
+use crate::cancellation::{CancelError, TerminateError};
+use crate::child::ChildWorkflowSpec;
+use crate::definition::{DefinitionError, StepDefinition, WorkflowDefinition};
+use crate::export::{self, ExportFormat, ExportOptions};
+use crate::grpc_auth::{
+    self, authorize_worker, reject_bootstrap, AuthInterceptor, GrpcAuthConfig, Principal,
+};
+use crate::health::{report_health, HEALTH_CHECK_INTERVAL};
+use crate::import::{ImportError, ImportOptions};
+use crate::memo::validate_memo;
+use crate::payload::{Payload, PayloadTooLarge};
+use crate::persistence::{
+    ListOptions, OrderBy, Persistence, PurgeFilter, StateKind, TerminalState,
+};
+use crate::proto;
+use crate::proto::admin_service_server::{AdminService, AdminServiceServer};
+use crate::proto::client_service_server::{ClientService, ClientServiceServer};
+use crate::proto::worker_service_server::{WorkerService, WorkerServiceServer};
+use crate::retention::DeleteError;
+use crate::retry::RetryError;
+use crate::schedule::{OverlapPolicy, Schedule, ScheduleError, ScheduleSpec};
+use crate::scheduler::{Scheduler, StartOptions};
+use crate::schema;
+use crate::server_info::ServerInfo;
+use crate::signal::SignalError;
+use crate::state_machine::{DurationStats, Priority, Workflow, WorkflowState};
+use crate::step_lifecycle::{parse_task_id, StepLifecycleError};
+use crate::task::{ResourceMetadata, ResourceType, RetryPolicy, ServiceResource, Task, TaskId};
+use crate::telemetry::grpc_request_span;
+use crate::tracker::StepProgress;
+
+type ResponseStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send + 'static>>;
+
+/// 错误码所在的 metadata 键，取值与 REST 错误响应中的 `code` 相同
+pub const ERROR_CODE_METADATA: &str = "aether-error-code";
+
+/// 在 metadata 中附带错误码的 status，客户端不必解析错误信息就能区分同一 gRPC code 下的错误
+fn status_with_error_code(code: Code, error_code: &'static str, message: String) -> Status {
+    let mut metadata = MetadataMap::new();
+    metadata.insert(ERROR_CODE_METADATA, MetadataValue::from_static(error_code));
+    Status::with_metadata(code, message, metadata)
+}
+
+impl From<StepLifecycleError> for Status {
+    fn from(e: StepLifecycleError) -> Self {
+        match &e {
+            StepLifecycleError::InvalidTaskId(_) => Status::invalid_argument(e.to_string()),
+            StepLifecycleError::WorkflowNotFound(_) => Status::not_found(e.to_string()),
+            StepLifecycleError::TaskNotOwned(_) => {
+                status_with_error_code(Code::FailedPrecondition, "NOT_OWNER", e.to_string())
+            }
+            StepLifecycleError::AlreadyCompleted(_) => {
+                status_with_error_code(Code::AlreadyExists, "ALREADY_COMPLETED", e.to_string())
+            }
+            StepLifecycleError::UnknownWorkflowType(_) => Status::invalid_argument(e.to_string()),
+            StepLifecycleError::WorkflowCancelled(_) => {
+                status_with_error_code(Code::Cancelled, "CANCELLED", e.to_string())
+            }
+            StepLifecycleError::WorkflowTerminated(_) => {
+                status_with_error_code(Code::Cancelled, "TERMINATED", e.to_string())
+            }
+            StepLifecycleError::SchemaValidation(_) => Status::invalid_argument(e.to_string()),
+            StepLifecycleError::PayloadTooLarge(_) => Status::resource_exhausted(e.to_string()),
+            StepLifecycleError::Persistence(_) => Status::internal(e.to_string()),
+        }
+    }
+}
+
+impl From<PayloadTooLarge> for Status {
+    fn from(e: PayloadTooLarge) -> Self {
+        Status::resource_exhausted(e.to_string())
+    }
+}
+
+impl From<TerminateError> for Status {
+    fn from(e: TerminateError) -> Self {
+        match e {
+            TerminateError::EmptyReason => Status::invalid_argument(e.to_string()),
+            TerminateError::WorkflowNotFound(_) => Status::not_found(e.to_string()),
+            TerminateError::AlreadyFinished(..) => Status::failed_precondition(e.to_string()),
+            TerminateError::Persistence(_) => Status::internal(e.to_string()),
+        }
+    }
+}
+
+impl From<RetryError> for Status {
+    fn from(e: RetryError) -> Self {
+        match e {
+            RetryError::WorkflowNotFound(_) => Status::not_found(e.to_string()),
+            RetryError::NotFailed(..) => Status::failed_precondition(e.to_string()),
+            RetryError::Persistence(_) => Status::internal(e.to_string()),
+        }
+    }
+}
+
+impl From<ImportError> for Status {
+    fn from(e: ImportError) -> Self {
+        match &e {
+            ImportError::Decode(_) | ImportError::Truncated(_) => {
+                Status::invalid_argument(e.to_string())
+            }
+            ImportError::AlreadyExists(_) => Status::already_exists(e.to_string()),
+            ImportError::NotResumable { .. } => Status::failed_precondition(e.to_string()),
+            ImportError::Persistence(_) => Status::internal(e.to_string()),
+        }
+    }
+}
+
+impl From<CancelError> for Status {
+    fn from(e: CancelError) -> Self {
+        match &e {
+            CancelError::WorkflowNotFound(_) => Status::not_found(e.to_string()),
+            CancelError::NotCancellable(..) => Status::failed_precondition(e.to_string()),
+            CancelError::Persistence(_) => Status::internal(e.to_string()),
+        }
+    }
+}
+
+impl From<DeleteError> for Status {
+    fn from(e: DeleteError) -> Self {
+        match &e {
+            DeleteError::WorkflowNotFound(_) => Status::not_found(e.to_string()),
+            DeleteError::NotTerminal(_) => Status::failed_precondition(e.to_string()),
+            DeleteError::Persistence(_) => Status::internal(e.to_string()),
+        }
+    }
+}
+
+impl From<ScheduleError> for Status {
+    fn from(e: ScheduleError) -> Self {
+        match &e {
+            ScheduleError::InvalidCron { .. } | ScheduleError::UnknownWorkflowType(_) => {
+                Status::invalid_argument(e.to_string())
+            }
+            ScheduleError::NotFound(_) => Status::not_found(e.to_string()),
+            ScheduleError::Persistence(_) => Status::internal(e.to_string()),
+        }
+    }
+}
+
+impl From<DefinitionError> for Status {
+    fn from(e: DefinitionError) -> Self {
+        match &e {
+            DefinitionError::VersionConflict(..) => Status::already_exists(e.to_string()),
+            DefinitionError::Persistence(_) => Status::internal(e.to_string()),
+            _ => Status::invalid_argument(e.to_string()),
+        }
+    }
+}
+
+impl From<SignalError> for Status {
+    fn from(e: SignalError) -> Self {
+        match &e {
+            SignalError::EmptyName => Status::invalid_argument(e.to_string()),
+            SignalError::WorkflowNotFound(_) => Status::not_found(e.to_string()),
+            SignalError::WorkflowFinished(_) => Status::failed_precondition(e.to_string()),
+            SignalError::Persistence(_) => Status::internal(e.to_string()),
+        }
+    }
+}
+
+impl From<ServerInfo> for proto::ServerInfo {
+    fn from(info: ServerInfo) -> Self {
+        proto::ServerInfo {
+            version: info.version,
+            features: info.features,
+            protocol_version: info.protocol_version,
+            subsystems: Some(proto::Subsystems {
+                auth: info.subsystems.auth,
+                namespaces: info.subsystems.namespaces,
+            }),
+            limits: Some(proto::ServerLimits {
+                max_payload_bytes: info.limits.max_payload_bytes,
+                max_batch_size: info.limits.max_batch_size,
+            }),
+        }
+    }
+}
+
+fn internal(e: anyhow::Error) -> Status {
+    Status::internal(e.to_string())
+}
+
+fn from_proto_overlap_policy(policy: proto::OverlapPolicy) -> OverlapPolicy {
+    match policy {
+        proto::OverlapPolicy::OverlapSkip => OverlapPolicy::Skip,
+        proto::OverlapPolicy::OverlapAllow => OverlapPolicy::Allow,
+        proto::OverlapPolicy::OverlapCancelPrevious => OverlapPolicy::CancelPrevious,
+    }
+}
+
+fn to_proto_schedule(schedule: Schedule) -> proto::ScheduleInfo {
+    let overlap_policy = match schedule.spec.overlap_policy {
+        OverlapPolicy::Skip => proto::OverlapPolicy::OverlapSkip,
+        OverlapPolicy::Allow => proto::OverlapPolicy::OverlapAllow,
+        OverlapPolicy::CancelPrevious => proto::OverlapPolicy::OverlapCancelPrevious,
+    };
+    proto::ScheduleInfo {
+        next_fire_at: schedule
+            .next_fire_after(Utc::now())
+            .map(|t| t.timestamp())
+            .unwrap_or_default(),
+        schedule_id: schedule.id,
+        cron: schedule.spec.cron,
+        workflow_type: schedule.spec.workflow_type,
+        input: schedule.spec.input,
+        overlap_policy: overlap_policy as i32,
+        created_at: schedule.created_at.timestamp(),
+        last_fired_at: schedule
+            .last_fired_at
+            .map(|t| t.timestamp())
+            .unwrap_or_default(),
+        last_workflow_id: schedule.last_workflow_id.unwrap_or_default(),
+    }
+}
+
+fn step_progress(progress: Option<proto::StepProgress>) -> StepProgress {
+    progress
+        .map(|p| StepProgress {
+            percent: p.percent,
+            message: p.message,
+            details: p.details.map(Payload::from),
+        })
+        .unwrap_or_default()
+}
+
+/// 客户端通过 `grpc-timeout` 头传递的 deadline
+fn grpc_timeout(metadata: &tonic::metadata::MetadataMap) -> Option<Duration> {
+    let value = metadata.get("grpc-timeout")?.to_str().ok()?;
+    let (amount, unit) = value.split_at(value.len().checked_sub(1)?);
+    let amount: u64 = amount.parse().ok()?;
+    match unit {
+        "H" => Some(Duration::from_secs(amount * 3600)),
+        "M" => Some(Duration::from_secs(amount * 60)),
+        "S" => Some(Duration::from_secs(amount)),
+        "m" => Some(Duration::from_millis(amount)),
+        "u" => Some(Duration::from_micros(amount)),
+        "n" => Some(Duration::from_nanos(amount)),
+        _ => None,
+    }
+}
+
+/// proto State 转换为列表过滤条件
+fn from_proto_state_kind(state: proto::State) -> StateKind {
+    match state {
+        proto::State::Pending => StateKind::Pending,
+        proto::State::Running => StateKind::Running,
+        proto::State::Completed => StateKind::Completed,
+        proto::State::Failed => StateKind::Failed,
+        proto::State::Cancelled => StateKind::Cancelled,
+        proto::State::Terminated => StateKind::Terminated,
+    }
+}
+
+/// 解析列表请求中的可选 State 过滤条件，ClientService 与 AdminService 共用
+fn state_filter(state: Option<i32>) -> Result<Option<StateKind>, Status> {
+    state
+        .map(|value| {
+            proto::State::try_from(value)
+                .map(from_proto_state_kind)
+                .map_err(|_| Status::invalid_argument(format!("Unknown state {}", value)))
+        })
+        .transpose()
+}
+
+fn list_order(order: i32) -> OrderBy {
+    match proto::ListOrder::try_from(order) {
+        Ok(proto::ListOrder::StartedAtDesc) => OrderBy::StartedAtDesc,
+        _ => OrderBy::StartedAtAsc,
+    }
+}
+
+/// 解析 unix 秒时间条件，0 表示不限
+fn time_filter(field: &str, seconds: i64) -> Result<Option<DateTime<Utc>>, Status> {
+    if seconds == 0 {
+        return Ok(None);
+    }
+    DateTime::from_timestamp(seconds, 0)
+        .map(Some)
+        .ok_or_else(|| Status::invalid_argument(format!("{} is out of range", field)))
+}
+
+/// WorkflowState 转换为 proto State
+fn to_proto_state(state: &WorkflowState) -> proto::State {
+    match state {
+        WorkflowState::Pending => proto::State::Pending,
+        WorkflowState::Running { .. } => proto::State::Running,
+        WorkflowState::Completed { .. } => proto::State::Completed,
+        WorkflowState::Failed { .. } => proto::State::Failed,
+        WorkflowState::Cancelled => proto::State::Cancelled,
+        WorkflowState::Terminated { .. } => proto::State::Terminated,
+    }
+}
+
+/// proto Priority 转换为调度优先级
+fn from_proto_priority(priority: proto::Priority) -> Priority {
+    match priority {
+        proto::Priority::Low => Priority::Low,
+        proto::Priority::Normal => Priority::Normal,
+        proto::Priority::High => Priority::High,
+    }
+}
+
+/// workflow 进入终态的时间（秒），未结束时为 0
+fn completed_at(workflow: &Workflow) -> i64 {
+    match workflow.state {
+        WorkflowState::Completed { .. }
+        | WorkflowState::Failed { .. }
+        | WorkflowState::Cancelled
+        | WorkflowState::Terminated { .. } => workflow.updated_at.timestamp(),
+        _ => 0,
+    }
+}
+
+fn to_resource_type(value: i32) -> ResourceType {
+    match proto::ResourceType::try_from(value) {
+        Ok(proto::ResourceType::Activity) => ResourceType::Activity,
+        Ok(proto::ResourceType::Workflow) => ResourceType::Workflow,
+        _ => ResourceType::Step,
+    }
+}
+
+/// proto 资源声明转换为注册表中的资源，未设置（为 0 或空）的元数据字段记为 `None`
+fn from_proto_resource(resource: &proto::ServiceResource) -> ServiceResource {
+    ServiceResource {
+        name: resource.name.clone(),
+        resource_type: to_resource_type(resource.r#type),
+        metadata: resource.metadata.as_ref().map(|m| ResourceMetadata {
+            max_attempts: Some(m.max_attempts).filter(|n| *n > 0).map(|n| n as u32),
+            timeout: Some(m.timeout).filter(|n| *n > 0).map(|n| n as u64),
+            input_schema: Some(m.input_schema.clone()).filter(|s| !s.is_empty()),
+            output_schema: Some(m.output_schema.clone()).filter(|s| !s.is_empty()),
+            cacheable: m.cacheable,
+        }),
+    }
+}
+
+fn to_proto_resource(resource: &ServiceResource) -> proto::ServiceResource {
+    proto::ServiceResource {
+        name: resource.name.clone(),
+        r#type: resource.resource_type as i32,
+        metadata: resource.metadata.as_ref().map(|m| proto::ResourceMetadata {
+            max_attempts: m.max_attempts.unwrap_or_default() as i32,
+            timeout: m.timeout.unwrap_or_default() as i32,
+            input_schema: m.input_schema.clone().unwrap_or_default(),
+            output_schema: m.output_schema.clone().unwrap_or_default(),
+            cacheable: m.cacheable,
+        }),
+    }
+}
+
+/// proto workflow 定义转换为调度器的定义，空字符串和 0 记为未设置
+pub(crate) fn from_proto_definition(definition: proto::WorkflowDefinition) -> WorkflowDefinition {
+    let non_empty = |value: String| Some(value).filter(|v| !v.is_empty());
+    let millis = |value: i64| {
+        Some(value)
+            .filter(|v| *v > 0)
+            .map(|v| Duration::from_millis(v as u64))
+    };
+    let steps = definition
+        .steps
+        .into_iter()
+        .map(|step| StepDefinition {
+            resource_type: to_resource_type(step.resource_type),
+            retry: step.retry.map(|r| RetryPolicy {
+                max_attempts: r.max_attempts.max(0) as u32,
+                initial_interval: r.initial_interval.max(0) as u64,
+                backoff_multiplier: if r.backoff_multiplier > 0 {
+                    r.backoff_multiplier as f64
+                } else {
+                    RetryPolicy::default().backoff_multiplier
+                },
+            }),
+            timeout: millis(step.timeout_ms),
+            external: step.external,
+            depends_on: step.depends_on,
+            wait_for_signal: non_empty(step.wait_for_signal),
+            timer: millis(step.timer_ms),
+            await_children: step.await_children,
+            cacheable: step.cacheable,
+            target_service: non_empty(step.target_service),
+            target_resource: non_empty(step.target_resource),
+            name: step.name,
+        })
+        .collect();
+    WorkflowDefinition {
+        name: definition.name,
+        version: definition.version.max(1),
+        steps,
+        output_step: non_empty(definition.output_step),
+        sticky: definition.sticky,
+    }
+}
+
+pub(crate) fn to_proto_definition(definition: &WorkflowDefinition) -> proto::WorkflowDefinition {
+    let millis = |value: Option<Duration>| value.map_or(0, |v| v.as_millis() as i64);
+    proto::WorkflowDefinition {
+        name: definition.name.clone(),
+        version: definition.version,
+        steps: definition
+            .steps
+            .iter()
+            .map(|step| proto::StepDefinition {
+                name: step.name.clone(),
+                depends_on: step.depends_on.clone(),
+                target_service: step.target_service.clone().unwrap_or_default(),
+                target_resource: step.target_resource.clone().unwrap_or_default(),
+                resource_type: step.resource_type as i32,
+                retry: step.retry.as_ref().map(|r| proto::RetryPolicy {
+                    max_attempts: r.max_attempts as i32,
+                    initial_interval: r.initial_interval as i32,
+                    backoff_multiplier: r.backoff_multiplier as i32,
+                }),
+                timeout_ms: millis(step.timeout),
+                external: step.external,
+                wait_for_signal: step.wait_for_signal.clone().unwrap_or_default(),
+                timer_ms: millis(step.timer),
+                await_children: step.await_children,
+                cacheable: step.cacheable,
+            })
+            .collect(),
+        output_step: definition.output_step.clone().unwrap_or_default(),
+        sticky: definition.sticky,
+    }
+}
+
+fn to_proto_task(task: Task) -> proto::Task {
+    proto::Task {
+        task_id: task.task_id,
+        workflow_id: task.workflow_id,
+        step_name: task.step_name,
+        target_service: task.target_service.unwrap_or_default(),
+        target_resource: task.target_resource.unwrap_or_default(),
+        resource_type: task.resource_type as i32,
+        input: task.input.into_bytes(),
+        retry: task.retry.map(|r| proto::RetryPolicy {
+            max_attempts: r.max_attempts as i32,
+            initial_interval: r.initial_interval as i32,
+            backoff_multiplier: r.backoff_multiplier as i32,
+        }),
+        workflow_type: task.workflow_type,
+        heartbeat_interval: task.heartbeat_interval as i64,
+        signal: task.signal.map(|signal| proto::Signal {
+            name: signal.name,
+            payload: signal.payload,
+            received_at: signal.received_at.timestamp(),
+        }),
+    }
+}
+
+// ========== ClientService ==========
+
+/// 面向客户端的 workflow 管理服务
+pub struct ClientServiceImpl<P: Persistence> {
+    scheduler: Arc<Scheduler<P>>,
+}
+
+impl<P: Persistence> ClientServiceImpl<P> {
+    pub fn new(scheduler: Arc<Scheduler<P>>) -> Self {
+        Self { scheduler }
+    }
+
+    async fn load_workflow(&self, workflow_id: &str) -> Result<Workflow, Status> {
+        self.scheduler
+            .persistence
+            .get_workflow(workflow_id)
+            .await
+            .map_err(internal)?
+            .ok_or_else(|| Status::not_found(format!("Workflow not found: {}", workflow_id)))
+    }
+
+    /// 等待 workflow 进入终态
+    async fn wait_for_terminal(&self, workflow_id: &str) -> Result<Workflow, Status> {
+        self.scheduler
+            .wait_for_terminal(workflow_id)
+            .await
+            .map_err(internal)?
+            .ok_or_else(|| Status::not_found(format!("Workflow not found: {}", workflow_id)))
+    }
+}
+
+#[tonic::async_trait]
+impl<P: Persistence + 'static> ClientService for ClientServiceImpl<P> {
+    async fn start_workflow(
+        &self,
+        request: Request<proto::StartWorkflowRequest>,
+    ) -> Result<Response<proto::StartWorkflowResponse>, Status> {
+        let req = request.into_inner();
+        if !req.dynamic
+            && !self
+                .scheduler
+                .accepts_workflow_type(&req.workflow_type)
+                .await
+        {
+            return Err(Status::invalid_argument(format!(
+                "Unknown workflow type '{}': expected one of {} (set dynamic to start a type without a definition)",
+                req.workflow_type,
+                self.scheduler.defined_workflow_types().await.join(", ")
+            )));
+        }
+        let priority = from_proto_priority(req.priority());
+        let version = Some(req.version).filter(|version| *version > 0);
+        if let Some(version) = version {
+            if self
+                .scheduler
+                .definition_version(&req.workflow_type, version)
+                .await
+                .is_none()
+            {
+                return Err(Status::invalid_argument(format!(
+                    "Workflow type '{}' has no definition version {}",
+                    req.workflow_type, version
+                )));
+            }
+        }
+        self.scheduler.check_input_size(&req.input)?;
+        validate_memo(&req.memo).map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let idempotency_key = Some(req.idempotency_key).filter(|key| !key.is_empty());
+        let started = self
+            .scheduler
+            .start_workflow(
+                idempotency_key,
+                req.workflow_type.clone(),
+                req.input,
+                StartOptions {
+                    priority,
+                    skip_schema_validation: req.skip_schema_validation,
+                    version,
+                    memo: req.memo,
+                },
+            )
+            .await
+            .map_err(internal)?;
+        if started.workflow.workflow_type != req.workflow_type {
+            return Err(Status::already_exists(format!(
+                "Workflow '{}' already exists with a different type",
+                started.workflow.id
+            )));
+        }
+
+        Ok(Response::new(proto::StartWorkflowResponse {
+            workflow_id: started.workflow.id,
+            already_exists: started.already_exists,
+        }))
+    }
+
+    async fn get_workflow_status(
+        &self,
+        request: Request<proto::GetStatusRequest>,
+    ) -> Result<Response<proto::WorkflowStatus>, Status> {
+        let workflow = self
+            .load_workflow(&request.into_inner().workflow_id)
+            .await?;
+
+        let (current_step, result, error) = match &workflow.state {
+            WorkflowState::Running { current_step } => (
+                current_step.clone().unwrap_or_default(),
+                vec![],
+                String::new(),
+            ),
+            WorkflowState::Completed { result } => {
+                (String::new(), result.data.clone(), String::new())
+            }
+            WorkflowState::Failed { error } => (String::new(), vec![], error.clone()),
+            WorkflowState::Terminated { reason, .. } => (String::new(), vec![], reason.clone()),
+            _ => (String::new(), vec![], String::new()),
+        };
+        let terminated_by = match &workflow.state {
+            WorkflowState::Terminated { requested_by, .. } => {
+                requested_by.clone().unwrap_or_default()
+            }
+            _ => String::new(),
+        };
+        let child_workflow_ids = self
+            .scheduler
+            .child_workflows(&workflow.id)
+            .await
+            .map_err(internal)?
+            .into_iter()
+            .map(|child| child.id)
+            .collect();
+
+        Ok(Response::new(proto::WorkflowStatus {
+            workflow_id: workflow.id.clone(),
+            state: to_proto_state(&workflow.state) as i32,
+            current_step,
+            result,
+            error,
+            started_at: workflow.started_at.timestamp(),
+            completed_at: completed_at(&workflow),
+            parent_workflow_id: workflow.parent_workflow_id.clone().unwrap_or_default(),
+            child_workflow_ids,
+            terminated_by,
+            memo: workflow.memo,
+        }))
+    }
+
+    /// 阻塞直到 workflow 进入终态
+    ///
+    /// 超时取请求中的 `timeout_seconds`，未设置时取 gRPC deadline，两者都没有则一直等待。
+    async fn await_result(
+        &self,
+        request: Request<proto::AwaitResultRequest>,
+    ) -> Result<Response<proto::WorkflowResult>, Status> {
+        let deadline = grpc_timeout(request.metadata());
+        let req = request.into_inner();
+        let deadline = match req.timeout_seconds {
+            secs if secs > 0 => Some(Duration::from_secs(secs as u64)),
+            _ => deadline,
+        };
+
+        let wait = self.wait_for_terminal(&req.workflow_id);
+        let workflow = match deadline {
+            Some(deadline) => tokio::time::timeout(deadline, wait).await.map_err(|_| {
+                Status::deadline_exceeded(format!(
+                    "Workflow {} did not finish in time",
+                    req.workflow_id
+                ))
+            })??,
+            None => wait.await?,
+        };
+
+        let state = to_proto_state(&workflow.state) as i32;
+        let (result, error) = match workflow.state {
+            WorkflowState::Completed { result } => (result.into_bytes(), String::new()),
+            WorkflowState::Failed { error } | WorkflowState::Terminated { reason: error, .. } => {
+                (vec![], error)
+            }
+            _ => (vec![], String::new()),
+        };
+        Ok(Response::new(proto::WorkflowResult {
+            result,
+            error,
+            state,
+        }))
+    }
+
+    async fn cancel_workflow(
+        &self,
+        request: Request<proto::CancelRequest>,
+    ) -> Result<Response<proto::CancelResponse>, Status> {
+        let workflow_id = request.into_inner().workflow_id;
+        self.scheduler.cancel_workflow(&workflow_id).await?;
+        Ok(Response::new(proto::CancelResponse { success: true }))
+    }
+
+    async fn terminate_workflow(
+        &self,
+        request: Request<proto::TerminateWorkflowRequest>,
+    ) -> Result<Response<proto::TerminateWorkflowResponse>, Status> {
+        let req = request.into_inner();
+        let requested_by = Some(req.requested_by.as_str()).filter(|by| !by.is_empty());
+        self.scheduler
+            .terminate_workflow(&req.workflow_id, &req.reason, requested_by)
+            .await?;
+
+        Ok(Response::new(proto::TerminateWorkflowResponse {
+            success: true,
+        }))
+    }
+
+    async fn retry_workflow(
+        &self,
+        request: Request<proto::RetryWorkflowRequest>,
+    ) -> Result<Response<proto::RetryWorkflowResponse>, Status> {
+        let workflow_id = request.into_inner().workflow_id;
+        let retried_steps = self.scheduler.retry_workflow(&workflow_id).await?;
+
+        Ok(Response::new(proto::RetryWorkflowResponse {
+            retried_steps,
+        }))
+    }
+
+    async fn signal_workflow(
+        &self,
+        request: Request<proto::SignalWorkflowRequest>,
+    ) -> Result<Response<proto::SignalWorkflowResponse>, Status> {
+        let req = request.into_inner();
+        self.scheduler
+            .signal_workflow(&req.workflow_id, &req.signal_name, req.payload)
+            .await?;
+
+        Ok(Response::new(proto::SignalWorkflowResponse {
+            success: true,
+        }))
+    }
+
+    async fn delete_workflow(
+        &self,
+        request: Request<proto::DeleteWorkflowRequest>,
+    ) -> Result<Response<proto::DeleteWorkflowResponse>, Status> {
+        let req = request.into_inner();
+        self.scheduler
+            .delete_workflow(&req.workflow_id, req.force)
+            .await?;
+
+        Ok(Response::new(proto::DeleteWorkflowResponse {
+            success: true,
+        }))
+    }
+
+    async fn export_workflow(
+        &self,
+        request: Request<proto::ExportWorkflowRequest>,
+    ) -> Result<Response<proto::ExportWorkflowResponse>, Status> {
+        let req = request.into_inner();
+        let format = match proto::ExportFormat::try_from(req.format) {
+            Ok(proto::ExportFormat::ExportJson) => ExportFormat::Json,
+            Ok(proto::ExportFormat::ExportJsonl) => ExportFormat::Jsonl,
+            Err(_) => {
+                return Err(Status::invalid_argument(format!(
+                    "Invalid export format: {}",
+                    req.format
+                )))
+            }
+        };
+        let options = ExportOptions {
+            max_payload_bytes: (req.max_payload_bytes > 0)
+                .then_some(req.max_payload_bytes as usize),
+        };
+        let export = export::export_workflow(&self.scheduler, &req.workflow_id, &options)
+            .await
+            .map_err(internal)?
+            .ok_or_else(|| Status::not_found(format!("Workflow not found: {}", req.workflow_id)))?;
+        let document = export
+            .encode(format)
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(proto::ExportWorkflowResponse { document }))
+    }
+
+    async fn import_workflow(
+        &self,
+        request: Request<proto::ImportWorkflowRequest>,
+    ) -> Result<Response<proto::ImportWorkflowResponse>, Status> {
+        let req = request.into_inner();
+        let options = ImportOptions {
+            keep_id: req.keep_id,
+            resume: req.resume,
+        };
+        let imported = self
+            .scheduler
+            .import_workflow(&req.document, &options)
+            .await?;
+
+        Ok(Response::new(proto::ImportWorkflowResponse {
+            state: to_proto_state(&imported.workflow.state) as i32,
+            workflow_id: imported.workflow.id,
+            resumed_steps: imported.resumed_steps,
+        }))
+    }
+
+    async fn get_server_info(
+        &self,
+        _request: Request<proto::GetServerInfoRequest>,
+    ) -> Result<Response<proto::ServerInfo>, Status> {
+        Ok(Response::new(self.scheduler.server_info().into()))
+    }
+
+    /// 分页查询 workflow
+    ///
+    /// 每页最多 `max_batch_size` 条；page token 是下一页的起始位置，对客户端不透明。
+    /// 未设置 page token 时从 `offset` 开始。
+    async fn list_workflows(
+        &self,
+        request: Request<proto::ListWorkflowsRequest>,
+    ) -> Result<Response<proto::ListWorkflowsResponse>, Status> {
+        let req = request.into_inner();
+        let max_page_size = self.scheduler.limits().max_batch_size as usize;
+        let page_size = match req.page_size as usize {
+            0 => max_page_size,
+            size => size.min(max_page_size),
+        };
+        let offset = if req.page_token.is_empty() {
+            req.offset as usize
+        } else {
+            req.page_token
+                .parse::<usize>()
+                .map_err(|_| Status::invalid_argument("Invalid page_token"))?
+        };
+        // 多取一条判断是否还有下一页
+        let options = ListOptions {
+            offset,
+            limit: Some(page_size + 1),
+            order_by: list_order(req.order),
+            state_filter: state_filter(req.state)?,
+            workflow_type: Some(req.workflow_type).filter(|t| !t.is_empty()),
+            started_after: time_filter("created_after", req.created_after)?,
+            started_before: time_filter("created_before", req.created_before)?,
+            memo: req.memo,
+            ..Default::default()
+        };
+
+        let mut workflows = self
+            .scheduler
+            .persistence
+            .list_workflows_paged(&options)
+            .await
+            .map_err(internal)?;
+        let next_page_token = if workflows.len() > page_size {
+            workflows.truncate(page_size);
+            (offset + page_size).to_string()
+        } else {
+            String::new()
+        };
+
+        Ok(Response::new(proto::ListWorkflowsResponse {
+            workflows: workflows
+                .into_iter()
+                .map(|w| proto::WorkflowSummary {
+                    state: to_proto_state(&w.state) as i32,
+                    current_step: match w.state {
+                        WorkflowState::Running { current_step } => current_step.unwrap_or_default(),
+                        _ => String::new(),
+                    },
+                    started_at: w.started_at.timestamp(),
+                    updated_at: w.updated_at.timestamp(),
+                    workflow_id: w.id,
+                    workflow_type: w.workflow_type,
+                    memo: w.memo,
+                })
+                .collect(),
+            next_page_token,
+        }))
+    }
+
+    async fn list_services(
+        &self,
+        _request: Request<proto::ListServicesRequest>,
+    ) -> Result<Response<proto::ListServicesResponse>, Status> {
+        let mut services = self.scheduler.service_registry.list();
+        services.sort_by(|a, b| {
+            (&a.service_name, &a.instance_id).cmp(&(&b.service_name, &b.instance_id))
+        });
+
+        Ok(Response::new(proto::ListServicesResponse {
+            services: services
+                .into_iter()
+                .map(|service| {
+                    let mut provides: Vec<_> =
+                        service.provides.values().map(to_proto_resource).collect();
+                    provides.sort_by(|a, b| a.name.cmp(&b.name));
+                    proto::RegisteredService {
+                        service_name: service.service_name,
+                        group: service.group,
+                        languages: service.languages,
+                        provides,
+                        endpoint: service.endpoint,
+                        registered_at: service.registered_at.timestamp(),
+                        instance_id: service.instance_id,
+                        last_heartbeat: service.last_heartbeat.timestamp(),
+                    }
+                })
+                .collect(),
+        }))
+    }
+
+    async fn create_schedule(
+        &self,
+        request: Request<proto::CreateScheduleRequest>,
+    ) -> Result<Response<proto::ScheduleInfo>, Status> {
+        let req = request.into_inner();
+        self.scheduler.check_input_size(&req.input)?;
+        let schedule = self
+            .scheduler
+            .create_schedule(ScheduleSpec {
+                overlap_policy: from_proto_overlap_policy(req.overlap_policy()),
+                cron: req.cron,
+                workflow_type: req.workflow_type,
+                input: req.input,
+            })
+            .await?;
+
+        Ok(Response::new(to_proto_schedule(schedule)))
+    }
+
+    async fn list_schedules(
+        &self,
+        _request: Request<proto::ListSchedulesRequest>,
+    ) -> Result<Response<proto::ListSchedulesResponse>, Status> {
+        let schedules = self.scheduler.list_schedules().await.map_err(internal)?;
+
+        Ok(Response::new(proto::ListSchedulesResponse {
+            schedules: schedules.into_iter().map(to_proto_schedule).collect(),
+        }))
+    }
+
+    async fn delete_schedule(
+        &self,
+        request: Request<proto::DeleteScheduleRequest>,
+    ) -> Result<Response<proto::DeleteScheduleResponse>, Status> {
+        let req = request.into_inner();
+        self.scheduler.delete_schedule(&req.schedule_id).await?;
+
+        Ok(Response::new(proto::DeleteScheduleResponse {
+            success: true,
+        }))
+    }
+
+    async fn register_workflow_definition(
+        &self,
+        request: Request<proto::RegisterWorkflowDefinitionRequest>,
+    ) -> Result<Response<proto::RegisterWorkflowDefinitionResponse>, Status> {
+        let definition = request
+            .into_inner()
+            .definition
+            .ok_or_else(|| Status::invalid_argument("definition is required"))?;
+        if definition.name.is_empty() {
+            return Err(Status::invalid_argument("definition.name is required"));
+        }
+        let definition = from_proto_definition(definition);
+        let problems = self.scheduler.definition_problems(&definition);
+        if !problems.is_empty() {
+            return Err(DefinitionError::Invalid(definition.name, problems).into());
+        }
+
+        let (name, version) = (definition.name.clone(), definition.version);
+        self.scheduler.register_definition(definition).await?;
+        Ok(Response::new(proto::RegisterWorkflowDefinitionResponse {
+            name,
+            version,
+        }))
+    }
+}
+
+// ========== WorkerService ==========
+
+/// 面向 worker 的任务分发服务
+pub struct WorkerServiceImpl<P: Persistence> {
+    scheduler: Arc<Scheduler<P>>,
+}
+
+impl<P: Persistence> WorkerServiceImpl<P> {
+    pub fn new(scheduler: Arc<Scheduler<P>>) -> Self {
+        Self { scheduler }
+    }
+}
+
+#[tonic::async_trait]
+impl<P: Persistence + 'static> WorkerService for WorkerServiceImpl<P> {
+    type PollTasksStream = ResponseStream<proto::Task>;
+    type WatchCancellationsStream = ResponseStream<proto::TaskCancelled>;
+
+    async fn register(
+        &self,
+        request: Request<proto::RegisterRequest>,
+    ) -> Result<Response<proto::RegisterResponse>, Status> {
+        // 启用认证时静态令牌、会话令牌和引导令牌都可以注册，会话令牌只能注册自己
+        let principal = grpc_auth::principal(&request);
+        let req = request.into_inner();
+        let in_use = matches!(principal, Some(Principal::Bootstrap))
+            && self.scheduler.is_worker_id_in_use(&req.worker_id).await;
+        let worker_id =
+            grpc_auth::authorize_registration(principal.as_ref(), &req.worker_id, in_use)?;
+        let provides: Vec<ServiceResource> = req.provides.iter().map(from_proto_resource).collect();
+        // 声明了格式错误的 schema 时拒绝注册
+        schema::check_resources(&provides).map_err(Status::invalid_argument)?;
+
+        // 资源声明了 max_attempts 时覆盖默认重试策略
+        for resource in &req.provides {
+            if let Some(max_attempts) = resource
+                .metadata
+                .as_ref()
+                .map(|m| m.max_attempts)
+                .filter(|n| *n > 0)
+            {
+                self.scheduler
+                    .set_retry_policy(
+                        &resource.name,
+                        RetryPolicy {
+                            max_attempts: max_attempts as u32,
+                            ..Default::default()
+                        },
+                    )
+                    .await;
+            }
+        }
+
+        self.scheduler.service_registry.register(
+            req.service_name.clone(),
+            worker_id.clone(),
+            req.group.clone(),
+            req.language,
+            provides,
+            req.endpoint,
+        );
+        let resources = req
+            .provides
+            .into_iter()
+            .map(|r| (r.name, to_resource_type(r.r#type)))
+            .collect();
+
+        let session_token = self.scheduler.sessions.issue(&worker_id);
+        self.scheduler
+            .register_worker(worker_id, req.service_name, req.group, vec![], resources)
+            .await;
+
+        Ok(Response::new(proto::RegisterResponse {
+            server_id: env!("CARGO_PKG_NAME").to_string(),
+            supported_workflow_types: vec![],
+            session_token,
+        }))
+    }
+
+    async fn poll_tasks(
+        &self,
+        request: Request<proto::PollRequest>,
+    ) -> Result<Response<Self::PollTasksStream>, Status> {
+        let principal = grpc_auth::principal(&request);
+        let req = request.into_inner();
+        let worker_id = authorize_worker(principal.as_ref(), &req.worker_id)?;
+        let max_tasks = if req.max_tasks > 0 {
+            req.max_tasks as usize
+        } else {
+            self.scheduler.poll_tasks_limit()
+        }
+        .min(self.scheduler.limits().max_batch_size as usize);
+
+        // 未注册或已因心跳超时被移除的 worker 需要重新注册
+        if !self.scheduler.touch_worker(&worker_id).await {
+            return Err(Status::not_found(format!(
+                "Worker not registered: {}",
+                worker_id
+            )));
+        }
+
+        // 流保持打开，直到客户端断开、送满 max_tasks、worker 被移除或调度器停止
+        let scheduler = self.scheduler.clone();
+        let (tx, rx) = mpsc::channel(1);
+        tokio::spawn(async move {
+            // 空闲时也定期 poll，使打开的流同时作为 worker 心跳
+            let max_wait = scheduler.poll_interval();
+            let mut ready = scheduler.subscribe_tasks();
+            let mut remaining = max_tasks;
+            loop {
+                ready.borrow_and_update();
+                if !scheduler.touch_worker(&worker_id).await {
+                    let _ = tx
+                        .send(Err(Status::not_found(format!(
+                            "Worker not registered: {}",
+                            worker_id
+                        ))))
+                        .await;
+                    return;
+                }
+
+                let tasks = match scheduler.poll_tasks(&worker_id, remaining).await {
+                    Ok(tasks) => tasks,
+                    Err(e) => {
+                        // 调度器内部的重试已用尽，结束流，由 worker 重新连接
+                        tracing::error!(%worker_id, "task dispatch failed: {:#}", e);
+                        let _ = tx.send(Err(internal(e))).await;
+                        return;
+                    }
+                };
+                for task in tasks {
+                    let task_id = TaskId::new(&task.workflow_id, &task.step_name);
+                    if tx.send(Ok(to_proto_task(task))).await.is_err() {
+                        // 客户端已断开，未送达的 task 立即回到队列
+                        scheduler.release_lease(&task_id).await;
+                        scheduler.notify_tasks_ready();
+                        continue;
+                    }
+                    remaining -= 1;
+                }
+                if remaining == 0 || tx.is_closed() {
+                    return;
+                }
+
+                tokio::select! {
+                    _ = scheduler.wait_for_tasks(&mut ready, max_wait) => {}
+                    _ = tx.closed() => return,
+                    _ = scheduler.stopped() => return,
+                }
+            }
+        });
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+
+    async fn watch_cancellations(
+        &self,
+        request: Request<proto::WatchCancellationsRequest>,
+    ) -> Result<Response<Self::WatchCancellationsStream>, Status> {
+        let principal = grpc_auth::principal(&request);
+        let worker_id = authorize_worker(principal.as_ref(), &request.into_inner().worker_id)?;
+        if !self.scheduler.touch_worker(&worker_id).await {
+            return Err(Status::not_found(format!(
+                "Worker not registered: {}",
+                worker_id
+            )));
+        }
+
+        // 先订阅再返回，保证之后的取消都能送达
+        let mut cancellations = self.scheduler.subscribe_cancellations();
+        let scheduler = self.scheduler.clone();
+        let (tx, rx) = mpsc::channel(16);
+        tokio::spawn(async move {
+            loop {
+                let cancellation = tokio::select! {
+                    received = cancellations.recv() => received,
+                    _ = tx.closed() => return,
+                    _ = scheduler.stopped() => return,
+                };
+                match cancellation {
+                    Ok(cancellation) if cancellation.worker_id == worker_id => {
+                        let message = proto::TaskCancelled {
+                            task_id: cancellation.task_id.to_string(),
+                            workflow_id: cancellation.task_id.workflow_id,
+                            step_name: cancellation.task_id.step_name,
+                        };
+                        if tx.send(Ok(message)).await.is_err() {
+                            return;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!(%worker_id, skipped, "cancellation stream lagged");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                }
+            }
+        });
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+
+    async fn complete_step(
+        &self,
+        request: Request<proto::CompleteStepRequest>,
+    ) -> Result<Response<proto::CompleteStepResponse>, Status> {
+        let principal = grpc_auth::principal(&request);
+        let req = request.into_inner();
+        let lifecycle = self.scheduler.lifecycle();
+        // 会话令牌只能完成自己租约下的 task
+        let worker_id = authorize_worker(principal.as_ref(), &req.worker_id)?;
+        let worker_id = Some(worker_id.as_str()).filter(|id| !id.is_empty());
+
+        if req.error.is_empty() {
+            let children = req
+                .start_children
+                .into_iter()
+                .map(|child| ChildWorkflowSpec {
+                    workflow_type: child.workflow_type,
+                    input: child.input,
+                })
+                .collect();
+            lifecycle
+                .complete_task_with_children(&req.task_id, worker_id, req.result, children)
+                .await?;
+        } else {
+            lifecycle
+                .fail_task(&req.task_id, worker_id, req.error)
+                .await?;
+        }
+
+        Ok(Response::new(proto::CompleteStepResponse { success: true }))
+    }
+
+    async fn report_step(
+        &self,
+        request: Request<proto::ReportStepRequest>,
+    ) -> Result<Response<proto::ReportStepResponse>, Status> {
+        let principal = grpc_auth::principal(&request);
+        reject_bootstrap(principal.as_ref())?;
+        let req = request.into_inner();
+        let status = proto::StepStatus::try_from(req.status).map_err(|_| {
+            Status::invalid_argument(format!("Invalid step status: {}", req.status))
+        })?;
+        let lifecycle = self.scheduler.lifecycle();
+
+        match status {
+            proto::StepStatus::StepStarted => {
+                lifecycle
+                    .step_started(&req.workflow_id, &req.step_name, req.input)
+                    .await?;
+            }
+            proto::StepStatus::StepCompleted => {
+                lifecycle
+                    .step_completed(&req.workflow_id, &req.step_name, req.output)
+                    .await?;
+            }
+            proto::StepStatus::StepFailed => {
+                // 失败会触发重试或让 workflow 失败，和 CompleteStep 一样只接受租约持有者的上报
+                let worker_id = authorize_worker(principal.as_ref(), &req.worker_id)?;
+                let worker_id = Some(worker_id.as_str()).filter(|id| !id.is_empty());
+                let task_id = TaskId::new(&req.workflow_id, &req.step_name);
+                lifecycle
+                    .fail_task(&task_id.to_string(), worker_id, req.error)
+                    .await?;
+            }
+            proto::StepStatus::StepHeartbeat => {
+                let task_id = TaskId::new(&req.workflow_id, &req.step_name);
+                lifecycle
+                    .heartbeat(&task_id.to_string(), step_progress(req.progress))
+                    .await?;
+            }
+        }
+
+        Ok(Response::new(proto::ReportStepResponse { success: true }))
+    }
+
+    async fn heartbeat(
+        &self,
+        request: Request<proto::HeartbeatRequest>,
+    ) -> Result<Response<proto::HeartbeatResponse>, Status> {
+        let principal = grpc_auth::principal(&request);
+        let req = request.into_inner();
+        let worker_id = authorize_worker(principal.as_ref(), &req.worker_id)?;
+        let mut ok = true;
+        if !worker_id.is_empty() {
+            ok &= self.scheduler.touch_worker(&worker_id).await;
+        }
+        if !req.task_id.is_empty() || worker_id.is_empty() {
+            let task_id = parse_task_id(&req.task_id)?;
+            ok &= self.scheduler.extend_lease(&task_id).await;
+        }
+        Ok(Response::new(proto::HeartbeatResponse { ok }))
+    }
+
+    async fn reject_task(
+        &self,
+        request: Request<proto::RejectTaskRequest>,
+    ) -> Result<Response<proto::RejectTaskResponse>, Status> {
+        let principal = grpc_auth::principal(&request);
+        let req = request.into_inner();
+        let worker_id = authorize_worker(principal.as_ref(), &req.worker_id)?;
+        if worker_id.is_empty() {
+            return Err(Status::invalid_argument("worker_id is required"));
+        }
+        let task_id = parse_task_id(&req.task_id)?;
+        let ok = self
+            .scheduler
+            .reject_task(
+                &task_id,
+                &worker_id,
+                &req.reason,
+                req.requeue.unwrap_or(true),
+            )
+            .await;
+        Ok(Response::new(proto::RejectTaskResponse { ok }))
+    }
+
+    async fn heartbeat_step(
+        &self,
+        request: Request<proto::HeartbeatStepRequest>,
+    ) -> Result<Response<proto::HeartbeatStepResponse>, Status> {
+        reject_bootstrap(grpc_auth::principal(&request).as_ref())?;
+        let req = request.into_inner();
+        self.scheduler
+            .lifecycle()
+            .heartbeat(&req.task_id, step_progress(req.progress))
+            .await?;
+
+        Ok(Response::new(proto::HeartbeatStepResponse {
+            success: true,
+            next_heartbeat: self.scheduler.heartbeat_interval().as_millis() as i64,
+        }))
+    }
+}
+
+// ========== AdminService ==========
+
+/// 管理服务
+pub struct AdminServiceImpl<P: Persistence> {
+    scheduler: Arc<Scheduler<P>>,
+}
+
+impl<P: Persistence> AdminServiceImpl<P> {
+    pub fn new(scheduler: Arc<Scheduler<P>>) -> Self {
+        Self { scheduler }
+    }
+}
+
+#[tonic::async_trait]
+impl<P: Persistence + 'static> AdminService for AdminServiceImpl<P> {
+    type ListWorkflowsStream = ResponseStream<proto::WorkflowInfo>;
+
+    async fn list_workflows(
+        &self,
+        request: Request<proto::ListRequest>,
+    ) -> Result<Response<Self::ListWorkflowsStream>, Status> {
+        let req = request.into_inner();
+        let options = ListOptions {
+            offset: req.offset as usize,
+            limit: Some(req.limit as usize).filter(|limit| *limit > 0),
+            order_by: list_order(req.order),
+            state_filter: state_filter(req.state)?,
+            workflow_type: Some(req.workflow_type).filter(|t| !t.is_empty()),
+            ..Default::default()
+        };
+
+        let workflows = self
+            .scheduler
+            .persistence
+            .list_workflows_paged(&options)
+            .await
+            .map_err(internal)?;
+
+        let infos: Vec<Result<proto::WorkflowInfo, Status>> = workflows
+            .iter()
+            .map(|w| {
+                Ok(proto::WorkflowInfo {
+                    workflow_id: w.id.clone(),
+                    workflow_type: w.workflow_type.clone(),
+                    state: to_proto_state(&w.state) as i32,
+                    started_at: w.started_at.timestamp(),
+                    completed_at: completed_at(w),
+                })
+            })
+            .collect();
+
+        Ok(Response::new(Box::pin(tokio_stream::iter(infos))))
+    }
+
+    async fn get_metrics(
+        &self,
+        _request: Request<proto::GetMetricsRequest>,
+    ) -> Result<Response<proto::Metrics>, Status> {
+        let workflows = self
+            .scheduler
+            .persistence
+            .list_workflows(None)
+            .await
+            .map_err(internal)?;
+
+        let mut metrics = proto::Metrics::default();
+        let mut durations = DurationStats::default();
+        for workflow in workflows {
+            durations.record(&workflow);
+            match workflow.state {
+                WorkflowState::Pending | WorkflowState::Running { .. } => {
+                    metrics.active_workflows += 1
+                }
+                WorkflowState::Completed { .. } => metrics.completed_workflows += 1,
+                WorkflowState::Failed { .. } => metrics.failed_workflows += 1,
+                WorkflowState::Cancelled | WorkflowState::Terminated { .. } => {}
+            }
+        }
+        metrics.purged_workflows = self.scheduler.purged_workflows() as i64;
+        let ready = self.scheduler.ready_task_counts().await.map_err(internal)?;
+        metrics.ready_tasks_high = ready[&Priority::High] as i64;
+        metrics.ready_tasks_normal = ready[&Priority::Normal] as i64;
+        metrics.ready_tasks_low = ready[&Priority::Low] as i64;
+        metrics.avg_queue_ms = durations.avg_queue().as_millis() as i64;
+        metrics.avg_execution_ms = durations.avg_execution().as_millis() as i64;
+        metrics.task_rejections = self
+            .scheduler
+            .task_rejections()
+            .into_iter()
+            .map(|(worker_id, rejected)| proto::WorkerRejections {
+                worker_id,
+                rejected: rejected as i64,
+            })
+            .collect();
+        let step_cache = self.scheduler.step_cache_stats();
+        metrics.step_cache_hits = step_cache.hits as i64;
+        metrics.step_cache_misses = step_cache.misses as i64;
+        metrics.step_cache_hit_rate = step_cache.hit_rate();
+
+        Ok(Response::new(metrics))
+    }
+
+    async fn get_server_info(
+        &self,
+        _request: Request<proto::GetServerInfoRequest>,
+    ) -> Result<Response<proto::ServerInfo>, Status> {
+        Ok(Response::new(self.scheduler.server_info().into()))
+    }
+
+    async fn get_diagnostics(
+        &self,
+        request: Request<proto::GetDiagnosticsRequest>,
+    ) -> Result<Response<proto::Diagnostics>, Status> {
+        let stuck_after =
+            Duration::from_secs(request.into_inner().stuck_after_seconds.max(0) as u64);
+        let diagnostics = self
+            .scheduler
+            .diagnostics(stuck_after)
+            .await
+            .map_err(internal)?;
+
+        let workers = diagnostics
+            .workers
+            .into_iter()
+            .map(|w| proto::RegisteredWorker {
+                worker_id: w.id,
+                service_name: w.service_name,
+                group: w.group,
+                workflow_types: w.workflow_types,
+                resources: w.resources.into_iter().map(|(name, _)| name).collect(),
+            })
+            .collect();
+
+        let stuck_workflows = diagnostics
+            .stuck_workflows
+            .iter()
+            .map(|w| proto::StuckWorkflow {
+                workflow_id: w.id.clone(),
+                workflow_type: w.workflow_type.clone(),
+                state: to_proto_state(&w.state) as i32,
+                updated_at: w.updated_at.timestamp(),
+            })
+            .collect();
+
+        Ok(Response::new(proto::Diagnostics {
+            persistence_backend: diagnostics.persistence_backend,
+            persistence_probe_micros: diagnostics.persistence_probe.as_micros() as i64,
+            workers,
+            stuck_workflows,
+            subscriber_count: diagnostics.subscriber_count as i64,
+        }))
+    }
+
+    async fn purge_workflows(
+        &self,
+        request: Request<proto::PurgeRequest>,
+    ) -> Result<Response<proto::PurgeResponse>, Status> {
+        let req = request.into_inner();
+
+        let states = req
+            .states
+            .iter()
+            .map(|value| match proto::State::try_from(*value) {
+                Ok(proto::State::Completed) => Ok(TerminalState::Completed),
+                Ok(proto::State::Failed) => Ok(TerminalState::Failed),
+                Ok(proto::State::Cancelled) => Ok(TerminalState::Cancelled),
+                Ok(proto::State::Terminated) => Ok(TerminalState::Terminated),
+                _ => Err(Status::invalid_argument(
+                    "Only COMPLETED, FAILED, CANCELLED and TERMINATED workflows can be purged",
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let completed_before = match req.completed_before {
+            0 => None,
+            secs => Some(chrono::DateTime::from_timestamp(secs, 0).ok_or_else(|| {
+                Status::invalid_argument(format!("Invalid completed_before: {}", secs))
+            })?),
+        };
+
+        let filter = PurgeFilter {
+            states,
+            completed_before,
+            workflow_type: Some(req.workflow_type).filter(|t| !t.is_empty()),
+        };
+        let removed = self
+            .scheduler
+            .purge_workflows(&filter)
+            .await
+            .map_err(internal)?;
+
+        Ok(Response::new(proto::PurgeResponse {
+            removed: removed as i64,
+        }))
+    }
+}
+
+// ========== 服务器启动 ==========
+
+/// 启动 gRPC 服务器
+pub async fn start_grpc_server<P: Persistence + 'static>(
+    scheduler: Arc<Scheduler<P>>,
+    listen_addr: &str,
+) -> anyhow::Result<()> {
+    start_grpc_server_with_shutdown(scheduler, listen_addr, std::future::pending()).await
+}
+
+/// 启动 gRPC 服务器，`shutdown` 完成后停止接受新请求并等待进行中的请求结束
+pub async fn start_grpc_server_with_shutdown<P: Persistence + 'static>(
+    scheduler: Arc<Scheduler<P>>,
+    listen_addr: &str,
+    shutdown: impl std::future::Future<Output = ()>,
+) -> anyhow::Result<()> {
+    start_grpc_server_with_options(scheduler, listen_addr, GrpcOptions::default(), shutdown).await
+}
+
+/// gRPC 服务器的附加服务和认证，默认启用全部附加服务、不做认证
+#[derive(Debug, Clone)]
+pub struct GrpcOptions {
+    /// 注册 `aether.v1` descriptor 的 gRPC reflection 服务，供 grpcurl 等工具使用
+    pub reflection: bool,
+    /// `grpc.health.v1` 健康检查服务，见 [`crate::health`]
+    pub health: bool,
+    /// client/worker/admin 服务接受的 bearer 令牌，见 [`crate::grpc_auth`]
+    pub auth: GrpcAuthConfig,
+}
+
+impl Default for GrpcOptions {
+    fn default() -> Self {
+        GrpcOptions {
+            reflection: true,
+            health: true,
+            auth: GrpcAuthConfig::default(),
+        }
+    }
+}
+
+impl GrpcOptions {
+    pub fn with_reflection(mut self, enabled: bool) -> Self {
+        self.reflection = enabled;
+        self
+    }
+
+    pub fn with_health(mut self, enabled: bool) -> Self {
+        self.health = enabled;
+        self
+    }
+
+    pub fn with_auth(mut self, auth: GrpcAuthConfig) -> Self {
+        self.auth = auth;
+        self
+    }
+}
+
+/// 按 `options` 启动 gRPC 服务器，`shutdown` 完成后停止接受新请求并等待进行中的请求结束
+pub async fn start_grpc_server_with_options<P: Persistence + 'static>(
+    scheduler: Arc<Scheduler<P>>,
+    listen_addr: &str,
+    options: GrpcOptions,
+    shutdown: impl std::future::Future<Output = ()>,
+) -> anyhow::Result<()> {
+    let addr: std::net::SocketAddr = listen_addr.parse()?;
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    serve_grpc_with_options(scheduler, listener, options, shutdown).await
+}
+
+/// 同 [`start_grpc_server_with_options`]，在已绑定的 `listener` 上提供服务
+pub async fn serve_grpc_with_options<P: Persistence + 'static>(
+    scheduler: Arc<Scheduler<P>>,
+    listener: tokio::net::TcpListener,
+    options: GrpcOptions,
+    shutdown: impl std::future::Future<Output = ()>,
+) -> anyhow::Result<()> {
+    options.auth.validate()?;
+    let listen_addr = listener.local_addr()?;
+    let incoming = TcpIncoming::from_listener(listener, false, None)
+        .map_err(|e| anyhow::anyhow!("gRPC listener on {}: {}", listen_addr, e))?;
+    let max_payload = scheduler.limits().max_payload_bytes as usize;
+
+    // reflection 和健康检查不需要认证
+    let auth = Arc::new(options.auth.clone());
+    let sessions = scheduler.sessions.clone();
+    let client = InterceptedService::new(
+        ClientServiceServer::new(ClientServiceImpl::new(scheduler.clone()))
+            .max_decoding_message_size(max_payload),
+        AuthInterceptor::new(auth.clone(), sessions.clone()),
+    );
+    let worker = InterceptedService::new(
+        WorkerServiceServer::new(WorkerServiceImpl::new(scheduler.clone()))
+            .max_decoding_message_size(max_payload),
+        AuthInterceptor::for_workers(auth.clone(), sessions.clone()),
+    );
+    let admin = InterceptedService::new(
+        AdminServiceServer::new(AdminServiceImpl::new(scheduler.clone()))
+            .max_decoding_message_size(max_payload),
+        AuthInterceptor::new(auth, sessions),
+    );
+    // 空字符串表示整个服务器
+    let services = vec![
+        "",
+        service_name(&client),
+        service_name(&worker),
+        service_name(&admin),
+    ];
+
+    let reflection = if options.reflection {
+        Some(
+            tonic_reflection::server::Builder::configure()
+                .register_encoded_file_descriptor_set(proto::FILE_DESCRIPTOR_SET)
+                .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
+                .build()?,
+        )
+    } else {
+        None
+    };
+    let (reporter, health) = if options.health {
+        let (reporter, health) = tonic_health::server::health_reporter();
+        (Some(reporter), Some(health))
+    } else {
+        (None, None)
+    };
+    tracing::info!(
+        %listen_addr,
+        reflection = options.reflection,
+        health = options.health,
+        auth = options.auth.is_enabled(),
+        "gRPC server listening"
+    );
+
+    let server = tonic::transport::Server::builder()
+        .trace_fn(grpc_request_span)
+        .add_service(client)
+        .add_service(worker)
+        .add_service(admin)
+        .add_optional_service(reflection)
+        .add_optional_service(health)
+        .serve_with_incoming_shutdown(incoming, shutdown);
+
+    match reporter {
+        // 健康状态随服务器一起停止更新
+        Some(reporter) => tokio::select! {
+            result = server => result?,
+            _ = report_health(scheduler, reporter, services, HEALTH_CHECK_INTERVAL) => {}
+        },
+        None => server.await?,
+    }
+
+    Ok(())
+}
+
+fn service_name<S: NamedService>(_: &S) -> &'static str {
+    S::NAME
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::l0_memory::L0MemoryStore;
+    use crate::scheduler::SchedulerConfig;
+    use tokio_stream::StreamExt;
+
+    type TestScheduler = Arc<Scheduler<Arc<L0MemoryStore>>>;
+
+    async fn register(
+        scheduler: &TestScheduler,
+        worker_id: &str,
+    ) -> WorkerServiceImpl<Arc<L0MemoryStore>> {
+        let worker = WorkerServiceImpl::new(scheduler.clone());
+        worker
+            .register(Request::new(proto::RegisterRequest {
+                worker_id: worker_id.to_string(),
+                service_name: "test-service".to_string(),
+                provides: vec![proto::ServiceResource {
+                    name: "start".to_string(),
+                    r#type: proto::ResourceType::Step as i32,
+                    metadata: None,
+                }],
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+        worker
+    }
+
+    async fn poll(
+        worker: &WorkerServiceImpl<Arc<L0MemoryStore>>,
+        worker_id: &str,
+    ) -> Vec<proto::Task> {
+        let mut stream = worker
+            .poll_tasks(Request::new(proto::PollRequest {
+                worker_id: worker_id.to_string(),
+                max_tasks: 10,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        // 流保持打开，读完当前可分发的 task 即断开
+        let mut tasks = Vec::new();
+        while let Ok(Some(task)) =
+            tokio::time::timeout(Duration::from_millis(50), stream.next()).await
+        {
+            tasks.push(task.unwrap());
+        }
+        tasks
+    }
+
+    async fn complete(worker: &WorkerServiceImpl<Arc<L0MemoryStore>>, task_id: &str) {
+        worker
+            .complete_step(Request::new(proto::CompleteStepRequest {
+                task_id: task_id.to_string(),
+                result: b"done".to_vec(),
+                error: String::new(),
+                worker_id: "worker-1".to_string(),
+                start_children: vec![],
+            }))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_poll_then_complete_advances_workflow() {
+        let store = Arc::new(L0MemoryStore::new());
+        let scheduler: TestScheduler = Arc::new(Scheduler::new(store));
+        let client = ClientServiceImpl::new(scheduler.clone());
+        let worker = register(&scheduler, "worker-1").await;
+
+        let workflow_id = client
+            .start_workflow(Request::new(proto::StartWorkflowRequest {
+                workflow_type: "test-type".to_string(),
+                input: vec![],
+                idempotency_key: String::new(),
+                priority: proto::Priority::Normal as i32,
+                skip_schema_validation: false,
+                version: 0,
+                dynamic: false,
+                memo: Default::default(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .workflow_id;
+
+        let tasks = poll(&worker, "worker-1").await;
+        assert_eq!(tasks.len(), 1);
+        let task_id = TaskId::parse(&tasks[0].task_id).unwrap();
+        assert_eq!(
+            scheduler.lease(&task_id).await.unwrap().worker_id,
+            "worker-1"
+        );
+
+        complete(&worker, &tasks[0].task_id).await;
+
+        let workflow = scheduler
+            .persistence
+            .get_workflow(&workflow_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            workflow.state,
+            WorkflowState::Completed {
+                result: b"done".to_vec().into()
+            }
+        );
+        assert!(scheduler.lease(&task_id).await.is_none());
+        assert!(poll(&worker, "worker-1").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_poll_stream_delivers_tasks_as_workflows_start() {
+        let scheduler: TestScheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        let client = ClientServiceImpl::new(scheduler.clone());
+        let worker = register(&scheduler, "worker-1").await;
+        let start = || {
+            Request::new(proto::StartWorkflowRequest {
+                workflow_type: "test-type".to_string(),
+                input: vec![],
+                idempotency_key: String::new(),
+                priority: proto::Priority::Normal as i32,
+                skip_schema_validation: false,
+                version: 0,
+                dynamic: false,
+                memo: Default::default(),
+            })
+        };
+
+        let mut stream = worker
+            .poll_tasks(Request::new(proto::PollRequest {
+                worker_id: "worker-1".to_string(),
+                max_tasks: 2,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let idle = tokio::time::timeout(Duration::from_millis(50), stream.next()).await;
+        assert!(idle.is_err(), "stream should stay open while idle");
+
+        for _ in 0..2 {
+            let workflow_id = client
+                .start_workflow(start())
+                .await
+                .unwrap()
+                .into_inner()
+                .workflow_id;
+            let task = tokio::time::timeout(Duration::from_secs(1), stream.next())
+                .await
+                .expect("task should be pushed without re-polling")
+                .unwrap()
+                .unwrap();
+            assert_eq!(task.workflow_id, workflow_id);
+        }
+
+        // 送满 max_tasks 后流结束
+        let end = tokio::time::timeout(Duration::from_secs(1), stream.next()).await;
+        assert!(matches!(end, Ok(None)));
+    }
+
+    #[tokio::test]
+    async fn test_poll_stream_ends_on_shutdown() {
+        let scheduler: TestScheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        let worker = register(&scheduler, "worker-1").await;
+        let mut stream = worker
+            .poll_tasks(Request::new(proto::PollRequest {
+                worker_id: "worker-1".to_string(),
+                max_tasks: 2,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert!(scheduler.shutdown(Duration::from_secs(1)).await.is_empty());
+        let end = tokio::time::timeout(Duration::from_secs(1), stream.next()).await;
+        assert!(matches!(end, Ok(None)));
+    }
+
+    #[tokio::test]
+    async fn test_signal_releases_waiting_step_on_open_stream() {
+        use crate::definition::{StepDefinition, WorkflowDefinition};
+
+        let scheduler: TestScheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        scheduler
+            .register_definition(WorkflowDefinition::new(
+                "approval",
+                vec![StepDefinition::new("start").wait_for_signal("approved")],
+            ))
+            .await
+            .unwrap();
+        let client = ClientServiceImpl::new(scheduler.clone());
+        let worker = register(&scheduler, "worker-1").await;
+        let workflow_id = client
+            .start_workflow(Request::new(proto::StartWorkflowRequest {
+                workflow_type: "approval".to_string(),
+                input: vec![],
+                idempotency_key: String::new(),
+                priority: proto::Priority::Normal as i32,
+                skip_schema_validation: false,
+                version: 0,
+                dynamic: false,
+                memo: Default::default(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .workflow_id;
+
+        let mut stream = worker
+            .poll_tasks(Request::new(proto::PollRequest {
+                worker_id: "worker-1".to_string(),
+                max_tasks: 1,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let idle = tokio::time::timeout(Duration::from_millis(50), stream.next()).await;
+        assert!(idle.is_err(), "step must wait for the signal");
+
+        let signal = |workflow_id: &str| {
+            Request::new(proto::SignalWorkflowRequest {
+                workflow_id: workflow_id.to_string(),
+                signal_name: "approved".to_string(),
+                payload: b"yes".to_vec(),
+            })
+        };
+        client.signal_workflow(signal(&workflow_id)).await.unwrap();
+        let task = tokio::time::timeout(Duration::from_secs(1), stream.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(task.step_name, "start");
+        let delivered = task.signal.unwrap();
+        assert_eq!(delivered.name, "approved");
+        assert_eq!(delivered.payload, b"yes".to_vec());
+
+        let status = client.signal_workflow(signal("missing")).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_notifies_worker_and_rejects_completion() {
+        let scheduler: TestScheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        let client = ClientServiceImpl::new(scheduler.clone());
+        let worker = register(&scheduler, "worker-1").await;
+        let workflow_id = client
+            .start_workflow(Request::new(proto::StartWorkflowRequest {
+                workflow_type: "order".to_string(),
+                input: vec![],
+                idempotency_key: String::new(),
+                priority: proto::Priority::Normal as i32,
+                skip_schema_validation: false,
+                version: 0,
+                dynamic: false,
+                memo: Default::default(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .workflow_id;
+        let tasks = poll(&worker, "worker-1").await;
+        assert_eq!(tasks.len(), 1);
+
+        let mut cancellations = worker
+            .watch_cancellations(Request::new(proto::WatchCancellationsRequest {
+                worker_id: "worker-1".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let cancelled = client
+            .cancel_workflow(Request::new(proto::CancelRequest {
+                workflow_id: workflow_id.clone(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(cancelled.success);
+
+        let message = tokio::time::timeout(Duration::from_secs(1), cancellations.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(message.task_id, tasks[0].task_id);
+        assert_eq!(message.workflow_id, workflow_id);
+        assert_eq!(message.step_name, "start");
+
+        let status = worker
+            .complete_step(Request::new(proto::CompleteStepRequest {
+                task_id: tasks[0].task_id.clone(),
+                result: b"done".to_vec(),
+                error: String::new(),
+                worker_id: "worker-1".to_string(),
+                start_children: vec![],
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Cancelled);
+
+        let again = client
+            .cancel_workflow(Request::new(proto::CancelRequest { workflow_id }))
+            .await
+            .unwrap_err();
+        assert_eq!(again.code(), tonic::Code::FailedPrecondition);
+        assert!(again.message().contains("is CANCELLED"));
+        let status = worker
+            .watch_cancellations(Request::new(proto::WatchCancellationsRequest {
+                worker_id: "unknown".to_string(),
+            }))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_list_workflows_pages_with_filters() {
+        let store = Arc::new(L0MemoryStore::new());
+        let base = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        for i in 0..5 {
+            let mut workflow = Workflow::new(format!("wf-{}", i), "order".to_string(), vec![]);
+            workflow.started_at = base + chrono::Duration::seconds(i);
+            if i < 3 {
+                workflow.state = WorkflowState::Running {
+                    current_step: Some("charge".to_string()),
+                };
+            }
+            store.save_workflow(&workflow).await.unwrap();
+        }
+        let client = ClientServiceImpl::new(Arc::new(Scheduler::new(store)));
+        let list = |request: proto::ListWorkflowsRequest| {
+            let client = &client;
+            async move { client.list_workflows(Request::new(request)).await }
+        };
+
+        let first = list(proto::ListWorkflowsRequest {
+            state: Some(proto::State::Running as i32),
+            page_size: 2,
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+        let ids: Vec<_> = first
+            .workflows
+            .iter()
+            .map(|w| w.workflow_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["wf-0", "wf-1"]);
+        assert_eq!(first.workflows[0].state, proto::State::Running as i32);
+        assert_eq!(first.workflows[0].current_step, "charge");
+        assert_eq!(first.workflows[0].started_at, base.timestamp());
+
+        let second = list(proto::ListWorkflowsRequest {
+            state: Some(proto::State::Running as i32),
+            page_size: 2,
+            page_token: first.next_page_token,
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+        assert_eq!(second.workflows.len(), 1);
+        assert_eq!(second.workflows[0].workflow_id, "wf-2");
+        assert!(second.next_page_token.is_empty());
+
+        let skipped = list(proto::ListWorkflowsRequest {
+            offset: 3,
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+        let ids: Vec<_> = skipped
+            .workflows
+            .iter()
+            .map(|w| w.workflow_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["wf-3", "wf-4"]);
+
+        let window = list(proto::ListWorkflowsRequest {
+            created_after: base.timestamp() + 1,
+            created_before: base.timestamp() + 3,
+            order: proto::ListOrder::StartedAtDesc as i32,
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+        let ids: Vec<_> = window
+            .workflows
+            .iter()
+            .map(|w| w.workflow_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["wf-2", "wf-1"]);
+        assert_eq!(window.workflows[0].current_step, "charge");
+
+        let status = list(proto::ListWorkflowsRequest {
+            page_token: "not-a-token".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_poll_stream_releases_undelivered_task_on_disconnect() {
+        let scheduler: TestScheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        let client = ClientServiceImpl::new(scheduler.clone());
+        let worker = register(&scheduler, "worker-1").await;
+
+        let stream = worker
+            .poll_tasks(Request::new(proto::PollRequest {
+                worker_id: "worker-1".to_string(),
+                max_tasks: 1,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        drop(stream);
+
+        client
+            .start_workflow(Request::new(proto::StartWorkflowRequest {
+                workflow_type: "test-type".to_string(),
+                input: vec![],
+                idempotency_key: String::new(),
+                priority: proto::Priority::Normal as i32,
+                skip_schema_validation: false,
+                version: 0,
+                dynamic: false,
+                memo: Default::default(),
+            }))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        // 断开的流不会占住 task，重连后仍能领取
+        let tasks = poll(&worker, "worker-1").await;
+        assert_eq!(tasks.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_complete_without_lease_after_restart() {
+        let store = Arc::new(L0MemoryStore::new());
+        let before: TestScheduler = Arc::new(Scheduler::new(store.clone()));
+        let mut workflow = Workflow::new("wf-1".to_string(), "test-type".to_string(), vec![]);
+        workflow.state = workflow.state.try_start().unwrap();
+        store.save_workflow(&workflow).await.unwrap();
+
+        let worker = register(&before, "worker-1").await;
+        let tasks = poll(&worker, "worker-1").await;
+        assert_eq!(tasks.len(), 1);
+
+        // 服务器重启：新的调度器没有任何租约
+        let after: TestScheduler = Arc::new(Scheduler::new(store.clone()));
+        let worker = WorkerServiceImpl::new(after.clone());
+        complete(&worker, &tasks[0].task_id).await;
+
+        let workflow = store.get_workflow("wf-1").await.unwrap().unwrap();
+        assert!(matches!(workflow.state, WorkflowState::Completed { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_register_sets_retry_policy() {
+        let scheduler: TestScheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        WorkerServiceImpl::new(scheduler.clone())
+            .register(Request::new(proto::RegisterRequest {
+                worker_id: "worker-1".to_string(),
+                provides: vec![proto::ServiceResource {
+                    name: "charge".to_string(),
+                    r#type: proto::ResourceType::Activity as i32,
+                    metadata: Some(proto::ResourceMetadata {
+                        max_attempts: 5,
+                        ..Default::default()
+                    }),
+                }],
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+
+        assert_eq!(scheduler.retry_policy("charge").await.max_attempts, 5);
+        assert_eq!(
+            scheduler.retry_policy("start").await,
+            RetryPolicy::default()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_services_reports_registered_resources() {
+        let scheduler: TestScheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        let worker = WorkerServiceImpl::new(scheduler.clone());
+        for (service_name, resources) in
+            [("payments", vec!["refund", "charge"]), ("billing", vec![])]
+        {
+            worker
+                .register(Request::new(proto::RegisterRequest {
+                    worker_id: format!("{}-worker", service_name),
+                    service_name: service_name.to_string(),
+                    group: "prod".to_string(),
+                    language: vec!["typescript".to_string()],
+                    endpoint: format!("{}:50051", service_name),
+                    provides: resources
+                        .into_iter()
+                        .map(|name| proto::ServiceResource {
+                            name: name.to_string(),
+                            r#type: proto::ResourceType::Activity as i32,
+                            metadata: Some(proto::ResourceMetadata {
+                                max_attempts: 5,
+                                ..Default::default()
+                            }),
+                        })
+                        .collect(),
+                }))
+                .await
+                .unwrap();
+        }
+
+        let services = ClientServiceImpl::new(scheduler)
+            .list_services(Request::new(proto::ListServicesRequest {}))
+            .await
+            .unwrap()
+            .into_inner()
+            .services;
+        let names: Vec<_> = services.iter().map(|s| s.service_name.as_str()).collect();
+        assert_eq!(names, vec!["billing", "payments"]);
+        let payments = &services[1];
+        assert_eq!(payments.group, "prod");
+        assert_eq!(payments.languages, vec!["typescript"]);
+        assert_eq!(payments.endpoint, "payments:50051");
+        assert_eq!(payments.instance_id, "payments-worker");
+        let resources: Vec<_> = payments.provides.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(resources, vec!["charge", "refund"]);
+        assert_eq!(
+            payments.provides[0].r#type,
+            proto::ResourceType::Activity as i32
+        );
+        assert_eq!(
+            payments.provides[0].metadata.as_ref().unwrap().max_attempts,
+            5
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unregistered_worker_must_register() {
+        let scheduler: TestScheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        let worker = WorkerServiceImpl::new(scheduler.clone());
+
+        let status = worker
+            .poll_tasks(Request::new(proto::PollRequest {
+                worker_id: "worker-1".to_string(),
+                max_tasks: 10,
+            }))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+
+        let heartbeat = |worker_id: &str| proto::HeartbeatRequest {
+            worker_id: worker_id.to_string(),
+            ..Default::default()
+        };
+        let response = worker
+            .heartbeat(Request::new(heartbeat("worker-1")))
+            .await
+            .unwrap();
+        assert!(!response.into_inner().ok);
+
+        let worker = register(&scheduler, "worker-1").await;
+        let response = worker
+            .heartbeat(Request::new(heartbeat("worker-1")))
+            .await
+            .unwrap();
+        assert!(response.into_inner().ok);
+        assert!(poll(&worker, "worker-1").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_start_rejects_unknown_workflow_type() {
+        use crate::definition::{StepDefinition, WorkflowDefinition};
+
+        let scheduler: TestScheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        scheduler
+            .register_definition(WorkflowDefinition::new(
+                "order",
+                vec![StepDefinition::new("charge")],
+            ))
+            .await
+            .unwrap();
+        let client = ClientServiceImpl::new(scheduler.clone());
+        let start = |workflow_type: &str| proto::StartWorkflowRequest {
+            workflow_type: workflow_type.to_string(),
+            input: vec![],
+            idempotency_key: String::new(),
+            priority: proto::Priority::Normal as i32,
+            skip_schema_validation: false,
+            version: 0,
+            dynamic: false,
+            memo: Default::default(),
+        };
+
+        let status = client
+            .start_workflow(Request::new(start("refund")))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert!(status.message().contains("refund"));
+        assert!(status.message().contains("order"));
+
+        client
+            .start_workflow(Request::new(start("order")))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_start_rejects_oversized_input() {
+        let scheduler: TestScheduler = Arc::new(Scheduler::with_config(
+            Arc::new(L0MemoryStore::new()),
+            SchedulerConfig {
+                max_input_bytes: 8,
+                ..Default::default()
+            },
+        ));
+        let client = ClientServiceImpl::new(scheduler.clone());
+
+        let status = client
+            .start_workflow(Request::new(proto::StartWorkflowRequest {
+                workflow_type: "order".to_string(),
+                input: vec![0; 64],
+                idempotency_key: String::new(),
+                priority: proto::Priority::Normal as i32,
+                skip_schema_validation: false,
+                version: 0,
+                dynamic: false,
+                memo: Default::default(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        assert!(status.message().contains("PAYLOAD_TOO_LARGE"));
+        assert!(scheduler
+            .persistence
+            .list_workflows(None)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_start_with_idempotency_key_returns_existing_workflow() {
+        let scheduler: TestScheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        let client = ClientServiceImpl::new(scheduler.clone());
+        let start = |workflow_type: &str| {
+            Request::new(proto::StartWorkflowRequest {
+                workflow_type: workflow_type.to_string(),
+                input: vec![],
+                idempotency_key: "order-42".to_string(),
+                priority: proto::Priority::Normal as i32,
+                skip_schema_validation: false,
+                version: 0,
+                dynamic: false,
+                memo: Default::default(),
+            })
+        };
+
+        let first = client
+            .start_workflow(start("test-type"))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(first.workflow_id, "order-42");
+        assert!(!first.already_exists);
+        let retried = client
+            .start_workflow(start("test-type"))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(retried.workflow_id, "order-42");
+        assert!(retried.already_exists);
+        assert_eq!(
+            scheduler
+                .persistence
+                .list_workflows(None)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let status = client.start_workflow(start("refund")).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::AlreadyExists);
+    }
+
+    #[tokio::test]
+    async fn test_complete_with_error_fails_workflow_once_retries_exhausted() {
+        let store = Arc::new(L0MemoryStore::new());
+        let scheduler: TestScheduler = Arc::new(Scheduler::new(store.clone()));
+        let mut workflow = Workflow::new("wf-1".to_string(), "test-type".to_string(), vec![]);
+        workflow.state = workflow.state.try_start().unwrap();
+        store.save_workflow(&workflow).await.unwrap();
+
+        let worker = WorkerServiceImpl::new(scheduler.clone());
+        worker
+            .register(Request::new(proto::RegisterRequest {
+                worker_id: "worker-1".to_string(),
+                service_name: "test-service".to_string(),
+                provides: vec![proto::ServiceResource {
+                    name: "start".to_string(),
+                    r#type: proto::ResourceType::Step as i32,
+                    metadata: Some(proto::ResourceMetadata {
+                        max_attempts: 1,
+                        ..Default::default()
+                    }),
+                }],
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+
+        let tasks = poll(&worker, "worker-1").await;
+        assert_eq!(tasks.len(), 1);
+        worker
+            .complete_step(Request::new(proto::CompleteStepRequest {
+                task_id: tasks[0].task_id.clone(),
+                result: vec![],
+                error: "card declined".to_string(),
+                worker_id: "worker-1".to_string(),
+                start_children: vec![],
+            }))
+            .await
+            .unwrap();
+
+        let workflow = store.get_workflow("wf-1").await.unwrap().unwrap();
+        assert_eq!(
+            workflow.state,
+            WorkflowState::Failed {
+                error: "card declined".to_string()
+            }
+        );
+        assert!(poll(&worker, "worker-1").await.is_empty());
+
+        let client = ClientServiceImpl::new(scheduler.clone());
+        let retry = || {
+            Request::new(proto::RetryWorkflowRequest {
+                workflow_id: "wf-1".to_string(),
+            })
+        };
+        client.retry_workflow(retry()).await.unwrap();
+        let tasks = poll(&worker, "worker-1").await;
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].step_name, "start");
+        let status = client.retry_workflow(retry()).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn test_await_result_blocks_until_completion() {
+        let store = Arc::new(L0MemoryStore::new());
+        let scheduler: TestScheduler = Arc::new(Scheduler::new(store.clone()));
+        let mut workflow = Workflow::new("wf-1".to_string(), "test-type".to_string(), vec![]);
+        workflow.state = workflow.state.try_start().unwrap();
+        store.save_workflow(&workflow).await.unwrap();
+
+        let client = ClientServiceImpl::new(scheduler.clone());
+        let waiter = tokio::spawn(async move {
+            client
+                .await_result(Request::new(proto::AwaitResultRequest {
+                    workflow_id: "wf-1".to_string(),
+                    timeout_seconds: 5,
+                }))
+                .await
+        });
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!waiter.is_finished());
+        scheduler
+            .complete_task(&TaskId::new("wf-1", "start").to_string(), b"done".to_vec())
+            .await
+            .unwrap();
+
+        let result = waiter.await.unwrap().unwrap().into_inner();
+        assert_eq!(result.state, proto::State::Completed as i32);
+        assert_eq!(result.result, b"done".to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_await_result_honors_grpc_deadline() {
+        let store = Arc::new(L0MemoryStore::new());
+        let scheduler: TestScheduler = Arc::new(Scheduler::new(store.clone()));
+        let mut workflow = Workflow::new("wf-1".to_string(), "test-type".to_string(), vec![]);
+        workflow.state = workflow.state.try_start().unwrap();
+        store.save_workflow(&workflow).await.unwrap();
+
+        let mut request = Request::new(proto::AwaitResultRequest {
+            workflow_id: "wf-1".to_string(),
+            timeout_seconds: 0,
+        });
+        request
+            .metadata_mut()
+            .insert("grpc-timeout", "50m".parse().unwrap());
+        let status = ClientServiceImpl::new(scheduler.clone())
+            .await_result(request)
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::DeadlineExceeded);
+
+        // 已结束的 workflow 立即返回
+        store
+            .update_workflow_state("wf-1", WorkflowState::Cancelled)
+            .await
+            .unwrap();
+        let result = ClientServiceImpl::new(scheduler)
+            .await_result(Request::new(proto::AwaitResultRequest {
+                workflow_id: "wf-1".to_string(),
+                timeout_seconds: 1,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(result.state, proto::State::Cancelled as i32);
+    }
+
+    #[tokio::test]
+    async fn test_report_step_for_unknown_workflow_is_not_found() {
+        let scheduler: TestScheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        let worker = register(&scheduler, "worker-1").await;
+        let status = worker
+            .report_step(Request::new(proto::ReportStepRequest {
+                workflow_id: "missing".to_string(),
+                step_name: "start".to_string(),
+                status: proto::StepStatus::StepStarted as i32,
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+        assert!(scheduler.tracker.get_execution("missing").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_failed_report_from_another_worker_is_rejected() {
+        let store = Arc::new(L0MemoryStore::new());
+        let scheduler: TestScheduler = Arc::new(Scheduler::new(store.clone()));
+        let mut workflow = Workflow::new("wf-1".to_string(), "test-type".to_string(), vec![]);
+        workflow.state = workflow.state.try_start().unwrap();
+        store.save_workflow(&workflow).await.unwrap();
+        let worker = register(&scheduler, "worker-1").await;
+        let tasks = poll(&worker, "worker-1").await;
+        assert_eq!(tasks.len(), 1);
+
+        let failed = |worker_id: &str| proto::ReportStepRequest {
+            workflow_id: "wf-1".to_string(),
+            step_name: "start".to_string(),
+            status: proto::StepStatus::StepFailed as i32,
+            error: "boom".to_string(),
+            worker_id: worker_id.to_string(),
+            ..Default::default()
+        };
+        // 其他 worker 的会话令牌，以及显式填写的其他 worker id
+        let mut session = Request::new(failed(""));
+        session
+            .extensions_mut()
+            .insert(Principal::Worker("worker-2".to_string()));
+        let status = worker.report_step(session).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+        let status = worker
+            .report_step(Request::new(failed("worker-2")))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+
+        let task_id = TaskId::parse(&tasks[0].task_id).unwrap();
+        assert_eq!(
+            scheduler.lease(&task_id).await.unwrap().worker_id,
+            "worker-1"
+        );
+        let workflow = store.get_workflow("wf-1").await.unwrap().unwrap();
+        assert!(matches!(workflow.state, WorkflowState::Running { .. }));
+
+        worker
+            .report_step(Request::new(failed("worker-1")))
+            .await
+            .unwrap();
+        assert!(scheduler.lease(&task_id).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_complete_step_reports_structured_error_codes() {
+        let store = Arc::new(L0MemoryStore::new());
+        let scheduler: TestScheduler = Arc::new(Scheduler::new(store.clone()));
+        let mut workflow = Workflow::new("wf-1".to_string(), "test-type".to_string(), vec![]);
+        workflow.state = workflow.state.try_start().unwrap();
+        store.save_workflow(&workflow).await.unwrap();
+        let worker = register(&scheduler, "worker-1").await;
+        let tasks = poll(&worker, "worker-1").await;
+        assert_eq!(tasks.len(), 1);
+
+        let error_code = |status: &Status| {
+            status
+                .metadata()
+                .get(ERROR_CODE_METADATA)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        // 未填写 worker_id 时无法证明持有租约
+        let status = worker
+            .complete_step(Request::new(proto::CompleteStepRequest {
+                task_id: tasks[0].task_id.clone(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+        assert_eq!(error_code(&status).as_deref(), Some("NOT_OWNER"));
+
+        complete(&worker, &tasks[0].task_id).await;
+        let status = worker
+            .complete_step(Request::new(proto::CompleteStepRequest {
+                task_id: tasks[0].task_id.clone(),
+                worker_id: "worker-1".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::AlreadyExists);
+        assert_eq!(error_code(&status).as_deref(), Some("ALREADY_COMPLETED"));
+    }
+}
@@ -0,0 +1,91 @@
+//! gRPC 健康检查
+//!
+//! 按 `grpc.health.v1` 协议上报服务状态，供 Kubernetes 等探针使用。
+//! 调度器正常运行且持久化层可读时为 SERVING；开始停机（等待进行中的 step）
+//! 或持久化层读取失败时为 NOT_SERVING，持久化层恢复后自动切回。
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tonic_health::server::HealthReporter;
+use tonic_health::ServingStatus;
+
+use crate::persistence::Persistence;
+use crate::scheduler::Scheduler;
+use crate::shutdown::ShutdownState;
+
+/// 检查持久化层的间隔（停机阶段变化时立即更新）
+pub const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 探测持久化层时读取的 workflow id，不需要存在
+const PROBE_WORKFLOW_ID: &str = "__aether_health_probe__";
+
+/// 当前的服务状态
+pub async fn serving_status<P: Persistence>(scheduler: &Scheduler<P>) -> ServingStatus {
+    if scheduler.shutdown_state() != ShutdownState::Running {
+        return ServingStatus::NotServing;
+    }
+    match scheduler.persistence.get_workflow(PROBE_WORKFLOW_ID).await {
+        Ok(_) => ServingStatus::Serving,
+        Err(e) => {
+            tracing::warn!("health check: persistence read failed: {}", e);
+            ServingStatus::NotServing
+        }
+    }
+}
+
+/// 持续更新 `services` 的健康状态，空字符串表示整个服务器
+///
+/// 不会自行返回，随 gRPC 服务器一起停止。
+pub async fn report_health<P: Persistence>(
+    scheduler: Arc<Scheduler<P>>,
+    mut reporter: HealthReporter,
+    services: Vec<&'static str>,
+    interval: Duration,
+) {
+    let mut shutdown = scheduler.shutdown.subscribe();
+    let mut current = None;
+    loop {
+        shutdown.borrow_and_update();
+        let status = serving_status(&scheduler).await;
+        if current != Some(status) {
+            tracing::info!(?status, "gRPC health status changed");
+            for service in &services {
+                reporter.set_service_status(service, status).await;
+            }
+            current = Some(status);
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            changed = shutdown.changed() => {
+                if changed.is_err() {
+                    // 调度器已释放，状态不会再变化
+                    std::future::pending::<()>().await;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::faulty::FaultyStore;
+    use crate::persistence::l0_memory::L0MemoryStore;
+
+    #[tokio::test]
+    async fn test_status_follows_persistence_and_shutdown() {
+        let scheduler = Scheduler::new(FaultyStore::new(L0MemoryStore::new(), 0));
+        assert_eq!(serving_status(&scheduler).await, ServingStatus::Serving);
+
+        // 持久化层故障期间不可用，恢复后自动切回
+        scheduler.persistence.fail_every(1);
+        assert_eq!(serving_status(&scheduler).await, ServingStatus::NotServing);
+        scheduler.persistence.fail_every(0);
+        assert_eq!(serving_status(&scheduler).await, ServingStatus::Serving);
+
+        // 开始停机后不可用
+        scheduler.begin_shutdown();
+        assert_eq!(serving_status(&scheduler).await, ServingStatus::NotServing);
+    }
+}
@@ -0,0 +1,451 @@
+//! 导入导出的 workflow 执行记录，用于本地调试
+//!
+//! 把 [`crate::export`] 导出的文档写回持久化层和追踪器：已完成 step 的结果写入
+//! `completed_steps`，step 执行记录写入追踪器，dashboard 中可以看到与原服务器相同的执行历史。
+//! 导入的 workflow 停在失败的 step 处：以 Failed 状态保存，不会被分发；导出时仍在执行的
+//! workflow 同样以 Failed 保存，正在执行的 step 记为失败。选择继续执行时按重试处理
+//! （见 [`crate::retry`]），调度器从失败的 step 重新分发。事件日志不导入。
+
+use std::collections::HashMap;
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+
+use crate::export::{DecodeError, ExportedPayload, ExportedStep, WorkflowExport};
+use crate::payload::Payload;
+use crate::persistence::Persistence;
+use crate::scheduler::Scheduler;
+use crate::state_machine::{Workflow, WorkflowState};
+use crate::tracker::{StepExecution, StepExecutionStatus, StepProgress, WorkflowExecution};
+
+/// 导入导出时正在执行的 step 的失败原因
+const INTERRUPTED_BY_IMPORT: &str = "Interrupted by import";
+
+/// 导入选项
+#[derive(Debug, Clone, Default)]
+pub struct ImportOptions {
+    /// 沿用导出文档中的 workflow id，默认生成新 id
+    pub keep_id: bool,
+    /// 导入后从失败的 step 继续执行，默认停在失败处
+    pub resume: bool,
+}
+
+/// 导入的结果
+#[derive(Debug, Clone)]
+pub struct ImportedWorkflow {
+    /// 导入后的 workflow（继续执行时为重试后的状态）
+    pub workflow: Workflow,
+    /// 继续执行时重新分发的 step
+    pub resumed_steps: Vec<String>,
+}
+
+/// 导入 workflow 的错误
+#[derive(Debug)]
+pub enum ImportError {
+    /// 文档无法读取或格式版本不受支持
+    Decode(DecodeError),
+    /// 重放需要的负载在导出时被截断
+    Truncated(String),
+    /// 同 id 的 workflow 已存在（`keep_id`）
+    AlreadyExists(String),
+    /// 已完成、取消或强制结束的 workflow 无法继续执行
+    NotResumable { workflow_id: String, status: String },
+    /// 持久化层错误
+    Persistence(anyhow::Error),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Decode(e) => write!(f, "{}", e),
+            ImportError::Truncated(what) => write!(
+                f,
+                "The export truncated the {}; export again without max_payload_bytes to replay it",
+                what
+            ),
+            ImportError::AlreadyExists(workflow_id) => {
+                write!(f, "Workflow {} already exists", workflow_id)
+            }
+            ImportError::NotResumable {
+                workflow_id,
+                status,
+            } => write!(
+                f,
+                "Workflow {} is {} and cannot be resumed",
+                workflow_id, status
+            ),
+            ImportError::Persistence(e) => write!(f, "Persistence error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<DecodeError> for ImportError {
+    fn from(e: DecodeError) -> Self {
+        ImportError::Decode(e)
+    }
+}
+
+impl From<anyhow::Error> for ImportError {
+    fn from(e: anyhow::Error) -> Self {
+        ImportError::Persistence(e)
+    }
+}
+
+impl<P: Persistence> Scheduler<P> {
+    /// 导入 JSON 或 JSONL 格式的导出文档
+    pub async fn import_workflow(
+        &self,
+        document: &[u8],
+        options: &ImportOptions,
+    ) -> Result<ImportedWorkflow, ImportError> {
+        let export = WorkflowExport::decode(document)?;
+        let exported = export.workflow;
+        let workflow_id = if options.keep_id {
+            exported.workflow_id.clone()
+        } else {
+            uuid::Uuid::new_v4().to_string()
+        };
+
+        let input = payload(&exported.input, "workflow input")?;
+        let state = match exported.status.as_str() {
+            "COMPLETED" => WorkflowState::Completed {
+                result: match &exported.output {
+                    Some(output) => payload(output, "workflow output")?,
+                    None => Payload::default(),
+                },
+            },
+            "FAILED" => WorkflowState::Failed {
+                error: exported.error.clone().unwrap_or_default(),
+            },
+            "CANCELLED" => WorkflowState::Cancelled,
+            "TERMINATED" => WorkflowState::Terminated {
+                reason: exported.error.clone().unwrap_or_default(),
+                requested_by: exported.terminated_by.clone(),
+            },
+            // 未结束的 workflow 停在正在执行的 step 处
+            "PENDING" | "RUNNING" => WorkflowState::Failed {
+                error: format!("Imported while {}", exported.status),
+            },
+            status => {
+                return Err(
+                    DecodeError::Invalid(format!("unknown workflow status {}", status)).into(),
+                )
+            }
+        };
+        if options.resume && state.try_retry().is_err() {
+            return Err(ImportError::NotResumable {
+                workflow_id: exported.workflow_id,
+                status: exported.status,
+            });
+        }
+
+        let mut completed_steps = HashMap::new();
+        for step in &export.steps {
+            if let ("COMPLETED", Some(output)) = (step.status.as_str(), &step.output) {
+                let what = format!("output of step {}", step.step_name);
+                completed_steps.insert(step.step_name.clone(), payload(output, &what)?);
+            }
+        }
+
+        let started_at = parse_time(&exported.started_at).unwrap_or_else(Utc::now);
+        let mut workflow = Workflow::new(
+            workflow_id.clone(),
+            exported.workflow_type.clone(),
+            input.data,
+        )
+        .with_priority(exported.priority.parse().unwrap_or_default());
+        if let Some(version) = exported.version {
+            workflow = workflow.with_definition_version(version);
+        }
+        workflow.state = state;
+        workflow.completed_steps = completed_steps;
+        workflow.started_at = started_at;
+        if !self.persistence.save_workflow_if_absent(&workflow).await? {
+            return Err(ImportError::AlreadyExists(workflow_id));
+        }
+
+        let completed_at = exported
+            .completed_at
+            .as_deref()
+            .and_then(parse_time)
+            .unwrap_or_else(Utc::now);
+        self.tracker
+            .restore_execution(WorkflowExecution {
+                workflow_id: workflow_id.clone(),
+                workflow_type: exported.workflow_type,
+                step_executions: export
+                    .steps
+                    .iter()
+                    .map(|step| (step.step_name.clone(), step_execution(step)))
+                    .collect(),
+                started_at,
+                completed_at: Some(completed_at),
+                current_step: None,
+                parent_workflow_id: None,
+                child_workflow_ids: Vec::new(),
+            })
+            .await;
+        tracing::info!(
+            workflow_id = %workflow_id,
+            exported_workflow_id = %exported.workflow_id,
+            resume = options.resume,
+            "workflow imported"
+        );
+
+        let mut resumed_steps = Vec::new();
+        if options.resume {
+            resumed_steps = self
+                .retry_workflow(&workflow_id)
+                .await
+                .map_err(anyhow::Error::from)?;
+            workflow = self
+                .persistence
+                .get_workflow(&workflow_id)
+                .await?
+                .unwrap_or(workflow);
+        }
+        Ok(ImportedWorkflow {
+            workflow,
+            resumed_steps,
+        })
+    }
+}
+
+fn payload(exported: &ExportedPayload, what: &str) -> Result<Payload, ImportError> {
+    exported
+        .to_payload()
+        .ok_or_else(|| ImportError::Truncated(what.to_string()))
+}
+
+/// 追踪器中的 step 记录；截断的负载只用于展示，不保留
+fn step_execution(step: &ExportedStep) -> StepExecution {
+    let status = match step.status.as_str() {
+        "COMPLETED" => StepExecutionStatus::Completed,
+        "FAILED" => StepExecutionStatus::Failed {
+            error: step.error.clone().unwrap_or_default(),
+        },
+        "RUNNING" => StepExecutionStatus::Failed {
+            error: INTERRUPTED_BY_IMPORT.to_string(),
+        },
+        "CANCELLED" => StepExecutionStatus::Cancelled,
+        _ => StepExecutionStatus::Pending,
+    };
+    let time = |value: &Option<String>| value.as_deref().and_then(parse_time);
+    StepExecution {
+        step_name: step.step_name.clone(),
+        status,
+        started_at: time(&step.started_at),
+        completed_at: time(&step.completed_at),
+        input: step
+            .input
+            .as_ref()
+            .and_then(ExportedPayload::to_payload)
+            .unwrap_or_default(),
+        output: step.output.as_ref().and_then(ExportedPayload::to_payload),
+        attempt: step.attempt.max(1),
+        dependencies: step.dependencies.clone(),
+        progress: step.progress.as_ref().map(|progress| StepProgress {
+            percent: progress.percent,
+            message: progress.message.clone(),
+            details: progress
+                .details
+                .as_ref()
+                .and_then(ExportedPayload::to_payload),
+        }),
+        last_heartbeat_at: time(&step.last_heartbeat_at),
+        timer_fire_at: None,
+        rejections: Vec::new(),
+        cache_hit: step.cache_hit,
+    }
+}
+
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::{StepDefinition, WorkflowDefinition};
+    use crate::export::{export_workflow, ExportFormat, ExportOptions};
+    use crate::persistence::l0_memory::L0MemoryStore;
+    use crate::scheduler::StartOptions;
+    use crate::task::RetryPolicy;
+
+    async fn scheduler() -> Scheduler<L0MemoryStore> {
+        let scheduler = Scheduler::new(L0MemoryStore::new());
+        let no_retries = RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        };
+        scheduler
+            .register_definition(WorkflowDefinition::new(
+                "order",
+                vec![
+                    StepDefinition::new("reserve"),
+                    StepDefinition::new("charge")
+                        .depends_on(["reserve"])
+                        .with_retry(no_retries),
+                ],
+            ))
+            .await
+            .unwrap();
+        scheduler
+            .register_worker(
+                "worker-1".to_string(),
+                "shop".to_string(),
+                "default".to_string(),
+                vec!["order".to_string()],
+                vec![],
+            )
+            .await;
+        scheduler
+    }
+
+    /// reserve 完成、charge 失败的 workflow 的导出文档
+    async fn failed_export(format: ExportFormat) -> (String, Vec<u8>) {
+        let scheduler = scheduler().await;
+        let workflow_id = scheduler
+            .start_workflow(
+                None,
+                "order".to_string(),
+                br#"{"id":1}"#.to_vec(),
+                StartOptions::default(),
+            )
+            .await
+            .unwrap()
+            .workflow
+            .id;
+        for step in ["reserve", "charge"] {
+            let task = scheduler.poll_tasks("worker-1", 1).await.unwrap().remove(0);
+            let lifecycle = scheduler.lifecycle();
+            lifecycle
+                .step_started(&workflow_id, step, task.input.data.clone())
+                .await
+                .unwrap();
+            if step == "reserve" {
+                lifecycle
+                    .complete_task(
+                        &task.task_id,
+                        Some("worker-1"),
+                        br#"{"held":true}"#.to_vec(),
+                    )
+                    .await
+                    .unwrap();
+            } else {
+                lifecycle
+                    .fail_task(&task.task_id, Some("worker-1"), "card declined".to_string())
+                    .await
+                    .unwrap();
+            }
+        }
+        let export = export_workflow(&scheduler, &workflow_id, &ExportOptions::default())
+            .await
+            .unwrap()
+            .unwrap();
+        (workflow_id, export.encode(format).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_import_pauses_at_failed_step_and_resumes() {
+        let (original_id, document) = failed_export(ExportFormat::Json).await;
+        let scheduler = scheduler().await;
+
+        let imported = scheduler
+            .import_workflow(&document, &ImportOptions::default())
+            .await
+            .unwrap();
+        let workflow_id = imported.workflow.id.clone();
+        assert_ne!(workflow_id, original_id);
+        assert!(imported.workflow.is_failed());
+        assert_eq!(
+            imported.workflow.completed_steps["reserve"].data,
+            br#"{"held":true}"#.to_vec()
+        );
+        let execution = scheduler.tracker.get_execution(&workflow_id).await.unwrap();
+        assert_eq!(
+            execution.step_executions["charge"].status,
+            StepExecutionStatus::Failed {
+                error: "card declined".to_string()
+            }
+        );
+        // 停在失败处，不分发
+        assert!(scheduler
+            .poll_tasks("worker-1", 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let resumed = scheduler.retry_workflow(&workflow_id).await.unwrap();
+        assert_eq!(resumed, vec!["charge".to_string()]);
+        let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].step_name, "charge");
+        assert_eq!(tasks[0].input.data, br#"{"id":1}"#.to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_import_jsonl_with_resume_and_kept_id() {
+        let (original_id, document) = failed_export(ExportFormat::Jsonl).await;
+        let scheduler = scheduler().await;
+        let options = ImportOptions {
+            keep_id: true,
+            resume: true,
+        };
+
+        let imported = scheduler
+            .import_workflow(&document, &options)
+            .await
+            .unwrap();
+        assert_eq!(imported.workflow.id, original_id);
+        assert!(matches!(
+            imported.workflow.state,
+            WorkflowState::Running { .. }
+        ));
+        assert_eq!(imported.resumed_steps, vec!["charge".to_string()]);
+        let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].step_name, "charge");
+
+        let err = scheduler
+            .import_workflow(&document, &options)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ImportError::AlreadyExists(id) if id == original_id));
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_newer_versions_and_truncated_payloads() {
+        let (_, document) = failed_export(ExportFormat::Json).await;
+        let scheduler = scheduler().await;
+
+        let mut value: serde_json::Value = serde_json::from_slice(&document).unwrap();
+        value["formatVersion"] = 99.into();
+        let err = scheduler
+            .import_workflow(
+                &serde_json::to_vec(&value).unwrap(),
+                &ImportOptions::default(),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ImportError::Decode(DecodeError::UnsupportedVersion(99))
+        ));
+
+        value["formatVersion"] = 1.into();
+        value["workflow"]["input"]["truncated"] = true.into();
+        let err = scheduler
+            .import_workflow(
+                &serde_json::to_vec(&value).unwrap(),
+                &ImportOptions::default(),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ImportError::Truncated(_)));
+    }
+}
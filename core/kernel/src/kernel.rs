@@ -1,7 +1,230 @@
-//! Core kernel module for Aether workflow engine
+//! Embeddable Aether kernel
+//!
+//! [`AetherKernel`] wires a scheduler to its persistence, the REST and gRPC
+//! servers, the optional dashboard and the background loops (lease expiry,
+//! worker eviction, timers, child workflows, retention), so an application can
+//! run the engine in its own binary without copying the CLI's setup:
+//!
+//! ```no_run
+//! # async fn run() -> anyhow::Result<()> {
+//! use aetherframework_kernel::{AetherKernel, StepDefinition, WorkflowDefinition};
+//!
+//! let kernel = AetherKernel::builder()
+//!     .http_addr("127.0.0.1:0")
+//!     .grpc_addr("127.0.0.1:0")
+//!     .build();
+//! kernel
+//!     .scheduler()
+//!     .register_definition(WorkflowDefinition::new("order", vec![StepDefinition::new("charge")]))
+//!     .await?;
+//! let addrs = kernel.start().await?;
+//! println!("gRPC on {}", addrs.grpc);
+//! kernel.shutdown().await?;
+//! # Ok(())
+//! # }
+//! ```
 
-pub struct AetherKernel {
-    // Kernel state and configuration
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use tokio::net::TcpListener;
+use tokio::sync::{oneshot, Mutex};
+use tokio::task::JoinHandle;
+
+use crate::api::auth::AuthConfig;
+use crate::child::spawn_child_wait_task;
+use crate::grpc_auth::GrpcAuthConfig;
+use crate::grpc_server::GrpcOptions;
+use crate::persistence::l0_memory::L0MemoryStore;
+use crate::persistence::Persistence;
+use crate::retention::spawn_retention_task;
+use crate::schedule::spawn_schedule_task;
+use crate::scheduler::{
+    spawn_lease_expiry_task, spawn_worker_eviction_task, Scheduler, SchedulerConfig,
+};
+use crate::server;
+use crate::server_info::ServerLimits;
+use crate::step_cache::spawn_step_cache_task;
+use crate::step_timeout::spawn_step_timeout_task;
+use crate::timer::spawn_timer_task;
+use crate::tracker::WorkflowTracker;
+
+/// Default REST listen address, same as `aether serve`
+pub const DEFAULT_HTTP_ADDR: &str = "0.0.0.0:7233";
+
+/// Default gRPC listen address, same as `aether serve`
+pub const DEFAULT_GRPC_ADDR: &str = "0.0.0.0:7234";
+
+/// Default dashboard WebSocket listen address, same as `aether serve`
+pub const DEFAULT_DASHBOARD_ADDR: &str = "0.0.0.0:7235";
+
+/// Addresses the kernel's servers are listening on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KernelAddrs {
+    pub http: SocketAddr,
+    pub grpc: SocketAddr,
+    /// `None` when the dashboard is disabled
+    pub dashboard: Option<SocketAddr>,
+}
+
+/// Builder for [`AetherKernel`], created by [`AetherKernel::builder`]
+pub struct AetherKernelBuilder<P: Persistence = Arc<L0MemoryStore>> {
+    persistence: P,
+    config: SchedulerConfig,
+    limits: ServerLimits,
+    tracker: Option<WorkflowTracker>,
+    concurrency_limits: Vec<(String, usize)>,
+    http_addr: String,
+    grpc_addr: String,
+    dashboard: bool,
+    dashboard_addr: String,
+    auth: AuthConfig,
+    grpc_options: GrpcOptions,
+}
+
+impl Default for AetherKernelBuilder {
+    fn default() -> Self {
+        AetherKernelBuilder {
+            persistence: Arc::new(L0MemoryStore::new()),
+            config: SchedulerConfig::default(),
+            limits: ServerLimits::default(),
+            tracker: None,
+            concurrency_limits: Vec::new(),
+            http_addr: DEFAULT_HTTP_ADDR.to_string(),
+            grpc_addr: DEFAULT_GRPC_ADDR.to_string(),
+            dashboard: false,
+            dashboard_addr: DEFAULT_DASHBOARD_ADDR.to_string(),
+            auth: AuthConfig::default(),
+            grpc_options: GrpcOptions::default(),
+        }
+    }
+}
+
+impl<P: Persistence + Clone + Send + Sync + 'static> AetherKernelBuilder<P> {
+    /// Store workflows in `persistence` instead of memory
+    pub fn persistence<Q: Persistence + Clone + Send + Sync + 'static>(
+        self,
+        persistence: Q,
+    ) -> AetherKernelBuilder<Q> {
+        AetherKernelBuilder {
+            persistence,
+            config: self.config,
+            limits: self.limits,
+            tracker: self.tracker,
+            concurrency_limits: self.concurrency_limits,
+            http_addr: self.http_addr,
+            grpc_addr: self.grpc_addr,
+            dashboard: self.dashboard,
+            dashboard_addr: self.dashboard_addr,
+            auth: self.auth,
+            grpc_options: self.grpc_options,
+        }
+    }
+
+    /// Scheduler settings; retention runs when `config.retention` is set
+    pub fn config(mut self, config: SchedulerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Request limits enforced by the servers
+    pub fn limits(mut self, limits: ServerLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Use `tracker` for execution history, e.g. one backed by the store
+    pub fn tracker(mut self, tracker: WorkflowTracker) -> Self {
+        self.tracker = Some(tracker);
+        self
+    }
+
+    /// Cap in-flight tasks of one workflow type
+    pub fn concurrency_limit(mut self, workflow_type: impl Into<String>, max: usize) -> Self {
+        self.concurrency_limits.push((workflow_type.into(), max));
+        self
+    }
+
+    /// REST listen address; port 0 picks a free port
+    pub fn http_addr(mut self, addr: impl Into<String>) -> Self {
+        self.http_addr = addr.into();
+        self
+    }
+
+    /// gRPC listen address; port 0 picks a free port
+    pub fn grpc_addr(mut self, addr: impl Into<String>) -> Self {
+        self.grpc_addr = addr.into();
+        self
+    }
+
+    /// Serve the dashboard WebSocket (requires the `dashboard` feature)
+    pub fn dashboard(mut self, enabled: bool) -> Self {
+        self.dashboard = enabled;
+        self
+    }
+
+    /// Dashboard WebSocket listen address; port 0 picks a free port
+    pub fn dashboard_addr(mut self, addr: impl Into<String>) -> Self {
+        self.dashboard_addr = addr.into();
+        self
+    }
+
+    /// API keys required by the REST API
+    pub fn auth(mut self, auth: AuthConfig) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Optional gRPC services (reflection, health) and gRPC auth
+    pub fn grpc_options(mut self, options: GrpcOptions) -> Self {
+        self.grpc_options = options;
+        self
+    }
+
+    /// Bearer tokens required by the gRPC services
+    pub fn grpc_auth(mut self, auth: GrpcAuthConfig) -> Self {
+        self.grpc_options.auth = auth;
+        self
+    }
+
+    pub fn build(self) -> AetherKernel<P> {
+        let mut scheduler =
+            Scheduler::with_config(self.persistence, self.config).with_limits(self.limits);
+        if let Some(tracker) = self.tracker {
+            scheduler = scheduler.with_tracker(tracker);
+        }
+        for (workflow_type, max) in self.concurrency_limits {
+            scheduler = scheduler.with_concurrency_limit(workflow_type, max);
+        }
+        AetherKernel {
+            scheduler: Arc::new(scheduler),
+            http_addr: self.http_addr,
+            grpc_addr: self.grpc_addr,
+            dashboard_addr: self.dashboard.then_some(self.dashboard_addr),
+            auth: self.auth,
+            grpc_options: self.grpc_options,
+            running: Mutex::new(None),
+        }
+    }
+}
+
+/// Servers and background loops of a started kernel
+struct Running {
+    stop: oneshot::Sender<()>,
+    servers: JoinHandle<anyhow::Result<()>>,
+    background: Vec<JoinHandle<()>>,
+}
+
+/// The Aether engine: scheduler, persistence, servers and background loops
+pub struct AetherKernel<P: Persistence = Arc<L0MemoryStore>> {
+    scheduler: Arc<Scheduler<P>>,
+    http_addr: String,
+    grpc_addr: String,
+    dashboard_addr: Option<String>,
+    auth: AuthConfig,
+    grpc_options: GrpcOptions,
+    running: Mutex<Option<Running>>,
 }
 
 impl Default for AetherKernel {
@@ -11,12 +234,244 @@ impl Default for AetherKernel {
 }
 
 impl AetherKernel {
+    /// In-memory kernel on the default addresses
     pub fn new() -> Self {
-        AetherKernel {}
+        Self::builder().build()
+    }
+
+    pub fn builder() -> AetherKernelBuilder {
+        AetherKernelBuilder::default()
+    }
+}
+
+impl<P: Persistence + Clone + Send + Sync + 'static> AetherKernel<P> {
+    /// The scheduler, e.g. to register workflow definitions or start workflows in-process
+    pub fn scheduler(&self) -> &Arc<Scheduler<P>> {
+        &self.scheduler
+    }
+
+    /// Bind every server and spawn the servers and background loops.
+    ///
+    /// Returns once all listeners are bound, with the actual addresses, so
+    /// callers can listen on port 0. Fails if a listener cannot be bound, the gRPC
+    /// auth config is invalid or the kernel is already running.
+    pub async fn start(&self) -> anyhow::Result<KernelAddrs> {
+        let mut running = self.running.lock().await;
+        if running.is_some() {
+            anyhow::bail!("kernel is already running");
+        }
+        self.grpc_options.auth.validate()?;
+
+        let http = TcpListener::bind(&self.http_addr).await?;
+        let grpc = TcpListener::bind(&self.grpc_addr).await?;
+        let dashboard = match &self.dashboard_addr {
+            Some(addr) => Some(bind_dashboard(addr).await?),
+            None => None,
+        };
+        let addrs = KernelAddrs {
+            http: http.local_addr()?,
+            grpc: grpc.local_addr()?,
+            dashboard: dashboard
+                .as_ref()
+                .map(TcpListener::local_addr)
+                .transpose()?,
+        };
+
+        // Overview counters are maintained incrementally from here on
+        self.scheduler.seed_stats().await?;
+        // Definitions pushed by SDKs survive restarts
+        self.scheduler.load_definitions().await?;
+
+        // Background loops exit once the scheduler stops
+        let scheduler = &self.scheduler;
+        let mut background = vec![
+            spawn_lease_expiry_task(scheduler.clone()),
+            spawn_worker_eviction_task(scheduler.clone()),
+            spawn_timer_task(scheduler.clone()),
+            spawn_step_timeout_task(scheduler.clone()),
+            spawn_schedule_task(scheduler.clone()),
+            spawn_child_wait_task(scheduler.clone()),
+            spawn_step_cache_task(scheduler.clone()),
+        ];
+        if let Some(policy) = scheduler.config().retention.clone() {
+            background.push(spawn_retention_task(scheduler.clone(), policy));
+        }
+        #[cfg(feature = "dashboard")]
+        if let Some(listener) = dashboard {
+            background.push(spawn_dashboard(scheduler.clone(), listener));
+        }
+
+        let (stop, stopped) = oneshot::channel();
+        let servers = tokio::spawn({
+            let scheduler = scheduler.clone();
+            let auth = self.auth.clone();
+            let grpc_options = self.grpc_options.clone();
+            async move {
+                let result = server::serve_listeners(
+                    scheduler.clone(),
+                    http,
+                    grpc,
+                    auth,
+                    grpc_options,
+                    async move {
+                        let _ = stopped.await;
+                    },
+                )
+                .await;
+                // A failed server leaves the scheduler running; stop it so the
+                // background loops and `run_until` callers finish too
+                scheduler.shutdown(std::time::Duration::ZERO).await;
+                result
+            }
+        });
+
+        *running = Some(Running {
+            stop,
+            servers,
+            background,
+        });
+        Ok(addrs)
+    }
+
+    /// Stop dispatching, wait up to the shutdown grace period for in-flight
+    /// steps, then stop the servers and background loops and flush persistence.
+    ///
+    /// Returns the error of a server that failed while running. Does nothing
+    /// if the kernel is not running.
+    pub async fn shutdown(&self) -> anyhow::Result<()> {
+        let Some(running) = self.running.lock().await.take() else {
+            return Ok(());
+        };
+        let _ = running.stop.send(());
+        let result = running
+            .servers
+            .await
+            .unwrap_or_else(|e| Err(anyhow::anyhow!("server task failed: {}", e)));
+        for handle in running.background {
+            let _ = handle.await;
+        }
+        result
+    }
+
+    /// Wait until `signal` completes or a server fails, then [`shutdown`](Self::shutdown).
+    pub async fn run_until(&self, signal: impl Future<Output = ()>) -> anyhow::Result<()> {
+        tokio::select! {
+            _ = signal => {}
+            _ = self.scheduler.stopped() => {}
+        }
+        self.shutdown().await
+    }
+}
+
+#[cfg(feature = "dashboard")]
+async fn bind_dashboard(addr: &str) -> anyhow::Result<TcpListener> {
+    Ok(TcpListener::bind(addr).await?)
+}
+
+#[cfg(not(feature = "dashboard"))]
+async fn bind_dashboard(_addr: &str) -> anyhow::Result<TcpListener> {
+    anyhow::bail!("the dashboard requires building with the `dashboard` feature")
+}
+
+/// Serve the dashboard until the scheduler stops
+#[cfg(feature = "dashboard")]
+fn spawn_dashboard<P: Persistence + 'static>(
+    scheduler: Arc<Scheduler<P>>,
+    listener: TcpListener,
+) -> JoinHandle<()> {
+    let dashboard = crate::dashboard_server::DashboardServer::new(
+        scheduler.tracker.clone(),
+        scheduler.broadcaster.clone(),
+    )
+    .with_graph_source(scheduler.clone());
+    tokio::spawn(async move {
+        let stopped = {
+            let scheduler = scheduler.clone();
+            async move { scheduler.stopped().await }
+        };
+        if let Err(e) = dashboard.serve_with_shutdown(listener, stopped).await {
+            tracing::error!("Dashboard server error: {}", e);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::{StepDefinition, WorkflowDefinition};
+    use crate::proto;
+    use crate::proto::client_service_client::ClientServiceClient;
+    use crate::shutdown::ShutdownState;
+
+    fn kernel() -> AetherKernel {
+        AetherKernel::builder()
+            .http_addr("127.0.0.1:0")
+            .grpc_addr("127.0.0.1:0")
+            .dashboard(cfg!(feature = "dashboard"))
+            .dashboard_addr("127.0.0.1:0")
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_start_binds_ephemeral_ports_and_shuts_down() {
+        let kernel = kernel();
+        kernel
+            .scheduler()
+            .register_definition(WorkflowDefinition::new(
+                "order",
+                vec![StepDefinition::new("charge")],
+            ))
+            .await
+            .unwrap();
+
+        let addrs = kernel.start().await.unwrap();
+        assert_ne!(addrs.http.port(), 0);
+        assert_ne!(addrs.grpc.port(), 0);
+        assert_eq!(addrs.dashboard.is_some(), cfg!(feature = "dashboard"));
+        tokio::net::TcpStream::connect(addrs.http).await.unwrap();
+
+        let mut client = ClientServiceClient::connect(format!("http://{}", addrs.grpc))
+            .await
+            .unwrap();
+        let started = client
+            .start_workflow(proto::StartWorkflowRequest {
+                workflow_type: "order".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        let workflow = kernel
+            .scheduler()
+            .persistence
+            .get_workflow(&started.workflow_id)
+            .await
+            .unwrap();
+        assert!(workflow.is_some());
+
+        kernel.shutdown().await.unwrap();
+        assert_eq!(kernel.scheduler().shutdown_state(), ShutdownState::Stopped);
+        assert!(tokio::net::TcpStream::connect(addrs.grpc).await.is_err());
+        // Already stopped
+        kernel.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_start_twice_is_rejected() {
+        let kernel = kernel();
+        kernel.start().await.unwrap();
+        assert!(kernel.start().await.is_err());
+        kernel.run_until(async {}).await.unwrap();
     }
 
-    pub fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
-        // Start the kernel
-        Ok(())
+    #[tokio::test]
+    async fn test_bind_failure_is_reported() {
+        let taken = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let kernel = AetherKernel::builder()
+            .http_addr(taken.local_addr().unwrap().to_string())
+            .grpc_addr("127.0.0.1:0")
+            .build();
+        assert!(kernel.start().await.is_err());
+        assert_eq!(kernel.scheduler().shutdown_state(), ShutdownState::Running);
     }
 }
@@ -5,23 +5,74 @@ pub mod dashboard_server;
 
 pub mod api;
 pub mod broadcaster;
+pub mod cancellation;
+pub mod child;
+pub mod client;
+pub mod config;
+pub mod definition;
+pub mod diagnostics;
 pub mod execution;
+pub mod export;
+pub mod graph;
+pub mod grpc_auth;
+pub mod grpc_server;
+pub mod health;
+pub mod import;
 pub mod kernel;
+pub mod memo;
+pub mod payload;
 pub mod persistence;
+pub mod proto;
+pub mod ready_queue;
+pub mod retention;
+pub mod retry;
+pub mod schedule;
 pub mod scheduler;
+pub mod schema;
 pub mod server;
+pub mod server_info;
 pub mod service_registry;
+pub mod session;
+pub mod shutdown;
+pub mod signal;
 pub mod state_machine;
+pub mod stats;
+pub mod step_cache;
+pub mod step_lifecycle;
+pub mod step_timeout;
+pub mod sticky;
 pub mod task;
+pub mod telemetry;
+pub mod time;
+pub mod timer;
 pub mod tracker;
 pub mod worker;
 pub mod workflow;
 
 pub use broadcaster::{EventBroadcaster, EventPayload, EventType, WorkflowEvent};
+pub use cancellation::{CancelError, TaskCancellation, TerminateError};
+pub use child::ChildWorkflowSpec;
+pub use config::{ServerConfig, ServerOverrides};
+pub use definition::{
+    DefinitionError, DefinitionProblem, RetryPolicySpec, StepDefinition, StepDefinitionSpec,
+    WorkflowDefinition, WorkflowDefinitionSpec,
+};
 pub use execution::{ExecutionContext, ExecutionResult};
 pub use kernel::AetherKernel;
+pub use payload::Payload;
+pub use retry::RetryError;
+pub use schedule::{OverlapPolicy, Schedule, ScheduleError, ScheduleSpec};
 pub use service_registry::{ServiceInfo, ServiceRegistry};
-pub use state_machine::{Workflow, WorkflowState};
-pub use task::{ResourceType, RetryPolicy, ServiceResource, Task};
-pub use tracker::{StepExecution, StepExecutionStatus, WorkflowExecution, WorkflowTracker};
+pub use session::SessionStore;
+pub use shutdown::ShutdownState;
+pub use signal::{Signal, SignalError};
+pub use state_machine::{Priority, Workflow, WorkflowState};
+pub use step_lifecycle::{StepLifecycle, StepLifecycleError};
+pub use step_timeout::StepDeadline;
+pub use task::{ResourceType, RetryPolicy, ServiceResource, Task, TaskId};
+pub use timer::Timer;
+pub use tracker::{
+    StepExecution, StepExecutionStatus, StepProgress, WorkflowExecution, WorkflowTracker,
+};
+pub use worker::Worker;
 pub use workflow::WorkflowExecutor;
@@ -0,0 +1,105 @@
+//! workflow 备注（memo）
+//!
+//! 启动 workflow 时附带的少量字符串键值对（如 `customer_id`、`region`），随 workflow 持久化，
+//! 在状态查询中返回，并可作为列表筛选条件。键的数量、键和值的长度受限，超限时返回 [`MemoError`]。
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// 每个 workflow 最多的键数
+pub const MAX_MEMO_KEYS: usize = 32;
+/// 键的最大字节数
+pub const MAX_MEMO_KEY_BYTES: usize = 64;
+/// 值的最大字节数
+pub const MAX_MEMO_VALUE_BYTES: usize = 256;
+
+/// memo 不符合限制
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemoError {
+    TooManyKeys(usize),
+    EmptyKey,
+    KeyTooLong(String),
+    ValueTooLong(String),
+}
+
+impl fmt::Display for MemoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemoError::TooManyKeys(count) => write!(
+                f,
+                "Memo has {} keys, exceeding the limit of {}",
+                count, MAX_MEMO_KEYS
+            ),
+            MemoError::EmptyKey => write!(f, "Memo keys must not be empty"),
+            MemoError::KeyTooLong(key) => {
+                write!(f, "Memo key '{}' exceeds {} bytes", key, MAX_MEMO_KEY_BYTES)
+            }
+            MemoError::ValueTooLong(key) => write!(
+                f,
+                "Memo value of '{}' exceeds {} bytes",
+                key, MAX_MEMO_VALUE_BYTES
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MemoError {}
+
+/// 检查 memo 是否符合限制
+pub fn validate_memo(memo: &HashMap<String, String>) -> Result<(), MemoError> {
+    if memo.len() > MAX_MEMO_KEYS {
+        return Err(MemoError::TooManyKeys(memo.len()));
+    }
+    // 按键排序，使错误信息稳定
+    let mut entries: Vec<_> = memo.iter().collect();
+    entries.sort();
+    for (key, value) in entries {
+        if key.is_empty() {
+            return Err(MemoError::EmptyKey);
+        }
+        if key.len() > MAX_MEMO_KEY_BYTES {
+            return Err(MemoError::KeyTooLong(key.clone()));
+        }
+        if value.len() > MAX_MEMO_VALUE_BYTES {
+            return Err(MemoError::ValueTooLong(key.clone()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memo(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_memo_limits() {
+        assert!(validate_memo(&memo(&[("customer_id", "123"), ("region", "eu")])).is_ok());
+        assert_eq!(validate_memo(&memo(&[("", "x")])), Err(MemoError::EmptyKey));
+
+        let long_key = "k".repeat(MAX_MEMO_KEY_BYTES + 1);
+        assert_eq!(
+            validate_memo(&memo(&[(&long_key, "x")])),
+            Err(MemoError::KeyTooLong(long_key.clone()))
+        );
+        let long_value = "v".repeat(MAX_MEMO_VALUE_BYTES + 1);
+        assert_eq!(
+            validate_memo(&memo(&[("region", &long_value)])),
+            Err(MemoError::ValueTooLong("region".to_string()))
+        );
+
+        let too_many: HashMap<String, String> = (0..=MAX_MEMO_KEYS)
+            .map(|i| (format!("key{}", i), String::new()))
+            .collect();
+        assert_eq!(
+            validate_memo(&too_many),
+            Err(MemoError::TooManyKeys(MAX_MEMO_KEYS + 1))
+        );
+    }
+}
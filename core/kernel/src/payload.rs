@@ -0,0 +1,270 @@
+//! 带内容类型的负载
+//!
+//! 内核内部以字节保存 workflow 输入输出、step 输入输出和事件负载，
+//! 同时记录内容类型。序列化时 JSON 内容输出为解码后的 JSON 值，其他内容输出为 base64 字符串：
+//!
+//! ```json
+//! {"content_type": "application/json", "data": {"order_id": 42}}
+//! {"content_type": "application/octet-stream", "data": "AAEC"}
+//! ```
+//!
+//! 旧版本把负载保存为数字数组（`[123, 34, ...]`），反序列化时仍然接受，并按内容推断类型。
+//! bincode 等二进制格式直接保存内容类型和原始字节。
+//!
+//! workflow 输入和 step 输出的大小受调度器配置限制，超限时返回 [`PayloadTooLarge`]。
+
+use std::fmt;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+/// JSON 内容类型
+pub const JSON_CONTENT_TYPE: &str = "application/json";
+/// 未知二进制内容类型
+pub const BINARY_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// 负载超过大小限制时错误信息的前缀
+pub const PAYLOAD_TOO_LARGE: &str = "PAYLOAD_TOO_LARGE";
+
+/// 负载超过调度器配置的大小限制
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayloadTooLarge {
+    /// 超限的负载，如 `workflow input`
+    pub what: &'static str,
+    pub size: usize,
+    pub limit: usize,
+}
+
+impl fmt::Display for PayloadTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} is {} bytes, exceeding the limit of {} bytes",
+            PAYLOAD_TOO_LARGE, self.what, self.size, self.limit
+        )
+    }
+}
+
+impl std::error::Error for PayloadTooLarge {}
+
+/// 字节内容及其内容类型
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Payload {
+    pub data: Vec<u8>,
+    pub content_type: String,
+}
+
+impl Payload {
+    /// 指定内容类型的负载
+    pub fn new(data: Vec<u8>, content_type: impl Into<String>) -> Self {
+        Payload {
+            data,
+            content_type: content_type.into(),
+        }
+    }
+
+    /// 按内容推断类型：合法的 JSON 视为 `application/json`，否则为二进制
+    pub fn from_bytes(data: Vec<u8>) -> Self {
+        let content_type = if !data.is_empty() && serde_json::from_slice::<Value>(&data).is_ok() {
+            JSON_CONTENT_TYPE
+        } else {
+            BINARY_CONTENT_TYPE
+        };
+        Payload::new(data, content_type)
+    }
+
+    /// JSON 值编码的负载
+    pub fn from_json(value: &Value) -> Self {
+        Payload::new(
+            serde_json::to_vec(value).expect("JSON value serializes"),
+            JSON_CONTENT_TYPE,
+        )
+    }
+
+    /// 内容类型是否为 JSON
+    pub fn is_json(&self) -> bool {
+        self.content_type == JSON_CONTENT_TYPE
+    }
+
+    /// 解码 JSON 内容，内容类型不是 JSON 或内容无法解析时返回 `None`
+    pub fn as_json(&self) -> Option<Value> {
+        if !self.is_json() {
+            return None;
+        }
+        serde_json::from_slice(&self.data).ok()
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+impl Default for Payload {
+    fn default() -> Self {
+        Payload::new(Vec::new(), BINARY_CONTENT_TYPE)
+    }
+}
+
+impl From<Vec<u8>> for Payload {
+    fn from(data: Vec<u8>) -> Self {
+        Payload::from_bytes(data)
+    }
+}
+
+impl From<Payload> for Vec<u8> {
+    fn from(payload: Payload) -> Self {
+        payload.data
+    }
+}
+
+impl AsRef<[u8]> for Payload {
+    fn as_ref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+#[derive(Serialize)]
+struct EncodedRef<'a> {
+    content_type: &'a str,
+    data: Value,
+}
+
+/// 二进制格式中的负载
+#[derive(Serialize)]
+struct RawRef<'a> {
+    content_type: &'a str,
+    data: &'a [u8],
+}
+
+#[derive(Deserialize)]
+struct Raw {
+    content_type: String,
+    data: Vec<u8>,
+}
+
+impl Serialize for Payload {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if !serializer.is_human_readable() {
+            return RawRef {
+                content_type: &self.content_type,
+                data: &self.data,
+            }
+            .serialize(serializer);
+        }
+        // 声明为 JSON 但内容无法解析时按二进制输出，保证能原样读回
+        let encoded = match self.as_json() {
+            Some(value) => EncodedRef {
+                content_type: JSON_CONTENT_TYPE,
+                data: value,
+            },
+            None => EncodedRef {
+                content_type: if self.is_json() {
+                    BINARY_CONTENT_TYPE
+                } else {
+                    &self.content_type
+                },
+                data: Value::String(BASE64.encode(&self.data)),
+            },
+        };
+        encoded.serialize(serializer)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Repr {
+    /// 旧版本的数字数组
+    Legacy(Vec<u8>),
+    Encoded {
+        content_type: String,
+        data: Value,
+    },
+}
+
+impl<'de> Deserialize<'de> for Payload {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if !deserializer.is_human_readable() {
+            let raw = Raw::deserialize(deserializer)?;
+            return Ok(Payload::new(raw.data, raw.content_type));
+        }
+        match Repr::deserialize(deserializer)? {
+            Repr::Legacy(data) => Ok(Payload::from_bytes(data)),
+            Repr::Encoded { content_type, data } if content_type == JSON_CONTENT_TYPE => {
+                Ok(Payload::from_json(&data))
+            }
+            Repr::Encoded { content_type, data } => {
+                let Value::String(encoded) = data else {
+                    return Err(D::Error::custom(format!(
+                        "expected base64 string data for content type {}",
+                        content_type
+                    )));
+                };
+                let data = BASE64.decode(encoded).map_err(D::Error::custom)?;
+                Ok(Payload::new(data, content_type))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_json_payload_serializes_as_decoded_json() {
+        let payload = Payload::from_json(&json!({ "order_id": 42 }));
+        let encoded = serde_json::to_value(&payload).unwrap();
+        assert_eq!(
+            encoded,
+            json!({ "content_type": "application/json", "data": { "order_id": 42 } })
+        );
+        let decoded: Payload = serde_json::from_value(encoded).unwrap();
+        assert_eq!(decoded, payload);
+        assert_eq!(decoded.as_json(), Some(json!({ "order_id": 42 })));
+    }
+
+    #[test]
+    fn test_binary_payload_serializes_as_base64() {
+        let payload = Payload::from_bytes(vec![0, 1, 2, 255]);
+        assert_eq!(payload.content_type, BINARY_CONTENT_TYPE);
+        let encoded = serde_json::to_value(&payload).unwrap();
+        assert_eq!(encoded["data"], "AAEC/w==");
+        let decoded: Payload = serde_json::from_value(encoded).unwrap();
+        assert_eq!(decoded, payload);
+        assert_eq!(decoded.as_json(), None);
+    }
+
+    #[test]
+    fn test_legacy_byte_arrays_are_accepted() {
+        let legacy = serde_json::to_string(&br#"{"ok":true}"#.to_vec()).unwrap();
+        let payload: Payload = serde_json::from_str(&legacy).unwrap();
+        assert!(payload.is_json());
+        assert_eq!(payload.as_json(), Some(json!({ "ok": true })));
+
+        let payload: Payload = serde_json::from_str("[0, 159]").unwrap();
+        assert_eq!(payload, Payload::new(vec![0, 159], BINARY_CONTENT_TYPE));
+        let payload: Payload = serde_json::from_str("[]").unwrap();
+        assert!(payload.is_empty());
+    }
+
+    #[test]
+    fn test_mislabelled_json_round_trips_as_binary() {
+        let payload = Payload::new(b"not json".to_vec(), JSON_CONTENT_TYPE);
+        let decoded: Payload =
+            serde_json::from_str(&serde_json::to_string(&payload).unwrap()).unwrap();
+        assert_eq!(decoded.data, payload.data);
+        assert_eq!(decoded.content_type, BINARY_CONTENT_TYPE);
+    }
+}
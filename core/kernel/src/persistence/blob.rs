@@ -0,0 +1,369 @@
+//! 大负载按引用保存
+//!
+//! 包装另一个存储，超过阈值的 workflow 输入、step 结果和 workflow 结果按 SHA-256
+//! 内容哈希写入外部目录，存储本身只保存引用，读取时自动还原。相同内容只写一次。
+//! 删除或清理 workflow 不会删除 blob（可能被其他 workflow 共享），需要时由运维自行清理。
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+
+use super::{ListOptions, Persistence, PurgeFilter};
+use crate::definition::WorkflowDefinition;
+use crate::schedule::Schedule;
+use crate::signal::Signal;
+use crate::state_machine::{Workflow, WorkflowState};
+use crate::step_cache::CachedResult;
+use crate::step_timeout::StepDeadline;
+use crate::timer::Timer;
+use crate::tracker::WorkflowExecution;
+
+/// 默认阈值，超过该大小的负载按引用保存
+pub const DEFAULT_BLOB_THRESHOLD_BYTES: usize = 64 * 1024;
+
+/// 引用的前缀，后接十六进制的 SHA-256
+const BLOB_REF_PREFIX: &[u8] = b"\0aether-blob:sha256:";
+
+/// 把大负载保存到 `dir` 的存储
+pub struct BlobStore<P> {
+    inner: P,
+    dir: PathBuf,
+    threshold: usize,
+}
+
+impl<P: Persistence> BlobStore<P> {
+    /// 创建存储，`dir` 不存在时自动创建
+    pub fn new(inner: P, dir: impl Into<PathBuf>, threshold: usize) -> anyhow::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(BlobStore {
+            inner,
+            dir,
+            threshold,
+        })
+    }
+
+    /// 被包装的存储，读到的是未还原的引用
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// blob 目录
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// 超过阈值时写入 blob 并返回引用，否则原样返回
+    async fn offload(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        if data.len() <= self.threshold {
+            return Ok(data.to_vec());
+        }
+        let hash = format!("{:x}", Sha256::digest(data));
+        let path = self.dir.join(&hash);
+        if !tokio::fs::try_exists(&path).await? {
+            // 先写临时文件再改名，避免崩溃时留下不完整的 blob
+            let tmp = self
+                .dir
+                .join(format!("{}.tmp-{}", hash, uuid::Uuid::new_v4()));
+            tokio::fs::write(&tmp, data).await?;
+            tokio::fs::rename(&tmp, &path).await?;
+        }
+        let mut reference = BLOB_REF_PREFIX.to_vec();
+        reference.extend_from_slice(hash.as_bytes());
+        Ok(reference)
+    }
+
+    /// 引用还原为 blob 内容，不是引用时原样返回
+    async fn resolve(&self, data: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        let Some(hash) = data.strip_prefix(BLOB_REF_PREFIX) else {
+            return Ok(data);
+        };
+        let hash = std::str::from_utf8(hash)?;
+        tokio::fs::read(self.dir.join(hash))
+            .await
+            .map_err(|e| anyhow::anyhow!("blob {} is unreadable: {}", hash, e))
+    }
+
+    async fn offload_state(&self, state: &WorkflowState) -> anyhow::Result<WorkflowState> {
+        let mut state = state.clone();
+        if let WorkflowState::Completed { result } = &mut state {
+            result.data = self.offload(&result.data).await?;
+        }
+        Ok(state)
+    }
+
+    async fn offload_workflow(&self, workflow: &Workflow) -> anyhow::Result<Workflow> {
+        let mut workflow = workflow.clone();
+        workflow.input = self.offload(&workflow.input).await?;
+        for output in workflow.completed_steps.values_mut() {
+            output.data = self.offload(&output.data).await?;
+        }
+        workflow.state = self.offload_state(&workflow.state).await?;
+        Ok(workflow)
+    }
+
+    async fn resolve_workflow(&self, mut workflow: Workflow) -> anyhow::Result<Workflow> {
+        workflow.input = self.resolve(std::mem::take(&mut workflow.input)).await?;
+        for output in workflow.completed_steps.values_mut() {
+            output.data = self.resolve(std::mem::take(&mut output.data)).await?;
+        }
+        if let WorkflowState::Completed { result } = &mut workflow.state {
+            result.data = self.resolve(std::mem::take(&mut result.data)).await?;
+        }
+        Ok(workflow)
+    }
+
+    async fn resolve_workflows(&self, workflows: Vec<Workflow>) -> anyhow::Result<Vec<Workflow>> {
+        let mut resolved = Vec::with_capacity(workflows.len());
+        for workflow in workflows {
+            resolved.push(self.resolve_workflow(workflow).await?);
+        }
+        Ok(resolved)
+    }
+}
+
+#[async_trait::async_trait]
+impl<P: Persistence> Persistence for BlobStore<P> {
+    async fn save_workflow(&self, workflow: &Workflow) -> anyhow::Result<()> {
+        let workflow = self.offload_workflow(workflow).await?;
+        self.inner.save_workflow(&workflow).await
+    }
+
+    async fn save_workflow_if_absent(&self, workflow: &Workflow) -> anyhow::Result<bool> {
+        let workflow = self.offload_workflow(workflow).await?;
+        self.inner.save_workflow_if_absent(&workflow).await
+    }
+
+    async fn save_workflows(&self, workflows: &[Workflow]) -> anyhow::Result<Vec<bool>> {
+        let mut offloaded = Vec::with_capacity(workflows.len());
+        for workflow in workflows {
+            offloaded.push(self.offload_workflow(workflow).await?);
+        }
+        self.inner.save_workflows(&offloaded).await
+    }
+
+    fn atomic_batches(&self) -> bool {
+        self.inner.atomic_batches()
+    }
+
+    async fn get_workflow(&self, id: &str) -> anyhow::Result<Option<Workflow>> {
+        match self.inner.get_workflow(id).await? {
+            Some(workflow) => Ok(Some(self.resolve_workflow(workflow).await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn list_workflows(&self, workflow_type: Option<&str>) -> anyhow::Result<Vec<Workflow>> {
+        let workflows = self.inner.list_workflows(workflow_type).await?;
+        self.resolve_workflows(workflows).await
+    }
+
+    async fn update_workflow_state(&self, id: &str, state: WorkflowState) -> anyhow::Result<()> {
+        let state = self.offload_state(&state).await?;
+        self.inner.update_workflow_state(id, state).await
+    }
+
+    async fn record_step_attempt(&self, workflow_id: &str, step_name: &str) -> anyhow::Result<u32> {
+        self.inner.record_step_attempt(workflow_id, step_name).await
+    }
+
+    async fn save_step_result(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+        result: Vec<u8>,
+    ) -> anyhow::Result<()> {
+        let result = self.offload(&result).await?;
+        self.inner
+            .save_step_result(workflow_id, step_name, result)
+            .await
+    }
+
+    async fn get_step_result(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        match self.inner.get_step_result(workflow_id, step_name).await? {
+            Some(result) => Ok(Some(self.resolve(result).await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn append_signal(&self, signal: &Signal) -> anyhow::Result<()> {
+        self.inner.append_signal(signal).await
+    }
+
+    async fn get_signals(&self, workflow_id: &str) -> anyhow::Result<Vec<Signal>> {
+        self.inner.get_signals(workflow_id).await
+    }
+
+    async fn save_timer(&self, timer: &Timer) -> anyhow::Result<()> {
+        self.inner.save_timer(timer).await
+    }
+
+    async fn list_timers(&self) -> anyhow::Result<Vec<Timer>> {
+        self.inner.list_timers().await
+    }
+
+    async fn delete_timer(&self, workflow_id: &str, step_name: &str) -> anyhow::Result<bool> {
+        self.inner.delete_timer(workflow_id, step_name).await
+    }
+
+    async fn save_step_deadline(&self, deadline: &StepDeadline) -> anyhow::Result<()> {
+        self.inner.save_step_deadline(deadline).await
+    }
+
+    async fn list_step_deadlines(&self) -> anyhow::Result<Vec<StepDeadline>> {
+        self.inner.list_step_deadlines().await
+    }
+
+    async fn delete_step_deadline(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+    ) -> anyhow::Result<bool> {
+        self.inner
+            .delete_step_deadline(workflow_id, step_name)
+            .await
+    }
+
+    async fn get_cached_result(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        self.inner.get_cached_result(key).await
+    }
+
+    async fn put_cached_result(
+        &self,
+        entry: &CachedResult,
+        max_entries: usize,
+    ) -> anyhow::Result<()> {
+        self.inner.put_cached_result(entry, max_entries).await
+    }
+
+    async fn save_schedule(&self, schedule: &Schedule) -> anyhow::Result<()> {
+        self.inner.save_schedule(schedule).await
+    }
+
+    async fn update_schedule(&self, schedule: &Schedule) -> anyhow::Result<bool> {
+        self.inner.update_schedule(schedule).await
+    }
+
+    async fn list_schedules(&self) -> anyhow::Result<Vec<Schedule>> {
+        self.inner.list_schedules().await
+    }
+
+    async fn delete_schedule(&self, id: &str) -> anyhow::Result<bool> {
+        self.inner.delete_schedule(id).await
+    }
+
+    async fn save_definition(&self, definition: &WorkflowDefinition) -> anyhow::Result<()> {
+        self.inner.save_definition(definition).await
+    }
+
+    async fn list_definitions(&self) -> anyhow::Result<Vec<WorkflowDefinition>> {
+        self.inner.list_definitions().await
+    }
+
+    async fn save_execution(&self, execution: &WorkflowExecution) -> anyhow::Result<()> {
+        self.inner.save_execution(execution).await
+    }
+
+    async fn load_executions(&self) -> anyhow::Result<Vec<WorkflowExecution>> {
+        self.inner.load_executions().await
+    }
+
+    async fn list_workflows_paged(&self, options: &ListOptions) -> anyhow::Result<Vec<Workflow>> {
+        let workflows = self.inner.list_workflows_paged(options).await?;
+        self.resolve_workflows(workflows).await
+    }
+
+    async fn count_workflows(&self, options: &ListOptions) -> anyhow::Result<usize> {
+        self.inner.count_workflows(options).await
+    }
+
+    async fn delete_workflow(&self, id: &str) -> anyhow::Result<bool> {
+        self.inner.delete_workflow(id).await
+    }
+
+    async fn purge_workflows(&self, filter: &PurgeFilter) -> anyhow::Result<Vec<String>> {
+        self.inner.purge_workflows(filter).await
+    }
+
+    async fn purge_completed_before(&self, cutoff: DateTime<Utc>) -> anyhow::Result<Vec<String>> {
+        self.inner.purge_completed_before(cutoff).await
+    }
+
+    async fn flush(&self) -> anyhow::Result<()> {
+        self.inner.flush().await
+    }
+
+    fn backend_name(&self) -> &'static str {
+        self.inner.backend_name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::Payload;
+    use crate::persistence::l0_memory::L0MemoryStore;
+
+    #[tokio::test]
+    async fn test_large_payloads_are_stored_by_reference() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = BlobStore::new(L0MemoryStore::new(), dir.path(), 16).unwrap();
+
+        let large = vec![7u8; 1024];
+        let mut workflow = Workflow::new("wf-1".into(), "test".into(), large.clone());
+        workflow.record_completion("small", b"ok".to_vec());
+        workflow.record_completion("big", large.clone());
+        store.save_workflow(&workflow).await.unwrap();
+        store
+            .save_step_result("wf-1", "big", large.clone())
+            .await
+            .unwrap();
+
+        // 底层存储只保存引用，相同内容只写一个 blob
+        let raw = store.inner().get_workflow("wf-1").await.unwrap().unwrap();
+        assert!(raw.input.starts_with(BLOB_REF_PREFIX));
+        assert_eq!(raw.completed_steps["small"].data, b"ok");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        let loaded = store.get_workflow("wf-1").await.unwrap().unwrap();
+        assert_eq!(loaded.input, large);
+        assert_eq!(loaded.completed_steps["big"].data, large);
+        assert_eq!(
+            store.get_step_result("wf-1", "big").await.unwrap(),
+            Some(large.clone())
+        );
+
+        let result = Payload::new(vec![9u8; 64], "application/octet-stream");
+        store
+            .update_workflow_state(
+                "wf-1",
+                WorkflowState::Completed {
+                    result: result.clone(),
+                },
+            )
+            .await
+            .unwrap();
+        let listed = store.list_workflows(None).await.unwrap();
+        assert_eq!(listed[0].state, WorkflowState::Completed { result });
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_missing_blob_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = BlobStore::new(L0MemoryStore::new(), dir.path(), 16).unwrap();
+        store
+            .save_step_result("wf-1", "big", vec![1u8; 100])
+            .await
+            .unwrap();
+        for entry in std::fs::read_dir(dir.path()).unwrap() {
+            std::fs::remove_file(entry.unwrap().path()).unwrap();
+        }
+        assert!(store.get_step_result("wf-1", "big").await.is_err());
+    }
+}
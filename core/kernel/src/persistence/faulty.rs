@@ -0,0 +1,249 @@
+//! 故障注入存储（仅测试）
+//!
+//! 包装另一个存储，每第 N 次调用返回 I/O 错误，用于验证调度器在持久化层暂时失败时不会崩溃。
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use chrono::{DateTime, Utc};
+
+use super::{ListOptions, Persistence, PurgeFilter};
+use crate::definition::WorkflowDefinition;
+use crate::schedule::Schedule;
+use crate::signal::Signal;
+use crate::state_machine::{Workflow, WorkflowState};
+use crate::step_cache::CachedResult;
+use crate::step_timeout::StepDeadline;
+use crate::timer::Timer;
+use crate::tracker::WorkflowExecution;
+
+/// 每第 `every` 次调用失败的存储，`every` 为 0 时不注入故障
+pub struct FaultyStore<P> {
+    inner: P,
+    every: AtomicUsize,
+    calls: AtomicUsize,
+    failures: AtomicUsize,
+}
+
+impl<P: Persistence> FaultyStore<P> {
+    pub fn new(inner: P, every: usize) -> Self {
+        FaultyStore {
+            inner,
+            every: AtomicUsize::new(every),
+            calls: AtomicUsize::new(0),
+            failures: AtomicUsize::new(0),
+        }
+    }
+
+    /// 修改故障间隔，0 表示停止注入故障
+    pub fn fail_every(&self, every: usize) {
+        self.every.store(every, Ordering::SeqCst);
+    }
+
+    /// 被包装的存储，读写不注入故障
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// 已注入的故障次数
+    pub fn failures(&self) -> usize {
+        self.failures.load(Ordering::SeqCst)
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+        let every = self.every.load(Ordering::SeqCst);
+        if every > 0 && call.is_multiple_of(every) {
+            self.failures.fetch_add(1, Ordering::SeqCst);
+            anyhow::bail!("injected I/O error on call {}", call);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl<P: Persistence> Persistence for FaultyStore<P> {
+    async fn save_workflow(&self, workflow: &Workflow) -> anyhow::Result<()> {
+        self.check()?;
+        self.inner.save_workflow(workflow).await
+    }
+
+    async fn save_workflow_if_absent(&self, workflow: &Workflow) -> anyhow::Result<bool> {
+        self.check()?;
+        self.inner.save_workflow_if_absent(workflow).await
+    }
+
+    async fn get_workflow(&self, id: &str) -> anyhow::Result<Option<Workflow>> {
+        self.check()?;
+        self.inner.get_workflow(id).await
+    }
+
+    async fn list_workflows(&self, workflow_type: Option<&str>) -> anyhow::Result<Vec<Workflow>> {
+        self.check()?;
+        self.inner.list_workflows(workflow_type).await
+    }
+
+    async fn update_workflow_state(&self, id: &str, state: WorkflowState) -> anyhow::Result<()> {
+        self.check()?;
+        self.inner.update_workflow_state(id, state).await
+    }
+
+    async fn record_step_attempt(&self, workflow_id: &str, step_name: &str) -> anyhow::Result<u32> {
+        self.check()?;
+        self.inner.record_step_attempt(workflow_id, step_name).await
+    }
+
+    async fn save_step_result(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+        result: Vec<u8>,
+    ) -> anyhow::Result<()> {
+        self.check()?;
+        self.inner
+            .save_step_result(workflow_id, step_name, result)
+            .await
+    }
+
+    async fn get_step_result(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        self.check()?;
+        self.inner.get_step_result(workflow_id, step_name).await
+    }
+
+    async fn append_signal(&self, signal: &Signal) -> anyhow::Result<()> {
+        self.check()?;
+        self.inner.append_signal(signal).await
+    }
+
+    async fn get_signals(&self, workflow_id: &str) -> anyhow::Result<Vec<Signal>> {
+        self.check()?;
+        self.inner.get_signals(workflow_id).await
+    }
+
+    async fn save_timer(&self, timer: &Timer) -> anyhow::Result<()> {
+        self.check()?;
+        self.inner.save_timer(timer).await
+    }
+
+    async fn list_timers(&self) -> anyhow::Result<Vec<Timer>> {
+        self.check()?;
+        self.inner.list_timers().await
+    }
+
+    async fn delete_timer(&self, workflow_id: &str, step_name: &str) -> anyhow::Result<bool> {
+        self.check()?;
+        self.inner.delete_timer(workflow_id, step_name).await
+    }
+
+    async fn save_step_deadline(&self, deadline: &StepDeadline) -> anyhow::Result<()> {
+        self.check()?;
+        self.inner.save_step_deadline(deadline).await
+    }
+
+    async fn list_step_deadlines(&self) -> anyhow::Result<Vec<StepDeadline>> {
+        self.check()?;
+        self.inner.list_step_deadlines().await
+    }
+
+    async fn delete_step_deadline(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+    ) -> anyhow::Result<bool> {
+        self.check()?;
+        self.inner
+            .delete_step_deadline(workflow_id, step_name)
+            .await
+    }
+
+    async fn get_cached_result(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        self.check()?;
+        self.inner.get_cached_result(key).await
+    }
+
+    async fn put_cached_result(
+        &self,
+        entry: &CachedResult,
+        max_entries: usize,
+    ) -> anyhow::Result<()> {
+        self.check()?;
+        self.inner.put_cached_result(entry, max_entries).await
+    }
+
+    async fn save_schedule(&self, schedule: &Schedule) -> anyhow::Result<()> {
+        self.check()?;
+        self.inner.save_schedule(schedule).await
+    }
+
+    async fn update_schedule(&self, schedule: &Schedule) -> anyhow::Result<bool> {
+        self.check()?;
+        self.inner.update_schedule(schedule).await
+    }
+
+    async fn list_schedules(&self) -> anyhow::Result<Vec<Schedule>> {
+        self.check()?;
+        self.inner.list_schedules().await
+    }
+
+    async fn delete_schedule(&self, id: &str) -> anyhow::Result<bool> {
+        self.check()?;
+        self.inner.delete_schedule(id).await
+    }
+
+    async fn save_definition(&self, definition: &WorkflowDefinition) -> anyhow::Result<()> {
+        self.check()?;
+        self.inner.save_definition(definition).await
+    }
+
+    async fn list_definitions(&self) -> anyhow::Result<Vec<WorkflowDefinition>> {
+        self.check()?;
+        self.inner.list_definitions().await
+    }
+
+    async fn save_execution(&self, execution: &WorkflowExecution) -> anyhow::Result<()> {
+        self.check()?;
+        self.inner.save_execution(execution).await
+    }
+
+    async fn load_executions(&self) -> anyhow::Result<Vec<WorkflowExecution>> {
+        self.check()?;
+        self.inner.load_executions().await
+    }
+
+    async fn list_workflows_paged(&self, options: &ListOptions) -> anyhow::Result<Vec<Workflow>> {
+        self.check()?;
+        self.inner.list_workflows_paged(options).await
+    }
+
+    async fn count_workflows(&self, options: &ListOptions) -> anyhow::Result<usize> {
+        self.check()?;
+        self.inner.count_workflows(options).await
+    }
+
+    async fn delete_workflow(&self, id: &str) -> anyhow::Result<bool> {
+        self.check()?;
+        self.inner.delete_workflow(id).await
+    }
+
+    async fn purge_workflows(&self, filter: &PurgeFilter) -> anyhow::Result<Vec<String>> {
+        self.check()?;
+        self.inner.purge_workflows(filter).await
+    }
+
+    async fn purge_completed_before(&self, cutoff: DateTime<Utc>) -> anyhow::Result<Vec<String>> {
+        self.check()?;
+        self.inner.purge_completed_before(cutoff).await
+    }
+
+    async fn flush(&self) -> anyhow::Result<()> {
+        self.check()?;
+        self.inner.flush().await
+    }
+
+    fn backend_name(&self) -> &'static str {
+        self.inner.backend_name()
+    }
+}
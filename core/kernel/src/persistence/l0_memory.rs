@@ -1,5 +1,13 @@
+use super::{ListOptions, PurgeFilter};
+use crate::definition::WorkflowDefinition;
+use crate::schedule::Schedule;
+use crate::signal::Signal;
 use crate::state_machine::Workflow;
 use crate::state_machine::WorkflowState;
+use crate::step_cache::{CachedResult, MemoryResultCache};
+use crate::step_timeout::StepDeadline;
+use crate::timer::Timer;
+use crate::tracker::WorkflowExecution;
 use chrono::Utc;
 use std::collections::HashMap;
 use tokio::sync::RwLock;
@@ -7,6 +15,13 @@ use tokio::sync::RwLock;
 pub struct L0MemoryStore {
     workflows: RwLock<HashMap<String, Workflow>>,
     step_results: RwLock<HashMap<String, HashMap<String, Vec<u8>>>>,
+    signals: RwLock<HashMap<String, Vec<Signal>>>,
+    timers: RwLock<HashMap<String, HashMap<String, Timer>>>,
+    step_deadlines: RwLock<HashMap<String, HashMap<String, StepDeadline>>>,
+    schedules: RwLock<HashMap<String, Schedule>>,
+    definitions: RwLock<HashMap<(String, u32), WorkflowDefinition>>,
+    executions: RwLock<HashMap<String, WorkflowExecution>>,
+    step_cache: MemoryResultCache,
 }
 
 impl Default for L0MemoryStore {
@@ -20,6 +35,13 @@ impl L0MemoryStore {
         L0MemoryStore {
             workflows: RwLock::new(HashMap::new()),
             step_results: RwLock::new(HashMap::new()),
+            signals: RwLock::new(HashMap::new()),
+            timers: RwLock::new(HashMap::new()),
+            step_deadlines: RwLock::new(HashMap::new()),
+            schedules: RwLock::new(HashMap::new()),
+            definitions: RwLock::new(HashMap::new()),
+            executions: RwLock::new(HashMap::new()),
+            step_cache: MemoryResultCache::new(),
         }
     }
 }
@@ -32,6 +54,15 @@ impl super::Persistence for L0MemoryStore {
         Ok(())
     }
 
+    async fn save_workflow_if_absent(&self, workflow: &Workflow) -> anyhow::Result<bool> {
+        let mut workflows = self.workflows.write().await;
+        if workflows.contains_key(&workflow.id) {
+            return Ok(false);
+        }
+        workflows.insert(workflow.id.clone(), workflow.clone());
+        Ok(true)
+    }
+
     async fn get_workflow(&self, id: &str) -> anyhow::Result<Option<Workflow>> {
         let workflows = self.workflows.read().await;
         Ok(workflows.get(id).cloned())
@@ -48,15 +79,26 @@ impl super::Persistence for L0MemoryStore {
         Ok(result)
     }
 
+    async fn list_workflows_paged(&self, options: &ListOptions) -> anyhow::Result<Vec<Workflow>> {
+        let workflows = self.workflows.read().await;
+        Ok(options.select(workflows.values()))
+    }
+
     async fn update_workflow_state(&self, id: &str, state: WorkflowState) -> anyhow::Result<()> {
         let mut workflows = self.workflows.write().await;
         if let Some(workflow) = workflows.get_mut(id) {
-            workflow.state = state;
-            workflow.updated_at = Utc::now();
+            workflow.transition(state, Utc::now());
         }
         Ok(())
     }
 
+    async fn record_step_attempt(&self, workflow_id: &str, step_name: &str) -> anyhow::Result<u32> {
+        let mut workflows = self.workflows.write().await;
+        Ok(workflows
+            .get_mut(workflow_id)
+            .map_or(0, |workflow| workflow.record_attempt(step_name)))
+    }
+
     async fn save_step_result(
         &self,
         workflow_id: &str,
@@ -81,6 +123,187 @@ impl super::Persistence for L0MemoryStore {
             .get(workflow_id)
             .and_then(|results| results.get(step_name).cloned()))
     }
+
+    async fn append_signal(&self, signal: &Signal) -> anyhow::Result<()> {
+        self.signals
+            .write()
+            .await
+            .entry(signal.workflow_id.clone())
+            .or_default()
+            .push(signal.clone());
+        Ok(())
+    }
+
+    async fn get_signals(&self, workflow_id: &str) -> anyhow::Result<Vec<Signal>> {
+        let signals = self.signals.read().await;
+        Ok(signals.get(workflow_id).cloned().unwrap_or_default())
+    }
+
+    async fn save_timer(&self, timer: &Timer) -> anyhow::Result<()> {
+        self.timers
+            .write()
+            .await
+            .entry(timer.workflow_id.clone())
+            .or_default()
+            .insert(timer.step_name.clone(), timer.clone());
+        Ok(())
+    }
+
+    async fn list_timers(&self) -> anyhow::Result<Vec<Timer>> {
+        let timers = self.timers.read().await;
+        Ok(timers.values().flat_map(|t| t.values().cloned()).collect())
+    }
+
+    async fn delete_timer(&self, workflow_id: &str, step_name: &str) -> anyhow::Result<bool> {
+        let removed = {
+            let mut timers = self.timers.write().await;
+            let removed = timers
+                .get_mut(workflow_id)
+                .and_then(|t| t.remove(step_name))
+                .is_some();
+            if timers.get(workflow_id).is_some_and(|t| t.is_empty()) {
+                timers.remove(workflow_id);
+            }
+            removed
+        };
+        Ok(removed)
+    }
+
+    async fn save_step_deadline(&self, deadline: &StepDeadline) -> anyhow::Result<()> {
+        self.step_deadlines
+            .write()
+            .await
+            .entry(deadline.workflow_id.clone())
+            .or_default()
+            .insert(deadline.step_name.clone(), deadline.clone());
+        Ok(())
+    }
+
+    async fn list_step_deadlines(&self) -> anyhow::Result<Vec<StepDeadline>> {
+        let deadlines = self.step_deadlines.read().await;
+        Ok(deadlines
+            .values()
+            .flat_map(|d| d.values().cloned())
+            .collect())
+    }
+
+    async fn delete_step_deadline(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+    ) -> anyhow::Result<bool> {
+        let mut deadlines = self.step_deadlines.write().await;
+        let removed = deadlines
+            .get_mut(workflow_id)
+            .and_then(|d| d.remove(step_name))
+            .is_some();
+        if deadlines.get(workflow_id).is_some_and(|d| d.is_empty()) {
+            deadlines.remove(workflow_id);
+        }
+        Ok(removed)
+    }
+
+    async fn get_cached_result(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self.step_cache.get(key))
+    }
+
+    async fn put_cached_result(
+        &self,
+        entry: &CachedResult,
+        max_entries: usize,
+    ) -> anyhow::Result<()> {
+        self.step_cache.put(entry, max_entries);
+        Ok(())
+    }
+
+    async fn save_schedule(&self, schedule: &Schedule) -> anyhow::Result<()> {
+        self.schedules
+            .write()
+            .await
+            .insert(schedule.id.clone(), schedule.clone());
+        Ok(())
+    }
+
+    async fn update_schedule(&self, schedule: &Schedule) -> anyhow::Result<bool> {
+        match self.schedules.write().await.get_mut(&schedule.id) {
+            Some(existing) => {
+                *existing = schedule.clone();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn list_schedules(&self) -> anyhow::Result<Vec<Schedule>> {
+        Ok(self.schedules.read().await.values().cloned().collect())
+    }
+
+    async fn delete_schedule(&self, id: &str) -> anyhow::Result<bool> {
+        Ok(self.schedules.write().await.remove(id).is_some())
+    }
+
+    async fn save_definition(&self, definition: &WorkflowDefinition) -> anyhow::Result<()> {
+        self.definitions.write().await.insert(
+            (definition.name.clone(), definition.version),
+            definition.clone(),
+        );
+        Ok(())
+    }
+
+    async fn list_definitions(&self) -> anyhow::Result<Vec<WorkflowDefinition>> {
+        Ok(self.definitions.read().await.values().cloned().collect())
+    }
+
+    async fn save_execution(&self, execution: &WorkflowExecution) -> anyhow::Result<()> {
+        self.executions
+            .write()
+            .await
+            .insert(execution.workflow_id.clone(), execution.clone());
+        Ok(())
+    }
+
+    async fn load_executions(&self) -> anyhow::Result<Vec<WorkflowExecution>> {
+        Ok(self.executions.read().await.values().cloned().collect())
+    }
+
+    async fn delete_workflow(&self, id: &str) -> anyhow::Result<bool> {
+        let mut workflows = self.workflows.write().await;
+        let mut step_results = self.step_results.write().await;
+        step_results.remove(id);
+        self.signals.write().await.remove(id);
+        self.timers.write().await.remove(id);
+        self.step_deadlines.write().await.remove(id);
+        self.executions.write().await.remove(id);
+        Ok(workflows.remove(id).is_some())
+    }
+
+    async fn purge_workflows(&self, filter: &PurgeFilter) -> anyhow::Result<Vec<String>> {
+        let mut workflows = self.workflows.write().await;
+        let mut step_results = self.step_results.write().await;
+        let mut signals = self.signals.write().await;
+        let mut timers = self.timers.write().await;
+        let mut step_deadlines = self.step_deadlines.write().await;
+        let mut executions = self.executions.write().await;
+        let ids: Vec<String> = workflows
+            .values()
+            .filter(|w| filter.matches(w))
+            .map(|w| w.id.clone())
+            .collect();
+
+        for id in &ids {
+            workflows.remove(id);
+            step_results.remove(id);
+            signals.remove(id);
+            timers.remove(id);
+            step_deadlines.remove(id);
+            executions.remove(id);
+        }
+        Ok(ids)
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "memory"
+    }
 }
 
 #[cfg(test)]
@@ -125,6 +348,56 @@ mod tests {
         assert_eq!(all_workflows.len(), 3);
     }
 
+    #[tokio::test]
+    async fn test_list_workflows_paged() {
+        use crate::persistence::{OrderBy, StateKind};
+
+        let store = L0MemoryStore::new();
+        let base = Utc::now();
+        for i in 0..5 {
+            let mut workflow = Workflow::new(format!("wf{}", i), "type-a".to_string(), vec![]);
+            // wf3 与 wf4 同时启动，按 id 决定先后
+            workflow.started_at = base + chrono::Duration::seconds(i.min(3));
+            if i == 1 {
+                workflow.state = WorkflowState::Cancelled;
+            }
+            store.save_workflow(&workflow).await.unwrap();
+        }
+
+        let ids = |workflows: Vec<Workflow>| -> Vec<String> {
+            workflows.into_iter().map(|w| w.id).collect()
+        };
+        let first = store
+            .list_workflows_paged(&ListOptions {
+                limit: Some(2),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(ids(first), vec!["wf0", "wf1"]);
+
+        let pending = store
+            .list_workflows_paged(&ListOptions {
+                offset: 1,
+                order_by: OrderBy::StartedAtDesc,
+                state_filter: Some(StateKind::Pending),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(ids(pending), vec!["wf3", "wf2", "wf0"]);
+
+        let window = store
+            .list_workflows_paged(&ListOptions {
+                started_after: Some(base + chrono::Duration::seconds(2)),
+                started_before: Some(base + chrono::Duration::seconds(3)),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(ids(window), vec!["wf2"]);
+    }
+
     #[tokio::test]
     async fn test_step_results() {
         let store = L0MemoryStore::new();
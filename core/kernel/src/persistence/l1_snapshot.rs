@@ -1,33 +1,245 @@
-use super::Persistence;
+//! L1 快照持久化
+//!
+//! 数据保存在内存中，每 `snapshot_interval` 次写操作把全部 workflow、step 结果、signal、定时器、
+//! cron 调度、workflow 定义和执行追踪记录序列化为 JSON 快照文件（先写临时文件再原子替换）。启动时从最新快照恢复，
+//! 因此崩溃最多丢失最近一次快照之后的写入。
+
+use super::{ListOptions, Persistence, PurgeFilter};
+use crate::definition::WorkflowDefinition;
+use crate::schedule::Schedule;
+use crate::signal::Signal;
 use crate::state_machine::Workflow;
 use crate::state_machine::WorkflowState;
+use crate::step_cache::{CachedResult, MemoryResultCache};
+use crate::step_timeout::StepDeadline;
+use crate::timer::Timer;
+use crate::tracker::WorkflowExecution;
+use anyhow::Context;
 use chrono::Utc;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use tokio::sync::RwLock;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::{Mutex, RwLock};
+
+/// 快照文件格式版本
+const SNAPSHOT_VERSION: u32 = 1;
 
 pub struct L1SnapshotStore {
     workflows: RwLock<HashMap<String, Workflow>>,
     step_results: RwLock<HashMap<String, HashMap<String, Vec<u8>>>>,
-    #[allow(dead_code)]
+    signals: RwLock<HashMap<String, Vec<Signal>>>,
+    timers: RwLock<HashMap<String, HashMap<String, Timer>>>,
+    step_deadlines: RwLock<HashMap<String, HashMap<String, StepDeadline>>>,
+    schedules: RwLock<HashMap<String, Schedule>>,
+    definitions: RwLock<HashMap<(String, u32), WorkflowDefinition>>,
+    executions: RwLock<HashMap<String, WorkflowExecution>>,
+    /// step 结果缓存只保存在内存中，不写入快照
+    step_cache: MemoryResultCache,
+    path: PathBuf,
     snapshot_interval: usize,
+    /// 上次快照之后的写操作次数
+    mutations: AtomicUsize,
+    /// 串行化快照写入，保证较旧的快照不会覆盖较新的快照
+    snapshot_lock: Mutex<()>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    version: u32,
+    workflows: Vec<Workflow>,
+    step_results: HashMap<String, HashMap<String, Vec<u8>>>,
+    /// 旧版本快照没有该字段
+    #[serde(default)]
+    signals: HashMap<String, Vec<Signal>>,
+    #[serde(default)]
+    timers: HashMap<String, HashMap<String, Timer>>,
+    #[serde(default)]
+    step_deadlines: HashMap<String, HashMap<String, StepDeadline>>,
+    #[serde(default)]
+    schedules: Vec<Schedule>,
+    #[serde(default)]
+    definitions: Vec<WorkflowDefinition>,
+    #[serde(default)]
+    executions: Vec<WorkflowExecution>,
 }
 
 impl L1SnapshotStore {
-    pub fn new(snapshot_interval: usize) -> Self {
-        L1SnapshotStore {
-            workflows: RwLock::new(HashMap::new()),
-            step_results: RwLock::new(HashMap::new()),
-            snapshot_interval,
+    /// 打开快照存储，`path` 存在时从中恢复数据
+    pub fn new(path: impl Into<PathBuf>, snapshot_interval: usize) -> anyhow::Result<Self> {
+        let path = path.into();
+        let (
+            workflows,
+            step_results,
+            signals,
+            timers,
+            step_deadlines,
+            schedules,
+            definitions,
+            executions,
+        ) = match Self::load(&path)? {
+            Some(snapshot) => (
+                snapshot
+                    .workflows
+                    .into_iter()
+                    .map(|w| (w.id.clone(), w))
+                    .collect(),
+                snapshot.step_results,
+                snapshot.signals,
+                snapshot.timers,
+                snapshot.step_deadlines,
+                snapshot
+                    .schedules
+                    .into_iter()
+                    .map(|s| (s.id.clone(), s))
+                    .collect(),
+                snapshot
+                    .definitions
+                    .into_iter()
+                    .map(|d| ((d.name.clone(), d.version), d))
+                    .collect(),
+                snapshot
+                    .executions
+                    .into_iter()
+                    .map(|e| (e.workflow_id.clone(), e))
+                    .collect(),
+            ),
+            None => (
+                HashMap::new(),
+                HashMap::new(),
+                HashMap::new(),
+                HashMap::new(),
+                HashMap::new(),
+                HashMap::new(),
+                HashMap::new(),
+                HashMap::new(),
+            ),
+        };
+
+        Ok(L1SnapshotStore {
+            workflows: RwLock::new(workflows),
+            step_results: RwLock::new(step_results),
+            signals: RwLock::new(signals),
+            timers: RwLock::new(timers),
+            step_deadlines: RwLock::new(step_deadlines),
+            schedules: RwLock::new(schedules),
+            definitions: RwLock::new(definitions),
+            executions: RwLock::new(executions),
+            step_cache: MemoryResultCache::new(),
+            path,
+            snapshot_interval: snapshot_interval.max(1),
+            mutations: AtomicUsize::new(0),
+            snapshot_lock: Mutex::new(()),
+        })
+    }
+
+    /// 快照文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn load(path: &Path) -> anyhow::Result<Option<Snapshot>> {
+        let content = match std::fs::read(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read snapshot {}", path.display()))
+            }
+        };
+
+        let snapshot: Snapshot = serde_json::from_slice(&content)
+            .with_context(|| format!("Invalid snapshot file {}", path.display()))?;
+        if snapshot.version != SNAPSHOT_VERSION {
+            anyhow::bail!(
+                "Unsupported snapshot version {} in {}",
+                snapshot.version,
+                path.display()
+            );
         }
+        Ok(Some(snapshot))
     }
+
+    /// 立即写入快照
+    pub async fn flush(&self) -> anyhow::Result<()> {
+        let _guard = self.snapshot_lock.lock().await;
+
+        // 先清零计数再读取数据：读取期间的写入会重新计数，由下一次快照覆盖
+        self.mutations.store(0, Ordering::SeqCst);
+        let content = {
+            let workflows = self.workflows.read().await;
+            let step_results = self.step_results.read().await;
+            let signals = self.signals.read().await;
+            let timers = self.timers.read().await;
+            let step_deadlines = self.step_deadlines.read().await;
+            let schedules = self.schedules.read().await;
+            let definitions = self.definitions.read().await;
+            let executions = self.executions.read().await;
+            serde_json::to_vec(&Snapshot {
+                version: SNAPSHOT_VERSION,
+                workflows: workflows.values().cloned().collect(),
+                step_results: step_results.clone(),
+                signals: signals.clone(),
+                timers: timers.clone(),
+                step_deadlines: step_deadlines.clone(),
+                schedules: schedules.values().cloned().collect(),
+                definitions: definitions.values().cloned().collect(),
+                executions: executions.values().cloned().collect(),
+            })?
+        };
+
+        let path = self.path.clone();
+        tokio::task::spawn_blocking(move || write_atomically(&path, &content)).await?
+    }
+
+    /// 记录一次写操作，达到间隔时写入快照
+    async fn record_mutation(&self) -> anyhow::Result<()> {
+        let count = self.mutations.fetch_add(1, Ordering::SeqCst) + 1;
+        if count >= self.snapshot_interval {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+}
+
+/// 写入临时文件并 fsync 后重命名，避免崩溃时留下不完整的快照
+fn write_atomically(path: &Path, content: &[u8]) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+
+    let tmp_path = path.with_extension("tmp");
+    let mut file = std::fs::File::create(&tmp_path)
+        .with_context(|| format!("Failed to create {}", tmp_path.display()))?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    std::fs::rename(&tmp_path, path)
+        .with_context(|| format!("Failed to replace snapshot {}", path.display()))?;
+    Ok(())
 }
 
 #[async_trait::async_trait]
 impl Persistence for L1SnapshotStore {
     async fn save_workflow(&self, workflow: &Workflow) -> anyhow::Result<()> {
-        let mut workflows = self.workflows.write().await;
-        workflows.insert(workflow.id.clone(), workflow.clone());
-        Ok(())
+        self.workflows
+            .write()
+            .await
+            .insert(workflow.id.clone(), workflow.clone());
+        self.record_mutation().await
+    }
+
+    async fn save_workflow_if_absent(&self, workflow: &Workflow) -> anyhow::Result<bool> {
+        {
+            let mut workflows = self.workflows.write().await;
+            if workflows.contains_key(&workflow.id) {
+                return Ok(false);
+            }
+            workflows.insert(workflow.id.clone(), workflow.clone());
+        }
+        self.record_mutation().await?;
+        Ok(true)
     }
 
     async fn get_workflow(&self, id: &str) -> anyhow::Result<Option<Workflow>> {
@@ -46,13 +258,30 @@ impl Persistence for L1SnapshotStore {
         Ok(result)
     }
 
+    async fn list_workflows_paged(&self, options: &ListOptions) -> anyhow::Result<Vec<Workflow>> {
+        let workflows = self.workflows.read().await;
+        Ok(options.select(workflows.values()))
+    }
+
     async fn update_workflow_state(&self, id: &str, state: WorkflowState) -> anyhow::Result<()> {
-        let mut workflows = self.workflows.write().await;
-        if let Some(workflow) = workflows.get_mut(id) {
-            workflow.state = state;
-            workflow.updated_at = Utc::now();
+        {
+            let mut workflows = self.workflows.write().await;
+            if let Some(workflow) = workflows.get_mut(id) {
+                workflow.transition(state, Utc::now());
+            }
         }
-        Ok(())
+        self.record_mutation().await
+    }
+
+    async fn record_step_attempt(&self, workflow_id: &str, step_name: &str) -> anyhow::Result<u32> {
+        let attempt = self
+            .workflows
+            .write()
+            .await
+            .get_mut(workflow_id)
+            .map_or(0, |workflow| workflow.record_attempt(step_name));
+        self.record_mutation().await?;
+        Ok(attempt)
     }
 
     async fn save_step_result(
@@ -61,12 +290,13 @@ impl Persistence for L1SnapshotStore {
         step_name: &str,
         result: Vec<u8>,
     ) -> anyhow::Result<()> {
-        let mut step_results = self.step_results.write().await;
-        let workflow_results = step_results
+        self.step_results
+            .write()
+            .await
             .entry(workflow_id.to_string())
-            .or_insert_with(HashMap::new);
-        workflow_results.insert(step_name.to_string(), result);
-        Ok(())
+            .or_insert_with(HashMap::new)
+            .insert(step_name.to_string(), result);
+        self.record_mutation().await
     }
 
     async fn get_step_result(
@@ -79,4 +309,322 @@ impl Persistence for L1SnapshotStore {
             .get(workflow_id)
             .and_then(|results| results.get(step_name).cloned()))
     }
+
+    async fn append_signal(&self, signal: &Signal) -> anyhow::Result<()> {
+        self.signals
+            .write()
+            .await
+            .entry(signal.workflow_id.clone())
+            .or_default()
+            .push(signal.clone());
+        self.record_mutation().await
+    }
+
+    async fn get_signals(&self, workflow_id: &str) -> anyhow::Result<Vec<Signal>> {
+        let signals = self.signals.read().await;
+        Ok(signals.get(workflow_id).cloned().unwrap_or_default())
+    }
+
+    async fn save_timer(&self, timer: &Timer) -> anyhow::Result<()> {
+        self.timers
+            .write()
+            .await
+            .entry(timer.workflow_id.clone())
+            .or_default()
+            .insert(timer.step_name.clone(), timer.clone());
+        self.record_mutation().await
+    }
+
+    async fn list_timers(&self) -> anyhow::Result<Vec<Timer>> {
+        let timers = self.timers.read().await;
+        Ok(timers.values().flat_map(|t| t.values().cloned()).collect())
+    }
+
+    async fn delete_timer(&self, workflow_id: &str, step_name: &str) -> anyhow::Result<bool> {
+        let removed = {
+            let mut timers = self.timers.write().await;
+            let removed = timers
+                .get_mut(workflow_id)
+                .and_then(|t| t.remove(step_name))
+                .is_some();
+            if timers.get(workflow_id).is_some_and(|t| t.is_empty()) {
+                timers.remove(workflow_id);
+            }
+            removed
+        };
+        if removed {
+            self.record_mutation().await?;
+        }
+        Ok(removed)
+    }
+
+    async fn save_step_deadline(&self, deadline: &StepDeadline) -> anyhow::Result<()> {
+        self.step_deadlines
+            .write()
+            .await
+            .entry(deadline.workflow_id.clone())
+            .or_default()
+            .insert(deadline.step_name.clone(), deadline.clone());
+        self.record_mutation().await
+    }
+
+    async fn list_step_deadlines(&self) -> anyhow::Result<Vec<StepDeadline>> {
+        let deadlines = self.step_deadlines.read().await;
+        Ok(deadlines
+            .values()
+            .flat_map(|d| d.values().cloned())
+            .collect())
+    }
+
+    async fn get_cached_result(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self.step_cache.get(key))
+    }
+
+    async fn put_cached_result(
+        &self,
+        entry: &CachedResult,
+        max_entries: usize,
+    ) -> anyhow::Result<()> {
+        self.step_cache.put(entry, max_entries);
+        Ok(())
+    }
+
+    async fn save_schedule(&self, schedule: &Schedule) -> anyhow::Result<()> {
+        self.schedules
+            .write()
+            .await
+            .insert(schedule.id.clone(), schedule.clone());
+        self.record_mutation().await
+    }
+
+    async fn update_schedule(&self, schedule: &Schedule) -> anyhow::Result<bool> {
+        match self.schedules.write().await.get_mut(&schedule.id) {
+            Some(existing) => *existing = schedule.clone(),
+            None => return Ok(false),
+        }
+        self.record_mutation().await?;
+        Ok(true)
+    }
+
+    async fn list_schedules(&self) -> anyhow::Result<Vec<Schedule>> {
+        Ok(self.schedules.read().await.values().cloned().collect())
+    }
+
+    async fn delete_schedule(&self, id: &str) -> anyhow::Result<bool> {
+        let removed = self.schedules.write().await.remove(id).is_some();
+        if removed {
+            self.record_mutation().await?;
+        }
+        Ok(removed)
+    }
+
+    async fn save_definition(&self, definition: &WorkflowDefinition) -> anyhow::Result<()> {
+        self.definitions.write().await.insert(
+            (definition.name.clone(), definition.version),
+            definition.clone(),
+        );
+        self.record_mutation().await
+    }
+
+    async fn list_definitions(&self) -> anyhow::Result<Vec<WorkflowDefinition>> {
+        Ok(self.definitions.read().await.values().cloned().collect())
+    }
+
+    async fn delete_step_deadline(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+    ) -> anyhow::Result<bool> {
+        let removed = {
+            let mut deadlines = self.step_deadlines.write().await;
+            let removed = deadlines
+                .get_mut(workflow_id)
+                .and_then(|d| d.remove(step_name))
+                .is_some();
+            if deadlines.get(workflow_id).is_some_and(|d| d.is_empty()) {
+                deadlines.remove(workflow_id);
+            }
+            removed
+        };
+        if removed {
+            self.record_mutation().await?;
+        }
+        Ok(removed)
+    }
+
+    async fn save_execution(&self, execution: &WorkflowExecution) -> anyhow::Result<()> {
+        self.executions
+            .write()
+            .await
+            .insert(execution.workflow_id.clone(), execution.clone());
+        self.record_mutation().await
+    }
+
+    async fn load_executions(&self) -> anyhow::Result<Vec<WorkflowExecution>> {
+        Ok(self.executions.read().await.values().cloned().collect())
+    }
+
+    async fn delete_workflow(&self, id: &str) -> anyhow::Result<bool> {
+        let existed = {
+            let mut workflows = self.workflows.write().await;
+            let mut step_results = self.step_results.write().await;
+            step_results.remove(id);
+            self.signals.write().await.remove(id);
+            self.timers.write().await.remove(id);
+            self.step_deadlines.write().await.remove(id);
+            self.executions.write().await.remove(id);
+            workflows.remove(id).is_some()
+        };
+        if existed {
+            self.record_mutation().await?;
+        }
+        Ok(existed)
+    }
+
+    async fn purge_workflows(&self, filter: &PurgeFilter) -> anyhow::Result<Vec<String>> {
+        let ids: Vec<String> = {
+            let mut workflows = self.workflows.write().await;
+            let mut step_results = self.step_results.write().await;
+            let mut signals = self.signals.write().await;
+            let mut timers = self.timers.write().await;
+            let mut step_deadlines = self.step_deadlines.write().await;
+            let mut executions = self.executions.write().await;
+            let ids: Vec<String> = workflows
+                .values()
+                .filter(|w| filter.matches(w))
+                .map(|w| w.id.clone())
+                .collect();
+
+            for id in &ids {
+                workflows.remove(id);
+                step_results.remove(id);
+                signals.remove(id);
+                timers.remove(id);
+                step_deadlines.remove(id);
+                executions.remove(id);
+            }
+            ids
+        };
+        if !ids.is_empty() {
+            self.record_mutation().await?;
+        }
+        Ok(ids)
+    }
+
+    async fn flush(&self) -> anyhow::Result<()> {
+        L1SnapshotStore::flush(self).await
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "snapshot"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn snapshot_path(dir: &tempfile::TempDir) -> PathBuf {
+        dir.path().join("data").join("snapshot.json")
+    }
+
+    #[tokio::test]
+    async fn test_reload_after_flush() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = snapshot_path(&dir);
+
+        let store = L1SnapshotStore::new(&path, 100).unwrap();
+        let workflow = Workflow::new("wf-1".to_string(), "order".to_string(), b"in".to_vec());
+        store.save_workflow(&workflow).await.unwrap();
+        store
+            .update_workflow_state("wf-1", WorkflowState::Running { current_step: None })
+            .await
+            .unwrap();
+        store
+            .save_step_result("wf-1", "charge", b"ok".to_vec())
+            .await
+            .unwrap();
+        let signal = Signal::new("wf-1", "approved", b"yes".to_vec());
+        store.append_signal(&signal).await.unwrap();
+        // 未达到间隔，尚未写入快照
+        assert!(!path.exists());
+
+        store.flush().await.unwrap();
+        drop(store);
+
+        let reopened = L1SnapshotStore::new(&path, 100).unwrap();
+        let restored = reopened.get_workflow("wf-1").await.unwrap().unwrap();
+        assert_eq!(restored.input, b"in".to_vec());
+        assert!(matches!(restored.state, WorkflowState::Running { .. }));
+        assert_eq!(
+            reopened.get_step_result("wf-1", "charge").await.unwrap(),
+            Some(b"ok".to_vec())
+        );
+        assert_eq!(reopened.get_signals("wf-1").await.unwrap(), vec![signal]);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_every_interval() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = snapshot_path(&dir);
+
+        let store = L1SnapshotStore::new(&path, 2).unwrap();
+        for id in ["wf-1", "wf-2", "wf-3"] {
+            let workflow = Workflow::new(id.to_string(), "order".to_string(), vec![]);
+            store.save_workflow(&workflow).await.unwrap();
+        }
+
+        // 第二次写入触发快照，第三次尚未写入
+        let reopened = L1SnapshotStore::new(&path, 2).unwrap();
+        assert_eq!(reopened.list_workflows(None).await.unwrap().len(), 2);
+        assert!(reopened.get_workflow("wf-3").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_saves_are_not_lost() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = snapshot_path(&dir);
+
+        let store = Arc::new(L1SnapshotStore::new(&path, 7).unwrap());
+        let handles: Vec<_> = (0..50)
+            .map(|i| {
+                let store = store.clone();
+                tokio::spawn(async move {
+                    let workflow = Workflow::new(format!("wf-{}", i), "order".to_string(), vec![]);
+                    store.save_workflow(&workflow).await.unwrap();
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        store.flush().await.unwrap();
+
+        let reopened = L1SnapshotStore::new(&path, 7).unwrap();
+        assert_eq!(reopened.list_workflows(None).await.unwrap().len(), 50);
+    }
+
+    #[tokio::test]
+    async fn test_deletes_are_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = snapshot_path(&dir);
+
+        let store = L1SnapshotStore::new(&path, 1).unwrap();
+        let workflow = Workflow::new("wf-1".to_string(), "order".to_string(), vec![]);
+        store.save_workflow(&workflow).await.unwrap();
+        assert!(store.delete_workflow("wf-1").await.unwrap());
+
+        let reopened = L1SnapshotStore::new(&path, 1).unwrap();
+        assert!(reopened.list_workflows(None).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_corrupt_snapshot_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot.json");
+        std::fs::write(&path, b"not json").unwrap();
+
+        assert!(L1SnapshotStore::new(&path, 1).is_err());
+    }
 }
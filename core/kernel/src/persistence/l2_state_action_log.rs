@@ -1,16 +1,42 @@
-use super::Persistence;
+//! L2 State-Action-Log 持久化
+//!
+//! 每次写操作先以长度前缀记录（4 字节小端长度 + JSON）追加到日志文件，
+//! 再更新内存。启动时按顺序重放日志重建数据；崩溃时写了一半的最后一条记录会被丢弃。
+
+use super::{ListOptions, Persistence, PurgeFilter};
+use crate::definition::WorkflowDefinition;
+use crate::schedule::Schedule;
+use crate::signal::Signal;
 use crate::state_machine::Workflow;
 use crate::state_machine::WorkflowState;
+use crate::step_cache::{CachedResult, MemoryResultCache};
+use crate::step_timeout::StepDeadline;
+use crate::timer::Timer;
+use crate::tracker::WorkflowExecution;
+use anyhow::Context;
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use tokio::sync::RwLock;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tokio::sync::{Mutex, RwLock};
 
 #[allow(dead_code)]
 pub struct L2StateActionStore {
     workflows: RwLock<HashMap<String, Workflow>>,
     step_results: RwLock<HashMap<String, HashMap<String, Vec<u8>>>>,
-    #[allow(dead_code)]
+    signals: RwLock<HashMap<String, Vec<Signal>>>,
+    timers: RwLock<HashMap<String, HashMap<String, Timer>>>,
+    step_deadlines: RwLock<HashMap<String, HashMap<String, StepDeadline>>>,
+    schedules: RwLock<HashMap<String, Schedule>>,
+    definitions: RwLock<HashMap<(String, u32), WorkflowDefinition>>,
+    executions: RwLock<HashMap<String, WorkflowExecution>>,
+    /// step 结果缓存只保存在内存中，不写入日志
+    step_cache: MemoryResultCache,
     action_logs: RwLock<Vec<ActionLog>>,
+    /// 日志文件，`None` 表示纯内存模式；写操作持有该锁以保证日志与内存顺序一致
+    log: Mutex<Option<LogWriter>>,
 }
 
 #[derive(Debug, Clone)]
@@ -23,6 +49,255 @@ pub struct ActionLog {
     pub output: Vec<u8>,
 }
 
+/// 日志 fsync 策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SyncPolicy {
+    /// 每条记录写入后立即 fsync
+    #[default]
+    Always,
+    /// 每写入 N 条记录 fsync 一次
+    EveryN(usize),
+    /// 不主动 fsync，由操作系统决定何时落盘
+    Never,
+}
+
+/// 日志记录
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum LogRecord {
+    SaveWorkflow {
+        workflow: Workflow,
+    },
+    UpdateState {
+        id: String,
+        state: WorkflowState,
+        updated_at: DateTime<Utc>,
+    },
+    RecordAttempt {
+        workflow_id: String,
+        step_name: String,
+    },
+    SaveStepResult {
+        workflow_id: String,
+        step_name: String,
+        result: Vec<u8>,
+    },
+    AppendSignal {
+        signal: Signal,
+    },
+    SaveTimer {
+        timer: Timer,
+    },
+    DeleteTimer {
+        workflow_id: String,
+        step_name: String,
+    },
+    SaveStepDeadline {
+        deadline: StepDeadline,
+    },
+    DeleteStepDeadline {
+        workflow_id: String,
+        step_name: String,
+    },
+    SaveSchedule {
+        schedule: Schedule,
+    },
+    DeleteSchedule {
+        id: String,
+    },
+    SaveDefinition {
+        definition: WorkflowDefinition,
+    },
+    SaveExecution {
+        execution: WorkflowExecution,
+    },
+    DeleteWorkflows {
+        ids: Vec<String>,
+    },
+}
+
+struct LogWriter {
+    file: File,
+    sync: SyncPolicy,
+    /// 上次 fsync 之后写入的记录数
+    unsynced: usize,
+}
+
+impl LogWriter {
+    fn append(&mut self, record: &LogRecord) -> anyhow::Result<()> {
+        let payload = serde_json::to_vec(record)?;
+        let len = u32::try_from(payload.len()).context("Log record too large")?;
+
+        let mut frame = Vec::with_capacity(4 + payload.len());
+        frame.extend_from_slice(&len.to_le_bytes());
+        frame.extend_from_slice(&payload);
+        self.file.write_all(&frame)?;
+
+        self.unsynced += 1;
+        let sync = match self.sync {
+            SyncPolicy::Always => true,
+            SyncPolicy::EveryN(n) => self.unsynced >= n.max(1),
+            SyncPolicy::Never => false,
+        };
+        if sync {
+            self.file.sync_data()?;
+            self.unsynced = 0;
+        }
+        Ok(())
+    }
+}
+
+/// 内存表，供重放使用
+#[derive(Default)]
+struct Tables {
+    workflows: HashMap<String, Workflow>,
+    step_results: HashMap<String, HashMap<String, Vec<u8>>>,
+    signals: HashMap<String, Vec<Signal>>,
+    timers: HashMap<String, HashMap<String, Timer>>,
+    step_deadlines: HashMap<String, HashMap<String, StepDeadline>>,
+    schedules: HashMap<String, Schedule>,
+    definitions: HashMap<(String, u32), WorkflowDefinition>,
+    executions: HashMap<String, WorkflowExecution>,
+}
+
+impl Tables {
+    fn apply(&mut self, record: LogRecord) {
+        match record {
+            LogRecord::SaveWorkflow { workflow } => {
+                self.workflows.insert(workflow.id.clone(), workflow);
+            }
+            LogRecord::UpdateState {
+                id,
+                state,
+                updated_at,
+            } => {
+                if let Some(workflow) = self.workflows.get_mut(&id) {
+                    workflow.transition(state, updated_at);
+                }
+            }
+            LogRecord::RecordAttempt {
+                workflow_id,
+                step_name,
+            } => {
+                if let Some(workflow) = self.workflows.get_mut(&workflow_id) {
+                    workflow.record_attempt(&step_name);
+                }
+            }
+            LogRecord::SaveStepResult {
+                workflow_id,
+                step_name,
+                result,
+            } => {
+                self.step_results
+                    .entry(workflow_id)
+                    .or_default()
+                    .insert(step_name, result);
+            }
+            LogRecord::AppendSignal { signal } => {
+                self.signals
+                    .entry(signal.workflow_id.clone())
+                    .or_default()
+                    .push(signal);
+            }
+            LogRecord::SaveTimer { timer } => {
+                self.timers
+                    .entry(timer.workflow_id.clone())
+                    .or_default()
+                    .insert(timer.step_name.clone(), timer);
+            }
+            LogRecord::DeleteTimer {
+                workflow_id,
+                step_name,
+            } => {
+                remove_step_entry(&mut self.timers, &workflow_id, &step_name);
+            }
+            LogRecord::SaveStepDeadline { deadline } => {
+                self.step_deadlines
+                    .entry(deadline.workflow_id.clone())
+                    .or_default()
+                    .insert(deadline.step_name.clone(), deadline);
+            }
+            LogRecord::DeleteStepDeadline {
+                workflow_id,
+                step_name,
+            } => {
+                remove_step_entry(&mut self.step_deadlines, &workflow_id, &step_name);
+            }
+            LogRecord::SaveSchedule { schedule } => {
+                self.schedules.insert(schedule.id.clone(), schedule);
+            }
+            LogRecord::DeleteSchedule { id } => {
+                self.schedules.remove(&id);
+            }
+            LogRecord::SaveDefinition { definition } => {
+                self.definitions
+                    .insert((definition.name.clone(), definition.version), definition);
+            }
+            LogRecord::SaveExecution { execution } => {
+                self.executions
+                    .insert(execution.workflow_id.clone(), execution);
+            }
+            LogRecord::DeleteWorkflows { ids } => {
+                for id in ids {
+                    self.workflows.remove(&id);
+                    self.step_results.remove(&id);
+                    self.signals.remove(&id);
+                    self.timers.remove(&id);
+                    self.step_deadlines.remove(&id);
+                    self.executions.remove(&id);
+                }
+            }
+        }
+    }
+}
+
+/// 删除某个 step 的定时器或截止时间，返回它是否存在
+fn remove_step_entry<T>(
+    entries: &mut HashMap<String, HashMap<String, T>>,
+    workflow_id: &str,
+    step_name: &str,
+) -> bool {
+    let removed = entries
+        .get_mut(workflow_id)
+        .and_then(|e| e.remove(step_name))
+        .is_some();
+    if entries.get(workflow_id).is_some_and(|e| e.is_empty()) {
+        entries.remove(workflow_id);
+    }
+    removed
+}
+
+/// 重放日志，返回重建的数据和有效数据的长度
+///
+/// 末尾不完整或无法解析的记录视为崩溃时写了一半，直接丢弃；
+/// 中间的记录损坏则返回错误。
+fn replay(content: &[u8]) -> anyhow::Result<(Tables, u64)> {
+    let mut tables = Tables::default();
+    let mut offset = 0;
+
+    while offset < content.len() {
+        let Some(header) = content.get(offset..offset + 4) else {
+            break;
+        };
+        let len = u32::from_le_bytes(header.try_into().expect("4-byte header")) as usize;
+        let end = offset + 4 + len;
+        let Some(payload) = content.get(offset + 4..end) else {
+            break;
+        };
+
+        match serde_json::from_slice::<LogRecord>(payload) {
+            Ok(record) => tables.apply(record),
+            Err(_) if end == content.len() => break,
+            Err(e) => {
+                return Err(e).with_context(|| format!("Corrupt log record at offset {}", offset))
+            }
+        }
+        offset = end;
+    }
+
+    Ok((tables, offset as u64))
+}
+
 impl Default for L2StateActionStore {
     fn default() -> Self {
         Self::new()
@@ -30,23 +305,124 @@ impl Default for L2StateActionStore {
 }
 
 impl L2StateActionStore {
+    /// 纯内存模式，不写日志
     pub fn new() -> Self {
+        Self::from_tables(Tables::default(), None)
+    }
+
+    /// 打开日志文件（不存在时创建）并重放，每条记录都会 fsync
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Self::open_with_sync(path, SyncPolicy::default())
+    }
+
+    /// 以指定的 fsync 策略打开日志文件
+    pub fn open_with_sync(path: impl AsRef<Path>, sync: SyncPolicy) -> anyhow::Result<Self> {
+        let path: PathBuf = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+
+        let content = match std::fs::read(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read log {}", path.display()))
+            }
+        };
+        let (tables, valid_len) =
+            replay(&content).with_context(|| format!("Failed to replay {}", path.display()))?;
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open log {}", path.display()))?;
+        if valid_len < content.len() as u64 {
+            tracing::warn!(
+                "Discarding {} bytes of incomplete record at the end of {}",
+                content.len() as u64 - valid_len,
+                path.display()
+            );
+            file.set_len(valid_len)?;
+            file.sync_all()?;
+        }
+
+        let writer = LogWriter {
+            file,
+            sync,
+            unsynced: 0,
+        };
+        Ok(Self::from_tables(tables, Some(writer)))
+    }
+
+    fn from_tables(tables: Tables, log: Option<LogWriter>) -> Self {
         L2StateActionStore {
-            workflows: RwLock::new(HashMap::new()),
-            step_results: RwLock::new(HashMap::new()),
+            workflows: RwLock::new(tables.workflows),
+            step_results: RwLock::new(tables.step_results),
+            signals: RwLock::new(tables.signals),
+            timers: RwLock::new(tables.timers),
+            step_deadlines: RwLock::new(tables.step_deadlines),
+            schedules: RwLock::new(tables.schedules),
+            definitions: RwLock::new(tables.definitions),
+            executions: RwLock::new(tables.executions),
+            step_cache: MemoryResultCache::new(),
             action_logs: RwLock::new(Vec::new()),
+            log: Mutex::new(log),
+        }
+    }
+
+    /// 把 fsync 策略尚未落盘的记录强制写入磁盘
+    pub async fn sync(&self) -> anyhow::Result<()> {
+        if let Some(writer) = self.log.lock().await.as_mut() {
+            writer.file.sync_data()?;
+            writer.unsynced = 0;
         }
+        Ok(())
+    }
+}
+
+fn append(log: &mut Option<LogWriter>, record: &LogRecord) -> anyhow::Result<()> {
+    match log {
+        Some(writer) => writer.append(record),
+        None => Ok(()),
     }
 }
 
 #[async_trait::async_trait]
 impl Persistence for L2StateActionStore {
     async fn save_workflow(&self, workflow: &Workflow) -> anyhow::Result<()> {
+        let mut log = self.log.lock().await;
+        append(
+            &mut log,
+            &LogRecord::SaveWorkflow {
+                workflow: workflow.clone(),
+            },
+        )?;
+
         let mut workflows = self.workflows.write().await;
         workflows.insert(workflow.id.clone(), workflow.clone());
         Ok(())
     }
 
+    async fn save_workflow_if_absent(&self, workflow: &Workflow) -> anyhow::Result<bool> {
+        // 持有日志锁完成检查和追加，与其他写入串行
+        let mut log = self.log.lock().await;
+        if self.workflows.read().await.contains_key(&workflow.id) {
+            return Ok(false);
+        }
+        append(
+            &mut log,
+            &LogRecord::SaveWorkflow {
+                workflow: workflow.clone(),
+            },
+        )?;
+
+        let mut workflows = self.workflows.write().await;
+        workflows.insert(workflow.id.clone(), workflow.clone());
+        Ok(true)
+    }
+
     async fn get_workflow(&self, id: &str) -> anyhow::Result<Option<Workflow>> {
         let workflows = self.workflows.read().await;
         Ok(workflows.get(id).cloned())
@@ -63,21 +439,61 @@ impl Persistence for L2StateActionStore {
         Ok(result)
     }
 
+    async fn list_workflows_paged(&self, options: &ListOptions) -> anyhow::Result<Vec<Workflow>> {
+        let workflows = self.workflows.read().await;
+        Ok(options.select(workflows.values()))
+    }
+
     async fn update_workflow_state(&self, id: &str, state: WorkflowState) -> anyhow::Result<()> {
+        let mut log = self.log.lock().await;
         let mut workflows = self.workflows.write().await;
         if let Some(workflow) = workflows.get_mut(id) {
-            workflow.state = state;
-            workflow.updated_at = Utc::now();
+            let updated_at = Utc::now();
+            append(
+                &mut log,
+                &LogRecord::UpdateState {
+                    id: id.to_string(),
+                    state: state.clone(),
+                    updated_at,
+                },
+            )?;
+            workflow.transition(state, updated_at);
         }
         Ok(())
     }
 
+    async fn record_step_attempt(&self, workflow_id: &str, step_name: &str) -> anyhow::Result<u32> {
+        let mut log = self.log.lock().await;
+        let mut workflows = self.workflows.write().await;
+        let Some(workflow) = workflows.get_mut(workflow_id) else {
+            return Ok(0);
+        };
+        append(
+            &mut log,
+            &LogRecord::RecordAttempt {
+                workflow_id: workflow_id.to_string(),
+                step_name: step_name.to_string(),
+            },
+        )?;
+        Ok(workflow.record_attempt(step_name))
+    }
+
     async fn save_step_result(
         &self,
         workflow_id: &str,
         step_name: &str,
         result: Vec<u8>,
     ) -> anyhow::Result<()> {
+        let mut log = self.log.lock().await;
+        append(
+            &mut log,
+            &LogRecord::SaveStepResult {
+                workflow_id: workflow_id.to_string(),
+                step_name: step_name.to_string(),
+                result: result.clone(),
+            },
+        )?;
+
         let mut step_results = self.step_results.write().await;
         let workflow_results = step_results
             .entry(workflow_id.to_string())
@@ -96,4 +512,497 @@ impl Persistence for L2StateActionStore {
             .get(workflow_id)
             .and_then(|results| results.get(step_name).cloned()))
     }
+
+    async fn append_signal(&self, signal: &Signal) -> anyhow::Result<()> {
+        let mut log = self.log.lock().await;
+        append(
+            &mut log,
+            &LogRecord::AppendSignal {
+                signal: signal.clone(),
+            },
+        )?;
+
+        self.signals
+            .write()
+            .await
+            .entry(signal.workflow_id.clone())
+            .or_default()
+            .push(signal.clone());
+        Ok(())
+    }
+
+    async fn get_signals(&self, workflow_id: &str) -> anyhow::Result<Vec<Signal>> {
+        let signals = self.signals.read().await;
+        Ok(signals.get(workflow_id).cloned().unwrap_or_default())
+    }
+
+    async fn save_timer(&self, timer: &Timer) -> anyhow::Result<()> {
+        let mut log = self.log.lock().await;
+        append(
+            &mut log,
+            &LogRecord::SaveTimer {
+                timer: timer.clone(),
+            },
+        )?;
+
+        self.timers
+            .write()
+            .await
+            .entry(timer.workflow_id.clone())
+            .or_default()
+            .insert(timer.step_name.clone(), timer.clone());
+        Ok(())
+    }
+
+    async fn list_timers(&self) -> anyhow::Result<Vec<Timer>> {
+        let timers = self.timers.read().await;
+        Ok(timers.values().flat_map(|t| t.values().cloned()).collect())
+    }
+
+    async fn delete_timer(&self, workflow_id: &str, step_name: &str) -> anyhow::Result<bool> {
+        let mut log = self.log.lock().await;
+        let mut timers = self.timers.write().await;
+        if !timers
+            .get(workflow_id)
+            .is_some_and(|t| t.contains_key(step_name))
+        {
+            return Ok(false);
+        }
+
+        append(
+            &mut log,
+            &LogRecord::DeleteTimer {
+                workflow_id: workflow_id.to_string(),
+                step_name: step_name.to_string(),
+            },
+        )?;
+        Ok(remove_step_entry(&mut timers, workflow_id, step_name))
+    }
+
+    async fn save_step_deadline(&self, deadline: &StepDeadline) -> anyhow::Result<()> {
+        let mut log = self.log.lock().await;
+        append(
+            &mut log,
+            &LogRecord::SaveStepDeadline {
+                deadline: deadline.clone(),
+            },
+        )?;
+
+        self.step_deadlines
+            .write()
+            .await
+            .entry(deadline.workflow_id.clone())
+            .or_default()
+            .insert(deadline.step_name.clone(), deadline.clone());
+        Ok(())
+    }
+
+    async fn list_step_deadlines(&self) -> anyhow::Result<Vec<StepDeadline>> {
+        let deadlines = self.step_deadlines.read().await;
+        Ok(deadlines
+            .values()
+            .flat_map(|d| d.values().cloned())
+            .collect())
+    }
+
+    async fn delete_step_deadline(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+    ) -> anyhow::Result<bool> {
+        let mut log = self.log.lock().await;
+        let mut deadlines = self.step_deadlines.write().await;
+        if !deadlines
+            .get(workflow_id)
+            .is_some_and(|d| d.contains_key(step_name))
+        {
+            return Ok(false);
+        }
+
+        append(
+            &mut log,
+            &LogRecord::DeleteStepDeadline {
+                workflow_id: workflow_id.to_string(),
+                step_name: step_name.to_string(),
+            },
+        )?;
+        Ok(remove_step_entry(&mut deadlines, workflow_id, step_name))
+    }
+
+    async fn get_cached_result(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self.step_cache.get(key))
+    }
+
+    async fn put_cached_result(
+        &self,
+        entry: &CachedResult,
+        max_entries: usize,
+    ) -> anyhow::Result<()> {
+        self.step_cache.put(entry, max_entries);
+        Ok(())
+    }
+
+    async fn save_schedule(&self, schedule: &Schedule) -> anyhow::Result<()> {
+        let mut log = self.log.lock().await;
+        append(
+            &mut log,
+            &LogRecord::SaveSchedule {
+                schedule: schedule.clone(),
+            },
+        )?;
+
+        self.schedules
+            .write()
+            .await
+            .insert(schedule.id.clone(), schedule.clone());
+        Ok(())
+    }
+
+    async fn update_schedule(&self, schedule: &Schedule) -> anyhow::Result<bool> {
+        let mut log = self.log.lock().await;
+        let mut schedules = self.schedules.write().await;
+        if !schedules.contains_key(&schedule.id) {
+            return Ok(false);
+        }
+
+        append(
+            &mut log,
+            &LogRecord::SaveSchedule {
+                schedule: schedule.clone(),
+            },
+        )?;
+        schedules.insert(schedule.id.clone(), schedule.clone());
+        Ok(true)
+    }
+
+    async fn list_schedules(&self) -> anyhow::Result<Vec<Schedule>> {
+        Ok(self.schedules.read().await.values().cloned().collect())
+    }
+
+    async fn delete_schedule(&self, id: &str) -> anyhow::Result<bool> {
+        let mut log = self.log.lock().await;
+        let mut schedules = self.schedules.write().await;
+        if !schedules.contains_key(id) {
+            return Ok(false);
+        }
+
+        append(&mut log, &LogRecord::DeleteSchedule { id: id.to_string() })?;
+        Ok(schedules.remove(id).is_some())
+    }
+
+    async fn save_definition(&self, definition: &WorkflowDefinition) -> anyhow::Result<()> {
+        let mut log = self.log.lock().await;
+        append(
+            &mut log,
+            &LogRecord::SaveDefinition {
+                definition: definition.clone(),
+            },
+        )?;
+
+        self.definitions.write().await.insert(
+            (definition.name.clone(), definition.version),
+            definition.clone(),
+        );
+        Ok(())
+    }
+
+    async fn list_definitions(&self) -> anyhow::Result<Vec<WorkflowDefinition>> {
+        Ok(self.definitions.read().await.values().cloned().collect())
+    }
+
+    async fn save_execution(&self, execution: &WorkflowExecution) -> anyhow::Result<()> {
+        let mut log = self.log.lock().await;
+        append(
+            &mut log,
+            &LogRecord::SaveExecution {
+                execution: execution.clone(),
+            },
+        )?;
+
+        self.executions
+            .write()
+            .await
+            .insert(execution.workflow_id.clone(), execution.clone());
+        Ok(())
+    }
+
+    async fn load_executions(&self) -> anyhow::Result<Vec<WorkflowExecution>> {
+        Ok(self.executions.read().await.values().cloned().collect())
+    }
+
+    async fn delete_workflow(&self, id: &str) -> anyhow::Result<bool> {
+        let mut log = self.log.lock().await;
+        let mut workflows = self.workflows.write().await;
+        let mut step_results = self.step_results.write().await;
+        if !workflows.contains_key(id) && !step_results.contains_key(id) {
+            return Ok(false);
+        }
+
+        append(
+            &mut log,
+            &LogRecord::DeleteWorkflows {
+                ids: vec![id.to_string()],
+            },
+        )?;
+        step_results.remove(id);
+        self.signals.write().await.remove(id);
+        self.timers.write().await.remove(id);
+        self.step_deadlines.write().await.remove(id);
+        self.executions.write().await.remove(id);
+        self.action_logs
+            .write()
+            .await
+            .retain(|log| log.workflow_id != id);
+        Ok(workflows.remove(id).is_some())
+    }
+
+    async fn purge_workflows(&self, filter: &PurgeFilter) -> anyhow::Result<Vec<String>> {
+        let mut log = self.log.lock().await;
+        let mut workflows = self.workflows.write().await;
+        let mut step_results = self.step_results.write().await;
+        let ids: Vec<String> = workflows
+            .values()
+            .filter(|w| filter.matches(w))
+            .map(|w| w.id.clone())
+            .collect();
+        if ids.is_empty() {
+            return Ok(ids);
+        }
+
+        append(&mut log, &LogRecord::DeleteWorkflows { ids: ids.clone() })?;
+        let mut signals = self.signals.write().await;
+        let mut timers = self.timers.write().await;
+        let mut step_deadlines = self.step_deadlines.write().await;
+        let mut executions = self.executions.write().await;
+        for id in &ids {
+            workflows.remove(id);
+            step_results.remove(id);
+            signals.remove(id);
+            timers.remove(id);
+            step_deadlines.remove(id);
+            executions.remove(id);
+        }
+        self.action_logs
+            .write()
+            .await
+            .retain(|log| !ids.contains(&log.workflow_id));
+        Ok(ids)
+    }
+
+    async fn flush(&self) -> anyhow::Result<()> {
+        self.sync().await
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "state-action-log"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracker::{StepExecutionStatus, WorkflowTracker};
+    use std::sync::Arc;
+
+    fn log_path(dir: &tempfile::TempDir) -> PathBuf {
+        dir.path().join("data").join("actions.log")
+    }
+
+    async fn sorted_workflows(store: &L2StateActionStore) -> Vec<Workflow> {
+        let mut workflows = store.list_workflows(None).await.unwrap();
+        workflows.sort_by(|a, b| a.id.cmp(&b.id));
+        workflows
+    }
+
+    #[tokio::test]
+    async fn test_reopen_restores_executions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = log_path(&dir);
+
+        let store = Arc::new(L2StateActionStore::open(&path).unwrap());
+        let tracker = WorkflowTracker::with_store(store.clone()).await.unwrap();
+        for id in ["wf-1", "wf-2"] {
+            let workflow = Workflow::new(id.to_string(), "order".to_string(), vec![]);
+            store.save_workflow(&workflow).await.unwrap();
+            tracker
+                .start_workflow(id.to_string(), "order".to_string())
+                .await;
+            tracker
+                .step_started(id, "charge", vec![], vec![])
+                .await
+                .unwrap();
+        }
+        tracker
+            .step_failed("wf-1", "charge", "declined".to_string())
+            .await;
+        store.delete_workflow("wf-2").await.unwrap();
+        drop((tracker, store));
+
+        let reopened = L2StateActionStore::open(&path).unwrap();
+        let executions = reopened.load_executions().await.unwrap();
+        assert_eq!(executions.len(), 1);
+        let step = &executions[0].step_executions["charge"];
+        assert_eq!(step.attempt, 2);
+        assert!(matches!(
+            step.status,
+            StepExecutionStatus::Failed { ref error } if error == "declined"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_reopen_restores_identical_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = log_path(&dir);
+
+        let store = L2StateActionStore::open(&path).unwrap();
+        for id in ["wf-1", "wf-2", "wf-3"] {
+            let workflow = Workflow::new(id.to_string(), "order".to_string(), b"in".to_vec());
+            store.save_workflow(&workflow).await.unwrap();
+        }
+        store
+            .update_workflow_state("wf-1", WorkflowState::Running { current_step: None })
+            .await
+            .unwrap();
+        store
+            .update_workflow_state(
+                "wf-1",
+                WorkflowState::Running {
+                    current_step: Some("charge".to_string()),
+                },
+            )
+            .await
+            .unwrap();
+        for expected in 1..=2 {
+            let attempt = store.record_step_attempt("wf-1", "charge").await.unwrap();
+            assert_eq!(attempt, expected);
+        }
+        store
+            .save_step_result("wf-1", "charge", b"ok".to_vec())
+            .await
+            .unwrap();
+        let signal = Signal::new("wf-1", "approved", b"yes".to_vec());
+        store.append_signal(&signal).await.unwrap();
+        store
+            .append_signal(&Signal::new("wf-3", "approved", vec![]))
+            .await
+            .unwrap();
+        let timer = Timer {
+            workflow_id: "wf-1".to_string(),
+            step_name: "wait".to_string(),
+            fire_at: Utc::now(),
+        };
+        store.save_timer(&timer).await.unwrap();
+        let fired = Timer {
+            step_name: "fired".to_string(),
+            ..timer.clone()
+        };
+        store.save_timer(&fired).await.unwrap();
+        assert!(store.delete_timer("wf-1", "fired").await.unwrap());
+        store.delete_workflow("wf-3").await.unwrap();
+        let before = sorted_workflows(&store).await;
+        drop(store);
+
+        let reopened = L2StateActionStore::open(&path).unwrap();
+        assert_eq!(sorted_workflows(&reopened).await, before);
+        assert_eq!(before.len(), 2);
+        let workflow = reopened.get_workflow("wf-1").await.unwrap().unwrap();
+        assert_eq!(workflow.attempts("charge"), 2);
+        assert_eq!(
+            reopened.get_step_result("wf-1", "charge").await.unwrap(),
+            Some(b"ok".to_vec())
+        );
+        assert_eq!(reopened.get_signals("wf-1").await.unwrap(), vec![signal]);
+        assert!(reopened.get_signals("wf-3").await.unwrap().is_empty());
+        assert_eq!(reopened.list_timers().await.unwrap(), vec![timer]);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_final_record_is_discarded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = log_path(&dir);
+
+        let store = L2StateActionStore::open(&path).unwrap();
+        for id in ["wf-1", "wf-2"] {
+            let workflow = Workflow::new(id.to_string(), "order".to_string(), vec![]);
+            store.save_workflow(&workflow).await.unwrap();
+        }
+        drop(store);
+
+        // 模拟写第二条记录时崩溃
+        let len = std::fs::metadata(&path).unwrap().len();
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(len - 3).unwrap();
+        drop(file);
+
+        let reopened = L2StateActionStore::open(&path).unwrap();
+        let ids: Vec<String> = sorted_workflows(&reopened)
+            .await
+            .into_iter()
+            .map(|w| w.id)
+            .collect();
+        assert_eq!(ids, vec!["wf-1"]);
+
+        // 丢弃残缺记录后可以继续追加
+        let workflow = Workflow::new("wf-3".to_string(), "order".to_string(), vec![]);
+        reopened.save_workflow(&workflow).await.unwrap();
+        drop(reopened);
+
+        let reopened = L2StateActionStore::open(&path).unwrap();
+        let ids: Vec<String> = sorted_workflows(&reopened)
+            .await
+            .into_iter()
+            .map(|w| w.id)
+            .collect();
+        assert_eq!(ids, vec!["wf-1", "wf-3"]);
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_middle_record_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = log_path(&dir);
+
+        let store = L2StateActionStore::open(&path).unwrap();
+        for id in ["wf-1", "wf-2"] {
+            let workflow = Workflow::new(id.to_string(), "order".to_string(), vec![]);
+            store.save_workflow(&workflow).await.unwrap();
+        }
+        drop(store);
+
+        let mut content = std::fs::read(&path).unwrap();
+        content[4] = b'#';
+        std::fs::write(&path, content).unwrap();
+
+        assert!(L2StateActionStore::open(&path).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_purge_and_batched_sync() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = log_path(&dir);
+
+        let store = L2StateActionStore::open_with_sync(&path, SyncPolicy::EveryN(10)).unwrap();
+        let mut done = Workflow::new("done".to_string(), "order".to_string(), vec![]);
+        done.state = WorkflowState::Completed {
+            result: vec![].into(),
+        };
+        store.save_workflow(&done).await.unwrap();
+        let running = Workflow::new("running".to_string(), "order".to_string(), vec![]);
+        store.save_workflow(&running).await.unwrap();
+
+        let removed = store
+            .purge_workflows(&PurgeFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(removed, vec!["done".to_string()]);
+        store.sync().await.unwrap();
+        drop(store);
+
+        let reopened = L2StateActionStore::open(&path).unwrap();
+        let ids: Vec<String> = sorted_workflows(&reopened)
+            .await
+            .into_iter()
+            .map(|w| w.id)
+            .collect();
+        assert_eq!(ids, vec!["running"]);
+    }
 }
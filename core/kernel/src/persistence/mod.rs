@@ -1,12 +1,238 @@
+use crate::definition::WorkflowDefinition;
+use crate::schedule::Schedule;
+use crate::signal::Signal;
 use crate::state_machine::Workflow;
 use crate::state_machine::WorkflowState;
+use crate::step_cache::CachedResult;
+use crate::step_timeout::StepDeadline;
+use crate::timer::Timer;
+use crate::tracker::WorkflowExecution;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// 批量清理的筛选条件，只会匹配终态 workflow
+#[derive(Debug, Clone, Default)]
+pub struct PurgeFilter {
+    /// 限定终态种类，为空表示全部终态
+    pub states: Vec<TerminalState>,
+    /// 只清理在此时间之前结束（最后更新）的 workflow
+    pub completed_before: Option<DateTime<Utc>>,
+    pub workflow_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminalState {
+    Completed,
+    Failed,
+    Cancelled,
+    Terminated,
+}
+
+impl TerminalState {
+    pub fn of(state: &WorkflowState) -> Option<Self> {
+        match state {
+            WorkflowState::Completed { .. } => Some(TerminalState::Completed),
+            WorkflowState::Failed { .. } => Some(TerminalState::Failed),
+            WorkflowState::Cancelled => Some(TerminalState::Cancelled),
+            WorkflowState::Terminated { .. } => Some(TerminalState::Terminated),
+            WorkflowState::Pending | WorkflowState::Running { .. } => None,
+        }
+    }
+}
+
+impl PurgeFilter {
+    /// 运行中的 workflow 永远不会匹配
+    pub fn matches(&self, workflow: &Workflow) -> bool {
+        let Some(state) = TerminalState::of(&workflow.state) else {
+            return false;
+        };
+        if !self.states.is_empty() && !self.states.contains(&state) {
+            return false;
+        }
+        if let Some(before) = self.completed_before {
+            if workflow.updated_at >= before {
+                return false;
+            }
+        }
+        if let Some(wf_type) = &self.workflow_type {
+            if &workflow.workflow_type != wf_type {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// workflow 状态种类，不携带状态数据
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StateKind {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+    Terminated,
+}
+
+impl StateKind {
+    pub fn of(state: &WorkflowState) -> Self {
+        match state {
+            WorkflowState::Pending => StateKind::Pending,
+            WorkflowState::Running { .. } => StateKind::Running,
+            WorkflowState::Completed { .. } => StateKind::Completed,
+            WorkflowState::Failed { .. } => StateKind::Failed,
+            WorkflowState::Cancelled => StateKind::Cancelled,
+            WorkflowState::Terminated { .. } => StateKind::Terminated,
+        }
+    }
+}
+
+impl FromStr for StateKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pending" => Ok(StateKind::Pending),
+            "running" => Ok(StateKind::Running),
+            "completed" => Ok(StateKind::Completed),
+            "failed" => Ok(StateKind::Failed),
+            "cancelled" => Ok(StateKind::Cancelled),
+            "terminated" => Ok(StateKind::Terminated),
+            _ => Err(format!(
+                "Unknown workflow state '{}': expected pending, running, completed, failed, cancelled or terminated",
+                s
+            )),
+        }
+    }
+}
+
+/// 列表排序方式，`started_at` 相同时按 id 排序以保证结果稳定
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OrderBy {
+    #[default]
+    StartedAtAsc,
+    StartedAtDesc,
+}
+
+impl FromStr for OrderBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "started_at" | "started_at_asc" | "asc" => Ok(OrderBy::StartedAtAsc),
+            "started_at_desc" | "desc" => Ok(OrderBy::StartedAtDesc),
+            _ => Err(format!(
+                "Unknown order '{}': expected started_at_asc or started_at_desc",
+                s
+            )),
+        }
+    }
+}
+
+/// 分页列表参数
+#[derive(Debug, Clone, Default)]
+pub struct ListOptions {
+    pub offset: usize,
+    /// `None` 表示不限制条数
+    pub limit: Option<usize>,
+    pub order_by: OrderBy,
+    pub state_filter: Option<StateKind>,
+    pub workflow_type: Option<String>,
+    /// 只列出该 workflow 的子 workflow
+    pub parent_workflow_id: Option<String>,
+    /// 只列出在此时间及之后启动的 workflow
+    pub started_after: Option<DateTime<Utc>>,
+    /// 只列出在此时间之前启动的 workflow
+    pub started_before: Option<DateTime<Utc>>,
+    /// 只列出 memo 包含全部这些键值的 workflow
+    pub memo: HashMap<String, String>,
+}
+
+impl ListOptions {
+    pub fn matches(&self, workflow: &Workflow) -> bool {
+        if let Some(kind) = self.state_filter {
+            if StateKind::of(&workflow.state) != kind {
+                return false;
+            }
+        }
+        if let Some(wf_type) = &self.workflow_type {
+            if &workflow.workflow_type != wf_type {
+                return false;
+            }
+        }
+        if let Some(parent) = &self.parent_workflow_id {
+            if workflow.parent_workflow_id.as_ref() != Some(parent) {
+                return false;
+            }
+        }
+        if self
+            .started_after
+            .is_some_and(|after| workflow.started_at < after)
+        {
+            return false;
+        }
+        if self
+            .started_before
+            .is_some_and(|before| workflow.started_at >= before)
+        {
+            return false;
+        }
+        self.memo
+            .iter()
+            .all(|(key, value)| workflow.memo.get(key) == Some(value))
+    }
+
+    /// 对内存中的 workflow 筛选、排序并分页，只克隆返回的那一页
+    pub fn select<'a>(&self, workflows: impl IntoIterator<Item = &'a Workflow>) -> Vec<Workflow> {
+        let mut matched: Vec<&Workflow> =
+            workflows.into_iter().filter(|w| self.matches(w)).collect();
+        matched.sort_by(|a, b| (a.started_at, &a.id).cmp(&(b.started_at, &b.id)));
+        if self.order_by == OrderBy::StartedAtDesc {
+            matched.reverse();
+        }
+
+        matched
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+}
 
 #[async_trait::async_trait]
 pub trait Persistence: Send + Sync {
     async fn save_workflow(&self, workflow: &Workflow) -> anyhow::Result<()>;
+    /// 同 id 的 workflow 不存在时保存，返回是否保存；检查与写入是原子的，
+    /// 并发保存同一个 id 时只有一个成功
+    async fn save_workflow_if_absent(&self, workflow: &Workflow) -> anyhow::Result<bool>;
+
+    /// 批量保存 workflow，每个 workflow 的语义同 `save_workflow_if_absent`，返回每个是否保存
+    ///
+    /// 默认实现逐个保存，中途失败时之前的 workflow 已经保存。支持事务的存储后端应覆盖它，
+    /// 并让 [`atomic_batches`](Self::atomic_batches) 返回 true。
+    async fn save_workflows(&self, workflows: &[Workflow]) -> anyhow::Result<Vec<bool>> {
+        let mut saved = Vec::with_capacity(workflows.len());
+        for workflow in workflows {
+            saved.push(self.save_workflow_if_absent(workflow).await?);
+        }
+        Ok(saved)
+    }
+
+    /// `save_workflows` 是否在一个事务中写入：失败时一个都不保存
+    fn atomic_batches(&self) -> bool {
+        false
+    }
+
     async fn get_workflow(&self, id: &str) -> anyhow::Result<Option<Workflow>>;
     async fn list_workflows(&self, workflow_type: Option<&str>) -> anyhow::Result<Vec<Workflow>>;
     async fn update_workflow_state(&self, id: &str, state: WorkflowState) -> anyhow::Result<()>;
+
+    /// step 开始一次新的尝试，返回这是第几次尝试；workflow 不存在时返回 0
+    async fn record_step_attempt(&self, workflow_id: &str, step_name: &str) -> anyhow::Result<u32>;
+
     async fn save_step_result(
         &self,
         workflow_id: &str,
@@ -18,12 +244,312 @@ pub trait Persistence: Send + Sync {
         workflow_id: &str,
         step_name: &str,
     ) -> anyhow::Result<Option<Vec<u8>>>;
+
+    /// 追加 workflow 收到的 signal
+    async fn append_signal(&self, signal: &Signal) -> anyhow::Result<()>;
+
+    /// workflow 收到的全部 signal，按到达顺序
+    async fn get_signals(&self, workflow_id: &str) -> anyhow::Result<Vec<Signal>>;
+
+    /// 保存定时器，同一 step 的定时器会被替换
+    async fn save_timer(&self, timer: &Timer) -> anyhow::Result<()>;
+
+    /// 全部尚未触发的定时器
+    async fn list_timers(&self) -> anyhow::Result<Vec<Timer>>;
+
+    /// 删除定时器，返回它是否存在
+    async fn delete_timer(&self, workflow_id: &str, step_name: &str) -> anyhow::Result<bool>;
+
+    /// 保存已分发 step 的执行截止时间，同一 step 的截止时间会被替换
+    async fn save_step_deadline(&self, deadline: &StepDeadline) -> anyhow::Result<()>;
+
+    /// 全部尚未结束的 step 截止时间
+    async fn list_step_deadlines(&self) -> anyhow::Result<Vec<StepDeadline>>;
+
+    /// 删除 step 截止时间，返回它是否存在
+    async fn delete_step_deadline(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+    ) -> anyhow::Result<bool>;
+
+    /// 按缓存键读取未过期的 step 结果缓存
+    async fn get_cached_result(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>>;
+
+    /// 保存 step 结果缓存，同 key 的条目会被替换；超过 `max_entries` 条时先淘汰过期的，
+    /// 再淘汰最早缓存的，`max_entries` 为 0 时不保存
+    async fn put_cached_result(
+        &self,
+        entry: &CachedResult,
+        max_entries: usize,
+    ) -> anyhow::Result<()>;
+
+    /// 保存 cron 调度，同 id 的调度会被替换
+    async fn save_schedule(&self, schedule: &Schedule) -> anyhow::Result<()>;
+
+    /// 更新已存在的 cron 调度，返回它是否存在；已被删除的调度不会被重新写入
+    async fn update_schedule(&self, schedule: &Schedule) -> anyhow::Result<bool>;
+
+    /// 全部 cron 调度
+    async fn list_schedules(&self) -> anyhow::Result<Vec<Schedule>>;
+
+    /// 删除 cron 调度，返回它是否存在
+    async fn delete_schedule(&self, id: &str) -> anyhow::Result<bool>;
+
+    /// 保存 workflow 定义，同名同版本的定义会被替换
+    async fn save_definition(&self, definition: &WorkflowDefinition) -> anyhow::Result<()>;
+
+    /// 全部已保存的 workflow 定义（包括每个类型的所有版本）
+    async fn list_definitions(&self) -> anyhow::Result<Vec<WorkflowDefinition>>;
+
+    /// 保存 workflow 的执行追踪记录，同一 workflow 的记录会被替换
+    ///
+    /// 默认不保存，追踪器只保留在内存中。
+    async fn save_execution(&self, _execution: &WorkflowExecution) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// 全部已保存的执行追踪记录，追踪器启动时用它恢复历史
+    async fn load_executions(&self) -> anyhow::Result<Vec<WorkflowExecution>> {
+        Ok(Vec::new())
+    }
+
+    /// 按 `started_at` 排序的分页列表
+    ///
+    /// 默认实现基于 `list_workflows` 在内存中分页，存储后端可以覆盖以避免加载全部数据。
+    async fn list_workflows_paged(&self, options: &ListOptions) -> anyhow::Result<Vec<Workflow>> {
+        let workflows = self
+            .list_workflows(options.workflow_type.as_deref())
+            .await?;
+        Ok(options.select(&workflows))
+    }
+
+    /// 匹配筛选条件的 workflow 总数，忽略 `offset` 和 `limit`
+    async fn count_workflows(&self, options: &ListOptions) -> anyhow::Result<usize> {
+        let workflows = self
+            .list_workflows(options.workflow_type.as_deref())
+            .await?;
+        Ok(workflows.iter().filter(|w| options.matches(w)).count())
+    }
+
+    /// 删除 workflow 及其 step 结果、signal、定时器、step 截止时间和执行追踪记录，返回该 workflow 是否存在
+    async fn delete_workflow(&self, id: &str) -> anyhow::Result<bool>;
+
+    /// 删除所有匹配筛选条件的 workflow，返回被删除的 id
+    async fn purge_workflows(&self, filter: &PurgeFilter) -> anyhow::Result<Vec<String>>;
+
+    /// 删除在 `cutoff` 之前结束的所有终态 workflow，返回被删除的 id
+    async fn purge_completed_before(&self, cutoff: DateTime<Utc>) -> anyhow::Result<Vec<String>> {
+        self.purge_workflows(&PurgeFilter {
+            completed_before: Some(cutoff),
+            ..Default::default()
+        })
+        .await
+    }
+
+    /// 把缓冲中的数据写入磁盘（停机时调用），默认无操作
+    async fn flush(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// 存储后端名称，用于诊断输出
+    fn backend_name(&self) -> &'static str {
+        "unknown"
+    }
+}
+
+/// 共享存储：`Arc<T>` 直接委托给内部实现
+#[async_trait::async_trait]
+impl<T: Persistence + ?Sized> Persistence for Arc<T> {
+    async fn save_workflow(&self, workflow: &Workflow) -> anyhow::Result<()> {
+        self.as_ref().save_workflow(workflow).await
+    }
+
+    async fn save_workflow_if_absent(&self, workflow: &Workflow) -> anyhow::Result<bool> {
+        self.as_ref().save_workflow_if_absent(workflow).await
+    }
+
+    async fn save_workflows(&self, workflows: &[Workflow]) -> anyhow::Result<Vec<bool>> {
+        self.as_ref().save_workflows(workflows).await
+    }
+
+    fn atomic_batches(&self) -> bool {
+        self.as_ref().atomic_batches()
+    }
+
+    async fn get_workflow(&self, id: &str) -> anyhow::Result<Option<Workflow>> {
+        self.as_ref().get_workflow(id).await
+    }
+
+    async fn list_workflows(&self, workflow_type: Option<&str>) -> anyhow::Result<Vec<Workflow>> {
+        self.as_ref().list_workflows(workflow_type).await
+    }
+
+    async fn update_workflow_state(&self, id: &str, state: WorkflowState) -> anyhow::Result<()> {
+        self.as_ref().update_workflow_state(id, state).await
+    }
+
+    async fn record_step_attempt(&self, workflow_id: &str, step_name: &str) -> anyhow::Result<u32> {
+        self.as_ref()
+            .record_step_attempt(workflow_id, step_name)
+            .await
+    }
+
+    async fn save_step_result(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+        result: Vec<u8>,
+    ) -> anyhow::Result<()> {
+        self.as_ref()
+            .save_step_result(workflow_id, step_name, result)
+            .await
+    }
+
+    async fn get_step_result(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        self.as_ref().get_step_result(workflow_id, step_name).await
+    }
+
+    async fn append_signal(&self, signal: &Signal) -> anyhow::Result<()> {
+        self.as_ref().append_signal(signal).await
+    }
+
+    async fn get_signals(&self, workflow_id: &str) -> anyhow::Result<Vec<Signal>> {
+        self.as_ref().get_signals(workflow_id).await
+    }
+
+    async fn save_timer(&self, timer: &Timer) -> anyhow::Result<()> {
+        self.as_ref().save_timer(timer).await
+    }
+
+    async fn list_timers(&self) -> anyhow::Result<Vec<Timer>> {
+        self.as_ref().list_timers().await
+    }
+
+    async fn delete_timer(&self, workflow_id: &str, step_name: &str) -> anyhow::Result<bool> {
+        self.as_ref().delete_timer(workflow_id, step_name).await
+    }
+
+    async fn save_step_deadline(&self, deadline: &StepDeadline) -> anyhow::Result<()> {
+        self.as_ref().save_step_deadline(deadline).await
+    }
+
+    async fn list_step_deadlines(&self) -> anyhow::Result<Vec<StepDeadline>> {
+        self.as_ref().list_step_deadlines().await
+    }
+
+    async fn delete_step_deadline(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+    ) -> anyhow::Result<bool> {
+        self.as_ref()
+            .delete_step_deadline(workflow_id, step_name)
+            .await
+    }
+
+    async fn get_cached_result(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        self.as_ref().get_cached_result(key).await
+    }
+
+    async fn put_cached_result(
+        &self,
+        entry: &CachedResult,
+        max_entries: usize,
+    ) -> anyhow::Result<()> {
+        self.as_ref().put_cached_result(entry, max_entries).await
+    }
+
+    async fn save_schedule(&self, schedule: &Schedule) -> anyhow::Result<()> {
+        self.as_ref().save_schedule(schedule).await
+    }
+
+    async fn update_schedule(&self, schedule: &Schedule) -> anyhow::Result<bool> {
+        self.as_ref().update_schedule(schedule).await
+    }
+
+    async fn list_schedules(&self) -> anyhow::Result<Vec<Schedule>> {
+        self.as_ref().list_schedules().await
+    }
+
+    async fn delete_schedule(&self, id: &str) -> anyhow::Result<bool> {
+        self.as_ref().delete_schedule(id).await
+    }
+
+    async fn save_definition(&self, definition: &WorkflowDefinition) -> anyhow::Result<()> {
+        self.as_ref().save_definition(definition).await
+    }
+
+    async fn list_definitions(&self) -> anyhow::Result<Vec<WorkflowDefinition>> {
+        self.as_ref().list_definitions().await
+    }
+
+    async fn save_execution(&self, execution: &WorkflowExecution) -> anyhow::Result<()> {
+        self.as_ref().save_execution(execution).await
+    }
+
+    async fn load_executions(&self) -> anyhow::Result<Vec<WorkflowExecution>> {
+        self.as_ref().load_executions().await
+    }
+
+    async fn list_workflows_paged(&self, options: &ListOptions) -> anyhow::Result<Vec<Workflow>> {
+        self.as_ref().list_workflows_paged(options).await
+    }
+
+    async fn count_workflows(&self, options: &ListOptions) -> anyhow::Result<usize> {
+        self.as_ref().count_workflows(options).await
+    }
+
+    async fn delete_workflow(&self, id: &str) -> anyhow::Result<bool> {
+        self.as_ref().delete_workflow(id).await
+    }
+
+    async fn purge_workflows(&self, filter: &PurgeFilter) -> anyhow::Result<Vec<String>> {
+        self.as_ref().purge_workflows(filter).await
+    }
+
+    async fn purge_completed_before(&self, cutoff: DateTime<Utc>) -> anyhow::Result<Vec<String>> {
+        self.as_ref().purge_completed_before(cutoff).await
+    }
+
+    async fn flush(&self) -> anyhow::Result<()> {
+        self.as_ref().flush().await
+    }
+
+    fn backend_name(&self) -> &'static str {
+        self.as_ref().backend_name()
+    }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PersistenceLevel {
     L0Memory,
     L1Snapshot,
     L2StateActionLog,
+    /// SQLite 数据库（需要 `sqlite` feature）
+    Sqlite,
+}
+
+impl FromStr for PersistenceLevel {
+    type Err = String;
+
+    /// 解析配置中的持久化模式（memory | snapshot | state-action-log | sqlite）
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "memory" => Ok(PersistenceLevel::L0Memory),
+            "snapshot" => Ok(PersistenceLevel::L1Snapshot),
+            "state-action-log" => Ok(PersistenceLevel::L2StateActionLog),
+            "sqlite" => Ok(PersistenceLevel::Sqlite),
+            _ => Err(format!(
+                "Unknown persistence mode '{}': expected memory, snapshot, state-action-log or sqlite",
+                s
+            )),
+        }
+    }
 }
 
 pub struct PersistenceConfig {
@@ -32,6 +558,37 @@ pub struct PersistenceConfig {
     pub path: Option<String>,
 }
 
+pub mod blob;
+#[cfg(test)]
+pub(crate) mod faulty;
 pub mod l0_memory;
 pub mod l1_snapshot;
 pub mod l2_state_action_log;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_persistence_level() {
+        assert_eq!(
+            "memory".parse::<PersistenceLevel>(),
+            Ok(PersistenceLevel::L0Memory)
+        );
+        assert_eq!(
+            "Snapshot".parse::<PersistenceLevel>(),
+            Ok(PersistenceLevel::L1Snapshot)
+        );
+        assert_eq!(
+            "state-action-log".parse::<PersistenceLevel>(),
+            Ok(PersistenceLevel::L2StateActionLog)
+        );
+        assert_eq!(
+            "sqlite".parse::<PersistenceLevel>(),
+            Ok(PersistenceLevel::Sqlite)
+        );
+        assert!("postgres".parse::<PersistenceLevel>().is_err());
+    }
+}
@@ -0,0 +1,1200 @@
+//! SQLite 持久化
+//!
+//! workflow、step 结果、signal 和定时器分别存放在 `workflows`、`step_results`、`signals` 和 `timers` 四张表中，
+//! 已分发 step 的执行截止时间存放在 `step_deadlines` 表中，cron 调度、workflow 定义和执行追踪记录以 JSON 文本分别存放在
+//! `schedules`、`workflow_definitions` 和 `executions` 表中。
+//! 状态、step 输出、尝试次数和状态变化记录以 JSON 文本保存，时间戳为定长（纳秒精度）的 RFC 3339 文本，可直接按字符串排序。
+//! workflow 的 memo 同样以 JSON 文本保存，插入时由触发器展开到按键值索引的 `workflow_memo` 表，供列表筛选使用。
+//! step 结果缓存按缓存键存放在 `step_cache` 表中，与 workflow 无关，删除 workflow 时保留。
+
+use super::{ListOptions, OrderBy, Persistence, PurgeFilter, StateKind};
+use crate::definition::WorkflowDefinition;
+use crate::schedule::Schedule;
+use crate::signal::Signal;
+use crate::state_machine::{Workflow, WorkflowState, WORKFLOW_SCHEMA_VERSION};
+use crate::step_cache::CachedResult;
+use crate::step_timeout::StepDeadline;
+use crate::timer::Timer;
+use crate::tracker::WorkflowExecution;
+use anyhow::Context;
+use chrono::{DateTime, SecondsFormat, Utc};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions};
+use sqlx::{QueryBuilder, Row, Sqlite};
+use std::path::Path;
+use std::str::FromStr;
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS workflows (
+    id TEXT PRIMARY KEY,
+    workflow_type TEXT NOT NULL,
+    state TEXT NOT NULL,
+    input BLOB NOT NULL,
+    steps_completed TEXT NOT NULL,
+    started_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    parent_workflow_id TEXT,
+    priority TEXT NOT NULL DEFAULT 'normal',
+    skip_schema_validation INTEGER NOT NULL DEFAULT 0,
+    definition_version INTEGER,
+    history TEXT NOT NULL DEFAULT '[]',
+    attempts TEXT NOT NULL DEFAULT '{}',
+    memo TEXT NOT NULL DEFAULT '{}'
+);
+CREATE INDEX IF NOT EXISTS idx_workflows_type ON workflows (workflow_type);
+CREATE TABLE IF NOT EXISTS workflow_memo (
+    workflow_id TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (workflow_id, key)
+);
+CREATE INDEX IF NOT EXISTS idx_workflow_memo_key_value ON workflow_memo (key, value);
+CREATE TABLE IF NOT EXISTS step_results (
+    workflow_id TEXT NOT NULL,
+    step_name TEXT NOT NULL,
+    result BLOB NOT NULL,
+    PRIMARY KEY (workflow_id, step_name)
+);
+CREATE TABLE IF NOT EXISTS signals (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    workflow_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    payload BLOB NOT NULL,
+    received_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_signals_workflow ON signals (workflow_id);
+CREATE TABLE IF NOT EXISTS timers (
+    workflow_id TEXT NOT NULL,
+    step_name TEXT NOT NULL,
+    fire_at TEXT NOT NULL,
+    PRIMARY KEY (workflow_id, step_name)
+);
+CREATE TABLE IF NOT EXISTS step_deadlines (
+    workflow_id TEXT NOT NULL,
+    step_name TEXT NOT NULL,
+    worker_id TEXT NOT NULL,
+    timeout_ms INTEGER NOT NULL,
+    deadline TEXT NOT NULL,
+    PRIMARY KEY (workflow_id, step_name)
+);
+CREATE TABLE IF NOT EXISTS schedules (
+    id TEXT PRIMARY KEY,
+    created_at TEXT NOT NULL,
+    schedule TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS workflow_definitions (
+    name TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    definition TEXT NOT NULL,
+    PRIMARY KEY (name, version)
+);
+CREATE TABLE IF NOT EXISTS executions (
+    workflow_id TEXT PRIMARY KEY,
+    execution TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS step_cache (
+    key TEXT PRIMARY KEY,
+    result BLOB NOT NULL,
+    cached_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
+"#;
+
+pub struct SqliteStore {
+    pool: SqlitePool,
+    /// 更新状态和尝试次数需要先读出已有记录再写回，同一进程内串行执行
+    state_updates: tokio::sync::Mutex<()>,
+}
+
+impl SqliteStore {
+    /// 打开（不存在时创建）数据库文件
+    pub async fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal);
+        let pool = SqlitePoolOptions::new()
+            .connect_with(options)
+            .await
+            .with_context(|| format!("Failed to open SQLite database {}", path.display()))?;
+        Self::with_pool(pool).await
+    }
+
+    /// 内存数据库，进程退出后数据丢失
+    pub async fn in_memory() -> anyhow::Result<Self> {
+        // 每个连接都有独立的内存数据库，因此只保留一个连接
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(SqliteConnectOptions::from_str("sqlite::memory:")?)
+            .await?;
+        Self::with_pool(pool).await
+    }
+
+    async fn with_pool(pool: SqlitePool) -> anyhow::Result<Self> {
+        sqlx::raw_sql(SCHEMA)
+            .execute(&pool)
+            .await
+            .context("Failed to create SQLite schema")?;
+        Self::migrate(&pool).await?;
+        Ok(SqliteStore {
+            pool,
+            state_updates: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    /// 为旧版本创建的数据库补充新增的列
+    async fn migrate(pool: &SqlitePool) -> anyhow::Result<()> {
+        let columns: Vec<String> = sqlx::query("SELECT name FROM pragma_table_info('workflows')")
+            .fetch_all(pool)
+            .await?
+            .iter()
+            .map(|row| row.try_get("name"))
+            .collect::<Result<_, _>>()?;
+        if !columns.iter().any(|c| c == "parent_workflow_id") {
+            sqlx::query("ALTER TABLE workflows ADD COLUMN parent_workflow_id TEXT")
+                .execute(pool)
+                .await?;
+        }
+        if !columns.iter().any(|c| c == "priority") {
+            sqlx::query("ALTER TABLE workflows ADD COLUMN priority TEXT NOT NULL DEFAULT 'normal'")
+                .execute(pool)
+                .await?;
+        }
+        if !columns.iter().any(|c| c == "skip_schema_validation") {
+            sqlx::query(
+                "ALTER TABLE workflows ADD COLUMN skip_schema_validation INTEGER NOT NULL DEFAULT 0",
+            )
+            .execute(pool)
+            .await?;
+        }
+        if !columns.iter().any(|c| c == "definition_version") {
+            sqlx::query("ALTER TABLE workflows ADD COLUMN definition_version INTEGER")
+                .execute(pool)
+                .await?;
+        }
+        if !columns.iter().any(|c| c == "history") {
+            sqlx::query("ALTER TABLE workflows ADD COLUMN history TEXT NOT NULL DEFAULT '[]'")
+                .execute(pool)
+                .await?;
+        }
+        if !columns.iter().any(|c| c == "attempts") {
+            sqlx::query("ALTER TABLE workflows ADD COLUMN attempts TEXT NOT NULL DEFAULT '{}'")
+                .execute(pool)
+                .await?;
+        }
+        if !columns.iter().any(|c| c == "memo") {
+            sqlx::query("ALTER TABLE workflows ADD COLUMN memo TEXT NOT NULL DEFAULT '{}'")
+                .execute(pool)
+                .await?;
+        }
+        // `INSERT OR REPLACE` 替换已有行时也会触发，`INSERT OR IGNORE` 跳过的行不会
+        sqlx::query(
+            "CREATE TRIGGER IF NOT EXISTS workflows_memo_insert AFTER INSERT ON workflows \
+             BEGIN \
+                 DELETE FROM workflow_memo WHERE workflow_id = NEW.id; \
+                 INSERT INTO workflow_memo (workflow_id, key, value) \
+                 SELECT NEW.id, key, value FROM json_each(NEW.memo); \
+             END",
+        )
+        .execute(pool)
+        .await
+        .context("Failed to migrate SQLite schema")?;
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_workflows_parent ON workflows (parent_workflow_id)",
+        )
+        .execute(pool)
+        .await
+        .context("Failed to migrate SQLite schema")?;
+        Ok(())
+    }
+}
+
+fn to_timestamp(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Nanos, true)
+}
+
+fn from_row(row: &sqlx::sqlite::SqliteRow) -> anyhow::Result<Workflow> {
+    let state: String = row.try_get("state")?;
+    let steps_completed: String = row.try_get("steps_completed")?;
+    let started_at: String = row.try_get("started_at")?;
+    let updated_at: String = row.try_get("updated_at")?;
+    let priority: String = row.try_get("priority")?;
+    let history: String = row.try_get("history")?;
+    let attempts: String = row.try_get("attempts")?;
+    let memo: String = row.try_get("memo")?;
+
+    Ok(Workflow {
+        // 打开数据库时已迁移到当前表结构
+        schema_version: WORKFLOW_SCHEMA_VERSION,
+        id: row.try_get("id")?,
+        workflow_type: row.try_get("workflow_type")?,
+        state: serde_json::from_str(&state)?,
+        input: row.try_get("input")?,
+        completed_steps: serde_json::from_str(&steps_completed)?,
+        attempts: serde_json::from_str(&attempts)?,
+        started_at: DateTime::parse_from_rfc3339(&started_at)?.with_timezone(&Utc),
+        updated_at: DateTime::parse_from_rfc3339(&updated_at)?.with_timezone(&Utc),
+        parent_workflow_id: row.try_get("parent_workflow_id")?,
+        priority: priority.parse().map_err(anyhow::Error::msg)?,
+        skip_schema_validation: row.try_get("skip_schema_validation")?,
+        definition_version: row.try_get("definition_version")?,
+        history: serde_json::from_str(&history)?,
+        memo: serde_json::from_str(&memo)?,
+    })
+}
+
+/// `WorkflowState` 序列化后的变体名
+fn variant_name(kind: StateKind) -> &'static str {
+    match kind {
+        StateKind::Pending => "Pending",
+        StateKind::Running => "Running",
+        StateKind::Completed => "Completed",
+        StateKind::Failed => "Failed",
+        StateKind::Cancelled => "Cancelled",
+        StateKind::Terminated => "Terminated",
+    }
+}
+
+/// 追加 `ListOptions` 的筛选条件（不含排序和分页）
+fn push_list_filters(query: &mut QueryBuilder<Sqlite>, options: &ListOptions) {
+    if let Some(wf_type) = &options.workflow_type {
+        query
+            .push(" AND workflow_type = ")
+            .push_bind(wf_type.clone());
+    }
+    if let Some(parent) = &options.parent_workflow_id {
+        query
+            .push(" AND parent_workflow_id = ")
+            .push_bind(parent.clone());
+    }
+    // 时间以固定精度的 RFC 3339 UTC 字符串保存，字符串比较即时间比较
+    if let Some(after) = &options.started_after {
+        query
+            .push(" AND started_at >= ")
+            .push_bind(to_timestamp(after));
+    }
+    if let Some(before) = &options.started_before {
+        query
+            .push(" AND started_at < ")
+            .push_bind(to_timestamp(before));
+    }
+    if let Some(kind) = options.state_filter {
+        // 无数据的变体序列化为 "Pending"，带数据的变体序列化为 {"Running":{...}}
+        let name = variant_name(kind);
+        query
+            .push(" AND (state = ")
+            .push_bind(format!("\"{}\"", name))
+            .push(" OR state LIKE ")
+            .push_bind(format!("{{\"{}\":%", name))
+            .push(")");
+    }
+    for (key, value) in &options.memo {
+        query
+            .push(" AND id IN (SELECT workflow_id FROM workflow_memo WHERE key = ")
+            .push_bind(key.clone())
+            .push(" AND value = ")
+            .push_bind(value.clone())
+            .push(")");
+    }
+}
+
+/// 以 `verb`（`INSERT OR REPLACE` / `INSERT OR IGNORE`）写入 workflow，返回写入的行数
+async fn insert_workflow<'e, E>(executor: E, verb: &str, workflow: &Workflow) -> anyhow::Result<u64>
+where
+    E: sqlx::Executor<'e, Database = Sqlite>,
+{
+    let sql = format!(
+        "{} INTO workflows \
+             (id, workflow_type, state, input, steps_completed, started_at, updated_at, \
+              parent_workflow_id, priority, skip_schema_validation, definition_version, history, \
+              attempts, memo) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        verb
+    );
+    let result = sqlx::query(&sql)
+        .bind(&workflow.id)
+        .bind(&workflow.workflow_type)
+        .bind(serde_json::to_string(&workflow.state)?)
+        .bind(&workflow.input)
+        .bind(serde_json::to_string(&workflow.completed_steps)?)
+        .bind(to_timestamp(&workflow.started_at))
+        .bind(to_timestamp(&workflow.updated_at))
+        .bind(&workflow.parent_workflow_id)
+        .bind(workflow.priority.as_str())
+        .bind(workflow.skip_schema_validation)
+        .bind(workflow.definition_version)
+        .bind(serde_json::to_string(&workflow.history)?)
+        .bind(serde_json::to_string(&workflow.attempts)?)
+        .bind(serde_json::to_string(&workflow.memo)?)
+        .execute(executor)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+#[async_trait::async_trait]
+impl Persistence for SqliteStore {
+    async fn save_workflow(&self, workflow: &Workflow) -> anyhow::Result<()> {
+        insert_workflow(&self.pool, "INSERT OR REPLACE", workflow).await?;
+        Ok(())
+    }
+
+    async fn save_workflow_if_absent(&self, workflow: &Workflow) -> anyhow::Result<bool> {
+        Ok(insert_workflow(&self.pool, "INSERT OR IGNORE", workflow).await? == 1)
+    }
+
+    async fn save_workflows(&self, workflows: &[Workflow]) -> anyhow::Result<Vec<bool>> {
+        let mut tx = self.pool.begin().await?;
+        let mut saved = Vec::with_capacity(workflows.len());
+        for workflow in workflows {
+            saved.push(insert_workflow(&mut *tx, "INSERT OR IGNORE", workflow).await? == 1);
+        }
+        tx.commit().await?;
+        Ok(saved)
+    }
+
+    fn atomic_batches(&self) -> bool {
+        true
+    }
+
+    async fn get_workflow(&self, id: &str) -> anyhow::Result<Option<Workflow>> {
+        sqlx::query("SELECT * FROM workflows WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| from_row(&row))
+            .transpose()
+    }
+
+    async fn list_workflows(&self, workflow_type: Option<&str>) -> anyhow::Result<Vec<Workflow>> {
+        let rows = match workflow_type {
+            Some(wf_type) => {
+                sqlx::query("SELECT * FROM workflows WHERE workflow_type = ?")
+                    .bind(wf_type)
+                    .fetch_all(&self.pool)
+                    .await?
+            }
+            None => {
+                sqlx::query("SELECT * FROM workflows")
+                    .fetch_all(&self.pool)
+                    .await?
+            }
+        };
+        rows.iter().map(from_row).collect()
+    }
+
+    async fn list_workflows_paged(&self, options: &ListOptions) -> anyhow::Result<Vec<Workflow>> {
+        let mut query: QueryBuilder<Sqlite> =
+            QueryBuilder::new("SELECT * FROM workflows WHERE 1 = 1");
+        push_list_filters(&mut query, options);
+        query.push(match options.order_by {
+            OrderBy::StartedAtAsc => " ORDER BY started_at ASC, id ASC",
+            OrderBy::StartedAtDesc => " ORDER BY started_at DESC, id DESC",
+        });
+        // SQLite 的 OFFSET 必须跟在 LIMIT 之后，-1 表示不限制
+        let limit = options
+            .limit
+            .map_or(-1, |limit| limit.min(i64::MAX as usize) as i64);
+        query
+            .push(" LIMIT ")
+            .push_bind(limit)
+            .push(" OFFSET ")
+            .push_bind(options.offset as i64);
+
+        let rows = query.build().fetch_all(&self.pool).await?;
+        rows.iter().map(from_row).collect()
+    }
+
+    async fn count_workflows(&self, options: &ListOptions) -> anyhow::Result<usize> {
+        let mut query: QueryBuilder<Sqlite> =
+            QueryBuilder::new("SELECT COUNT(*) FROM workflows WHERE 1 = 1");
+        push_list_filters(&mut query, options);
+        let count: i64 = query.build_query_scalar().fetch_one(&self.pool).await?;
+        Ok(count as usize)
+    }
+
+    async fn update_workflow_state(&self, id: &str, state: WorkflowState) -> anyhow::Result<()> {
+        let _guard = self.state_updates.lock().await;
+        let Some(mut workflow) = self.get_workflow(id).await? else {
+            return Ok(());
+        };
+        workflow.transition(state, Utc::now());
+        // 单条 UPDATE 语句，状态、更新时间与状态变化记录原子写入
+        sqlx::query("UPDATE workflows SET state = ?, updated_at = ?, history = ? WHERE id = ?")
+            .bind(serde_json::to_string(&workflow.state)?)
+            .bind(to_timestamp(&workflow.updated_at))
+            .bind(serde_json::to_string(&workflow.history)?)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn record_step_attempt(&self, workflow_id: &str, step_name: &str) -> anyhow::Result<u32> {
+        let _guard = self.state_updates.lock().await;
+        let Some(mut workflow) = self.get_workflow(workflow_id).await? else {
+            return Ok(0);
+        };
+        let attempt = workflow.record_attempt(step_name);
+        sqlx::query("UPDATE workflows SET attempts = ? WHERE id = ?")
+            .bind(serde_json::to_string(&workflow.attempts)?)
+            .bind(workflow_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(attempt)
+    }
+
+    async fn save_step_result(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+        result: Vec<u8>,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO step_results (workflow_id, step_name, result) VALUES (?, ?, ?)",
+        )
+        .bind(workflow_id)
+        .bind(step_name)
+        .bind(result)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_step_result(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        let row =
+            sqlx::query("SELECT result FROM step_results WHERE workflow_id = ? AND step_name = ?")
+                .bind(workflow_id)
+                .bind(step_name)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(row.map(|row| row.try_get("result")).transpose()?)
+    }
+
+    async fn append_signal(&self, signal: &Signal) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO signals (workflow_id, name, payload, received_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(&signal.workflow_id)
+        .bind(&signal.name)
+        .bind(&signal.payload)
+        .bind(to_timestamp(&signal.received_at))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_signals(&self, workflow_id: &str) -> anyhow::Result<Vec<Signal>> {
+        let rows = sqlx::query("SELECT * FROM signals WHERE workflow_id = ? ORDER BY seq")
+            .bind(workflow_id)
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter()
+            .map(|row| {
+                let received_at: String = row.try_get("received_at")?;
+                Ok(Signal {
+                    workflow_id: row.try_get("workflow_id")?,
+                    name: row.try_get("name")?,
+                    payload: row.try_get("payload")?,
+                    received_at: DateTime::parse_from_rfc3339(&received_at)?.with_timezone(&Utc),
+                })
+            })
+            .collect()
+    }
+
+    async fn save_timer(&self, timer: &Timer) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO timers (workflow_id, step_name, fire_at) VALUES (?, ?, ?)
+             ON CONFLICT (workflow_id, step_name) DO UPDATE SET fire_at = excluded.fire_at",
+        )
+        .bind(&timer.workflow_id)
+        .bind(&timer.step_name)
+        .bind(to_timestamp(&timer.fire_at))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn list_timers(&self) -> anyhow::Result<Vec<Timer>> {
+        let rows = sqlx::query("SELECT * FROM timers ORDER BY fire_at")
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter()
+            .map(|row| {
+                let fire_at: String = row.try_get("fire_at")?;
+                Ok(Timer {
+                    workflow_id: row.try_get("workflow_id")?,
+                    step_name: row.try_get("step_name")?,
+                    fire_at: DateTime::parse_from_rfc3339(&fire_at)?.with_timezone(&Utc),
+                })
+            })
+            .collect()
+    }
+
+    async fn delete_timer(&self, workflow_id: &str, step_name: &str) -> anyhow::Result<bool> {
+        let deleted = sqlx::query("DELETE FROM timers WHERE workflow_id = ? AND step_name = ?")
+            .bind(workflow_id)
+            .bind(step_name)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        Ok(deleted > 0)
+    }
+
+    async fn save_step_deadline(&self, deadline: &StepDeadline) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO step_deadlines (workflow_id, step_name, worker_id, timeout_ms, deadline)
+             VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT (workflow_id, step_name) DO UPDATE SET
+                worker_id = excluded.worker_id,
+                timeout_ms = excluded.timeout_ms,
+                deadline = excluded.deadline",
+        )
+        .bind(&deadline.workflow_id)
+        .bind(&deadline.step_name)
+        .bind(&deadline.worker_id)
+        .bind(deadline.timeout_ms as i64)
+        .bind(to_timestamp(&deadline.deadline))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn list_step_deadlines(&self) -> anyhow::Result<Vec<StepDeadline>> {
+        let rows = sqlx::query("SELECT * FROM step_deadlines ORDER BY deadline")
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter()
+            .map(|row| {
+                let timeout_ms: i64 = row.try_get("timeout_ms")?;
+                let deadline: String = row.try_get("deadline")?;
+                Ok(StepDeadline {
+                    workflow_id: row.try_get("workflow_id")?,
+                    step_name: row.try_get("step_name")?,
+                    worker_id: row.try_get("worker_id")?,
+                    timeout_ms: timeout_ms as u64,
+                    deadline: DateTime::parse_from_rfc3339(&deadline)?.with_timezone(&Utc),
+                })
+            })
+            .collect()
+    }
+
+    async fn delete_step_deadline(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+    ) -> anyhow::Result<bool> {
+        let deleted =
+            sqlx::query("DELETE FROM step_deadlines WHERE workflow_id = ? AND step_name = ?")
+                .bind(workflow_id)
+                .bind(step_name)
+                .execute(&self.pool)
+                .await?
+                .rows_affected();
+        Ok(deleted > 0)
+    }
+
+    async fn get_cached_result(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let row = sqlx::query("SELECT result FROM step_cache WHERE key = ? AND expires_at > ?")
+            .bind(key)
+            .bind(to_timestamp(&Utc::now()))
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|row| row.try_get("result")).transpose()?)
+    }
+
+    async fn put_cached_result(
+        &self,
+        entry: &CachedResult,
+        max_entries: usize,
+    ) -> anyhow::Result<()> {
+        if max_entries == 0 {
+            return Ok(());
+        }
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "INSERT INTO step_cache (key, result, cached_at, expires_at) VALUES (?, ?, ?, ?)
+             ON CONFLICT (key) DO UPDATE SET
+                result = excluded.result,
+                cached_at = excluded.cached_at,
+                expires_at = excluded.expires_at",
+        )
+        .bind(&entry.key)
+        .bind(&entry.result)
+        .bind(to_timestamp(&entry.cached_at))
+        .bind(to_timestamp(&entry.expires_at))
+        .execute(&mut *tx)
+        .await?;
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM step_cache")
+            .fetch_one(&mut *tx)
+            .await?;
+        if count as usize > max_entries {
+            sqlx::query("DELETE FROM step_cache WHERE expires_at <= ?")
+                .bind(to_timestamp(&Utc::now()))
+                .execute(&mut *tx)
+                .await?;
+            // 仍然超出时保留刚写入的条目和最近缓存的条目
+            sqlx::query(
+                "DELETE FROM step_cache WHERE key NOT IN (
+                    SELECT key FROM step_cache ORDER BY key = ? DESC, cached_at DESC LIMIT ?
+                 )",
+            )
+            .bind(&entry.key)
+            .bind(max_entries as i64)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn save_schedule(&self, schedule: &Schedule) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO schedules (id, created_at, schedule) VALUES (?, ?, ?)
+             ON CONFLICT (id) DO UPDATE SET schedule = excluded.schedule",
+        )
+        .bind(&schedule.id)
+        .bind(to_timestamp(&schedule.created_at))
+        .bind(serde_json::to_string(schedule)?)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update_schedule(&self, schedule: &Schedule) -> anyhow::Result<bool> {
+        let updated = sqlx::query("UPDATE schedules SET schedule = ? WHERE id = ?")
+            .bind(serde_json::to_string(schedule)?)
+            .bind(&schedule.id)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        Ok(updated > 0)
+    }
+
+    async fn list_schedules(&self) -> anyhow::Result<Vec<Schedule>> {
+        let rows = sqlx::query("SELECT schedule FROM schedules ORDER BY created_at, id")
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter()
+            .map(|row| {
+                let schedule: String = row.try_get("schedule")?;
+                Ok(serde_json::from_str(&schedule)?)
+            })
+            .collect()
+    }
+
+    async fn delete_schedule(&self, id: &str) -> anyhow::Result<bool> {
+        let deleted = sqlx::query("DELETE FROM schedules WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        Ok(deleted > 0)
+    }
+
+    async fn save_definition(&self, definition: &WorkflowDefinition) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO workflow_definitions (name, version, definition) VALUES (?, ?, ?)
+             ON CONFLICT (name, version) DO UPDATE SET definition = excluded.definition",
+        )
+        .bind(&definition.name)
+        .bind(definition.version as i64)
+        .bind(serde_json::to_string(definition)?)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn list_definitions(&self) -> anyhow::Result<Vec<WorkflowDefinition>> {
+        let rows =
+            sqlx::query("SELECT definition FROM workflow_definitions ORDER BY name, version")
+                .fetch_all(&self.pool)
+                .await?;
+        rows.iter()
+            .map(|row| {
+                let definition: String = row.try_get("definition")?;
+                Ok(serde_json::from_str(&definition)?)
+            })
+            .collect()
+    }
+
+    async fn save_execution(&self, execution: &WorkflowExecution) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO executions (workflow_id, execution) VALUES (?, ?)
+             ON CONFLICT (workflow_id) DO UPDATE SET execution = excluded.execution",
+        )
+        .bind(&execution.workflow_id)
+        .bind(serde_json::to_string(execution)?)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn load_executions(&self) -> anyhow::Result<Vec<WorkflowExecution>> {
+        let rows = sqlx::query("SELECT execution FROM executions")
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter()
+            .map(|row| {
+                let execution: String = row.try_get("execution")?;
+                Ok(serde_json::from_str(&execution)?)
+            })
+            .collect()
+    }
+
+    async fn delete_workflow(&self, id: &str) -> anyhow::Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM step_results WHERE workflow_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM signals WHERE workflow_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM timers WHERE workflow_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM step_deadlines WHERE workflow_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM executions WHERE workflow_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM workflow_memo WHERE workflow_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        let deleted = sqlx::query("DELETE FROM workflows WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        tx.commit().await?;
+        Ok(deleted > 0)
+    }
+
+    async fn purge_workflows(&self, filter: &PurgeFilter) -> anyhow::Result<Vec<String>> {
+        let mut tx = self.pool.begin().await?;
+        let rows = sqlx::query("SELECT * FROM workflows")
+            .fetch_all(&mut *tx)
+            .await?;
+
+        let mut ids = Vec::new();
+        for row in &rows {
+            let workflow = from_row(row)?;
+            if filter.matches(&workflow) {
+                ids.push(workflow.id);
+            }
+        }
+
+        for id in &ids {
+            sqlx::query("DELETE FROM step_results WHERE workflow_id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM signals WHERE workflow_id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM timers WHERE workflow_id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM step_deadlines WHERE workflow_id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM executions WHERE workflow_id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM workflow_memo WHERE workflow_id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM workflows WHERE id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(ids)
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "sqlite"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_roundtrip_and_type_filter() {
+        let store = SqliteStore::in_memory().await.unwrap();
+
+        let mut wf1 = Workflow::new("wf1".to_string(), "type-a".to_string(), b"in".to_vec());
+        wf1.record_completion("charge", b"ok".to_vec());
+        store.save_workflow(&wf1).await.unwrap();
+        let wf2 = Workflow::new("wf2".to_string(), "type-b".to_string(), vec![]);
+        store.save_workflow(&wf2).await.unwrap();
+
+        let restored = store.get_workflow("wf1").await.unwrap().unwrap();
+        assert_eq!(restored.input, wf1.input);
+        assert_eq!(restored.completed_steps, wf1.completed_steps);
+        assert_eq!(restored.started_at, wf1.started_at);
+
+        let type_a = store.list_workflows(Some("type-a")).await.unwrap();
+        assert_eq!(type_a.len(), 1);
+        assert_eq!(type_a[0].id, "wf1");
+        assert_eq!(store.list_workflows(None).await.unwrap().len(), 2);
+        assert!(store.get_workflow("missing").await.unwrap().is_none());
+
+        let child =
+            Workflow::new("wf3".to_string(), "type-b".to_string(), vec![]).with_parent("wf1");
+        store.save_workflow(&child).await.unwrap();
+        let children = store
+            .list_workflows_paged(&ListOptions {
+                parent_workflow_id: Some("wf1".to_string()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(children, vec![child]);
+    }
+
+    #[tokio::test]
+    async fn test_migrates_databases_without_new_columns() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(SqliteConnectOptions::from_str("sqlite::memory:").unwrap())
+            .await
+            .unwrap();
+        sqlx::raw_sql(
+            "CREATE TABLE workflows (id TEXT PRIMARY KEY, workflow_type TEXT NOT NULL, \
+             state TEXT NOT NULL, input BLOB NOT NULL, steps_completed TEXT NOT NULL, \
+             started_at TEXT NOT NULL, updated_at TEXT NOT NULL)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let store = SqliteStore::with_pool(pool).await.unwrap();
+        let child = Workflow::new("wf1".to_string(), "type-a".to_string(), vec![])
+            .with_parent("p")
+            .with_priority(crate::state_machine::Priority::High)
+            .with_schema_validation_skipped()
+            .with_definition_version(2)
+            .with_memo([("region".to_string(), "eu".to_string())].into());
+        store.save_workflow(&child).await.unwrap();
+        assert_eq!(store.get_workflow("wf1").await.unwrap(), Some(child));
+    }
+
+    #[tokio::test]
+    async fn test_save_if_absent_keeps_existing_workflow() {
+        let store = SqliteStore::in_memory().await.unwrap();
+        let first = Workflow::new("wf1".to_string(), "type-a".to_string(), b"first".to_vec());
+        assert!(store.save_workflow_if_absent(&first).await.unwrap());
+
+        let second = Workflow::new("wf1".to_string(), "type-b".to_string(), b"second".to_vec());
+        assert!(!store.save_workflow_if_absent(&second).await.unwrap());
+        assert_eq!(store.get_workflow("wf1").await.unwrap(), Some(first));
+    }
+
+    #[tokio::test]
+    async fn test_update_state_and_step_results() {
+        let store = SqliteStore::in_memory().await.unwrap();
+        let workflow = Workflow::new("wf1".to_string(), "type-a".to_string(), vec![]);
+        store.save_workflow(&workflow).await.unwrap();
+
+        store
+            .update_workflow_state(
+                "wf1",
+                WorkflowState::Running {
+                    current_step: Some("charge".to_string()),
+                },
+            )
+            .await
+            .unwrap();
+        let updated = store.get_workflow("wf1").await.unwrap().unwrap();
+        assert_eq!(
+            updated.state,
+            WorkflowState::Running {
+                current_step: Some("charge".to_string())
+            }
+        );
+        assert!(updated.updated_at >= workflow.updated_at);
+        assert_eq!(updated.history.len(), 1);
+        assert_eq!(updated.history[0].from, "PENDING");
+        assert_eq!(updated.history[0].step.as_deref(), Some("charge"));
+
+        store
+            .save_step_result("wf1", "charge", b"ok".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get_step_result("wf1", "charge").await.unwrap(),
+            Some(b"ok".to_vec())
+        );
+
+        let signals = [
+            Signal::new("wf1", "approved", b"first".to_vec()),
+            Signal::new("wf1", "approved", b"second".to_vec()),
+        ];
+        for signal in &signals {
+            store.append_signal(signal).await.unwrap();
+        }
+        assert_eq!(store.get_signals("wf1").await.unwrap(), signals.to_vec());
+
+        let timer = Timer {
+            workflow_id: "wf1".to_string(),
+            step_name: "wait".to_string(),
+            fire_at: Utc::now(),
+        };
+        store.save_timer(&timer).await.unwrap();
+        assert_eq!(store.list_timers().await.unwrap(), vec![timer.clone()]);
+
+        assert!(store.delete_workflow("wf1").await.unwrap());
+        assert!(store.get_workflow("wf1").await.unwrap().is_none());
+        assert!(store.get_signals("wf1").await.unwrap().is_empty());
+        assert!(store.list_timers().await.unwrap().is_empty());
+        assert!(store
+            .get_step_result("wf1", "charge")
+            .await
+            .unwrap()
+            .is_none());
+        assert!(!store.delete_workflow("wf1").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_purge_only_terminal() {
+        let store = SqliteStore::in_memory().await.unwrap();
+        let mut done = Workflow::new("done".to_string(), "type-a".to_string(), vec![]);
+        done.state = WorkflowState::Completed {
+            result: vec![].into(),
+        };
+        store.save_workflow(&done).await.unwrap();
+        let running = Workflow::new("running".to_string(), "type-a".to_string(), vec![]);
+        store.save_workflow(&running).await.unwrap();
+
+        let removed = store
+            .purge_workflows(&PurgeFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(removed, vec!["done".to_string()]);
+        assert_eq!(store.list_workflows(None).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_list_paged_in_sql() {
+        let store = SqliteStore::in_memory().await.unwrap();
+        let base = Utc::now();
+        for i in 0..5 {
+            let mut workflow = Workflow::new(format!("wf{}", i), "type-a".to_string(), vec![]);
+            workflow.started_at = base + chrono::Duration::seconds(i);
+            if i % 2 == 0 {
+                workflow.state = WorkflowState::Running { current_step: None };
+            }
+            store.save_workflow(&workflow).await.unwrap();
+        }
+
+        let ids = |workflows: Vec<Workflow>| -> Vec<String> {
+            workflows.into_iter().map(|w| w.id).collect()
+        };
+        let page = store
+            .list_workflows_paged(&ListOptions {
+                offset: 1,
+                limit: Some(2),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(ids(page), vec!["wf1", "wf2"]);
+
+        let running = store
+            .list_workflows_paged(&ListOptions {
+                order_by: OrderBy::StartedAtDesc,
+                state_filter: Some(StateKind::Running),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(ids(running), vec!["wf4", "wf2", "wf0"]);
+
+        let pending = store
+            .list_workflows_paged(&ListOptions {
+                offset: 1,
+                state_filter: Some(StateKind::Pending),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(ids(pending), vec!["wf3"]);
+
+        let window = store
+            .list_workflows_paged(&ListOptions {
+                started_after: Some(base + chrono::Duration::seconds(1)),
+                started_before: Some(base + chrono::Duration::seconds(3)),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(ids(window), vec!["wf1", "wf2"]);
+
+        // 计数忽略分页，只应用筛选条件
+        let count = |options: ListOptions| {
+            let store = &store;
+            async move { store.count_workflows(&options).await.unwrap() }
+        };
+        assert_eq!(count(ListOptions::default()).await, 5);
+        assert_eq!(
+            count(ListOptions {
+                offset: 2,
+                limit: Some(1),
+                state_filter: Some(StateKind::Running),
+                started_after: Some(base + chrono::Duration::seconds(1)),
+                ..Default::default()
+            })
+            .await,
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_filters_by_indexed_memo() {
+        let store = SqliteStore::in_memory().await.unwrap();
+        for (id, customer, region) in [("wf0", "1", "eu"), ("wf1", "2", "eu"), ("wf2", "1", "us")] {
+            let memo = [
+                ("customer_id".to_string(), customer.to_string()),
+                ("region".to_string(), region.to_string()),
+            ];
+            let workflow =
+                Workflow::new(id.to_string(), "type-a".to_string(), vec![]).with_memo(memo.into());
+            store.save_workflow(&workflow).await.unwrap();
+        }
+        // 重复保存同一 workflow 不会产生重复的索引行
+        let wf0 = store.get_workflow("wf0").await.unwrap().unwrap();
+        store.save_workflow(&wf0).await.unwrap();
+
+        let list = |memo: &[(&str, &str)]| {
+            let options = ListOptions {
+                memo: memo
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect(),
+                ..Default::default()
+            };
+            let store = &store;
+            async move {
+                let ids: Vec<String> = store
+                    .list_workflows_paged(&options)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|w| w.id)
+                    .collect();
+                assert_eq!(store.count_workflows(&options).await.unwrap(), ids.len());
+                ids
+            }
+        };
+        assert_eq!(list(&[("customer_id", "1")]).await, vec!["wf0", "wf2"]);
+        assert_eq!(
+            list(&[("customer_id", "1"), ("region", "eu")]).await,
+            vec!["wf0"]
+        );
+        assert!(list(&[("customer_id", "3")]).await.is_empty());
+
+        assert!(store.delete_workflow("wf0").await.unwrap());
+        let indexed: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM workflow_memo")
+            .fetch_one(&store.pool)
+            .await
+            .unwrap();
+        assert_eq!(indexed, 4);
+    }
+
+    #[tokio::test]
+    async fn test_step_cache_expires_and_evicts_oldest() {
+        use std::time::Duration;
+
+        let store = SqliteStore::in_memory().await.unwrap();
+        let ttl = Duration::from_secs(60);
+        store
+            .put_cached_result(
+                &CachedResult::new("expired", b"0".to_vec(), Duration::ZERO),
+                2,
+            )
+            .await
+            .unwrap();
+        assert_eq!(store.get_cached_result("expired").await.unwrap(), None);
+
+        let mut oldest = CachedResult::new("a", b"1".to_vec(), ttl);
+        oldest.cached_at -= chrono::Duration::seconds(10);
+        store.put_cached_result(&oldest, 2).await.unwrap();
+        store
+            .put_cached_result(&CachedResult::new("b", b"2".to_vec(), ttl), 2)
+            .await
+            .unwrap();
+        store
+            .put_cached_result(&CachedResult::new("c", b"3".to_vec(), ttl), 2)
+            .await
+            .unwrap();
+
+        assert_eq!(store.get_cached_result("a").await.unwrap(), None);
+        assert_eq!(
+            store.get_cached_result("b").await.unwrap(),
+            Some(b"2".to_vec())
+        );
+        assert_eq!(
+            store.get_cached_result("c").await.unwrap(),
+            Some(b"3".to_vec())
+        );
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM step_cache")
+            .fetch_one(&store.pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_data_survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("aether.sqlite");
+
+        let store = SqliteStore::open(&path).await.unwrap();
+        let workflow = Workflow::new("wf1".to_string(), "type-a".to_string(), vec![]);
+        store.save_workflow(&workflow).await.unwrap();
+        store.pool.close().await;
+
+        let reopened = SqliteStore::open(&path).await.unwrap();
+        assert_eq!(reopened.get_workflow("wf1").await.unwrap(), Some(workflow));
+    }
+
+    #[tokio::test]
+    async fn test_save_workflows_skips_existing_ids() {
+        let store = SqliteStore::in_memory().await.unwrap();
+        assert!(store.atomic_batches());
+        let existing = Workflow::new("wf1".to_string(), "type-a".to_string(), b"old".to_vec());
+        store.save_workflow(&existing).await.unwrap();
+
+        let batch = [
+            Workflow::new("wf1".to_string(), "type-a".to_string(), b"new".to_vec()),
+            Workflow::new("wf2".to_string(), "type-a".to_string(), vec![]),
+            Workflow::new("wf2".to_string(), "type-b".to_string(), vec![]),
+        ];
+        let saved = store.save_workflows(&batch).await.unwrap();
+        assert_eq!(saved, [false, true, false]);
+
+        let wf1 = store.get_workflow("wf1").await.unwrap().unwrap();
+        assert_eq!(wf1.input, b"old");
+        let wf2 = store.get_workflow("wf2").await.unwrap().unwrap();
+        assert_eq!(wf2.workflow_type, "type-a");
+    }
+}
@@ -0,0 +1,10 @@
+//! gRPC 协议类型
+//!
+//! 由 build.rs 根据 `proto/aether.proto` 生成。
+
+#![allow(clippy::all)]
+
+tonic::include_proto!("aether.v1");
+
+/// `aether.v1` 的 descriptor set，注册到 gRPC reflection 服务
+pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("aether_descriptor");
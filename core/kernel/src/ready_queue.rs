@@ -0,0 +1,303 @@
+//! 就绪队列
+//!
+//! 调度器在内存中维护运行中 workflow 的就绪 step，poll 按优先级和启动时间顺序取出
+//! worker 能处理的 task，不再在每次轮询时从持久化层列出全部 workflow。
+//! workflow 启动、step 完成或失败、收到 signal、重试和取消等状态变化把 workflow 标记为待刷新，
+//! 下一次 poll 只从持久化层重新读取这些 workflow；租约和重试退避在分发时按实时状态过滤。
+//! 启动后的第一次 poll（以及注册新定义后）从持久化层加载全部运行中的 workflow，重建队列。
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+
+use crate::state_machine::Priority;
+use crate::task::Task;
+
+/// 队列中的排序键：优先级高的在前，同一优先级内先启动的在前
+type QueueKey = (Reverse<Priority>, DateTime<Utc>, String);
+
+/// 队列中的运行中 workflow
+#[derive(Debug, Clone)]
+pub struct QueuedWorkflow {
+    pub workflow_id: String,
+    pub priority: Priority,
+    pub started_at: DateTime<Utc>,
+    /// 正在执行的 step，其 task 租约过期或等待重试时从重新分发队列中取出
+    pub current_step: Option<String>,
+    /// 定义是否启用粘性路由
+    pub sticky: bool,
+    /// 启动时选择了跳过资源 schema 校验
+    pub skip_schema_validation: bool,
+    /// 就绪 step 的 task，不考虑租约和重试退避
+    pub tasks: Vec<Task>,
+    /// 可缓存 step 的缓存键，按 step 名称索引
+    pub cache_keys: HashMap<String, String>,
+}
+
+/// 运行中的 workflow，按分发顺序排列
+#[derive(Debug, Default)]
+pub struct ReadyQueue {
+    entries: BTreeMap<QueueKey, QueuedWorkflow>,
+    keys: HashMap<String, QueueKey>,
+}
+
+impl ReadyQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 加入或替换 workflow
+    pub fn insert(&mut self, workflow: QueuedWorkflow) {
+        self.remove(&workflow.workflow_id);
+        let key = (
+            Reverse(workflow.priority),
+            workflow.started_at,
+            workflow.workflow_id.clone(),
+        );
+        self.keys.insert(workflow.workflow_id.clone(), key.clone());
+        self.entries.insert(key, workflow);
+    }
+
+    /// 移除已结束或已删除的 workflow
+    pub fn remove(&mut self, workflow_id: &str) {
+        if let Some(key) = self.keys.remove(workflow_id) {
+            self.entries.remove(&key);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.keys.clear();
+    }
+
+    pub fn get(&self, workflow_id: &str) -> Option<&QueuedWorkflow> {
+        self.keys
+            .get(workflow_id)
+            .and_then(|key| self.entries.get(key))
+    }
+
+    pub fn contains(&self, workflow_id: &str) -> bool {
+        self.keys.contains_key(workflow_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 按分发顺序遍历
+    pub fn iter(&self) -> impl Iterator<Item = &QueuedWorkflow> {
+        self.entries.values()
+    }
+}
+
+/// 下一次 poll 需要刷新的内容
+#[derive(Debug, PartialEq)]
+pub enum Refresh {
+    /// 从持久化层重新加载全部运行中的 workflow
+    All,
+    /// 只重新读取这些 workflow
+    Workflows(Vec<String>),
+}
+
+/// 状态发生变化、等待刷新的 workflow
+///
+/// 使用同步锁，持有租约锁时也可以标记。
+#[derive(Debug)]
+pub struct StaleWorkflows {
+    inner: Mutex<StaleState>,
+}
+
+#[derive(Debug)]
+struct StaleState {
+    reload: bool,
+    workflow_ids: HashSet<String>,
+}
+
+impl Default for StaleWorkflows {
+    fn default() -> Self {
+        StaleWorkflows {
+            // 首次 poll 从持久化层恢复队列
+            inner: Mutex::new(StaleState {
+                reload: true,
+                workflow_ids: HashSet::new(),
+            }),
+        }
+    }
+}
+
+impl StaleWorkflows {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 标记 workflow 待刷新
+    pub fn mark(&self, workflow_id: &str) {
+        let mut state = self.inner.lock().unwrap();
+        if !state.reload {
+            state.workflow_ids.insert(workflow_id.to_string());
+        }
+    }
+
+    /// 标记整个队列待重建
+    pub fn reload(&self) {
+        let mut state = self.inner.lock().unwrap();
+        state.reload = true;
+        state.workflow_ids.clear();
+    }
+
+    /// 取出待刷新的内容
+    pub fn take(&self) -> Refresh {
+        let mut state = self.inner.lock().unwrap();
+        if std::mem::take(&mut state.reload) {
+            state.workflow_ids.clear();
+            return Refresh::All;
+        }
+        Refresh::Workflows(state.workflow_ids.drain().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use crate::persistence::l0_memory::L0MemoryStore;
+    use crate::persistence::Persistence;
+    use crate::scheduler::Scheduler;
+    use crate::state_machine::{Workflow, WorkflowState};
+    use crate::task::TaskId;
+
+    fn queued(workflow_id: &str, priority: Priority, started_at: DateTime<Utc>) -> QueuedWorkflow {
+        QueuedWorkflow {
+            workflow_id: workflow_id.to_string(),
+            priority,
+            started_at,
+            current_step: None,
+            sticky: false,
+            skip_schema_validation: false,
+            tasks: Vec::new(),
+            cache_keys: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_queue_orders_by_priority_then_start_time() {
+        let now = Utc::now();
+        let mut queue = ReadyQueue::new();
+        queue.insert(queued("old-low", Priority::Low, now));
+        queue.insert(queued(
+            "new-normal",
+            Priority::Normal,
+            now + chrono::Duration::seconds(1),
+        ));
+        queue.insert(queued("old-normal", Priority::Normal, now));
+        queue.insert(queued(
+            "high",
+            Priority::High,
+            now + chrono::Duration::seconds(2),
+        ));
+        queue.insert(queued("old-normal", Priority::Normal, now));
+
+        let order: Vec<&str> = queue.iter().map(|w| w.workflow_id.as_str()).collect();
+        assert_eq!(order, vec!["high", "old-normal", "new-normal", "old-low"]);
+        queue.remove("high");
+        assert!(!queue.contains("high"));
+        assert_eq!(queue.len(), 3);
+    }
+
+    #[test]
+    fn test_stale_workflows_start_with_reload() {
+        let stale = StaleWorkflows::new();
+        stale.mark("wf-1");
+        assert_eq!(stale.take(), Refresh::All);
+        stale.mark("wf-2");
+        assert_eq!(stale.take(), Refresh::Workflows(vec!["wf-2".to_string()]));
+        assert_eq!(stale.take(), Refresh::Workflows(Vec::new()));
+    }
+
+    /// 存有 `finished` 个已结束 workflow 和一个运行中 workflow 的调度器
+    async fn scheduler_with_history(finished: usize) -> Scheduler<Arc<L0MemoryStore>> {
+        let store = Arc::new(L0MemoryStore::new());
+        for i in 0..finished {
+            let mut workflow = Workflow::new(format!("done-{}", i), "order".to_string(), vec![]);
+            workflow.state = WorkflowState::Completed {
+                result: vec![].into(),
+            };
+            store.save_workflow(&workflow).await.unwrap();
+        }
+        let mut workflow = Workflow::new("wf-1".to_string(), "order".to_string(), vec![]);
+        workflow.state = workflow.state.try_start().unwrap();
+        store.save_workflow(&workflow).await.unwrap();
+
+        let scheduler = Scheduler::new(store);
+        scheduler
+            .register_worker(
+                "worker-1".to_string(),
+                "shop".to_string(),
+                "default".to_string(),
+                vec!["order".to_string()],
+                vec![],
+            )
+            .await;
+        scheduler
+    }
+
+    /// 轮询 `polls` 次的耗时，task 每次都放回队列
+    async fn dispatch_time(scheduler: &Scheduler<Arc<L0MemoryStore>>, polls: usize) -> Duration {
+        let started = Instant::now();
+        for _ in 0..polls {
+            let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+            assert_eq!(tasks.len(), 1);
+            scheduler
+                .release_lease(&TaskId::new(&tasks[0].workflow_id, &tasks[0].step_name))
+                .await;
+        }
+        started.elapsed()
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_cost_does_not_grow_with_stored_workflows() {
+        let small = scheduler_with_history(0).await;
+        let large = scheduler_with_history(50_000).await;
+        // 第一次 poll 从持久化层恢复队列
+        dispatch_time(&small, 1).await;
+        dispatch_time(&large, 1).await;
+
+        let small_time = dispatch_time(&small, 200).await;
+        let large_time = dispatch_time(&large, 200).await;
+        assert!(
+            large_time < small_time * 3 + Duration::from_millis(20),
+            "dispatch with 50k stored workflows took {:?}, with none {:?}",
+            large_time,
+            small_time
+        );
+    }
+
+    #[tokio::test]
+    async fn test_queue_is_rebuilt_from_persistence_on_startup() {
+        let scheduler = scheduler_with_history(3).await;
+        assert_eq!(scheduler.poll_tasks("worker-1", 10).await.unwrap().len(), 1);
+
+        // 模拟重启：新的调度器使用同一份持久化数据
+        let restarted = Scheduler::new(scheduler.persistence.clone());
+        restarted
+            .register_worker(
+                "worker-1".to_string(),
+                "shop".to_string(),
+                "default".to_string(),
+                vec!["order".to_string()],
+                vec![],
+            )
+            .await;
+        let tasks = restarted.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].workflow_id, "wf-1");
+    }
+}
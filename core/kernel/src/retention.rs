@@ -0,0 +1,385 @@
+//! Workflow 删除与保留策略
+//!
+//! 删除会同时清理持久化中的 step 结果、追踪器中的执行历史和残留的 task 租约。
+//! 只有终态 workflow 可以被清理，运行中的 workflow 需要显式 `force` 才能删除。
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use tokio::task::JoinHandle;
+
+use crate::persistence::{Persistence, PurgeFilter};
+use crate::scheduler::Scheduler;
+
+/// 删除 workflow 的错误
+#[derive(Debug)]
+pub enum DeleteError {
+    /// workflow 不存在
+    WorkflowNotFound(String),
+    /// workflow 尚未结束且未指定 force
+    NotTerminal(String),
+    /// 持久化层错误
+    Persistence(anyhow::Error),
+}
+
+impl fmt::Display for DeleteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeleteError::WorkflowNotFound(workflow_id) => {
+                write!(f, "Workflow not found: {}", workflow_id)
+            }
+            DeleteError::NotTerminal(workflow_id) => write!(
+                f,
+                "Workflow {} is still active; cancel it first or use force",
+                workflow_id
+            ),
+            DeleteError::Persistence(e) => write!(f, "Persistence error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for DeleteError {}
+
+impl From<anyhow::Error> for DeleteError {
+    fn from(e: anyhow::Error) -> Self {
+        DeleteError::Persistence(e)
+    }
+}
+
+/// 终态 workflow 的保留策略
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetentionPolicy {
+    /// 清理任务的运行间隔
+    pub interval: Duration,
+    /// 默认保留时长，`None` 表示未在 `per_type` 中列出的类型永久保留
+    pub max_age: Option<Duration>,
+    /// 按 workflow 类型覆盖的保留时长
+    pub per_type: HashMap<String, Duration>,
+}
+
+impl RetentionPolicy {
+    /// 某个 workflow 类型的保留时长
+    pub fn max_age_for(&self, workflow_type: &str) -> Option<Duration> {
+        self.per_type.get(workflow_type).copied().or(self.max_age)
+    }
+}
+
+impl<P: Persistence> Scheduler<P> {
+    /// 删除单个 workflow
+    ///
+    /// 非终态 workflow 只有在 `force` 时才会被删除。
+    pub async fn delete_workflow(&self, workflow_id: &str, force: bool) -> Result<(), DeleteError> {
+        let workflow = self
+            .persistence
+            .get_workflow(workflow_id)
+            .await?
+            .ok_or_else(|| DeleteError::WorkflowNotFound(workflow_id.to_string()))?;
+
+        if !force && !workflow.state.is_terminal() {
+            return Err(DeleteError::NotTerminal(workflow_id.to_string()));
+        }
+
+        if self.persistence.delete_workflow(workflow_id).await? {
+            self.forget_workflows(&[workflow_id.to_string()]).await;
+        }
+        Ok(())
+    }
+
+    /// 批量删除匹配的终态 workflow，返回删除数量
+    pub async fn purge_workflows(&self, filter: &PurgeFilter) -> anyhow::Result<usize> {
+        let removed = self.persistence.purge_workflows(filter).await?;
+        self.forget_workflows(&removed).await;
+        Ok(removed.len())
+    }
+
+    /// 按保留策略执行一次清理，返回删除数量
+    pub async fn apply_retention(&self, policy: &RetentionPolicy) -> anyhow::Result<usize> {
+        let now = Utc::now();
+
+        // 没有按类型覆盖时，所有类型共用一个截止时间，交给存储层一次完成
+        if policy.per_type.is_empty() {
+            let Some(max_age) = policy.max_age else {
+                return Ok(0);
+            };
+            let cutoff = now - chrono::Duration::from_std(max_age)?;
+            let removed = self.persistence.purge_completed_before(cutoff).await?;
+            self.forget_workflows(&removed).await;
+            return Ok(removed.len());
+        }
+
+        let mut expired = Vec::new();
+
+        for workflow in self.persistence.list_workflows(None).await? {
+            let Some(max_age) = policy.max_age_for(&workflow.workflow_type) else {
+                continue;
+            };
+            if workflow.state.is_terminal()
+                && workflow.updated_at + chrono::Duration::from_std(max_age)? < now
+            {
+                expired.push(workflow.id);
+            }
+        }
+
+        let mut removed = Vec::new();
+        for workflow_id in expired {
+            if self.persistence.delete_workflow(&workflow_id).await? {
+                removed.push(workflow_id);
+            }
+        }
+
+        self.forget_workflows(&removed).await;
+        Ok(removed.len())
+    }
+
+    /// 清理已删除 workflow 的执行历史与租约
+    async fn forget_workflows(&self, workflow_ids: &[String]) {
+        for workflow_id in workflow_ids {
+            self.tracker.remove(workflow_id).await;
+            self.release_workflow_leases(workflow_id).await;
+        }
+        self.record_purged(workflow_ids.len());
+    }
+}
+
+/// 启动后台保留任务，按 `policy.interval` 定期清理过期 workflow
+pub fn spawn_retention_task<P: Persistence + 'static>(
+    scheduler: Arc<Scheduler<P>>,
+    policy: RetentionPolicy,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(policy.interval);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = scheduler.stopped() => break,
+            }
+            match scheduler.apply_retention(&policy).await {
+                Ok(0) => {}
+                Ok(removed) => tracing::info!(
+                    removed,
+                    total = scheduler.purged_workflows(),
+                    "retention removed expired workflows"
+                ),
+                Err(e) => tracing::warn!("retention pass failed: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::l0_memory::L0MemoryStore;
+    use crate::persistence::TerminalState;
+    use crate::state_machine::{Workflow, WorkflowState};
+
+    async fn save(
+        scheduler: &Scheduler<L0MemoryStore>,
+        id: &str,
+        workflow_type: &str,
+        state: WorkflowState,
+        age: chrono::Duration,
+    ) {
+        let mut workflow = Workflow::new(id.to_string(), workflow_type.to_string(), vec![]);
+        workflow.state = state;
+        workflow.updated_at = Utc::now() - age;
+        scheduler
+            .persistence
+            .save_workflow(&workflow)
+            .await
+            .unwrap();
+        scheduler
+            .tracker
+            .start_workflow(id.to_string(), workflow_type.to_string())
+            .await;
+    }
+
+    fn completed() -> WorkflowState {
+        WorkflowState::Completed {
+            result: vec![].into(),
+        }
+    }
+
+    fn running() -> WorkflowState {
+        WorkflowState::Running { current_step: None }
+    }
+
+    #[tokio::test]
+    async fn test_delete_requires_terminal_state() {
+        let scheduler = Scheduler::new(L0MemoryStore::new());
+        save(
+            &scheduler,
+            "wf-1",
+            "order",
+            running(),
+            chrono::Duration::zero(),
+        )
+        .await;
+        scheduler
+            .persistence
+            .save_step_result("wf-1", "charge", b"ok".to_vec())
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            scheduler.delete_workflow("wf-1", false).await,
+            Err(DeleteError::NotTerminal(_))
+        ));
+        assert!(scheduler
+            .persistence
+            .get_workflow("wf-1")
+            .await
+            .unwrap()
+            .is_some());
+
+        scheduler.delete_workflow("wf-1", true).await.unwrap();
+        assert!(scheduler
+            .persistence
+            .get_workflow("wf-1")
+            .await
+            .unwrap()
+            .is_none());
+        assert!(scheduler
+            .persistence
+            .get_step_result("wf-1", "charge")
+            .await
+            .unwrap()
+            .is_none());
+        assert!(scheduler.tracker.get_execution("wf-1").await.is_none());
+
+        assert!(matches!(
+            scheduler.delete_workflow("wf-1", false).await,
+            Err(DeleteError::WorkflowNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_purge_filters_and_protects_running() {
+        let scheduler = Scheduler::new(L0MemoryStore::new());
+        let old = chrono::Duration::days(2);
+        save(&scheduler, "done-old", "order", completed(), old).await;
+        save(
+            &scheduler,
+            "done-new",
+            "order",
+            completed(),
+            chrono::Duration::zero(),
+        )
+        .await;
+        save(
+            &scheduler,
+            "failed-old",
+            "order",
+            WorkflowState::Failed {
+                error: "boom".to_string(),
+            },
+            old,
+        )
+        .await;
+        save(&scheduler, "other-old", "report", completed(), old).await;
+        save(&scheduler, "running-old", "order", running(), old).await;
+
+        let filter = PurgeFilter {
+            states: vec![TerminalState::Completed],
+            completed_before: Some(Utc::now() - chrono::Duration::days(1)),
+            workflow_type: Some("order".to_string()),
+        };
+        assert_eq!(scheduler.purge_workflows(&filter).await.unwrap(), 1);
+
+        let remaining: Vec<String> = scheduler
+            .persistence
+            .list_workflows(None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|w| w.id)
+            .collect();
+        assert!(!remaining.contains(&"done-old".to_string()));
+        assert_eq!(remaining.len(), 4);
+        assert!(scheduler.tracker.get_execution("done-old").await.is_none());
+
+        // 不带条件的清理也不会删除运行中的 workflow
+        assert_eq!(
+            scheduler
+                .purge_workflows(&PurgeFilter::default())
+                .await
+                .unwrap(),
+            3
+        );
+        let remaining = scheduler.persistence.list_workflows(None).await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, "running-old");
+        assert!(scheduler
+            .tracker
+            .get_execution("running-old")
+            .await
+            .is_some());
+        assert_eq!(scheduler.purged_workflows(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_apply_retention_uniform_cutoff() {
+        let scheduler = Scheduler::new(L0MemoryStore::new());
+        let hour = chrono::Duration::hours(1);
+        save(&scheduler, "old", "order", completed(), hour * 3).await;
+        save(&scheduler, "old-running", "order", running(), hour * 3).await;
+        save(&scheduler, "recent", "report", completed(), hour).await;
+
+        let policy = RetentionPolicy {
+            interval: Duration::from_secs(60),
+            max_age: Some(Duration::from_secs(2 * 3600)),
+            per_type: HashMap::new(),
+        };
+        assert_eq!(scheduler.apply_retention(&policy).await.unwrap(), 1);
+        assert!(scheduler.tracker.get_execution("old").await.is_none());
+        assert!(scheduler
+            .tracker
+            .get_execution("old-running")
+            .await
+            .is_some());
+
+        let mut remaining: Vec<String> = scheduler
+            .persistence
+            .list_workflows(None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|w| w.id)
+            .collect();
+        remaining.sort();
+        assert_eq!(remaining, vec!["old-running", "recent"]);
+        assert_eq!(scheduler.purged_workflows(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_apply_retention_per_type() {
+        let scheduler = Scheduler::new(L0MemoryStore::new());
+        let hours = chrono::Duration::hours;
+        save(&scheduler, "order-3h", "order", completed(), hours(3)).await;
+        save(&scheduler, "report-3h", "report", completed(), hours(3)).await;
+        save(&scheduler, "report-30h", "report", completed(), hours(30)).await;
+        save(&scheduler, "order-running", "order", running(), hours(30)).await;
+
+        let policy = RetentionPolicy {
+            interval: Duration::from_secs(60),
+            max_age: Some(Duration::from_secs(24 * 3600)),
+            per_type: HashMap::from([("order".to_string(), Duration::from_secs(3600))]),
+        };
+
+        assert_eq!(scheduler.apply_retention(&policy).await.unwrap(), 2);
+
+        let mut remaining: Vec<String> = scheduler
+            .persistence
+            .list_workflows(None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|w| w.id)
+            .collect();
+        remaining.sort();
+        assert_eq!(remaining, vec!["order-running", "report-3h"]);
+    }
+}
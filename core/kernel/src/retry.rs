@@ -0,0 +1,228 @@
+//! 失败 workflow 的重试
+//!
+//! 失败的 workflow 可以从失败的 step 重新开始，不必重新启动一个新的 workflow：
+//! 已完成 step 的结果保留，失败 step 的执行记录重置为等待分发（保留尝试次数），
+//! workflow 回到 Running，调度器只分发尚未完成的 step。
+//! worker 上报 step 开始时，`StepStarted` 事件携带递增后的尝试次数。
+
+use std::fmt;
+
+use crate::persistence::Persistence;
+use crate::scheduler::Scheduler;
+use crate::state_machine::InvalidTransition;
+
+/// 重试 workflow 的错误
+#[derive(Debug)]
+pub enum RetryError {
+    /// workflow 不存在
+    WorkflowNotFound(String),
+    /// workflow 不处于失败状态，无法重试
+    NotFailed(String, InvalidTransition),
+    /// 持久化层错误
+    Persistence(anyhow::Error),
+}
+
+impl fmt::Display for RetryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RetryError::WorkflowNotFound(workflow_id) => {
+                write!(f, "Workflow not found: {}", workflow_id)
+            }
+            RetryError::NotFailed(workflow_id, transition) => write!(
+                f,
+                "Workflow {} is {} and cannot be retried: only FAILED workflows can be retried",
+                workflow_id, transition.from
+            ),
+            RetryError::Persistence(e) => write!(f, "Persistence error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RetryError {}
+
+impl From<anyhow::Error> for RetryError {
+    fn from(e: anyhow::Error) -> Self {
+        RetryError::Persistence(e)
+    }
+}
+
+impl<P: Persistence> Scheduler<P> {
+    /// 从失败的 step 重新开始失败的 workflow，返回执行记录被重置的 step
+    ///
+    /// 执行记录已被追踪器淘汰时重新开始追踪，返回空列表；尚未完成的 step 仍会重新分发。
+    pub async fn retry_workflow(&self, workflow_id: &str) -> Result<Vec<String>, RetryError> {
+        // 与 step 完成推进 workflow 互斥，避免读到重试前的状态
+        let _guard = self.advance_lock.lock().await;
+        let workflow = self
+            .persistence
+            .get_workflow(workflow_id)
+            .await?
+            .ok_or_else(|| RetryError::WorkflowNotFound(workflow_id.to_string()))?;
+        let running = workflow
+            .state
+            .try_retry()
+            .map_err(|e| RetryError::NotFailed(workflow_id.to_string(), e))?;
+        self.persistence
+            .update_workflow_state(workflow_id, running)
+            .await?;
+
+        let steps = self
+            .tracker
+            .workflow_retried(workflow_id, &workflow.workflow_type)
+            .await;
+        tracing::info!(
+            workflow_id,
+            workflow_type = %workflow.workflow_type,
+            steps = ?steps,
+            "workflow retried"
+        );
+        self.notify_workflow_changed(workflow_id);
+        Ok(steps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::broadcaster::{EventPayload, EventType};
+    use crate::definition::{StepDefinition, WorkflowDefinition};
+    use crate::persistence::l0_memory::L0MemoryStore;
+    use crate::state_machine::{Workflow, WorkflowState};
+    use crate::task::RetryPolicy;
+    use crate::tracker::StepExecutionStatus;
+
+    async fn failed_scheduler() -> Scheduler<L0MemoryStore> {
+        let store = L0MemoryStore::new();
+        let mut workflow = Workflow::new("wf-1".to_string(), "order".to_string(), vec![]);
+        workflow.state = workflow.state.try_start().unwrap();
+        store.save_workflow(&workflow).await.unwrap();
+
+        let scheduler = Scheduler::new(store);
+        let no_retries = RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        };
+        scheduler
+            .register_definition(WorkflowDefinition::new(
+                "order",
+                vec![
+                    StepDefinition::new("reserve"),
+                    StepDefinition::new("charge")
+                        .depends_on(["reserve"])
+                        .with_retry(no_retries),
+                    StepDefinition::new("ship").depends_on(["charge"]),
+                ],
+            ))
+            .await
+            .unwrap();
+        scheduler.workflow_started(&workflow).await;
+        scheduler
+            .register_worker(
+                "worker-1".to_string(),
+                "shop".to_string(),
+                "default".to_string(),
+                vec!["order".to_string()],
+                vec![],
+            )
+            .await;
+
+        for step in ["reserve", "charge"] {
+            let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+            assert_eq!(tasks.len(), 1);
+            assert_eq!(tasks[0].step_name, step);
+            let lifecycle = scheduler.lifecycle();
+            lifecycle.step_started("wf-1", step, vec![]).await.unwrap();
+            if step == "reserve" {
+                lifecycle
+                    .complete_task(&tasks[0].task_id, Some("worker-1"), b"{}".to_vec())
+                    .await
+                    .unwrap();
+            } else {
+                lifecycle
+                    .fail_task(&tasks[0].task_id, Some("worker-1"), "declined".to_string())
+                    .await
+                    .unwrap();
+            }
+        }
+        scheduler
+    }
+
+    #[tokio::test]
+    async fn test_retry_resumes_from_failed_step() {
+        let scheduler = failed_scheduler().await;
+        let workflow = scheduler.persistence.get_workflow("wf-1").await.unwrap();
+        assert!(workflow.unwrap().is_failed());
+        assert!(scheduler
+            .poll_tasks("worker-1", 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let steps = scheduler.retry_workflow("wf-1").await.unwrap();
+        assert_eq!(steps, vec!["charge".to_string()]);
+        let workflow = scheduler
+            .persistence
+            .get_workflow("wf-1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(workflow.state, WorkflowState::Running { .. }));
+        assert!(workflow.completed_steps.contains_key("reserve"));
+        let execution = scheduler.tracker.get_execution("wf-1").await.unwrap();
+        assert!(execution.completed_at.is_none());
+        let charge = &execution.step_executions["charge"];
+        assert_eq!(charge.status, StepExecutionStatus::Pending);
+        assert_eq!(charge.attempt, 2);
+
+        // 只重新分发失败的 step
+        let mut events = scheduler.broadcaster.subscribe();
+        let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].step_name, "charge");
+        scheduler
+            .lifecycle()
+            .step_started("wf-1", "charge", vec![])
+            .await
+            .unwrap();
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.event_type, EventType::StepStarted);
+        match event.payload {
+            EventPayload::StepStarted(payload) => assert_eq!(payload.attempt, 2),
+            payload => panic!("unexpected payload {:?}", payload),
+        }
+
+        scheduler
+            .lifecycle()
+            .complete_task(&tasks[0].task_id, Some("worker-1"), b"{}".to_vec())
+            .await
+            .unwrap();
+        let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(tasks[0].step_name, "ship");
+    }
+
+    #[tokio::test]
+    async fn test_retry_requires_failed_workflow() {
+        let scheduler = failed_scheduler().await;
+        scheduler.retry_workflow("wf-1").await.unwrap();
+        assert!(matches!(
+            scheduler.retry_workflow("wf-1").await,
+            Err(RetryError::NotFailed(_, transition)) if transition.from == "RUNNING"
+        ));
+        assert!(matches!(
+            scheduler.retry_workflow("missing").await,
+            Err(RetryError::WorkflowNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_retry_without_execution_record_restarts_tracking() {
+        let scheduler = failed_scheduler().await;
+        scheduler.tracker.remove("wf-1").await;
+
+        assert!(scheduler.retry_workflow("wf-1").await.unwrap().is_empty());
+        assert!(scheduler.tracker.get_execution("wf-1").await.is_some());
+        let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].step_name, "charge");
+    }
+}
@@ -0,0 +1,468 @@
+//! 按 cron 表达式定时启动 workflow
+//!
+//! 调度保存在持久化层，服务器重启后继续生效。后台调度任务每秒检查一次，为到达触发时间的调度启动
+//! workflow；停机期间错过的触发时间只补发追赶窗口
+//! （[`SchedulerConfig::schedule_catchup_window`](crate::scheduler::SchedulerConfig::schedule_catchup_window)）
+//! 之内的，更早的直接跳过。每次触发启动的 workflow id 由调度 id 和触发时间决定，
+//! 同一个触发时间被重复处理时不会启动第二个 workflow。
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+
+use crate::cancellation::CancelError;
+use crate::persistence::Persistence;
+use crate::scheduler::{Scheduler, StartOptions};
+
+/// 默认追赶窗口：错过的触发时间在该时长之内时仍然补发
+pub const DEFAULT_SCHEDULE_CATCHUP_WINDOW: Duration = Duration::from_secs(60);
+
+/// 调度任务的检查间隔，也是触发时间的精度
+pub const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 上一次触发的 workflow 仍在运行时如何处理新的触发
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlapPolicy {
+    /// 跳过这次触发
+    #[default]
+    Skip,
+    /// 照常启动，多个 workflow 同时运行
+    Allow,
+    /// 取消上一次的 workflow 后启动
+    CancelPrevious,
+}
+
+impl OverlapPolicy {
+    pub const ALL: [OverlapPolicy; 3] = [
+        OverlapPolicy::Skip,
+        OverlapPolicy::Allow,
+        OverlapPolicy::CancelPrevious,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OverlapPolicy::Skip => "skip",
+            OverlapPolicy::Allow => "allow",
+            OverlapPolicy::CancelPrevious => "cancel_previous",
+        }
+    }
+}
+
+impl FromStr for OverlapPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized = s.replace('-', "_");
+        OverlapPolicy::ALL
+            .into_iter()
+            .find(|policy| policy.as_str().eq_ignore_ascii_case(&normalized))
+            .ok_or_else(|| {
+                format!(
+                    "Unknown overlap policy '{}': expected skip, allow or cancel_previous",
+                    s
+                )
+            })
+    }
+}
+
+/// 调度的内容：何时启动哪种 workflow
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleSpec {
+    /// cron 表达式，`秒 分 时 日 月 周 [年]`（UTC）；五段的标准写法在第 0 秒触发
+    pub cron: String,
+    pub workflow_type: String,
+    #[serde(default)]
+    pub input: Vec<u8>,
+    #[serde(default)]
+    pub overlap_policy: OverlapPolicy,
+}
+
+/// 已注册的调度
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Schedule {
+    pub id: String,
+    #[serde(flatten)]
+    pub spec: ScheduleSpec,
+    pub created_at: DateTime<Utc>,
+    /// 最近处理过的触发时间，包括按重叠策略跳过的
+    #[serde(default)]
+    pub last_fired_at: Option<DateTime<Utc>>,
+    /// 最近一次触发启动的 workflow
+    #[serde(default)]
+    pub last_workflow_id: Option<String>,
+}
+
+impl Schedule {
+    /// `after` 之后的下一个触发时间
+    pub fn next_fire_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        parse_cron(&self.spec.cron).ok()?.after(&after).next()
+    }
+
+    /// 某个触发时间启动的 workflow id
+    pub fn workflow_id_at(&self, fire_at: DateTime<Utc>) -> String {
+        format!("{}-{}", self.id, fire_at.timestamp())
+    }
+}
+
+/// 解析 cron 表达式，五段的标准写法补上第 0 秒
+pub fn parse_cron(expression: &str) -> Result<cron::Schedule, String> {
+    let expression = expression.trim();
+    let expression = if expression.split_whitespace().count() == 5 {
+        format!("0 {}", expression)
+    } else {
+        expression.to_string()
+    };
+    cron::Schedule::from_str(&expression).map_err(|e| e.to_string())
+}
+
+/// 管理调度的错误
+#[derive(Debug)]
+pub enum ScheduleError {
+    /// cron 表达式无法解析
+    InvalidCron { cron: String, reason: String },
+    /// 不接受的 workflow 类型
+    UnknownWorkflowType(String),
+    /// 调度不存在
+    NotFound(String),
+    /// 持久化层错误
+    Persistence(anyhow::Error),
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScheduleError::InvalidCron { cron, reason } => {
+                write!(f, "Invalid cron expression '{}': {}", cron, reason)
+            }
+            ScheduleError::UnknownWorkflowType(workflow_type) => {
+                write!(f, "Unknown workflow type '{}'", workflow_type)
+            }
+            ScheduleError::NotFound(id) => write!(f, "Schedule not found: {}", id),
+            ScheduleError::Persistence(e) => write!(f, "Persistence error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ScheduleError {}
+
+impl From<anyhow::Error> for ScheduleError {
+    fn from(e: anyhow::Error) -> Self {
+        ScheduleError::Persistence(e)
+    }
+}
+
+impl<P: Persistence> Scheduler<P> {
+    /// 注册调度，从现在起按 cron 表达式触发
+    pub async fn create_schedule(&self, spec: ScheduleSpec) -> Result<Schedule, ScheduleError> {
+        parse_cron(&spec.cron).map_err(|reason| ScheduleError::InvalidCron {
+            cron: spec.cron.clone(),
+            reason,
+        })?;
+        if !self.accepts_workflow_type(&spec.workflow_type).await {
+            return Err(ScheduleError::UnknownWorkflowType(spec.workflow_type));
+        }
+
+        let schedule = Schedule {
+            id: uuid::Uuid::new_v4().to_string(),
+            spec,
+            created_at: Utc::now(),
+            last_fired_at: None,
+            last_workflow_id: None,
+        };
+        self.persistence.save_schedule(&schedule).await?;
+        tracing::info!(schedule_id = %schedule.id, cron = %schedule.spec.cron, "schedule created");
+        Ok(schedule)
+    }
+
+    /// 全部调度，按创建时间排序
+    pub async fn list_schedules(&self) -> anyhow::Result<Vec<Schedule>> {
+        let mut schedules = self.persistence.list_schedules().await?;
+        schedules.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+        Ok(schedules)
+    }
+
+    /// 删除调度，已经启动的 workflow 不受影响
+    pub async fn delete_schedule(&self, id: &str) -> Result<(), ScheduleError> {
+        if !self.persistence.delete_schedule(id).await? {
+            return Err(ScheduleError::NotFound(id.to_string()));
+        }
+        tracing::info!(schedule_id = %id, "schedule deleted");
+        Ok(())
+    }
+
+    /// 为到达触发时间的调度启动 workflow，返回启动的数量
+    ///
+    /// 早于追赶窗口的触发时间直接跳过；窗口内错过的每个触发时间各按重叠策略处理一次。
+    pub async fn process_schedules(&self) -> anyhow::Result<usize> {
+        let now = Utc::now();
+        let earliest = chrono::Duration::from_std(self.config().schedule_catchup_window)
+            .ok()
+            .and_then(|window| now.checked_sub_signed(window))
+            .unwrap_or(DateTime::<Utc>::MIN_UTC);
+
+        let mut started = 0;
+        for mut schedule in self.persistence.list_schedules().await? {
+            let cron = match parse_cron(&schedule.spec.cron) {
+                Ok(cron) => cron,
+                Err(e) => {
+                    tracing::warn!(schedule_id = %schedule.id, "invalid cron expression: {}", e);
+                    continue;
+                }
+            };
+            let since = schedule.last_fired_at.unwrap_or(schedule.created_at);
+            let due: Vec<DateTime<Utc>> = cron
+                .after(&since.max(earliest))
+                .take_while(|fire_at| *fire_at <= now)
+                .collect();
+            if due.is_empty() {
+                continue;
+            }
+
+            for fire_at in due {
+                match self.fire_schedule(&mut schedule, fire_at).await {
+                    Ok(true) => started += 1,
+                    Ok(false) => {}
+                    // 保留已处理的触发时间，其余的下次再处理
+                    Err(e) => {
+                        tracing::warn!(schedule_id = %schedule.id, "failed to fire schedule: {}", e);
+                        break;
+                    }
+                }
+                schedule.last_fired_at = Some(fire_at);
+            }
+            // 触发期间被删除的调度不再写回
+            match self.persistence.update_schedule(&schedule).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    tracing::info!(schedule_id = %schedule.id, "schedule deleted while firing");
+                }
+                Err(e) => {
+                    tracing::warn!(schedule_id = %schedule.id, "failed to save schedule: {}", e);
+                }
+            }
+        }
+        Ok(started)
+    }
+
+    /// 按重叠策略处理一个触发时间，返回是否启动了 workflow
+    async fn fire_schedule(
+        &self,
+        schedule: &mut Schedule,
+        fire_at: DateTime<Utc>,
+    ) -> anyhow::Result<bool> {
+        let previous = match &schedule.last_workflow_id {
+            Some(workflow_id) => self
+                .persistence
+                .get_workflow(workflow_id)
+                .await?
+                .filter(|workflow| !workflow.state.is_terminal()),
+            None => None,
+        };
+        if let Some(previous) = previous {
+            match schedule.spec.overlap_policy {
+                OverlapPolicy::Skip => {
+                    tracing::info!(
+                        schedule_id = %schedule.id,
+                        workflow_id = %previous.id,
+                        "previous scheduled workflow still running, skipping fire"
+                    );
+                    return Ok(false);
+                }
+                OverlapPolicy::Allow => {}
+                OverlapPolicy::CancelPrevious => {
+                    if let Err(CancelError::Persistence(e)) =
+                        self.cancel_workflow(&previous.id).await
+                    {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+
+        let workflow_id = schedule.workflow_id_at(fire_at);
+        match self
+            .start_workflow(
+                Some(workflow_id.clone()),
+                schedule.spec.workflow_type.clone(),
+                schedule.spec.input.clone(),
+                StartOptions::default(),
+            )
+            .await
+        {
+            Ok(started) => {
+                if !started.already_exists {
+                    tracing::info!(
+                        schedule_id = %schedule.id,
+                        %workflow_id,
+                        "scheduled workflow started"
+                    );
+                }
+                schedule.last_workflow_id = Some(workflow_id);
+                Ok(!started.already_exists)
+            }
+            Err(e) => {
+                tracing::warn!(
+                    schedule_id = %schedule.id,
+                    %workflow_id,
+                    "failed to start scheduled workflow: {}",
+                    e
+                );
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// 启动后台调度任务，每 [`SCHEDULE_CHECK_INTERVAL`] 检查一次
+pub fn spawn_schedule_task<P: Persistence + 'static>(
+    scheduler: Arc<Scheduler<P>>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SCHEDULE_CHECK_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = scheduler.stopped() => break,
+            }
+            match scheduler.process_schedules().await {
+                Ok(0) => {}
+                Ok(started) => tracing::info!(started, "scheduled workflows started"),
+                Err(e) => tracing::warn!("schedule processing failed: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::l0_memory::L0MemoryStore;
+    use crate::scheduler::SchedulerConfig;
+    use crate::state_machine::WorkflowState;
+
+    fn every_second(overlap_policy: OverlapPolicy) -> ScheduleSpec {
+        ScheduleSpec {
+            cron: "* * * * * *".to_string(),
+            workflow_type: "report".to_string(),
+            input: b"{}".to_vec(),
+            overlap_policy,
+        }
+    }
+
+    /// 假装调度在 `secs` 秒之前创建
+    async fn backdate(scheduler: &Scheduler<Arc<L0MemoryStore>>, schedule: &Schedule, secs: i64) {
+        let mut schedule = schedule.clone();
+        schedule.created_at = Utc::now() - chrono::Duration::seconds(secs);
+        scheduler
+            .persistence
+            .save_schedule(&schedule)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_schedule_starts_workflows_and_survives_reprocessing() {
+        let scheduler = Scheduler::new(Arc::new(L0MemoryStore::new()));
+        assert!(matches!(
+            scheduler
+                .create_schedule(ScheduleSpec {
+                    cron: "every minute".to_string(),
+                    ..every_second(OverlapPolicy::Allow)
+                })
+                .await,
+            Err(ScheduleError::InvalidCron { .. })
+        ));
+        // 五段的标准写法同样接受
+        assert!(parse_cron("*/5 * * * *").is_ok());
+
+        let schedule = scheduler
+            .create_schedule(every_second(OverlapPolicy::Allow))
+            .await
+            .unwrap();
+        backdate(&scheduler, &schedule, 3).await;
+
+        let started = scheduler.process_schedules().await.unwrap();
+        assert!((2..=4).contains(&started), "{}", started);
+        let workflows = scheduler
+            .persistence
+            .list_workflows(Some("report"))
+            .await
+            .unwrap();
+        assert_eq!(workflows.len(), started);
+        assert!(workflows.iter().all(|w| w.id.starts_with(&schedule.id)));
+
+        let mut saved = scheduler.list_schedules().await.unwrap().remove(0);
+        let last_fired_at = saved.last_fired_at.unwrap();
+        assert_eq!(
+            saved.last_workflow_id,
+            Some(schedule.workflow_id_at(last_fired_at))
+        );
+
+        // 保存进度之前崩溃：重新处理同样的触发时间不会重复启动
+        saved.last_fired_at = None;
+        scheduler.persistence.save_schedule(&saved).await.unwrap();
+        let restarted = scheduler.process_schedules().await.unwrap();
+        let again = scheduler
+            .persistence
+            .list_workflows(Some("report"))
+            .await
+            .unwrap();
+        assert_eq!(again.len(), started + restarted);
+
+        scheduler.delete_schedule(&schedule.id).await.unwrap();
+        assert!(matches!(
+            scheduler.delete_schedule(&schedule.id).await,
+            Err(ScheduleError::NotFound(_))
+        ));
+        // 触发期间被删除：写回进度不会让调度重新出现
+        assert!(!scheduler.persistence.update_schedule(&saved).await.unwrap());
+        assert!(scheduler.list_schedules().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_overlap_policies() {
+        let scheduler = Scheduler::new(Arc::new(L0MemoryStore::new()));
+        let skip = scheduler
+            .create_schedule(every_second(OverlapPolicy::Skip))
+            .await
+            .unwrap();
+        backdate(&scheduler, &skip, 3).await;
+
+        // 没有 worker 完成 workflow，第一个仍在运行时后续的触发全部跳过
+        assert_eq!(scheduler.process_schedules().await.unwrap(), 1);
+
+        let mut cancel = scheduler.list_schedules().await.unwrap().remove(0);
+        let first = cancel.last_workflow_id.clone().unwrap();
+        cancel.spec.overlap_policy = OverlapPolicy::CancelPrevious;
+        cancel.last_fired_at = Some(Utc::now() - chrono::Duration::seconds(2));
+        scheduler.persistence.save_schedule(&cancel).await.unwrap();
+
+        assert!(scheduler.process_schedules().await.unwrap() >= 1);
+        let first = scheduler.persistence.get_workflow(&first).await.unwrap();
+        assert_eq!(first.unwrap().state, WorkflowState::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn test_missed_fires_outside_catchup_window_are_skipped() {
+        let config = SchedulerConfig {
+            schedule_catchup_window: Duration::from_secs(5),
+            ..SchedulerConfig::default()
+        };
+        let scheduler = Scheduler::with_config(Arc::new(L0MemoryStore::new()), config);
+        let schedule = scheduler
+            .create_schedule(every_second(OverlapPolicy::Allow))
+            .await
+            .unwrap();
+        // 停机一小时：只补发最近 5 秒的触发时间
+        backdate(&scheduler, &schedule, 3600).await;
+
+        let started = scheduler.process_schedules().await.unwrap();
+        assert!((4..=6).contains(&started), "{}", started);
+    }
+}
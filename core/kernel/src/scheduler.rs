@@ -1,39 +1,195 @@
-use crate::broadcaster::EventBroadcaster;
-use crate::persistence::Persistence;
+use crate::api::rate_limit::{RateLimitConfig, RateLimiter};
+use crate::broadcaster::{
+    EventBroadcaster, DEFAULT_BROADCAST_CAPACITY, DEFAULT_EVENT_JOURNAL_CAPACITY,
+};
+use crate::cancellation::TaskCancellation;
+use crate::definition::{DefinitionError, DefinitionProblem, StepDefinition, WorkflowDefinition};
+use crate::memo::validate_memo;
+use crate::payload::PayloadTooLarge;
+use crate::persistence::{ListOptions, Persistence, StateKind};
+use crate::ready_queue::{QueuedWorkflow, ReadyQueue, Refresh, StaleWorkflows};
+use crate::retention::RetentionPolicy;
+use crate::schema::{self, SchemaTarget, SchemaViolation};
+use crate::server_info::ServerLimits;
 use crate::service_registry::ServiceRegistry;
-use crate::state_machine::{Workflow, WorkflowState};
-use crate::task::{ResourceType, Task};
+use crate::session::SessionStore;
+use crate::shutdown::{ShutdownState, DEFAULT_SHUTDOWN_GRACE};
+use crate::signal::Signal;
+use crate::state_machine::{Priority, Workflow, WorkflowState};
+use crate::step_cache::{
+    CachedResult, CachedSteps, DEFAULT_STEP_CACHE_MAX_ENTRIES, DEFAULT_STEP_CACHE_TTL,
+};
+use crate::step_timeout::{missed_heartbeat_error, timeout_error, StepDeadline};
+use crate::sticky::{StickyRoutes, DEFAULT_STICKY_TIMEOUT};
+use crate::task::{ResourceType, RetryPolicy, Task, TaskId};
+use crate::telemetry::{task_span, workflow_span};
 use crate::tracker::WorkflowTracker;
-use std::collections::HashMap;
-use tokio::sync::{Mutex, RwLock};
-use tokio::time::Duration;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::{broadcast, watch, Mutex, RwLock};
+use tokio::time::{Duration, Instant};
 
-pub struct Scheduler<P: Persistence> {
-    pub persistence: P,
-    pub service_registry: ServiceRegistry,
-    pub tracker: WorkflowTracker,      // 新增：执行追踪器
-    pub broadcaster: EventBroadcaster, // 新增：事件广播器
-    active_workers: RwLock<HashMap<String, WorkerInfo>>,
-    #[allow(dead_code)]
-    running_tasks: Mutex<HashMap<String, Task>>,
-    poll_interval: Duration,
+/// 默认 task 租约时长：超过该时间没有心跳，task 会被重新分发
+pub const DEFAULT_TASK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 默认 worker 存活超时：超过该时间没有心跳或轮询，worker 会被移除
+pub const DEFAULT_WORKER_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// 租约过期时 StepFailed 事件中的错误信息
+pub const LEASE_EXPIRED_ERROR: &str = "lease expired";
+
+/// 分发路径上的持久化层读取失败时的最多尝试次数（含第一次）
+pub const PERSISTENCE_READ_ATTEMPTS: u32 = 3;
+
+/// 持久化层读取第一次重试前的等待时间，之后每次翻倍
+pub const PERSISTENCE_RETRY_BACKOFF: Duration = Duration::from_millis(10);
+
+/// 默认 workflow 输入上限
+pub const DEFAULT_MAX_INPUT_BYTES: usize = 1024 * 1024;
+
+/// 默认 step 输出上限
+pub const DEFAULT_MAX_OUTPUT_BYTES: usize = 1024 * 1024;
+
+/// 默认单次分发给一个 task 流的最多 task 数量
+pub const DEFAULT_POLL_TASKS_LIMIT: usize = 10;
+
+/// 默认允许连续错过的进度心跳次数
+pub const DEFAULT_MAX_MISSED_HEARTBEATS: u32 = 3;
+
+/// 默认拒绝冷却期：worker 拒绝 task 且不要求重新收到时，该时长内不再分发给它
+pub const DEFAULT_REJECT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// 调度器配置
+///
+/// 默认值与各项的 `DEFAULT_*` 常量一致。负载在内核中会被复制到 task、事件和执行追踪记录里，
+/// 限制大小避免单个请求占满内存。
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchedulerConfig {
+    /// task 租约时长：超过该时间没有心跳，task 会被重新分发
+    pub task_timeout: Duration,
+    /// 上报过进度心跳的 step 连续错过该次数的心跳（按心跳间隔计）后按超时失败
+    pub max_missed_heartbeats: u32,
+    /// worker 存活超时：超过该时间没有心跳或轮询，worker 会被移除
+    pub worker_timeout: Duration,
+    /// 粘性超时：粘性 worker 超过该时间没有领取就绪的 step，改由其他 worker 领取
+    pub sticky_timeout: Duration,
+    /// 拒绝冷却期：worker 拒绝 task 且不要求重新收到时，该时长内不再把该 task 分发给它
+    pub reject_cooldown: Duration,
+    /// 停机时等待已分发 step 完成的宽限期
+    pub shutdown_grace: Duration,
+    /// 空闲的 task 流重新检查的最长间隔，`None` 时为 worker 存活超时的 1/3
+    pub poll_interval: Option<Duration>,
+    /// 单次分发给一个 task 流的最多 task 数量（gRPC 请求未指定时同样使用）
+    pub poll_tasks_limit: usize,
+    /// 事件广播通道的容量，订阅者落后超过该数量时丢失事件
+    pub broadcast_capacity: usize,
+    /// 事件日志保留的最近事件数量
+    pub event_journal_capacity: usize,
+    /// 资源和定义都没有指定时使用的重试策略
+    pub default_retry: RetryPolicy,
+    /// workflow 输入的最大字节数，超过时拒绝启动
+    pub max_input_bytes: usize,
+    /// step 输出的最大字节数，超过时 step 失败
+    pub max_output_bytes: usize,
+    /// 终态 workflow 的保留策略，`None` 时不自动清理
+    pub retention: Option<RetentionPolicy>,
+    /// cron 调度的追赶窗口：停机期间错过的触发时间在该时长之内时补发，更早的跳过
+    pub schedule_catchup_window: Duration,
+    /// REST API 的限流和并发限制，默认不限制
+    pub rate_limits: RateLimitConfig,
+    /// 允许跨域调用 REST API 的 origin，`*` 允许所有 origin，`:*` 结尾时允许该主机的任意端口
+    pub cors_origins: Vec<String>,
+    /// step 结果缓存的有效期，见 [`crate::step_cache`]
+    pub step_cache_ttl: Duration,
+    /// 最多缓存的 step 结果数量，为 0 时不缓存
+    pub step_cache_max_entries: usize,
 }
 
-impl<P: Persistence + Clone> Clone for Scheduler<P> {
-    fn clone(&self) -> Self {
-        Scheduler {
-            persistence: self.persistence.clone(),
-            service_registry: ServiceRegistry::new(),
-            tracker: self.tracker.clone(),
-            broadcaster: self.broadcaster.clone(),
-            active_workers: RwLock::new(HashMap::new()),
-            running_tasks: Mutex::new(HashMap::new()),
-            poll_interval: self.poll_interval,
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        SchedulerConfig {
+            task_timeout: DEFAULT_TASK_TIMEOUT,
+            max_missed_heartbeats: DEFAULT_MAX_MISSED_HEARTBEATS,
+            worker_timeout: DEFAULT_WORKER_TIMEOUT,
+            sticky_timeout: DEFAULT_STICKY_TIMEOUT,
+            reject_cooldown: DEFAULT_REJECT_COOLDOWN,
+            shutdown_grace: DEFAULT_SHUTDOWN_GRACE,
+            poll_interval: None,
+            poll_tasks_limit: DEFAULT_POLL_TASKS_LIMIT,
+            broadcast_capacity: DEFAULT_BROADCAST_CAPACITY,
+            event_journal_capacity: DEFAULT_EVENT_JOURNAL_CAPACITY,
+            default_retry: RetryPolicy::default(),
+            max_input_bytes: DEFAULT_MAX_INPUT_BYTES,
+            max_output_bytes: DEFAULT_MAX_OUTPUT_BYTES,
+            retention: None,
+            schedule_catchup_window: crate::schedule::DEFAULT_SCHEDULE_CATCHUP_WINDOW,
+            rate_limits: RateLimitConfig::default(),
+            cors_origins: crate::api::cors::default_cors_origins(),
+            step_cache_ttl: DEFAULT_STEP_CACHE_TTL,
+            step_cache_max_entries: DEFAULT_STEP_CACHE_MAX_ENTRIES,
         }
     }
 }
 
+/// 调度器
+///
+/// 运行状态都放在 `Arc` 中，克隆得到的是指向同一份状态的句柄：
+/// 通过任一克隆注册的 worker、领取的 task 和注册的定义对其他克隆同样可见。
 #[derive(Clone)]
+pub struct Scheduler<P: Persistence> {
+    pub persistence: P,
+    pub service_registry: Arc<ServiceRegistry>,
+    /// REST worker 的会话令牌
+    pub sessions: Arc<SessionStore>,
+    /// REST API 的限流状态，按 `config.rate_limits` 创建
+    pub rate_limiter: Arc<RateLimiter>,
+    pub tracker: WorkflowTracker,      // 新增：执行追踪器
+    pub broadcaster: EventBroadcaster, // 新增：事件广播器
+    active_workers: Arc<RwLock<HashMap<String, WorkerInfo>>>,
+    running_tasks: Arc<Mutex<HashMap<TaskId, TaskLease>>>,
+    /// 拒绝 task 的 worker 及其冷却结束时间，冷却期内不再把该 task 分发给这些 worker
+    task_exclusions: Arc<std::sync::Mutex<HashMap<TaskId, HashMap<String, Instant>>>>,
+    /// 服务器启动以来各 worker 拒绝的 task 数量
+    task_rejections: Arc<std::sync::Mutex<HashMap<String, u64>>>,
+    /// 租约过期或等待重试、尚未重新分发的 task
+    requeued_tasks: Arc<Mutex<HashMap<TaskId, RequeuedTask>>>,
+    /// 按资源名注册的重试策略
+    retry_policies: Arc<RwLock<HashMap<String, RetryPolicy>>>,
+    /// 按 workflow 类型注册的定义，每个类型按版本排列
+    definitions: Arc<RwLock<HashMap<String, BTreeMap<u32, WorkflowDefinition>>>>,
+    /// 运行中 workflow 的就绪 step，poll 从这里取 task 而不扫描持久化层
+    pub(crate) ready_queue: Arc<Mutex<ReadyQueue>>,
+    /// 状态发生变化、下一次 poll 前需要从持久化层重新读取的 workflow
+    pub(crate) stale_workflows: Arc<StaleWorkflows>,
+    /// 粘性路由 workflow 的 worker 分配
+    sticky_routes: Arc<StickyRoutes>,
+    /// 按 workflow 类型限制同时执行（已分发、尚未完成）的 task 数量
+    concurrency_limits: HashMap<String, usize>,
+    /// 串行化按定义推进 workflow 的读改写，避免并行 step 同时完成时丢失更新
+    pub(crate) advance_lock: Arc<Mutex<()>>,
+    /// 正在完成或失败的 task，同一 task 的并发上报只有一个生效
+    pub(crate) settling_tasks: Arc<std::sync::Mutex<HashSet<TaskId>>>,
+    /// 可能有新 task 可分发时递增，长连接的 poll 在此等待
+    task_ready: Arc<watch::Sender<u64>>,
+    /// 取消 workflow 时通知持有其 task 的 worker
+    task_cancelled: broadcast::Sender<TaskCancellation>,
+    /// 停机阶段
+    pub(crate) shutdown: Arc<watch::Sender<ShutdownState>>,
+    purged_workflows: Arc<AtomicU64>,
+    /// 服务器启动以来 step 结果缓存的命中和未命中次数
+    pub(crate) step_cache_hits: Arc<AtomicU64>,
+    pub(crate) step_cache_misses: Arc<AtomicU64>,
+    /// 就绪时命中缓存、等待以缓存结果完成的 step
+    pub(crate) cached_steps: Arc<CachedSteps>,
+    /// 是否已从持久化层恢复重启前的 step 截止时间
+    pub(crate) deadlines_recovered: Arc<AtomicBool>,
+    limits: ServerLimits,
+    config: SchedulerConfig,
+}
+
+#[derive(Debug, Clone)]
 pub struct WorkerInfo {
     pub id: String,
     pub service_name: String,
@@ -43,286 +199,3824 @@ pub struct WorkerInfo {
     pub last_seen: std::time::SystemTime,
 }
 
+/// 已分发 task 的租约
+struct TaskLease {
+    task: Task,
+    /// 领取该 task 的 worker
+    worker_id: String,
+    /// 分发时间
+    dispatched_at: std::time::SystemTime,
+    expires_at: Instant,
+    /// worker 是否已确认收到该 task
+    confirmed: bool,
+    /// 目标资源声明的执行超时，未声明时为 `None`
+    step_timeout: Option<Duration>,
+    /// step 执行截止时间，进度心跳会推迟
+    deadline: Option<Instant>,
+    /// 最近一次心跳的时间，收到第一次进度心跳之前为 `None`
+    last_heartbeat: Option<Instant>,
+    /// 可缓存 task 的缓存键，完成后结果写入缓存
+    cache_key: Option<String>,
+}
+
+/// 一次领取的结果
+struct ClaimedTasks {
+    tasks: Vec<Task>,
+    /// 输入不符合 schema 的 task
+    rejected: Vec<(Task, SchemaViolation)>,
+    /// 需要持久化的 step 截止时间
+    deadlines: Vec<StepDeadline>,
+    /// 沿用上一次分发记录的尝试、不再计尝试次数的 task
+    carried_attempts: HashSet<TaskId>,
+}
+
+/// 等待重新分发的 task
+struct RequeuedTask {
+    task: Task,
+    /// 最早可重新分发的时间（重试退避）
+    ready_at: Instant,
+    /// worker 没有执行就交还的 task：再次分发时沿用上一次分发记录的尝试，不再计一次尝试
+    carries_attempt: bool,
+}
+
+/// 租约的只读视图
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeaseInfo {
+    pub worker_id: String,
+    pub dispatched_at: std::time::SystemTime,
+    /// 租约是否仍然有效
+    pub active: bool,
+    /// worker 是否已确认收到该 task
+    pub confirmed: bool,
+}
+
+/// 启动 workflow 的选项
+#[derive(Debug, Clone, Default)]
+pub struct StartOptions {
+    pub priority: Priority,
+    /// 跳过资源 schema 校验，用于紧急情况
+    pub skip_schema_validation: bool,
+    /// 固定使用的定义版本，未指定时使用最新版本
+    pub version: Option<u32>,
+    /// 随 workflow 保存的备注，见 [`crate::memo`]
+    pub memo: HashMap<String, String>,
+}
+
+impl StartOptions {
+    pub fn with_priority(priority: Priority) -> Self {
+        StartOptions {
+            priority,
+            ..Default::default()
+        }
+    }
+}
+
+/// 批量启动中的一个 workflow，字段同 [`Scheduler::start_workflow`] 的参数
+#[derive(Debug, Clone)]
+pub struct WorkflowStart {
+    pub workflow_id: Option<String>,
+    pub workflow_type: String,
+    pub input: Vec<u8>,
+    pub options: StartOptions,
+}
+
+/// 启动 workflow 的结果
+#[derive(Debug, Clone)]
+pub struct StartedWorkflow {
+    pub workflow: Workflow,
+    /// 同一 id 的 workflow 已经存在，返回的是已有的 workflow
+    pub already_exists: bool,
+}
+
 impl<P: Persistence> Scheduler<P> {
     pub fn new(persistence: P) -> Self {
+        Self::with_config(persistence, SchedulerConfig::default())
+    }
+
+    /// 按配置创建调度器
+    pub fn with_config(persistence: P, config: SchedulerConfig) -> Self {
         Scheduler {
             persistence,
-            service_registry: ServiceRegistry::new(),
+            service_registry: Arc::new(ServiceRegistry::new()),
+            sessions: Arc::new(SessionStore::new()),
+            rate_limiter: Arc::new(RateLimiter::new(config.rate_limits.clone())),
             tracker: WorkflowTracker::new(),
-            broadcaster: EventBroadcaster::new(),
-            active_workers: RwLock::new(HashMap::new()),
-            running_tasks: Mutex::new(HashMap::new()),
-            poll_interval: Duration::from_millis(100),
+            broadcaster: EventBroadcaster::with_capacity(
+                config.broadcast_capacity,
+                config.event_journal_capacity,
+            ),
+            active_workers: Arc::new(RwLock::new(HashMap::new())),
+            running_tasks: Arc::new(Mutex::new(HashMap::new())),
+            task_exclusions: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            task_rejections: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            requeued_tasks: Arc::new(Mutex::new(HashMap::new())),
+            retry_policies: Arc::new(RwLock::new(HashMap::new())),
+            definitions: Arc::new(RwLock::new(HashMap::new())),
+            ready_queue: Arc::new(Mutex::new(ReadyQueue::new())),
+            stale_workflows: Arc::new(StaleWorkflows::new()),
+            sticky_routes: Arc::new(StickyRoutes::new()),
+            concurrency_limits: HashMap::new(),
+            advance_lock: Arc::new(Mutex::new(())),
+            settling_tasks: Arc::new(std::sync::Mutex::new(HashSet::new())),
+            task_ready: Arc::new(watch::channel(0).0),
+            task_cancelled: broadcast::channel(256).0,
+            shutdown: Arc::new(watch::channel(ShutdownState::Running).0),
+            purged_workflows: Arc::new(AtomicU64::new(0)),
+            step_cache_hits: Arc::new(AtomicU64::new(0)),
+            step_cache_misses: Arc::new(AtomicU64::new(0)),
+            cached_steps: Arc::new(CachedSteps::new()),
+            deadlines_recovered: Arc::new(AtomicBool::new(false)),
+            limits: ServerLimits::default(),
+            config,
         }
     }
 
-    pub async fn register_worker(
-        &self,
-        worker_id: String,
-        service_name: String,
-        group: String,
-        workflow_types: Vec<String>,
-        resources: Vec<(String, ResourceType)>,
-    ) {
-        let mut workers = self.active_workers.write().await;
-        workers.insert(
-            worker_id.clone(),
-            WorkerInfo {
-                id: worker_id,
-                service_name,
-                group,
-                workflow_types,
-                resources,
-                last_seen: std::time::SystemTime::now(),
-            },
-        );
+    /// 设置 task 租约时长
+    pub fn with_task_timeout(mut self, task_timeout: Duration) -> Self {
+        self.config.task_timeout = task_timeout;
+        self
     }
 
-    pub async fn poll_tasks(&self, worker_id: &str, max_tasks: usize) -> Vec<Task> {
-        let workers = self.active_workers.read().await;
-        if let Some(worker) = workers.get(worker_id) {
-            self.find_available_tasks(worker, max_tasks).await
-        } else {
-            Vec::new()
-        }
+    /// 设置 worker 存活超时
+    pub fn with_worker_timeout(mut self, worker_timeout: Duration) -> Self {
+        self.config.worker_timeout = worker_timeout;
+        self
     }
 
-    async fn find_available_tasks(&self, worker: &WorkerInfo, max_tasks: usize) -> Vec<Task> {
-        let mut tasks = Vec::new();
-        let workflows = self.persistence.list_workflows(None).await.unwrap();
+    /// worker 存活超时
+    pub fn worker_timeout(&self) -> Duration {
+        self.config.worker_timeout
+    }
 
-        for workflow in workflows {
-            if matches!(workflow.state, WorkflowState::Running { .. }) {
-                if let Some((step_name, target_service, target_resource, resource_type)) =
-                    self.find_next_step(&workflow).await
-                {
-                    // Check if this worker can handle this task
-                    if self.can_worker_handle_task(
-                        worker,
-                        &target_service,
-                        &target_resource,
-                        resource_type,
-                        &workflow.workflow_type,
-                    ) {
-                        let task = Task {
-                            task_id: format!("{}-{}", workflow.id, step_name),
-                            workflow_id: workflow.id.clone(),
-                            step_name: step_name.clone(),
-                            target_service: target_service.clone(),
-                            target_resource: target_resource.clone(),
-                            resource_type,
-                            input: workflow.input.clone(),
-                            retry: None,
-                            workflow_type: workflow.workflow_type.clone(),
-                        };
-                        tasks.push(task);
-                        if tasks.len() >= max_tasks {
-                            break;
-                        }
+    /// 空闲的 task 流重新检查的间隔，同时用作 worker 的保活周期
+    pub fn poll_interval(&self) -> Duration {
+        self.config
+            .poll_interval
+            .unwrap_or(self.config.worker_timeout / 3)
+            .max(Duration::from_millis(10))
+    }
+
+    /// 单次分发给一个 task 流的最多 task 数量
+    pub fn poll_tasks_limit(&self) -> usize {
+        self.config.poll_tasks_limit
+    }
+
+    /// 设置粘性超时：粘性 worker 超过该时间没有领取就绪的 step，改由其他 worker 领取
+    pub fn with_sticky_timeout(mut self, sticky_timeout: Duration) -> Self {
+        self.config.sticky_timeout = sticky_timeout;
+        self
+    }
+
+    /// 设置拒绝冷却期
+    pub fn with_reject_cooldown(mut self, reject_cooldown: Duration) -> Self {
+        self.config.reject_cooldown = reject_cooldown;
+        self
+    }
+
+    /// workflow 的粘性 worker，workflow 未启用粘性路由或尚未分发时返回 `None`
+    pub fn sticky_worker(&self, workflow_id: &str) -> Option<String> {
+        self.sticky_routes.worker(workflow_id)
+    }
+
+    /// 限制某个 workflow 类型同时执行的 task 数量，避免单个类型占满 worker
+    pub fn with_concurrency_limit(mut self, workflow_type: impl Into<String>, max: usize) -> Self {
+        self.concurrency_limits.insert(workflow_type.into(), max);
+        self
+    }
+
+    /// workflow 类型同时执行的 task 数量上限，未限制时返回 `None`
+    pub fn concurrency_limit(&self, workflow_type: &str) -> Option<usize> {
+        self.concurrency_limits.get(workflow_type).copied()
+    }
+
+    /// 设置停机时等待已分发 step 完成的宽限期
+    pub fn with_shutdown_grace(mut self, shutdown_grace: Duration) -> Self {
+        self.config.shutdown_grace = shutdown_grace;
+        self
+    }
+
+    /// 停机宽限期
+    pub fn shutdown_grace(&self) -> Duration {
+        self.config.shutdown_grace
+    }
+
+    /// 设置事件日志保留的最近事件数量
+    pub fn with_event_journal_capacity(mut self, capacity: usize) -> Self {
+        self.config.event_journal_capacity = capacity;
+        self.broadcaster =
+            EventBroadcaster::with_capacity(self.config.broadcast_capacity, capacity);
+        self
+    }
+
+    /// 使用指定的执行追踪器（例如写入存储的追踪器）
+    pub fn with_tracker(mut self, tracker: WorkflowTracker) -> Self {
+        self.tracker = tracker;
+        self
+    }
+
+    /// 设置请求限制
+    pub fn with_limits(mut self, limits: ServerLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// 当前请求限制
+    pub fn limits(&self) -> &ServerLimits {
+        &self.limits
+    }
+
+    /// 当前调度器配置
+    pub fn config(&self) -> &SchedulerConfig {
+        &self.config
+    }
+
+    /// 检查 workflow 输入是否超过上限
+    pub fn check_input_size(&self, input: &[u8]) -> Result<(), PayloadTooLarge> {
+        check_size("workflow input", input, self.config.max_input_bytes)
+    }
+
+    /// 检查 step 输出是否超过上限
+    pub fn check_output_size(&self, output: &[u8]) -> Result<(), PayloadTooLarge> {
+        check_size("step output", output, self.config.max_output_bytes)
+    }
+
+    /// worker 应发送心跳的间隔
+    pub fn heartbeat_interval(&self) -> Duration {
+        self.config.task_timeout / 3
+    }
+
+    /// 续约 task，返回该 task 是否处于租约中
+    ///
+    /// task 心跳同时视为持有该 task 的 worker 的心跳。
+    pub async fn extend_lease(&self, task_id: &TaskId) -> bool {
+        let worker_id = {
+            let mut leases = self.running_tasks.lock().await;
+            match leases.get_mut(task_id) {
+                Some(lease) => {
+                    let now = Instant::now();
+                    lease.expires_at = now + self.config.task_timeout;
+                    if lease.last_heartbeat.is_some() {
+                        lease.last_heartbeat = Some(now);
                     }
+                    lease.worker_id.clone()
                 }
+                None => return false,
             }
-        }
-
-        tasks
+        };
+        self.touch_worker(&worker_id).await;
+        true
     }
 
-    fn can_worker_handle_task(
-        &self,
-        worker: &WorkerInfo,
-        target_service: &Option<String>,
-        target_resource: &Option<String>,
-        resource_type: ResourceType,
-        workflow_type: &str,
-    ) -> bool {
-        // If no target service specified, check if worker supports this workflow type
-        if target_service.is_none() {
-            return worker.workflow_types.contains(&workflow_type.to_string())
-                || worker.resources.iter().any(|(name, rtype)| {
-                    rtype == &resource_type && target_resource.as_ref().is_none_or(|r| r == name)
-                });
+    /// 记录 step 的进度心跳，返回该 task 是否处于租约中
+    ///
+    /// 除续约外，把执行截止时间推迟到心跳之后的资源超时，并要求之后按心跳间隔继续上报：
+    /// 连续错过 `max_missed_heartbeats` 次心跳的 step 按超时失败。
+    pub async fn record_step_heartbeat(&self, task_id: &TaskId) -> bool {
+        let renewed = {
+            let mut leases = self.running_tasks.lock().await;
+            let Some(lease) = leases.get_mut(task_id) else {
+                return false;
+            };
+            let now = Instant::now();
+            lease.last_heartbeat = Some(now);
+            lease.step_timeout.map(|timeout| {
+                lease.deadline = now.checked_add(timeout);
+                StepDeadline::new(task_id, &lease.worker_id, timeout)
+            })
+        };
+        if let Some(deadline) = renewed {
+            if let Err(e) = self.persistence.save_step_deadline(&deadline).await {
+                tracing::warn!(%task_id, "failed to persist step deadline: {}", e);
+            }
         }
+        self.extend_lease(task_id).await
+    }
 
-        let target = target_service.as_ref().unwrap();
+    /// 连续错过允许次数的心跳后按超时处理的时长
+    pub fn missed_heartbeat_window(&self) -> Duration {
+        self.heartbeat_interval() * self.config.max_missed_heartbeats.max(1)
+    }
 
-        // Check if this worker is the target service
-        if worker.service_name == *target {
-            // Worker can handle its own resources
-            return true;
+    /// 记录 worker 心跳，返回该 worker 是否仍处于注册状态
+    pub async fn touch_worker(&self, worker_id: &str) -> bool {
+        match self.active_workers.write().await.get_mut(worker_id) {
+            Some(worker) => {
+                worker.last_seen = std::time::SystemTime::now();
+                self.service_registry
+                    .heartbeat(&worker.service_name, worker_id);
+                true
+            }
+            None => false,
         }
+    }
 
-        // Check if worker has matching resources
-        worker.resources.iter().any(|(name, rtype)| {
-            rtype == &resource_type && target_resource.as_ref().is_none_or(|r| r == name)
-        })
+    /// 校验会话令牌，返回令牌所属且仍处于注册状态的 worker
+    pub async fn authenticate_worker(&self, token: &str) -> Option<String> {
+        let worker_id = self.sessions.worker_id(token)?;
+        self.active_workers
+            .read()
+            .await
+            .contains_key(&worker_id)
+            .then_some(worker_id)
     }
 
-    async fn find_next_step(
-        &self,
-        workflow: &Workflow,
-    ) -> Option<(String, Option<String>, Option<String>, ResourceType)> {
-        match &workflow.state {
-            WorkflowState::Running { current_step } => {
-                if current_step.is_none() {
-                    Some(("start".to_string(), None, None, ResourceType::Step))
-                } else {
-                    None
+    /// worker id 是否属于仍处于注册状态或持有会话令牌的 worker
+    pub async fn is_worker_id_in_use(&self, worker_id: &str) -> bool {
+        self.active_workers.read().await.contains_key(worker_id)
+            || self.sessions.has_session(worker_id)
+    }
+
+    /// 移除超过存活超时没有心跳的 worker，返回被移除的 worker id
+    ///
+    /// 这些 worker 持有的 task 立即按租约过期处理，回到可分发队列，它们的会话令牌随之失效。
+    /// 被移除的 worker 对应的服务实例从服务注册表中注销，服务的最后一个实例注销时服务随之移除。
+    pub async fn evict_stale_workers(&self) -> Vec<String> {
+        let now = std::time::SystemTime::now();
+        let evicted: HashMap<String, String> = {
+            let mut workers = self.active_workers.write().await;
+            let stale: HashMap<String, String> = workers
+                .values()
+                .filter(|worker| {
+                    now.duration_since(worker.last_seen)
+                        .is_ok_and(|idle| idle > self.config.worker_timeout)
+                })
+                .map(|worker| (worker.id.clone(), worker.service_name.clone()))
+                .collect();
+            workers.retain(|worker_id, _| !stale.contains_key(worker_id));
+            self.tracker.live_stats().set_workers(workers.len());
+            stale
+        };
+        if evicted.is_empty() {
+            return Vec::new();
+        }
+        for (worker_id, service_name) in &evicted {
+            self.sessions.revoke(worker_id);
+            self.sticky_routes.evict_worker(worker_id);
+            if self.service_registry.unregister(service_name, worker_id)
+                && !self.service_registry.exists(service_name)
+            {
+                tracing::info!(service_name, "service unregistered: no instances left");
+            }
+        }
+
+        {
+            let expires_at = Instant::now();
+            let mut leases = self.running_tasks.lock().await;
+            for lease in leases.values_mut() {
+                if evicted.contains_key(&lease.worker_id) {
+                    lease.expires_at = expires_at;
                 }
             }
-            _ => None,
         }
+        self.expire_leases().await;
+
+        let mut evicted: Vec<String> = evicted.into_keys().collect();
+        evicted.sort();
+        evicted
     }
 
-    pub async fn complete_task(&self, task_id: &str, result: Vec<u8>) -> anyhow::Result<()> {
-        // 解析 task_id (格式: workflow_id-step_name)
-        // 注意: workflow_id 是 UUID，包含 '-'，所以我们从后往前找最后一个 '-'
-        let parts: Vec<&str> = task_id.rsplitn(2, '-').collect();
-        if parts.len() != 2 {
-            return Err(anyhow::anyhow!("Invalid task_id format: {}", task_id));
-        }
-        let step_name = parts[0];
-        let workflow_id = parts[1];
+    /// task 当前的租约，task 未分发或已完成时返回 `None`
+    pub async fn lease(&self, task_id: &TaskId) -> Option<LeaseInfo> {
+        let now = Instant::now();
+        self.running_tasks
+            .lock()
+            .await
+            .get(task_id)
+            .map(|lease| LeaseInfo {
+                worker_id: lease.worker_id.clone(),
+                dispatched_at: lease.dispatched_at,
+                active: lease.expires_at > now,
+                confirmed: lease.confirmed,
+            })
+    }
 
-        // 保存 step 结果到持久化层
-        self.persistence
-            .save_step_result(workflow_id, step_name, result.clone())
-            .await?;
+    /// 当前注册的 worker
+    pub async fn workers(&self) -> Vec<WorkerInfo> {
+        self.active_workers.read().await.values().cloned().collect()
+    }
 
-        // 获取 workflow 信息用于追踪和广播
-        if let Some(workflow) = self.persistence.get_workflow(workflow_id).await? {
-            // 记录 step 完成到追踪器
-            self.tracker
-                .step_completed(workflow_id, step_name, result.clone())
-                .await;
+    /// 已分发且尚未完成的 task
+    pub async fn in_flight_tasks(&self) -> Vec<TaskId> {
+        let mut tasks: Vec<TaskId> = self.running_tasks.lock().await.keys().cloned().collect();
+        tasks.sort_by_key(|task_id| task_id.to_string());
+        tasks
+    }
 
-            // 广播 step 完成事件
-            let _ = self
-                .broadcaster
-                .broadcast_step_completed(
-                    workflow_id,
-                    &workflow.workflow_type,
-                    step_name,
-                    result.clone(),
-                )
+    /// 持有有效租约的 workflow id
+    pub async fn leased_workflow_ids(&self) -> HashSet<String> {
+        let now = Instant::now();
+        self.running_tasks
+            .lock()
+            .await
+            .iter()
+            .filter(|(_, lease)| lease.expires_at > now)
+            .map(|(task_id, _)| task_id.workflow_id.clone())
+            .collect()
+    }
+
+    /// 释放 task 租约（task 完成或失败后调用）
+    pub async fn release_lease(&self, task_id: &TaskId) {
+        let released = {
+            let mut leases = self.running_tasks.lock().await;
+            let released = leases.remove(task_id);
+            self.requeued_tasks.lock().await.remove(task_id);
+            released
+        };
+        self.stale_workflows.mark(&task_id.workflow_id);
+        if released.is_some_and(|lease| lease.deadline.is_some()) {
+            self.forget_step_deadlines(std::slice::from_ref(task_id))
                 .await;
+        }
+    }
 
-            // 对于 "start" step，整个 workflow 执行完成
-            // 使用 complete() 而不是 step_completed() 来标记为已完成
-            if step_name == "start" {
-                if let Some(completed_state) = workflow.state.complete(result.clone()) {
-                    self.persistence
-                        .update_workflow_state(workflow_id, completed_state)
-                        .await?;
-
-                    self.tracker.workflow_completed(workflow_id).await;
-                    let _ = self
-                        .broadcaster
-                        .broadcast_workflow_completed(workflow_id, &workflow.workflow_type, result)
-                        .await;
+    /// worker 确认收到 task，返回租约是否属于该 worker
+    ///
+    /// 确认时从收到的时刻重新计算租约过期时间，同时视为该 worker 的心跳。
+    pub async fn confirm_lease(&self, task_id: &TaskId, worker_id: &str) -> bool {
+        {
+            let mut leases = self.running_tasks.lock().await;
+            let now = Instant::now();
+            match leases.get_mut(task_id) {
+                Some(lease) if lease.worker_id == worker_id && lease.expires_at > now => {
+                    lease.confirmed = true;
+                    lease.expires_at = now + self.config.task_timeout;
                 }
-            } else if let Some(new_state) = workflow.state.step_completed() {
-                // 普通 step 完成，继续执行下一个 step
-                self.persistence
-                    .update_workflow_state(workflow_id, new_state)
-                    .await?;
+                _ => return false,
             }
         }
+        self.touch_worker(worker_id).await;
+        true
+    }
 
-        Ok(())
+    /// 把 worker 没有执行的 task 交还调度器，返回租约是否属于该 worker
+    ///
+    /// 用于没能送达 worker 的 task：task 立即回到可分发队列，不计为失败尝试，
+    /// 再次分发时沿用这次分发记录的尝试次数。
+    pub async fn return_task(&self, task_id: &TaskId, worker_id: &str) -> bool {
+        if !self.requeue_leased_task(task_id, worker_id, None).await {
+            return false;
+        }
+        self.notify_tasks_ready();
+        true
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::broadcaster::EventType;
-    use crate::persistence::l0_memory::L0MemoryStore;
-    use crate::tracker::StepExecutionStatus;
+    /// worker 拒绝 task，返回租约是否属于该 worker
+    ///
+    /// 与 [`Scheduler::return_task`] 一样立即释放租约且不消耗尝试次数，另外在追踪器的 step
+    /// 执行记录中记下拒绝原因，并计入该 worker 的拒绝次数。`requeue` 为 `false` 时
+    /// 冷却期（`reject_cooldown`）内不再把该 task 分发给这个 worker。
+    pub async fn reject_task(
+        &self,
+        task_id: &TaskId,
+        worker_id: &str,
+        reason: &str,
+        requeue: bool,
+    ) -> bool {
+        let excluded_until = (!requeue).then(|| Instant::now() + self.config.reject_cooldown);
+        if !self
+            .requeue_leased_task(task_id, worker_id, excluded_until)
+            .await
+        {
+            return false;
+        }
+        *self
+            .task_rejections
+            .lock()
+            .unwrap()
+            .entry(worker_id.to_string())
+            .or_default() += 1;
+        task_span(task_id, Some(worker_id))
+            .in_scope(|| tracing::info!(reason, requeue, "task rejected by worker"));
+        self.tracker
+            .step_rejected(&task_id.workflow_id, &task_id.step_name, worker_id, reason)
+            .await;
+        // 先记下拒绝再通知，避免其他 worker 开始执行后才把 step 记为等待分发
+        self.notify_tasks_ready();
+        true
+    }
 
-    #[tokio::test]
-    async fn test_task_scheduling() {
-        let store = L0MemoryStore::new();
+    /// 释放 worker 持有的租约，task 回到可分发队列，由调用方通知 task 可分发
+    ///
+    /// 设置 `excluded_until` 时，该时间之前不再把 task 分发给这个 worker；
+    /// 排除记录与 task 回到队列同时写入，避免被同一个 worker 立即领回。
+    async fn requeue_leased_task(
+        &self,
+        task_id: &TaskId,
+        worker_id: &str,
+        excluded_until: Option<Instant>,
+    ) -> bool {
+        let had_deadline;
+        {
+            let mut leases = self.running_tasks.lock().await;
+            match leases.get(task_id) {
+                Some(lease) if lease.worker_id == worker_id => {}
+                _ => return false,
+            }
+            let lease = leases.remove(task_id).unwrap();
+            had_deadline = lease.deadline.is_some();
+            if let Some(until) = excluded_until {
+                self.task_exclusions
+                    .lock()
+                    .unwrap()
+                    .entry(task_id.clone())
+                    .or_default()
+                    .insert(worker_id.to_string(), until);
+            }
+            self.requeued_tasks.lock().await.insert(
+                task_id.clone(),
+                RequeuedTask {
+                    task: lease.task,
+                    ready_at: Instant::now(),
+                    carries_attempt: true,
+                },
+            );
+        }
+        if had_deadline {
+            self.forget_step_deadlines(std::slice::from_ref(task_id))
+                .await;
+        }
+        true
+    }
 
-        let workflow = Workflow::new(
-            "test-wf".to_string(),
-            "test-type".to_string(),
-            b"test-input".to_vec(),
-        );
+    /// 服务器启动以来各 worker 拒绝的 task 数量，按 worker id 排序
+    pub fn task_rejections(&self) -> Vec<(String, u64)> {
+        let mut rejections: Vec<(String, u64)> = self
+            .task_rejections
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(worker_id, count)| (worker_id.clone(), *count))
+            .collect();
+        rejections.sort();
+        rejections
+    }
 
-        store.save_workflow(&workflow).await.unwrap();
+    /// 等待 workflow 进入终态，workflow 不存在时返回 `None`
+    ///
+    /// 先订阅该 workflow 的事件再读取持久化层，避免在两者之间完成的 workflow 被漏掉；
+    /// 只在收到终态事件、通道关闭或事件滞后时重新读取，不轮询存储。
+    pub async fn wait_for_terminal(&self, workflow_id: &str) -> anyhow::Result<Option<Workflow>> {
+        loop {
+            let mut events = self.broadcaster.subscribe_workflow(workflow_id);
+            let Some(workflow) = self.persistence.get_workflow(workflow_id).await? else {
+                return Ok(None);
+            };
+            if workflow.state.is_terminal() {
+                return Ok(Some(workflow));
+            }
 
-        let started_state = workflow.state.start().unwrap();
-        store
-            .update_workflow_state("test-wf", started_state)
-            .await
-            .unwrap();
+            loop {
+                match events.recv().await {
+                    Ok(event) if event.event_type.is_terminal() => break,
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        self.broadcaster.record_lagged(skipped);
+                        break;
+                    }
+                    // 终态事件送达后通道关闭
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        }
+    }
 
-        let scheduler = Scheduler::new(store);
+    /// 订阅 task 取消通知，worker 的任务流据此通知正在执行的 worker 停止
+    pub fn subscribe_cancellations(&self) -> broadcast::Receiver<TaskCancellation> {
+        self.task_cancelled.subscribe()
+    }
 
-        scheduler
-            .register_worker(
-                "worker-1".to_string(),
-                "test-service".to_string(),
-                "test-group".to_string(),
-                vec!["test-type".to_string()],
-                vec![],
-            )
-            .await;
+    /// 撤销某个 workflow 的全部租约（workflow 被取消时调用），并通知持有这些 task 的 worker
+    pub async fn revoke_workflow_leases(&self, workflow_id: &str) -> Vec<TaskCancellation> {
+        let mut deadlines = Vec::new();
+        let mut cancellations: Vec<TaskCancellation> = {
+            let mut leases = self.running_tasks.lock().await;
+            let revoked: Vec<TaskId> = leases
+                .keys()
+                .filter(|task_id| task_id.workflow_id == workflow_id)
+                .cloned()
+                .collect();
+            revoked
+                .into_iter()
+                .filter_map(|task_id| {
+                    let lease = leases.remove(&task_id)?;
+                    if lease.deadline.is_some() {
+                        deadlines.push(task_id.clone());
+                    }
+                    Some(TaskCancellation {
+                        task_id,
+                        worker_id: lease.worker_id,
+                    })
+                })
+                .collect()
+        };
+        self.forget_step_deadlines(&deadlines).await;
+        self.requeued_tasks
+            .lock()
+            .await
+            .retain(|task_id, _| task_id.workflow_id != workflow_id);
+        self.stale_workflows.mark(workflow_id);
 
-        let tasks = scheduler.poll_tasks("worker-1", 1).await;
-        assert_eq!(tasks.len(), 1);
-        assert_eq!(tasks[0].step_name, "start");
+        cancellations.sort_by_key(|c| c.task_id.to_string());
+        for cancellation in &cancellations {
+            let _ = self.task_cancelled.send(cancellation.clone());
+        }
+        cancellations
     }
 
-    #[tokio::test]
-    async fn test_tracker_integration() {
-        let store = L0MemoryStore::new();
-        let scheduler = Scheduler::new(store);
-
-        // 开始追踪 workflow
-        scheduler
-            .tracker
-            .start_workflow("wf-1".to_string(), "test-type".to_string())
-            .await;
+    /// 释放某个 workflow 的全部租约（workflow 被删除时调用）
+    pub async fn release_workflow_leases(&self, workflow_id: &str) {
+        let deadlines: Vec<TaskId> = {
+            let mut leases = self.running_tasks.lock().await;
+            let deadlines = leases
+                .iter()
+                .filter(|(task_id, lease)| {
+                    task_id.workflow_id == workflow_id && lease.deadline.is_some()
+                })
+                .map(|(task_id, _)| task_id.clone())
+                .collect();
+            leases.retain(|task_id, _| task_id.workflow_id != workflow_id);
+            self.requeued_tasks
+                .lock()
+                .await
+                .retain(|task_id, _| task_id.workflow_id != workflow_id);
+            deadlines
+        };
+        self.stale_workflows.mark(workflow_id);
+        self.forget_step_deadlines(&deadlines).await;
+    }
 
-        // 开始 step
-        let step = scheduler
-            .tracker
-            .step_started("wf-1", "step-1", vec![1, 2, 3], vec![])
-            .await;
+    /// worker 是否仍然持有该 task
+    ///
+    /// 租约已过期的 task 不再属于任何 worker；有租约时要求指定的 `worker_id` 与租约一致，
+    /// 未指定 worker 的上报无法证明持有租约，同样不算持有。
+    /// 没有租约记录（例如服务器重启后）时视为持有。
+    pub async fn owns_task(&self, task_id: &TaskId, worker_id: Option<&str>) -> bool {
+        let leases = self.running_tasks.lock().await;
+        if self.requeued_tasks.lock().await.contains_key(task_id) {
+            return false;
+        }
+        match leases.get(task_id) {
+            Some(lease) if lease.expires_at <= Instant::now() => false,
+            Some(lease) => worker_id == Some(lease.worker_id.as_str()),
+            None => true,
+        }
+    }
 
-        assert_eq!(step.status, StepExecutionStatus::Running);
+    /// 回收过期租约，task 回到可分发队列，返回过期的 task
+    ///
+    /// 每个过期的 task 在追踪器中记一次失败尝试，并广播 `StepFailed` 事件。
+    pub async fn expire_leases(&self) -> Vec<TaskId> {
+        let now = Instant::now();
+        let mut deadlines = Vec::new();
+        let expired: Vec<(TaskId, Task)> = {
+            let mut leases = self.running_tasks.lock().await;
+            let mut pool = self.requeued_tasks.lock().await;
+            let ids: Vec<TaskId> = leases
+                .iter()
+                .filter(|(_, lease)| lease.expires_at <= now)
+                .map(|(task_id, _)| task_id.clone())
+                .collect();
+            ids.into_iter()
+                .filter_map(|task_id| {
+                    let lease = leases.remove(&task_id)?;
+                    if lease.deadline.is_some() {
+                        deadlines.push(task_id.clone());
+                    }
+                    pool.insert(
+                        task_id.clone(),
+                        RequeuedTask {
+                            task: lease.task.clone(),
+                            ready_at: now,
+                            carries_attempt: false,
+                        },
+                    );
+                    Some((task_id, lease.task))
+                })
+                .collect()
+        };
+        self.forget_step_deadlines(&deadlines).await;
 
-        // 完成 step
-        scheduler
-            .tracker
-            .step_completed("wf-1", "step-1", vec![4, 5, 6])
-            .await;
+        for (task_id, task) in &expired {
+            let attempt = self
+                .tracker
+                .get_execution(&task_id.workflow_id)
+                .await
+                .and_then(|e| e.step_executions.get(&task_id.step_name).map(|s| s.attempt))
+                .unwrap_or(1);
+            self.tracker
+                .step_failed(
+                    &task_id.workflow_id,
+                    &task_id.step_name,
+                    LEASE_EXPIRED_ERROR.to_string(),
+                )
+                .await;
+            self.broadcaster
+                .broadcast_step_failed(
+                    &task_id.workflow_id,
+                    &task.workflow_type,
+                    &task_id.step_name,
+                    LEASE_EXPIRED_ERROR.to_string(),
+                    attempt,
+                )
+                .await;
+        }
 
-        let execution = scheduler.tracker.get_execution("wf-1").await;
-        assert!(execution.is_some());
-        assert_eq!(execution.unwrap().step_executions.len(), 1);
+        if !expired.is_empty() {
+            self.notify_tasks_ready();
+        }
+        expired.into_iter().map(|(task_id, _)| task_id).collect()
     }
 
-    #[tokio::test]
-    async fn test_broadcaster() {
-        let store = L0MemoryStore::new();
-        let scheduler = Scheduler::new(store);
+    /// 取出超过执行截止时间或错过心跳的 task 的租约，通知持有它们的 worker 停止执行，
+    /// 返回超时的 task、持有它的 worker 和超时错误
+    ///
+    /// task 放回重新分发队列，在按重试策略设置退避之前暂不分发（安排重试失败时按租约过期处理），
+    /// 原 worker 也不能再完成它。
+    pub(crate) async fn take_timed_out_leases(&self) -> Vec<(TaskId, String, String)> {
+        let now = Instant::now();
+        let window = self.missed_heartbeat_window();
+        let timed_out: Vec<(TaskId, String, String)> = {
+            let mut leases = self.running_tasks.lock().await;
+            let mut pool = self.requeued_tasks.lock().await;
+            let errors: Vec<(TaskId, String)> = leases
+                .iter()
+                .filter_map(|(task_id, lease)| {
+                    let error = match (lease.step_timeout, lease.deadline) {
+                        (Some(timeout), Some(deadline)) if deadline <= now => {
+                            timeout_error(timeout)
+                        }
+                        _ if lease.last_heartbeat.is_some_and(|at| at + window <= now) => {
+                            missed_heartbeat_error(window)
+                        }
+                        _ => return None,
+                    };
+                    Some((task_id.clone(), error))
+                })
+                .collect();
+            errors
+                .into_iter()
+                .filter_map(|(task_id, error)| {
+                    let lease = leases.remove(&task_id)?;
+                    pool.insert(
+                        task_id.clone(),
+                        RequeuedTask {
+                            task: lease.task,
+                            ready_at: now + self.config.task_timeout,
+                            carries_attempt: false,
+                        },
+                    );
+                    Some((task_id, lease.worker_id, error))
+                })
+                .collect()
+        };
 
-        let mut rx = scheduler.broadcaster.subscribe();
+        let task_ids: Vec<TaskId> = timed_out.iter().map(|(t, _, _)| t.clone()).collect();
+        self.forget_step_deadlines(&task_ids).await;
+        for (task_id, worker_id, _) in &timed_out {
+            let _ = self.task_cancelled.send(TaskCancellation {
+                task_id: task_id.clone(),
+                worker_id: worker_id.clone(),
+            });
+        }
+        timed_out
+    }
 
-        // 广播 step 完成事件
-        let count = scheduler
-            .broadcaster
-            .broadcast_step_completed("wf-1", "test-type", "step-1", vec![1, 2, 3])
+    /// 按持久化的截止时间为重启前分发的 step 恢复租约，已有租约时不覆盖
+    pub(crate) async fn restore_step_lease(
+        &self,
+        workflow: &Workflow,
+        deadline: &StepDeadline,
+    ) -> anyhow::Result<()> {
+        let task = self.rebuild_task(workflow, &deadline.step_name).await?;
+        let timeout = Duration::from_millis(deadline.timeout_ms);
+        let remaining = (deadline.deadline - chrono::Utc::now())
+            .to_std()
+            .unwrap_or_default();
+        let now = Instant::now();
+        self.running_tasks
+            .lock()
             .await
-            .unwrap();
+            .entry(TaskId::new(&workflow.id, &deadline.step_name))
+            .or_insert(TaskLease {
+                task,
+                worker_id: deadline.worker_id.clone(),
+                dispatched_at: (deadline.deadline - chrono::Duration::from_std(timeout)?).into(),
+                expires_at: now + self.config.task_timeout,
+                confirmed: true,
+                step_timeout: Some(timeout),
+                deadline: Some(now + remaining),
+                last_heartbeat: None,
+                cache_key: None,
+            });
+        Ok(())
+    }
+
+    /// 删除不再需要的 step 截止时间，删除失败只记录警告
+    async fn forget_step_deadlines(&self, task_ids: &[TaskId]) {
+        for task_id in task_ids {
+            if let Err(e) = self
+                .persistence
+                .delete_step_deadline(&task_id.workflow_id, &task_id.step_name)
+                .await
+            {
+                tracing::warn!(%task_id, "failed to delete step deadline: {}", e);
+            }
+        }
+    }
+
+    /// 创建并启动 workflow
+    ///
+    /// `workflow_id` 作为幂等键：同一 id 的 workflow 已存在时不再创建，返回已有的 workflow。
+    /// 通过持久化层的原子插入保证并发的重复启动只有一个成功。
+    pub async fn start_workflow(
+        &self,
+        workflow_id: Option<String>,
+        workflow_type: String,
+        input: Vec<u8>,
+        options: StartOptions,
+    ) -> anyhow::Result<StartedWorkflow> {
+        let workflow = self
+            .new_workflow(WorkflowStart {
+                workflow_id,
+                workflow_type,
+                input,
+                options,
+            })
+            .await?;
+        let saved = self.persistence.save_workflow_if_absent(&workflow).await?;
+        self.finish_start(workflow, saved).await
+    }
+
+    /// 批量创建并启动 workflow，按 `starts` 的顺序返回每个 workflow 的结果
+    ///
+    /// 持久化层支持原子批量写入（[`Persistence::atomic_batches`]）时在一个事务中写入全部
+    /// workflow，写入失败时返回错误，一个都不启动；否则逐个写入，写入失败只影响对应的
+    /// workflow。每个 workflow 的幂等语义同 [`start_workflow`](Self::start_workflow)。
+    pub async fn start_workflows(
+        &self,
+        starts: Vec<WorkflowStart>,
+    ) -> anyhow::Result<Vec<anyhow::Result<StartedWorkflow>>> {
+        // 每个请求对应 `workflows` 中的下标，或创建失败的原因
+        let mut created = Vec::with_capacity(starts.len());
+        let mut workflows = Vec::new();
+        for start in starts {
+            match self.new_workflow(start).await {
+                Ok(workflow) => {
+                    created.push(Ok(workflows.len()));
+                    workflows.push(workflow);
+                }
+                Err(e) => created.push(Err(e)),
+            }
+        }
+
+        let mut saved = if self.persistence.atomic_batches() {
+            self.persistence
+                .save_workflows(&workflows)
+                .await?
+                .into_iter()
+                .map(Ok)
+                .collect()
+        } else {
+            let mut saved = Vec::with_capacity(workflows.len());
+            for workflow in &workflows {
+                saved.push(self.persistence.save_workflow_if_absent(workflow).await);
+            }
+            saved
+        }
+        .into_iter();
+
+        let mut workflows = workflows.into_iter();
+        let mut results = Vec::with_capacity(created.len());
+        for created in created {
+            results.push(match created {
+                Ok(_) => {
+                    let workflow = workflows.next().expect("one workflow per created entry");
+                    match saved.next().expect("one save result per workflow") {
+                        Ok(saved) => self.finish_start(workflow, saved).await,
+                        Err(e) => Err(e),
+                    }
+                }
+                Err(e) => Err(e),
+            });
+        }
+        Ok(results)
+    }
+
+    /// 按启动参数创建处于运行状态的 workflow，解析要固定的定义版本
+    async fn new_workflow(&self, start: WorkflowStart) -> anyhow::Result<Workflow> {
+        let WorkflowStart {
+            workflow_id,
+            workflow_type,
+            input,
+            options,
+        } = start;
+        self.check_input_size(&input)?;
+        validate_memo(&options.memo)?;
+        let workflow_id = workflow_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        let version = match options.version {
+            Some(version) => {
+                if self
+                    .definition_version(&workflow_type, version)
+                    .await
+                    .is_none()
+                {
+                    anyhow::bail!(
+                        "Workflow type '{}' has no definition version {}",
+                        workflow_type,
+                        version
+                    );
+                }
+                Some(version)
+            }
+            None => self
+                .definition(&workflow_type)
+                .await
+                .map(|definition| definition.version),
+        };
+        let mut workflow = Workflow::new(workflow_id, workflow_type, input)
+            .with_priority(options.priority)
+            .with_memo(options.memo);
+        if options.skip_schema_validation {
+            workflow = workflow.with_schema_validation_skipped();
+        }
+        if let Some(version) = version {
+            workflow = workflow.with_definition_version(version);
+        }
+        if let Ok(running) = workflow.state.try_start() {
+            let started_at = workflow.started_at;
+            workflow.transition(running, started_at);
+        }
+        Ok(workflow)
+    }
+
+    /// 写入 workflow 之后：`saved` 为 false 时同 id 的 workflow 已存在，返回已有的 workflow
+    async fn finish_start(
+        &self,
+        workflow: Workflow,
+        saved: bool,
+    ) -> anyhow::Result<StartedWorkflow> {
+        if !saved {
+            let existing = self
+                .persistence
+                .get_workflow(&workflow.id)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Workflow {} disappeared", workflow.id))?;
+            tracing::info!(workflow_id = %existing.id, "workflow already exists");
+            return Ok(StartedWorkflow {
+                workflow: existing,
+                already_exists: true,
+            });
+        }
+
+        self.workflow_started(&workflow).await;
+        self.notify_workflow_changed(&workflow.id);
+        Ok(StartedWorkflow {
+            workflow,
+            already_exists: false,
+        })
+    }
+
+    /// 记录新启动的 workflow：创建追踪记录并输出启动日志
+    pub async fn workflow_started(&self, workflow: &Workflow) {
+        self.stale_workflows.mark(&workflow.id);
+        self.tracker
+            .start_workflow(workflow.id.clone(), workflow.workflow_type.clone())
+            .await;
+        workflow_span(&workflow.id, &workflow.workflow_type).in_scope(|| {
+            tracing::info!(
+                parent_workflow_id = workflow.parent_workflow_id.as_deref(),
+                "workflow started"
+            )
+        });
+    }
+
+    /// 订阅 task 就绪通知
+    pub fn subscribe_tasks(&self) -> watch::Receiver<u64> {
+        self.task_ready.subscribe()
+    }
+
+    /// 通知等待中的 poll：workflow 启动、step 完成或 task 回到队列后可能有新 task 可分发
+    pub fn notify_tasks_ready(&self) {
+        self.task_ready
+            .send_modify(|generation| *generation = generation.wrapping_add(1));
+    }
+
+    /// workflow 的状态发生变化（step 完成、收到 signal、重试、结束等）：
+    /// 下一次 poll 从持久化层重新读取它的就绪 step，并通知等待中的 poll
+    pub(crate) fn notify_workflow_changed(&self, workflow_id: &str) {
+        self.stale_workflows.mark(workflow_id);
+        self.notify_tasks_ready();
+    }
+
+    /// 等待可能有新 task 可分发
+    ///
+    /// 收到就绪通知、最早的重试退避结束或超过 `max_wait` 时返回。调用方应在 poll 之前
+    /// 通过 `borrow_and_update` 标记已读，这样 poll 期间的通知不会丢失。
+    pub async fn wait_for_tasks(&self, ready: &mut watch::Receiver<u64>, max_wait: Duration) {
+        let now = Instant::now();
+        let next_retry = self
+            .requeued_tasks
+            .lock()
+            .await
+            .values()
+            .map(|requeued| requeued.ready_at)
+            .filter(|ready_at| *ready_at > now)
+            .min();
+        let wait = next_retry.map_or(max_wait, |at| (at - now).min(max_wait));
+
+        tokio::select! {
+            _ = ready.changed() => {}
+            _ = tokio::time::sleep(wait) => {}
+        }
+    }
+
+    /// 为资源注册重试策略，覆盖默认策略
+    pub async fn set_retry_policy(&self, resource: &str, policy: RetryPolicy) {
+        self.retry_policies
+            .write()
+            .await
+            .insert(resource.to_string(), policy);
+        // 已入队的 task 携带旧的重试策略
+        self.stale_workflows.reload();
+    }
+
+    /// step 的重试策略，未注册时使用配置中的默认策略
+    pub async fn retry_policy(&self, step_name: &str) -> RetryPolicy {
+        self.retry_policies
+            .read()
+            .await
+            .get(step_name)
+            .cloned()
+            .unwrap_or_else(|| self.config.default_retry.clone())
+    }
+
+    /// 注册 workflow 定义的一个版本
+    ///
+    /// 依赖图有环、依赖不存在或 step 重名的定义会被拒绝；同名同版本的定义已注册时，
+    /// 内容相同视为重复注册，内容不同则拒绝。新版本只用于之后启动的 workflow。
+    /// 定义保存到持久化层，服务器重启后由 [`Scheduler::load_definitions`] 重新加载。
+    pub async fn register_definition(
+        &self,
+        definition: WorkflowDefinition,
+    ) -> Result<(), DefinitionError> {
+        definition.validate()?;
+        let mut definitions = self.definitions.write().await;
+        if let Some(existing) = definitions
+            .get(&definition.name)
+            .and_then(|versions| versions.get(&definition.version))
+        {
+            if *existing == definition {
+                return Ok(());
+            }
+            return Err(DefinitionError::VersionConflict(
+                definition.name,
+                definition.version,
+            ));
+        }
+        self.persistence
+            .save_definition(&definition)
+            .await
+            .map_err(|e| DefinitionError::Persistence(e.to_string()))?;
+        definitions
+            .entry(definition.name.clone())
+            .or_default()
+            .insert(definition.version, definition);
+        // 没有固定版本的运行中 workflow 按最新定义重新计算就绪 step
+        self.stale_workflows.reload();
+        Ok(())
+    }
+
+    /// 加载持久化的定义，服务器启动时调用
+    ///
+    /// 已在代码中注册的同名同版本定义优先；无效的定义被跳过。返回加载的定义数量。
+    pub async fn load_definitions(&self) -> anyhow::Result<usize> {
+        let mut loaded = 0;
+        let mut definitions = self.definitions.write().await;
+        for definition in self.persistence.list_definitions().await? {
+            if let Err(e) = definition.validate() {
+                tracing::warn!(
+                    workflow_type = %definition.name,
+                    version = definition.version,
+                    "skipping invalid persisted workflow definition: {}",
+                    e
+                );
+                continue;
+            }
+            let versions = definitions.entry(definition.name.clone()).or_default();
+            match versions.get(&definition.version) {
+                Some(existing) if *existing != definition => tracing::warn!(
+                    workflow_type = %definition.name,
+                    version = definition.version,
+                    "persisted workflow definition differs from the registered one, keeping the registered one"
+                ),
+                Some(_) => {}
+                None => {
+                    versions.insert(definition.version, definition);
+                    loaded += 1;
+                }
+            }
+        }
+        if loaded > 0 {
+            self.stale_workflows.reload();
+        }
+        Ok(loaded)
+    }
+
+    /// 通过 API 注册的定义中的全部错误：定义本身的错误，以及目标资源不在服务注册表中的 step
+    ///
+    /// 在代码中注册的定义通常早于 worker 连接，[`Scheduler::register_definition`] 不检查目标资源。
+    pub fn definition_problems(&self, definition: &WorkflowDefinition) -> Vec<DefinitionProblem> {
+        let mut problems = definition.problems();
+        problems.extend(self.unknown_resources(definition));
+        problems
+    }
+
+    /// 目标资源不在服务注册表中的 step，标记为 `external` 的 step 不检查
+    fn unknown_resources(&self, definition: &WorkflowDefinition) -> Vec<DefinitionProblem> {
+        definition
+            .steps
+            .iter()
+            .enumerate()
+            .filter(|(_, step)| !step.external && step.is_dispatched())
+            .filter_map(|(index, step)| {
+                let resource = step.target_resource.as_ref()?;
+                let found = match &step.target_service {
+                    Some(service) => self
+                        .service_registry
+                        .find_resource_in_service(service, resource)
+                        .is_some(),
+                    None => !self.service_registry.find_resource(resource).is_empty(),
+                };
+                (!found).then(|| {
+                    DefinitionProblem::new(
+                        format!("steps[{}].targetResource", index),
+                        DefinitionError::UnknownResource(
+                            step.name.clone(),
+                            step.target_service.clone(),
+                            resource.clone(),
+                        ),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// 全部已注册的定义，按类型名称和版本排序
+    pub async fn definitions(&self) -> Vec<WorkflowDefinition> {
+        let definitions = self.definitions.read().await;
+        let mut names: Vec<&String> = definitions.keys().collect();
+        names.sort();
+        names
+            .into_iter()
+            .flat_map(|name| definitions[name].values().cloned())
+            .collect()
+    }
+
+    /// workflow 类型的最新版本定义，新启动的 workflow 使用该版本
+    pub async fn definition(&self, workflow_type: &str) -> Option<WorkflowDefinition> {
+        self.definitions
+            .read()
+            .await
+            .get(workflow_type)?
+            .values()
+            .next_back()
+            .cloned()
+    }
+
+    /// workflow 类型指定版本的定义
+    pub async fn definition_version(
+        &self,
+        workflow_type: &str,
+        version: u32,
+    ) -> Option<WorkflowDefinition> {
+        self.definitions
+            .read()
+            .await
+            .get(workflow_type)?
+            .get(&version)
+            .cloned()
+    }
+
+    /// workflow 启动时固定的定义版本
+    ///
+    /// 没有固定版本（启动时类型尚未定义）或该版本已不再注册（如重启后只注册了新版本）时使用最新版本。
+    pub async fn workflow_definition(&self, workflow: &Workflow) -> Option<WorkflowDefinition> {
+        if let Some(version) = workflow.definition_version {
+            if let Some(definition) = self
+                .definition_version(&workflow.workflow_type, version)
+                .await
+            {
+                return Some(definition);
+            }
+        }
+        self.definition(&workflow.workflow_type).await
+    }
+
+    /// 是否可以启动该类型的 workflow
+    ///
+    /// 未注册任何定义时，每个 workflow 都按单个 "start" step 执行（由 SDK worker 完成整个
+    /// workflow），任何类型都可以启动；注册定义之后只接受已定义的类型，除非启动请求设置了
+    /// `dynamic`（由 SDK worker 以单个 "start" step 执行）。
+    pub async fn accepts_workflow_type(&self, workflow_type: &str) -> bool {
+        let definitions = self.definitions.read().await;
+        definitions.is_empty() || definitions.contains_key(workflow_type)
+    }
+
+    /// 已注册定义的 workflow 类型，按名称排序
+    pub async fn defined_workflow_types(&self) -> Vec<String> {
+        let mut types: Vec<String> = self.definitions.read().await.keys().cloned().collect();
+        types.sort();
+        types
+    }
+
+    /// workflow 中某个 step 的重试策略：定义中的策略优先，其次是资源注册的策略
+    pub async fn step_retry_policy(&self, workflow: &Workflow, step_name: &str) -> RetryPolicy {
+        let defined = self
+            .workflow_definition(workflow)
+            .await
+            .and_then(|definition| definition.step(step_name).and_then(|s| s.retry.clone()));
+        match defined {
+            Some(policy) => policy,
+            None => self.retry_policy(step_name).await,
+        }
+    }
+
+    /// 在 `delay` 之后重新分发失败的 task
+    ///
+    /// 释放当前租约；task 在退避结束前不会被分发，原 worker 也不能再完成它。
+    pub async fn schedule_retry(
+        &self,
+        workflow: &Workflow,
+        step_name: &str,
+        delay: Duration,
+    ) -> anyhow::Result<()> {
+        let task_id = TaskId::new(&workflow.id, step_name);
+        let mut leases = self.running_tasks.lock().await;
+        let released = leases.remove(&task_id);
+        let had_deadline = released
+            .as_ref()
+            .is_some_and(|lease| lease.deadline.is_some());
+        // 超时的 task 已在重新分发队列中等待安排重试
+        let requeued = self.requeued_tasks.lock().await.remove(&task_id);
+        let task = match (released, requeued) {
+            (Some(lease), _) => lease.task,
+            (None, Some(requeued)) => requeued.task,
+            // 没有租约（例如服务器重启后）时按 workflow 重建 task
+            (None, None) => self.rebuild_task(workflow, step_name).await?,
+        };
+        self.requeued_tasks.lock().await.insert(
+            task_id.clone(),
+            RequeuedTask {
+                task,
+                ready_at: Instant::now() + delay,
+                carries_attempt: false,
+            },
+        );
+        drop(leases);
+        if had_deadline {
+            self.forget_step_deadlines(&[task_id]).await;
+        }
+        Ok(())
+    }
+
+    /// 没有租约时按 workflow 和定义重建 step 的 task
+    async fn rebuild_task(&self, workflow: &Workflow, step_name: &str) -> anyhow::Result<Task> {
+        let step = self
+            .workflow_definition(workflow)
+            .await
+            .and_then(|definition| definition.step(step_name).cloned());
+        Ok(Task {
+            task_id: TaskId::new(&workflow.id, step_name).to_string(),
+            workflow_id: workflow.id.clone(),
+            step_name: step_name.to_string(),
+            target_service: step.as_ref().and_then(|s| s.target_service.clone()),
+            target_resource: step.as_ref().and_then(|s| s.target_resource.clone()),
+            resource_type: step
+                .as_ref()
+                .map_or(ResourceType::Step, |s| s.resource_type),
+            input: workflow.input.clone().into(),
+            retry: Some(self.step_retry_policy(workflow, step_name).await),
+            workflow_type: workflow.workflow_type.clone(),
+            heartbeat_interval: self.heartbeat_interval().as_millis() as u64,
+            signal: self.step_signal(workflow, step_name).await?,
+            timeout: step
+                .as_ref()
+                .and_then(|s| s.timeout)
+                .map(|timeout| timeout.as_millis() as u64),
+        })
+    }
+
+    /// 服务器启动以来删除的 workflow 数量
+    pub fn purged_workflows(&self) -> u64 {
+        self.purged_workflows.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn record_purged(&self, count: usize) {
+        self.purged_workflows
+            .fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub async fn register_worker(
+        &self,
+        worker_id: String,
+        service_name: String,
+        group: String,
+        workflow_types: Vec<String>,
+        resources: Vec<(String, ResourceType)>,
+    ) {
+        let mut workers = self.active_workers.write().await;
+        workers.insert(
+            worker_id.clone(),
+            WorkerInfo {
+                id: worker_id,
+                service_name,
+                group,
+                workflow_types,
+                resources,
+                last_seen: std::time::SystemTime::now(),
+            },
+        );
+        self.tracker.live_stats().set_workers(workers.len());
+    }
+
+    /// 为 worker 领取 task，轮询同时视为 worker 心跳
+    ///
+    /// 未注册（或已被移除）的 worker 领不到 task，需要重新注册。
+    /// 持久化层暂时失败时按退避重试，重试仍失败时返回错误，已入队的 task 不受影响。
+    pub async fn poll_tasks(&self, worker_id: &str, max_tasks: usize) -> anyhow::Result<Vec<Task>> {
+        // 停机期间不再分发新 task
+        if self.shutdown_state() != ShutdownState::Running {
+            return Ok(Vec::new());
+        }
+        let worker = {
+            let mut workers = self.active_workers.write().await;
+            match workers.get_mut(worker_id) {
+                Some(worker) => {
+                    worker.last_seen = std::time::SystemTime::now();
+                    worker.clone()
+                }
+                None => return Ok(Vec::new()),
+            }
+        };
+        self.find_available_tasks(&worker, max_tasks).await
+    }
+
+    async fn find_available_tasks(
+        &self,
+        worker: &WorkerInfo,
+        max_tasks: usize,
+    ) -> anyhow::Result<Vec<Task>> {
+        // 先回收过期租约和超时的 step，避免等待后台任务
+        self.expire_leases().await;
+        if let Err(e) = self.expire_step_deadlines().await {
+            tracing::warn!("step timeout check failed: {}", e);
+        }
+        // 刷新时不持有租约锁，重试退避不阻塞 task 的完成和续约
+        self.refresh_ready_queue(&mut *self.ready_queue.lock().await)
+            .await?;
+
+        let ClaimedTasks {
+            tasks,
+            rejected,
+            deadlines,
+            carried_attempts,
+        } = self.claim_tasks(worker, max_tasks).await;
+        // task 交给 worker 之前保存截止时间，保存失败时截止时间只在内存中生效
+        for deadline in &deadlines {
+            if let Err(e) = self.persistence.save_step_deadline(deadline).await {
+                tracing::warn!(
+                    workflow_id = %deadline.workflow_id,
+                    step_name = %deadline.step_name,
+                    "failed to persist step deadline: {}",
+                    e
+                );
+            }
+        }
+        // 分发即开始一次新的尝试，尝试次数随 workflow 持久化，重启后重试策略仍然有效；
+        // worker 没有执行就交还的 task 沿用上一次分发的尝试。与推进 workflow 的读改写互斥，避免被覆盖
+        if !tasks.is_empty() {
+            let _guard = self.advance_lock.lock().await;
+            for task in &tasks {
+                if carried_attempts.contains(&TaskId::new(&task.workflow_id, &task.step_name)) {
+                    continue;
+                }
+                if let Err(e) = self
+                    .persistence
+                    .record_step_attempt(&task.workflow_id, &task.step_name)
+                    .await
+                {
+                    tracing::warn!(
+                        workflow_id = %task.workflow_id,
+                        step_name = %task.step_name,
+                        "failed to record step attempt: {}",
+                        e
+                    );
+                }
+            }
+        }
+        // 输入不符合 schema 的 task 已被领取，避免其他 worker 同时领取；step 直接失败，不再重试
+        for (task, violation) in rejected {
+            task_span(
+                &TaskId::new(&task.workflow_id, &task.step_name),
+                Some(&worker.id),
+            )
+            .in_scope(|| tracing::warn!(%violation, "task input rejected"));
+            self.lifecycle()
+                .fail_step(&task.workflow_id, &task.step_name, violation.to_string())
+                .await?;
+        }
+        // 刷新队列时命中缓存的 step 不交给 worker，直接以缓存的结果完成
+        self.complete_cached_steps().await;
+        Ok(tasks)
+    }
+
+    /// 按队列顺序为 worker 领取 task，同时返回输入不符合 schema 的 task 和新的 step 截止时间
+    async fn claim_tasks(&self, worker: &WorkerInfo, max_tasks: usize) -> ClaimedTasks {
+        let mut tasks = Vec::new();
+        let mut rejected = Vec::new();
+        let mut deadlines = Vec::new();
+        let mut leases = self.running_tasks.lock().await;
+        let queue = self.ready_queue.lock().await;
+        // 已结束的 workflow 不再需要粘性分配
+        self.sticky_routes
+            .retain(|workflow_id| queue.contains(workflow_id));
+        let mut requeued = self.requeued_tasks.lock().await;
+        let now = Instant::now();
+        let mut exclusions = self.task_exclusions.lock().unwrap();
+        exclusions.retain(|_, workers| {
+            workers.retain(|_, until| *until > now);
+            !workers.is_empty()
+        });
+        let mut carried_attempts = HashSet::new();
+        let mut in_flight: HashMap<String, usize> = HashMap::new();
+        for lease in leases.values() {
+            *in_flight
+                .entry(lease.task.workflow_type.clone())
+                .or_default() += 1;
+        }
+
+        // 队列按优先级排列，同一优先级内先启动的先分发
+        'workflows: for workflow in queue.iter() {
+            for task in dispatch_candidates(workflow, &leases, &requeued, now) {
+                if tasks.len() >= max_tasks {
+                    break 'workflows;
+                }
+                // 达到并发上限的 workflow 类型不再分发
+                let running = in_flight.get(&task.workflow_type).copied().unwrap_or(0);
+                if self
+                    .concurrency_limit(&task.workflow_type)
+                    .is_some_and(|limit| running >= limit)
+                {
+                    continue 'workflows;
+                }
+
+                // 拒绝过该 task 的 worker 在冷却期内不再领取
+                let task_id = TaskId::new(&task.workflow_id, &task.step_name);
+                let excluded = exclusions
+                    .get(&task_id)
+                    .is_some_and(|workers| workers.contains_key(&worker.id));
+
+                // Check if this worker can handle this task
+                if !excluded
+                    && self.can_worker_handle_task(worker, &task)
+                    && (!workflow.sticky
+                        || self.sticky_routes.claim(
+                            &workflow.workflow_id,
+                            &worker.id,
+                            self.config.sticky_timeout,
+                        ))
+                {
+                    let violation = self.input_violation(worker, workflow, &task);
+                    if violation.is_none() {
+                        task_span(&task_id, Some(&worker.id)).in_scope(|| {
+                            tracing::info!(
+                                target_service = task.target_service.as_deref(),
+                                "task dispatched"
+                            )
+                        });
+                    }
+                    if requeued
+                        .remove(&task_id)
+                        .is_some_and(|pending| pending.carries_attempt)
+                    {
+                        carried_attempts.insert(task_id.clone());
+                    }
+                    *in_flight.entry(task.workflow_type.clone()).or_default() += 1;
+                    let step_timeout = violation
+                        .is_none()
+                        .then(|| self.step_timeout(worker, &task))
+                        .flatten();
+                    if let Some(timeout) = step_timeout {
+                        deadlines.push(StepDeadline::new(&task_id, &worker.id, timeout));
+                    }
+                    leases.insert(
+                        task_id,
+                        TaskLease {
+                            task: task.clone(),
+                            worker_id: worker.id.clone(),
+                            dispatched_at: std::time::SystemTime::now(),
+                            expires_at: now + self.config.task_timeout,
+                            confirmed: false,
+                            step_timeout,
+                            deadline: step_timeout.and_then(|timeout| now.checked_add(timeout)),
+                            last_heartbeat: None,
+                            cache_key: violation
+                                .is_none()
+                                .then(|| workflow.cache_keys.get(&task.step_name).cloned())
+                                .flatten(),
+                        },
+                    );
+                    match violation {
+                        Some(violation) => rejected.push((task, violation)),
+                        None => tasks.push(task),
+                    }
+                }
+            }
+        }
+
+        ClaimedTasks {
+            tasks,
+            rejected,
+            deadlines,
+            carried_attempts,
+        }
+    }
+
+    /// 可缓存的 task 由 worker 成功完成时，把结果写入 step 结果缓存，写入失败只记录警告
+    pub(crate) async fn cache_step_result(&self, task_id: &TaskId, result: &[u8]) {
+        let key = self
+            .running_tasks
+            .lock()
+            .await
+            .get(task_id)
+            .and_then(|lease| lease.cache_key.clone());
+        let Some(key) = key else {
+            return;
+        };
+        let entry = CachedResult::new(key, result.to_vec(), self.config.step_cache_ttl);
+        if let Err(e) = self
+            .persistence
+            .put_cached_result(&entry, self.config.step_cache_max_entries)
+            .await
+        {
+            tracing::warn!(%task_id, "failed to cache step result: {}", e);
+        }
+    }
+
+    /// step 的执行超时：定义中声明的超时优先，其次是执行实例为 task 的目标资源
+    /// （未指定时为同名资源）声明的超时
+    fn step_timeout(&self, worker: &WorkerInfo, task: &Task) -> Option<Duration> {
+        if let Some(timeout) = task.timeout.filter(|timeout| *timeout > 0) {
+            return Some(Duration::from_millis(timeout));
+        }
+        let resource = task.target_resource.as_deref().unwrap_or(&task.step_name);
+        let timeout = self
+            .service_registry
+            .find_resource_in_instance(&worker.service_name, &worker.id, resource)?
+            .metadata?
+            .timeout?;
+        (timeout > 0).then(|| Duration::from_millis(timeout))
+    }
+
+    /// task 输入不符合执行实例为目标资源声明的 input schema 时，返回不符合项
+    fn input_violation(
+        &self,
+        worker: &WorkerInfo,
+        workflow: &QueuedWorkflow,
+        task: &Task,
+    ) -> Option<SchemaViolation> {
+        if workflow.skip_schema_validation {
+            return None;
+        }
+        let resource = task.target_resource.as_deref()?;
+        let schema = self
+            .service_registry
+            .find_resource_in_instance(&worker.service_name, &worker.id, resource)?
+            .metadata?
+            .input_schema?;
+        schema::validate(
+            resource,
+            SchemaTarget::Input,
+            &schema,
+            task.input.as_bytes(),
+        )
+        .err()
+    }
+
+    /// step 输出不符合目标资源声明的 output schema 时，返回不符合项
+    ///
+    /// 按领取 task 的实例的声明校验；租约已不存在时（如服务器重启后）
+    /// 取目标服务（未指定时为任意服务）中声明了该资源的实例。
+    pub(crate) async fn output_violation(
+        &self,
+        workflow: &Workflow,
+        task_id: &TaskId,
+        output: &[u8],
+    ) -> Option<SchemaViolation> {
+        if workflow.skip_schema_validation {
+            return None;
+        }
+        let step = self
+            .workflow_definition(workflow)
+            .await?
+            .step(&task_id.step_name)?
+            .clone();
+        let resource = step.target_resource.as_deref()?;
+
+        let lease_holder = self
+            .running_tasks
+            .lock()
+            .await
+            .get(task_id)
+            .map(|lease| lease.worker_id.clone());
+        let instance = match lease_holder {
+            Some(worker_id) => self
+                .active_workers
+                .read()
+                .await
+                .get(&worker_id)
+                .map(|worker| (worker.service_name.clone(), worker_id)),
+            None => None,
+        };
+        let declared = match (&instance, &step.target_service) {
+            (Some((service, worker_id)), _) => self
+                .service_registry
+                .find_resource_in_instance(service, worker_id, resource),
+            (None, Some(service)) => self
+                .service_registry
+                .find_resource_in_service(service, resource),
+            (None, None) => self
+                .service_registry
+                .find_resource(resource)
+                .into_iter()
+                .next()
+                .map(|(_, _, declared)| declared),
+        };
+        let schema = declared?.metadata?.output_schema?;
+        schema::validate(resource, SchemaTarget::Output, &schema, output).err()
+    }
+
+    /// 从持久化层重新读取状态发生变化的 workflow；首次调用或注册定义后重建整个队列
+    ///
+    /// 读取失败时未刷新的 workflow 保持待刷新，下一次 poll 重试。
+    pub(crate) async fn refresh_ready_queue(&self, queue: &mut ReadyQueue) -> anyhow::Result<()> {
+        match self.stale_workflows.take() {
+            Refresh::All => match self.load_ready_queue(queue).await {
+                Ok(loaded) => {
+                    *queue = loaded;
+                    tracing::debug!(workflows = queue.len(), "ready queue rebuilt");
+                }
+                Err(e) => {
+                    self.stale_workflows.reload();
+                    return Err(e);
+                }
+            },
+            Refresh::Workflows(workflow_ids) => {
+                for (i, workflow_id) in workflow_ids.iter().enumerate() {
+                    if let Err(e) = self.refresh_workflow(queue, workflow_id).await {
+                        for workflow_id in &workflow_ids[i..] {
+                            self.stale_workflows.mark(workflow_id);
+                        }
+                        return Err(e);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 从持久化层加载全部运行中的 workflow，`previous` 是重建前的队列
+    async fn load_ready_queue(&self, previous: &ReadyQueue) -> anyhow::Result<ReadyQueue> {
+        let options = ListOptions {
+            state_filter: Some(StateKind::Running),
+            ..Default::default()
+        };
+        let workflows = retry_transient("list running workflows", || {
+            self.persistence.list_workflows_paged(&options)
+        })
+        .await?;
+        let mut queue = ReadyQueue::new();
+        for workflow in &workflows {
+            let queued = self
+                .queued_workflow(workflow, previous.get(&workflow.id))
+                .await?;
+            queue.insert(queued);
+        }
+        Ok(queue)
+    }
+
+    /// 重新读取一个 workflow，已结束或已删除的移出队列
+    async fn refresh_workflow(
+        &self,
+        queue: &mut ReadyQueue,
+        workflow_id: &str,
+    ) -> anyhow::Result<()> {
+        let workflow = retry_transient("load workflow", || {
+            self.persistence.get_workflow(workflow_id)
+        })
+        .await?;
+        match workflow {
+            Some(workflow) if matches!(workflow.state, WorkflowState::Running { .. }) => {
+                let queued = self
+                    .queued_workflow(&workflow, queue.get(workflow_id))
+                    .await?;
+                queue.insert(queued);
+            }
+            _ => queue.remove(workflow_id),
+        }
+        Ok(())
+    }
+
+    /// 运行中 workflow 在队列中的条目：就绪 step 的 task，不考虑租约和重试退避
+    ///
+    /// 可缓存的 step 在刚就绪（不在刷新前的条目 `previous` 中）时查找缓存，
+    /// 命中的不进入队列，交给 [`Self::complete_cached_steps`] 以缓存的结果完成。
+    async fn queued_workflow(
+        &self,
+        workflow: &Workflow,
+        previous: Option<&QueuedWorkflow>,
+    ) -> anyhow::Result<QueuedWorkflow> {
+        let current_step = match &workflow.state {
+            WorkflowState::Running { current_step } => current_step.clone(),
+            _ => None,
+        };
+        let mut tasks = Vec::new();
+        let mut cache_keys = HashMap::new();
+        for (step, signal) in self.find_ready_steps(workflow).await? {
+            let cacheable = step.cacheable;
+            let task = Task {
+                task_id: TaskId::new(&workflow.id, &step.name).to_string(),
+                workflow_id: workflow.id.clone(),
+                retry: Some(match step.retry {
+                    Some(policy) => policy,
+                    None => self.retry_policy(&step.name).await,
+                }),
+                step_name: step.name,
+                target_service: step.target_service,
+                target_resource: step.target_resource,
+                resource_type: step.resource_type,
+                input: workflow.input.clone().into(),
+                workflow_type: workflow.workflow_type.clone(),
+                heartbeat_interval: self.heartbeat_interval().as_millis() as u64,
+                signal,
+                timeout: step.timeout.map(|timeout| timeout.as_millis() as u64),
+            };
+            let task_id = TaskId::new(&task.workflow_id, &task.step_name);
+            if self.cached_steps.is_serving(&task_id) {
+                continue;
+            }
+            if let Some(key) = self.step_cache_key(cacheable, &task) {
+                let newly_ready = previous.is_none_or(|previous| {
+                    !previous
+                        .tasks
+                        .iter()
+                        .any(|queued| queued.step_name == task.step_name)
+                });
+                if newly_ready {
+                    if let Some(result) = self.cached_step_result(&key).await {
+                        self.cached_steps.push(task, result);
+                        self.notify_tasks_ready();
+                        continue;
+                    }
+                }
+                cache_keys.insert(task.step_name.clone(), key);
+            }
+            tasks.push(task);
+        }
+        Ok(QueuedWorkflow {
+            workflow_id: workflow.id.clone(),
+            priority: workflow.priority,
+            started_at: workflow.started_at,
+            current_step,
+            sticky: self
+                .workflow_definition(workflow)
+                .await
+                .is_some_and(|definition| definition.sticky),
+            skip_schema_validation: workflow.skip_schema_validation,
+            tasks,
+            cache_keys,
+        })
+    }
+
+    /// 等待分发的 task 数量，按 workflow 优先级统计
+    pub async fn ready_task_counts(&self) -> anyhow::Result<HashMap<Priority, usize>> {
+        self.refresh_ready_queue(&mut *self.ready_queue.lock().await)
+            .await?;
+        let leases = self.running_tasks.lock().await;
+        let queue = self.ready_queue.lock().await;
+        let requeued = self.requeued_tasks.lock().await;
+        let now = Instant::now();
+
+        let mut counts: HashMap<Priority, usize> =
+            Priority::ALL.into_iter().map(|p| (p, 0)).collect();
+        for workflow in queue.iter() {
+            let ready = dispatch_candidates(workflow, &leases, &requeued, now).len();
+            *counts.entry(workflow.priority).or_default() += ready;
+        }
+        Ok(counts)
+    }
+
+    /// worker 能否执行该 task，按 worker 所属服务在服务注册表中声明的资源判断
+    ///
+    /// 指定了目标资源的 task 只分发给声明了同名同类型资源的服务（指定了目标服务时还要求服务一致）；
+    /// 只指定目标服务的 task 分发给该服务的任意 worker；都未指定时，
+    /// 分发给支持该 workflow 类型或声明了同类型资源的 worker。
+    fn can_worker_handle_task(&self, worker: &WorkerInfo, task: &Task) -> bool {
+        match (&task.target_service, &task.target_resource) {
+            (service, Some(resource)) => {
+                service.as_ref().is_none_or(|s| *s == worker.service_name)
+                    && self
+                        .service_registry
+                        .find_resource_in_instance(&worker.service_name, &worker.id, resource)
+                        .is_some_and(|r| r.resource_type == task.resource_type)
+            }
+            (Some(service), None) => worker.service_name == *service,
+            (None, None) => {
+                worker.workflow_types.contains(&task.workflow_type)
+                    || self.service_registry.provides_resource_type(
+                        &worker.service_name,
+                        &worker.id,
+                        task.resource_type,
+                    )
+            }
+        }
+    }
+
+    /// 就绪的 step 及其等待的 signal：依赖已满足、等待的 signal 已到达且尚未完成
+    ///
+    /// 有定义时按定义的依赖图计算，互不依赖的 step 同时就绪；
+    /// 否则是单个 "start" step，在它开始执行之前就绪。已被领取的 step 在分发时过滤。
+    async fn find_ready_steps(
+        &self,
+        workflow: &Workflow,
+    ) -> anyhow::Result<Vec<(StepDefinition, Option<Signal>)>> {
+        let steps = match (&workflow.state, self.workflow_definition(workflow).await) {
+            (WorkflowState::Running { .. }, Some(definition)) => definition
+                .ready_steps(workflow)
+                .into_iter()
+                .filter(|step| step.is_dispatched())
+                .cloned()
+                .collect(),
+            (WorkflowState::Running { current_step: None }, None) => {
+                vec![StepDefinition::new("start")]
+            }
+            _ => Vec::new(),
+        };
+
+        let signals = if steps.iter().any(|step| step.wait_for_signal.is_some()) {
+            self.signals(&workflow.id).await?
+        } else {
+            Vec::new()
+        };
+        Ok(steps
+            .into_iter()
+            .filter_map(|step| match &step.wait_for_signal {
+                None => Some((step, None)),
+                Some(name) => latest_signal(&signals, name).map(|signal| (step, Some(signal))),
+            })
+            .collect())
+    }
+
+    /// workflow 收到的 signal
+    async fn signals(&self, workflow_id: &str) -> anyhow::Result<Vec<Signal>> {
+        retry_transient("load signals", || self.persistence.get_signals(workflow_id)).await
+    }
+
+    /// step 等待的 signal 中最新的一个
+    async fn step_signal(
+        &self,
+        workflow: &Workflow,
+        step_name: &str,
+    ) -> anyhow::Result<Option<Signal>> {
+        let name = self
+            .workflow_definition(workflow)
+            .await
+            .and_then(|definition| definition.step(step_name)?.wait_for_signal.clone());
+        match name {
+            Some(name) => Ok(latest_signal(&self.signals(&workflow.id).await?, &name)),
+            None => Ok(None),
+        }
+    }
+
+    /// 在进程内完成 task，详见 [`StepLifecycle::complete_task`](crate::step_lifecycle::StepLifecycle::complete_task)
+    ///
+    /// 嵌入方代表当前持有租约的 worker 完成；租约已过期的 task 仍然拒绝完成。
+    pub async fn complete_task(&self, task_id: &str, result: Vec<u8>) -> anyhow::Result<()> {
+        let holder = match TaskId::parse(task_id) {
+            Some(id) => self.lease(&id).await.map(|lease| lease.worker_id),
+            None => None,
+        };
+        self.lifecycle()
+            .complete_task(task_id, holder.as_deref(), result)
+            .await?;
+        Ok(())
+    }
+}
+
+fn check_size(what: &'static str, data: &[u8], limit: usize) -> Result<(), PayloadTooLarge> {
+    if data.len() > limit {
+        return Err(PayloadTooLarge {
+            what,
+            size: data.len(),
+            limit,
+        });
+    }
+    Ok(())
+}
+
+/// 读取持久化层，失败时按指数退避重试，重试用尽后返回最后一次的错误
+async fn retry_transient<T, F, Fut>(operation: &str, mut read: F) -> anyhow::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    let mut backoff = PERSISTENCE_RETRY_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match read().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < PERSISTENCE_READ_ATTEMPTS => {
+                tracing::warn!(
+                    operation,
+                    attempt,
+                    "persistence read failed, retrying: {}",
+                    e
+                );
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                attempt += 1;
+            }
+            Err(e) => {
+                return Err(e.context(format!("{} failed after {} attempts", operation, attempt)))
+            }
+        }
+    }
+}
+
+/// workflow 当前可以分发的 task：没有被领取的就绪 step，以及退避结束、等待重新分发的 task
+fn dispatch_candidates(
+    workflow: &QueuedWorkflow,
+    leases: &HashMap<TaskId, TaskLease>,
+    requeued: &HashMap<TaskId, RequeuedTask>,
+    now: Instant,
+) -> Vec<Task> {
+    // 执行中的 step 租约过期（worker 停止心跳）或等待重试时，从重新分发队列中取出
+    let current_step = workflow
+        .current_step
+        .as_deref()
+        .filter(|step| !workflow.tasks.iter().any(|task| task.step_name == *step));
+    let steps = workflow
+        .tasks
+        .iter()
+        .map(|task| (task.step_name.as_str(), Some(task)))
+        .chain(current_step.map(|step| (step, None)));
+
+    let mut candidates = Vec::new();
+    for (step_name, task) in steps {
+        let task_id = TaskId::new(&workflow.workflow_id, step_name);
+        if leases.contains_key(&task_id) {
+            continue;
+        }
+        match (requeued.get(&task_id), task) {
+            // 等待重试的 task 在退避结束前不分发
+            (Some(pending), _) if pending.ready_at > now => {}
+            (Some(pending), _) => candidates.push(pending.task.clone()),
+            (None, Some(task)) => candidates.push(task.clone()),
+            (None, None) => {}
+        }
+    }
+    candidates
+}
+
+/// 同名 signal 中最新到达的一个
+fn latest_signal(signals: &[Signal], name: &str) -> Option<Signal> {
+    signals
+        .iter()
+        .rev()
+        .find(|signal| signal.name == name)
+        .cloned()
+}
+
+/// 启动后台 worker 清理任务，定期移除失联的 worker
+pub fn spawn_worker_eviction_task<P: Persistence + 'static>(
+    scheduler: std::sync::Arc<Scheduler<P>>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let period = scheduler.poll_interval();
+        let mut interval = tokio::time::interval(period);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = scheduler.stopped() => break,
+            }
+            let evicted = scheduler.evict_stale_workers().await;
+            if !evicted.is_empty() {
+                tracing::warn!(workers = ?evicted, "stale workers evicted, their tasks requeued");
+            }
+        }
+    })
+}
+
+/// 启动后台租约回收任务，定期把过期 task 放回可分发队列
+pub fn spawn_lease_expiry_task<P: Persistence + 'static>(
+    scheduler: std::sync::Arc<Scheduler<P>>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let period = scheduler
+            .heartbeat_interval()
+            .max(Duration::from_millis(10));
+        let mut interval = tokio::time::interval(period);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = scheduler.stopped() => break,
+            }
+            let expired = scheduler.expire_leases().await;
+            if !expired.is_empty() {
+                tracing::info!(count = expired.len(), "task leases expired, tasks requeued");
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::broadcaster::EventType;
+    use crate::persistence::faulty::FaultyStore;
+    use crate::persistence::l0_memory::L0MemoryStore;
+    use crate::signal::SignalError;
+    use crate::step_lifecycle::StepLifecycleError;
+    use crate::tracker::StepExecutionStatus;
+
+    #[tokio::test]
+    async fn test_task_scheduling() {
+        let store = L0MemoryStore::new();
+
+        let workflow = Workflow::new(
+            "test-wf".to_string(),
+            "test-type".to_string(),
+            b"test-input".to_vec(),
+        );
+
+        store.save_workflow(&workflow).await.unwrap();
+
+        let started_state = workflow.state.try_start().unwrap();
+        store
+            .update_workflow_state("test-wf", started_state)
+            .await
+            .unwrap();
+
+        let scheduler = Scheduler::new(store);
+
+        scheduler
+            .register_worker(
+                "worker-1".to_string(),
+                "test-service".to_string(),
+                "test-group".to_string(),
+                vec!["test-type".to_string()],
+                vec![],
+            )
+            .await;
+
+        let tasks = scheduler.poll_tasks("worker-1", 1).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].step_name, "start");
+    }
+
+    #[tokio::test]
+    async fn test_tracker_integration() {
+        let store = L0MemoryStore::new();
+        let scheduler = Scheduler::new(store);
+
+        // 开始追踪 workflow
+        scheduler
+            .tracker
+            .start_workflow("wf-1".to_string(), "test-type".to_string())
+            .await;
+
+        // 开始 step
+        let step = scheduler
+            .tracker
+            .step_started("wf-1", "step-1", vec![1, 2, 3], vec![])
+            .await
+            .unwrap();
+
+        assert_eq!(step.status, StepExecutionStatus::Running);
+
+        // 完成 step
+        scheduler
+            .tracker
+            .step_completed("wf-1", "step-1", vec![4, 5, 6])
+            .await;
+
+        let execution = scheduler.tracker.get_execution("wf-1").await;
+        assert!(execution.is_some());
+        assert_eq!(execution.unwrap().step_executions.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_broadcaster() {
+        let store = L0MemoryStore::new();
+        let scheduler = Scheduler::new(store);
+
+        let mut rx = scheduler.broadcaster.subscribe();
+
+        // 广播 step 完成事件
+        let result = scheduler
+            .broadcaster
+            .broadcast_step_completed("wf-1", "test-type", "step-1", vec![1, 2, 3])
+            .await;
+
+        assert_eq!(result.delivered, 1);
+
+        // 接收事件
+        let event = rx.recv().await.unwrap();
+        assert_eq!(event.workflow_id, "wf-1");
+        assert_eq!(event.event_type, EventType::StepCompleted);
+    }
+
+    async fn leased_scheduler(task_timeout: Duration) -> Scheduler<L0MemoryStore> {
+        let store = L0MemoryStore::new();
+        let mut workflow = Workflow::new("wf-1".to_string(), "test-type".to_string(), vec![]);
+        workflow.state = workflow.state.try_start().unwrap();
+        store.save_workflow(&workflow).await.unwrap();
+
+        let scheduler = Scheduler::new(store).with_task_timeout(task_timeout);
+        scheduler
+            .register_worker(
+                "worker-1".to_string(),
+                "test-service".to_string(),
+                "test-group".to_string(),
+                vec!["test-type".to_string()],
+                vec![],
+            )
+            .await;
+        scheduler
+    }
+
+    #[tokio::test]
+    async fn test_dispatched_task_is_leased() {
+        let scheduler = leased_scheduler(Duration::from_secs(60)).await;
+
+        let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].heartbeat_interval, 20_000);
+
+        let lease = scheduler
+            .lease(&TaskId::new("wf-1", "start"))
+            .await
+            .unwrap();
+        assert_eq!(lease.worker_id, "worker-1");
+        assert!(lease.active);
+
+        // 租约有效期内不会重复分发
+        assert!(scheduler
+            .poll_tasks("worker-1", 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_clones_share_workers_and_leases() {
+        let store = Arc::new(L0MemoryStore::new());
+        let mut workflow = Workflow::new("wf-1".to_string(), "test-type".to_string(), vec![]);
+        workflow.state = workflow.state.try_start().unwrap();
+        store.save_workflow(&workflow).await.unwrap();
+
+        let rest = Scheduler::new(store);
+        let grpc = rest.clone();
+        let ready = grpc.subscribe_tasks();
+        rest.register_worker(
+            "worker-1".to_string(),
+            "test-service".to_string(),
+            "test-group".to_string(),
+            vec!["test-type".to_string()],
+            vec![],
+        )
+        .await;
+        rest.notify_tasks_ready();
+        assert!(ready.has_changed().unwrap());
+
+        // 通过一个克隆注册的 worker 可以通过另一个克隆领取 task
+        assert_eq!(grpc.workers().await.len(), 1);
+        let tasks = grpc.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        let lease = rest.lease(&TaskId::new("wf-1", "start")).await.unwrap();
+        assert_eq!(lease.worker_id, "worker-1");
+        assert!(rest.poll_tasks("worker-1", 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rejected_task_goes_to_another_worker() {
+        let scheduler = leased_scheduler(Duration::from_secs(60)).await;
+        scheduler
+            .register_worker(
+                "worker-2".to_string(),
+                "test-service".to_string(),
+                "test-group".to_string(),
+                vec!["test-type".to_string()],
+                vec![],
+            )
+            .await;
+        let task_id = TaskId::new("wf-1", "start");
+
+        assert_eq!(scheduler.poll_tasks("worker-1", 10).await.unwrap().len(), 1);
+        assert!(scheduler
+            .poll_tasks("worker-2", 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // 只有持有租约的 worker 可以确认或拒绝
+        assert!(!scheduler.confirm_lease(&task_id, "worker-2").await);
+        assert!(!scheduler.reject_task(&task_id, "worker-2", "", true).await);
+        assert!(scheduler.confirm_lease(&task_id, "worker-1").await);
+        assert!(scheduler.lease(&task_id).await.unwrap().confirmed);
+
+        assert!(scheduler.reject_task(&task_id, "worker-1", "", true).await);
+        assert!(scheduler.lease(&task_id).await.is_none());
+        let tasks = scheduler.poll_tasks("worker-2", 10).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        let lease = scheduler.lease(&task_id).await.unwrap();
+        assert_eq!(lease.worker_id, "worker-2");
+        assert!(!lease.confirmed);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_task_keeps_attempt_and_excludes_worker() {
+        let scheduler = leased_scheduler(Duration::from_secs(60))
+            .await
+            .with_reject_cooldown(Duration::from_millis(100));
+        scheduler
+            .register_worker(
+                "worker-2".to_string(),
+                "test-service".to_string(),
+                "test-group".to_string(),
+                vec!["test-type".to_string()],
+                vec![],
+            )
+            .await;
+        scheduler
+            .tracker
+            .start_workflow("wf-1".to_string(), "test-type".to_string())
+            .await;
+        let task_id = TaskId::new("wf-1", "start");
+        let attempts = || async {
+            let workflow = scheduler.persistence.get_workflow("wf-1").await.unwrap();
+            workflow.unwrap().attempts("start")
+        };
+
+        assert_eq!(scheduler.poll_tasks("worker-1", 10).await.unwrap().len(), 1);
+        assert_eq!(attempts().await, 1);
+        assert!(
+            scheduler
+                .reject_task(&task_id, "worker-1", "model file missing", false)
+                .await
+        );
+
+        // 冷却期内拒绝的 worker 领不到该 task，其他 worker 领取时不再计一次尝试
+        assert!(scheduler
+            .poll_tasks("worker-1", 10)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(scheduler.poll_tasks("worker-2", 10).await.unwrap().len(), 1);
+        assert_eq!(attempts().await, 1);
+
+        let execution = scheduler.tracker.get_execution("wf-1").await.unwrap();
+        let step = &execution.step_executions["start"];
+        assert_eq!(step.status, StepExecutionStatus::Pending);
+        assert_eq!(step.attempt, 1);
+        assert_eq!(step.rejections.len(), 1);
+        assert_eq!(step.rejections[0].worker_id, "worker-1");
+        assert_eq!(step.rejections[0].reason, "model file missing");
+
+        // requeue 时拒绝的 worker 可以立即再次领取
+        assert!(
+            scheduler
+                .reject_task(&task_id, "worker-2", "busy", true)
+                .await
+        );
+        assert_eq!(scheduler.poll_tasks("worker-2", 10).await.unwrap().len(), 1);
+        assert!(
+            scheduler
+                .reject_task(&task_id, "worker-2", "busy", false)
+                .await
+        );
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(scheduler.poll_tasks("worker-1", 10).await.unwrap().len(), 1);
+        assert_eq!(attempts().await, 1);
+        assert_eq!(
+            scheduler.task_rejections(),
+            vec![("worker-1".to_string(), 1), ("worker-2".to_string(), 2)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_extends_lease() {
+        let scheduler = leased_scheduler(Duration::from_millis(200)).await;
+        let task_id = TaskId::new("wf-1", "start");
+
+        assert_eq!(scheduler.poll_tasks("worker-1", 10).await.unwrap().len(), 1);
+        scheduler
+            .lifecycle()
+            .step_started("wf-1", "start", vec![])
+            .await
+            .unwrap();
+
+        for _ in 0..3 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            scheduler
+                .lifecycle()
+                .heartbeat(&task_id.to_string(), Default::default())
+                .await
+                .unwrap();
+            assert!(scheduler
+                .poll_tasks("worker-1", 10)
+                .await
+                .unwrap()
+                .is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_missed_heartbeats_trigger_redispatch() {
+        let scheduler = leased_scheduler(Duration::from_millis(100)).await;
+
+        let first = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(first.len(), 1);
+        scheduler
+            .lifecycle()
+            .step_started("wf-1", "start", vec![])
+            .await
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+
+        let redispatched = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(redispatched.len(), 1);
+        assert_eq!(redispatched[0].task_id, first[0].task_id);
+
+        // 完成后租约释放，不再分发
+        scheduler
+            .complete_task(&first[0].task_id, b"done".to_vec())
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(scheduler
+            .poll_tasks("worker-1", 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_expired_lease_requeues_task() {
+        use crate::broadcaster::EventPayload;
+
+        let scheduler = leased_scheduler(Duration::from_millis(100)).await;
+        scheduler
+            .tracker
+            .start_workflow("wf-1".to_string(), "test-type".to_string())
+            .await;
+        let mut events = scheduler.broadcaster.subscribe();
+
+        assert_eq!(scheduler.poll_tasks("worker-1", 10).await.unwrap().len(), 1);
+        scheduler
+            .lifecycle()
+            .step_started("wf-1", "start", vec![])
+            .await
+            .unwrap();
+        let _ = events.recv().await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        let expired = scheduler.expire_leases().await;
+        assert_eq!(expired, vec![TaskId::new("wf-1", "start")]);
+        assert!(scheduler.lease(&expired[0]).await.is_none());
+
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.event_type, EventType::StepFailed);
+        match event.payload {
+            EventPayload::StepFailed(payload) => {
+                assert_eq!(payload.error, LEASE_EXPIRED_ERROR);
+                assert_eq!(payload.attempt, 1);
+            }
+            other => panic!("unexpected payload: {:?}", other),
+        }
+
+        // 重新分发后尝试次数延续
+        assert_eq!(scheduler.poll_tasks("worker-1", 10).await.unwrap().len(), 1);
+        scheduler
+            .lifecycle()
+            .step_started("wf-1", "start", vec![])
+            .await
+            .unwrap();
+        let execution = scheduler.tracker.get_execution("wf-1").await.unwrap();
+        assert_eq!(execution.step_executions["start"].attempt, 2);
+    }
+
+    #[tokio::test]
+    async fn test_completing_expired_task_is_rejected() {
+        use crate::step_lifecycle::StepLifecycleError;
+
+        let scheduler = leased_scheduler(Duration::from_millis(100)).await;
+        let task_id = scheduler.poll_tasks("worker-1", 10).await.unwrap()[0]
+            .task_id
+            .clone();
+        tokio::time::sleep(Duration::from_millis(150)).await;
+
+        let result = scheduler
+            .lifecycle()
+            .complete_task(&task_id, Some("worker-1"), vec![])
+            .await;
+        assert!(matches!(result, Err(StepLifecycleError::TaskNotOwned(_))));
+
+        scheduler
+            .register_worker(
+                "worker-2".to_string(),
+                "test-service".to_string(),
+                "test-group".to_string(),
+                vec!["test-type".to_string()],
+                vec![],
+            )
+            .await;
+        assert_eq!(scheduler.poll_tasks("worker-2", 10).await.unwrap().len(), 1);
+
+        let result = scheduler
+            .lifecycle()
+            .complete_task(&task_id, Some("worker-1"), vec![])
+            .await;
+        assert!(matches!(result, Err(StepLifecycleError::TaskNotOwned(_))));
+        // 不指明 worker 的上报同样不能完成其他 worker 持有的 task
+        let err = scheduler
+            .lifecycle()
+            .complete_task(&task_id, None, vec![])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, StepLifecycleError::TaskNotOwned(_)));
+        let status = tonic::Status::from(err);
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+        assert_eq!(
+            status
+                .metadata()
+                .get(crate::grpc_server::ERROR_CODE_METADATA)
+                .unwrap(),
+            "NOT_OWNER"
+        );
+        scheduler
+            .lifecycle()
+            .complete_task(&task_id, Some("worker-2"), b"done".to_vec())
+            .await
+            .unwrap();
+
+        let workflow = scheduler.persistence.get_workflow("wf-1").await.unwrap();
+        assert!(matches!(
+            workflow.unwrap().state,
+            WorkflowState::Completed { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_completions_keep_the_first() {
+        use crate::api::error::ApiError;
+        use crate::step_lifecycle::StepLifecycleError;
+
+        let scheduler = leased_scheduler(Duration::from_secs(30)).await;
+        let task_id = scheduler.poll_tasks("worker-1", 10).await.unwrap()[0]
+            .task_id
+            .clone();
+
+        let lifecycle = scheduler.lifecycle();
+        lifecycle
+            .step_started("wf-1", "start", vec![])
+            .await
+            .unwrap();
+        let (first, second) = tokio::join!(
+            lifecycle.complete_task(&task_id, Some("worker-1"), b"\"first\"".to_vec()),
+            lifecycle.complete_task(&task_id, Some("worker-1"), b"\"second\"".to_vec()),
+        );
+        let (winner, loser) = match (first, second) {
+            (Ok(()), Err(e)) => (b"\"first\"".to_vec(), e),
+            (Err(e), Ok(())) => (b"\"second\"".to_vec(), e),
+            other => panic!("exactly one completion should win: {:?}", other),
+        };
+        assert!(matches!(loser, StepLifecycleError::AlreadyCompleted(_)));
+        let error = ApiError::from(loser);
+        assert_eq!(error.status, axum::http::StatusCode::CONFLICT);
+        assert_eq!(error.body.code, "ALREADY_COMPLETED");
+
+        // 之后的完成和失败上报都不会覆盖第一次完成的输出
+        let late = lifecycle
+            .complete_task(&task_id, None, b"\"late\"".to_vec())
+            .await
+            .unwrap_err();
+        let status = tonic::Status::from(late);
+        assert_eq!(status.code(), tonic::Code::AlreadyExists);
+        assert_eq!(
+            status
+                .metadata()
+                .get(crate::grpc_server::ERROR_CODE_METADATA)
+                .unwrap(),
+            "ALREADY_COMPLETED"
+        );
+        assert!(matches!(
+            lifecycle
+                .fail_task(&task_id, None, "boom".to_string())
+                .await,
+            Err(StepLifecycleError::AlreadyCompleted(_))
+        ));
+
+        let result = scheduler
+            .persistence
+            .get_step_result("wf-1", "start")
+            .await
+            .unwrap();
+        assert_eq!(result, Some(winner.clone()));
+        let execution = scheduler.tracker.get_execution("wf-1").await.unwrap();
+        let output = execution.step_executions["start"].output.clone().unwrap();
+        assert_eq!(output.as_bytes(), winner.as_slice());
+        let workflow = scheduler.persistence.get_workflow("wf-1").await.unwrap();
+        assert!(matches!(
+            workflow.unwrap().state,
+            WorkflowState::Completed { result } if result.as_bytes() == winner.as_slice()
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_failed_step_is_retried_with_backoff() {
+        let scheduler = leased_scheduler(Duration::from_secs(60)).await;
+        scheduler
+            .set_retry_policy(
+                "start",
+                RetryPolicy {
+                    max_attempts: 2,
+                    initial_interval: 100,
+                    backoff_multiplier: 2.0,
+                },
+            )
+            .await;
+
+        let first = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(first[0].retry.as_ref().unwrap().max_attempts, 2);
+        scheduler
+            .lifecycle()
+            .step_started("wf-1", "start", vec![])
+            .await
+            .unwrap();
+        scheduler
+            .lifecycle()
+            .fail_task(&first[0].task_id, Some("worker-1"), "boom".to_string())
+            .await
+            .unwrap();
+
+        // 退避期间不分发，原 worker 也不能再完成该 task
+        let workflow = scheduler.persistence.get_workflow("wf-1").await.unwrap();
+        assert!(matches!(
+            workflow.unwrap().state,
+            WorkflowState::Running { .. }
+        ));
+        assert!(scheduler
+            .poll_tasks("worker-1", 10)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(
+            !scheduler
+                .owns_task(&TaskId::new("wf-1", "start"), Some("worker-1"))
+                .await
+        );
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        let retried = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(retried.len(), 1);
+        assert_eq!(retried[0].task_id, first[0].task_id);
+
+        // 第二次失败后尝试次数用尽，workflow 失败
+        scheduler
+            .lifecycle()
+            .step_started("wf-1", "start", vec![])
+            .await
+            .unwrap();
+        scheduler
+            .lifecycle()
+            .fail_task(&retried[0].task_id, Some("worker-1"), "boom".to_string())
+            .await
+            .unwrap();
+
+        let execution = scheduler.tracker.get_execution("wf-1").await.unwrap();
+        assert_eq!(execution.step_executions["start"].attempt, 3);
+        let workflow = scheduler.persistence.get_workflow("wf-1").await.unwrap();
+        assert!(matches!(
+            workflow.unwrap().state,
+            WorkflowState::Failed { .. }
+        ));
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert!(scheduler
+            .poll_tasks("worker-1", 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_retry_backoff() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(1), Duration::from_millis(1000));
+        assert_eq!(policy.backoff(2), Duration::from_millis(2000));
+        assert_eq!(policy.backoff(3), Duration::from_millis(4000));
+    }
+
+    #[tokio::test]
+    async fn test_config_drives_scheduler_knobs() {
+        let defaults = Scheduler::new(L0MemoryStore::new());
+        assert_eq!(defaults.config(), &SchedulerConfig::default());
+        assert_eq!(defaults.poll_interval(), DEFAULT_WORKER_TIMEOUT / 3);
+        assert_eq!(defaults.poll_tasks_limit(), DEFAULT_POLL_TASKS_LIMIT);
+        assert_eq!(defaults.retry_policy("step1").await, RetryPolicy::default());
+
+        let retry = RetryPolicy {
+            max_attempts: 7,
+            initial_interval: 50,
+            backoff_multiplier: 1.5,
+        };
+        let scheduler = Scheduler::with_config(
+            L0MemoryStore::new(),
+            SchedulerConfig {
+                task_timeout: Duration::from_secs(9),
+                poll_interval: Some(Duration::from_millis(250)),
+                poll_tasks_limit: 3,
+                event_journal_capacity: 5,
+                default_retry: retry.clone(),
+                ..Default::default()
+            },
+        );
+        assert_eq!(scheduler.heartbeat_interval(), Duration::from_secs(3));
+        assert_eq!(scheduler.poll_interval(), Duration::from_millis(250));
+        assert_eq!(scheduler.poll_tasks_limit(), 3);
+        assert_eq!(scheduler.broadcaster.journal_capacity(), 5);
+        assert_eq!(scheduler.retry_policy("step1").await, retry);
+
+        // 为资源注册的策略优先于默认策略
+        scheduler
+            .set_retry_policy("step1", RetryPolicy::default())
+            .await;
+        assert_eq!(
+            scheduler.retry_policy("step1").await,
+            RetryPolicy::default()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stale_worker_is_evicted_and_tasks_requeued() {
+        let scheduler = leased_scheduler(Duration::from_secs(60))
+            .await
+            .with_worker_timeout(Duration::from_millis(100));
+        let task_id = TaskId::new("wf-1", "start");
+
+        assert_eq!(scheduler.poll_tasks("worker-1", 10).await.unwrap().len(), 1);
+        scheduler
+            .lifecycle()
+            .step_started("wf-1", "start", vec![])
+            .await
+            .unwrap();
+
+        // task 心跳也算 worker 心跳
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(scheduler.extend_lease(&task_id).await);
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(scheduler.evict_stale_workers().await.is_empty());
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(scheduler.evict_stale_workers().await, vec!["worker-1"]);
+        assert!(scheduler.lease(&task_id).await.is_none());
+        assert!(!scheduler.owns_task(&task_id, Some("worker-1")).await);
+
+        // 被移除的 worker 领不到 task，重新注册后可以
+        assert!(scheduler
+            .poll_tasks("worker-1", 10)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(!scheduler.touch_worker("worker-1").await);
+        scheduler
+            .register_worker(
+                "worker-2".to_string(),
+                "test-service".to_string(),
+                "test-group".to_string(),
+                vec!["test-type".to_string()],
+                vec![],
+            )
+            .await;
+        let tasks = scheduler.poll_tasks("worker-2", 10).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].task_id, task_id.to_string());
+    }
+
+    /// 注册粘性路由定义和第二个 worker
+    async fn sticky_scheduler(sticky_timeout: Duration) -> Scheduler<L0MemoryStore> {
+        let scheduler = leased_scheduler(Duration::from_secs(60))
+            .await
+            .with_sticky_timeout(sticky_timeout);
+        scheduler
+            .register_definition(
+                WorkflowDefinition::new(
+                    "test-type",
+                    vec![
+                        StepDefinition::new("load"),
+                        StepDefinition::new("infer"),
+                        StepDefinition::new("store"),
+                    ],
+                )
+                .with_sticky_routing(),
+            )
+            .await
+            .unwrap();
+        scheduler
+            .register_worker(
+                "worker-2".to_string(),
+                "test-service".to_string(),
+                "test-group".to_string(),
+                vec!["test-type".to_string()],
+                vec![],
+            )
+            .await;
+        scheduler
+    }
+
+    #[tokio::test]
+    async fn test_sticky_workflow_steps_go_to_first_poller() {
+        let scheduler = sticky_scheduler(Duration::from_secs(60)).await;
+
+        for step_name in ["load", "infer", "store"] {
+            if step_name != "load" {
+                // 其他 worker 领不到后续 step
+                assert!(scheduler
+                    .poll_tasks("worker-2", 10)
+                    .await
+                    .unwrap()
+                    .is_empty());
+            }
+            let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+            assert_eq!(tasks.len(), 1, "{}", step_name);
+            assert_eq!(tasks[0].step_name, step_name);
+            assert_eq!(scheduler.sticky_worker("wf-1").as_deref(), Some("worker-1"));
+            scheduler
+                .complete_task(&tasks[0].task_id, vec![])
+                .await
+                .unwrap();
+        }
+
+        // workflow 结束后清除分配
+        assert!(scheduler
+            .poll_tasks("worker-2", 10)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(scheduler.sticky_worker("wf-1").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_sticky_falls_back_after_timeout_and_eviction() {
+        let scheduler = sticky_scheduler(Duration::from_millis(50))
+            .await
+            .with_worker_timeout(Duration::from_millis(100));
+        let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        scheduler
+            .complete_task(&tasks[0].task_id, vec![])
+            .await
+            .unwrap();
+
+        // 粘性 worker 超时未领取，其他 worker 接管
+        assert!(scheduler
+            .poll_tasks("worker-2", 10)
+            .await
+            .unwrap()
+            .is_empty());
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        let tasks = scheduler.poll_tasks("worker-2", 10).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].step_name, "infer");
+        assert_eq!(scheduler.sticky_worker("wf-1").as_deref(), Some("worker-2"));
+        scheduler
+            .complete_task(&tasks[0].task_id, vec![])
+            .await
+            .unwrap();
+
+        // 粘性 worker 被移除后，其他 worker 立即可以领取
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(scheduler.touch_worker("worker-1").await);
+        assert_eq!(scheduler.evict_stale_workers().await, vec!["worker-2"]);
+        assert!(scheduler.sticky_worker("wf-1").is_none());
+        let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].step_name, "store");
+    }
+
+    #[tokio::test]
+    async fn test_defined_steps_run_in_sequence() {
+        let scheduler = leased_scheduler(Duration::from_secs(60)).await;
+        scheduler
+            .register_definition(WorkflowDefinition::new(
+                "test-type",
+                vec![
+                    StepDefinition::new("reserve"),
+                    StepDefinition::new("charge"),
+                    StepDefinition::new("ship"),
+                ],
+            ))
+            .await
+            .unwrap();
+
+        for step_name in ["reserve", "charge", "ship"] {
+            let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+            assert_eq!(tasks.len(), 1, "{}", step_name);
+            assert_eq!(tasks[0].step_name, step_name);
+            // 当前 step 完成前不分发下一个
+            assert!(scheduler
+                .poll_tasks("worker-1", 10)
+                .await
+                .unwrap()
+                .is_empty());
+
+            scheduler
+                .complete_task(&tasks[0].task_id, step_name.as_bytes().to_vec())
+                .await
+                .unwrap();
+        }
+
+        let workflow = scheduler
+            .persistence
+            .get_workflow("wf-1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            workflow.state,
+            WorkflowState::Completed {
+                result: b"ship".to_vec().into()
+            }
+        );
+        assert_eq!(workflow.completed_steps.len(), 3);
+        assert!(scheduler
+            .poll_tasks("worker-1", 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_diamond_fans_out_independent_steps() {
+        let scheduler = leased_scheduler(Duration::from_secs(60)).await;
+        scheduler
+            .register_definition(WorkflowDefinition::new(
+                "test-type",
+                vec![
+                    StepDefinition::new("a"),
+                    StepDefinition::new("b").depends_on(["a"]),
+                    StepDefinition::new("c").depends_on(["a"]),
+                    StepDefinition::new("d").depends_on(["b", "c"]),
+                ],
+            ))
+            .await
+            .unwrap();
+        let step_names =
+            |tasks: &[Task]| -> Vec<String> { tasks.iter().map(|t| t.step_name.clone()).collect() };
+
+        let first = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(step_names(&first), vec!["a"]);
+        scheduler
+            .complete_task(&first[0].task_id, b"a".to_vec())
+            .await
+            .unwrap();
+
+        // B 和 C 只依赖 A，同一批次分发
+        let fan_out = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(step_names(&fan_out), vec!["b", "c"]);
+        scheduler
+            .lifecycle()
+            .step_started("wf-1", "b", vec![])
+            .await
+            .unwrap();
+        let execution = scheduler.tracker.get_execution("wf-1").await.unwrap();
+        assert_eq!(execution.step_executions["b"].dependencies, vec!["a"]);
+
+        scheduler
+            .complete_task(&fan_out[0].task_id, b"b".to_vec())
+            .await
+            .unwrap();
+        // D 还在等待 C
+        assert!(scheduler
+            .poll_tasks("worker-1", 10)
+            .await
+            .unwrap()
+            .is_empty());
+        scheduler
+            .complete_task(&fan_out[1].task_id, b"c".to_vec())
+            .await
+            .unwrap();
+
+        let last = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(step_names(&last), vec!["d"]);
+        scheduler
+            .complete_task(&last[0].task_id, b"d".to_vec())
+            .await
+            .unwrap();
+
+        let workflow = scheduler.persistence.get_workflow("wf-1").await.unwrap();
+        assert_eq!(
+            workflow.unwrap().state,
+            WorkflowState::Completed {
+                result: b"d".to_vec().into()
+            }
+        );
+    }
 
-        assert_eq!(count, 1);
+    #[tokio::test]
+    async fn test_cyclic_definition_is_rejected() {
+        let scheduler = Scheduler::new(L0MemoryStore::new());
+        let result = scheduler
+            .register_definition(WorkflowDefinition::new(
+                "cyclic",
+                vec![
+                    StepDefinition::new("a").depends_on(["b"]),
+                    StepDefinition::new("b").depends_on(["a"]),
+                ],
+            ))
+            .await;
+        assert!(matches!(result, Err(DefinitionError::Cycle(_))));
+        assert!(scheduler.definition("cyclic").await.is_none());
+    }
 
-        // 接收事件
-        let event = rx.recv().await.unwrap();
-        assert_eq!(event.workflow_id, "wf-1");
-        assert_eq!(event.event_type, EventType::StepCompleted);
+    #[tokio::test]
+    async fn test_step_waits_for_signal() {
+        let scheduler = leased_scheduler(Duration::from_secs(60)).await;
+        scheduler
+            .register_definition(WorkflowDefinition::new(
+                "test-type",
+                vec![
+                    StepDefinition::new("request"),
+                    StepDefinition::new("approve").wait_for_signal("approved"),
+                ],
+            ))
+            .await
+            .unwrap();
+        let mut events = scheduler.broadcaster.subscribe();
+
+        let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(tasks[0].step_name, "request");
+        assert!(tasks[0].signal.is_none());
+        scheduler
+            .complete_task(&tasks[0].task_id, b"requested".to_vec())
+            .await
+            .unwrap();
+
+        // 依赖已完成，但 signal 到达之前不分发
+        assert!(scheduler
+            .poll_tasks("worker-1", 10)
+            .await
+            .unwrap()
+            .is_empty());
+        scheduler
+            .signal_workflow("wf-1", "other", vec![])
+            .await
+            .unwrap();
+        assert!(scheduler
+            .poll_tasks("worker-1", 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        scheduler
+            .signal_workflow("wf-1", "approved", b"alice".to_vec())
+            .await
+            .unwrap();
+        let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].step_name, "approve");
+        let signal = tasks[0].signal.as_ref().unwrap();
+        assert_eq!(signal.name, "approved");
+        assert_eq!(signal.payload, b"alice".to_vec());
+
+        let received: Vec<_> = std::iter::from_fn(|| events.try_recv().ok())
+            .filter(|e| e.event_type == EventType::SignalReceived)
+            .collect();
+        assert_eq!(received.len(), 2);
+
+        scheduler
+            .complete_task(&tasks[0].task_id, b"done".to_vec())
+            .await
+            .unwrap();
+        let err = scheduler
+            .signal_workflow("wf-1", "approved", vec![])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SignalError::WorkflowFinished(_)));
+        assert!(matches!(
+            scheduler
+                .signal_workflow("missing", "approved", vec![])
+                .await,
+            Err(SignalError::WorkflowNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_timer_step_fires_and_advances() {
+        let scheduler = leased_scheduler(Duration::from_secs(60)).await;
+        scheduler
+            .register_definition(WorkflowDefinition::new(
+                "test-type",
+                vec![
+                    StepDefinition::new("request"),
+                    StepDefinition::timer("wait", Duration::ZERO).depends_on(["request"]),
+                    StepDefinition::new("notify").depends_on(["wait"]),
+                ],
+            ))
+            .await
+            .unwrap();
+
+        let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(tasks[0].step_name, "request");
+        scheduler
+            .complete_task(&tasks[0].task_id, vec![])
+            .await
+            .unwrap();
+
+        // 定时器 step 不分发给 worker
+        assert!(scheduler
+            .poll_tasks("worker-1", 10)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(scheduler.process_timers().await.unwrap(), 1);
+        assert!(scheduler
+            .persistence
+            .list_timers()
+            .await
+            .unwrap()
+            .is_empty());
+
+        let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].step_name, "notify");
+        let workflow = scheduler.persistence.get_workflow("wf-1").await.unwrap();
+        assert!(workflow.unwrap().completed_steps.contains_key("wait"));
+    }
+
+    #[tokio::test]
+    async fn test_pending_timer_is_tracked_and_cancelled() {
+        let scheduler = leased_scheduler(Duration::from_secs(60)).await;
+        scheduler
+            .register_definition(WorkflowDefinition::new(
+                "test-type",
+                vec![StepDefinition::timer("wait", Duration::from_secs(3600))],
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(scheduler.process_timers().await.unwrap(), 0);
+        // 再次处理不会重置触发时间
+        let timers = scheduler.persistence.list_timers().await.unwrap();
+        assert_eq!(scheduler.process_timers().await.unwrap(), 0);
+        assert_eq!(scheduler.persistence.list_timers().await.unwrap(), timers);
+        assert_eq!(timers.len(), 1);
+        assert!(timers[0].fire_at > chrono::Utc::now());
+
+        let execution = scheduler.tracker.get_execution("wf-1").await.unwrap();
+        let step = &execution.step_executions["wait"];
+        assert_eq!(step.status, StepExecutionStatus::Running);
+        assert_eq!(step.timer_fire_at, Some(timers[0].fire_at));
+
+        assert_eq!(scheduler.cancel_timers("wf-1").await.unwrap(), 1);
+        assert!(scheduler
+            .persistence
+            .list_timers()
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_persisted_timer_fires_after_restart() {
+        let scheduler = leased_scheduler(Duration::from_secs(60)).await;
+        scheduler
+            .register_definition(WorkflowDefinition::new(
+                "test-type",
+                vec![StepDefinition::timer("wait", Duration::from_secs(3600))],
+            ))
+            .await
+            .unwrap();
+        // 模拟重启前持久化的、已经到期的定时器
+        scheduler
+            .persistence
+            .save_timer(&crate::timer::Timer {
+                workflow_id: "wf-1".to_string(),
+                step_name: "wait".to_string(),
+                fire_at: chrono::Utc::now() - chrono::Duration::seconds(1),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(scheduler.process_timers().await.unwrap(), 1);
+        let workflow = scheduler.persistence.get_workflow("wf-1").await.unwrap();
+        assert!(workflow.unwrap().state.is_terminal());
+    }
+
+    /// 注册能处理 `workflow_types` 的 worker
+    #[tokio::test]
+    async fn test_running_workflows_keep_their_definition_version() {
+        let scheduler = Scheduler::new(L0MemoryStore::new());
+        register(&scheduler, "worker-1", &["order"]).await;
+        let v1 = WorkflowDefinition::new(
+            "order",
+            vec![StepDefinition::new("reserve"), StepDefinition::new("ship")],
+        );
+        let v2 = WorkflowDefinition::new(
+            "order",
+            vec![
+                StepDefinition::new("reserve"),
+                StepDefinition::new("charge"),
+                StepDefinition::new("ship"),
+            ],
+        )
+        .with_version(2);
+        let start = |id: &str, version: Option<u32>| {
+            scheduler.start_workflow(
+                Some(id.to_string()),
+                "order".to_string(),
+                vec![],
+                StartOptions {
+                    version,
+                    ..Default::default()
+                },
+            )
+        };
+
+        scheduler.register_definition(v1.clone()).await.unwrap();
+        start("old", None).await.unwrap();
+        // 部署新版本时 old 仍在运行
+        scheduler.register_definition(v2).await.unwrap();
+        start("new", None).await.unwrap();
+        start("pinned", Some(1)).await.unwrap();
+        assert!(start("missing", Some(3)).await.is_err());
+
+        // 交替推进三个 workflow，记录各自执行的 step
+        let mut executed: HashMap<String, Vec<String>> = HashMap::new();
+        loop {
+            let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+            if tasks.is_empty() {
+                break;
+            }
+            for task in tasks {
+                executed
+                    .entry(task.workflow_id.clone())
+                    .or_default()
+                    .push(task.step_name.clone());
+                scheduler
+                    .complete_task(&task.task_id, vec![])
+                    .await
+                    .unwrap();
+            }
+        }
+        assert_eq!(executed["old"], vec!["reserve", "ship"]);
+        assert_eq!(executed["pinned"], vec!["reserve", "ship"]);
+        assert_eq!(executed["new"], vec!["reserve", "charge", "ship"]);
+        for (id, version) in [("old", 1), ("pinned", 1), ("new", 2)] {
+            let workflow = scheduler
+                .persistence
+                .get_workflow(id)
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(workflow.is_complete(), "{}", id);
+            assert_eq!(workflow.definition_version, Some(version));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_changed_definition_needs_a_new_version() {
+        let scheduler = Scheduler::new(L0MemoryStore::new());
+        let v1 = WorkflowDefinition::new("order", vec![StepDefinition::new("reserve")]);
+        scheduler.register_definition(v1.clone()).await.unwrap();
+        // 重复注册同一内容不算冲突
+        scheduler.register_definition(v1).await.unwrap();
+
+        let changed = WorkflowDefinition::new("order", vec![StepDefinition::new("charge")]);
+        assert_eq!(
+            scheduler.register_definition(changed.clone()).await,
+            Err(DefinitionError::VersionConflict("order".to_string(), 1))
+        );
+        scheduler
+            .register_definition(changed.with_version(2))
+            .await
+            .unwrap();
+        assert_eq!(scheduler.definition("order").await.unwrap().version, 2);
+        assert_eq!(
+            scheduler
+                .definition_version("order", 1)
+                .await
+                .unwrap()
+                .steps[0]
+                .name,
+            "reserve"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_registered_definitions_are_reloaded_after_restart() {
+        let store = Arc::new(L0MemoryStore::new());
+        let scheduler = Scheduler::new(store.clone());
+        let definition = WorkflowDefinition::new(
+            "order",
+            vec![StepDefinition::new("charge").with_timeout(Duration::from_secs(5))],
+        );
+        scheduler
+            .register_definition(definition.clone())
+            .await
+            .unwrap();
+
+        let restarted = Scheduler::new(store);
+        assert!(restarted.definition("order").await.is_none());
+        assert_eq!(restarted.load_definitions().await.unwrap(), 1);
+        assert_eq!(restarted.definition("order").await, Some(definition));
+        // 已加载的定义不会重复加载
+        assert_eq!(restarted.load_definitions().await.unwrap(), 0);
+    }
+
+    async fn register<P: Persistence>(
+        scheduler: &Scheduler<P>,
+        worker_id: &str,
+        workflow_types: &[&str],
+    ) {
+        scheduler
+            .register_worker(
+                worker_id.to_string(),
+                "test-service".to_string(),
+                "test-group".to_string(),
+                workflow_types.iter().map(|t| t.to_string()).collect(),
+                vec![],
+            )
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_high_priority_tasks_are_dispatched_first() {
+        let scheduler = Scheduler::new(L0MemoryStore::new());
+        for (id, priority) in [
+            ("low", Priority::Low),
+            ("normal", Priority::Normal),
+            ("high", Priority::High),
+        ] {
+            scheduler
+                .start_workflow(
+                    Some(id.to_string()),
+                    "order".to_string(),
+                    vec![],
+                    StartOptions::with_priority(priority),
+                )
+                .await
+                .unwrap();
+        }
+        register(&scheduler, "worker-1", &["order"]).await;
+
+        let ready = scheduler.ready_task_counts().await.unwrap();
+        assert_eq!(ready[&Priority::High], 1);
+        assert_eq!(ready[&Priority::Normal], 1);
+        assert_eq!(ready[&Priority::Low], 1);
+
+        let order: Vec<String> = [
+            scheduler.poll_tasks("worker-1", 1).await.unwrap(),
+            scheduler.poll_tasks("worker-1", 1).await.unwrap(),
+            scheduler.poll_tasks("worker-1", 1).await.unwrap(),
+        ]
+        .into_iter()
+        .map(|tasks| tasks[0].workflow_id.clone())
+        .collect();
+        assert_eq!(order, vec!["high", "normal", "low"]);
+        assert_eq!(
+            scheduler.ready_task_counts().await.unwrap()[&Priority::High],
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limit_holds_under_concurrent_polls() {
+        let scheduler = Scheduler::new(L0MemoryStore::new()).with_concurrency_limit("bulk", 2);
+        for i in 0..6 {
+            scheduler
+                .start_workflow(
+                    None,
+                    "bulk".to_string(),
+                    vec![i],
+                    StartOptions::with_priority(Priority::High),
+                )
+                .await
+                .unwrap();
+        }
+        scheduler
+            .start_workflow(
+                None,
+                "order".to_string(),
+                vec![],
+                StartOptions::with_priority(Priority::Low),
+            )
+            .await
+            .unwrap();
+        for worker_id in ["worker-1", "worker-2", "worker-3"] {
+            register(&scheduler, worker_id, &["bulk", "order"]).await;
+        }
+
+        let polls = ["worker-1", "worker-2", "worker-3"]
+            .map(|worker_id| scheduler.poll_tasks(worker_id, 10));
+        let tasks: Vec<Task> = futures::future::join_all(polls)
+            .await
+            .into_iter()
+            .flat_map(Result::unwrap)
+            .collect();
+        let count = |workflow_type: &str| {
+            tasks
+                .iter()
+                .filter(|task| task.workflow_type == workflow_type)
+                .count()
+        };
+        // 达到上限的类型不会挤占其他类型
+        assert_eq!(count("bulk"), 2);
+        assert_eq!(count("order"), 1);
+
+        // 完成一个后才能再分发一个
+        scheduler
+            .complete_task(&tasks[0].task_id, vec![])
+            .await
+            .unwrap();
+        let more = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(more.len(), 1);
+        assert_eq!(more[0].workflow_type, "bulk");
+        assert!(scheduler
+            .poll_tasks("worker-2", 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_duplicate_starts_create_one_workflow() {
+        let scheduler = Scheduler::new(L0MemoryStore::new());
+        let starts = (0..8).map(|i| {
+            scheduler.start_workflow(
+                Some("order-42".to_string()),
+                "order".to_string(),
+                vec![i],
+                StartOptions::default(),
+            )
+        });
+        let started = futures::future::try_join_all(starts).await.unwrap();
+
+        let created: Vec<_> = started.iter().filter(|s| !s.already_exists).collect();
+        assert_eq!(created.len(), 1);
+        assert!(started.iter().all(|s| s.workflow.id == "order-42"));
+        let workflow = scheduler
+            .persistence
+            .get_workflow("order-42")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(workflow.input, created[0].workflow.input);
+        assert_eq!(
+            scheduler
+                .persistence
+                .list_workflows(None)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(scheduler.tracker.get_execution("order-42").await.is_some());
+    }
+
+    /// 存有 `count` 个运行中 workflow、每第 `every` 次调用失败的调度器
+    async fn faulty_scheduler(count: usize, every: usize) -> Scheduler<FaultyStore<L0MemoryStore>> {
+        let store = L0MemoryStore::new();
+        for i in 0..count {
+            let mut workflow = Workflow::new(format!("wf-{}", i), "order".to_string(), vec![]);
+            workflow.state = workflow.state.try_start().unwrap();
+            store.save_workflow(&workflow).await.unwrap();
+        }
+        let scheduler = Scheduler::new(FaultyStore::new(store, every));
+        register(&scheduler, "worker-1", &["order"]).await;
+        scheduler
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_survives_injected_persistence_faults() {
+        let scheduler = faulty_scheduler(20, 7).await;
+
+        let mut completed = 0;
+        for _ in 0..100 {
+            let tasks = scheduler
+                .poll_tasks("worker-1", 5)
+                .await
+                .expect("transient failures are retried inside the scheduler");
+            for task in tasks {
+                // worker 收到错误后重新上报
+                let mut reported = false;
+                for _ in 0..5 {
+                    if scheduler.complete_task(&task.task_id, vec![]).await.is_ok() {
+                        reported = true;
+                        break;
+                    }
+                }
+                assert!(reported);
+            }
+            let options = ListOptions {
+                state_filter: Some(StateKind::Completed),
+                ..Default::default()
+            };
+            completed = scheduler
+                .persistence
+                .inner()
+                .count_workflows(&options)
+                .await
+                .unwrap();
+            if completed == 20 {
+                break;
+            }
+        }
+        assert_eq!(completed, 20);
+        assert!(scheduler.persistence.failures() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_poll_returns_error_while_persistence_is_down() {
+        let scheduler = faulty_scheduler(1, 1).await;
+        assert!(scheduler.poll_tasks("worker-1", 10).await.is_err());
+        assert!(scheduler.ready_task_counts().await.is_err());
+
+        // 存储恢复后，之前未能加载的 workflow 照常分发
+        scheduler.persistence.fail_every(0);
+        let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].workflow_id, "wf-0");
+    }
+
+    /// 注册服务及其声明的 activity 资源，再注册属于该服务的 worker
+    async fn register_service(
+        scheduler: &Scheduler<L0MemoryStore>,
+        worker_id: &str,
+        service_name: &str,
+        resources: &[&str],
+    ) {
+        let provides: Vec<crate::task::ServiceResource> = resources
+            .iter()
+            .map(|name| crate::task::ServiceResource {
+                name: name.to_string(),
+                resource_type: ResourceType::Activity,
+                metadata: None,
+            })
+            .collect();
+        scheduler.service_registry.register(
+            service_name.to_string(),
+            worker_id.to_string(),
+            "default".to_string(),
+            vec![],
+            provides.clone(),
+            String::new(),
+        );
+        scheduler
+            .register_worker(
+                worker_id.to_string(),
+                service_name.to_string(),
+                "default".to_string(),
+                vec![],
+                provides
+                    .into_iter()
+                    .map(|r| (r.name, r.resource_type))
+                    .collect(),
+            )
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_targeted_steps_route_by_declared_resources() {
+        let scheduler = Scheduler::new(L0MemoryStore::new());
+        scheduler
+            .register_definition(WorkflowDefinition::new(
+                "order",
+                vec![StepDefinition::new("charge")
+                    .with_target("billing", "charge")
+                    .with_resource_type(ResourceType::Activity)],
+            ))
+            .await
+            .unwrap();
+        scheduler
+            .start_workflow(None, "order".to_string(), vec![], StartOptions::default())
+            .await
+            .unwrap();
+
+        // 其他服务声明了同名资源，目标服务没有声明该资源
+        register_service(&scheduler, "shop-1", "shop", &["charge"]).await;
+        register_service(&scheduler, "billing-1", "billing", &["refund"]).await;
+        assert!(scheduler.poll_tasks("shop-1", 10).await.unwrap().is_empty());
+        assert!(scheduler
+            .poll_tasks("billing-1", 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // 只有声明了该资源的实例领取 task
+        register_service(&scheduler, "billing-2", "billing", &["charge", "refund"]).await;
+        assert!(scheduler
+            .poll_tasks("billing-1", 10)
+            .await
+            .unwrap()
+            .is_empty());
+        let tasks = scheduler.poll_tasks("billing-2", 10).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].step_name, "charge");
+    }
+
+    /// 启动 workflow，billing-1 领取到 task 时以 `output` 完成，返回 workflow 的状态和完成时的错误
+    async fn schema_checked_run(
+        scheduler: &Scheduler<L0MemoryStore>,
+        input: &str,
+        output: &str,
+        options: StartOptions,
+    ) -> (WorkflowState, Option<StepLifecycleError>) {
+        let workflow_id = scheduler
+            .start_workflow(
+                None,
+                "order".to_string(),
+                input.as_bytes().to_vec(),
+                options,
+            )
+            .await
+            .unwrap()
+            .workflow
+            .id;
+        let tasks = scheduler.poll_tasks("billing-1", 10).await.unwrap();
+        let error = match tasks.first() {
+            Some(task) => scheduler
+                .lifecycle()
+                .complete_task(&task.task_id, Some("billing-1"), output.as_bytes().to_vec())
+                .await
+                .err(),
+            None => None,
+        };
+        let workflow = scheduler
+            .persistence
+            .get_workflow(&workflow_id)
+            .await
+            .unwrap()
+            .unwrap();
+        (workflow.state, error)
+    }
+
+    #[tokio::test]
+    async fn test_schema_violations_fail_the_step() {
+        let scheduler = Scheduler::new(L0MemoryStore::new());
+        scheduler
+            .register_definition(WorkflowDefinition::new(
+                "order",
+                vec![StepDefinition::new("charge")
+                    .with_target("billing", "charge")
+                    .with_resource_type(ResourceType::Activity)],
+            ))
+            .await
+            .unwrap();
+        let charge = crate::task::ServiceResource {
+            name: "charge".to_string(),
+            resource_type: ResourceType::Activity,
+            metadata: Some(crate::task::ResourceMetadata {
+                max_attempts: None,
+                timeout: None,
+                input_schema: Some(
+                    r#"{"type": "object", "required": ["amount"],
+                        "properties": {"amount": {"type": "number"}}}"#
+                        .to_string(),
+                ),
+                output_schema: Some(r#"{"type": "object", "required": ["receipt"]}"#.to_string()),
+                cacheable: false,
+            }),
+        };
+        scheduler.service_registry.register(
+            "billing".to_string(),
+            "billing-1".to_string(),
+            "default".to_string(),
+            vec![],
+            vec![charge],
+            String::new(),
+        );
+        scheduler
+            .register_worker(
+                "billing-1".to_string(),
+                "billing".to_string(),
+                "default".to_string(),
+                vec![],
+                vec![("charge".to_string(), ResourceType::Activity)],
+            )
+            .await;
+
+        // 输入不符合：不分发，step 直接失败
+        let (state, _) = schema_checked_run(
+            &scheduler,
+            r#"{"amount": "ten"}"#,
+            "{}",
+            StartOptions::default(),
+        )
+        .await;
+        let WorkflowState::Failed { error } = state else {
+            panic!("expected failure, got {:?}", state);
+        };
+        assert!(error.starts_with("SCHEMA_VALIDATION: input of resource 'charge'"));
+        assert!(error.contains("/amount"), "{}", error);
+
+        // 输出不符合：完成被拒绝，结果不保存
+        let (state, error) = schema_checked_run(
+            &scheduler,
+            r#"{"amount": 10}"#,
+            r#"{"status": "ok"}"#,
+            StartOptions::default(),
+        )
+        .await;
+        assert!(matches!(
+            error,
+            Some(StepLifecycleError::SchemaValidation(ref v)) if v.target == SchemaTarget::Output
+        ));
+        assert!(
+            matches!(state, WorkflowState::Failed { ref error } if error.contains("receipt")),
+            "{:?}",
+            state
+        );
+
+        let (state, error) = schema_checked_run(
+            &scheduler,
+            r#"{"amount": 10}"#,
+            r#"{"receipt": "r-1"}"#,
+            StartOptions::default(),
+        )
+        .await;
+        assert!(error.is_none());
+        assert!(matches!(state, WorkflowState::Completed { .. }));
+
+        // 启动时跳过校验
+        let skip = StartOptions {
+            skip_schema_validation: true,
+            ..Default::default()
+        };
+        let (state, error) =
+            schema_checked_run(&scheduler, r#"{"amount": "ten"}"#, "{}", skip).await;
+        assert!(error.is_none());
+        assert!(matches!(state, WorkflowState::Completed { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_cacheable_step_is_served_from_cache() {
+        let scheduler = Scheduler::new(L0MemoryStore::new());
+        scheduler
+            .register_definition(WorkflowDefinition::new(
+                "lookup",
+                vec![StepDefinition::new("geo")
+                    .with_target("geo", "geo")
+                    .with_resource_type(ResourceType::Activity)],
+            ))
+            .await
+            .unwrap();
+        let geo = crate::task::ServiceResource {
+            name: "geo".to_string(),
+            resource_type: ResourceType::Activity,
+            metadata: Some(crate::task::ResourceMetadata {
+                max_attempts: None,
+                timeout: None,
+                input_schema: None,
+                output_schema: None,
+                cacheable: true,
+            }),
+        };
+        scheduler.service_registry.register(
+            "geo".to_string(),
+            "geo-1".to_string(),
+            "default".to_string(),
+            vec![],
+            vec![geo],
+            String::new(),
+        );
+        scheduler
+            .register_worker(
+                "geo-1".to_string(),
+                "geo".to_string(),
+                "default".to_string(),
+                vec![],
+                vec![("geo".to_string(), ResourceType::Activity)],
+            )
+            .await;
+
+        let start = |id: &str| {
+            scheduler.start_workflow(
+                Some(id.to_string()),
+                "lookup".to_string(),
+                br#"{"ip": "1.2.3.4"}"#.to_vec(),
+                StartOptions::default(),
+            )
+        };
+        start("wf-1").await.unwrap();
+        let tasks = scheduler.poll_tasks("geo-1", 10).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        scheduler
+            .lifecycle()
+            .step_started("wf-1", "geo", tasks[0].input.data.clone())
+            .await
+            .unwrap();
+        scheduler
+            .lifecycle()
+            .complete_task(&tasks[0].task_id, Some("geo-1"), b"EU".to_vec())
+            .await
+            .unwrap();
+
+        // 相同输入：step 就绪时直接用缓存的结果完成，不需要 worker 来 poll
+        start("wf-2").await.unwrap();
+        assert_eq!(scheduler.serve_cached_steps().await.unwrap(), 1);
+        assert!(scheduler.poll_tasks("geo-1", 10).await.unwrap().is_empty());
+        let workflow = scheduler
+            .persistence
+            .get_workflow("wf-2")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(
+            matches!(workflow.state, WorkflowState::Completed { ref result } if result.as_bytes() == b"EU"),
+            "{:?}",
+            workflow.state
+        );
+
+        let execution = scheduler.tracker.get_execution("wf-2").await.unwrap();
+        let step = &execution.step_executions["geo"];
+        assert_eq!(step.status, StepExecutionStatus::Completed);
+        assert!(step.cache_hit);
+        let execution = scheduler.tracker.get_execution("wf-1").await.unwrap();
+        assert!(!execution.step_executions["geo"].cache_hit);
+
+        let stats = scheduler.step_cache_stats();
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+
+        // 输入不同时照常分发
+        scheduler
+            .start_workflow(
+                Some("wf-3".to_string()),
+                "lookup".to_string(),
+                br#"{"ip": "5.6.7.8"}"#.to_vec(),
+                StartOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(scheduler.poll_tasks("geo-1", 10).await.unwrap().len(), 1);
+        assert_eq!(scheduler.step_cache_stats().misses, 2);
+    }
+
+    #[tokio::test]
+    async fn test_step_marked_cacheable_is_served_without_workers() {
+        let scheduler = Scheduler::new(L0MemoryStore::new());
+        scheduler
+            .register_definition(WorkflowDefinition::new(
+                "lookup",
+                vec![StepDefinition::new("geo").cacheable()],
+            ))
+            .await
+            .unwrap();
+        let input = br#"{"ip": "1.2.3.4"}"#.to_vec();
+        scheduler
+            .persistence
+            .put_cached_result(
+                &CachedResult::new(
+                    crate::step_cache::cache_key("geo", &input),
+                    b"EU".to_vec(),
+                    Duration::from_secs(60),
+                ),
+                10,
+            )
+            .await
+            .unwrap();
+
+        scheduler
+            .start_workflow(
+                Some("wf-1".to_string()),
+                "lookup".to_string(),
+                input,
+                StartOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(scheduler.serve_cached_steps().await.unwrap(), 1);
+        let workflow = scheduler
+            .persistence
+            .get_workflow("wf-1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(
+            matches!(workflow.state, WorkflowState::Completed { ref result } if result.as_bytes() == b"EU"),
+            "{:?}",
+            workflow.state
+        );
+        assert_eq!(scheduler.step_cache_stats().hits, 1);
     }
 }
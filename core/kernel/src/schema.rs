@@ -0,0 +1,188 @@
+//! 资源的 JSON Schema 校验
+//!
+//! 服务注册时为资源声明的 `input_schema` / `output_schema` 在注册时编译检查，格式错误的拒绝注册。
+//! task 分发前按执行实例声明的 schema 校验输入，step 完成时校验输出，
+//! 不符合的 step 以 `SCHEMA_VALIDATION` 错误直接失败（不重试），避免错误数据传到下游。
+//! workflow 启动时可以关闭校验，用于紧急情况。
+
+use std::fmt;
+
+use jsonschema::JSONSchema;
+use serde_json::Value;
+
+use crate::task::ServiceResource;
+
+/// schema 校验失败时 step 错误信息的前缀
+pub const SCHEMA_VALIDATION: &str = "SCHEMA_VALIDATION";
+
+/// 被校验的数据
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaTarget {
+    Input,
+    Output,
+}
+
+impl SchemaTarget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SchemaTarget::Input => "input",
+            SchemaTarget::Output => "output",
+        }
+    }
+}
+
+/// 数据不符合资源声明的 schema
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaViolation {
+    pub resource: String,
+    pub target: SchemaTarget,
+    /// 每条不符合项，格式为 `{JSON Pointer}: {原因}`
+    pub details: Vec<String>,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} of resource '{}' does not match its schema: {}",
+            SCHEMA_VALIDATION,
+            self.target.as_str(),
+            self.resource,
+            self.details.join("; ")
+        )
+    }
+}
+
+impl std::error::Error for SchemaViolation {}
+
+/// 编译 schema，schema 不是合法的 JSON 或 JSON Schema 时返回原因
+pub fn compile(schema: &str) -> Result<JSONSchema, String> {
+    let schema: Value =
+        serde_json::from_str(schema).map_err(|e| format!("schema is not valid JSON: {}", e))?;
+    JSONSchema::compile(&schema).map_err(|e| format!("invalid JSON Schema: {}", e))
+}
+
+/// 检查资源声明的 schema 都能编译，用于服务注册
+pub fn check_resources(resources: &[ServiceResource]) -> Result<(), String> {
+    for resource in resources {
+        let Some(metadata) = &resource.metadata else {
+            continue;
+        };
+        for (target, schema) in [
+            (SchemaTarget::Input, &metadata.input_schema),
+            (SchemaTarget::Output, &metadata.output_schema),
+        ] {
+            if let Some(schema) = schema {
+                compile(schema).map_err(|e| {
+                    format!(
+                        "{} schema of resource '{}': {}",
+                        target.as_str(),
+                        resource.name,
+                        e
+                    )
+                })?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 按 schema 校验 JSON 数据，空数据按 `null` 校验
+///
+/// 注册时已检查过 schema，这里编译失败同样视为不符合。
+pub fn validate(
+    resource: &str,
+    target: SchemaTarget,
+    schema: &str,
+    data: &[u8],
+) -> Result<(), SchemaViolation> {
+    let violation = |details: Vec<String>| SchemaViolation {
+        resource: resource.to_string(),
+        target,
+        details,
+    };
+    let compiled = compile(schema).map_err(|e| violation(vec![e]))?;
+    let instance: Value = if data.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(data).map_err(|e| {
+            violation(vec![format!(
+                "{} is not valid JSON: {}",
+                target.as_str(),
+                e
+            )])
+        })?
+    };
+    compiled.validate(&instance).map_err(|errors| {
+        violation(
+            errors
+                .map(|error| {
+                    let path = error.instance_path.to_string();
+                    let path = if path.is_empty() { "/" } else { &path };
+                    format!("{}: {}", path, error)
+                })
+                .collect(),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::{ResourceMetadata, ResourceType};
+
+    const AMOUNT_SCHEMA: &str = r#"{
+        "type": "object",
+        "properties": {"amount": {"type": "number", "minimum": 0}},
+        "required": ["amount"]
+    }"#;
+
+    #[test]
+    fn test_validate_reports_each_violation() {
+        assert!(validate(
+            "charge",
+            SchemaTarget::Input,
+            AMOUNT_SCHEMA,
+            br#"{"amount": 5}"#
+        )
+        .is_ok());
+
+        let violation = validate(
+            "charge",
+            SchemaTarget::Input,
+            AMOUNT_SCHEMA,
+            br#"{"amount": -1}"#,
+        )
+        .unwrap_err();
+        assert_eq!(violation.details.len(), 1);
+        assert!(violation.details[0].starts_with("/amount: "));
+        let message = violation.to_string();
+        assert!(message.starts_with("SCHEMA_VALIDATION: input of resource 'charge'"));
+
+        let violation =
+            validate("charge", SchemaTarget::Output, AMOUNT_SCHEMA, b"not json").unwrap_err();
+        assert!(violation.details[0].starts_with("output is not valid JSON"));
+        assert!(validate("charge", SchemaTarget::Output, AMOUNT_SCHEMA, b"").is_err());
+    }
+
+    #[test]
+    fn test_malformed_schemas_are_rejected() {
+        let resource = |input_schema: &str| ServiceResource {
+            name: "charge".to_string(),
+            resource_type: ResourceType::Activity,
+            metadata: Some(ResourceMetadata {
+                max_attempts: None,
+                timeout: None,
+                input_schema: Some(input_schema.to_string()),
+                output_schema: None,
+                cacheable: false,
+            }),
+        };
+        assert!(check_resources(&[resource(AMOUNT_SCHEMA)]).is_ok());
+
+        let error = check_resources(&[resource("{not json")]).unwrap_err();
+        assert!(error.starts_with("input schema of resource 'charge': schema is not valid JSON"));
+        let error = check_resources(&[resource(r#"{"type": 12}"#)]).unwrap_err();
+        assert!(error.contains("invalid JSON Schema"), "{}", error);
+    }
+}
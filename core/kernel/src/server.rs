@@ -1,21 +1,393 @@
+use std::future::Future;
 use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::watch;
 use tower_http::trace::TraceLayer;
 
-use crate::api::routes::create_router;
+use crate::api::auth::AuthConfig;
+use crate::api::routes::create_router_with_auth;
+use crate::grpc_server::{serve_grpc_with_options, GrpcOptions};
 use crate::persistence::Persistence;
 use crate::scheduler::Scheduler;
 
 pub async fn start_server<P: Persistence + Clone + Send + Sync + 'static>(
-    scheduler: Scheduler<P>,
+    scheduler: Arc<Scheduler<P>>,
     listen_addr: &str,
 ) -> anyhow::Result<()> {
-    let scheduler = Arc::new(scheduler);
+    start_server_with_auth(scheduler, listen_addr, AuthConfig::default()).await
+}
+
+/// Start the REST API, requiring an `X-Api-Key` header when `auth` has keys
+pub async fn start_server_with_auth<P: Persistence + Clone + Send + Sync + 'static>(
+    scheduler: Arc<Scheduler<P>>,
+    listen_addr: &str,
+    auth: AuthConfig,
+) -> anyhow::Result<()> {
+    start_server_with_shutdown(scheduler, listen_addr, auth, std::future::pending()).await
+}
 
-    let app = create_router(scheduler).layer(TraceLayer::new_for_http());
+/// Start the REST API and stop gracefully once `shutdown` completes
+pub async fn start_server_with_shutdown<P: Persistence + Clone + Send + Sync + 'static>(
+    scheduler: Arc<Scheduler<P>>,
+    listen_addr: &str,
+    auth: AuthConfig,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(listen_addr).await?;
+    serve_rest(scheduler, listener, auth, shutdown).await
+}
 
-    let listener = tokio::net::TcpListener::bind(listen_addr).await?;
-    tracing::info!("REST API server listening on {}", listen_addr);
+/// Like [`start_server_with_shutdown`], on an already bound `listener`
+pub async fn serve_rest<P: Persistence + Clone + Send + Sync + 'static>(
+    scheduler: Arc<Scheduler<P>>,
+    listener: TcpListener,
+    auth: AuthConfig,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> anyhow::Result<()> {
+    let app = create_router_with_auth(scheduler, auth).layer(TraceLayer::new_for_http());
+    tracing::info!("REST API server listening on {}", listener.local_addr()?);
 
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown)
+        .await?;
     Ok(())
 }
+
+/// Serve the REST API and gRPC on one shared scheduler.
+///
+/// Once `shutdown` completes the scheduler stops dispatching new tasks and
+/// waits up to its shutdown grace period for in-flight steps, which can still
+/// be reported over either API. Then both servers drain, worker streams are
+/// closed and persistence is flushed. Returns as soon as either server fails.
+///
+/// gRPC reflection and health services are enabled; use [`serve_with_options`]
+/// to turn them off.
+pub async fn serve<P: Persistence + Clone + Send + Sync + 'static>(
+    scheduler: Arc<Scheduler<P>>,
+    rest_addr: &str,
+    grpc_addr: &str,
+    auth: AuthConfig,
+    shutdown: impl Future<Output = ()>,
+) -> anyhow::Result<()> {
+    serve_with_options(
+        scheduler,
+        rest_addr,
+        grpc_addr,
+        auth,
+        GrpcOptions::default(),
+        shutdown,
+    )
+    .await
+}
+
+/// Like [`serve`], choosing which optional gRPC services to expose and which
+/// bearer tokens gRPC requests must carry.
+///
+/// The health service reports NOT_SERVING as soon as the shutdown drain
+/// starts, so load balancers stop sending new work before the servers close.
+pub async fn serve_with_options<P: Persistence + Clone + Send + Sync + 'static>(
+    scheduler: Arc<Scheduler<P>>,
+    rest_addr: &str,
+    grpc_addr: &str,
+    auth: AuthConfig,
+    grpc_options: GrpcOptions,
+    shutdown: impl Future<Output = ()>,
+) -> anyhow::Result<()> {
+    let rest = TcpListener::bind(rest_addr).await?;
+    let grpc = TcpListener::bind(grpc_addr).await?;
+    serve_listeners(scheduler, rest, grpc, auth, grpc_options, shutdown).await
+}
+
+/// Like [`serve_with_options`], on already bound listeners.
+///
+/// Binding first lets callers listen on port 0 and read the actual addresses
+/// before serving.
+pub async fn serve_listeners<P: Persistence + Clone + Send + Sync + 'static>(
+    scheduler: Arc<Scheduler<P>>,
+    rest: TcpListener,
+    grpc: TcpListener,
+    auth: AuthConfig,
+    grpc_options: GrpcOptions,
+    shutdown: impl Future<Output = ()>,
+) -> anyhow::Result<()> {
+    let (stop, _) = watch::channel(());
+    let until_stopped = |mut stopped: watch::Receiver<()>| async move {
+        let _ = stopped.changed().await;
+    };
+    // Subscribe before either branch runs so a stop sent before the servers
+    // are first polled is not missed
+    let rest_stopped = until_stopped(stop.subscribe());
+    let grpc_stopped = until_stopped(stop.subscribe());
+
+    let servers = async {
+        tokio::try_join!(
+            serve_rest(scheduler.clone(), rest, auth, rest_stopped),
+            serve_grpc_with_options(scheduler.clone(), grpc, grpc_options, grpc_stopped),
+        )
+        .map(|_| ())
+    };
+    let signal = async {
+        shutdown.await;
+        tracing::info!(
+            grace_secs = scheduler.shutdown_grace().as_secs(),
+            "Shutting down, waiting for in-flight steps"
+        );
+        scheduler.shutdown(scheduler.shutdown_grace()).await;
+        tracing::info!("Shutting down REST and gRPC servers");
+        let _ = stop.send(());
+        // Keep waiting for the servers to drain
+        std::future::pending::<()>().await
+    };
+
+    let result = tokio::select! {
+        result = servers => result,
+        _ = signal => unreachable!(),
+    };
+    scheduler.persistence.flush().await?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grpc_server::start_grpc_server_with_options;
+    use crate::persistence::l0_memory::L0MemoryStore;
+    use crate::proto::client_service_client::ClientServiceClient;
+    use crate::proto::{GetStatusRequest, State};
+    use crate::shutdown::ShutdownState;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn free_port() -> u16 {
+        std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+    }
+
+    /// Minimal HTTP/1.1 POST returning the JSON body, retried until the server is up
+    async fn post_json(port: u16, path: &str, body: serde_json::Value) -> serde_json::Value {
+        let body = body.to_string();
+        let request = format!(
+            "POST {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            path,
+            body.len(),
+            body
+        );
+        for _ in 0..50 {
+            let Ok(mut stream) = tokio::net::TcpStream::connect(("127.0.0.1", port)).await else {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                continue;
+            };
+            stream.write_all(request.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+            let (head, mut body) = response.split_once("\r\n\r\n").unwrap();
+            // Compressed or not, responses go through the compression layer and are chunked
+            let mut json = String::new();
+            if head
+                .to_ascii_lowercase()
+                .contains("transfer-encoding: chunked")
+            {
+                while let Some((size, rest)) = body.split_once("\r\n") {
+                    let size = usize::from_str_radix(size, 16).unwrap();
+                    if size == 0 {
+                        break;
+                    }
+                    json.push_str(&rest[..size]);
+                    body = &rest[size + 2..];
+                }
+            } else {
+                json.push_str(body);
+            }
+            return serde_json::from_str(&json).unwrap();
+        }
+        panic!("REST server did not start on port {}", port);
+    }
+
+    #[tokio::test]
+    async fn test_rest_and_grpc_share_one_scheduler() {
+        let scheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        let (rest_port, grpc_port) = (free_port(), free_port());
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn({
+            let scheduler = scheduler.clone();
+            async move {
+                serve(
+                    scheduler,
+                    &format!("127.0.0.1:{}", rest_port),
+                    &format!("127.0.0.1:{}", grpc_port),
+                    AuthConfig::default(),
+                    async {
+                        let _ = stopped.await;
+                    },
+                )
+                .await
+            }
+        });
+
+        let created = post_json(
+            rest_port,
+            "/workflows",
+            serde_json::json!({ "workflowType": "order", "input": { "id": 1 } }),
+        )
+        .await;
+        let workflow_id = created["workflowId"].as_str().unwrap().to_string();
+
+        let mut client = ClientServiceClient::connect(format!("http://127.0.0.1:{}", grpc_port))
+            .await
+            .unwrap();
+        let status = client
+            .get_workflow_status(GetStatusRequest {
+                workflow_id: workflow_id.clone(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(status.workflow_id, workflow_id);
+        assert_eq!(status.state, State::Running as i32);
+        drop(client);
+
+        stop.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("servers should shut down")
+            .unwrap()
+            .unwrap();
+        assert_eq!(scheduler.shutdown_state(), ShutdownState::Stopped);
+    }
+
+    /// Connect to the gRPC server, retrying until it is up
+    async fn grpc_channel(port: u16) -> tonic::transport::Channel {
+        let endpoint =
+            tonic::transport::Endpoint::from_shared(format!("http://127.0.0.1:{}", port)).unwrap();
+        for _ in 0..50 {
+            if let Ok(channel) = endpoint.connect().await {
+                return channel;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("gRPC server did not start on port {}", port);
+    }
+
+    async fn list_services(channel: tonic::transport::Channel) -> Vec<String> {
+        use tonic_reflection::pb::server_reflection_client::ServerReflectionClient;
+        use tonic_reflection::pb::server_reflection_request::MessageRequest;
+        use tonic_reflection::pb::server_reflection_response::MessageResponse;
+        use tonic_reflection::pb::ServerReflectionRequest;
+
+        let request = ServerReflectionRequest {
+            host: String::new(),
+            message_request: Some(MessageRequest::ListServices(String::new())),
+        };
+        let mut responses = ServerReflectionClient::new(channel)
+            .server_reflection_info(tokio_stream::once(request))
+            .await
+            .unwrap()
+            .into_inner();
+        let response = responses.message().await.unwrap().unwrap();
+        match response.message_response {
+            Some(MessageResponse::ListServicesResponse(list)) => {
+                list.service.into_iter().map(|s| s.name).collect()
+            }
+            other => panic!("unexpected reflection response: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_grpc_reflection_and_health() {
+        use tonic_health::pb::health_check_response::ServingStatus;
+        use tonic_health::pb::health_client::HealthClient;
+        use tonic_health::pb::HealthCheckRequest;
+
+        let scheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        let port = free_port();
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn({
+            let scheduler = scheduler.clone();
+            async move {
+                start_grpc_server_with_options(
+                    scheduler,
+                    &format!("127.0.0.1:{}", port),
+                    GrpcOptions::default(),
+                    async {
+                        let _ = stopped.await;
+                    },
+                )
+                .await
+            }
+        });
+        let channel = grpc_channel(port).await;
+
+        let services = list_services(channel.clone()).await;
+        for service in [
+            "aether.v1.ClientService",
+            "aether.v1.WorkerService",
+            "aether.v1.AdminService",
+            "grpc.health.v1.Health",
+        ] {
+            assert!(services.iter().any(|s| s == service), "{:?}", services);
+        }
+
+        let mut health = HealthClient::new(channel);
+        let check = |service: &str| HealthCheckRequest {
+            service: service.to_string(),
+        };
+        let status = health.check(check("")).await.unwrap().into_inner().status;
+        assert_eq!(status, ServingStatus::Serving as i32);
+
+        // Draining flips every service to NOT_SERVING
+        scheduler.begin_shutdown();
+        let mut status = 0;
+        for _ in 0..50 {
+            status = health
+                .check(check("aether.v1.WorkerService"))
+                .await
+                .unwrap()
+                .into_inner()
+                .status;
+            if status == ServingStatus::NotServing as i32 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(status, ServingStatus::NotServing as i32);
+
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_grpc_optional_services_can_be_disabled() {
+        use tonic_health::pb::health_client::HealthClient;
+        use tonic_health::pb::HealthCheckRequest;
+
+        let scheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        let port = free_port();
+        let options = GrpcOptions::default()
+            .with_reflection(false)
+            .with_health(false);
+        let server = tokio::spawn(async move {
+            start_grpc_server_with_options(
+                scheduler,
+                &format!("127.0.0.1:{}", port),
+                options,
+                std::future::pending(),
+            )
+            .await
+        });
+        let channel = grpc_channel(port).await;
+
+        let error = HealthClient::new(channel)
+            .check(HealthCheckRequest {
+                service: String::new(),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), tonic::Code::Unimplemented);
+        server.abort();
+    }
+}
@@ -0,0 +1,126 @@
+//! 服务器能力信息
+//!
+//! SDK 在使用较新的功能前通过 `GetServerInfo` 或 `GET /info` 查询服务器能力。
+//! gRPC、REST 和 CLI 都从 [`ServerInfo`] 生成输出；它的 JSON 字段名属于对外协议，
+//! 重命名即为破坏性变更。
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::persistence::Persistence;
+use crate::scheduler::Scheduler;
+
+/// 客户端协议版本，新增 SDK 可感知的行为时递增
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// 默认单个请求体上限（与 tonic 默认解码上限一致）
+pub const DEFAULT_MAX_PAYLOAD_BYTES: u64 = 4 * 1024 * 1024;
+
+/// 默认单次批量操作的条目上限
+pub const DEFAULT_MAX_BATCH_SIZE: u32 = 100;
+
+/// 服务器版本、功能与限制
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerInfo {
+    /// kernel crate 版本
+    pub version: String,
+    pub protocol_version: u32,
+    /// 编译时启用的功能
+    pub features: Vec<String>,
+    pub subsystems: Subsystems,
+    pub limits: ServerLimits,
+}
+
+/// 可选子系统的开关
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Subsystems {
+    pub auth: bool,
+    pub namespaces: bool,
+}
+
+/// 服务器对请求施加的限制
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerLimits {
+    /// 单个请求体的最大字节数
+    pub max_payload_bytes: u64,
+    /// 单次批量操作（如 poll）的最大条目数
+    pub max_batch_size: u32,
+}
+
+impl Default for ServerLimits {
+    fn default() -> Self {
+        ServerLimits {
+            max_payload_bytes: DEFAULT_MAX_PAYLOAD_BYTES,
+            max_batch_size: DEFAULT_MAX_BATCH_SIZE,
+        }
+    }
+}
+
+impl ServerInfo {
+    /// 当前构建在给定限制下的服务器信息
+    pub fn new(limits: ServerLimits) -> Self {
+        let mut features = vec!["grpc".to_string(), "rest".to_string()];
+        if cfg!(feature = "dashboard") {
+            features.push("dashboard".to_string());
+        }
+        if cfg!(feature = "sqlite") {
+            features.push("sqlite".to_string());
+        }
+
+        ServerInfo {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            protocol_version: PROTOCOL_VERSION,
+            features,
+            // 认证与命名空间尚未实现
+            subsystems: Subsystems::default(),
+            limits,
+        }
+    }
+}
+
+impl<P: Persistence> Scheduler<P> {
+    /// 该调度器所服务的服务器信息
+    pub fn server_info(&self) -> ServerInfo {
+        ServerInfo::new(self.limits().clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialization_is_stable() {
+        let info = ServerInfo {
+            version: "1.2.3".to_string(),
+            protocol_version: 1,
+            features: vec!["grpc".to_string()],
+            subsystems: Subsystems {
+                auth: true,
+                namespaces: false,
+            },
+            limits: ServerLimits {
+                max_payload_bytes: 1024,
+                max_batch_size: 10,
+            },
+        };
+
+        // SDK 依赖这些字段名，修改此断言意味着协议不兼容
+        assert_eq!(
+            serde_json::to_string(&info).unwrap(),
+            r#"{"version":"1.2.3","protocolVersion":1,"features":["grpc"],"subsystems":{"auth":true,"namespaces":false},"limits":{"maxPayloadBytes":1024,"maxBatchSize":10}}"#
+        );
+    }
+
+    #[test]
+    fn test_current_build() {
+        let info = ServerInfo::new(ServerLimits::default());
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(info.protocol_version, PROTOCOL_VERSION);
+        assert!(info.features.contains(&"grpc".to_string()));
+        assert_eq!(info.limits.max_batch_size, DEFAULT_MAX_BATCH_SIZE);
+    }
+}
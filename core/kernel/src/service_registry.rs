@@ -2,21 +2,34 @@ use crate::task::{ResourceType, ServiceResource};
 use std::collections::HashMap;
 use std::sync::RwLock;
 
-/// Service registration information
+/// Service registration information for one running instance
 #[derive(Debug, Clone)]
 pub struct ServiceInfo {
     pub service_name: String,
+    /// Identifies the instance among replicas of the same service (the worker id)
+    pub instance_id: String,
     pub group: String,
     pub languages: Vec<String>,
     pub provides: HashMap<String, ServiceResource>,
     pub endpoint: String,
     pub registered_at: chrono::DateTime<chrono::Utc>,
+    pub last_heartbeat: chrono::DateTime<chrono::Utc>,
+}
+
+/// Registered instances of one service, in registration order
+#[derive(Debug, Default)]
+struct ServiceInstances {
+    instances: Vec<ServiceInfo>,
+    /// Round-robin cursor for `pick_instance`
+    next: usize,
 }
 
 /// Service registry for cross-language support
+///
+/// A service can run several instances (replicas); each registers under its own instance id.
 #[derive(Debug, Default)]
 pub struct ServiceRegistry {
-    services: RwLock<HashMap<String, ServiceInfo>>,
+    services: RwLock<HashMap<String, ServiceInstances>>,
 }
 
 impl ServiceRegistry {
@@ -27,10 +40,11 @@ impl ServiceRegistry {
         }
     }
 
-    /// Register a service
+    /// Register a service instance, replacing an earlier registration with the same instance id
     pub fn register(
         &self,
         service_name: String,
+        instance_id: String,
         group: String,
         languages: Vec<String>,
         provides: Vec<ServiceResource>,
@@ -40,30 +54,85 @@ impl ServiceRegistry {
 
         let provides_map: HashMap<String, ServiceResource> =
             provides.into_iter().map(|r| (r.name.clone(), r)).collect();
+        let now = chrono::Utc::now();
+        let info = ServiceInfo {
+            service_name: service_name.clone(),
+            instance_id,
+            group,
+            languages,
+            provides: provides_map,
+            endpoint,
+            registered_at: now,
+            last_heartbeat: now,
+        };
+
+        let service = services.entry(service_name).or_default();
+        match service
+            .instances
+            .iter_mut()
+            .find(|i| i.instance_id == info.instance_id)
+        {
+            Some(existing) => *existing = info,
+            None => service.instances.push(info),
+        }
+    }
 
-        services.insert(
-            service_name.clone(),
-            ServiceInfo {
-                service_name,
-                group,
-                languages,
-                provides: provides_map,
-                endpoint,
-                registered_at: chrono::Utc::now(),
-            },
-        );
+    /// Unregister a single instance; the service is removed with its last instance
+    pub fn unregister(&self, service_name: &str, instance_id: &str) -> bool {
+        let mut services = self.services.write().unwrap();
+        let Some(service) = services.get_mut(service_name) else {
+            return false;
+        };
+        let before = service.instances.len();
+        service.instances.retain(|i| i.instance_id != instance_id);
+        let removed = service.instances.len() < before;
+        if service.instances.is_empty() {
+            services.remove(service_name);
+        }
+        removed
     }
 
-    /// Unregister a service
-    pub fn unregister(&self, service_name: &str) -> bool {
+    /// Record a heartbeat from an instance
+    pub fn heartbeat(&self, service_name: &str, instance_id: &str) -> bool {
         let mut services = self.services.write().unwrap();
-        services.remove(service_name).is_some()
+        match services.get_mut(service_name).and_then(|s| {
+            s.instances
+                .iter_mut()
+                .find(|i| i.instance_id == instance_id)
+        }) {
+            Some(instance) => {
+                instance.last_heartbeat = chrono::Utc::now();
+                true
+            }
+            None => false,
+        }
     }
 
-    /// Get a service by name
+    /// Get the earliest registered instance of a service
     pub fn get(&self, service_name: &str) -> Option<ServiceInfo> {
         let services = self.services.read().unwrap();
-        services.get(service_name).cloned()
+        services
+            .get(service_name)
+            .and_then(|s| s.instances.first())
+            .cloned()
+    }
+
+    /// Get all instances of a service, in registration order
+    pub fn get_instances(&self, service_name: &str) -> Vec<ServiceInfo> {
+        let services = self.services.read().unwrap();
+        services
+            .get(service_name)
+            .map(|s| s.instances.clone())
+            .unwrap_or_default()
+    }
+
+    /// Pick an instance of a service, rotating through the instances on each call
+    pub fn pick_instance(&self, service_name: &str) -> Option<ServiceInfo> {
+        let mut services = self.services.write().unwrap();
+        let service = services.get_mut(service_name)?;
+        let instance = service.instances[service.next % service.instances.len()].clone();
+        service.next = service.next.wrapping_add(1);
+        Some(instance)
     }
 
     /// Check if a service exists
@@ -72,45 +141,90 @@ impl ServiceRegistry {
         services.contains_key(service_name)
     }
 
-    /// List all services
+    /// List all instances of all services
     pub fn list(&self) -> Vec<ServiceInfo> {
         let services = self.services.read().unwrap();
-        services.values().cloned().collect()
+        services
+            .values()
+            .flat_map(|s| s.instances.iter().cloned())
+            .collect()
     }
 
-    /// Find a resource in any registered service
-    pub fn find_resource(&self, resource_name: &str) -> Option<(String, ServiceResource)> {
+    /// Find every instance that provides a resource, as (service, instance, resource) tuples
+    pub fn find_resource(&self, resource_name: &str) -> Vec<(String, String, ServiceResource)> {
         let services = self.services.read().unwrap();
 
+        let mut found = Vec::new();
         for (service_name, service) in services.iter() {
-            if let Some(resource) = service.provides.get(resource_name) {
-                return Some((service_name.clone(), resource.clone()));
+            for instance in &service.instances {
+                if let Some(resource) = instance.provides.get(resource_name) {
+                    found.push((
+                        service_name.clone(),
+                        instance.instance_id.clone(),
+                        resource.clone(),
+                    ));
+                }
             }
         }
-
-        None
+        found
     }
 
-    /// Find a resource in a specific service
+    /// Find a resource in any instance of a specific service
     pub fn find_resource_in_service(
         &self,
         service_name: &str,
         resource_name: &str,
+    ) -> Option<ServiceResource> {
+        let services = self.services.read().unwrap();
+        services.get(service_name).and_then(|s| {
+            s.instances
+                .iter()
+                .find_map(|i| i.provides.get(resource_name))
+                .cloned()
+        })
+    }
+
+    /// Find a resource in a specific instance of a service
+    pub fn find_resource_in_instance(
+        &self,
+        service_name: &str,
+        instance_id: &str,
+        resource_name: &str,
     ) -> Option<ServiceResource> {
         let services = self.services.read().unwrap();
         services
             .get(service_name)
-            .and_then(|s| s.provides.get(resource_name))
+            .and_then(|s| s.instances.iter().find(|i| i.instance_id == instance_id))
+            .and_then(|i| i.provides.get(resource_name))
             .cloned()
     }
 
-    /// Get all services that provide a specific resource type
+    /// Check if an instance provides any resource of the given type
+    pub fn provides_resource_type(
+        &self,
+        service_name: &str,
+        instance_id: &str,
+        resource_type: ResourceType,
+    ) -> bool {
+        let services = self.services.read().unwrap();
+        services
+            .get(service_name)
+            .and_then(|s| s.instances.iter().find(|i| i.instance_id == instance_id))
+            .is_some_and(|i| {
+                i.provides
+                    .values()
+                    .any(|r| r.resource_type == resource_type)
+            })
+    }
+
+    /// Get all instances that provide a specific resource type
     pub fn get_services_by_resource_type(&self, resource_type: ResourceType) -> Vec<ServiceInfo> {
         let services = self.services.read().unwrap();
         services
             .values()
-            .filter(|s| {
-                s.provides
+            .flat_map(|s| s.instances.iter())
+            .filter(|i| {
+                i.provides
                     .values()
                     .any(|r| r.resource_type == resource_type)
             })
@@ -118,7 +232,7 @@ impl ServiceRegistry {
             .collect()
     }
 
-    /// Get service count
+    /// Get service count (replicas of one service count once)
     pub fn len(&self) -> usize {
         let services = self.services.read().unwrap();
         services.len()
@@ -153,12 +267,14 @@ mod tests {
                     timeout: Some(30000),
                     input_schema: None,
                     output_schema: None,
+                    cacheable: false,
                 }),
             },
         ];
 
         registry.register(
             "data-proc".to_string(),
+            "instance-1".to_string(),
             "data-group".to_string(),
             vec!["python".to_string()],
             provides,
@@ -187,6 +303,7 @@ mod tests {
 
         registry.register(
             "data-proc".to_string(),
+            "instance-1".to_string(),
             "data-group".to_string(),
             vec!["python".to_string()],
             provides,
@@ -194,9 +311,10 @@ mod tests {
         );
 
         let result = registry.find_resource("process");
-        assert!(result.is_some());
-        let (service_name, resource) = result.unwrap();
+        assert_eq!(result.len(), 1);
+        let (service_name, instance_id, resource) = &result[0];
         assert_eq!(service_name, "data-proc");
+        assert_eq!(instance_id, "instance-1");
         assert_eq!(resource.name, "process");
         assert_eq!(resource.resource_type, ResourceType::Step);
     }
@@ -206,7 +324,7 @@ mod tests {
         let registry = ServiceRegistry::new();
 
         let result = registry.find_resource("nonexistent");
-        assert!(result.is_none());
+        assert!(result.is_empty());
     }
 
     #[test]
@@ -216,6 +334,7 @@ mod tests {
         let provides = vec![];
         registry.register(
             "data-proc".to_string(),
+            "instance-1".to_string(),
             "data-group".to_string(),
             vec!["python".to_string()],
             provides,
@@ -224,11 +343,80 @@ mod tests {
 
         assert!(registry.exists("data-proc"));
 
-        let removed = registry.unregister("data-proc");
+        let removed = registry.unregister("data-proc", "instance-1");
         assert!(removed);
         assert!(!registry.exists("data-proc"));
 
-        let removed_again = registry.unregister("data-proc");
+        let removed_again = registry.unregister("data-proc", "instance-1");
         assert!(!removed_again);
     }
+
+    fn register_instance(registry: &ServiceRegistry, instance_id: &str, endpoint: &str) {
+        registry.register(
+            "data-proc".to_string(),
+            instance_id.to_string(),
+            "data-group".to_string(),
+            vec!["python".to_string()],
+            vec![ServiceResource {
+                name: "process".to_string(),
+                resource_type: ResourceType::Step,
+                metadata: None,
+            }],
+            endpoint.to_string(),
+        );
+    }
+
+    #[test]
+    fn test_replicas_register_as_separate_instances() {
+        let registry = ServiceRegistry::new();
+        register_instance(&registry, "instance-1", "python-1:50051");
+        register_instance(&registry, "instance-2", "python-2:50051");
+        // Re-registering an instance replaces it instead of adding another
+        register_instance(&registry, "instance-1", "python-1:50052");
+
+        assert_eq!(registry.len(), 1);
+        let instances = registry.get_instances("data-proc");
+        let endpoints: Vec<&str> = instances.iter().map(|i| i.endpoint.as_str()).collect();
+        assert_eq!(endpoints, vec!["python-1:50052", "python-2:50051"]);
+        assert_eq!(registry.list().len(), 2);
+
+        let mut found: Vec<String> = registry
+            .find_resource("process")
+            .into_iter()
+            .map(|(_, instance_id, _)| instance_id)
+            .collect();
+        found.sort();
+        assert_eq!(found, vec!["instance-1", "instance-2"]);
+        assert!(registry
+            .find_resource_in_instance("data-proc", "instance-2", "process")
+            .is_some());
+        assert!(registry.heartbeat("data-proc", "instance-2"));
+        assert!(!registry.heartbeat("data-proc", "instance-3"));
+    }
+
+    #[test]
+    fn test_pick_instance_rotates() {
+        let registry = ServiceRegistry::new();
+        assert!(registry.pick_instance("data-proc").is_none());
+        register_instance(&registry, "instance-1", "python-1:50051");
+        register_instance(&registry, "instance-2", "python-2:50051");
+
+        let picked: Vec<String> = (0..4)
+            .map(|_| registry.pick_instance("data-proc").unwrap().instance_id)
+            .collect();
+        assert_eq!(
+            picked,
+            vec!["instance-1", "instance-2", "instance-1", "instance-2"]
+        );
+
+        // Removing one instance keeps the service; removing the last removes it
+        assert!(registry.unregister("data-proc", "instance-1"));
+        assert!(registry.exists("data-proc"));
+        assert_eq!(
+            registry.pick_instance("data-proc").unwrap().instance_id,
+            "instance-2"
+        );
+        assert!(registry.unregister("data-proc", "instance-2"));
+        assert!(!registry.exists("data-proc"));
+    }
 }
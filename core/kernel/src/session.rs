@@ -0,0 +1,73 @@
+//! REST worker 会话令牌
+//!
+//! `POST /workers` 注册 worker 时签发令牌，worker 建立任务 WebSocket 和上报 step 时携带。
+//! worker 因心跳超时被移除时，它的令牌随之失效。
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// 已签发的会话令牌
+#[derive(Debug, Default)]
+pub struct SessionStore {
+    /// 令牌到 worker id
+    tokens: RwLock<HashMap<String, String>>,
+}
+
+impl SessionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 为 worker 签发新令牌
+    pub fn issue(&self, worker_id: &str) -> String {
+        let token = uuid::Uuid::new_v4().to_string();
+        self.tokens
+            .write()
+            .unwrap()
+            .insert(token.clone(), worker_id.to_string());
+        token
+    }
+
+    /// 令牌所属的 worker，令牌未签发或已失效时返回 `None`
+    pub fn worker_id(&self, token: &str) -> Option<String> {
+        self.tokens.read().unwrap().get(token).cloned()
+    }
+
+    /// worker 是否持有未失效的令牌
+    pub fn has_session(&self, worker_id: &str) -> bool {
+        self.tokens
+            .read()
+            .unwrap()
+            .values()
+            .any(|owner| owner == worker_id)
+    }
+
+    /// 使 worker 的全部令牌失效
+    pub fn revoke(&self, worker_id: &str) {
+        self.tokens
+            .write()
+            .unwrap()
+            .retain(|_, owner| owner != worker_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_revoked_tokens_are_rejected() {
+        let sessions = SessionStore::new();
+        let first = sessions.issue("worker-1");
+        let second = sessions.issue("worker-2");
+        assert_ne!(first, second);
+        assert_eq!(sessions.worker_id(&first).as_deref(), Some("worker-1"));
+        assert!(sessions.has_session("worker-1"));
+
+        sessions.revoke("worker-1");
+        assert!(!sessions.has_session("worker-1"));
+        assert!(sessions.worker_id(&first).is_none());
+        assert_eq!(sessions.worker_id(&second).as_deref(), Some("worker-2"));
+        assert!(sessions.worker_id("unknown").is_none());
+    }
+}
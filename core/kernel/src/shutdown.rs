@@ -0,0 +1,179 @@
+//! 优雅停机
+//!
+//! 停机分两个阶段：先停止分发新 task，已分发的 step 在宽限期内仍可上报结果；
+//! 全部完成或宽限期结束后进入停止状态，后台任务随之退出，worker 的任务流以 Close 帧或流结束关闭。
+//! 服务器停止后由调用方刷新持久化层（见 [`crate::server::serve`]）。
+
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::persistence::Persistence;
+use crate::scheduler::Scheduler;
+use crate::task::TaskId;
+
+/// 默认停机宽限期
+pub const DEFAULT_SHUTDOWN_GRACE: Duration = Duration::from_secs(30);
+
+/// 宽限期内检查已分发 step 是否完成的间隔（step 完成时会立即检查）
+const DRAIN_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 调度器的停机阶段，按先后顺序排列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ShutdownState {
+    /// 正常分发 task
+    Running,
+    /// 不再分发新 task，等待已分发的 step 完成
+    Draining,
+    /// 已停止，后台任务和 worker 连接应退出
+    Stopped,
+}
+
+impl<P: Persistence> Scheduler<P> {
+    /// 当前的停机阶段
+    pub fn shutdown_state(&self) -> ShutdownState {
+        *self.shutdown.borrow()
+    }
+
+    /// 开始停机：不再分发新 task
+    pub fn begin_shutdown(&self) {
+        self.advance_shutdown(ShutdownState::Draining);
+    }
+
+    /// 停机：停止分发新 task，等待已分发的 step 在 `grace` 内完成，然后进入停止状态
+    ///
+    /// 返回宽限期结束时仍未完成的 task。
+    pub async fn shutdown(&self, grace: Duration) -> Vec<TaskId> {
+        self.begin_shutdown();
+        let deadline = Instant::now() + grace;
+        let mut ready = self.subscribe_tasks();
+        let remaining = loop {
+            ready.borrow_and_update();
+            let in_flight = self.in_flight_tasks().await;
+            if in_flight.is_empty() || Instant::now() >= deadline {
+                break in_flight;
+            }
+            let wait = (deadline - Instant::now()).min(DRAIN_CHECK_INTERVAL);
+            tokio::select! {
+                _ = ready.changed() => {}
+                _ = tokio::time::sleep(wait) => {}
+            }
+        };
+        if !remaining.is_empty() {
+            tracing::warn!(
+                count = remaining.len(),
+                "shutdown grace period ended with steps still running"
+            );
+        }
+        self.advance_shutdown(ShutdownState::Stopped);
+        remaining
+    }
+
+    /// 等待调度器进入停止状态
+    pub async fn stopped(&self) {
+        let mut state = self.shutdown.subscribe();
+        let _ = state
+            .wait_for(|state| *state == ShutdownState::Stopped)
+            .await;
+    }
+
+    fn advance_shutdown(&self, next: ShutdownState) {
+        // 阶段只能前进
+        let advanced = self.shutdown.send_if_modified(|state| {
+            let advance = *state < next;
+            if advance {
+                *state = next;
+            }
+            advance
+        });
+        if advanced {
+            tracing::info!(state = ?next, "scheduler shutting down");
+            // 唤醒等待中的 poll，让它们看到新的阶段
+            self.notify_tasks_ready();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::l0_memory::L0MemoryStore;
+    use crate::state_machine::Workflow;
+    use std::sync::Arc;
+
+    async fn scheduler() -> Arc<Scheduler<L0MemoryStore>> {
+        let store = L0MemoryStore::new();
+        for i in 0..2 {
+            let mut workflow = Workflow::new(format!("wf-{}", i), "order".to_string(), vec![]);
+            workflow.state = workflow.state.try_start().unwrap();
+            store.save_workflow(&workflow).await.unwrap();
+        }
+
+        let scheduler = Arc::new(Scheduler::new(store));
+        scheduler
+            .register_worker(
+                "worker-1".to_string(),
+                "shop".to_string(),
+                "default".to_string(),
+                vec!["order".to_string()],
+                vec![],
+            )
+            .await;
+        scheduler
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_waits_for_in_flight_steps() {
+        let scheduler = scheduler().await;
+        let tasks = scheduler.poll_tasks("worker-1", 1).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+
+        let shutdown = tokio::spawn({
+            let scheduler = scheduler.clone();
+            async move { scheduler.shutdown(Duration::from_secs(5)).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(scheduler.shutdown_state(), ShutdownState::Draining);
+        // 停机期间不再分发新 task，但已分发的 step 仍可完成
+        assert!(scheduler
+            .poll_tasks("worker-1", 10)
+            .await
+            .unwrap()
+            .is_empty());
+        scheduler
+            .complete_task(&tasks[0].task_id, vec![])
+            .await
+            .unwrap();
+
+        let remaining = tokio::time::timeout(Duration::from_secs(1), shutdown)
+            .await
+            .expect("shutdown should finish once the step completes")
+            .unwrap();
+        assert!(remaining.is_empty());
+        assert_eq!(scheduler.shutdown_state(), ShutdownState::Stopped);
+        tokio::time::timeout(Duration::from_secs(1), scheduler.stopped())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_gives_up_after_grace_period() {
+        let scheduler = scheduler().await;
+        let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(tasks.len(), 2);
+
+        let remaining = scheduler.shutdown(Duration::from_millis(50)).await;
+        let remaining: Vec<String> = remaining
+            .iter()
+            .map(|task_id| task_id.to_string())
+            .collect();
+        let mut expected: Vec<String> = tasks.into_iter().map(|task| task.task_id).collect();
+        expected.sort();
+        assert_eq!(remaining, expected);
+        assert_eq!(scheduler.shutdown_state(), ShutdownState::Stopped);
+
+        // 阶段只能前进
+        scheduler.begin_shutdown();
+        assert_eq!(scheduler.shutdown_state(), ShutdownState::Stopped);
+    }
+}
@@ -0,0 +1,108 @@
+//! Workflow signal
+//!
+//! signal 是从外部发送给运行中 workflow 的事件（例如人工审批）。signal 按到达顺序持久化；
+//! 定义中声明了 `wait_for_signal` 的 step 在对应 signal 到达之前不会被分发，
+//! 分发时 task 携带该 signal（同名 signal 取最新的一个）。
+
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::persistence::Persistence;
+use crate::scheduler::Scheduler;
+
+/// 发送给 workflow 的 signal
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Signal {
+    pub workflow_id: String,
+    pub name: String,
+    pub payload: Vec<u8>,
+    pub received_at: DateTime<Utc>,
+}
+
+impl Signal {
+    pub fn new(workflow_id: impl Into<String>, name: impl Into<String>, payload: Vec<u8>) -> Self {
+        Signal {
+            workflow_id: workflow_id.into(),
+            name: name.into(),
+            payload,
+            received_at: Utc::now(),
+        }
+    }
+}
+
+/// 发送 signal 的错误
+#[derive(Debug)]
+pub enum SignalError {
+    /// signal 名称为空
+    EmptyName,
+    /// workflow 不存在
+    WorkflowNotFound(String),
+    /// workflow 已经结束，不再接收 signal
+    WorkflowFinished(String),
+    /// 持久化层错误
+    Persistence(anyhow::Error),
+}
+
+impl fmt::Display for SignalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignalError::EmptyName => write!(f, "Signal name must not be empty"),
+            SignalError::WorkflowNotFound(workflow_id) => {
+                write!(f, "Workflow not found: {}", workflow_id)
+            }
+            SignalError::WorkflowFinished(workflow_id) => write!(
+                f,
+                "Workflow {} has already finished and cannot receive signals",
+                workflow_id
+            ),
+            SignalError::Persistence(e) => write!(f, "Persistence error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SignalError {}
+
+impl From<anyhow::Error> for SignalError {
+    fn from(e: anyhow::Error) -> Self {
+        SignalError::Persistence(e)
+    }
+}
+
+impl<P: Persistence> Scheduler<P> {
+    /// 向未结束的 workflow 发送 signal
+    ///
+    /// signal 持久化后广播 `SignalReceived` 事件，等待该 signal 的 step 随即可以分发。
+    pub async fn signal_workflow(
+        &self,
+        workflow_id: &str,
+        name: &str,
+        payload: Vec<u8>,
+    ) -> Result<Signal, SignalError> {
+        if name.is_empty() {
+            return Err(SignalError::EmptyName);
+        }
+        let workflow = self
+            .persistence
+            .get_workflow(workflow_id)
+            .await?
+            .ok_or_else(|| SignalError::WorkflowNotFound(workflow_id.to_string()))?;
+        if workflow.state.is_terminal() {
+            return Err(SignalError::WorkflowFinished(workflow_id.to_string()));
+        }
+
+        let signal = Signal::new(workflow_id, name, payload);
+        self.persistence.append_signal(&signal).await?;
+        self.broadcaster
+            .broadcast_signal_received(
+                workflow_id,
+                &workflow.workflow_type,
+                name,
+                signal.payload.clone(),
+            )
+            .await;
+        self.notify_workflow_changed(workflow_id);
+        Ok(signal)
+    }
+}
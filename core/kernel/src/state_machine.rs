@@ -1,88 +1,407 @@
+use crate::payload::Payload;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// 每个 workflow 保留的状态变化记录上限
+///
+/// 超出时丢弃最早的记录，但保留开始执行第一个 step 之前的记录，排队时间仍可计算。
+pub const MAX_STATE_HISTORY: usize = 100;
+
+/// [`Workflow`] 当前的序列化格式版本
+///
+/// 字段变化需要持久化层迁移时递增；加入该字段之前保存的记录读出为 0。
+pub const WORKFLOW_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum WorkflowState {
     Pending,
-    Running { current_step: Option<String> },
-    Completed { result: Vec<u8> },
-    Failed { error: String },
+    Running {
+        current_step: Option<String>,
+    },
+    Completed {
+        result: Payload,
+    },
+    Failed {
+        error: String,
+    },
     Cancelled,
+    /// 运维强制结束，记录原因和发起人
+    Terminated {
+        reason: String,
+        #[serde(default)]
+        requested_by: Option<String>,
+    },
 }
 
+/// 当前状态下不允许的状态转换
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidTransition {
+    /// 转换前的状态名，如 `COMPLETED`
+    pub from: &'static str,
+    /// 尝试的转换，如 `cancel`
+    pub attempted: &'static str,
+}
+
+impl fmt::Display for InvalidTransition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cannot {} a workflow in state {}",
+            self.attempted, self.from
+        )
+    }
+}
+
+impl std::error::Error for InvalidTransition {}
+
 impl WorkflowState {
-    pub fn start(&self) -> Option<Self> {
+    fn invalid(&self, attempted: &'static str) -> InvalidTransition {
+        InvalidTransition {
+            from: self.label(),
+            attempted,
+        }
+    }
+
+    /// PENDING → RUNNING
+    pub fn try_start(&self) -> Result<Self, InvalidTransition> {
         match self {
-            WorkflowState::Pending => Some(WorkflowState::Running { current_step: None }),
-            _ => None,
+            WorkflowState::Pending => Ok(WorkflowState::Running { current_step: None }),
+            _ => Err(self.invalid("start")),
         }
     }
 
-    pub fn step_started(&self, step_name: &str) -> Option<Self> {
+    /// RUNNING → RUNNING，当前 step 为 `step_name`
+    pub fn try_step_started(&self, step_name: &str) -> Result<Self, InvalidTransition> {
         match self {
-            WorkflowState::Running { .. } => Some(WorkflowState::Running {
+            WorkflowState::Running { .. } => Ok(WorkflowState::Running {
                 current_step: Some(step_name.to_string()),
             }),
-            _ => None,
+            _ => Err(self.invalid("start a step of")),
         }
     }
 
-    pub fn step_completed(&self) -> Option<Self> {
+    /// RUNNING → RUNNING，没有正在执行的 step
+    pub fn try_step_completed(&self) -> Result<Self, InvalidTransition> {
         match self {
-            WorkflowState::Running { .. } => Some(WorkflowState::Running { current_step: None }),
-            _ => None,
+            WorkflowState::Running { .. } => Ok(WorkflowState::Running { current_step: None }),
+            _ => Err(self.invalid("complete a step of")),
         }
     }
 
-    pub fn complete(&self, result: Vec<u8>) -> Option<Self> {
+    /// RUNNING → COMPLETED
+    pub fn try_complete(&self, result: impl Into<Payload>) -> Result<Self, InvalidTransition> {
         match self {
-            WorkflowState::Running { .. } => Some(WorkflowState::Completed { result }),
-            _ => None,
+            WorkflowState::Running { .. } => Ok(WorkflowState::Completed {
+                result: result.into(),
+            }),
+            _ => Err(self.invalid("complete")),
         }
     }
 
-    pub fn fail(&self, error: String) -> Option<Self> {
+    /// RUNNING → FAILED
+    pub fn try_fail(&self, error: String) -> Result<Self, InvalidTransition> {
         match self {
-            WorkflowState::Running { .. } => Some(WorkflowState::Failed { error }),
-            _ => None,
+            WorkflowState::Running { .. } => Ok(WorkflowState::Failed { error }),
+            _ => Err(self.invalid("fail")),
+        }
+    }
+
+    /// PENDING / RUNNING → CANCELLED
+    pub fn try_cancel(&self) -> Result<Self, InvalidTransition> {
+        match self {
+            WorkflowState::Pending | WorkflowState::Running { .. } => Ok(WorkflowState::Cancelled),
+            _ => Err(self.invalid("cancel")),
+        }
+    }
+
+    /// PENDING / RUNNING → TERMINATED，强制结束未结束的 workflow，不论当前执行到哪个 step
+    pub fn try_terminate(
+        &self,
+        reason: String,
+        requested_by: Option<String>,
+    ) -> Result<Self, InvalidTransition> {
+        match self {
+            WorkflowState::Pending | WorkflowState::Running { .. } => {
+                Ok(WorkflowState::Terminated {
+                    reason,
+                    requested_by,
+                })
+            }
+            _ => Err(self.invalid("terminate")),
         }
     }
 
+    /// FAILED → RUNNING，失败的 workflow 重新开始，由调度器分发尚未完成的 step
+    pub fn try_retry(&self) -> Result<Self, InvalidTransition> {
+        match self {
+            WorkflowState::Failed { .. } => Ok(WorkflowState::Running { current_step: None }),
+            _ => Err(self.invalid("retry")),
+        }
+    }
+
+    #[deprecated(note = "use `try_start`, which reports why the transition is invalid")]
+    pub fn start(&self) -> Option<Self> {
+        self.try_start().ok()
+    }
+
+    #[deprecated(note = "use `try_step_started`, which reports why the transition is invalid")]
+    pub fn step_started(&self, step_name: &str) -> Option<Self> {
+        self.try_step_started(step_name).ok()
+    }
+
+    #[deprecated(note = "use `try_step_completed`, which reports why the transition is invalid")]
+    pub fn step_completed(&self) -> Option<Self> {
+        self.try_step_completed().ok()
+    }
+
+    #[deprecated(note = "use `try_complete`, which reports why the transition is invalid")]
+    pub fn complete(&self, result: impl Into<Payload>) -> Option<Self> {
+        self.try_complete(result).ok()
+    }
+
+    #[deprecated(note = "use `try_fail`, which reports why the transition is invalid")]
+    pub fn fail(&self, error: String) -> Option<Self> {
+        self.try_fail(error).ok()
+    }
+
+    #[deprecated(note = "use `try_cancel`, which reports why the transition is invalid")]
     pub fn cancel(&self) -> Option<Self> {
+        self.try_cancel().ok()
+    }
+
+    #[deprecated(note = "use `try_terminate`, which reports why the transition is invalid")]
+    pub fn terminate(&self, reason: String, requested_by: Option<String>) -> Option<Self> {
+        self.try_terminate(reason, requested_by).ok()
+    }
+
+    #[deprecated(note = "use `try_retry`, which reports why the transition is invalid")]
+    pub fn retry(&self) -> Option<Self> {
+        self.try_retry().ok()
+    }
+
+    /// 是否为终态（Completed / Failed / Cancelled / Terminated）
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            WorkflowState::Completed { .. }
+                | WorkflowState::Failed { .. }
+                | WorkflowState::Cancelled
+                | WorkflowState::Terminated { .. }
+        )
+    }
+
+    /// 对外展示的状态名，如 `RUNNING`
+    pub fn label(&self) -> &'static str {
         match self {
-            WorkflowState::Pending => Some(WorkflowState::Cancelled),
-            WorkflowState::Running { .. } => Some(WorkflowState::Cancelled),
-            _ => None,
+            WorkflowState::Pending => "PENDING",
+            WorkflowState::Running { .. } => "RUNNING",
+            WorkflowState::Completed { .. } => "COMPLETED",
+            WorkflowState::Failed { .. } => "FAILED",
+            WorkflowState::Cancelled => "CANCELLED",
+            WorkflowState::Terminated { .. } => "TERMINATED",
+        }
+    }
+}
+
+/// workflow 的调度优先级，优先级高的 workflow 的 task 先分发
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl Priority {
+    /// 全部优先级，从高到低
+    pub const ALL: [Priority; 3] = [Priority::High, Priority::Normal, Priority::Low];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::High => "high",
         }
     }
 }
 
-#[derive(Debug, Clone)]
+impl std::str::FromStr for Priority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Priority::ALL
+            .into_iter()
+            .find(|priority| priority.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown priority '{}': expected low, normal or high", s))
+    }
+}
+
+/// 一次状态变化
+///
+/// 状态种类变化时记录；同为 RUNNING 时只记录开始执行新的 step，此时 `step` 为该 step。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateTransition {
+    /// 变化前的状态名，如 `RUNNING`
+    pub from: String,
+    pub to: String,
+    pub at: DateTime<Utc>,
+    /// 失败的错误或终止的原因
+    #[serde(default)]
+    pub reason: Option<String>,
+    #[serde(default)]
+    pub step: Option<String>,
+}
+
+/// workflow 实例，持久化层按字段名保存，JSON 和 bincode 等二进制格式都可以往返
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Workflow {
+    /// 序列化格式版本，见 [`WORKFLOW_SCHEMA_VERSION`]
+    #[serde(default)]
+    pub schema_version: u32,
     pub id: String,
     pub workflow_type: String,
     pub state: WorkflowState,
     pub input: Vec<u8>,
-    pub steps_completed: HashMap<String, Vec<u8>>,
+    /// 已完成 step 的输出
+    #[serde(alias = "steps_completed")]
+    pub completed_steps: HashMap<String, Payload>,
+    /// 每个 step 已开始的尝试次数，包括正在执行的一次
+    #[serde(default)]
+    pub attempts: HashMap<String, u32>,
     pub started_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// 启动该 workflow 的父 workflow
+    #[serde(default)]
+    pub parent_workflow_id: Option<String>,
+    #[serde(default)]
+    pub priority: Priority,
+    /// 跳过资源 schema 校验，用于紧急情况
+    #[serde(default)]
+    pub skip_schema_validation: bool,
+    /// 启动时固定的定义版本，启动时没有定义的 workflow 为 `None`
+    #[serde(default)]
+    pub definition_version: Option<u32>,
+    /// 状态变化记录，按时间顺序，最多保留 [`MAX_STATE_HISTORY`] 条
+    #[serde(default)]
+    pub history: Vec<StateTransition>,
+    /// 启动时附带的备注，见 [`crate::memo`]
+    #[serde(default)]
+    pub memo: HashMap<String, String>,
 }
 
 impl Workflow {
     pub fn new(id: String, workflow_type: String, input: Vec<u8>) -> Self {
         let now = Utc::now();
         Workflow {
+            schema_version: WORKFLOW_SCHEMA_VERSION,
             id,
             workflow_type,
             state: WorkflowState::Pending,
             input,
-            steps_completed: HashMap::new(),
+            completed_steps: HashMap::new(),
+            attempts: HashMap::new(),
             started_at: now,
             updated_at: now,
+            parent_workflow_id: None,
+            priority: Priority::Normal,
+            skip_schema_validation: false,
+            definition_version: None,
+            history: Vec::new(),
+            memo: HashMap::new(),
         }
     }
 
+    /// 切换到新状态并记录状态变化，各持久化层更新状态时调用
+    pub fn transition(&mut self, state: WorkflowState, at: DateTime<Utc>) {
+        let step = match (&self.state, &state) {
+            (
+                WorkflowState::Running {
+                    current_step: Some(current),
+                },
+                WorkflowState::Running {
+                    current_step: Some(next),
+                },
+            ) if current == next => None,
+            (_, WorkflowState::Running { current_step }) => current_step.clone(),
+            _ => None,
+        };
+        if self.state.label() != state.label() || step.is_some() {
+            let reason = match &state {
+                WorkflowState::Failed { error } => Some(error.clone()),
+                WorkflowState::Terminated { reason, .. } => Some(reason.clone()),
+                _ => None,
+            };
+            self.history.push(StateTransition {
+                from: self.state.label().to_string(),
+                to: state.label().to_string(),
+                at,
+                reason,
+                step,
+            });
+            if self.history.len() > MAX_STATE_HISTORY {
+                let first_step = self.history.iter().position(|t| t.step.is_some());
+                let oldest = match first_step {
+                    Some(i) if i + 2 < self.history.len() => i + 1,
+                    _ => 0,
+                };
+                self.history.remove(oldest);
+            }
+        }
+        self.state = state;
+        self.updated_at = at;
+    }
+
+    /// 从创建到开始执行第一个 step 的排队时间
+    pub fn queue_time(&self) -> Option<Duration> {
+        let first_step = self.history.iter().find(|t| t.step.is_some())?;
+        (first_step.at - self.started_at).to_std().ok()
+    }
+
+    /// 从开始执行第一个 step 到结束的执行时间，未结束时为 `None`
+    pub fn execution_time(&self) -> Option<Duration> {
+        if !self.state.is_terminal() {
+            return None;
+        }
+        let first_step = self.history.iter().find(|t| t.step.is_some())?;
+        let finished = self.history.last()?;
+        (finished.at - first_step.at).to_std().ok()
+    }
+
+    /// 设置调度优先级
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// 跳过资源声明的输入输出 schema 校验
+    pub fn with_schema_validation_skipped(mut self) -> Self {
+        self.skip_schema_validation = true;
+        self
+    }
+
+    /// 固定使用的定义版本
+    pub fn with_definition_version(mut self, version: u32) -> Self {
+        self.definition_version = Some(version);
+        self
+    }
+
+    pub fn with_memo(mut self, memo: HashMap<String, String>) -> Self {
+        self.memo = memo;
+        self
+    }
+
+    /// 作为指定 workflow 的子 workflow
+    pub fn with_parent(mut self, parent_workflow_id: impl Into<String>) -> Self {
+        self.parent_workflow_id = Some(parent_workflow_id.into());
+        self
+    }
+
     pub fn is_complete(&self) -> bool {
         matches!(self.state, WorkflowState::Completed { .. })
     }
@@ -91,13 +410,62 @@ impl Workflow {
         matches!(self.state, WorkflowState::Failed { .. })
     }
 
+    /// step 开始一次新的尝试，返回这是第几次尝试
+    pub fn record_attempt(&mut self, step_name: &str) -> u32 {
+        let attempts = self.attempts.entry(step_name.to_string()).or_insert(0);
+        *attempts = attempts.saturating_add(1);
+        *attempts
+    }
+
+    /// 记录 step 完成及其输出
+    pub fn record_completion(&mut self, step_name: &str, output: impl Into<Payload>) {
+        self.completed_steps
+            .insert(step_name.to_string(), output.into());
+    }
+
+    /// step 已开始的尝试次数，尚未开始时为 0
+    pub fn attempts(&self, step_name: &str) -> u32 {
+        self.attempts.get(step_name).copied().unwrap_or(0)
+    }
+
+    /// step 的一次尝试失败后能否再试：尚未完成，且尝试次数未达到 `max_attempts`
+    ///
+    /// 失败说明至少尝试过一次，没有记录尝试次数时按一次计算。
     pub fn can_retry(&self, step_name: &str, max_attempts: u32) -> bool {
-        !self.steps_completed.contains_key(step_name)
-            && self
-                .steps_completed
-                .get(step_name)
-                .map(|v| v.len() < max_attempts as usize)
-                .unwrap_or(true)
+        !self.completed_steps.contains_key(step_name)
+            && self.attempts(step_name).max(1) < max_attempts
+    }
+}
+
+/// 已结束 workflow 的排队时间和执行时间汇总，用于指标接口
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DurationStats {
+    /// 计入统计的 workflow 数量
+    pub workflows: u64,
+    queue: Duration,
+    execution: Duration,
+}
+
+impl DurationStats {
+    /// 计入一个 workflow，未结束或没有执行过 step 的不计入
+    pub fn record(&mut self, workflow: &Workflow) {
+        if let (Some(queue), Some(execution)) = (workflow.queue_time(), workflow.execution_time()) {
+            self.workflows += 1;
+            self.queue += queue;
+            self.execution += execution;
+        }
+    }
+
+    pub fn avg_queue(&self) -> Duration {
+        self.queue / self.divisor()
+    }
+
+    pub fn avg_execution(&self) -> Duration {
+        self.execution / self.divisor()
+    }
+
+    fn divisor(&self) -> u32 {
+        u32::try_from(self.workflows.max(1)).unwrap_or(u32::MAX)
     }
 }
 
@@ -115,25 +483,296 @@ mod tests {
 
         assert!(matches!(workflow.state, WorkflowState::Pending));
 
-        let started = workflow.state.start().unwrap();
+        let started = workflow.state.try_start().unwrap();
         assert!(matches!(started, WorkflowState::Running { .. }));
 
-        let step_started = started.step_started("step1").unwrap();
+        let step_started = started.try_step_started("step1").unwrap();
         assert!(matches!(
             step_started,
             WorkflowState::Running { current_step: Some(ref step) } if step == "step1"
         ));
 
-        let step_completed = step_started.step_completed().unwrap();
+        let step_completed = step_started.try_step_completed().unwrap();
         assert!(matches!(
             step_completed,
             WorkflowState::Running { current_step: None }
         ));
 
-        let completed = step_completed.complete(b"result".to_vec()).unwrap();
+        let completed = step_completed.try_complete(b"result".to_vec()).unwrap();
         assert!(matches!(
             completed,
-            WorkflowState::Completed { result } if result == b"result"
+            WorkflowState::Completed { result } if result.as_bytes() == b"result"
+        ));
+    }
+
+    #[test]
+    fn test_only_documented_transitions_are_possible() {
+        let states = [
+            WorkflowState::Pending,
+            WorkflowState::Running { current_step: None },
+            WorkflowState::Running {
+                current_step: Some("fetch".to_string()),
+            },
+            WorkflowState::Completed {
+                result: b"done".to_vec().into(),
+            },
+            WorkflowState::Failed {
+                error: "boom".to_string(),
+            },
+            WorkflowState::Cancelled,
+            WorkflowState::Terminated {
+                reason: "stuck".to_string(),
+                requested_by: None,
+            },
+        ];
+        type Transition = fn(&WorkflowState) -> Result<WorkflowState, InvalidTransition>;
+        // (尝试的转换, 允许的起始状态, 目标状态)
+        let documented: [(&str, Transition, &[&str], &str); 8] = [
+            ("start", |s| s.try_start(), &["PENDING"], "RUNNING"),
+            (
+                "start a step of",
+                |s| s.try_step_started("next"),
+                &["RUNNING"],
+                "RUNNING",
+            ),
+            (
+                "complete a step of",
+                |s| s.try_step_completed(),
+                &["RUNNING"],
+                "RUNNING",
+            ),
+            (
+                "complete",
+                |s| s.try_complete(b"out".to_vec()),
+                &["RUNNING"],
+                "COMPLETED",
+            ),
+            (
+                "fail",
+                |s| s.try_fail("err".to_string()),
+                &["RUNNING"],
+                "FAILED",
+            ),
+            (
+                "cancel",
+                |s| s.try_cancel(),
+                &["PENDING", "RUNNING"],
+                "CANCELLED",
+            ),
+            (
+                "terminate",
+                |s| s.try_terminate("ops".to_string(), None),
+                &["PENDING", "RUNNING"],
+                "TERMINATED",
+            ),
+            ("retry", |s| s.try_retry(), &["FAILED"], "RUNNING"),
+        ];
+
+        for state in &states {
+            for (attempted, transition, allowed_from, to) in &documented {
+                match transition(state) {
+                    Ok(next) => {
+                        assert!(
+                            allowed_from.contains(&state.label()),
+                            "{} from {} should be rejected",
+                            attempted,
+                            state.label()
+                        );
+                        assert_eq!(next.label(), *to);
+                    }
+                    Err(e) => {
+                        assert!(
+                            !allowed_from.contains(&state.label()),
+                            "{} from {} should be allowed",
+                            attempted,
+                            state.label()
+                        );
+                        assert_eq!(
+                            e,
+                            InvalidTransition {
+                                from: state.label(),
+                                attempted,
+                            }
+                        );
+                    }
+                }
+            }
+            // 终态不能再转换到其他状态，只有失败后可以重试
+            let reachable = documented
+                .iter()
+                .filter(|(_, transition, _, _)| transition(state).is_ok())
+                .count();
+            match state {
+                WorkflowState::Failed { .. } => assert_eq!(reachable, 1),
+                _ if state.is_terminal() => assert_eq!(reachable, 0),
+                _ => assert!(reachable > 0),
+            }
+        }
+
+        let err = WorkflowState::Cancelled
+            .try_complete(Vec::new())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot complete a workflow in state CANCELLED"
+        );
+    }
+
+    #[test]
+    fn test_workflow_serde_roundtrip() {
+        let states = [
+            WorkflowState::Pending,
+            WorkflowState::Running {
+                current_step: Some("fetch".to_string()),
+            },
+            WorkflowState::Completed {
+                result: Payload::from_bytes(br#"{"total":42}"#.to_vec()),
+            },
+            WorkflowState::Completed {
+                result: Payload::from_bytes(vec![0xff, 0x00, 0xfe]),
+            },
+            WorkflowState::Failed {
+                error: "boom".to_string(),
+            },
+            WorkflowState::Cancelled,
+            WorkflowState::Terminated {
+                reason: "stuck".to_string(),
+                requested_by: Some("ops".to_string()),
+            },
+        ];
+        for state in states {
+            // 输入不是合法的 UTF-8
+            let mut workflow = Workflow::new(
+                "wf-1".to_string(),
+                "order".to_string(),
+                vec![0xc3, 0x28, 0xff],
+            );
+            workflow
+                .completed_steps
+                .insert("fetch".to_string(), Payload::from_bytes(vec![0x80, 0x81]));
+            workflow.attempts.insert("fetch".to_string(), 2);
+            workflow.parent_workflow_id = Some("parent".to_string());
+            workflow.definition_version = Some(3);
+            let running = workflow.state.try_start().unwrap();
+            workflow.transition(running, Utc::now());
+            workflow.transition(state, Utc::now());
+
+            let json = serde_json::to_string(&workflow).unwrap();
+            assert_eq!(serde_json::from_str::<Workflow>(&json).unwrap(), workflow);
+            let bytes = bincode::serialize(&workflow).unwrap();
+            assert_eq!(bincode::deserialize::<Workflow>(&bytes).unwrap(), workflow);
+        }
+    }
+
+    #[test]
+    fn test_workflow_without_schema_version_deserializes() {
+        let json = r#"{
+            "id": "wf-1",
+            "workflow_type": "order",
+            "state": {"Running": {"current_step": null}},
+            "input": [1, 2, 3],
+            "steps_completed": {},
+            "started_at": "2024-05-01T12:00:00Z",
+            "updated_at": "2024-05-01T12:00:00Z"
+        }"#;
+        let workflow: Workflow = serde_json::from_str(json).unwrap();
+        assert_eq!(workflow.schema_version, 0);
+        assert_eq!(workflow.input, vec![1, 2, 3]);
+        assert!(matches!(workflow.state, WorkflowState::Running { .. }));
+
+        let created = Workflow::new("wf-2".to_string(), "order".to_string(), vec![]);
+        assert_eq!(created.schema_version, WORKFLOW_SCHEMA_VERSION);
+        let value = serde_json::to_value(&created).unwrap();
+        assert_eq!(value["schema_version"], WORKFLOW_SCHEMA_VERSION);
+        assert!(value.get("completed_steps").is_some());
+    }
+
+    #[test]
+    fn test_can_retry_until_attempts_are_exhausted() {
+        let mut workflow = Workflow::new("wf-1".to_string(), "order".to_string(), vec![]);
+        // 没有记录尝试次数的失败按一次计算
+        assert!(!workflow.can_retry("charge", 1));
+        assert!(workflow.can_retry("charge", 2));
+
+        assert_eq!(workflow.record_attempt("charge"), 1);
+        assert!(workflow.can_retry("charge", 2));
+        assert!(!workflow.can_retry("charge", 1));
+
+        assert_eq!(workflow.record_attempt("charge"), 2);
+        assert!(!workflow.can_retry("charge", 2));
+        assert!(workflow.can_retry("charge", 3));
+        assert_eq!(workflow.attempts("refund"), 0);
+
+        // 完成的 step 不再重试，输出以负载保存
+        workflow.record_completion("charge", br#"{"ok":true}"#.to_vec());
+        assert!(!workflow.can_retry("charge", 3));
+        assert!(workflow.completed_steps["charge"].is_json());
+    }
+
+    #[test]
+    fn test_legacy_step_outputs_deserialize() {
+        let mut value = serde_json::to_value(Workflow::new(
+            "wf-1".to_string(),
+            "order".to_string(),
+            vec![],
+        ))
+        .unwrap();
+        let object = value.as_object_mut().unwrap();
+        object.remove("completed_steps");
+        object.remove("attempts");
+        object.insert(
+            "steps_completed".to_string(),
+            serde_json::json!({ "charge": [111, 107] }),
+        );
+
+        let workflow: Workflow = serde_json::from_value(value).unwrap();
+        assert_eq!(workflow.completed_steps["charge"].as_bytes(), b"ok");
+        assert!(workflow.attempts.is_empty());
+    }
+
+    #[test]
+    fn test_transitions_are_recorded_and_bounded() {
+        let mut workflow = Workflow::new("wf-1".to_string(), "loop".to_string(), vec![]);
+        let started_at = workflow.started_at;
+        let at = |secs| started_at + chrono::Duration::seconds(secs);
+        let (t0, t5) = (at(0), at(5));
+        workflow.transition(workflow.state.try_start().unwrap(), t0);
+        workflow.transition(workflow.state.try_step_started("fetch").unwrap(), t5);
+        // 同一 step 再次开始、step 完成都不记录
+        workflow.transition(workflow.state.try_step_started("fetch").unwrap(), at(6));
+        workflow.transition(workflow.state.try_step_completed().unwrap(), at(7));
+        assert_eq!(workflow.history.len(), 2);
+        assert_eq!(workflow.queue_time(), Some(Duration::from_secs(5)));
+        assert_eq!(workflow.execution_time(), None);
+
+        for i in 0..MAX_STATE_HISTORY as i64 {
+            let step = if i % 2 == 0 { "poll" } else { "fetch" };
+            workflow.transition(workflow.state.try_step_started(step).unwrap(), at(10 + i));
+        }
+        let failed = workflow.state.try_fail("boom".to_string()).unwrap();
+        workflow.transition(failed, at(200));
+        assert_eq!(workflow.history.len(), MAX_STATE_HISTORY);
+        // 开始执行前的记录保留，排队时间不变
+        assert_eq!(workflow.history[0].to, "RUNNING");
+        assert_eq!(workflow.history[1].step.as_deref(), Some("fetch"));
+        assert_eq!(workflow.history[1].at, t5);
+        let last = workflow.history.last().unwrap();
+        assert_eq!(
+            (last.from.as_str(), last.to.as_str()),
+            ("RUNNING", "FAILED")
+        );
+        assert_eq!(last.reason.as_deref(), Some("boom"));
+        assert_eq!(workflow.queue_time(), Some(Duration::from_secs(5)));
+        assert_eq!(workflow.execution_time(), Some(Duration::from_secs(195)));
+
+        let mut stats = DurationStats::default();
+        stats.record(&workflow);
+        stats.record(&Workflow::new(
+            "wf-2".to_string(),
+            "loop".to_string(),
+            vec![],
         ));
+        assert_eq!(stats.workflows, 1);
+        assert_eq!(stats.avg_execution(), Duration::from_secs(195));
     }
 }
@@ -0,0 +1,398 @@
+//! 系统概览统计
+//!
+//! workflow 计数、启动速率和 step 耗时在状态变化时增量更新（由 [`WorkflowTracker`] 记录），
+//! 查询只在固定大小的窗口上汇总，不扫描持久化层，dashboard 可以每隔几秒轮询一次。
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::broadcaster::EventBroadcaster;
+use crate::persistence::{ListOptions, Persistence, StateKind};
+use crate::scheduler::Scheduler;
+use crate::tracker::WorkflowTracker;
+
+/// 启动速率窗口覆盖的秒数（最长统计最近 60 分钟）
+pub const STARTED_WINDOW_SECS: usize = 3600;
+
+/// 计算 step 耗时分位数时保留的最近样本数
+pub const STEP_DURATION_SAMPLES: usize = 1024;
+
+/// 按秒分桶的滚动计数器，覆盖最近 `buckets.len()` 秒
+#[derive(Debug, Clone)]
+struct RollingCounter {
+    buckets: Vec<u64>,
+    /// 最近一次推进到的秒
+    latest: i64,
+}
+
+impl RollingCounter {
+    fn new(window_secs: usize) -> Self {
+        RollingCounter {
+            buckets: vec![0; window_secs.max(1)],
+            latest: 0,
+        }
+    }
+
+    fn slot(&self, second: i64) -> usize {
+        second.rem_euclid(self.buckets.len() as i64) as usize
+    }
+
+    /// 推进到 `now`，清空这期间滚出窗口的桶
+    fn advance(&mut self, now: i64) {
+        if now <= self.latest {
+            return;
+        }
+        let len = self.buckets.len() as i64;
+        if now - self.latest >= len {
+            self.buckets.iter_mut().for_each(|bucket| *bucket = 0);
+        } else {
+            for second in self.latest + 1..=now {
+                let slot = self.slot(second);
+                self.buckets[slot] = 0;
+            }
+        }
+        self.latest = now;
+    }
+
+    /// 在 `second` 计一次，早于窗口的记录被忽略
+    fn record(&mut self, second: i64) {
+        self.advance(second);
+        if self.latest - second >= self.buckets.len() as i64 {
+            return;
+        }
+        let slot = self.slot(second);
+        self.buckets[slot] += 1;
+    }
+
+    /// 截至 `now`（含）最近 `window_secs` 秒内的计数
+    fn count(&mut self, now: i64, window_secs: usize) -> u64 {
+        self.advance(now);
+        let len = self.buckets.len() as i64;
+        let window = window_secs.min(self.buckets.len()) as i64;
+        (now - window + 1..=now)
+            .filter(|second| *second <= self.latest && self.latest - second < len)
+            .map(|second| self.buckets[self.slot(second)])
+            .sum()
+    }
+}
+
+/// 最近若干个 step 耗时样本（毫秒）
+#[derive(Debug, Clone)]
+struct DurationSamples {
+    samples: VecDeque<u64>,
+    capacity: usize,
+}
+
+impl DurationSamples {
+    fn new(capacity: usize) -> Self {
+        DurationSamples {
+            samples: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    fn record(&mut self, duration: Duration) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(duration.as_millis() as u64);
+    }
+
+    /// 按最近秩法计算分位数，`percentile` 取 0-100，没有样本时返回 `None`
+    fn percentile(&self, percentile: f64) -> Option<u64> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<u64> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let rank = (percentile / 100.0 * sorted.len() as f64).ceil() as usize;
+        Some(sorted[rank.clamp(1, sorted.len()) - 1])
+    }
+}
+
+/// 按状态统计的 workflow 数量
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkflowCounts {
+    /// 未结束（等待中或运行中）的 workflow
+    pub active: u64,
+    /// 已完成的 workflow，清理不会扣减
+    pub completed: u64,
+    /// 失败的 workflow，重试时扣减，清理不会扣减
+    pub failed: u64,
+}
+
+struct LiveStatsInner {
+    workflows: WorkflowCounts,
+    started: RollingCounter,
+    step_durations: DurationSamples,
+    workers: u64,
+}
+
+/// 增量维护的统计数据，克隆得到的是指向同一份数据的句柄
+#[derive(Clone)]
+pub struct LiveStats {
+    inner: Arc<Mutex<LiveStatsInner>>,
+}
+
+impl LiveStats {
+    pub fn new() -> Self {
+        LiveStats {
+            inner: Arc::new(Mutex::new(LiveStatsInner {
+                workflows: WorkflowCounts::default(),
+                started: RollingCounter::new(STARTED_WINDOW_SECS),
+                step_durations: DurationSamples::new(STEP_DURATION_SAMPLES),
+                workers: 0,
+            })),
+        }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut LiveStatsInner)) {
+        f(&mut self.inner.lock().expect("stats lock poisoned"));
+    }
+
+    /// 用持久化层中的数量替换 workflow 计数，启动时调用一次
+    pub fn seed(&self, workflows: WorkflowCounts) {
+        self.update(|stats| stats.workflows = workflows);
+    }
+
+    pub fn workflow_started(&self, at: DateTime<Utc>) {
+        self.update(|stats| {
+            stats.workflows.active += 1;
+            stats.started.record(at.timestamp());
+        });
+    }
+
+    pub fn workflow_completed(&self) {
+        self.update(|stats| {
+            stats.workflows.active = stats.workflows.active.saturating_sub(1);
+            stats.workflows.completed += 1;
+        });
+    }
+
+    pub fn workflow_failed(&self) {
+        self.update(|stats| {
+            stats.workflows.active = stats.workflows.active.saturating_sub(1);
+            stats.workflows.failed += 1;
+        });
+    }
+
+    /// workflow 被取消、强制结束或在运行中被删除
+    pub fn workflow_stopped(&self) {
+        self.update(|stats| stats.workflows.active = stats.workflows.active.saturating_sub(1));
+    }
+
+    pub fn workflow_retried(&self) {
+        self.update(|stats| {
+            stats.workflows.failed = stats.workflows.failed.saturating_sub(1);
+            stats.workflows.active += 1;
+        });
+    }
+
+    pub fn step_completed(&self, duration: Duration) {
+        self.update(|stats| stats.step_durations.record(duration));
+    }
+
+    pub fn set_workers(&self, workers: usize) {
+        self.update(|stats| stats.workers = workers as u64);
+    }
+
+    /// 截至 `now` 的统计快照
+    pub fn snapshot(&self, now: DateTime<Utc>) -> LiveSnapshot {
+        let mut stats = self.inner.lock().expect("stats lock poisoned");
+        LiveSnapshot {
+            workflows: stats.workflows,
+            started_last_1m: stats.started.count(now.timestamp(), 60),
+            started_last_5m: stats.started.count(now.timestamp(), 5 * 60),
+            started_last_60m: stats.started.count(now.timestamp(), 60 * 60),
+            step_p50_ms: stats.step_durations.percentile(50.0),
+            step_p95_ms: stats.step_durations.percentile(95.0),
+            workers: stats.workers,
+        }
+    }
+}
+
+impl Default for LiveStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// [`LiveStats`] 的只读快照
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LiveSnapshot {
+    pub workflows: WorkflowCounts,
+    pub started_last_1m: u64,
+    pub started_last_5m: u64,
+    pub started_last_60m: u64,
+    /// 最近完成的 step 耗时中位数，没有样本时为 `None`
+    pub step_p50_ms: Option<u64>,
+    pub step_p95_ms: Option<u64>,
+    /// 当前注册的 worker 数量
+    pub workers: u64,
+}
+
+/// 系统概览：增量统计加上事件广播的订阅和丢失计数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SystemStats {
+    #[serde(flatten)]
+    pub live: LiveSnapshot,
+    pub event_subscribers: u64,
+    pub events_lagged: u64,
+}
+
+impl SystemStats {
+    pub fn collect(tracker: &WorkflowTracker, broadcaster: &EventBroadcaster) -> Self {
+        SystemStats {
+            live: tracker.live_stats().snapshot(Utc::now()),
+            event_subscribers: broadcaster.subscriber_count() as u64,
+            events_lagged: broadcaster.lagged(),
+        }
+    }
+}
+
+impl<P: Persistence> Scheduler<P> {
+    /// 用持久化层中各状态的 workflow 数量初始化增量计数，服务启动时调用一次
+    pub async fn seed_stats(&self) -> anyhow::Result<()> {
+        let mut counts = [0u64; 4];
+        let kinds = [
+            StateKind::Pending,
+            StateKind::Running,
+            StateKind::Completed,
+            StateKind::Failed,
+        ];
+        for (count, kind) in counts.iter_mut().zip(kinds) {
+            let options = ListOptions {
+                state_filter: Some(kind),
+                ..Default::default()
+            };
+            *count = self.persistence.count_workflows(&options).await? as u64;
+        }
+        let [pending, running, completed, failed] = counts;
+        self.tracker.live_stats().seed(WorkflowCounts {
+            active: pending + running,
+            completed,
+            failed,
+        });
+        Ok(())
+    }
+
+    /// 系统概览统计
+    pub fn stats(&self) -> SystemStats {
+        SystemStats::collect(&self.tracker, &self.broadcaster)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rolling_counter_windows() {
+        let mut counter = RollingCounter::new(STARTED_WINDOW_SECS);
+        let base = 1_700_000_000;
+        // 一小时前（刚好滚出窗口）、50 分钟前、4 分钟前、30 秒前和当前各启动一次
+        for offset in [3600, 3000, 240, 30, 0] {
+            counter.record(base - offset);
+        }
+
+        assert_eq!(counter.count(base, 60), 2);
+        assert_eq!(counter.count(base, 5 * 60), 3);
+        assert_eq!(counter.count(base, 60 * 60), 4);
+
+        // 时间推进后旧的计数滚出窗口
+        assert_eq!(counter.count(base + 61, 60), 0);
+        assert_eq!(counter.count(base + 61, 5 * 60), 2);
+        assert_eq!(counter.count(base + 601, 60 * 60), 3);
+        assert_eq!(counter.count(base + 10_000, 60 * 60), 0);
+    }
+
+    #[test]
+    fn test_rolling_counter_late_records() {
+        let mut counter = RollingCounter::new(60);
+        counter.record(1_000);
+        // 稍晚到达的记录仍计入它所在的秒，早于窗口的被忽略
+        counter.record(995);
+        counter.record(900);
+        assert_eq!(counter.count(1_000, 60), 2);
+        assert_eq!(counter.count(1_000, 5), 1);
+    }
+
+    #[test]
+    fn test_duration_percentiles() {
+        let mut samples = DurationSamples::new(100);
+        assert_eq!(samples.percentile(50.0), None);
+
+        for ms in 1..=100 {
+            samples.record(Duration::from_millis(ms));
+        }
+        assert_eq!(samples.percentile(50.0), Some(50));
+        assert_eq!(samples.percentile(95.0), Some(95));
+        assert_eq!(samples.percentile(100.0), Some(100));
+
+        // 只保留最近的样本
+        for _ in 0..100 {
+            samples.record(Duration::from_millis(7));
+        }
+        assert_eq!(samples.percentile(95.0), Some(7));
+    }
+
+    #[test]
+    fn test_live_stats_counts() {
+        let stats = LiveStats::new();
+        stats.seed(WorkflowCounts {
+            active: 1,
+            completed: 5,
+            failed: 2,
+        });
+        let now = Utc::now();
+        stats.workflow_started(now);
+        stats.workflow_started(now);
+        stats.workflow_completed();
+        stats.workflow_failed();
+        stats.workflow_retried();
+        stats.workflow_stopped();
+        stats.set_workers(3);
+
+        let snapshot = stats.snapshot(now);
+        assert_eq!(
+            snapshot.workflows,
+            WorkflowCounts {
+                active: 1,
+                completed: 6,
+                failed: 2,
+            }
+        );
+        assert_eq!(snapshot.started_last_1m, 2);
+        assert_eq!(snapshot.workers, 3);
+        assert_eq!(snapshot.step_p50_ms, None);
+    }
+
+    #[tokio::test]
+    async fn test_seed_counts_stored_workflows() {
+        let store = Arc::new(crate::persistence::l0_memory::L0MemoryStore::new());
+        let scheduler = Scheduler::new(store.clone());
+        for _ in 0..2 {
+            scheduler
+                .start_workflow(
+                    None,
+                    "order".to_string(),
+                    b"{}".to_vec(),
+                    crate::scheduler::StartOptions::default(),
+                )
+                .await
+                .unwrap();
+        }
+
+        // 重启后追踪器的计数从零开始，由持久化层初始化
+        let restarted = Scheduler::new(store);
+        assert_eq!(restarted.stats().live.workflows, WorkflowCounts::default());
+        restarted.seed_stats().await.unwrap();
+        let stats = restarted.stats();
+        assert_eq!(stats.live.workflows.active, 2);
+        assert_eq!(stats.live.started_last_60m, 0);
+    }
+}
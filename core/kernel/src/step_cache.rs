@@ -0,0 +1,342 @@
+//! step 结果缓存
+//!
+//! 定义中标记 `cacheable` 的 step，或目标资源在元数据中声明了 `cacheable` 的 step 被视为纯函数：
+//! 同一资源、同一输入的结果可以复用。这类 step 就绪时，调度器按 (资源名, 输入) 的哈希查找缓存，
+//! 命中时不进入就绪队列，直接以缓存的结果完成 step，不需要有能执行它的 worker 在线，
+//! 也不占用 poll 的 task 数量；step 执行记录标记 `cache_hit`，事件照常广播。
+//! 未命中的 task 正常分发，worker 成功完成后结果写入缓存。缓存条目在
+//! [`SchedulerConfig::step_cache_ttl`](crate::scheduler::SchedulerConfig::step_cache_ttl) 之后过期，
+//! 条目数超过 `step_cache_max_entries` 时先淘汰过期的，再淘汰最早缓存的。
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::task::JoinHandle;
+
+use crate::persistence::Persistence;
+use crate::scheduler::Scheduler;
+use crate::task::{Task, TaskId};
+use crate::telemetry::task_span;
+
+/// 默认缓存有效期
+pub const DEFAULT_STEP_CACHE_TTL: Duration = Duration::from_secs(3600);
+
+/// 默认最多缓存的结果数量
+pub const DEFAULT_STEP_CACHE_MAX_ENTRIES: usize = 10_000;
+
+/// 缓存任务在没有就绪通知时的检查间隔
+pub const STEP_CACHE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 缓存键：资源名和输入的 SHA-256
+pub fn cache_key(resource: &str, input: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(resource.as_bytes());
+    // 资源名不含 NUL，分隔后不同的 (资源名, 输入) 不会拼出相同的字节
+    hasher.update([0]);
+    hasher.update(input);
+    format!("{:x}", hasher.finalize())
+}
+
+/// 一条缓存的 step 结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedResult {
+    pub key: String,
+    pub result: Vec<u8>,
+    pub cached_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl CachedResult {
+    /// 从现在起 `ttl` 之后过期的缓存条目
+    pub fn new(key: impl Into<String>, result: Vec<u8>, ttl: Duration) -> Self {
+        let cached_at = Utc::now();
+        CachedResult {
+            key: key.into(),
+            result,
+            cached_at,
+            expires_at: chrono::Duration::from_std(ttl)
+                .ok()
+                .and_then(|ttl| cached_at.checked_add_signed(ttl))
+                .unwrap_or(DateTime::<Utc>::MAX_UTC),
+        }
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at <= now
+    }
+}
+
+/// 内存中的结果缓存
+///
+/// 内存和文件存储使用；文件存储不把缓存写入磁盘，服务器重启后缓存为空。
+#[derive(Debug, Default)]
+pub struct MemoryResultCache {
+    entries: Mutex<HashMap<String, CachedResult>>,
+}
+
+impl MemoryResultCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 未过期的缓存结果，过期的条目顺便删除
+    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some(entry) if !entry.is_expired(Utc::now()) => Some(entry.result.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// 保存缓存结果，同 key 的条目被替换；超过 `max_entries` 时先淘汰过期的，再淘汰最早缓存的
+    pub fn put(&self, entry: &CachedResult, max_entries: usize) {
+        if max_entries == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.insert(entry.key.clone(), entry.clone());
+        if entries.len() > max_entries {
+            let now = Utc::now();
+            entries.retain(|_, cached| !cached.is_expired(now));
+        }
+        while entries.len() > max_entries {
+            let oldest = entries
+                .values()
+                .filter(|cached| cached.key != entry.key)
+                .min_by_key(|cached| cached.cached_at)
+                .map(|cached| cached.key.clone());
+            match oldest {
+                Some(key) => entries.remove(&key),
+                None => break,
+            };
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// 就绪时命中缓存、等待以缓存结果完成的 step
+///
+/// 使用同步锁，刷新就绪队列时也可以登记。
+#[derive(Debug, Default)]
+pub(crate) struct CachedSteps {
+    inner: Mutex<CachedStepsState>,
+}
+
+#[derive(Debug, Default)]
+struct CachedStepsState {
+    /// 尚未完成的命中及缓存的结果
+    pending: Vec<(Task, Vec<u8>)>,
+    /// 已命中、尚未完成的 task，刷新队列时不再重复查找
+    serving: HashSet<TaskId>,
+}
+
+impl CachedSteps {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn push(&self, task: Task, result: Vec<u8>) {
+        let mut state = self.inner.lock().unwrap();
+        state
+            .serving
+            .insert(TaskId::new(&task.workflow_id, &task.step_name));
+        state.pending.push((task, result));
+    }
+
+    pub(crate) fn is_serving(&self, task_id: &TaskId) -> bool {
+        self.inner.lock().unwrap().serving.contains(task_id)
+    }
+
+    /// 取出等待完成的命中，完成后调用 [`Self::finish`]
+    fn take(&self) -> Vec<(Task, Vec<u8>)> {
+        std::mem::take(&mut self.inner.lock().unwrap().pending)
+    }
+
+    fn finish(&self, task_id: &TaskId) {
+        self.inner.lock().unwrap().serving.remove(task_id);
+    }
+}
+
+/// 服务器启动以来的缓存查找次数
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StepCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl StepCacheStats {
+    /// 命中率，没有查找过时为 0
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+impl<P: Persistence> Scheduler<P> {
+    /// task 的缓存键；step 未标记 `cacheable`、服务注册表中也没有实例把目标资源
+    /// （未指定时为同名资源）声明为 `cacheable`，或缓存容量为 0 时返回 `None`
+    pub(crate) fn step_cache_key(&self, cacheable: bool, task: &Task) -> Option<String> {
+        if self.config().step_cache_max_entries == 0 {
+            return None;
+        }
+        let resource = task.target_resource.as_deref().unwrap_or(&task.step_name);
+        let cacheable = cacheable
+            || self
+                .service_registry
+                .find_resource(resource)
+                .into_iter()
+                .filter(|(service, _, _)| {
+                    task.target_service
+                        .as_ref()
+                        .is_none_or(|target| target == service)
+                })
+                .any(|(_, _, declared)| declared.metadata.is_some_and(|m| m.cacheable));
+        cacheable.then(|| cache_key(resource, task.input.as_bytes()))
+    }
+
+    /// 查找缓存的 step 结果并计入命中统计；读取失败时按未命中处理
+    pub(crate) async fn cached_step_result(&self, key: &str) -> Option<Vec<u8>> {
+        let cached = match self.persistence.get_cached_result(key).await {
+            Ok(cached) => cached,
+            Err(e) => {
+                tracing::warn!(key, "failed to read step cache: {}", e);
+                None
+            }
+        };
+        let counter = match cached {
+            Some(_) => &self.step_cache_hits,
+            None => &self.step_cache_misses,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        cached
+    }
+
+    /// 以缓存的结果完成刷新就绪队列时命中缓存的 step，返回完成的数量
+    ///
+    /// 完成失败（例如 workflow 已被取消）时 workflow 标记为待刷新，step 仍就绪时下一次刷新重新查找。
+    pub(crate) async fn complete_cached_steps(&self) -> usize {
+        let mut completed = 0;
+        for (task, result) in self.cached_steps.take() {
+            let task_id = TaskId::new(&task.workflow_id, &task.step_name);
+            task_span(&task_id, None).in_scope(|| tracing::info!("step result served from cache"));
+            match self.lifecycle().complete_cached(&task, result).await {
+                Ok(()) => completed += 1,
+                Err(e) => {
+                    tracing::warn!(%task_id, "failed to complete step from cache: {}", e);
+                    self.stale_workflows.mark(&task.workflow_id);
+                }
+            }
+            self.cached_steps.finish(&task_id);
+        }
+        completed
+    }
+
+    /// 刷新就绪队列并以缓存的结果完成命中的 step，不依赖 worker 的 poll
+    pub async fn serve_cached_steps(&self) -> anyhow::Result<usize> {
+        if self.config().step_cache_max_entries == 0 {
+            return Ok(0);
+        }
+        self.refresh_ready_queue(&mut *self.ready_queue.lock().await)
+            .await?;
+        Ok(self.complete_cached_steps().await)
+    }
+
+    /// 服务器启动以来的缓存命中统计
+    pub fn step_cache_stats(&self) -> StepCacheStats {
+        StepCacheStats {
+            hits: self.step_cache_hits.load(Ordering::Relaxed),
+            misses: self.step_cache_misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// 启动后台缓存任务
+///
+/// workflow 启动或 step 完成时立即检查新就绪的 step，否则每 [`STEP_CACHE_CHECK_INTERVAL`] 检查一次。
+pub fn spawn_step_cache_task<P: Persistence + 'static>(
+    scheduler: Arc<Scheduler<P>>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ready = scheduler.subscribe_tasks();
+        loop {
+            ready.borrow_and_update();
+            match scheduler.serve_cached_steps().await {
+                Ok(0) => {}
+                Ok(served) => tracing::debug!(served, "steps served from cache"),
+                Err(e) => tracing::warn!("step cache check failed: {}", e),
+            }
+            tokio::select! {
+                _ = ready.changed() => {}
+                _ = scheduler.stopped() => break,
+                _ = tokio::time::sleep(STEP_CACHE_CHECK_INTERVAL) => {}
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_key_depends_on_resource_and_input() {
+        let key = cache_key("geo-lookup", b"{\"ip\":\"1.2.3.4\"}");
+        assert_eq!(key, cache_key("geo-lookup", b"{\"ip\":\"1.2.3.4\"}"));
+        assert_ne!(key, cache_key("geo-lookup", b"{\"ip\":\"5.6.7.8\"}"));
+        assert_ne!(key, cache_key("fx-rate", b"{\"ip\":\"1.2.3.4\"}"));
+        assert_ne!(cache_key("ab", b"c"), cache_key("a", b"bc"));
+    }
+
+    #[test]
+    fn test_memory_cache_expires_and_evicts_oldest() {
+        let cache = MemoryResultCache::new();
+        cache.put(&CachedResult::new("a", b"1".to_vec(), Duration::ZERO), 2);
+        assert_eq!(cache.get("a"), None);
+
+        let mut first = CachedResult::new("b", b"2".to_vec(), Duration::from_secs(60));
+        first.cached_at -= chrono::Duration::seconds(10);
+        cache.put(&first, 2);
+        cache.put(
+            &CachedResult::new("c", b"3".to_vec(), Duration::from_secs(60)),
+            2,
+        );
+        cache.put(
+            &CachedResult::new("d", b"4".to_vec(), Duration::from_secs(60)),
+            2,
+        );
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("c"), Some(b"3".to_vec()));
+        assert_eq!(cache.get("d"), Some(b"4".to_vec()));
+
+        cache.put(
+            &CachedResult::new("e", b"5".to_vec(), Duration::from_secs(60)),
+            0,
+        );
+        assert_eq!(cache.get("e"), None);
+    }
+
+    #[test]
+    fn test_hit_rate() {
+        assert_eq!(StepCacheStats::default().hit_rate(), 0.0);
+        let stats = StepCacheStats { hits: 3, misses: 1 };
+        assert_eq!(stats.hit_rate(), 0.75);
+    }
+}
@@ -0,0 +1,1044 @@
+//! Step 生命周期
+//!
+//! gRPC、REST 和 WebSocket 上报的 step 状态都经过这里，
+//! 统一处理追踪器更新、持久化写入、状态转换和事件广播。
+
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Mutex;
+
+use crate::child::ChildWorkflowSpec;
+use crate::definition::WorkflowDefinition;
+use crate::payload::PayloadTooLarge;
+use crate::persistence::Persistence;
+use crate::scheduler::Scheduler;
+use crate::schema::SchemaViolation;
+use crate::state_machine::{Workflow, WorkflowState};
+use crate::task::{Task, TaskId};
+use crate::telemetry::record_task;
+use crate::timer::Timer;
+use crate::tracker::{StepProgress, TrackerError};
+
+/// Step 生命周期错误
+#[derive(Debug)]
+pub enum StepLifecycleError {
+    /// task_id 格式无法解析
+    InvalidTaskId(String),
+    /// workflow 不存在
+    WorkflowNotFound(String),
+    /// task 租约已过期或已转交给其他 worker
+    TaskNotOwned(String),
+    /// step 已经完成，或同一 task 的另一次上报正在处理
+    AlreadyCompleted(String),
+    /// 请求启动的子 workflow 类型未定义
+    UnknownWorkflowType(String),
+    /// workflow 已被取消，不再接受该 task 的上报
+    WorkflowCancelled(String),
+    /// workflow 已被强制结束，不再接受该 task 的上报
+    WorkflowTerminated(String),
+    /// step 输出不符合资源声明的 schema，step 已失败
+    SchemaValidation(SchemaViolation),
+    /// step 输出超过大小上限（step 已失败），或子 workflow 的输入超过上限
+    PayloadTooLarge(PayloadTooLarge),
+    /// 持久化层错误
+    Persistence(anyhow::Error),
+}
+
+impl fmt::Display for StepLifecycleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StepLifecycleError::InvalidTaskId(task_id) => {
+                write!(f, "Invalid task_id format: {}", task_id)
+            }
+            StepLifecycleError::WorkflowNotFound(workflow_id) => {
+                write!(f, "Workflow not found: {}", workflow_id)
+            }
+            StepLifecycleError::TaskNotOwned(task_id) => write!(
+                f,
+                "Task {} is no longer owned by this worker: its lease expired or was handed to another worker",
+                task_id
+            ),
+            StepLifecycleError::AlreadyCompleted(task_id) => {
+                write!(f, "Task {} has already been completed", task_id)
+            }
+            StepLifecycleError::UnknownWorkflowType(workflow_type) => {
+                write!(f, "Unknown child workflow type '{}'", workflow_type)
+            }
+            StepLifecycleError::WorkflowCancelled(workflow_id) => {
+                write!(f, "Workflow {} has been cancelled", workflow_id)
+            }
+            StepLifecycleError::WorkflowTerminated(workflow_id) => {
+                write!(f, "Workflow {} has been terminated", workflow_id)
+            }
+            StepLifecycleError::SchemaValidation(violation) => write!(f, "{}", violation),
+            StepLifecycleError::PayloadTooLarge(e) => write!(f, "{}", e),
+            StepLifecycleError::Persistence(e) => write!(f, "Persistence error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StepLifecycleError {}
+
+impl From<anyhow::Error> for StepLifecycleError {
+    fn from(e: anyhow::Error) -> Self {
+        StepLifecycleError::Persistence(e)
+    }
+}
+
+impl From<TrackerError> for StepLifecycleError {
+    fn from(e: TrackerError) -> Self {
+        match e {
+            TrackerError::WorkflowNotTracked(workflow_id) => {
+                StepLifecycleError::WorkflowNotFound(workflow_id)
+            }
+        }
+    }
+}
+
+/// Step 生命周期服务
+///
+/// 所有传输层都通过 `Scheduler::lifecycle()` 获取该服务，
+/// 保证同样的调用序列产生同样的追踪状态和事件。
+pub struct StepLifecycle<'a, P: Persistence> {
+    scheduler: &'a Scheduler<P>,
+}
+
+impl<P: Persistence> Scheduler<P> {
+    /// 获取 step 生命周期服务
+    pub fn lifecycle(&self) -> StepLifecycle<'_, P> {
+        StepLifecycle { scheduler: self }
+    }
+}
+
+impl<P: Persistence> StepLifecycle<'_, P> {
+    /// 记录 step 开始执行
+    #[tracing::instrument(name = "step", skip_all, fields(workflow_id = %workflow_id, step_name = %step_name))]
+    pub async fn step_started(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+        input: Vec<u8>,
+    ) -> Result<(), StepLifecycleError> {
+        let workflow = self.load_tracked(workflow_id).await?;
+        let dependencies = self
+            .scheduler
+            .workflow_definition(&workflow)
+            .await
+            .map(|definition| definition.dependencies(step_name))
+            .unwrap_or_default();
+
+        self.scheduler
+            .tracker
+            .step_started(workflow_id, step_name, input.clone(), dependencies)
+            .await?;
+        // 尝试次数在分发时随 workflow 持久化
+        let attempt = attempt(&workflow, step_name);
+        tracing::info!(attempt, "step started");
+
+        if let Ok(new_state) = workflow.state.try_step_started(step_name) {
+            self.scheduler
+                .persistence
+                .update_workflow_state(workflow_id, new_state)
+                .await?;
+            self.scheduler.notify_workflow_changed(workflow_id);
+        }
+
+        self.scheduler
+            .broadcaster
+            .broadcast_step_started(
+                workflow_id,
+                &workflow.workflow_type,
+                step_name,
+                input,
+                attempt,
+            )
+            .await;
+
+        Ok(())
+    }
+
+    /// 记录 step 完成
+    ///
+    /// 只更新追踪器并广播事件，不推进 workflow；推进由 `complete_task` 负责。
+    pub async fn step_completed(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+        output: Vec<u8>,
+    ) -> Result<(), StepLifecycleError> {
+        let workflow = self.load_tracked(workflow_id).await?;
+        self.record_step_completed(&workflow, step_name, output)
+            .await;
+        Ok(())
+    }
+
+    /// 记录 step 失败
+    ///
+    /// 尝试次数未达到重试策略的 `max_attempts` 时，task 在退避之后重新分发；
+    /// 次数用尽后 workflow 随之失败。
+    pub async fn step_failed(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+        error: String,
+    ) -> Result<(), StepLifecycleError> {
+        let workflow = self.load_tracked(workflow_id).await?;
+        let attempt = attempt(&workflow, step_name);
+
+        self.scheduler
+            .tracker
+            .step_failed(workflow_id, step_name, error.clone())
+            .await;
+        tracing::warn!(workflow_id, step_name, attempt, %error, "step failed");
+
+        self.scheduler
+            .broadcaster
+            .broadcast_step_failed(
+                workflow_id,
+                &workflow.workflow_type,
+                step_name,
+                error.clone(),
+                attempt,
+            )
+            .await;
+
+        let policy = self.scheduler.step_retry_policy(&workflow, step_name).await;
+        let running = matches!(workflow.state, WorkflowState::Running { .. });
+        if running && workflow.can_retry(step_name, policy.max_attempts) {
+            let backoff = policy.backoff(attempt);
+            tracing::info!(
+                workflow_id,
+                step_name,
+                attempt,
+                ?backoff,
+                "step retry scheduled"
+            );
+            self.scheduler
+                .schedule_retry(&workflow, step_name, backoff)
+                .await?;
+            return Ok(());
+        }
+
+        self.fail_workflow(&workflow, step_name, error).await
+    }
+
+    /// step 最终失败，workflow 随之失败
+    async fn fail_workflow(
+        &self,
+        workflow: &Workflow,
+        step_name: &str,
+        error: String,
+    ) -> Result<(), StepLifecycleError> {
+        let workflow_id = workflow.id.as_str();
+        if let Ok(failed_state) = workflow.state.try_fail(error.clone()) {
+            self.scheduler
+                .persistence
+                .update_workflow_state(workflow_id, failed_state)
+                .await?;
+
+            self.scheduler
+                .release_lease(&TaskId::new(workflow_id, step_name))
+                .await;
+            self.scheduler.tracker.workflow_failed(workflow_id).await;
+            tracing::warn!(workflow_id, step_name, "workflow failed");
+            self.scheduler
+                .broadcaster
+                .broadcast_workflow_failed(workflow_id, &workflow.workflow_type, error)
+                .await;
+            // 父 workflow 可能在等待该 workflow 结束
+            self.scheduler.notify_workflow_changed(workflow_id);
+        }
+
+        Ok(())
+    }
+
+    /// 完成 task：保存 step 结果并推进 workflow
+    ///
+    /// workflow 与 step 从 task_id 中解析，不依赖租约：服务器重启后租约丢失，
+    /// worker 仍然可以完成之前领取的 task。租约已过期或属于其他 worker 时拒绝完成，
+    /// 避免同一个 step 被完成两次。
+    pub async fn complete_task(
+        &self,
+        task_id: &str,
+        worker_id: Option<&str>,
+        result: Vec<u8>,
+    ) -> Result<(), StepLifecycleError> {
+        self.complete_task_with_children(task_id, worker_id, result, Vec::new())
+            .await
+    }
+
+    /// 完成 task，并在推进 workflow 之前启动 step 请求的子 workflow
+    ///
+    /// 子 workflow 的类型全部校验通过后才会保存任何数据。
+    #[tracing::instrument(
+        name = "task",
+        skip_all,
+        fields(task_id = %task_id, workflow_id, step_name, worker_id = worker_id)
+    )]
+    pub async fn complete_task_with_children(
+        &self,
+        task_id: &str,
+        worker_id: Option<&str>,
+        result: Vec<u8>,
+        children: Vec<ChildWorkflowSpec>,
+    ) -> Result<(), StepLifecycleError> {
+        let task_id = parse_task_id(task_id)?;
+        record_task(&tracing::Span::current(), &task_id);
+        let _claim = self.claim(&task_id).await?;
+        self.ensure_owned(&task_id, worker_id).await?;
+        let (workflow_id, step_name) = (task_id.workflow_id.as_str(), task_id.step_name.as_str());
+        let workflow = self.load_tracked(workflow_id).await?;
+        ensure_not_cancelled(&workflow)?;
+
+        // 输出过大或不符合 schema 时 step 直接失败，不保存结果，也不启动子 workflow
+        if let Err(too_large) = self.scheduler.check_output_size(&result) {
+            tracing::warn!(%too_large, "step output rejected");
+            self.fail_step(workflow_id, step_name, too_large.to_string())
+                .await?;
+            return Err(StepLifecycleError::PayloadTooLarge(too_large));
+        }
+        if let Some(violation) = self
+            .scheduler
+            .output_violation(&workflow, &task_id, &result)
+            .await
+        {
+            tracing::warn!(%violation, "step output rejected");
+            self.fail_step(workflow_id, step_name, violation.to_string())
+                .await?;
+            return Err(StepLifecycleError::SchemaValidation(violation));
+        }
+
+        let spawns_children = !children.is_empty();
+        for child in &children {
+            if !self
+                .scheduler
+                .accepts_workflow_type(&child.workflow_type)
+                .await
+            {
+                return Err(StepLifecycleError::UnknownWorkflowType(
+                    child.workflow_type.clone(),
+                ));
+            }
+            self.scheduler
+                .check_input_size(&child.input)
+                .map_err(StepLifecycleError::PayloadTooLarge)?;
+        }
+        if !children.is_empty() {
+            self.scheduler
+                .start_child_workflows(workflow_id, children)
+                .await?;
+        }
+
+        if self.scheduler.lease(&task_id).await.is_none() {
+            tracing::debug!(%task_id, "completing task without a lease");
+        }
+
+        // 保存 step 结果到持久化层
+        self.scheduler
+            .persistence
+            .save_step_result(workflow_id, step_name, result.clone())
+            .await?;
+        // 启动了子 workflow 的结果不缓存，复用结果会跳过子 workflow
+        if !spawns_children {
+            self.scheduler.cache_step_result(&task_id, &result).await;
+        }
+
+        self.record_step_completed(&workflow, step_name, result.clone())
+            .await;
+        self.scheduler.release_lease(&task_id).await;
+        tracing::info!("step completed");
+
+        if let Some(definition) = self.scheduler.workflow_definition(&workflow).await {
+            self.advance_defined(workflow_id, &definition, step_name, result)
+                .await?;
+            // 依赖该 step 的后续 step 可能已经就绪
+            self.scheduler.notify_workflow_changed(workflow_id);
+            return Ok(());
+        }
+
+        // 没有定义时只有一个 "start" step，它完成即整个 workflow 执行完成
+        // 使用 complete() 而不是 step_completed() 来标记为已完成
+        if step_name == "start" {
+            if let Ok(completed_state) = workflow.state.try_complete(result.clone()) {
+                self.scheduler
+                    .persistence
+                    .update_workflow_state(workflow_id, completed_state)
+                    .await?;
+
+                self.scheduler.tracker.workflow_completed(workflow_id).await;
+                tracing::info!(workflow_id, "workflow completed");
+                self.scheduler
+                    .broadcaster
+                    .broadcast_workflow_completed(workflow_id, &workflow.workflow_type, result)
+                    .await;
+                // 父 workflow 可能在等待该 workflow 结束
+                self.scheduler.notify_workflow_changed(workflow_id);
+            }
+        } else if let Ok(new_state) = workflow.state.try_step_completed() {
+            // 普通 step 完成，继续执行下一个 step
+            self.scheduler
+                .persistence
+                .update_workflow_state(workflow_id, new_state)
+                .await?;
+            self.scheduler.notify_workflow_changed(workflow_id);
+        }
+
+        Ok(())
+    }
+
+    /// 以缓存的结果完成已领取的 task，不交给 worker 执行
+    ///
+    /// 与 worker 完成 task 一样记录 step 开始和完成、广播事件并推进 workflow，
+    /// step 执行记录另外标记 `cache_hit`。
+    pub async fn complete_cached(
+        &self,
+        task: &Task,
+        result: Vec<u8>,
+    ) -> Result<(), StepLifecycleError> {
+        self.step_started(
+            &task.workflow_id,
+            &task.step_name,
+            task.input.as_bytes().to_vec(),
+        )
+        .await?;
+        self.scheduler
+            .tracker
+            .step_cache_hit(&task.workflow_id, &task.step_name)
+            .await;
+        let task_id = TaskId::new(&task.workflow_id, &task.step_name);
+        self.complete_task(&task_id.to_string(), None, result).await
+    }
+
+    /// 按定义推进 workflow：记录已完成的 step，全部 step 完成时 workflow 完成
+    ///
+    /// 并行的 step 可能同时完成，读改写在 `advance_lock` 下进行。
+    async fn advance_defined(
+        &self,
+        workflow_id: &str,
+        definition: &WorkflowDefinition,
+        step_name: &str,
+        result: Vec<u8>,
+    ) -> Result<(), StepLifecycleError> {
+        let _guard = self.scheduler.advance_lock.lock().await;
+        let mut workflow = self
+            .scheduler
+            .persistence
+            .get_workflow(workflow_id)
+            .await?
+            .ok_or_else(|| StepLifecycleError::WorkflowNotFound(workflow_id.to_string()))?;
+        workflow.record_completion(step_name, result);
+
+        if !definition.is_complete(&workflow) {
+            // 回到 Running { current_step: None }，由调度器分发后续 step
+            if let Ok(new_state) = workflow.state.try_step_completed() {
+                workflow.transition(new_state, chrono::Utc::now());
+                self.scheduler.persistence.save_workflow(&workflow).await?;
+            }
+            return Ok(());
+        }
+
+        let output = definition.output(&workflow);
+        if let Ok(completed_state) = workflow.state.try_complete(output.clone()) {
+            workflow.transition(completed_state, chrono::Utc::now());
+            self.scheduler.persistence.save_workflow(&workflow).await?;
+
+            self.scheduler
+                .tracker
+                .workflow_completed(&workflow.id)
+                .await;
+            tracing::info!(workflow_id, "workflow completed");
+            self.scheduler
+                .broadcaster
+                .broadcast_workflow_completed(&workflow.id, &workflow.workflow_type, output)
+                .await;
+        }
+
+        Ok(())
+    }
+
+    /// 定时器 step 开始等待：记录 step 开始和触发时间
+    pub async fn timer_started(&self, timer: &Timer) -> Result<(), StepLifecycleError> {
+        self.step_started(&timer.workflow_id, &timer.step_name, Vec::new())
+            .await?;
+        self.scheduler
+            .tracker
+            .timer_scheduled(&timer.workflow_id, &timer.step_name, timer.fire_at)
+            .await;
+        Ok(())
+    }
+
+    /// 定时器到期：以空结果完成 step 并推进 workflow
+    pub async fn timer_fired(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+    ) -> Result<(), StepLifecycleError> {
+        self.complete_server_step(workflow_id, step_name).await
+    }
+
+    /// 子 workflow 全部结束：没有失败时以空结果完成等待 step，否则 workflow 以 `failure` 失败
+    pub async fn children_settled(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+        failure: Option<String>,
+    ) -> Result<(), StepLifecycleError> {
+        match failure {
+            None => self.complete_server_step(workflow_id, step_name).await,
+            Some(error) => self.fail_step(workflow_id, step_name, error).await,
+        }
+    }
+
+    /// step 失败且不再重试，workflow 随之失败
+    pub async fn fail_step(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+        error: String,
+    ) -> Result<(), StepLifecycleError> {
+        let workflow = self.load_tracked(workflow_id).await?;
+        let attempt = attempt(&workflow, step_name);
+        self.scheduler
+            .tracker
+            .step_failed(workflow_id, step_name, error.clone())
+            .await;
+        self.scheduler
+            .broadcaster
+            .broadcast_step_failed(
+                workflow_id,
+                &workflow.workflow_type,
+                step_name,
+                error.clone(),
+                attempt,
+            )
+            .await;
+        self.fail_workflow(&workflow, step_name, error).await
+    }
+
+    /// 以空结果完成不分发给 worker 的 step 并推进 workflow
+    async fn complete_server_step(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+    ) -> Result<(), StepLifecycleError> {
+        let workflow = self.load_tracked(workflow_id).await?;
+        if workflow.completed_steps.contains_key(step_name) {
+            return Ok(());
+        }
+        let Some(definition) = self.scheduler.workflow_definition(&workflow).await else {
+            return Ok(());
+        };
+
+        self.scheduler
+            .persistence
+            .save_step_result(workflow_id, step_name, Vec::new())
+            .await?;
+        self.record_step_completed(&workflow, step_name, Vec::new())
+            .await;
+        self.advance_defined(workflow_id, &definition, step_name, Vec::new())
+            .await?;
+        self.scheduler.notify_workflow_changed(workflow_id);
+        Ok(())
+    }
+
+    /// task 心跳：续约、推迟执行截止时间并记录最新进度
+    #[tracing::instrument(name = "task", skip_all, fields(task_id = %task_id, workflow_id, step_name))]
+    pub async fn heartbeat(
+        &self,
+        task_id: &str,
+        progress: StepProgress,
+    ) -> Result<(), StepLifecycleError> {
+        let task_id = parse_task_id(task_id)?;
+        record_task(&tracing::Span::current(), &task_id);
+        let workflow = self.load_tracked(&task_id.workflow_id).await?;
+        ensure_not_cancelled(&workflow)?;
+
+        self.scheduler.record_step_heartbeat(&task_id).await;
+        tracing::debug!(percent = progress.percent, "heartbeat");
+        self.scheduler
+            .tracker
+            .step_progress(&task_id.workflow_id, &task_id.step_name, progress.clone())
+            .await;
+
+        self.scheduler
+            .broadcaster
+            .broadcast_step_progress(
+                &task_id.workflow_id,
+                &workflow.workflow_type,
+                &task_id.step_name,
+                progress,
+            )
+            .await;
+
+        Ok(())
+    }
+
+    /// task 执行失败，租约检查同 [`StepLifecycle::complete_task`]
+    #[tracing::instrument(
+        name = "task",
+        skip_all,
+        fields(task_id = %task_id, workflow_id, step_name, worker_id = worker_id)
+    )]
+    pub async fn fail_task(
+        &self,
+        task_id: &str,
+        worker_id: Option<&str>,
+        error: String,
+    ) -> Result<(), StepLifecycleError> {
+        let task_id = parse_task_id(task_id)?;
+        record_task(&tracing::Span::current(), &task_id);
+        let _claim = self.claim(&task_id).await?;
+        self.ensure_owned(&task_id, worker_id).await?;
+        let workflow = self.load_tracked(&task_id.workflow_id).await?;
+        ensure_not_cancelled(&workflow)?;
+        self.step_failed(&task_id.workflow_id, &task_id.step_name, error)
+            .await
+    }
+
+    /// 占用 task 直到上报处理完毕
+    ///
+    /// 同一 task 的另一次完成或失败正在处理，或 step 已有结果时返回 `AlreadyCompleted`，
+    /// 先到的上报生效，之后的不会覆盖它的输出。
+    async fn claim(&self, task_id: &TaskId) -> Result<SettlingClaim<'_>, StepLifecycleError> {
+        let tasks = self.scheduler.settling_tasks.as_ref();
+        if !tasks.lock().unwrap().insert(task_id.clone()) {
+            return Err(StepLifecycleError::AlreadyCompleted(task_id.to_string()));
+        }
+        let claim = SettlingClaim {
+            tasks,
+            task_id: task_id.clone(),
+        };
+        let completed = self
+            .scheduler
+            .persistence
+            .get_step_result(&task_id.workflow_id, &task_id.step_name)
+            .await?
+            .is_some();
+        if completed {
+            return Err(StepLifecycleError::AlreadyCompleted(task_id.to_string()));
+        }
+        Ok(claim)
+    }
+
+    async fn ensure_owned(
+        &self,
+        task_id: &TaskId,
+        worker_id: Option<&str>,
+    ) -> Result<(), StepLifecycleError> {
+        if self.scheduler.owns_task(task_id, worker_id).await {
+            Ok(())
+        } else {
+            Err(StepLifecycleError::TaskNotOwned(task_id.to_string()))
+        }
+    }
+
+    /// 读取 workflow，并确保追踪器中有对应的执行记录
+    async fn load_tracked(&self, workflow_id: &str) -> Result<Workflow, StepLifecycleError> {
+        let workflow = self
+            .scheduler
+            .persistence
+            .get_workflow(workflow_id)
+            .await?
+            .ok_or_else(|| StepLifecycleError::WorkflowNotFound(workflow_id.to_string()))?;
+
+        if self
+            .scheduler
+            .tracker
+            .get_execution(workflow_id)
+            .await
+            .is_none()
+        {
+            self.scheduler
+                .tracker
+                .start_workflow(workflow.id.clone(), workflow.workflow_type.clone())
+                .await;
+        }
+
+        Ok(workflow)
+    }
+
+    async fn record_step_completed(&self, workflow: &Workflow, step_name: &str, output: Vec<u8>) {
+        self.scheduler
+            .tracker
+            .step_completed(&workflow.id, step_name, output.clone())
+            .await;
+
+        self.scheduler
+            .broadcaster
+            .broadcast_step_completed(&workflow.id, &workflow.workflow_type, step_name, output)
+            .await;
+    }
+}
+
+/// 正在处理完成或失败上报的 task，离开作用域时释放
+struct SettlingClaim<'a> {
+    tasks: &'a Mutex<HashSet<TaskId>>,
+    task_id: TaskId,
+}
+
+impl Drop for SettlingClaim<'_> {
+    fn drop(&mut self) {
+        self.tasks.lock().unwrap().remove(&self.task_id);
+    }
+}
+
+/// workflow 被取消或强制结束后，worker 对其 task 的上报一律拒绝
+fn ensure_not_cancelled(workflow: &Workflow) -> Result<(), StepLifecycleError> {
+    match workflow.state {
+        WorkflowState::Cancelled => Err(StepLifecycleError::WorkflowCancelled(workflow.id.clone())),
+        WorkflowState::Terminated { .. } => {
+            Err(StepLifecycleError::WorkflowTerminated(workflow.id.clone()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// 解析 task_id，格式见 [`TaskId`]
+pub fn parse_task_id(task_id: &str) -> Result<TaskId, StepLifecycleError> {
+    TaskId::parse(task_id).ok_or_else(|| StepLifecycleError::InvalidTaskId(task_id.to_string()))
+}
+
+/// step 当前的尝试次数，尚未开始时为 1
+fn attempt(workflow: &Workflow, step_name: &str) -> u32 {
+    workflow.attempts(step_name).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use crate::api::handlers::steps::{apply_complete, apply_report};
+    use crate::api::models::{CompleteStepRequest, ReportStepRequest};
+    use crate::api::websocket::handle_worker_message;
+    use crate::broadcaster::EventType;
+    use crate::grpc_server::WorkerServiceImpl;
+    use crate::payload::Payload;
+    use crate::persistence::l0_memory::L0MemoryStore;
+    use crate::proto;
+    use crate::proto::worker_service_server::WorkerService;
+    use crate::task::RetryPolicy;
+    use crate::tracker::StepExecutionStatus;
+
+    type TestScheduler = Arc<Scheduler<Arc<L0MemoryStore>>>;
+
+    #[derive(Clone, Copy, Debug)]
+    enum Transport {
+        Grpc,
+        Rest,
+        WebSocket,
+    }
+
+    /// 一次调用序列之后可观察到的全部结果
+    #[derive(Debug, PartialEq)]
+    struct Outcome {
+        step_status: StepExecutionStatus,
+        attempt: u32,
+        output: Option<Payload>,
+        state: String,
+        events: Vec<EventType>,
+    }
+
+    async fn running_scheduler() -> TestScheduler {
+        let scheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        let mut workflow = Workflow::new("wf-1".to_string(), "test-type".to_string(), vec![]);
+        workflow.state = workflow.state.try_start().unwrap();
+        scheduler
+            .persistence
+            .save_workflow(&workflow)
+            .await
+            .unwrap();
+        scheduler
+    }
+
+    async fn report_started(transport: Transport, scheduler: &TestScheduler) {
+        match transport {
+            Transport::Grpc => {
+                WorkerServiceImpl::new(scheduler.clone())
+                    .report_step(tonic::Request::new(proto::ReportStepRequest {
+                        workflow_id: "wf-1".to_string(),
+                        step_name: "start".to_string(),
+                        status: proto::StepStatus::StepStarted as i32,
+                        ..Default::default()
+                    }))
+                    .await
+                    .unwrap();
+            }
+            Transport::Rest => {
+                let req = ReportStepRequest {
+                    status: "STARTED".to_string(),
+                    message: None,
+                    percent: None,
+                    details: None,
+                };
+                apply_report(scheduler, "wf-1-start", None, req)
+                    .await
+                    .unwrap();
+            }
+            Transport::WebSocket => {
+                let text = r#"{"type":"report","taskId":"wf-1-start","status":"STARTED"}"#;
+                handle_worker_message(scheduler, "worker-1", text).await;
+            }
+        }
+    }
+
+    async fn complete(transport: Transport, scheduler: &TestScheduler, error: Option<&str>) {
+        match transport {
+            Transport::Grpc => {
+                WorkerServiceImpl::new(scheduler.clone())
+                    .complete_step(tonic::Request::new(proto::CompleteStepRequest {
+                        task_id: "wf-1-start".to_string(),
+                        result: if error.is_none() {
+                            br#"{"ok":true}"#.to_vec()
+                        } else {
+                            vec![]
+                        },
+                        error: error.unwrap_or_default().to_string(),
+                        worker_id: String::new(),
+                        start_children: vec![],
+                    }))
+                    .await
+                    .unwrap();
+            }
+            Transport::Rest => {
+                let req = CompleteStepRequest {
+                    output: error.is_none().then(|| serde_json::json!({ "ok": true })),
+                    output_base64: None,
+                    error: error.map(str::to_string),
+                    worker_id: None,
+                    start_children: vec![],
+                };
+                apply_complete(scheduler, "wf-1-start", req).await.unwrap();
+            }
+            Transport::WebSocket => {
+                let text = match error {
+                    Some(e) => serde_json::json!({
+                        "type": "complete",
+                        "taskId": "wf-1-start",
+                        "error": e,
+                    }),
+                    None => serde_json::json!({
+                        "type": "complete",
+                        "taskId": "wf-1-start",
+                        "output": { "ok": true },
+                    }),
+                };
+                handle_worker_message(scheduler, "worker-1", &text.to_string()).await;
+            }
+        }
+    }
+
+    async fn run(transport: Transport, error: Option<&str>, max_attempts: u32) -> Outcome {
+        let scheduler = running_scheduler().await;
+        scheduler
+            .set_retry_policy(
+                "start",
+                RetryPolicy {
+                    max_attempts,
+                    ..Default::default()
+                },
+            )
+            .await;
+        let mut rx = scheduler.broadcaster.subscribe();
+
+        report_started(transport, &scheduler).await;
+        complete(transport, &scheduler, error).await;
+
+        let execution = scheduler.tracker.get_execution("wf-1").await.unwrap();
+        let step = execution.step_executions.get("start").unwrap().clone();
+        let workflow = scheduler
+            .persistence
+            .get_workflow("wf-1")
+            .await
+            .unwrap()
+            .unwrap();
+
+        let mut events = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            events.push(event.event_type);
+        }
+
+        Outcome {
+            step_status: step.status,
+            attempt: step.attempt,
+            output: step.output,
+            state: format!("{:?}", workflow.state),
+            events,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transports_agree_on_completion() {
+        let expected = run(Transport::Grpc, None, 1).await;
+        assert_eq!(expected.step_status, StepExecutionStatus::Completed);
+        assert_eq!(
+            expected.events,
+            vec![
+                EventType::StepStarted,
+                EventType::StepCompleted,
+                EventType::WorkflowCompleted
+            ]
+        );
+
+        for transport in [Transport::Rest, Transport::WebSocket] {
+            assert_eq!(run(transport, None, 1).await, expected, "{:?}", transport);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transports_agree_on_failure() {
+        let expected = run(Transport::Grpc, Some("boom"), 1).await;
+        assert_eq!(
+            expected.step_status,
+            StepExecutionStatus::Failed {
+                error: "boom".to_string()
+            }
+        );
+        assert!(expected.state.starts_with("Failed"));
+        assert_eq!(
+            expected.events,
+            vec![
+                EventType::StepStarted,
+                EventType::StepFailed,
+                EventType::WorkflowFailed
+            ]
+        );
+
+        for transport in [Transport::Rest, Transport::WebSocket] {
+            assert_eq!(
+                run(transport, Some("boom"), 1).await,
+                expected,
+                "{:?}",
+                transport
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transports_agree_on_retry() {
+        let expected = run(Transport::Grpc, Some("boom"), 3).await;
+        assert_eq!(expected.attempt, 2);
+        assert!(expected.state.starts_with("Running"));
+        assert_eq!(
+            expected.events,
+            vec![EventType::StepStarted, EventType::StepFailed]
+        );
+
+        for transport in [Transport::Rest, Transport::WebSocket] {
+            assert_eq!(
+                run(transport, Some("boom"), 3).await,
+                expected,
+                "{:?}",
+                transport
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unknown_workflow() {
+        let scheduler = running_scheduler().await;
+        let result = scheduler
+            .lifecycle()
+            .complete_task("missing-start", None, vec![])
+            .await;
+        assert!(matches!(
+            result,
+            Err(StepLifecycleError::WorkflowNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_output_fails_step() {
+        let scheduler = Scheduler::with_config(
+            Arc::new(L0MemoryStore::new()),
+            crate::scheduler::SchedulerConfig {
+                max_output_bytes: 4,
+                ..Default::default()
+            },
+        );
+        let mut workflow = Workflow::new("wf-1".to_string(), "test-type".to_string(), vec![]);
+        workflow.state = workflow.state.try_start().unwrap();
+        scheduler
+            .persistence
+            .save_workflow(&workflow)
+            .await
+            .unwrap();
+
+        let result = scheduler
+            .lifecycle()
+            .complete_task("wf-1-start", None, vec![0; 16])
+            .await;
+        assert!(matches!(
+            result,
+            Err(StepLifecycleError::PayloadTooLarge(PayloadTooLarge {
+                size: 16,
+                limit: 4,
+                ..
+            }))
+        ));
+
+        let workflow = scheduler
+            .persistence
+            .get_workflow("wf-1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(workflow.state, WorkflowState::Failed { .. }));
+        assert!(workflow.completed_steps.is_empty());
+    }
+
+    #[test]
+    fn test_parse_task_id() {
+        assert_eq!(
+            parse_task_id("4:wf-1:fetch-data").unwrap(),
+            TaskId::new("wf-1", "fetch-data")
+        );
+        // 旧格式仍可解析
+        assert_eq!(
+            parse_task_id("wf-1-start").unwrap(),
+            TaskId::new("wf-1", "start")
+        );
+        assert!(parse_task_id("nodash").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_step_names_with_dashes_and_unicode() {
+        let scheduler = Arc::new(Scheduler::new(Arc::new(L0MemoryStore::new())));
+        let mut workflow = Workflow::new("订单-42".to_string(), "test-type".to_string(), vec![]);
+        workflow.state = workflow.state.try_start().unwrap();
+        scheduler
+            .persistence
+            .save_workflow(&workflow)
+            .await
+            .unwrap();
+
+        let task_id = TaskId::new("订单-42", "fetch-数据").to_string();
+        let req = ReportStepRequest {
+            status: "STARTED".to_string(),
+            message: None,
+            percent: None,
+            details: None,
+        };
+        apply_report(&scheduler, &task_id, None, req).await.unwrap();
+
+        let req = CompleteStepRequest {
+            output: Some(serde_json::json!({ "ok": true })),
+            output_base64: None,
+            error: None,
+            worker_id: None,
+            start_children: vec![],
+        };
+        apply_complete(&scheduler, &task_id, req).await.unwrap();
+
+        let execution = scheduler.tracker.get_execution("订单-42").await.unwrap();
+        let step = execution.step_executions.get("fetch-数据").unwrap();
+        assert_eq!(step.status, StepExecutionStatus::Completed);
+
+        let result = scheduler
+            .persistence
+            .get_step_result("订单-42", "fetch-数据")
+            .await
+            .unwrap();
+        assert_eq!(result, Some(br#"{"ok":true}"#.to_vec()));
+    }
+}
@@ -0,0 +1,409 @@
+//! step 执行超时
+//!
+//! 目标资源在元数据中声明了 `timeout`（毫秒）时，调度器在分发 task 时为 step 设置执行截止时间，
+//! 每次进度心跳把截止时间推迟到心跳之后的 `timeout`；只续约租约的心跳不推迟截止时间。
+//! 上报过进度心跳的 step 还需要按心跳间隔继续上报，连续错过
+//! [`SchedulerConfig::max_missed_heartbeats`](crate::scheduler::SchedulerConfig::max_missed_heartbeats) 次同样视为超时。
+//! 超时的 step 记为一次 `TIMEOUT` 失败，持有它的 worker 通过取消通知停止执行，
+//! step 按重试策略重新分发或让 workflow 失败。
+//! 截止时间随租约一起保存在持久化层，服务器重启后为尚未结束的 step 恢复。
+
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+
+use crate::persistence::Persistence;
+use crate::scheduler::Scheduler;
+use crate::state_machine::WorkflowState;
+use crate::task::TaskId;
+use crate::telemetry::task_span;
+
+/// 超时失败的错误码，StepFailed 事件和 workflow 失败原因以它开头
+pub const STEP_TIMEOUT_ERROR: &str = "TIMEOUT";
+
+/// 超时检查任务的检查间隔
+pub const STEP_TIMEOUT_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 已分发 step 的执行截止时间
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepDeadline {
+    pub workflow_id: String,
+    pub step_name: String,
+    /// 领取该 step 的 worker
+    pub worker_id: String,
+    /// 目标资源声明的超时（毫秒）
+    pub timeout_ms: u64,
+    pub deadline: DateTime<Utc>,
+}
+
+impl StepDeadline {
+    /// 从现在起 `timeout` 之后到期的截止时间
+    pub fn new(task_id: &TaskId, worker_id: &str, timeout: Duration) -> Self {
+        StepDeadline {
+            workflow_id: task_id.workflow_id.clone(),
+            step_name: task_id.step_name.clone(),
+            worker_id: worker_id.to_string(),
+            timeout_ms: timeout.as_millis() as u64,
+            deadline: chrono::Duration::from_std(timeout)
+                .ok()
+                .and_then(|timeout| Utc::now().checked_add_signed(timeout))
+                .unwrap_or(DateTime::<Utc>::MAX_UTC),
+        }
+    }
+}
+
+/// 超过执行超时的 step 的错误信息
+pub fn timeout_error(timeout: Duration) -> String {
+    format!(
+        "{}: step did not finish within {}ms",
+        STEP_TIMEOUT_ERROR,
+        timeout.as_millis()
+    )
+}
+
+/// 错过心跳的 step 的错误信息
+pub fn missed_heartbeat_error(window: Duration) -> String {
+    format!(
+        "{}: no heartbeat within {}ms",
+        STEP_TIMEOUT_ERROR,
+        window.as_millis()
+    )
+}
+
+impl<P: Persistence> Scheduler<P> {
+    /// 处理超过截止时间的 step，返回超时的 task
+    ///
+    /// 首次调用时先恢复持久化层中重启前的截止时间。每个超时的 step 通知持有它的 worker 停止执行，
+    /// 记一次失败并广播 `StepFailed` 事件，再按重试策略重新分发或让 workflow 失败。
+    pub async fn expire_step_deadlines(&self) -> anyhow::Result<Vec<TaskId>> {
+        if !self.deadlines_recovered.load(Ordering::SeqCst) {
+            self.recover_step_deadlines().await?;
+            self.deadlines_recovered.store(true, Ordering::SeqCst);
+        }
+
+        let timed_out = self.take_timed_out_leases().await;
+        for (task_id, worker_id, error) in &timed_out {
+            task_span(task_id, Some(worker_id))
+                .in_scope(|| tracing::warn!(%error, "step timed out"));
+            if let Err(e) = self
+                .lifecycle()
+                .step_failed(&task_id.workflow_id, &task_id.step_name, error.clone())
+                .await
+            {
+                tracing::warn!(%task_id, "failed to record step timeout: {}", e);
+            }
+        }
+        Ok(timed_out
+            .into_iter()
+            .map(|(task_id, _, _)| task_id)
+            .collect())
+    }
+
+    /// 为重启前分发、尚未结束的 step 恢复租约和截止时间，删除已失效的截止时间
+    ///
+    /// 截止时间已过的 step 在随后的检查中按超时处理。
+    async fn recover_step_deadlines(&self) -> anyhow::Result<()> {
+        for deadline in self.persistence.list_step_deadlines().await? {
+            let workflow = self.persistence.get_workflow(&deadline.workflow_id).await?;
+            let completed = self
+                .persistence
+                .get_step_result(&deadline.workflow_id, &deadline.step_name)
+                .await?
+                .is_some();
+            match workflow {
+                Some(workflow)
+                    if matches!(workflow.state, WorkflowState::Running { .. }) && !completed =>
+                {
+                    self.restore_step_lease(&workflow, &deadline).await?;
+                }
+                _ => {
+                    self.persistence
+                        .delete_step_deadline(&deadline.workflow_id, &deadline.step_name)
+                        .await?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 启动后台超时检查任务，每 [`STEP_TIMEOUT_CHECK_INTERVAL`] 检查一次
+pub fn spawn_step_timeout_task<P: Persistence + 'static>(
+    scheduler: Arc<Scheduler<P>>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(STEP_TIMEOUT_CHECK_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = scheduler.stopped() => break,
+            }
+            match scheduler.expire_step_deadlines().await {
+                Ok(timed_out) if !timed_out.is_empty() => {
+                    tracing::info!(count = timed_out.len(), "steps timed out");
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("step timeout check failed: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::broadcaster::{EventPayload, EventType};
+    use crate::persistence::l0_memory::L0MemoryStore;
+    use crate::scheduler::SchedulerConfig;
+    use crate::state_machine::Workflow;
+    use crate::task::{ResourceMetadata, ResourceType, RetryPolicy, ServiceResource};
+    use crate::tracker::StepProgress;
+
+    /// 声明了 50ms 超时的 "start" 资源，worker 领取后从不完成
+    async fn timed_scheduler(store: Arc<L0MemoryStore>) -> Scheduler<Arc<L0MemoryStore>> {
+        scheduler_with(store, SchedulerConfig::default(), Some(50)).await
+    }
+
+    async fn scheduler_with(
+        store: Arc<L0MemoryStore>,
+        config: SchedulerConfig,
+        timeout: Option<u64>,
+    ) -> Scheduler<Arc<L0MemoryStore>> {
+        let scheduler = Scheduler::with_config(store, config);
+        scheduler
+            .set_retry_policy(
+                "start",
+                RetryPolicy {
+                    max_attempts: 2,
+                    initial_interval: 10,
+                    backoff_multiplier: 1.0,
+                },
+            )
+            .await;
+        scheduler.service_registry.register(
+            "billing".to_string(),
+            "worker-1".to_string(),
+            "default".to_string(),
+            vec![],
+            vec![ServiceResource {
+                name: "start".to_string(),
+                resource_type: ResourceType::Step,
+                metadata: Some(ResourceMetadata {
+                    max_attempts: None,
+                    timeout,
+                    input_schema: None,
+                    output_schema: None,
+                    cacheable: false,
+                }),
+            }],
+            String::new(),
+        );
+        scheduler
+            .register_worker(
+                "worker-1".to_string(),
+                "billing".to_string(),
+                "default".to_string(),
+                vec!["order".to_string()],
+                vec![("start".to_string(), ResourceType::Step)],
+            )
+            .await;
+        scheduler
+    }
+
+    async fn running_store() -> Arc<L0MemoryStore> {
+        let store = Arc::new(L0MemoryStore::new());
+        let mut workflow = Workflow::new("wf-1".to_string(), "order".to_string(), vec![]);
+        workflow.state = workflow.state.try_start().unwrap();
+        store.save_workflow(&workflow).await.unwrap();
+        store
+    }
+
+    #[tokio::test]
+    async fn test_step_that_never_finishes_times_out_and_is_retried() {
+        let scheduler = timed_scheduler(running_store().await).await;
+        let mut cancellations = scheduler.subscribe_cancellations();
+        let mut events = scheduler.broadcaster.subscribe();
+
+        let first = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(first.len(), 1);
+        scheduler
+            .lifecycle()
+            .step_started("wf-1", "start", vec![])
+            .await
+            .unwrap();
+        let task_id = TaskId::new("wf-1", "start");
+        let persisted = scheduler.persistence.list_step_deadlines().await.unwrap();
+        assert_eq!(persisted.len(), 1);
+        assert_eq!(persisted[0].timeout_ms, 50);
+        assert!(scheduler.expire_step_deadlines().await.unwrap().is_empty());
+
+        // 心跳不推迟截止时间
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(scheduler.extend_lease(&task_id).await);
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(
+            scheduler.expire_step_deadlines().await.unwrap(),
+            vec![task_id.clone()]
+        );
+
+        let cancellation = cancellations.try_recv().unwrap();
+        assert_eq!(cancellation.task_id, task_id);
+        assert_eq!(cancellation.worker_id, "worker-1");
+        let failed = loop {
+            let event = events.recv().await.unwrap();
+            if event.event_type == EventType::StepFailed {
+                break event;
+            }
+        };
+        let EventPayload::StepFailed(payload) = failed.payload else {
+            panic!("expected StepFailed payload");
+        };
+        assert_eq!(payload.error, "TIMEOUT: step did not finish within 50ms");
+        assert!(!scheduler.owns_task(&task_id, Some("worker-1")).await);
+        assert!(scheduler
+            .persistence
+            .list_step_deadlines()
+            .await
+            .unwrap()
+            .is_empty());
+
+        // 退避结束后重新分发，第二次超时后尝试次数用尽
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let retried = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(retried.len(), 1);
+        assert_eq!(retried[0].task_id, first[0].task_id);
+        scheduler
+            .lifecycle()
+            .step_started("wf-1", "start", vec![])
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        scheduler.expire_step_deadlines().await.unwrap();
+
+        let workflow = scheduler.persistence.get_workflow("wf-1").await.unwrap();
+        let WorkflowState::Failed { error } = workflow.unwrap().state else {
+            panic!("expected the workflow to fail");
+        };
+        assert!(error.starts_with(STEP_TIMEOUT_ERROR), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_progress_heartbeat_pushes_the_deadline() {
+        let scheduler = timed_scheduler(running_store().await).await;
+        scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        let task_id = TaskId::new("wf-1", "start");
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        let progress = StepProgress {
+            percent: Some(50.0),
+            message: Some("halfway".to_string()),
+            details: None,
+        };
+        scheduler
+            .lifecycle()
+            .heartbeat(&task_id.to_string(), progress)
+            .await
+            .unwrap();
+        let persisted = scheduler.persistence.list_step_deadlines().await.unwrap();
+        assert_eq!(persisted.len(), 1);
+
+        // 距领取已超过 50ms，但距心跳尚未超过
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(scheduler.expire_step_deadlines().await.unwrap().is_empty());
+        assert!(scheduler.owns_task(&task_id, Some("worker-1")).await);
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(
+            scheduler.expire_step_deadlines().await.unwrap(),
+            vec![task_id]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_step_that_stops_heartbeating_times_out() {
+        let config = SchedulerConfig {
+            task_timeout: Duration::from_millis(300),
+            max_missed_heartbeats: 1,
+            ..SchedulerConfig::default()
+        };
+        let scheduler = scheduler_with(running_store().await, config, None).await;
+        let mut events = scheduler.broadcaster.subscribe();
+        scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        let task_id = TaskId::new("wf-1", "start");
+        assert_eq!(
+            scheduler.missed_heartbeat_window(),
+            Duration::from_millis(100)
+        );
+
+        // 从未上报进度的 step 只受租约约束
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        assert!(scheduler.expire_step_deadlines().await.unwrap().is_empty());
+
+        scheduler
+            .lifecycle()
+            .heartbeat(&task_id.to_string(), StepProgress::default())
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(scheduler.expire_step_deadlines().await.unwrap().is_empty());
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(
+            scheduler.expire_step_deadlines().await.unwrap(),
+            vec![task_id]
+        );
+
+        let failed = loop {
+            let event = events.recv().await.unwrap();
+            if event.event_type == EventType::StepFailed {
+                break event;
+            }
+        };
+        let EventPayload::StepFailed(payload) = failed.payload else {
+            panic!("expected StepFailed payload");
+        };
+        assert_eq!(payload.error, "TIMEOUT: no heartbeat within 100ms");
+    }
+
+    #[tokio::test]
+    async fn test_completed_step_clears_its_deadline() {
+        let scheduler = timed_scheduler(running_store().await).await;
+        let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        scheduler
+            .complete_task(&tasks[0].task_id, b"{}".to_vec())
+            .await
+            .unwrap();
+        assert!(scheduler
+            .persistence
+            .list_step_deadlines()
+            .await
+            .unwrap()
+            .is_empty());
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(scheduler.expire_step_deadlines().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_deadline_is_recovered_after_restart() {
+        let store = running_store().await;
+        let before = timed_scheduler(store.clone()).await;
+        assert_eq!(before.poll_tasks("worker-1", 10).await.unwrap().len(), 1);
+
+        // 重启后 worker 重新注册：截止之前不重复分发，到期后按超时重试
+        let after = timed_scheduler(store).await;
+        assert!(after.poll_tasks("worker-1", 10).await.unwrap().is_empty());
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(
+            after.expire_step_deadlines().await.unwrap(),
+            vec![TaskId::new("wf-1", "start")]
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let retried = after.poll_tasks("worker-1", 10).await.unwrap();
+        assert_eq!(retried.len(), 1);
+        assert_eq!(retried[0].step_name, "start");
+    }
+}
@@ -0,0 +1,112 @@
+//! 粘性路由
+//!
+//! 定义声明粘性路由（[`WorkflowDefinition::with_sticky_routing`]）的 workflow，
+//! 第一个领取其 step 的 worker 成为它的粘性 worker，后续 step 只分发给该 worker，
+//! 适合按 workflow 缓存数据的 worker。其他 worker 等待超过粘性超时后仍可领取，
+//! 领取者成为新的粘性 worker；worker 被移除时它的粘性分配随之清除。
+//!
+//! [`WorkflowDefinition::with_sticky_routing`]: crate::definition::WorkflowDefinition::with_sticky_routing
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// 默认粘性超时：粘性 worker 超过该时间没有领取就绪的 step，改由其他 worker 领取
+pub const DEFAULT_STICKY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// workflow 的粘性 worker
+#[derive(Debug, Clone)]
+struct StickyAssignment {
+    worker_id: String,
+    /// 其他 worker 开始等待该 workflow 的 step 的时间
+    waiting_since: Option<Instant>,
+}
+
+/// workflow 到粘性 worker 的分配
+#[derive(Debug, Default)]
+pub struct StickyRoutes {
+    assignments: Mutex<HashMap<String, StickyAssignment>>,
+}
+
+impl StickyRoutes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// workflow 的粘性 worker，尚未分配时返回 `None`
+    pub fn worker(&self, workflow_id: &str) -> Option<String> {
+        self.assignments
+            .lock()
+            .unwrap()
+            .get(workflow_id)
+            .map(|assignment| assignment.worker_id.clone())
+    }
+
+    /// worker 能否领取该 workflow 的 step，能领取时把 workflow 分配给它
+    ///
+    /// 尚未分配的 workflow 分配给第一个领取者；其他 worker 从第一次被拒绝起等待 `timeout`。
+    pub fn claim(&self, workflow_id: &str, worker_id: &str, timeout: Duration) -> bool {
+        let now = Instant::now();
+        let mut assignments = self.assignments.lock().unwrap();
+        let assignment = assignments
+            .entry(workflow_id.to_string())
+            .or_insert_with(|| StickyAssignment {
+                worker_id: worker_id.to_string(),
+                waiting_since: None,
+            });
+        if assignment.worker_id != worker_id {
+            let waiting_since = *assignment.waiting_since.get_or_insert(now);
+            if now.duration_since(waiting_since) < timeout {
+                return false;
+            }
+            tracing::info!(
+                workflow_id,
+                from = %assignment.worker_id,
+                to = worker_id,
+                "sticky worker reassigned"
+            );
+            assignment.worker_id = worker_id.to_string();
+        }
+        assignment.waiting_since = None;
+        true
+    }
+
+    /// 清除分配给该 worker 的全部 workflow
+    pub fn evict_worker(&self, worker_id: &str) {
+        self.assignments
+            .lock()
+            .unwrap()
+            .retain(|_, assignment| assignment.worker_id != worker_id);
+    }
+
+    /// 只保留满足条件的 workflow 的分配，用于清理已结束的 workflow
+    pub fn retain(&self, mut keep: impl FnMut(&str) -> bool) {
+        self.assignments
+            .lock()
+            .unwrap()
+            .retain(|workflow_id, _| keep(workflow_id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_claim_assigns_and_others_wait() {
+        let routes = StickyRoutes::new();
+        assert!(routes.claim("wf-1", "worker-1", Duration::from_secs(60)));
+        assert!(!routes.claim("wf-1", "worker-2", Duration::from_secs(60)));
+        assert!(routes.claim("wf-1", "worker-1", Duration::from_secs(60)));
+        assert_eq!(routes.worker("wf-1").as_deref(), Some("worker-1"));
+
+        // 超时后其他 worker 接管
+        assert!(routes.claim("wf-1", "worker-2", Duration::ZERO));
+        assert_eq!(routes.worker("wf-1").as_deref(), Some("worker-2"));
+
+        routes.evict_worker("worker-2");
+        assert!(routes.worker("wf-1").is_none());
+    }
+}
@@ -1,3 +1,6 @@
+use crate::payload::Payload;
+use crate::signal::Signal;
+
 /// Resource type enumeration
 #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum ResourceType {
@@ -6,6 +9,17 @@ pub enum ResourceType {
     Workflow = 2,
 }
 
+impl ResourceType {
+    /// Name used by the REST API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResourceType::Step => "STEP",
+            ResourceType::Activity => "ACTIVITY",
+            ResourceType::Workflow => "WORKFLOW",
+        }
+    }
+}
+
 /// Task metadata for activity retry configuration
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ResourceMetadata {
@@ -13,6 +27,9 @@ pub struct ResourceMetadata {
     pub timeout: Option<u64>,
     pub input_schema: Option<String>,
     pub output_schema: Option<String>,
+    /// 相同输入的结果可以复用，见 [`crate::step_cache`]
+    #[serde(default)]
+    pub cacheable: bool,
 }
 
 /// A resource offered by a service
@@ -23,7 +40,7 @@ pub struct ServiceResource {
     pub metadata: Option<ResourceMetadata>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Task {
     pub task_id: String,
     pub workflow_id: String,
@@ -31,18 +48,33 @@ pub struct Task {
     pub target_service: Option<String>,
     pub target_resource: Option<String>,
     pub resource_type: ResourceType,
-    pub input: Vec<u8>,
+    pub input: Payload,
     pub retry: Option<RetryPolicy>,
     pub workflow_type: String,
+    pub heartbeat_interval: u64, // 期望的心跳间隔（毫秒），0 表示不要求心跳
+    /// step 等待的 signal，未声明 `wait_for_signal` 时为 `None`
+    pub signal: Option<Signal>,
+    /// 定义中声明的执行超时（毫秒），未设置时使用资源声明的超时
+    #[serde(default)]
+    pub timeout: Option<u64>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct RetryPolicy {
     pub max_attempts: u32,
     pub initial_interval: u64,
     pub backoff_multiplier: f64,
 }
 
+impl RetryPolicy {
+    /// 第 `attempt` 次尝试失败后，重新分发前的等待时间
+    pub fn backoff(&self, attempt: u32) -> std::time::Duration {
+        let exponent = attempt.saturating_sub(1) as i32;
+        let millis = self.initial_interval as f64 * self.backoff_multiplier.powi(exponent);
+        std::time::Duration::from_millis(millis as u64)
+    }
+}
+
 impl Default for RetryPolicy {
     fn default() -> Self {
         RetryPolicy {
@@ -52,3 +84,129 @@ impl Default for RetryPolicy {
         }
     }
 }
+
+/// Task 标识
+///
+/// 编码格式为 `{workflow_id 字节长度}:{workflow_id}:{step_name}`，
+/// workflow_id 和 step_name 中可以包含 '-'、':' 或任意 Unicode 字符。
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TaskId {
+    pub workflow_id: String,
+    pub step_name: String,
+}
+
+impl TaskId {
+    pub fn new(workflow_id: impl Into<String>, step_name: impl Into<String>) -> Self {
+        TaskId {
+            workflow_id: workflow_id.into(),
+            step_name: step_name.into(),
+        }
+    }
+
+    /// 解析 task_id，无法解析时返回 None
+    ///
+    /// 兼容旧格式 `workflow_id-step_name`（按最后一个 '-' 切分）。
+    /// 旧格式在 step_name 含 '-' 时会解析错误，已废弃，将在后续版本移除。
+    pub fn parse(task_id: &str) -> Option<Self> {
+        Self::parse_structured(task_id).or_else(|| Self::parse_legacy(task_id))
+    }
+
+    fn parse_structured(task_id: &str) -> Option<Self> {
+        let (len, rest) = task_id.split_once(':')?;
+        if len.is_empty() || !len.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let len: usize = len.parse().ok()?;
+        let workflow_id = rest.get(..len)?;
+        let step_name = rest.get(len..)?.strip_prefix(':')?;
+        Some(TaskId::new(workflow_id, step_name))
+    }
+
+    fn parse_legacy(task_id: &str) -> Option<Self> {
+        let (workflow_id, step_name) = task_id.rsplit_once('-')?;
+        Some(TaskId::new(workflow_id, step_name))
+    }
+}
+
+impl std::fmt::Display for TaskId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.workflow_id.len(),
+            self.workflow_id,
+            self.step_name
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_id_roundtrip() {
+        let cases = [
+            ("abc", "fetch-data"),
+            ("550e8400-e29b-41d4-a716-446655440000", "start"),
+            ("wf-with-dashes", "step-with-dashes"),
+            ("工作流-1", "获取-数据"),
+            ("wf:colon", "step:colon"),
+        ];
+
+        for (workflow_id, step_name) in cases {
+            let id = TaskId::new(workflow_id, step_name);
+            assert_eq!(TaskId::parse(&id.to_string()), Some(id));
+        }
+    }
+
+    #[test]
+    fn test_task_id_legacy_format() {
+        assert_eq!(
+            TaskId::parse("550e8400-e29b-41d4-a716-446655440000-start"),
+            Some(TaskId::new("550e8400-e29b-41d4-a716-446655440000", "start"))
+        );
+        assert_eq!(TaskId::parse("nodash"), None);
+    }
+
+    #[test]
+    fn test_task_serde_roundtrip() {
+        let task = Task {
+            task_id: TaskId::new("wf-1", "charge").to_string(),
+            workflow_id: "wf-1".to_string(),
+            step_name: "charge".to_string(),
+            target_service: Some("payments".to_string()),
+            target_resource: Some("charge".to_string()),
+            resource_type: ResourceType::Activity,
+            input: Payload::from_bytes(vec![0xff, 0xfe]),
+            retry: Some(RetryPolicy {
+                max_attempts: 5,
+                initial_interval: 250,
+                backoff_multiplier: 1.5,
+            }),
+            workflow_type: "order".to_string(),
+            heartbeat_interval: 1000,
+            signal: None,
+            timeout: None,
+        };
+
+        let json = serde_json::to_string(&task).unwrap();
+        let decoded: Task = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.input, task.input);
+        assert_eq!(decoded.retry, task.retry);
+        assert_eq!(decoded.target_service, task.target_service);
+
+        let bytes = bincode::serialize(&task).unwrap();
+        let decoded: Task = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(decoded.task_id, task.task_id);
+        assert_eq!(decoded.input, task.input);
+        assert_eq!(decoded.retry, task.retry);
+    }
+
+    #[test]
+    fn test_task_id_invalid_structured() {
+        // 长度超出或落在字符中间时回退到旧格式
+        assert_eq!(TaskId::parse("99:abc:step"), None);
+        assert_eq!(TaskId::parse("1:工作流:step"), None);
+    }
+}
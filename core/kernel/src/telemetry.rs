@@ -0,0 +1,188 @@
+//! 日志追踪
+//!
+//! workflow 执行和分发出去的 task 都有对应的 tracing span，字段统一为
+//! `workflow_id`、`step_name`、`task_id` 和 `worker_id`，按 `workflow_id` 过滤 JSON 日志
+//! 即可按顺序看到 workflow 启动、task 分发和 step 完成。
+//!
+//! REST 和 gRPC 请求的 `x-request-id` header 作为关联 id 记录在请求 span 上，
+//! 请求没有携带时生成新的；REST 响应回写同一个 header。
+
+use axum::extract::Request;
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+use tracing::{Instrument, Span};
+
+use crate::task::TaskId;
+
+/// 携带关联 id 的 header
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// 关联 id 的最大长度，超出时改为生成新的 id
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// 请求携带的关联 id，缺失、过长或含有不可见字符时生成新的
+pub fn request_id(header: Option<&str>) -> String {
+    header
+        .map(str::trim)
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= MAX_REQUEST_ID_LEN
+                && id.chars().all(|c| c.is_ascii_graphic())
+        })
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
+/// workflow 执行的 span
+pub fn workflow_span(workflow_id: &str, workflow_type: &str) -> Span {
+    tracing::info_span!("workflow", workflow_id, workflow_type)
+}
+
+/// task 的 span，`worker_id` 为 `None` 时表示上报方未提供
+pub fn task_span(task_id: &TaskId, worker_id: Option<&str>) -> Span {
+    let span = tracing::info_span!(
+        "task",
+        task_id = %task_id,
+        workflow_id = tracing::field::Empty,
+        step_name = tracing::field::Empty,
+        worker_id,
+    );
+    record_task(&span, task_id);
+    span
+}
+
+/// 在 span 上记录 task 所属的 workflow 和 step
+pub fn record_task(span: &Span, task_id: &TaskId) {
+    span.record("workflow_id", task_id.workflow_id.as_str());
+    span.record("step_name", task_id.step_name.as_str());
+}
+
+/// REST 请求中间件：为请求创建带关联 id 的 span，并在响应中回写关联 id
+pub async fn trace_request(request: Request, next: Next) -> Response {
+    let header = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok());
+    let request_id = request_id(header);
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %request.method(),
+        path = %request.uri().path(),
+    );
+
+    let mut response = next.run(request).instrument(span).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// gRPC 请求的 span，用作 `tonic::transport::Server::trace_fn`
+pub fn grpc_request_span(request: &tonic::codegen::http::Request<()>) -> Span {
+    let header = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok());
+    tracing::info_span!(
+        "grpc_request",
+        request_id = %request_id(header),
+        path = %request.uri().path(),
+        principal = tracing::field::Empty,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    use crate::persistence::l0_memory::L0MemoryStore;
+    use crate::persistence::Persistence;
+    use crate::scheduler::Scheduler;
+    use crate::state_machine::Workflow;
+
+    /// 把日志写入共享缓冲
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_workflow_logs_follow_execution_order() {
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let store = L0MemoryStore::new();
+        let mut workflow = Workflow::new("wf-1".to_string(), "order".to_string(), vec![]);
+        workflow.state = workflow.state.try_start().unwrap();
+        store.save_workflow(&workflow).await.unwrap();
+        let scheduler = Scheduler::new(store);
+        scheduler.workflow_started(&workflow).await;
+        scheduler
+            .register_worker(
+                "worker-1".to_string(),
+                "shop".to_string(),
+                "default".to_string(),
+                vec!["order".to_string()],
+                vec![],
+            )
+            .await;
+        let tasks = scheduler.poll_tasks("worker-1", 10).await.unwrap();
+        scheduler
+            .lifecycle()
+            .complete_task(&tasks[0].task_id, Some("worker-1"), vec![])
+            .await
+            .unwrap();
+
+        let logs = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        let messages: Vec<&str> = logs
+            .lines()
+            .filter(|line| line.contains(r#"workflow_id="wf-1""#))
+            .filter_map(|line| {
+                [
+                    "workflow started",
+                    "task dispatched",
+                    "step completed",
+                    "workflow completed",
+                ]
+                .into_iter()
+                .find(|message| line.contains(message))
+            })
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "workflow started",
+                "task dispatched",
+                "step completed",
+                "workflow completed"
+            ]
+        );
+        assert!(logs.contains(r#"worker_id="worker-1""#));
+    }
+
+    #[test]
+    fn test_request_id_is_kept_or_generated() {
+        assert_eq!(request_id(Some("req-42")), "req-42");
+        for invalid in [None, Some(""), Some("has space"), Some(&*"x".repeat(200))] {
+            let generated = request_id(invalid);
+            assert!(uuid::Uuid::parse_str(&generated).is_ok());
+        }
+    }
+}
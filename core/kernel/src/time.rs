@@ -0,0 +1,129 @@
+//! 时间类型
+//!
+//! 内核统一使用 `chrono::DateTime<Utc>` 表示时间：workflow 状态、执行记录和事件都用它，
+//! JSON 中序列化为 RFC 3339 字符串。这里提供与 protobuf 时间戳之间的转换，
+//! 以及读取旧版执行记录时兼容 `{seconds, nanos}` 格式的反序列化。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer};
+use std::time::{Duration, SystemTime};
+
+/// 转换为 protobuf 时间戳
+pub fn to_proto(time: DateTime<Utc>) -> prost_types::Timestamp {
+    SystemTime::from(time).into()
+}
+
+/// 从 protobuf 时间戳转换，超出可表示范围时返回 `None`
+pub fn from_proto(timestamp: prost_types::Timestamp) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(timestamp.seconds, u32::try_from(timestamp.nanos).ok()?)
+}
+
+/// 从 `earlier` 到 `later` 经过的时长，`earlier` 更晚时返回 `None`
+pub fn duration_between(earlier: DateTime<Utc>, later: DateTime<Utc>) -> Option<Duration> {
+    (later - earlier).to_std().ok()
+}
+
+/// 持久化的时间：RFC 3339 字符串，或旧版执行记录的 `{seconds, nanos}`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredTime {
+    DateTime(DateTime<Utc>),
+    Unix { seconds: i64, nanos: i32 },
+}
+
+impl StoredTime {
+    fn into_datetime<E: serde::de::Error>(self) -> Result<DateTime<Utc>, E> {
+        match self {
+            StoredTime::DateTime(time) => Ok(time),
+            StoredTime::Unix { seconds, nanos } => {
+                from_proto(prost_types::Timestamp { seconds, nanos })
+                    .ok_or_else(|| E::custom(format!("timestamp out of range: {}", seconds)))
+            }
+        }
+    }
+}
+
+/// 用于 `#[serde(deserialize_with)]`，兼容旧版执行记录的 `{seconds, nanos}` 时间戳
+pub fn deserialize_compat<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    StoredTime::deserialize(deserializer)?.into_datetime()
+}
+
+/// [`deserialize_compat`] 的 `Option` 版本
+pub fn deserialize_compat_option<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<StoredTime>::deserialize(deserializer)?
+        .map(StoredTime::into_datetime)
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+
+    #[derive(Serialize, Deserialize)]
+    struct Record {
+        #[serde(deserialize_with = "deserialize_compat")]
+        at: DateTime<Utc>,
+        #[serde(default, deserialize_with = "deserialize_compat_option")]
+        done_at: Option<DateTime<Utc>>,
+    }
+
+    fn time() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-05-01T12:30:00.250Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_proto_conversions() {
+        let proto = to_proto(time());
+        assert_eq!((proto.seconds, proto.nanos), (1_714_566_600, 250_000_000));
+        assert_eq!(from_proto(proto), Some(time()));
+        assert_eq!(
+            from_proto(prost_types::Timestamp {
+                seconds: 0,
+                nanos: -1
+            }),
+            None
+        );
+
+        let later = time() + chrono::Duration::milliseconds(1_500);
+        assert_eq!(
+            duration_between(time(), later),
+            Some(Duration::from_millis(1_500))
+        );
+        assert_eq!(duration_between(later, time()), None);
+    }
+
+    #[test]
+    fn test_persisted_timestamps_keep_deserializing() {
+        // 已持久化的执行记录仍按 {seconds, nanos} 读取
+        let old: Record = serde_json::from_str(
+            r#"{"at":{"seconds":1714566600,"nanos":250000000},"done_at":null}"#,
+        )
+        .unwrap();
+        assert_eq!(old.at, time());
+        assert_eq!(old.done_at, None);
+
+        // 新记录写出 RFC 3339 字符串
+        let record = Record {
+            at: time(),
+            done_at: Some(time()),
+        };
+        let json = serde_json::to_string(&record).unwrap();
+        assert_eq!(
+            json,
+            r#"{"at":"2024-05-01T12:30:00.250Z","done_at":"2024-05-01T12:30:00.250Z"}"#
+        );
+        let read: Record = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.done_at, Some(time()));
+
+        assert!(serde_json::from_str::<Record>(r#"{"at":"yesterday"}"#).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+//! 持久化定时器
+//!
+//! 定义中的定时器 step 不分发给 worker：调度器在 step 就绪时持久化触发时间，
+//! 后台定时器任务在到期后把 step 标记为完成并推进 workflow。触发时间保存在持久化层，
+//! 服务器重启后由定时器任务继续处理；workflow 取消、失败或删除后其定时器随之作废。
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+
+use crate::persistence::{ListOptions, Persistence, StateKind};
+use crate::scheduler::Scheduler;
+
+/// 定时器任务在没有就绪通知时的检查间隔
+pub const TIMER_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 等待中的定时器 step
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Timer {
+    pub workflow_id: String,
+    pub step_name: String,
+    pub fire_at: DateTime<Utc>,
+}
+
+impl<P: Persistence> Scheduler<P> {
+    /// 处理定时器：为就绪的定时器 step 记录触发时间，完成已到期的定时器，返回完成的数量
+    ///
+    /// 不再运行的 workflow 的定时器直接删除。
+    pub async fn process_timers(&self) -> anyhow::Result<usize> {
+        let now = Utc::now();
+        let options = ListOptions {
+            state_filter: Some(StateKind::Running),
+            ..Default::default()
+        };
+        let running: HashMap<String, _> = self
+            .persistence
+            .list_workflows_paged(&options)
+            .await?
+            .into_iter()
+            .map(|workflow| (workflow.id.clone(), workflow))
+            .collect();
+
+        let mut pending = Vec::new();
+        for timer in self.persistence.list_timers().await? {
+            if !running.contains_key(&timer.workflow_id) {
+                self.persistence
+                    .delete_timer(&timer.workflow_id, &timer.step_name)
+                    .await?;
+                continue;
+            }
+            // 重启后追踪器中没有记录，重新登记以便 dashboard 显示倒计时
+            let tracked = self
+                .tracker
+                .get_execution(&timer.workflow_id)
+                .await
+                .is_some_and(|e| e.step_executions.contains_key(&timer.step_name));
+            if !tracked {
+                self.lifecycle().timer_started(&timer).await?;
+            }
+            pending.push(timer);
+        }
+
+        let started: HashSet<(String, String)> = pending
+            .iter()
+            .map(|timer| (timer.workflow_id.clone(), timer.step_name.clone()))
+            .collect();
+        for workflow in running.values() {
+            let Some(definition) = self.workflow_definition(workflow).await else {
+                continue;
+            };
+            for step in definition.ready_steps(workflow) {
+                let Some(delay) = step.timer else {
+                    continue;
+                };
+                if started.contains(&(workflow.id.clone(), step.name.clone())) {
+                    continue;
+                }
+                let timer = Timer {
+                    workflow_id: workflow.id.clone(),
+                    step_name: step.name.clone(),
+                    fire_at: now + chrono::Duration::from_std(delay)?,
+                };
+                self.persistence.save_timer(&timer).await?;
+                self.lifecycle().timer_started(&timer).await?;
+                pending.push(timer);
+            }
+        }
+
+        let mut fired = 0;
+        for timer in pending.iter().filter(|timer| timer.fire_at <= now) {
+            self.lifecycle()
+                .timer_fired(&timer.workflow_id, &timer.step_name)
+                .await?;
+            self.persistence
+                .delete_timer(&timer.workflow_id, &timer.step_name)
+                .await?;
+            fired += 1;
+        }
+        Ok(fired)
+    }
+
+    /// 取消 workflow 的全部定时器，返回取消的数量
+    pub async fn cancel_timers(&self, workflow_id: &str) -> anyhow::Result<usize> {
+        let mut cancelled = 0;
+        for timer in self.persistence.list_timers().await? {
+            if timer.workflow_id == workflow_id
+                && self
+                    .persistence
+                    .delete_timer(&timer.workflow_id, &timer.step_name)
+                    .await?
+            {
+                cancelled += 1;
+            }
+        }
+        Ok(cancelled)
+    }
+}
+
+/// 启动后台定时器任务
+///
+/// workflow 启动或 step 完成时立即检查，否则每 [`TIMER_CHECK_INTERVAL`] 检查一次。
+pub fn spawn_timer_task<P: Persistence + 'static>(scheduler: Arc<Scheduler<P>>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ready = scheduler.subscribe_tasks();
+        loop {
+            ready.borrow_and_update();
+            match scheduler.process_timers().await {
+                Ok(0) => {}
+                Ok(fired) => tracing::info!(fired, "timers fired"),
+                Err(e) => tracing::warn!("timer processing failed: {}", e),
+            }
+            tokio::select! {
+                _ = ready.changed() => {}
+                _ = scheduler.stopped() => break,
+                _ = tokio::time::sleep(TIMER_CHECK_INTERVAL) => {}
+            }
+        }
+    })
+}
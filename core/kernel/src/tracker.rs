@@ -1,7 +1,14 @@
+use crate::payload::Payload;
+use crate::persistence::Persistence;
+use crate::stats::LiveStats;
+use crate::time;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 
 /// Step 执行状态
@@ -14,24 +21,46 @@ pub enum StepExecutionStatus {
     Cancelled,                // 取消
 }
 
-/// Unix 时间戳（秒）
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
-pub struct Timestamp {
-    pub seconds: i64,
-    pub nanos: i32,
-}
-
 /// 单个 Step 的执行记录
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StepExecution {
     pub step_name: String,
     pub status: StepExecutionStatus,
-    pub started_at: Option<Timestamp>,
-    pub completed_at: Option<Timestamp>,
-    pub input: Vec<u8>,
-    pub output: Option<Vec<u8>>,
+    #[serde(deserialize_with = "time::deserialize_compat_option")]
+    pub started_at: Option<DateTime<Utc>>,
+    #[serde(deserialize_with = "time::deserialize_compat_option")]
+    pub completed_at: Option<DateTime<Utc>>,
+    pub input: Payload,
+    pub output: Option<Payload>,
     pub attempt: u32,
     pub dependencies: Vec<String>, // 依赖的 step 名称
+    #[serde(default)]
+    pub progress: Option<StepProgress>, // 最近一次心跳上报的进度
+    #[serde(default, deserialize_with = "time::deserialize_compat_option")]
+    pub last_heartbeat_at: Option<DateTime<Utc>>, // 最近一次进度心跳的时间
+    #[serde(default, deserialize_with = "time::deserialize_compat_option")]
+    pub timer_fire_at: Option<DateTime<Utc>>, // 定时器 step 的触发时间
+    #[serde(default)]
+    pub rejections: Vec<StepRejection>, // worker 拒绝执行的记录，不计入尝试次数
+    #[serde(default)]
+    pub cache_hit: bool, // 结果取自 step 结果缓存，没有交给 worker 执行
+}
+
+/// worker 拒绝执行 step 的一次记录
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StepRejection {
+    pub worker_id: String,
+    pub reason: String,
+    #[serde(deserialize_with = "time::deserialize_compat")]
+    pub rejected_at: DateTime<Utc>,
+}
+
+/// Step 执行进度（由心跳上报）
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct StepProgress {
+    pub percent: Option<f64>,
+    pub message: Option<String>,
+    pub details: Option<Payload>, // 部分输出
 }
 
 /// Workflow 执行追踪信息
@@ -40,9 +69,15 @@ pub struct WorkflowExecution {
     pub workflow_id: String,
     pub workflow_type: String,
     pub step_executions: HashMap<String, StepExecution>,
-    pub started_at: Timestamp,
-    pub completed_at: Option<Timestamp>,
+    #[serde(deserialize_with = "time::deserialize_compat")]
+    pub started_at: DateTime<Utc>,
+    #[serde(deserialize_with = "time::deserialize_compat_option")]
+    pub completed_at: Option<DateTime<Utc>>,
     pub current_step: Option<String>,
+    #[serde(default)]
+    pub parent_workflow_id: Option<String>,
+    #[serde(default)]
+    pub child_workflow_ids: Vec<String>, // 由该 workflow 的 step 启动的子 workflow
 }
 
 impl fmt::Display for StepExecutionStatus {
@@ -57,136 +92,513 @@ impl fmt::Display for StepExecutionStatus {
     }
 }
 
+/// 追踪器错误
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrackerError {
+    /// workflow 没有执行记录（未开始追踪或已被淘汰）
+    WorkflowNotTracked(String),
+}
+
+impl fmt::Display for TrackerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrackerError::WorkflowNotTracked(workflow_id) => {
+                write!(f, "Workflow is not tracked: {}", workflow_id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TrackerError {}
+
+/// 默认在内存中保留的已结束 workflow 执行记录数量
+pub const DEFAULT_MAX_RETAINED_EXECUTIONS: usize = 10_000;
+
+/// 追踪器内存占用统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrackerStats {
+    /// 未结束的 workflow 数量
+    pub active: usize,
+    /// 仍保留在内存中的已结束 workflow 数量
+    pub retained: usize,
+    /// 启动以来被淘汰的已结束 workflow 数量
+    pub evicted: u64,
+}
+
+/// 执行记录及已结束 workflow 的结束顺序
+#[derive(Default)]
+struct Executions {
+    by_id: HashMap<String, WorkflowExecution>,
+    /// 按结束先后排列的已结束 workflow，淘汰时从队首开始；
+    /// 已被移除或重新开始的 id 在出队时跳过
+    terminal: VecDeque<String>,
+}
+
+impl Executions {
+    fn from_loaded(loaded: Vec<WorkflowExecution>) -> Self {
+        let mut finished: Vec<(DateTime<Utc>, String)> = loaded
+            .iter()
+            .filter_map(|e| e.completed_at.map(|t| (t, e.workflow_id.clone())))
+            .collect();
+        finished.sort();
+        Executions {
+            by_id: loaded
+                .into_iter()
+                .map(|e| (e.workflow_id.clone(), e))
+                .collect(),
+            terminal: finished.into_iter().map(|(_, id)| id).collect(),
+        }
+    }
+}
+
 /// Workflow 执行追踪器
 ///
 /// 追踪 workflow 的执行历史，包括每个 step 的状态变化。
 /// 用于 Dashboard 的实时可视化。
+///
+/// 默认只保存在内存中；通过 [`WorkflowTracker::with_store`] 创建时，
+/// 每次变化都会写入存储，重启后从存储恢复。
+///
+/// 已结束的执行记录超过 `max_retained` 条或超过 TTL 时，从最早结束的开始淘汰，
+/// 未结束的记录不会被淘汰。
 #[derive(Clone)]
 pub struct WorkflowTracker {
-    executions: Arc<RwLock<HashMap<String, WorkflowExecution>>>,
+    executions: Arc<RwLock<Executions>>,
+    store: Option<Arc<dyn Persistence>>,
+    max_retained: usize,
+    ttl: Option<Duration>,
+    evicted: Arc<AtomicU64>,
+    /// 随状态变化增量更新的概览统计
+    live: LiveStats,
 }
 
 impl WorkflowTracker {
     /// 创建新的追踪器
     pub fn new() -> Self {
+        Self::from_executions(Executions::default(), None)
+    }
+
+    /// 创建写入 `store` 的追踪器，并恢复其中已保存的执行记录
+    pub async fn with_store(store: Arc<dyn Persistence>) -> anyhow::Result<Self> {
+        let loaded = store.load_executions().await?;
+        Ok(Self::from_executions(
+            Executions::from_loaded(loaded),
+            Some(store),
+        ))
+    }
+
+    fn from_executions(executions: Executions, store: Option<Arc<dyn Persistence>>) -> Self {
         Self {
-            executions: Arc::new(RwLock::new(HashMap::new())),
+            executions: Arc::new(RwLock::new(executions)),
+            store,
+            max_retained: DEFAULT_MAX_RETAINED_EXECUTIONS,
+            ttl: None,
+            evicted: Arc::new(AtomicU64::new(0)),
+            live: LiveStats::new(),
+        }
+    }
+
+    /// 设置内存中保留的已结束执行记录上限
+    pub fn with_max_retained_executions(mut self, max_retained: usize) -> Self {
+        self.max_retained = max_retained;
+        self
+    }
+
+    /// 设置已结束执行记录的保留时长，`None` 表示只按数量淘汰
+    pub fn with_execution_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// 增量维护的概览统计
+    pub fn live_stats(&self) -> &LiveStats {
+        &self.live
+    }
+
+    /// 把执行记录写入存储，失败只记录日志，不影响调度
+    ///
+    /// 调用方持有 `executions` 写锁，保证同一 workflow 的记录按变化顺序写入。
+    async fn persist(&self, execution: Option<&WorkflowExecution>) {
+        let (Some(store), Some(execution)) = (&self.store, execution) else {
+            return;
+        };
+        if let Err(e) = store.save_execution(execution).await {
+            tracing::warn!(
+                workflow_id = %execution.workflow_id,
+                "failed to persist workflow execution: {}",
+                e
+            );
+        }
+    }
+
+    /// 淘汰超出数量上限或 TTL 的已结束执行记录
+    ///
+    /// 只从内存中移除，存储中的记录随 workflow 一起删除。
+    fn evict(&self, executions: &mut Executions) {
+        let expire_before = self
+            .ttl
+            .and_then(|ttl| chrono::Duration::from_std(ttl).ok())
+            .map(|ttl| Utc::now() - ttl);
+        while let Some(id) = executions.terminal.front() {
+            let completed_at = match executions.by_id.get(id) {
+                Some(execution) => execution.completed_at,
+                None => None,
+            };
+            let Some(completed_at) = completed_at else {
+                // 已被移除或重新开始
+                executions.terminal.pop_front();
+                continue;
+            };
+            let over_capacity = executions.terminal.len() > self.max_retained;
+            let expired = expire_before.is_some_and(|before| completed_at < before);
+            if !over_capacity && !expired {
+                break;
+            }
+            let id = executions.terminal.pop_front().expect("front exists");
+            executions.by_id.remove(&id);
+            self.evicted.fetch_add(1, Ordering::Relaxed);
         }
     }
 
+    /// 标记 workflow 结束，返回它之前是否仍在运行（没有执行记录时按仍在运行处理）
+    fn finish(executions: &mut Executions, workflow_id: &str) -> bool {
+        let Some(execution) = executions.by_id.get_mut(workflow_id) else {
+            return true;
+        };
+        let was_active = execution.completed_at.is_none();
+        execution.completed_at = Some(Utc::now());
+        execution.current_step = None;
+        if was_active {
+            executions.terminal.push_back(workflow_id.to_string());
+        }
+        was_active
+    }
+
     /// 开始追踪一个 workflow
     pub async fn start_workflow(&self, workflow_id: String, workflow_type: String) {
         let mut executions = self.executions.write().await;
-        let now = std::time::SystemTime::now();
-        let seconds = now.duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
-
-        executions.insert(
-            workflow_id.clone(),
-            WorkflowExecution {
-                workflow_id,
-                workflow_type,
-                step_executions: HashMap::new(),
-                started_at: Timestamp { seconds, nanos: 0 },
-                completed_at: None,
-                current_step: None,
-            },
-        );
+        let execution = WorkflowExecution {
+            workflow_id: workflow_id.clone(),
+            workflow_type,
+            step_executions: HashMap::new(),
+            started_at: Utc::now(),
+            completed_at: None,
+            current_step: None,
+            parent_workflow_id: None,
+            child_workflow_ids: Vec::new(),
+        };
+        self.persist(Some(&execution)).await;
+        self.live.workflow_started(execution.started_at);
+        executions.by_id.insert(workflow_id, execution);
+        self.evict(&mut executions);
     }
 
-    /// 记录 step 开始执行
+    /// 写入完整的执行记录（如从导出文件导入），替换同一 workflow 已有的记录
+    pub async fn restore_execution(&self, execution: WorkflowExecution) {
+        let mut executions = self.executions.write().await;
+        self.persist(Some(&execution)).await;
+        if execution.completed_at.is_some() {
+            executions.terminal.push_back(execution.workflow_id.clone());
+        }
+        executions
+            .by_id
+            .insert(execution.workflow_id.clone(), execution);
+        self.evict(&mut executions);
+    }
+
+    /// 记录 step 开始执行，workflow 没有执行记录时返回错误
     pub async fn step_started(
         &self,
         workflow_id: &str,
         step_name: &str,
-        input: Vec<u8>,
+        input: impl Into<Payload>,
         dependencies: Vec<String>,
-    ) -> StepExecution {
+    ) -> Result<StepExecution, TrackerError> {
         let mut executions = self.executions.write().await;
-        let execution = executions.get_mut(workflow_id).expect("Workflow not found");
-
-        let now = std::time::SystemTime::now();
-        let seconds = now.duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
+        let execution = executions
+            .by_id
+            .get_mut(workflow_id)
+            .ok_or_else(|| TrackerError::WorkflowNotTracked(workflow_id.to_string()))?;
+
+        // 重新分发或重试的 step 延续上一次失败后的尝试次数
+        let attempt = match execution.step_executions.get(step_name) {
+            Some(previous)
+                if matches!(
+                    previous.status,
+                    StepExecutionStatus::Failed { .. } | StepExecutionStatus::Pending
+                ) =>
+            {
+                previous.attempt
+            }
+            _ => 1,
+        };
+        // 拒绝记录不随重新开始清空
+        let rejections = execution
+            .step_executions
+            .get(step_name)
+            .map(|previous| previous.rejections.clone())
+            .unwrap_or_default();
 
         let step_execution = StepExecution {
             step_name: step_name.to_string(),
             status: StepExecutionStatus::Running,
-            started_at: Some(Timestamp { seconds, nanos: 0 }),
+            started_at: Some(Utc::now()),
             completed_at: None,
-            input,
+            input: input.into(),
             output: None,
-            attempt: 1,
+            attempt,
             dependencies,
+            progress: None,
+            last_heartbeat_at: None,
+            timer_fire_at: None,
+            rejections,
+            cache_hit: false,
         };
 
         execution
             .step_executions
             .insert(step_name.to_string(), step_execution.clone());
         execution.current_step = Some(step_name.to_string());
+        self.persist(Some(execution)).await;
 
-        step_execution
+        Ok(step_execution)
     }
 
-    /// 记录 step 完成
-    pub async fn step_completed(&self, workflow_id: &str, step_name: &str, output: Vec<u8>) {
+    /// 记录定时器 step 的触发时间
+    pub async fn timer_scheduled(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+        fire_at: DateTime<Utc>,
+    ) {
         let mut executions = self.executions.write().await;
-        if let Some(execution) = executions.get_mut(workflow_id) {
-            if let Some(step) = execution.step_executions.get_mut(step_name) {
-                let now = std::time::SystemTime::now();
-                let seconds = now.duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
+        if let Some(step) = executions
+            .by_id
+            .get_mut(workflow_id)
+            .and_then(|execution| execution.step_executions.get_mut(step_name))
+        {
+            step.timer_fire_at = Some(fire_at);
+        }
+        self.persist(executions.by_id.get(workflow_id)).await;
+    }
+
+    /// 标记 step 的结果取自缓存
+    pub async fn step_cache_hit(&self, workflow_id: &str, step_name: &str) {
+        let mut executions = self.executions.write().await;
+        if let Some(step) = executions
+            .by_id
+            .get_mut(workflow_id)
+            .and_then(|execution| execution.step_executions.get_mut(step_name))
+        {
+            step.cache_hit = true;
+        }
+        self.persist(executions.by_id.get(workflow_id)).await;
+    }
 
+    /// 记录 step 完成，已完成的 step 保留第一次完成的输出
+    pub async fn step_completed(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+        output: impl Into<Payload>,
+    ) {
+        let mut executions = self.executions.write().await;
+        if let Some(execution) = executions.by_id.get_mut(workflow_id) {
+            if let Some(step) = execution
+                .step_executions
+                .get_mut(step_name)
+                .filter(|step| step.status != StepExecutionStatus::Completed)
+            {
+                let now = Utc::now();
+                if let Some(duration) = step
+                    .started_at
+                    .and_then(|at| time::duration_between(at, now))
+                {
+                    self.live.step_completed(duration);
+                }
                 step.status = StepExecutionStatus::Completed;
-                step.completed_at = Some(Timestamp { seconds, nanos: 0 });
-                step.output = Some(output);
+                step.completed_at = Some(now);
+                step.output = Some(output.into());
             }
             execution.current_step = None;
         }
+        self.persist(executions.by_id.get(workflow_id)).await;
+    }
+
+    /// 记录 step 进度和心跳时间
+    pub async fn step_progress(&self, workflow_id: &str, step_name: &str, progress: StepProgress) {
+        let mut executions = self.executions.write().await;
+        if let Some(execution) = executions.by_id.get_mut(workflow_id) {
+            if let Some(step) = execution.step_executions.get_mut(step_name) {
+                step.progress = Some(progress);
+                step.last_heartbeat_at = Some(Utc::now());
+            }
+        }
+        self.persist(executions.by_id.get(workflow_id)).await;
+    }
+
+    /// 记录 worker 拒绝执行 step
+    ///
+    /// 拒绝不计入尝试次数：执行中的 step 回到等待分发，尚无记录的 step 以等待分发状态加入。
+    /// 之前的拒绝记录在 step 重新开始后保留。
+    pub async fn step_rejected(
+        &self,
+        workflow_id: &str,
+        step_name: &str,
+        worker_id: &str,
+        reason: &str,
+    ) {
+        let mut executions = self.executions.write().await;
+        let Some(execution) = executions.by_id.get_mut(workflow_id) else {
+            return;
+        };
+        let step = execution
+            .step_executions
+            .entry(step_name.to_string())
+            .or_insert_with(|| StepExecution {
+                step_name: step_name.to_string(),
+                status: StepExecutionStatus::Pending,
+                started_at: None,
+                completed_at: None,
+                input: Payload::default(),
+                output: None,
+                attempt: 1,
+                dependencies: Vec::new(),
+                progress: None,
+                last_heartbeat_at: None,
+                timer_fire_at: None,
+                rejections: Vec::new(),
+                cache_hit: false,
+            });
+        if step.status == StepExecutionStatus::Running {
+            step.status = StepExecutionStatus::Pending;
+            step.started_at = None;
+            step.progress = None;
+            step.last_heartbeat_at = None;
+        }
+        step.rejections.push(StepRejection {
+            worker_id: worker_id.to_string(),
+            reason: reason.to_string(),
+            rejected_at: Utc::now(),
+        });
+        self.persist(executions.by_id.get(workflow_id)).await;
     }
 
     /// 记录 step 失败
     pub async fn step_failed(&self, workflow_id: &str, step_name: &str, error: String) {
         let mut executions = self.executions.write().await;
-        if let Some(execution) = executions.get_mut(workflow_id) {
+        if let Some(execution) = executions.by_id.get_mut(workflow_id) {
             if let Some(step) = execution.step_executions.get_mut(step_name) {
-                let now = std::time::SystemTime::now();
-                let seconds = now.duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
-
                 step.status = StepExecutionStatus::Failed {
                     error: error.clone(),
                 };
-                step.completed_at = Some(Timestamp { seconds, nanos: 0 });
+                step.completed_at = Some(Utc::now());
                 step.attempt += 1;
             }
             execution.current_step = Some(step_name.to_string());
         }
+        self.persist(executions.by_id.get(workflow_id)).await;
+    }
+
+    /// 记录失败的 workflow 重新开始
+    ///
+    /// workflow 重新计为未结束，失败 step 的记录重置为等待分发并保留尝试次数。
+    /// 返回被重置的 step；workflow 没有执行记录（例如已被淘汰）时重新开始追踪，返回空列表。
+    pub async fn workflow_retried(&self, workflow_id: &str, workflow_type: &str) -> Vec<String> {
+        self.live.workflow_retried();
+        let mut executions = self.executions.write().await;
+        let execution = executions
+            .by_id
+            .entry(workflow_id.to_string())
+            .or_insert_with(|| WorkflowExecution {
+                workflow_id: workflow_id.to_string(),
+                workflow_type: workflow_type.to_string(),
+                step_executions: HashMap::new(),
+                started_at: Utc::now(),
+                completed_at: None,
+                current_step: None,
+                parent_workflow_id: None,
+                child_workflow_ids: Vec::new(),
+            });
+        execution.completed_at = None;
+        let mut retried = Vec::new();
+        for step in execution.step_executions.values_mut() {
+            if matches!(step.status, StepExecutionStatus::Failed { .. }) {
+                step.status = StepExecutionStatus::Pending;
+                step.started_at = None;
+                step.completed_at = None;
+                step.output = None;
+                step.progress = None;
+                retried.push(step.step_name.clone());
+            }
+        }
+        retried.sort();
+        self.persist(Some(execution)).await;
+        retried
+    }
+
+    /// 记录父 workflow 启动了子 workflow
+    pub async fn child_started(&self, parent_id: &str, child_id: &str) {
+        let mut executions = self.executions.write().await;
+        if let Some(child) = executions.by_id.get_mut(child_id) {
+            child.parent_workflow_id = Some(parent_id.to_string());
+        }
+        if let Some(parent) = executions.by_id.get_mut(parent_id) {
+            parent.child_workflow_ids.push(child_id.to_string());
+        }
+        self.persist(executions.by_id.get(child_id)).await;
+        self.persist(executions.by_id.get(parent_id)).await;
     }
 
     /// 记录 workflow 完成
     pub async fn workflow_completed(&self, workflow_id: &str) {
         let mut executions = self.executions.write().await;
-        if let Some(execution) = executions.get_mut(workflow_id) {
-            let now = std::time::SystemTime::now();
-            let seconds = now.duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
-
-            execution.completed_at = Some(Timestamp { seconds, nanos: 0 });
-            execution.current_step = None;
+        if Self::finish(&mut executions, workflow_id) {
+            self.live.workflow_completed();
         }
+        self.persist(executions.by_id.get(workflow_id)).await;
+        self.evict(&mut executions);
     }
 
     /// 记录 workflow 失败
     pub async fn workflow_failed(&self, workflow_id: &str) {
         let mut executions = self.executions.write().await;
-        if let Some(execution) = executions.get_mut(workflow_id) {
-            let now = std::time::SystemTime::now();
-            let seconds = now.duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
+        if Self::finish(&mut executions, workflow_id) {
+            self.live.workflow_failed();
+        }
+        self.persist(executions.by_id.get(workflow_id)).await;
+        self.evict(&mut executions);
+    }
 
-            execution.completed_at = Some(Timestamp { seconds, nanos: 0 });
-            execution.current_step = None;
+    /// 记录 workflow 被取消，仍在执行的 step 标记为取消
+    pub async fn workflow_cancelled(&self, workflow_id: &str) {
+        self.stop_workflow(workflow_id).await;
+    }
+
+    /// 记录 workflow 被强制结束，仍在执行的 step 同样标记为取消
+    pub async fn workflow_terminated(&self, workflow_id: &str) {
+        self.stop_workflow(workflow_id).await;
+    }
+
+    async fn stop_workflow(&self, workflow_id: &str) {
+        let mut executions = self.executions.write().await;
+        if let Some(execution) = executions.by_id.get_mut(workflow_id) {
+            for step in execution.step_executions.values_mut() {
+                if step.status == StepExecutionStatus::Running {
+                    step.status = StepExecutionStatus::Cancelled;
+                }
+            }
+        }
+        if Self::finish(&mut executions, workflow_id) {
+            self.live.workflow_stopped();
         }
+        self.persist(executions.by_id.get(workflow_id)).await;
+        self.evict(&mut executions);
     }
 
     /// 获取 workflow 执行信息
     pub async fn get_execution(&self, workflow_id: &str) -> Option<WorkflowExecution> {
-        self.executions.read().await.get(workflow_id).cloned()
+        self.executions.read().await.by_id.get(workflow_id).cloned()
     }
 
     /// 获取所有正在执行的 workflow
@@ -194,6 +606,7 @@ impl WorkflowTracker {
         self.executions
             .read()
             .await
+            .by_id
             .values()
             .filter(|e| e.completed_at.is_none())
             .cloned()
@@ -202,19 +615,46 @@ impl WorkflowTracker {
 
     /// 获取所有执行信息
     pub async fn get_all_executions(&self) -> Vec<WorkflowExecution> {
-        self.executions.read().await.values().cloned().collect()
+        self.executions
+            .read()
+            .await
+            .by_id
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// 内存占用统计，统计前先淘汰已过期的记录
+    pub async fn stats(&self) -> TrackerStats {
+        let mut executions = self.executions.write().await;
+        self.evict(&mut executions);
+        let active = executions
+            .by_id
+            .values()
+            .filter(|e| e.completed_at.is_none())
+            .count();
+        TrackerStats {
+            active,
+            retained: executions.by_id.len() - active,
+            evicted: self.evicted.load(Ordering::Relaxed),
+        }
     }
 
     /// 清除所有执行记录
     pub async fn clear(&self) {
         let mut executions = self.executions.write().await;
-        executions.clear();
+        executions.by_id.clear();
+        executions.terminal.clear();
     }
 
     /// 移除指定 workflow 的记录
     pub async fn remove(&self, workflow_id: &str) {
         let mut executions = self.executions.write().await;
-        executions.remove(workflow_id);
+        if let Some(execution) = executions.by_id.remove(workflow_id) {
+            if execution.completed_at.is_none() {
+                self.live.workflow_stopped();
+            }
+        }
     }
 }
 
@@ -240,7 +680,8 @@ mod tests {
         // 开始 step
         let step = tracker
             .step_started("wf-1", "step-1", vec![1, 2, 3], vec![])
-            .await;
+            .await
+            .unwrap();
 
         assert_eq!(step.status, StepExecutionStatus::Running);
         assert!(step.started_at.is_some());
@@ -257,7 +698,8 @@ mod tests {
         // 开始另一个 step
         tracker
             .step_started("wf-1", "step-2", vec![], vec!["step-1".to_string()])
-            .await;
+            .await
+            .unwrap();
 
         // 模拟失败
         tracker
@@ -293,4 +735,186 @@ mod tests {
         assert_eq!(active.len(), 1);
         assert_eq!(active[0].workflow_id, "wf-2");
     }
+
+    #[tokio::test]
+    async fn test_store_backed_tracker_survives_restart() {
+        let store: Arc<dyn Persistence> =
+            Arc::new(crate::persistence::l0_memory::L0MemoryStore::new());
+        let tracker = WorkflowTracker::with_store(store.clone()).await.unwrap();
+        tracker
+            .start_workflow("wf-1".to_string(), "order".to_string())
+            .await;
+        tracker
+            .step_started("wf-1", "charge", vec![1], vec![])
+            .await
+            .unwrap();
+        tracker.step_completed("wf-1", "charge", vec![2]).await;
+        tracker.workflow_completed("wf-1").await;
+        drop(tracker);
+
+        let restored = WorkflowTracker::with_store(store).await.unwrap();
+        let execution = restored.get_execution("wf-1").await.unwrap();
+        assert_eq!(execution.workflow_type, "order");
+        assert!(execution.completed_at.is_some());
+        let step = &execution.step_executions["charge"];
+        assert_eq!(step.status, StepExecutionStatus::Completed);
+        assert_eq!(step.output, Some(vec![2].into()));
+    }
+
+    #[tokio::test]
+    async fn test_finished_executions_are_evicted_oldest_first() {
+        let tracker = WorkflowTracker::new().with_max_retained_executions(1_000);
+        for i in 0..20_000 {
+            let id = format!("wf-{}", i);
+            tracker
+                .start_workflow(id.clone(), "order".to_string())
+                .await;
+            // 每 100 个 workflow 留一个一直运行
+            if i % 100 != 0 {
+                tracker.workflow_completed(&id).await;
+            }
+        }
+
+        let stats = tracker.stats().await;
+        assert_eq!(stats.active, 200);
+        assert_eq!(stats.retained, 1_000);
+        assert_eq!(stats.evicted, 20_000 - 200 - 1_000);
+        assert_eq!(tracker.get_all_executions().await.len(), 1_200);
+        assert!(tracker.get_execution("wf-0").await.is_some());
+        assert!(tracker.get_execution("wf-1").await.is_none());
+        assert!(tracker.get_execution("wf-19999").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_expired_executions_are_evicted() {
+        let tracker = WorkflowTracker::new().with_execution_ttl(Some(Duration::from_secs(60)));
+        for id in ["old", "recent", "running"] {
+            tracker
+                .start_workflow(id.to_string(), "order".to_string())
+                .await;
+        }
+        tracker.workflow_failed("old").await;
+        tracker.workflow_completed("recent").await;
+        tracker
+            .executions
+            .write()
+            .await
+            .by_id
+            .get_mut("old")
+            .unwrap()
+            .completed_at = Some(Utc::now() - chrono::Duration::seconds(120));
+
+        let stats = tracker.stats().await;
+        assert_eq!(
+            stats,
+            TrackerStats {
+                active: 1,
+                retained: 1,
+                evicted: 1
+            }
+        );
+        assert!(tracker.get_execution("old").await.is_none());
+    }
+
+    #[test]
+    fn test_execution_persisted_with_unix_timestamps_keeps_loading() {
+        // 旧版执行记录的时间戳是 {seconds, nanos}
+        let persisted = r#"{
+            "workflow_id": "wf-1",
+            "workflow_type": "order",
+            "step_executions": {
+                "charge": {
+                    "step_name": "charge",
+                    "status": "Completed",
+                    "started_at": {"seconds": 1714566600, "nanos": 0},
+                    "completed_at": {"seconds": 1714566601, "nanos": 500000000},
+                    "input": [],
+                    "output": null,
+                    "attempt": 0,
+                    "dependencies": [],
+                    "rejections": [
+                        {"worker_id": "worker-1", "reason": "busy",
+                         "rejected_at": {"seconds": 1714566599, "nanos": 0}}
+                    ]
+                }
+            },
+            "started_at": {"seconds": 1714566600, "nanos": 0},
+            "completed_at": null,
+            "current_step": null
+        }"#;
+        let execution: WorkflowExecution = serde_json::from_str(persisted).unwrap();
+        let started = DateTime::from_timestamp(1_714_566_600, 0).unwrap();
+        assert_eq!(execution.started_at, started);
+        let step = &execution.step_executions["charge"];
+        assert_eq!(step.started_at, Some(started));
+        assert_eq!(
+            step.completed_at
+                .and_then(|at| time::duration_between(started, at)),
+            Some(Duration::from_millis(1_500))
+        );
+        assert_eq!(step.last_heartbeat_at, None);
+        assert_eq!(
+            step.rejections[0].rejected_at,
+            started - chrono::Duration::seconds(1)
+        );
+
+        // 重新保存后写出 RFC 3339 字符串，仍能读回
+        let json = serde_json::to_value(&execution).unwrap();
+        assert_eq!(json["started_at"], "2024-05-01T12:30:00Z");
+        let reloaded: WorkflowExecution = serde_json::from_value(json).unwrap();
+        assert_eq!(reloaded.started_at, started);
+    }
+
+    #[tokio::test]
+    async fn test_live_stats_follow_transitions() {
+        let tracker = WorkflowTracker::new();
+        for id in ["done", "broken", "stopped"] {
+            tracker
+                .start_workflow(id.to_string(), "order".to_string())
+                .await;
+        }
+        tracker
+            .step_started("done", "charge", vec![], vec![])
+            .await
+            .unwrap();
+        tracker.step_completed("done", "charge", vec![]).await;
+        // 重复上报不会重复计数
+        tracker.step_completed("done", "charge", vec![]).await;
+        tracker.workflow_completed("done").await;
+        tracker.workflow_completed("done").await;
+        tracker.workflow_failed("broken").await;
+        tracker.workflow_cancelled("stopped").await;
+
+        let snapshot = tracker.live_stats().snapshot(Utc::now());
+        assert_eq!(
+            snapshot.workflows,
+            crate::stats::WorkflowCounts {
+                active: 0,
+                completed: 1,
+                failed: 1,
+            }
+        );
+        assert_eq!(snapshot.started_last_1m, 3);
+        assert!(snapshot.step_p50_ms.is_some());
+
+        assert!(tracker.workflow_retried("broken", "order").await.is_empty());
+        let snapshot = tracker.live_stats().snapshot(Utc::now());
+        assert_eq!(
+            (snapshot.workflows.active, snapshot.workflows.failed),
+            (1, 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_step_started_on_untracked_workflow_is_an_error() {
+        let tracker = WorkflowTracker::new();
+        let result = tracker
+            .step_started("missing", "charge", vec![], vec![])
+            .await;
+        assert_eq!(
+            result.unwrap_err(),
+            TrackerError::WorkflowNotTracked("missing".to_string())
+        );
+        assert!(tracker.get_all_executions().await.is_empty());
+    }
 }
@@ -1,10 +1,52 @@
+//! 进程内 worker
+//!
+//! 直接通过 [`Scheduler`] 领取和上报 task，不经过 gRPC，适合嵌入式部署和端到端测试。
+//! step 的开始、完成和失败经 [`StepLifecycle`](crate::step_lifecycle::StepLifecycle)
+//! 记录到追踪器并广播事件；失败后的重试和退避由调度器按 task 的重试策略处理。
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
 use std::time::Duration;
 
+use futures::future::BoxFuture;
+use tokio::sync::broadcast;
+use tokio::task::JoinSet;
+
+use crate::cancellation::TaskCancellation;
+use crate::payload::Payload;
+use crate::persistence::Persistence;
+use crate::scheduler::Scheduler;
+use crate::task::{Task, TaskId};
+
+/// 进程内 worker 注册时使用的服务名
+pub const IN_PROCESS_SERVICE: &str = "in-process";
+
+/// 进程内 worker 注册时使用的分组
+const IN_PROCESS_GROUP: &str = "default";
+
+/// step 处理函数：输入为 step 的输入，返回 step 的输出，返回错误时 step 失败
+type StepHandler =
+    Arc<dyn Fn(Payload) -> BoxFuture<'static, anyhow::Result<Payload>> + Send + Sync>;
+
 pub struct Worker {
     pub id: String,
     pub workflow_types: Vec<String>,
     pub poll_interval: Duration,
+    /// 单次领取的 task 数上限，同时也是同时执行的 task 数上限
     pub max_tasks_per_poll: usize,
+    /// 单个 step 的执行时限，超时的 step 按失败上报，`None` 表示不限
+    pub step_timeout: Option<Duration>,
+    handlers: HashMap<String, StepHandler>,
+}
+
+/// 一次 step 执行的结果
+enum Outcome {
+    Completed(Payload),
+    Failed(String),
+    /// task 已被撤销（workflow 被取消或终止），不再上报
+    Revoked,
 }
 
 impl Worker {
@@ -14,6 +56,8 @@ impl Worker {
             workflow_types,
             poll_interval: Duration::from_millis(100),
             max_tasks_per_poll: 10,
+            step_timeout: None,
+            handlers: HashMap::new(),
         }
     }
 
@@ -26,12 +70,193 @@ impl Worker {
         self.max_tasks_per_poll = max;
         self
     }
+
+    pub fn with_step_timeout(mut self, timeout: Duration) -> Self {
+        self.step_timeout = Some(timeout);
+        self
+    }
+
+    /// 注册 step 的处理函数，同名 step 的处理函数被替换
+    ///
+    /// 领取到没有处理函数的 step 时按失败上报。
+    pub fn register_step<F, Fut>(mut self, step_name: impl Into<String>, handler: F) -> Self
+    where
+        F: Fn(Payload) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = anyhow::Result<Payload>> + Send + 'static,
+    {
+        let handler: StepHandler = Arc::new(move |input| Box::pin(handler(input)));
+        self.handlers.insert(step_name.into(), handler);
+        self
+    }
+
+    /// 持续领取并执行 task，直到调度器停止
+    pub async fn run<P: Persistence + 'static>(self, scheduler: Arc<Scheduler<P>>) {
+        self.run_until(scheduler, std::future::pending()).await
+    }
+
+    /// 持续领取并执行 task，直到 `shutdown` 完成或调度器停止
+    ///
+    /// 停止后不再领取新 task，等待进行中的 task 执行完毕并上报结果后返回。
+    pub async fn run_until<P: Persistence + 'static>(
+        self,
+        scheduler: Arc<Scheduler<P>>,
+        shutdown: impl Future<Output = ()>,
+    ) {
+        let worker = Arc::new(self);
+        worker.register(&scheduler).await;
+
+        let mut shutdown = pin!(shutdown);
+        let mut running = JoinSet::new();
+        let mut ready = scheduler.subscribe_tasks();
+        loop {
+            ready.borrow_and_update();
+            while running.try_join_next().is_some() {}
+
+            // 因心跳超时被移除后重新注册
+            if !scheduler.touch_worker(&worker.id).await {
+                worker.register(&scheduler).await;
+            }
+            let capacity = worker.max_tasks_per_poll.saturating_sub(running.len());
+            if capacity > 0 {
+                match scheduler.poll_tasks(&worker.id, capacity).await {
+                    Ok(tasks) => {
+                        for task in tasks {
+                            running.spawn(worker.clone().execute(scheduler.clone(), task));
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!(worker_id = %worker.id, "task dispatch failed: {:#}", e)
+                    }
+                }
+            }
+
+            tokio::select! {
+                _ = scheduler.wait_for_tasks(&mut ready, worker.poll_interval) => {}
+                Some(_) = running.join_next(), if !running.is_empty() => {}
+                _ = &mut shutdown => break,
+                _ = scheduler.stopped() => break,
+            }
+        }
+
+        while running.join_next().await.is_some() {}
+        tracing::info!(worker_id = %worker.id, "in-process worker stopped");
+    }
+
+    async fn register<P: Persistence>(&self, scheduler: &Scheduler<P>) {
+        scheduler
+            .register_worker(
+                self.id.clone(),
+                IN_PROCESS_SERVICE.to_string(),
+                IN_PROCESS_GROUP.to_string(),
+                self.workflow_types.clone(),
+                vec![],
+            )
+            .await;
+    }
+
+    /// 执行一个 task 并上报结果
+    async fn execute<P: Persistence>(self: Arc<Self>, scheduler: Arc<Scheduler<P>>, task: Task) {
+        let task_id = TaskId::new(&task.workflow_id, &task.step_name);
+        // 在记录开始之前订阅，不错过执行期间的撤销
+        let cancellations = scheduler.subscribe_cancellations();
+        let lifecycle = scheduler.lifecycle();
+        if let Err(e) = lifecycle
+            .step_started(&task.workflow_id, &task.step_name, task.input.data.clone())
+            .await
+        {
+            tracing::warn!(%task_id, "failed to record step start: {}", e);
+        }
+
+        let outcome = match self.handlers.get(&task.step_name) {
+            Some(handler) => {
+                self.invoke(
+                    &scheduler,
+                    &task_id,
+                    cancellations,
+                    handler(task.input.clone()),
+                )
+                .await
+            }
+            None => Outcome::Failed(format!("no handler registered for step {}", task.step_name)),
+        };
+        let reported = match outcome {
+            Outcome::Completed(output) => {
+                lifecycle
+                    .complete_task(&task.task_id, Some(&self.id), output.data)
+                    .await
+            }
+            Outcome::Failed(error) => {
+                tracing::warn!(%task_id, %error, "step failed");
+                lifecycle
+                    .fail_task(&task.task_id, Some(&self.id), error)
+                    .await
+            }
+            Outcome::Revoked => return,
+        };
+        if let Err(e) = reported {
+            tracing::warn!(%task_id, "failed to report step result: {}", e);
+        }
+    }
+
+    /// 运行处理函数，期间按心跳间隔续约，超时或 task 被撤销时放弃
+    async fn invoke<P: Persistence>(
+        &self,
+        scheduler: &Scheduler<P>,
+        task_id: &TaskId,
+        mut cancellations: broadcast::Receiver<TaskCancellation>,
+        handler: BoxFuture<'static, anyhow::Result<Payload>>,
+    ) -> Outcome {
+        let mut handler = pin!(handler);
+        let heartbeat = scheduler.heartbeat_interval();
+        let mut ticker =
+            tokio::time::interval_at(tokio::time::Instant::now() + heartbeat, heartbeat);
+        let timeout = self.step_timeout;
+        let mut deadline = pin!(async {
+            match timeout {
+                Some(timeout) => tokio::time::sleep(timeout).await,
+                None => std::future::pending().await,
+            }
+        });
+
+        loop {
+            tokio::select! {
+                result = &mut handler => {
+                    return match result {
+                        Ok(output) => Outcome::Completed(output),
+                        Err(e) => Outcome::Failed(format!("{:#}", e)),
+                    };
+                }
+                _ = &mut deadline => {
+                    return Outcome::Failed(format!(
+                        "step timed out after {}ms",
+                        timeout.unwrap_or_default().as_millis()
+                    ));
+                }
+                _ = ticker.tick() => {
+                    scheduler.extend_lease(task_id).await;
+                }
+                cancelled = cancellations.recv() => {
+                    if cancelled.is_ok_and(|c| c.task_id == *task_id && c.worker_id == self.id) {
+                        tracing::info!(%task_id, "step revoked");
+                        return Outcome::Revoked;
+                    }
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::definition::{StepDefinition, WorkflowDefinition};
+    use crate::persistence::l0_memory::L0MemoryStore;
+    use crate::scheduler::StartOptions;
+    use crate::state_machine::WorkflowState;
+    use crate::task::RetryPolicy;
+    use std::sync::atomic::{AtomicU32, Ordering};
     use std::time::Duration;
+    use tokio::sync::oneshot;
 
     #[test]
     fn test_worker_creation() {
@@ -52,4 +277,140 @@ mod tests {
         assert_eq!(worker.poll_interval, Duration::from_millis(500));
         assert_eq!(worker.max_tasks_per_poll, 5);
     }
+
+    /// 注册定义并启动一个 workflow，返回 workflow id
+    async fn start(scheduler: &Scheduler<L0MemoryStore>, steps: Vec<StepDefinition>) -> String {
+        scheduler
+            .register_definition(WorkflowDefinition::new("pipeline", steps))
+            .await
+            .unwrap();
+        scheduler
+            .start_workflow(
+                None,
+                "pipeline".to_string(),
+                b"{}".to_vec(),
+                StartOptions::default(),
+            )
+            .await
+            .unwrap()
+            .workflow
+            .id
+    }
+
+    /// 等待 workflow 结束，返回结束状态
+    async fn finished(scheduler: &Scheduler<L0MemoryStore>, workflow_id: &str) -> WorkflowState {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let workflow = scheduler
+                    .persistence
+                    .get_workflow(workflow_id)
+                    .await
+                    .unwrap()
+                    .unwrap();
+                if workflow.state.is_terminal() {
+                    return workflow.state;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("workflow did not finish")
+    }
+
+    #[tokio::test]
+    async fn test_runs_multi_step_workflow_end_to_end() {
+        let scheduler = Arc::new(Scheduler::new(L0MemoryStore::new()));
+        let workflow_id = start(
+            &scheduler,
+            vec![
+                StepDefinition::new("fetch"),
+                StepDefinition::new("store").depends_on(["fetch"]),
+            ],
+        )
+        .await;
+
+        let worker = Worker::new("worker-1".to_string(), vec!["pipeline".to_string()])
+            .register_step("fetch", |_input: Payload| async {
+                Ok(Payload::from_bytes(br#"{"items":3}"#.to_vec()))
+            })
+            .register_step("store", |input: Payload| async move { Ok(input) });
+        let (stop, stopped) = oneshot::channel::<()>();
+        let handle = tokio::spawn(worker.run_until(scheduler.clone(), async {
+            let _ = stopped.await;
+        }));
+
+        assert!(matches!(
+            finished(&scheduler, &workflow_id).await,
+            WorkflowState::Completed { .. }
+        ));
+        let execution = scheduler.tracker.get_execution(&workflow_id).await.unwrap();
+        assert_eq!(execution.step_executions.len(), 2);
+
+        stop.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), handle)
+            .await
+            .expect("worker did not stop")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failed_step_is_retried_by_policy() {
+        let scheduler = Arc::new(Scheduler::new(L0MemoryStore::new()));
+        let retry = RetryPolicy {
+            max_attempts: 3,
+            initial_interval: 10,
+            backoff_multiplier: 1.0,
+        };
+        let workflow_id = start(
+            &scheduler,
+            vec![StepDefinition::new("flaky").with_retry(retry)],
+        )
+        .await;
+
+        let attempts = Arc::new(AtomicU32::new(0));
+        let counter = attempts.clone();
+        let worker = Worker::new("worker-1".to_string(), vec!["pipeline".to_string()])
+            .with_poll_interval(Duration::from_millis(10))
+            .register_step("flaky", move |input: Payload| {
+                let attempt = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                async move {
+                    anyhow::ensure!(attempt > 1, "transient failure");
+                    Ok(input)
+                }
+            });
+        tokio::spawn(worker.run(scheduler.clone()));
+
+        assert!(matches!(
+            finished(&scheduler, &workflow_id).await,
+            WorkflowState::Completed { .. }
+        ));
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_step_timeout_fails_step() {
+        let scheduler = Arc::new(Scheduler::new(L0MemoryStore::new()));
+        let no_retry = RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        };
+        let workflow_id = start(
+            &scheduler,
+            vec![StepDefinition::new("slow").with_retry(no_retry)],
+        )
+        .await;
+
+        let worker = Worker::new("worker-1".to_string(), vec!["pipeline".to_string()])
+            .with_step_timeout(Duration::from_millis(20))
+            .register_step("slow", |_input: Payload| async {
+                std::future::pending::<()>().await;
+                unreachable!()
+            });
+        tokio::spawn(worker.run(scheduler.clone()));
+
+        match finished(&scheduler, &workflow_id).await {
+            WorkflowState::Failed { error } => assert!(error.contains("timed out"), "{}", error),
+            state => panic!("unexpected state: {:?}", state),
+        }
+    }
 }
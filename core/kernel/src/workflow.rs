@@ -1,5 +1,5 @@
 use crate::state_machine::{Workflow, WorkflowState};
-use crate::task::Task;
+use crate::task::{Task, TaskId};
 
 pub struct WorkflowExecutor {
     workflow: Workflow,
@@ -11,11 +11,7 @@ impl WorkflowExecutor {
     }
 
     pub fn start(&mut self) -> Result<(), String> {
-        let new_state = self
-            .workflow
-            .state
-            .start()
-            .ok_or("Cannot start workflow from current state")?;
+        let new_state = self.workflow.state.try_start().map_err(|e| e.to_string())?;
         self.workflow.state = new_state;
         Ok(())
     }
@@ -23,30 +19,31 @@ impl WorkflowExecutor {
     pub fn poll_task(&mut self) -> Option<Task> {
         match &self.workflow.state {
             WorkflowState::Running { current_step: None } => Some(Task {
-                task_id: format!("{}-start", self.workflow.id),
+                task_id: TaskId::new(&self.workflow.id, "start").to_string(),
                 workflow_id: self.workflow.id.clone(),
                 step_name: "start".to_string(),
                 target_service: None,
                 target_resource: None,
                 resource_type: crate::task::ResourceType::Step,
-                input: self.workflow.input.clone(),
+                input: self.workflow.input.clone().into(),
                 retry: None,
                 workflow_type: self.workflow.workflow_type.clone(),
+                heartbeat_interval: 0,
+                signal: None,
+                timeout: None,
             }),
             _ => None,
         }
     }
 
     pub fn complete_step(&mut self, step_name: &str, result: Vec<u8>) -> Result<(), String> {
-        self.workflow
-            .steps_completed
-            .insert(step_name.to_string(), result);
+        self.workflow.record_completion(step_name, result);
 
         let new_state = self
             .workflow
             .state
-            .step_completed()
-            .ok_or("Cannot complete step from current state")?;
+            .try_step_completed()
+            .map_err(|e| e.to_string())?;
         self.workflow.state = new_state;
 
         Ok(())
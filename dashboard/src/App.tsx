@@ -16,6 +16,7 @@ import {
 	TooltipTrigger,
 } from "@/components/ui/tooltip";
 import { fadeVariants } from "@/lib/motion";
+import { resolveWsUrl } from "@/lib/websocket";
 import type {
 	WorkflowInfoDto,
 	WorkflowDetailResponse,
@@ -71,13 +72,17 @@ function Dashboard() {
 		(w) => w.workflow_id === selectedWorkflowId,
 	);
 
-	const connect = useCallback(() => {
+	const connect = useCallback(async () => {
 		// 防止重复创建连接
 		if (wsRef.current?.readyState === WebSocket.OPEN || wsRef.current?.readyState === WebSocket.CONNECTING) {
 			return;
 		}
 
-		const ws = new WebSocket(`ws://${window.location.host}/ws`);
+		const url = await resolveWsUrl();
+		if (wsRef.current?.readyState === WebSocket.OPEN || wsRef.current?.readyState === WebSocket.CONNECTING) {
+			return;
+		}
+		const ws = new WebSocket(url);
 
 		ws.onopen = () => {
 			console.log("[Dashboard] WebSocket connected");
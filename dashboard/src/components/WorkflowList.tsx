@@ -21,6 +21,8 @@ const WorkflowList: React.FC<Props> = ({ workflows, onSelect, selectedWorkflow }
         return 'bg-green-100 text-green-800';
       case 'FAILED':
         return 'bg-red-100 text-red-800';
+      case 'TERMINATED':
+        return 'bg-orange-100 text-orange-800';
       case 'RUNNING':
         return 'bg-blue-100 text-blue-800';
       case 'PENDING':
@@ -30,6 +30,7 @@ export type StepEventType =
   | 'step:started'
   | 'step:completed'
   | 'step:failed'
+  | 'step:progress'
   | 'workflow:completed'
   | 'workflow:failed';
 
@@ -39,17 +40,24 @@ export interface WorkflowEvent {
   workflow_id: string;
   workflow_type: string;
   timestamp: number;
-  payload: StepStartedPayload | StepCompletedPayload | StepFailedPayload | WorkflowCompletedPayload | WorkflowFailedPayload;
+  payload: StepStartedPayload | StepCompletedPayload | StepFailedPayload | StepProgressPayload | WorkflowCompletedPayload | WorkflowFailedPayload | WorkflowTerminatedPayload;
+}
+
+// 带内容类型的负载：JSON 内容为解码后的值，其他内容为 base64 字符串
+export interface Payload {
+  content_type: string;
+  data: unknown;
 }
 
 export interface StepStartedPayload {
   step_name: string;
-  input: unknown;
+  input: Payload;
+  attempt: number;
 }
 
 export interface StepCompletedPayload {
   step_name: string;
-  output: unknown;
+  output: Payload;
 }
 
 export interface StepFailedPayload {
@@ -58,14 +66,26 @@ export interface StepFailedPayload {
   attempt: number;
 }
 
+export interface StepProgressPayload {
+  step_name: string;
+  percent: number | null;
+  message: string | null;
+  details: Payload | null;
+}
+
 export interface WorkflowCompletedPayload {
-  result: unknown;
+  result: Payload;
 }
 
 export interface WorkflowFailedPayload {
   error: string;
 }
 
+export interface WorkflowTerminatedPayload {
+  reason: string;
+  requested_by: string | null;
+}
+
 // API 响应类型 (snake_case 匹配后端)
 export interface WorkflowListResponse {
   workflows: WorkflowInfoDto[];
@@ -78,6 +98,15 @@ export interface WorkflowDetailResponse {
   step_executions: StepExecutionDto[];
   started_at: number;
   completed_at: number | null;
+  pending_timers: PendingTimerDto[];
+  parent_workflow_id: string | null;
+  child_workflow_ids: string[];
+}
+
+export interface PendingTimerDto {
+  step_name: string;
+  fire_at: number;
+  remaining_ms: number;
 }
 
 export interface StepExecutionDto {
@@ -86,6 +115,16 @@ export interface StepExecutionDto {
   started_at: number | null;
   completed_at: number | null;
   attempt: number;
+  progress: StepProgressDto | null;
+  last_heartbeat_at: number | null;
+  input: Payload;
+  output: Payload | null;
+}
+
+export interface StepProgressDto {
+  percent: number | null;
+  message: string | null;
+  details: Payload | null;
 }
 
 export interface WorkflowInfoDto {
@@ -101,14 +140,37 @@ export type ApiRequest =
   | { ListActiveWorkflows: null }
   | { ListAllWorkflows: null }
   | { GetWorkflow: { workflow_id: string } }
-  | { GetWorkflowHistory: { workflow_id: string } };
+  | { GetWorkflowHistory: { workflow_id: string } }
+  | { GetEventHistory: { workflow_id: string; since_timestamp?: number } }
+  | {
+      Subscribe: {
+        workflow_ids?: string[];
+        workflow_types?: string[];
+        event_types?: string[];
+      };
+    }
+  | 'Unsubscribe';
 
 // Dashboard API 响应 (Rust enum 格式)
 export type ApiResponse =
   | { WorkflowList: { workflows: WorkflowInfoDto[] } }
   | { WorkflowDetail: { detail: WorkflowDetailResponse } }
   | { WorkflowHistory: { history: StepHistoryDto[] } }
-  | { Error: { message: string } };
+  | { Subscribed: { filter: EventFilter } }
+  | { EventHistory: { workflow_id: string; events: WorkflowEvent[] } }
+  | { Error: { code: ApiErrorCode; message: string } };
+
+export type ApiErrorCode = 'INVALID_REQUEST' | 'NOT_FOUND';
+
+// 请求可带 request_id，对应的响应原样带回
+export type ApiResponseEnvelope = ApiResponse & { request_id?: string };
+
+// 实时事件过滤条件，空数组表示不限制
+export interface EventFilter {
+  workflow_ids: string[];
+  workflow_types: string[];
+  event_types: string[];
+}
 
 export interface StepHistoryDto {
   step_name: string;
@@ -116,3 +178,9 @@ export interface StepHistoryDto {
   timestamp: number;
   duration_ms: number | null;
 }
+
+// GET /dashboard/config.json
+export interface DashboardConfigDto {
+  ws_port: number;
+  ws_path: string;
+}
@@ -1,5 +1,20 @@
 import { useEffect, useRef, useState, useCallback } from 'react';
-import type { WorkflowEvent } from './types';
+import type { DashboardConfigDto, WorkflowEvent } from './types';
+
+// 从服务器获取 WebSocket 地址，获取失败时使用当前页面的地址
+export async function resolveWsUrl(): Promise<string> {
+  const protocol = window.location.protocol === 'https:' ? 'wss:' : 'ws:';
+  try {
+    const response = await fetch('/dashboard/config.json');
+    if (response.ok) {
+      const config = (await response.json()) as DashboardConfigDto;
+      return `${protocol}//${window.location.hostname}:${config.ws_port}${config.ws_path}`;
+    }
+  } catch (error) {
+    console.warn('[WebSocket] Failed to load dashboard config:', error);
+  }
+  return `${protocol}//${window.location.host}/ws`;
+}
 
 interface UseWebSocketOptions {
   url: string;
@@ -137,7 +152,7 @@ export function useWorkflowEvents(workflowId: string, options?: Partial<UseWebSo
   // 订阅指定 workflow
   useEffect(() => {
     if (isConnected) {
-      send({ type: 'subscribe', workflowId });
+      send({ Subscribe: { workflow_ids: [workflowId] } });
     }
   }, [isConnected, workflowId, send]);
 
@@ -17,6 +17,10 @@ export default defineConfig({
 				target: "http://localhost:7234",
 				changeOrigin: true,
 			},
+			"/dashboard/config.json": {
+				target: "http://localhost:7235",
+				changeOrigin: true,
+			},
 			"/ws": {
 				target: "ws://localhost:7235",
 				ws: true,
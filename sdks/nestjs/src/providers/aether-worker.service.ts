@@ -220,7 +220,10 @@ class AetherHttpClient {
   ): Promise<boolean> {
     const res = await fetch(`${this.baseUrl}/steps/${taskId}/complete`, {
       method: "POST",
-      headers: { "Content-Type": "application/json" },
+      headers: {
+        "Content-Type": "application/json",
+        Authorization: `Bearer ${this.sessionToken}`,
+      },
       body: JSON.stringify({ output: result, error }),
     });
     return res.ok;
@@ -138,7 +138,10 @@ export class AetherWorker {
   async completeStep(taskId: string, output?: any, error?: string): Promise<boolean> {
     const res = await fetch(`${this.baseUrl}/steps/${taskId}/complete`, {
       method: 'POST',
-      headers: { 'Content-Type': 'application/json' },
+      headers: {
+        'Content-Type': 'application/json',
+        Authorization: `Bearer ${this.sessionToken}`,
+      },
       body: JSON.stringify({ output, error }),
     });
     return res.ok;
@@ -147,7 +150,10 @@ export class AetherWorker {
   async reportStep(taskId: string, status: 'STARTED' | 'COMPLETED' | 'FAILED', message?: string): Promise<boolean> {
     const res = await fetch(`${this.baseUrl}/steps/${taskId}/report`, {
       method: 'POST',
-      headers: { 'Content-Type': 'application/json' },
+      headers: {
+        'Content-Type': 'application/json',
+        Authorization: `Bearer ${this.sessionToken}`,
+      },
       body: JSON.stringify({ status, message }),
     });
     return res.ok;